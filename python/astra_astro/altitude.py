@@ -331,3 +331,249 @@ def get_sunset_sunrise(
         prev_event = event
 
     return result
+
+
+# Sun altitude phases from almanac.dark_twilight_day
+_TWILIGHT_PHASES = ["night", "astronomical", "nautical", "civil", "day"]
+
+
+def _intervals(times: list[datetime], flags: list[bool]) -> list[dict]:
+    """Runs of consecutive flagged samples, from the first to the last
+    sample of each run."""
+    intervals = []
+    start = None
+    for i, (time, flag) in enumerate(zip(times, flags)):
+        if flag and start is None:
+            start = time
+        if start is not None and (not flag or i == len(times) - 1):
+            end = time if flag else times[i - 1]
+            intervals.append({"start": start.isoformat(), "end": end.isoformat()})
+            start = None
+    return intervals
+
+
+def _local_noon(date: datetime, longitude: float) -> datetime:
+    """UTC time of mean local noon on the calendar date of ``date``."""
+    day = date.replace(hour=0, minute=0, second=0, microsecond=0)
+    return day + timedelta(hours=12 - longitude / 15.0)
+
+
+def _night_range(
+    ts, eph, topos, location: ObserverLocation, date: Optional[datetime]
+) -> tuple[datetime, datetime, Optional[datetime], Optional[datetime]]:
+    """Chart range for the night starting on ``date`` (default tonight): an
+    hour before sunset to an hour after the following sunrise. Without them
+    (polar day or night) the range is the 24 hours from local noon.
+
+    Returns:
+        Start and end of the range, sunset and sunrise
+    """
+    if date is None:
+        # Tonight, or the night in progress before local noon
+        date = datetime.now(timezone.utc) + timedelta(hours=location.longitude / 15.0)
+        if date.hour < 12:
+            date -= timedelta(days=1)
+    elif date.tzinfo is None:
+        date = date.replace(tzinfo=timezone.utc)
+    noon = _local_noon(date, location.longitude)
+
+    times, events = almanac.find_discrete(
+        ts.from_datetime(noon),
+        ts.from_datetime(noon + timedelta(days=1)),
+        almanac.sunrise_sunset(eph, topos),
+    )
+    events = [(t.utc_datetime(), e) for t, e in zip(times, events)]
+    sunset = next((time for time, e in events if e == 0), None)
+    sunrise = next((time for time, e in events if e == 1 and sunset and time > sunset), None)
+    if sunset and sunrise:
+        return sunset - timedelta(hours=1), sunrise + timedelta(hours=1), sunset, sunrise
+    return noon, noon + timedelta(days=1), None, None
+
+
+def _twilight(ts, eph, topos, start: datetime, end: datetime) -> list[dict]:
+    """Sun phases from ``start`` to ``end``, starting with the one in effect
+    at the start."""
+    phase_of = almanac.dark_twilight_day(eph, topos)
+    t0, t1 = ts.from_datetime(start), ts.from_datetime(end)
+    times, events = almanac.find_discrete(t0, t1, phase_of)
+    boundaries = [start] + [t.utc_datetime() for t in times] + [end]
+    phases = [int(phase_of(t0))] + [int(e) for e in events]
+    return [
+        {
+            "start": boundaries[i].isoformat(),
+            "end": boundaries[i + 1].isoformat(),
+            "phase": _TWILIGHT_PHASES[phase],
+        }
+        for i, phase in enumerate(phases)
+        if boundaries[i + 1] > boundaries[i]
+    ]
+
+
+def _sample_times(start: datetime, end: datetime, interval_minutes: int) -> list[datetime]:
+    step = timedelta(minutes=interval_minutes)
+    return [start + i * step for i in range(int((end - start) / step) + 1)]
+
+
+def _track(observer, body, t, sample_times: list[datetime]) -> list[dict]:
+    """AltitudePoint dictionaries for ``body`` at all times of ``t`` at once."""
+    alt, az, _ = observer.at(t).observe(body).apparent().altaz()
+    return [
+        AltitudePoint(
+            time=time.isoformat(),
+            altitude=float(a),
+            azimuth=float(z),
+            compass_direction=_azimuth_to_compass(float(z)),
+        ).to_dict()
+        for time, a, z in zip(sample_times, alt.degrees, az.degrees)
+    ]
+
+
+def get_night_chart(
+    ra_deg: float,
+    dec_deg: float,
+    location: ObserverLocation,
+    date: Optional[datetime] = None,
+    min_altitude: float = 30.0,
+    interval_minutes: int = 10,
+) -> dict:
+    """
+    Everything an altitude chart shows for one night: the target and moon
+    tracks, twilight phases, and when the target is imageable.
+
+    The chart runs from an hour before sunset on ``date`` (defaults to
+    tonight) to an hour after the following sunrise. Imaging windows are
+    the stretches of astronomical darkness with the target at or above
+    ``min_altitude``, to the resolution of ``interval_minutes``.
+
+    Args:
+        ra_deg: Right ascension in degrees
+        dec_deg: Declination in degrees
+        location: Observer's location
+        date: Date of the evening the night starts on
+        min_altitude: Lowest usable target altitude in degrees
+        interval_minutes: Time interval between track points
+
+    Returns:
+        Dictionary with the chart's time range, sunset and sunrise, target
+        and moon tracks, moon illumination, twilight phases, darkness
+        intervals and imaging windows
+    """
+    ts = load.timescale()
+    eph = load("de421.bsp")
+    topos = wgs84.latlon(location.latitude, location.longitude, elevation_m=location.elevation)
+    observer = eph["earth"] + topos
+
+    start, end, sunset, sunrise = _night_range(ts, eph, topos, location, date)
+    twilight = _twilight(ts, eph, topos, start, end)
+    darkness = [{"start": p["start"], "end": p["end"]} for p in twilight if p["phase"] == "night"]
+
+    from skyfield.starlib import Star
+
+    sample_times = _sample_times(start, end, interval_minutes)
+    t = ts.from_datetimes(sample_times)
+    target = Star(ra_hours=ra_deg / 15.0, dec_degrees=dec_deg)
+    target_track = _track(observer, target, t, sample_times)
+    dark = [int(p) == 0 for p in almanac.dark_twilight_day(eph, topos)(t)]
+
+    middle = ts.from_datetime(start + (end - start) / 2)
+    return {
+        "start": start.isoformat(),
+        "end": end.isoformat(),
+        "sunset": sunset.isoformat() if sunset else None,
+        "sunrise": sunrise.isoformat() if sunrise else None,
+        "minAltitude": min_altitude,
+        "target": target_track,
+        "moon": _track(observer, eph["moon"], t, sample_times),
+        "moonIllumination": float(almanac.fraction_illuminated(eph, "moon", middle)),
+        "twilight": twilight,
+        "darkness": darkness,
+        "imagingWindows": _intervals(
+            sample_times,
+            [d and p["altitude"] >= min_altitude for d, p in zip(dark, target_track)],
+        ),
+    }
+
+
+def _summarize_track(
+    points: list[dict], dark: list[bool], min_altitude: float, interval_minutes: int
+) -> dict:
+    """When a target is best placed: its highest point in darkness (or at
+    all, if it's never dark) and the dark time it spends above
+    ``min_altitude``."""
+    candidates = [p for p, d in zip(points, dark) if d] or points
+    best = max(candidates, key=lambda p: p["altitude"], default=None)
+    usable = sum(1 for p, d in zip(points, dark) if d and p["altitude"] >= min_altitude)
+    return {
+        "maxAltitude": best["altitude"] if best else None,
+        "bestTime": best["time"] if best else None,
+        "hoursAboveMin": usable * interval_minutes / 60.0,
+        "windows": _intervals(
+            [datetime.fromisoformat(p["time"]) for p in points],
+            [d and p["altitude"] >= min_altitude for p, d in zip(points, dark)],
+        ),
+    }
+
+
+def compare_altitudes(
+    targets: list[dict],
+    location: ObserverLocation,
+    date: Optional[datetime] = None,
+    min_altitude: float = 30.0,
+    interval_minutes: int = 10,
+) -> dict:
+    """
+    Altitude tracks of several targets over one night, for choosing the
+    order to image them in.
+
+    Args:
+        targets: Dictionaries with ``ra_deg``, ``dec_deg`` and an optional
+            ``name``
+        location: Observer's location
+        date: Date of the evening the night starts on (defaults to tonight)
+        min_altitude: Lowest usable target altitude in degrees
+        interval_minutes: Time interval between track points
+
+    Returns:
+        Dictionary with the chart's time range, sunset and sunrise,
+        darkness intervals, and per target (in request order) its track,
+        best time and the dark time it spends above ``min_altitude``
+    """
+    ts = load.timescale()
+    eph = load("de421.bsp")
+    topos = wgs84.latlon(location.latitude, location.longitude, elevation_m=location.elevation)
+    observer = eph["earth"] + topos
+
+    start, end, sunset, sunrise = _night_range(ts, eph, topos, location, date)
+    twilight = _twilight(ts, eph, topos, start, end)
+
+    from skyfield.starlib import Star
+
+    sample_times = _sample_times(start, end, interval_minutes)
+    t = ts.from_datetimes(sample_times)
+    dark = [int(p) == 0 for p in almanac.dark_twilight_day(eph, topos)(t)]
+
+    tracks = []
+    for target in targets:
+        star = Star(ra_hours=target["ra_deg"] / 15.0, dec_degrees=target["dec_deg"])
+        points = _track(observer, star, t, sample_times)
+        tracks.append(
+            {
+                "name": target.get("name"),
+                "raDeg": target["ra_deg"],
+                "decDeg": target["dec_deg"],
+                "points": points,
+                **_summarize_track(points, dark, min_altitude, interval_minutes),
+            }
+        )
+
+    return {
+        "start": start.isoformat(),
+        "end": end.isoformat(),
+        "sunset": sunset.isoformat() if sunset else None,
+        "sunrise": sunrise.isoformat() if sunrise else None,
+        "minAltitude": min_altitude,
+        "darkness": [
+            {"start": p["start"], "end": p["end"]} for p in twilight if p["phase"] == "night"
+        ],
+        "targets": tracks,
+    }
@@ -0,0 +1,321 @@
+"""
+Annotated previews of plate-solved images.
+
+Draws the solved catalog objects, an RA/Dec grid and constellation
+boundaries onto an image preview using the stored WCS solution.
+"""
+
+import math
+from typing import Optional
+
+# Colors by catalog object type (RGB)
+OBJECT_COLORS = {
+    "galaxy": (255, 170, 60),
+    "nebula": (120, 220, 255),
+    "cluster": (255, 240, 120),
+    "star": (255, 255, 255),
+}
+DEFAULT_OBJECT_COLOR = (180, 255, 150)
+GRID_COLOR = (110, 150, 255)
+CONSTELLATION_COLOR = (200, 120, 255)
+
+# Grid spacings to choose from, in degrees
+GRID_STEPS = [
+    1 / 60,
+    2 / 60,
+    5 / 60,
+    10 / 60,
+    15 / 60,
+    20 / 60,
+    30 / 60,
+    1.0,
+    2.0,
+    5.0,
+    10.0,
+    15.0,
+    20.0,
+    30.0,
+]
+
+
+def grid_step(span_deg: float, target_lines: int = 6) -> float:
+    """Pick a grid spacing that gives roughly ``target_lines`` lines over ``span_deg``."""
+    if span_deg <= 0:
+        return GRID_STEPS[0]
+    ideal = span_deg / target_lines
+    return min(GRID_STEPS, key=lambda step: abs(math.log(step / ideal)))
+
+
+def grid_values(start: float, stop: float, step: float) -> list[float]:
+    """Multiples of ``step`` between ``start`` and ``stop`` (inclusive)."""
+    first = math.ceil(start / step)
+    last = math.floor(stop / step)
+    return [round(i * step, 9) for i in range(first, last + 1)]
+
+
+def format_ra(ra_deg: float) -> str:
+    """Format RA in degrees as ``HHhMMm`` (with seconds for sub-minute grids)."""
+    total_seconds = round((ra_deg % 360.0) / 15.0 * 3600.0)
+    hours, rest = divmod(total_seconds, 3600)
+    minutes, seconds = divmod(rest, 60)
+    if seconds:
+        return f"{hours % 24:02d}h{minutes:02d}m{seconds:02d}s"
+    return f"{hours % 24:02d}h{minutes:02d}m"
+
+
+def format_dec(dec_deg: float) -> str:
+    """Format Dec in degrees as ``+DD°MM'``."""
+    sign = "-" if dec_deg < 0 else "+"
+    total_minutes = round(abs(dec_deg) * 60.0)
+    degrees, minutes = divmod(total_minutes, 60)
+    return f"{sign}{degrees:02d}°{minutes:02d}'"
+
+
+def object_color(object_type: Optional[str]) -> tuple:
+    """Overlay color for a catalog object type."""
+    kind = (object_type or "").lower()
+    for key, color in OBJECT_COLORS.items():
+        if key in kind:
+            return color
+    return DEFAULT_OBJECT_COLOR
+
+
+def _wcs_from_params(params: dict):
+    """Build an astropy WCS from the stored ``{crval, crpix, cd, sip}`` solution."""
+    import numpy as np
+    from astropy.wcs import WCS, Sip
+
+    wcs = WCS(naxis=2)
+    wcs.wcs.crpix = np.array(params["crpix"])
+    wcs.wcs.crval = np.array(params["crval"])
+    wcs.wcs.cd = np.array(params["cd"])
+    wcs.wcs.ctype = ["RA---TAN", "DEC--TAN"]
+    sip = params.get("sip")
+    if sip:
+        ap, bp = sip.get("ap"), sip.get("bp")
+        wcs.sip = Sip(
+            np.array(sip["a"]),
+            np.array(sip["b"]),
+            np.array(ap) if ap is not None else None,
+            np.array(bp) if bp is not None else None,
+            wcs.wcs.crpix,
+        )
+        wcs.wcs.ctype = ["RA---TAN-SIP", "DEC--TAN-SIP"]
+    return wcs
+
+
+def _draw_polyline(draw, points, width: int, height: int, color, line_width: int) -> None:
+    """Draw a line through ``points``, breaking it where points are invalid or far off-image."""
+    segment = []
+    margin = max(width, height)
+    for x, y in points:
+        valid = (
+            math.isfinite(x)
+            and math.isfinite(y)
+            and -margin < x < width + margin
+            and -margin < y < height + margin
+        )
+        if valid:
+            segment.append((x, y))
+            continue
+        if len(segment) > 1:
+            draw.line(segment, fill=color, width=line_width)
+        segment = []
+    if len(segment) > 1:
+        draw.line(segment, fill=color, width=line_width)
+
+
+def _draw_grid(draw, font, wcs, to_preview, width: int, height: int, line_width: int) -> None:
+    """Draw RA/Dec grid lines with labels along the image edges."""
+    import numpy as np
+
+    # Sky extent from a ring of points around the image edge
+    xs = np.linspace(0, width, 24)
+    ys = np.linspace(0, height, 24)
+    edge = np.concatenate(
+        [
+            np.column_stack([xs, np.zeros_like(xs)]),
+            np.column_stack([xs, np.full_like(xs, height)]),
+            np.column_stack([np.zeros_like(ys), ys]),
+            np.column_stack([np.full_like(ys, width), ys]),
+            [[width / 2, height / 2]],
+        ]
+    )
+    source = np.array([to_preview(x, y, inverse=True) for x, y in edge])
+    ra, dec = wcs.all_pix2world(source[:, 0], source[:, 1], 0)
+    center_ra = ra[-1]
+    # Unwrap RA around the center so fields crossing 0h have a continuous span
+    ra_unwrapped = center_ra + (ra - center_ra + 180.0) % 360.0 - 180.0
+    dec_min, dec_max = float(np.min(dec)), float(np.max(dec))
+    ra_min, ra_max = float(np.min(ra_unwrapped)), float(np.max(ra_unwrapped))
+
+    # Near a pole the field contains every RA, so draw them all
+    pole_in_field = False
+    for pole in (90.0, -90.0):
+        px, py = wcs.all_world2pix([0.0], [pole], 0, quiet=True)
+        x, y = to_preview(float(px[0]), float(py[0]))
+        if 0 <= x <= width and 0 <= y <= height:
+            pole_in_field = True
+            dec_min, dec_max = (min(dec_min, pole), max(dec_max, pole))
+    if pole_in_field:
+        ra_min, ra_max = 0.0, 360.0
+
+    dec_step = grid_step(dec_max - dec_min)
+    ra_step = grid_step(ra_max - ra_min)
+    samples = 100
+
+    for dec_value in grid_values(dec_min, dec_max, dec_step):
+        if abs(dec_value) >= 90.0:
+            continue
+        ra_line = np.linspace(ra_min, ra_max, samples)
+        px, py = wcs.all_world2pix(ra_line % 360.0, np.full(samples, dec_value), 0, quiet=True)
+        points = [to_preview(x, y) for x, y in zip(px, py)]
+        _draw_polyline(draw, points, width, height, GRID_COLOR, line_width)
+        _label_edge(draw, font, points, format_dec(dec_value), width, height)
+
+    for ra_value in grid_values(ra_min, ra_max, ra_step):
+        dec_line = np.linspace(max(dec_min, -89.9), min(dec_max, 89.9), samples)
+        px, py = wcs.all_world2pix(np.full(samples, ra_value % 360.0), dec_line, 0, quiet=True)
+        points = [to_preview(x, y) for x, y in zip(px, py)]
+        _draw_polyline(draw, points, width, height, GRID_COLOR, line_width)
+        _label_edge(draw, font, points, format_ra(ra_value), width, height)
+
+
+def _label_edge(draw, font, points, text: str, width: int, height: int) -> None:
+    """Label a grid line at the first of its points that lies inside the image."""
+    inset = 4
+    for x, y in points:
+        if inset <= x <= width - inset and inset <= y <= height - inset:
+            draw.text((x + 3, y + 2), text, fill=GRID_COLOR, font=font)
+            return
+
+
+def _draw_constellations(
+    draw, font, wcs, to_preview, width: int, height: int, line_width: int
+) -> None:
+    """Draw constellation boundaries and names using astropy's boundary data."""
+    import numpy as np
+    from astropy.coordinates import SkyCoord, get_constellation
+
+    # Constellation of each cell of a coarse grid over the preview
+    cells = 48
+    step_x, step_y = width / cells, height / cells
+    gx, gy = np.meshgrid(
+        (np.arange(cells) + 0.5) * step_x,
+        (np.arange(cells) + 0.5) * step_y,
+    )
+    source = np.array([to_preview(x, y, inverse=True) for x, y in zip(gx.ravel(), gy.ravel())])
+    ra, dec = wcs.all_pix2world(source[:, 0], source[:, 1], 0)
+    names = np.asarray(get_constellation(SkyCoord(ra=ra, dec=dec, unit="deg"))).reshape(
+        cells, cells
+    )
+
+    # Boundaries between neighbouring cells in different constellations
+    for row in range(cells):
+        for col in range(cells):
+            x0, y0 = col * step_x, row * step_y
+            if col + 1 < cells and names[row, col] != names[row, col + 1]:
+                x = x0 + step_x
+                draw.line([(x, y0), (x, y0 + step_y)], fill=CONSTELLATION_COLOR, width=line_width)
+            if row + 1 < cells and names[row, col] != names[row + 1, col]:
+                y = y0 + step_y
+                draw.line([(x0, y), (x0 + step_x, y)], fill=CONSTELLATION_COLOR, width=line_width)
+
+    # Name each constellation at the middle of its cells
+    for name in np.unique(names):
+        rows, cols = np.nonzero(names == name)
+        x = (float(np.mean(cols)) + 0.5) * step_x
+        y = (float(np.mean(rows)) + 0.5) * step_y
+        draw.text((x, y), str(name), fill=CONSTELLATION_COLOR, font=font, anchor="mm")
+
+
+def _draw_objects(
+    draw, font, wcs, objects: list, to_preview, scale: float, line_width: int
+) -> None:
+    """Circle and label each catalog object."""
+    for obj in objects:
+        x, y = obj.get("pixelX"), obj.get("pixelY")
+        if x is None or y is None:
+            ra, dec = obj.get("ra"), obj.get("dec")
+            if ra is None or dec is None:
+                continue
+            px, py = wcs.all_world2pix([ra], [dec], 0, quiet=True)
+            x, y = float(px[0]), float(py[0])
+        if not (math.isfinite(x) and math.isfinite(y)):
+            continue
+        x, y = to_preview(x, y)
+
+        radius = max(float(obj.get("radiusPx") or 10.0) * scale, 6.0)
+        color = object_color(obj.get("objectType"))
+        draw.ellipse(
+            [(x - radius, y - radius), (x + radius, y + radius)],
+            outline=color,
+            width=line_width,
+        )
+        label = obj.get("commonName") or obj.get("name")
+        if label:
+            draw.text((x + radius * 0.75 + 4, y - radius * 0.75), label, fill=color, font=font)
+
+
+def render_annotated_preview(
+    preview_path: str,
+    output_path: str,
+    wcs: dict,
+    objects: Optional[list] = None,
+    source_width: int = 0,
+    source_height: int = 0,
+    grid: bool = True,
+    constellations: bool = True,
+    labels: bool = True,
+) -> dict:
+    """
+    Render a preview with catalog objects, an RA/Dec grid and constellation
+    boundaries drawn over it, and save it as a PNG.
+
+    Args:
+        preview_path: Preview image (JPEG/PNG/TIFF) to draw on
+        output_path: Where to write the annotated PNG
+        wcs: Stored plate solution (crval, crpix, cd and optional sip)
+        objects: Catalog objects from the solve (pixelX/pixelY or ra/dec)
+        source_width: Width of the solved image in pixels (default: preview width)
+        source_height: Height of the solved image in pixels (default: preview height)
+        grid: Draw an RA/Dec grid
+        constellations: Draw constellation boundaries and names
+        labels: Draw catalog object circles and labels
+
+    Returns:
+        dict with success, path, width and height, or success False and error
+    """
+    from PIL import Image, ImageDraw, ImageFont
+
+    try:
+        solution = _wcs_from_params(wcs)
+        with Image.open(preview_path) as opened:
+            preview = opened.convert("RGB")
+        width, height = preview.size
+        sx = width / source_width if source_width > 0 else 1.0
+        sy = height / source_height if source_height > 0 else 1.0
+
+        # Pixel coordinates are in the solved image's frame with the same row
+        # order as the preview, so converting is just a scale
+        def to_preview(x: float, y: float, inverse: bool = False) -> tuple:
+            if inverse:
+                return (x / sx, y / sy)
+            return (x * sx, y * sy)
+
+        draw = ImageDraw.Draw(preview)
+        font = ImageFont.load_default(size=max(12, round(min(width, height) / 60)))
+        line_width = max(1, round(min(width, height) / 800))
+
+        if constellations:
+            _draw_constellations(draw, font, solution, to_preview, width, height, line_width)
+        if grid:
+            _draw_grid(draw, font, solution, to_preview, width, height, line_width)
+        if labels and objects:
+            scale = (sx + sy) / 2.0
+            _draw_objects(draw, font, solution, objects, to_preview, scale, line_width + 1)
+
+        preview.save(output_path, format="PNG")
+        return {"success": True, "path": output_path, "width": width, "height": height}
+    except Exception as e:
+        return {"success": False, "error": str(e)}
@@ -1105,7 +1105,7 @@ def add_pixel_positions(
     """
     try:
         from astropy.io import fits
-        from astropy.wcs import WCS
+        from astropy.wcs import WCS, Sip
         from astropy.coordinates import SkyCoord
         import numpy as np
 
@@ -1126,6 +1126,17 @@ def add_pixel_positions(
             wcs.wcs.ctype = ["RA---TAN", "DEC--TAN"]
             if "cd" in wcs_params:
                 wcs.wcs.cd = np.array(wcs_params["cd"])
+            sip = wcs_params.get("sip")
+            if sip:
+                ap, bp = sip.get("ap"), sip.get("bp")
+                wcs.sip = Sip(
+                    np.array(sip["a"]),
+                    np.array(sip["b"]),
+                    np.array(ap) if ap is not None else None,
+                    np.array(bp) if bp is not None else None,
+                    wcs.wcs.crpix,
+                )
+                wcs.wcs.ctype = ["RA---TAN-SIP", "DEC--TAN-SIP"]
 
             # For TOP-DOWN FITS, flip the Y pixel coordinates after projection
             # since the WCS was computed with standard FITS convention (Y=0 at bottom)
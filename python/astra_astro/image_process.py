@@ -9,12 +9,16 @@ MTF(m, x) = (m - 1) * x / ((2m - 1) * x - m)
 where m is the midtones balance parameter (0 < m < 1) and x is the input value.
 """
 
+import base64
+import io
 import logging
+import math
 import os
 import time
 from dataclasses import dataclass, field
 from pathlib import Path
-from typing import Optional
+from functools import lru_cache
+from typing import Callable, Optional
 
 import numpy as np
 from astropy.io import fits
@@ -38,6 +42,8 @@ class ProcessingParams:
     color_calibration: bool = True
     noise_reduction: float = 0.0  # 0-1 strength
     contrast: float = 1.3  # 1.0=none, 1.3=Seestar-like, 1.5=moderate, 2.0=strong
+    background_method: str = "median"  # "median" or "polynomial"
+    gradient_order: int = 2  # Polynomial order for the "polynomial" method (1-4)
 
     def to_dict(self) -> dict:
         """Convert to dictionary for JSON serialization."""
@@ -50,6 +56,8 @@ class ProcessingParams:
             "colorCalibration": self.color_calibration,
             "noiseReduction": self.noise_reduction,
             "contrast": self.contrast,
+            "backgroundMethod": self.background_method,
+            "gradientOrder": self.gradient_order,
         }
 
 
@@ -329,6 +337,87 @@ def _remove_background(data: np.ndarray, sigma: float = 50.0) -> np.ndarray:
     return result
 
 
+def _remove_gradient_polynomial(
+    data: np.ndarray, order: int = 2, grid: int = 32, sigma_clip: float = 2.5
+) -> np.ndarray:
+    """
+    Remove background gradient by fitting a low-order 2D polynomial surface.
+
+    Same approach as the native preview pipeline: the background is sampled
+    as patch medians on a grid, samples on stars/nebulosity are rejected by
+    sigma clipping, and the fitted surface is subtracted. Much faster than
+    the median filter and better at smooth light-pollution gradients.
+
+    Args:
+        data: Image data
+        order: Polynomial order (1 = plane, 2 = quadratic, ...)
+        grid: Number of samples along each axis
+        sigma_clip: Rejection threshold in standard deviations
+
+    Returns:
+        Background-subtracted image (0-1 normalized)
+    """
+    if len(data.shape) == 3:
+        return np.stack(
+            [
+                _remove_gradient_polynomial(data[:, :, i], order, grid, sigma_clip)
+                for i in range(data.shape[2])
+            ],
+            axis=-1,
+        )
+
+    h, w = data.shape
+    patch_h = max(1, h // (grid * 2))
+    patch_w = max(1, w // (grid * 2))
+
+    # Sample background on a grid using patch medians
+    ys, xs = np.meshgrid(
+        np.linspace(0, h - 1, grid).astype(int),
+        np.linspace(0, w - 1, grid).astype(int),
+        indexing="ij",
+    )
+    ys, xs = ys.ravel(), xs.ravel()
+    values = np.array(
+        [
+            np.median(
+                data[max(0, y - patch_h) : y + patch_h + 1, max(0, x - patch_w) : x + patch_w + 1]
+            )
+            for y, x in zip(ys, xs)
+        ]
+    )
+
+    # Sigma-clip to reject stars
+    for _ in range(3):
+        median = np.median(values)
+        std = np.median(np.abs(values - median)) * 1.4826
+        if std < 1e-10:
+            break
+        keep = np.abs(values - median) < sigma_clip * std
+        if keep.sum() < 6:
+            break
+        ys, xs, values = ys[keep], xs[keep], values[keep]
+
+    # Least-squares fit in coordinates normalized to [-1, 1]
+    yn = ys / max(1, h - 1) * 2 - 1
+    xn = xs / max(1, w - 1) * 2 - 1
+    powers = [(i, total - i) for total in range(order + 1) for i in range(total, -1, -1)]
+    design = np.stack([xn**i * yn**j for i, j in powers], axis=1)
+    coeffs, *_ = np.linalg.lstsq(design, values, rcond=None)
+
+    # Evaluate the surface with broadcasting (no full-size coordinate grids)
+    y_axis = np.linspace(-1, 1, h)[:, np.newaxis]
+    x_axis = np.linspace(-1, 1, w)[np.newaxis, :]
+    model = sum(c * x_axis**i * y_axis**j for c, (i, j) in zip(coeffs, powers))
+
+    # Shift so the 1st percentile is zero, then normalize
+    result = np.clip(data - model - np.percentile(data - model, 1), 0, None)
+    max_val = np.max(result)
+    if max_val > 0:
+        result = result / max_val
+
+    return result
+
+
 def _color_calibrate(data: np.ndarray) -> np.ndarray:
     """
     Apply simple color calibration (background neutralization).
@@ -374,13 +463,16 @@ def _color_calibrate(data: np.ndarray) -> np.ndarray:
     return data
 
 
-def _apply_noise_reduction(data: np.ndarray, strength: float = 0.5) -> np.ndarray:
+def _apply_noise_reduction(
+    data: np.ndarray, strength: float = 0.5, max_sigma: float = 1.5
+) -> np.ndarray:
     """
     Apply light noise reduction using Gaussian blur.
 
     Args:
         data: Image data
         strength: Reduction strength (0-1)
+        max_sigma: Blur sigma in pixels at full strength
 
     Returns:
         Noise-reduced image
@@ -388,7 +480,7 @@ def _apply_noise_reduction(data: np.ndarray, strength: float = 0.5) -> np.ndarra
     if strength <= 0:
         return data
 
-    sigma = strength * 1.5  # Max sigma of 1.5 pixels
+    sigma = strength * max_sigma
 
     if len(data.shape) == 3:
         result = np.zeros_like(data)
@@ -525,27 +617,68 @@ def _save_fits(data: np.ndarray, output_path: str, header: Optional[dict] = None
     hdu.writeto(output_path, overwrite=True)
 
 
-def _save_preview(data: np.ndarray, output_path: str) -> None:
+def _to_image(data: np.ndarray) -> Image.Image:
     """
-    Save preview as PNG.
+    Convert processed data to an 8-bit PIL image.
 
     Args:
         data: Image data (0-1 normalized)
-        output_path: Output path
+
+    Returns:
+        Grayscale or RGB image
     """
     # Convert to 8-bit
     img_data = (data * 255).astype(np.uint8)
 
-    # Create PIL image
     if len(img_data.shape) == 2:
         # Grayscale
-        img = Image.fromarray(img_data, mode="L")
-    else:
-        # RGB
-        img = Image.fromarray(img_data, mode="RGB")
+        return Image.fromarray(img_data, mode="L")
+    # RGB
+    return Image.fromarray(img_data, mode="RGB")
+
+
+def _save_preview(data: np.ndarray, output_path: str) -> None:
+    """
+    Save preview as PNG.
+
+    Args:
+        data: Image data (0-1 normalized)
+        output_path: Output path
+    """
+    _to_image(data).save(output_path, "PNG")
+
+
+def _downsample(data: np.ndarray, max_size: int) -> tuple[np.ndarray, int]:
+    """
+    Bin an image down so its longest side is at most max_size pixels.
+
+    Pixels are averaged in square blocks; edge rows/columns that don't fill
+    a whole block are dropped.
+
+    Args:
+        data: Image data (H, W) or (H, W, C)
+        max_size: Maximum width/height of the result
+
+    Returns:
+        Tuple of (binned data, binning factor)
+    """
+    binning = max(1, math.ceil(max(data.shape[:2]) / max_size))
+    if binning == 1:
+        return data, 1
+
+    h = data.shape[0] // binning
+    w = data.shape[1] // binning
+    blocks = data[: h * binning, : w * binning].reshape(
+        (h, binning, w, binning) + data.shape[2:]
+    )
+    return blocks.mean(axis=(1, 3)), binning
 
-    # Save
-    img.save(output_path, "PNG")
+
+@lru_cache(maxsize=4)
+def _load_preview_data(fits_path: str, mtime: float, max_size: int) -> tuple[np.ndarray, int]:
+    """Load and bin a FITS file for previews, cached while the file is unchanged."""
+    data, _header = _load_fits(fits_path)
+    return _downsample(data, max_size)
 
 
 def quick_preview(
@@ -601,6 +734,140 @@ def quick_preview(
         }
 
 
+def _classify_target(
+    params: ProcessingParams,
+    object_name: Optional[str],
+    report_progress: Callable[..., None],
+) -> TargetType:
+    """Resolve the target type from the params, classifying by name for "auto"."""
+    report_progress("classifying", 0.18, "Classifying target type")
+    target_type = TargetType.UNKNOWN
+    if params.target_type == "auto" and object_name:
+        target_info = classify_from_name(object_name)
+        target_type = target_info.target_type
+        logger.info(f"Auto-classified {object_name} as {target_type.value}")
+        report_progress("classifying", 0.20, f"Classified as {target_type.value}")
+    elif params.target_type != "auto":
+        try:
+            target_type = TargetType(params.target_type)
+        except ValueError:
+            target_type = TargetType.UNKNOWN
+        report_progress("classifying", 0.20, f"Using target type: {target_type.value}")
+
+    return target_type
+
+
+def _process_data(
+    data: np.ndarray,
+    params: ProcessingParams,
+    target_type: TargetType,
+    report_progress: Callable[..., None],
+    binning: int = 1,
+) -> np.ndarray:
+    """
+    Run the processing pipeline on loaded image data.
+
+    Args:
+        data: Image data as loaded from FITS
+        params: Processing parameters
+        target_type: Resolved target type
+        report_progress: Callback(step, progress, message)
+        binning: Binning factor of data relative to the original image; pixel
+            based filter sizes are scaled down to match
+
+    Returns:
+        Processed image data (0-1 normalized)
+    """
+    # Get target-specific parameters
+    target_params = TARGET_PARAMS.get(target_type, TARGET_PARAMS[TargetType.UNKNOWN])
+
+    # Merge with user params
+    if params.stretch_factor > 0:
+        stretch_factor = params.stretch_factor
+    else:
+        stretch_factor = target_params["stretch_factor"]
+    background_removal = params.background_removal
+    star_reduction = params.star_reduction or target_params.get("star_reduction", False)
+
+    logger.info(
+        f"Processing with: stretch={stretch_factor}, "
+        f"bg_removal={background_removal}, star_reduction={star_reduction}"
+    )
+
+    # Process pipeline
+    processed = data.copy()
+
+    # 1. Background removal
+    if background_removal:
+        report_progress("background", 0.25, "Removing background gradient")
+        if params.background_method == "polynomial":
+            logger.info(f"Removing background gradient (polynomial order {params.gradient_order})")
+            processed = _remove_gradient_polynomial(processed, params.gradient_order)
+        else:
+            logger.info("Removing background gradient")
+            processed = _remove_background(processed, sigma=max(1.0, 50.0 / binning))
+        report_progress("background", 0.40, "Background gradient removed")
+    else:
+        report_progress("background", 0.40, "Skipping background removal")
+
+    # 2. Color calibration (RGB only)
+    if params.color_calibration and len(processed.shape) == 3:
+        report_progress("calibration", 0.42, "Applying color calibration")
+        logger.info("Applying color calibration")
+        processed = _color_calibrate(processed)
+        report_progress("calibration", 0.50, "Color calibration complete")
+    else:
+        report_progress("calibration", 0.50, "Skipping color calibration")
+
+    # 3. Apply stretch
+    stretch_name = params.stretch_method.replace("_", " ").title()
+    report_progress("stretch", 0.52, f"Applying {stretch_name} stretch")
+    logger.info(f"Applying {params.stretch_method} stretch")
+    if params.stretch_method == "statistical":
+        processed = _statistical_stretch(processed, stretch_factor)
+    elif params.stretch_method == "arcsinh":
+        processed = _arcsinh_stretch(processed, stretch_factor)
+    elif params.stretch_method == "log":
+        processed = _log_stretch(processed, stretch_factor)
+    else:
+        # Default to statistical
+        processed = _statistical_stretch(processed, stretch_factor)
+    report_progress("stretch", 0.70, f"{stretch_name} stretch complete")
+
+    # 3b. Apply contrast adjustment if requested
+    if params.contrast > 1.0:
+        report_progress("contrast", 0.72, f"Applying contrast ({params.contrast:.1f})")
+        logger.info(f"Applying contrast: {params.contrast}")
+        processed = _apply_contrast_curve(processed, params.contrast)
+        report_progress("contrast", 0.74, "Contrast adjustment complete")
+
+    # 4. Star reduction (optional)
+    if star_reduction:
+        report_progress("stars", 0.72, "Reducing star brightness")
+        logger.info("Reducing star brightness")
+        processed = _reduce_stars(processed)
+        report_progress("stars", 0.80, "Star reduction complete")
+    else:
+        report_progress("stars", 0.80, "Skipping star reduction")
+
+    # 5. Noise reduction (optional)
+    if params.noise_reduction > 0:
+        nr_pct = int(params.noise_reduction * 100)
+        report_progress("noise", 0.82, f"Applying noise reduction ({nr_pct}%)")
+        logger.info(f"Applying noise reduction: {params.noise_reduction}")
+        processed = _apply_noise_reduction(
+            processed, params.noise_reduction, max_sigma=1.5 / binning
+        )
+        report_progress("noise", 0.88, "Noise reduction complete")
+    else:
+        report_progress("noise", 0.88, "Skipping noise reduction")
+
+    # Ensure output is in valid range
+    processed = np.clip(processed, 0, 1)
+
+    return processed
+
+
 def process_image(
     input_fits_path: str,
     output_dir: str,
@@ -665,101 +932,8 @@ def process_image(
         logger.info(f"Loaded image shape: {data.shape}")
         report_progress("loading", 0.15, f"Loaded image ({data.shape[1]}x{data.shape[0]})")
 
-        # Determine target type
-        report_progress("classifying", 0.18, "Classifying target type")
-        target_type = TargetType.UNKNOWN
-        if params.target_type == "auto" and object_name:
-            target_info = classify_from_name(object_name)
-            target_type = target_info.target_type
-            logger.info(f"Auto-classified {object_name} as {target_type.value}")
-            report_progress("classifying", 0.20, f"Classified as {target_type.value}")
-        elif params.target_type != "auto":
-            try:
-                target_type = TargetType(params.target_type)
-            except ValueError:
-                target_type = TargetType.UNKNOWN
-            report_progress("classifying", 0.20, f"Using target type: {target_type.value}")
-
-        # Get target-specific parameters
-        target_params = TARGET_PARAMS.get(target_type, TARGET_PARAMS[TargetType.UNKNOWN])
-
-        # Merge with user params
-        if params.stretch_factor > 0:
-            stretch_factor = params.stretch_factor
-        else:
-            stretch_factor = target_params["stretch_factor"]
-        background_removal = params.background_removal
-        star_reduction = params.star_reduction or target_params.get("star_reduction", False)
-
-        logger.info(
-            f"Processing with: stretch={stretch_factor}, "
-            f"bg_removal={background_removal}, star_reduction={star_reduction}"
-        )
-
-        # Process pipeline
-        processed = data.copy()
-
-        # 1. Background removal
-        if background_removal:
-            report_progress("background", 0.25, "Removing background gradient")
-            logger.info("Removing background gradient")
-            processed = _remove_background(processed)
-            report_progress("background", 0.40, "Background gradient removed")
-        else:
-            report_progress("background", 0.40, "Skipping background removal")
-
-        # 2. Color calibration (RGB only)
-        if params.color_calibration and len(processed.shape) == 3:
-            report_progress("calibration", 0.42, "Applying color calibration")
-            logger.info("Applying color calibration")
-            processed = _color_calibrate(processed)
-            report_progress("calibration", 0.50, "Color calibration complete")
-        else:
-            report_progress("calibration", 0.50, "Skipping color calibration")
-
-        # 3. Apply stretch
-        stretch_name = params.stretch_method.replace("_", " ").title()
-        report_progress("stretch", 0.52, f"Applying {stretch_name} stretch")
-        logger.info(f"Applying {params.stretch_method} stretch")
-        if params.stretch_method == "statistical":
-            processed = _statistical_stretch(processed, stretch_factor)
-        elif params.stretch_method == "arcsinh":
-            processed = _arcsinh_stretch(processed, stretch_factor)
-        elif params.stretch_method == "log":
-            processed = _log_stretch(processed, stretch_factor)
-        else:
-            # Default to statistical
-            processed = _statistical_stretch(processed, stretch_factor)
-        report_progress("stretch", 0.70, f"{stretch_name} stretch complete")
-
-        # 3b. Apply contrast adjustment if requested
-        if params.contrast > 1.0:
-            report_progress("contrast", 0.72, f"Applying contrast ({params.contrast:.1f})")
-            logger.info(f"Applying contrast: {params.contrast}")
-            processed = _apply_contrast_curve(processed, params.contrast)
-            report_progress("contrast", 0.74, "Contrast adjustment complete")
-
-        # 4. Star reduction (optional)
-        if star_reduction:
-            report_progress("stars", 0.72, "Reducing star brightness")
-            logger.info("Reducing star brightness")
-            processed = _reduce_stars(processed)
-            report_progress("stars", 0.80, "Star reduction complete")
-        else:
-            report_progress("stars", 0.80, "Skipping star reduction")
-
-        # 5. Noise reduction (optional)
-        if params.noise_reduction > 0:
-            nr_pct = int(params.noise_reduction * 100)
-            report_progress("noise", 0.82, f"Applying noise reduction ({nr_pct}%)")
-            logger.info(f"Applying noise reduction: {params.noise_reduction}")
-            processed = _apply_noise_reduction(processed, params.noise_reduction)
-            report_progress("noise", 0.88, "Noise reduction complete")
-        else:
-            report_progress("noise", 0.88, "Skipping noise reduction")
-
-        # Ensure output is in valid range
-        processed = np.clip(processed, 0, 1)
+        target_type = _classify_target(params, object_name, report_progress)
+        processed = _process_data(data, params, target_type, report_progress)
 
         # Save outputs
         report_progress("saving", 0.90, "Saving processed FITS")
@@ -792,6 +966,35 @@ def process_image(
         )
 
 
+def _params_from_dict(params_dict: Optional[dict]) -> ProcessingParams:
+    """Build ProcessingParams from a camelCase dictionary; missing keys use defaults."""
+    params = ProcessingParams()
+
+    if params_dict:
+        if "targetType" in params_dict:
+            params.target_type = params_dict["targetType"]
+        if "stretchMethod" in params_dict:
+            params.stretch_method = params_dict["stretchMethod"]
+        if "stretchFactor" in params_dict:
+            params.stretch_factor = float(params_dict["stretchFactor"])
+        if "backgroundRemoval" in params_dict:
+            params.background_removal = bool(params_dict["backgroundRemoval"])
+        if "starReduction" in params_dict:
+            params.star_reduction = bool(params_dict["starReduction"])
+        if "colorCalibration" in params_dict:
+            params.color_calibration = bool(params_dict["colorCalibration"])
+        if "noiseReduction" in params_dict:
+            params.noise_reduction = float(params_dict["noiseReduction"])
+        if "contrast" in params_dict:
+            params.contrast = float(params_dict["contrast"])
+        if "backgroundMethod" in params_dict:
+            params.background_method = params_dict["backgroundMethod"]
+        if "gradientOrder" in params_dict:
+            params.gradient_order = int(params_dict["gradientOrder"])
+
+    return params
+
+
 def process_image_from_dict(
     input_fits_path: str,
     output_dir: str,
@@ -814,25 +1017,86 @@ def process_image_from_dict(
     Returns:
         Dictionary with processing results
     """
-    params = ProcessingParams()
-
-    if params_dict:
-        if "targetType" in params_dict:
-            params.target_type = params_dict["targetType"]
-        if "stretchMethod" in params_dict:
-            params.stretch_method = params_dict["stretchMethod"]
-        if "stretchFactor" in params_dict:
-            params.stretch_factor = float(params_dict["stretchFactor"])
-        if "backgroundRemoval" in params_dict:
-            params.background_removal = bool(params_dict["backgroundRemoval"])
-        if "starReduction" in params_dict:
-            params.star_reduction = bool(params_dict["starReduction"])
-        if "colorCalibration" in params_dict:
-            params.color_calibration = bool(params_dict["colorCalibration"])
-        if "noiseReduction" in params_dict:
-            params.noise_reduction = float(params_dict["noiseReduction"])
-        if "contrast" in params_dict:
-            params.contrast = float(params_dict["contrast"])
-
+    params = _params_from_dict(params_dict)
     result = process_image(input_fits_path, output_dir, params, object_name, progress_callback)
     return result.to_dict()
+
+
+def preview_image(
+    input_fits_path: str,
+    params: Optional[ProcessingParams] = None,
+    object_name: Optional[str] = None,
+    max_size: int = 1024,
+) -> dict:
+    """
+    Process a downsampled copy of a FITS image without writing any files.
+
+    Runs the same pipeline as process_image on data binned to at most
+    max_size pixels per side, for interactive tuning of the parameters.
+    The binned data is cached, so repeated previews of the same file only
+    pay for the processing.
+
+    Args:
+        input_fits_path: Path to input FITS file
+        params: Processing parameters (uses defaults if None)
+        object_name: Object name for auto-classification
+        max_size: Maximum width/height of the preview
+
+    Returns:
+        Dictionary with success status and the preview as a PNG data URL
+    """
+    start_time = time.time()
+    if params is None:
+        params = ProcessingParams()
+
+    try:
+        mtime = os.path.getmtime(input_fits_path)
+        data, binning = _load_preview_data(input_fits_path, mtime, max_size)
+
+        def ignore_progress(*_args):
+            pass
+
+        target_type = _classify_target(params, object_name, ignore_progress)
+        processed = _process_data(data, params, target_type, ignore_progress, binning)
+
+        buffer = io.BytesIO()
+        _to_image(processed).save(buffer, "PNG")
+        encoded = base64.b64encode(buffer.getvalue()).decode("ascii")
+
+        return {
+            "success": True,
+            "previewData": f"data:image/png;base64,{encoded}",
+            "width": processed.shape[1],
+            "height": processed.shape[0],
+            "targetType": target_type.value,
+            "processingParams": params.to_dict(),
+            "processingTime": time.time() - start_time,
+        }
+    except Exception as e:
+        logger.exception(f"Preview processing failed: {e}")
+        return {
+            "success": False,
+            "errorMessage": str(e),
+            "processingTime": time.time() - start_time,
+        }
+
+
+def preview_image_from_dict(
+    input_fits_path: str,
+    params_dict: Optional[dict] = None,
+    object_name: Optional[str] = None,
+    max_size: int = 1024,
+) -> dict:
+    """
+    Preview processing with parameters from dictionary.
+
+    Args:
+        input_fits_path: Path to input FITS file
+        params_dict: Processing parameters as dictionary
+        object_name: Object name for auto-classification
+        max_size: Maximum width/height of the preview
+
+    Returns:
+        Dictionary with preview results
+    """
+    return preview_image(input_fits_path, _params_from_dict(params_dict), object_name, max_size)
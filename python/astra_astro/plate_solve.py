@@ -126,6 +126,13 @@ def _extract_wcs_info(wcs: WCS, image_width: int, image_height: int) -> dict:
         wcs_params["crval"] = [float(v) for v in wcs.wcs.crval]
     if hasattr(wcs.wcs, "cd") and wcs.wcs.cd is not None:
         wcs_params["cd"] = [[float(v) for v in row] for row in wcs.wcs.cd]
+    if wcs.sip is not None:
+        # Distortion terms as [p][q] matrices; ap/bp (the inverse) are optional
+        sip = {"a": wcs.sip.a.tolist(), "b": wcs.sip.b.tolist()}
+        if wcs.sip.ap is not None and wcs.sip.bp is not None:
+            sip["ap"] = wcs.sip.ap.tolist()
+            sip["bp"] = wcs.sip.bp.tolist()
+        wcs_params["sip"] = sip
 
     return {
         "center_ra": center_ra,
@@ -0,0 +1,276 @@
+"""
+PDF rendering of observing session reports.
+
+The report arrives as a list of blocks (headings, paragraphs, lists, tables
+and image rows), the same structure the Rust side renders to Markdown, and
+is laid out onto A4 pages with Pillow.
+"""
+
+import os
+from typing import Callable, Optional
+
+# A4 at 150 dpi
+PAGE_WIDTH = 1240
+PAGE_HEIGHT = 1754
+RESOLUTION = 150.0
+MARGIN = 100
+
+BACKGROUND = (255, 255, 255)
+TEXT_COLOR = (25, 28, 36)
+MUTED_COLOR = (110, 116, 130)
+RULE_COLOR = (214, 218, 226)
+HEADER_FILL = (238, 241, 246)
+
+BODY_SIZE = 22
+HEADING_SIZES = {1: 44, 2: 32, 3: 26}
+LINE_SPACING = 1.4
+CELL_PADDING = 10
+IMAGES_PER_ROW = 3
+
+Measure = Callable[[str], float]
+
+
+def wrap_text(text: str, max_width: float, measure: Measure) -> list[str]:
+    """
+    Break ``text`` into lines no wider than ``max_width``.
+
+    Words wider than a whole line are split by character. Existing newlines
+    are kept as line breaks.
+    """
+    lines = []
+    for paragraph in text.split("\n"):
+        line = ""
+        for word in paragraph.split():
+            candidate = f"{line} {word}" if line else word
+            if measure(candidate) <= max_width:
+                line = candidate
+                continue
+            if line:
+                lines.append(line)
+            line = ""
+            for char in word:
+                if line and measure(line + char) > max_width:
+                    lines.append(line)
+                    line = ""
+                line += char
+        lines.append(line)
+    return lines
+
+
+def truncate_text(text: str, max_width: float, measure: Measure) -> str:
+    """Shorten ``text`` with an ellipsis so it fits in ``max_width``."""
+    if measure(text) <= max_width:
+        return text
+    while text and measure(text + "…") > max_width:
+        text = text[:-1]
+    return text + "…"
+
+
+def column_widths(
+    header: list[str], rows: list[list[str]], total_width: float, measure: Measure
+) -> list[float]:
+    """
+    Split ``total_width`` between table columns.
+
+    Columns get their natural width when everything fits; otherwise the
+    space is shared in proportion to each column's widest cell.
+    """
+    count = len(header)
+    if count == 0:
+        return []
+    natural = [measure(str(cell)) + 2 * CELL_PADDING for cell in header]
+    for row in rows:
+        for i, cell in enumerate(row[:count]):
+            natural[i] = max(natural[i], measure(str(cell)) + 2 * CELL_PADDING)
+    needed = sum(natural)
+    if needed <= total_width:
+        return natural
+    return [width * total_width / needed for width in natural]
+
+
+class _Document:
+    """Pages being filled top to bottom, with a cursor on the current one."""
+
+    def __init__(self):
+        from PIL import ImageFont
+
+        self.pages = []
+        self.fonts = {size: ImageFont.load_default(size=size) for size in HEADING_SIZES.values()}
+        self.fonts[BODY_SIZE] = ImageFont.load_default(size=BODY_SIZE)
+        self.content_width = PAGE_WIDTH - 2 * MARGIN
+        self._new_page()
+
+    def _new_page(self) -> None:
+        from PIL import Image, ImageDraw
+
+        page = Image.new("RGB", (PAGE_WIDTH, PAGE_HEIGHT), BACKGROUND)
+        self.pages.append(page)
+        self.draw = ImageDraw.Draw(page)
+        self.y = MARGIN
+
+    def ensure_space(self, height: float) -> None:
+        """Start a new page unless ``height`` fits below the cursor."""
+        if self.y + height > PAGE_HEIGHT - MARGIN and self.y > MARGIN:
+            self._new_page()
+
+    def measure(self, size: int) -> Measure:
+        font = self.fonts[size]
+        return lambda text: self.draw.textlength(text, font=font)
+
+    def line_height(self, size: int) -> int:
+        return round(size * LINE_SPACING)
+
+    def text_lines(self, lines: list[str], size: int, indent: int = 0, color=TEXT_COLOR) -> None:
+        for line in lines:
+            self.ensure_space(self.line_height(size))
+            self.draw.text((MARGIN + indent, self.y), line, font=self.fonts[size], fill=color)
+            self.y += self.line_height(size)
+
+    def heading(self, text: str, level: int) -> None:
+        size = HEADING_SIZES.get(level, HEADING_SIZES[3])
+        lines = wrap_text(text, self.content_width, self.measure(size))
+        # Keep a heading together with at least a line of what follows
+        self.ensure_space(self.line_height(size) * len(lines) + self.line_height(BODY_SIZE) * 2)
+        self.y += size // 2 if self.y > MARGIN else 0
+        self.text_lines(lines, size)
+        if level == 1:
+            self.draw.line(
+                (MARGIN, self.y, PAGE_WIDTH - MARGIN, self.y), fill=RULE_COLOR, width=2
+            )
+            self.y += BODY_SIZE // 2
+        self.y += BODY_SIZE // 4
+
+    def paragraph(self, text: str) -> None:
+        self.text_lines(wrap_text(text, self.content_width, self.measure(BODY_SIZE)), BODY_SIZE)
+        self.y += BODY_SIZE // 2
+
+    def bullet_list(self, items: list[str]) -> None:
+        indent = BODY_SIZE * 2
+        measure = self.measure(BODY_SIZE)
+        for item in items:
+            lines = wrap_text(item, self.content_width - indent, measure)
+            self.ensure_space(self.line_height(BODY_SIZE))
+            self.draw.text(
+                (MARGIN + BODY_SIZE // 2, self.y), "•", font=self.fonts[BODY_SIZE], fill=TEXT_COLOR
+            )
+            self.text_lines(lines, BODY_SIZE, indent)
+        self.y += BODY_SIZE // 2
+
+    def table(self, header: list[str], rows: list[list[str]]) -> None:
+        measure = self.measure(BODY_SIZE)
+        widths = column_widths(header, rows, self.content_width, measure)
+        row_height = self.line_height(BODY_SIZE) + CELL_PADDING
+
+        def draw_row(cells: list[str], fill=None) -> None:
+            if self.y + row_height > PAGE_HEIGHT - MARGIN:
+                self._new_page()
+                # Repeat the header at the top of a continued table
+                if cells is not header:
+                    draw_row(header, HEADER_FILL)
+            if fill:
+                self.draw.rectangle(
+                    (MARGIN, self.y, MARGIN + sum(widths), self.y + row_height), fill=fill
+                )
+            x = MARGIN
+            for cell, width in zip(cells, widths):
+                text = truncate_text(str(cell), width - 2 * CELL_PADDING, measure)
+                self.draw.text(
+                    (x + CELL_PADDING, self.y + CELL_PADDING // 2),
+                    text,
+                    font=self.fonts[BODY_SIZE],
+                    fill=TEXT_COLOR,
+                )
+                x += width
+            self.y += row_height
+            self.draw.line((MARGIN, self.y, MARGIN + sum(widths), self.y), fill=RULE_COLOR)
+
+        draw_row(header, HEADER_FILL)
+        for row in rows:
+            draw_row(row)
+        self.y += BODY_SIZE
+
+    def images(self, images: list[dict], base_dir: str) -> None:
+        from PIL import Image
+
+        count = 1 if len(images) == 1 else IMAGES_PER_ROW
+        gap = BODY_SIZE
+        cell_width = (self.content_width - gap * (count - 1)) / count
+        max_height = cell_width if count > 1 else (PAGE_HEIGHT - 2 * MARGIN) / 2
+        measure = self.measure(BODY_SIZE)
+
+        for start in range(0, len(images), count):
+            row = []
+            for entry in images[start : start + count]:
+                path = entry.get("path", "")
+                if not os.path.isabs(path):
+                    path = os.path.join(base_dir, path)
+                try:
+                    with Image.open(path) as opened:
+                        picture = opened.convert("RGB")
+                except OSError:
+                    picture = None
+                if picture is not None:
+                    picture.thumbnail((round(cell_width), round(max_height)))
+                row.append((picture, entry.get("caption") or ""))
+
+            height = max((p.height for p, _ in row if p is not None), default=0)
+            has_captions = any(caption for _, caption in row)
+            self.ensure_space(height + (self.line_height(BODY_SIZE) if has_captions else 0))
+            for i, (picture, caption) in enumerate(row):
+                x = MARGIN + i * (cell_width + gap)
+                if picture is not None:
+                    offset = (cell_width - picture.width) / 2
+                    self.pages[-1].paste(picture, (round(x + offset), round(self.y)))
+                if caption:
+                    text = truncate_text(caption, cell_width, measure)
+                    self.draw.text(
+                        (x + (cell_width - measure(text)) / 2, self.y + height + 4),
+                        text,
+                        font=self.fonts[BODY_SIZE],
+                        fill=MUTED_COLOR,
+                    )
+            self.y += height + (self.line_height(BODY_SIZE) if has_captions else 0) + gap
+
+
+def render_report_pdf(
+    output_path: str,
+    blocks: list,
+    base_dir: Optional[str] = None,
+) -> dict:
+    """
+    Lay out a session report onto A4 pages and save it as a PDF.
+
+    Args:
+        output_path: Where to write the PDF
+        blocks: Report content; each block is a dict with a ``type`` of
+            heading (text, level), paragraph (text), list (items),
+            table (header, rows) or images (images: [{path, caption}])
+        base_dir: Directory relative image paths are resolved against
+            (default: the PDF's directory)
+
+    Returns:
+        dict with success, path and pages, or success False and error
+    """
+    try:
+        base_dir = base_dir or os.path.dirname(os.path.abspath(output_path))
+        document = _Document()
+        for block in blocks:
+            kind = block.get("type")
+            if kind == "heading":
+                document.heading(block.get("text", ""), int(block.get("level", 1)))
+            elif kind == "paragraph":
+                document.paragraph(block.get("text", ""))
+            elif kind == "list":
+                document.bullet_list(block.get("items", []))
+            elif kind == "table":
+                document.table(block.get("header", []), block.get("rows", []))
+            elif kind == "images":
+                document.images(block.get("images", []), base_dir)
+
+        first, *rest = document.pages
+        first.save(
+            output_path, format="PDF", save_all=True, append_images=rest, resolution=RESOLUTION
+        )
+        return {"success": True, "path": output_path, "pages": len(document.pages)}
+    except Exception as e:
+        return {"success": False, "error": str(e)}
@@ -0,0 +1,177 @@
+"""JSON-RPC server for running astra_astro out of process.
+
+The desktop app normally calls astra_astro through an embedded interpreter.
+When that interpreter can't load the package (e.g. the linked Python doesn't
+match the one the dependencies were installed for), the app starts this
+server with ``python -m astra_astro.server`` instead and talks to it over
+stdin/stdout, one JSON object per line:
+
+    request:  {"id": 1, "method": "lookup_object", "params": {"object_name": "M31"}}
+    response: {"id": 1, "result": {...}}  or  {"id": 1, "error": "message"}
+    progress: {"id": 1, "progress": {"step": ..., "progress": ..., "message": ...}}
+
+Method names and parameters mirror the astra_astro functions. The embedded
+backend dispatches through the same ``METHODS`` table, so both behave alike.
+"""
+
+import json
+import math
+import platform
+import sys
+import traceback
+from datetime import datetime
+from typing import Callable, Optional, TextIO
+
+import astra_astro
+from astra_astro import altitude, annotate, catalog_query, image_process, plate_solve, report
+
+
+def _location(params: dict) -> altitude.ObserverLocation:
+    return altitude.ObserverLocation(**params.pop("location"))
+
+
+def _clean(value):
+    """Make a result JSON-safe for the Rust side.
+
+    None entries are dropped from dicts (the Rust structs treat missing and
+    null alike), non-finite floats become null, and numpy scalars/arrays are
+    converted to plain Python values.
+    """
+    if isinstance(value, dict):
+        cleaned = {str(k): _clean(v) for k, v in value.items()}
+        return {k: v for k, v in cleaned.items() if v is not None}
+    if isinstance(value, (list, tuple)):
+        return [_clean(v) for v in value]
+    if isinstance(value, float):
+        return value if math.isfinite(value) else None
+    if hasattr(value, "tolist"):
+        return _clean(value.tolist())
+    return value
+
+
+def ping(**_params) -> dict:
+    return {
+        "pythonVersion": platform.python_version(),
+        "executable": sys.executable,
+        "version": astra_astro.__version__,
+    }
+
+
+def calculate_altitude(**params) -> dict:
+    location = _location(params)
+    return altitude.calculate_altitude(location=location, **params)
+
+
+def calculate_altitude_data(**params) -> list:
+    location = _location(params)
+    return altitude.calculate_altitude_data(location=location, **params)
+
+
+def get_sunset_sunrise(**params) -> dict:
+    return altitude.get_sunset_sunrise(_location(params))
+
+
+def get_night_chart(date: Optional[str] = None, **params) -> dict:
+    location = _location(params)
+    night = datetime.fromisoformat(date) if date else None
+    return altitude.get_night_chart(location=location, date=night, **params)
+
+
+def compare_altitudes(date: Optional[str] = None, **params) -> dict:
+    location = _location(params)
+    night = datetime.fromisoformat(date) if date else None
+    return altitude.compare_altitudes(location=location, date=night, **params)
+
+
+def query_objects_in_fov(
+    fits_path: Optional[str] = None,
+    solve_result: Optional[dict] = None,
+    **params,
+) -> list:
+    objects = catalog_query.query_objects_in_fov(**params)
+    if fits_path:
+        objects = catalog_query.add_pixel_positions(objects, fits_path, solve_result)
+    return objects
+
+
+METHODS: dict[str, Callable] = {
+    "ping": ping,
+    "lookup_object": astra_astro.lookup_object,
+    "calculate_altitude": calculate_altitude,
+    "calculate_altitude_data": calculate_altitude_data,
+    "get_sunset_sunrise": get_sunset_sunrise,
+    "get_night_chart": get_night_chart,
+    "compare_altitudes": compare_altitudes,
+    "solve_image": astra_astro.solve_image,
+    "detect_solvers": plate_solve.detect_solvers,
+    "extract_solve_hints": plate_solve.extract_solve_hints,
+    "query_objects_in_fov": query_objects_in_fov,
+    "generate_skymap": astra_astro.generate_skymap,
+    "generate_wide_skymap": astra_astro.generate_wide_skymap,
+    "render_annotated_preview": annotate.render_annotated_preview,
+    "process_image_from_dict": astra_astro.process_image_from_dict,
+    "preview_image_from_dict": image_process.preview_image_from_dict,
+    "classify_target": astra_astro.classify_target,
+    "quick_preview": image_process.quick_preview,
+    "render_report_pdf": report.render_report_pdf,
+}
+
+# Methods that accept a progress_callback(step, progress, message)
+PROGRESS_METHODS = {"process_image_from_dict"}
+
+
+def handle(request: dict, emit: Callable[[dict], None]) -> dict:
+    """Run one request and return its response (progress goes through ``emit``)."""
+    request_id = request.get("id")
+    method = METHODS.get(request.get("method"))
+    if method is None:
+        return {"id": request_id, "error": f"Unknown method: {request.get('method')}"}
+
+    # Omitted and null parameters both fall back to the Python defaults
+    params = {k: v for k, v in (request.get("params") or {}).items() if v is not None}
+    if request.get("method") in PROGRESS_METHODS:
+
+        def progress_callback(step: str, progress: float, message: str = ""):
+            emit(
+                {
+                    "id": request_id,
+                    "progress": {"step": step, "progress": progress, "message": message},
+                }
+            )
+
+        params["progress_callback"] = progress_callback
+
+    try:
+        result = method(**params)
+    except Exception as e:
+        traceback.print_exc(file=sys.stderr)
+        return {"id": request_id, "error": str(e) or type(e).__name__}
+    return {"id": request_id, "result": _clean(result)}
+
+
+def serve(stdin: TextIO, stdout: TextIO) -> None:
+    def emit(message: dict) -> None:
+        stdout.write(json.dumps(message) + "\n")
+        stdout.flush()
+
+    for line in stdin:
+        line = line.strip()
+        if not line:
+            continue
+        try:
+            request = json.loads(line)
+        except json.JSONDecodeError as e:
+            emit({"id": None, "error": f"Invalid request: {e}"})
+            continue
+        emit(handle(request, emit))
+
+
+def main() -> None:
+    # Keep stray prints from libraries off the protocol stream
+    protocol = sys.stdout
+    sys.stdout = sys.stderr
+    serve(sys.stdin, protocol)
+
+
+if __name__ == "__main__":
+    main()
@@ -1,8 +1,15 @@
 """Tests for astra_astro.altitude pure functions."""
 
+from datetime import datetime, timedelta, timezone
+
 import pytest
 
-from astra_astro.altitude import _azimuth_to_compass
+from astra_astro.altitude import (
+    _azimuth_to_compass,
+    _intervals,
+    _local_noon,
+    _summarize_track,
+)
 
 
 class TestAzimuthToCompass:
@@ -57,3 +64,57 @@ class TestAzimuthToCompass:
             assert _azimuth_to_compass(azimuth) == direction, (
                 f"Expected {direction} at {azimuth} degrees"
             )
+
+
+START = datetime(2026, 1, 1, tzinfo=timezone.utc)
+
+
+class TestIntervals:
+    times = [START + timedelta(minutes=10 * i) for i in range(6)]
+
+    def test_runs_of_flagged_samples(self):
+        intervals = _intervals(self.times, [False, True, True, False, True, True])
+        assert [(i["start"][11:16], i["end"][11:16]) for i in intervals] == [
+            ("00:10", "00:20"),
+            ("00:40", "00:50"),
+        ]
+
+    def test_all_flagged(self):
+        assert _intervals(self.times, [True] * 6) == [
+            {"start": self.times[0].isoformat(), "end": self.times[-1].isoformat()}
+        ]
+
+    def test_none_flagged(self):
+        assert _intervals(self.times, [False] * 6) == []
+
+
+class TestLocalNoon:
+    def test_west_of_greenwich(self):
+        date = datetime(2026, 1, 1, 23, 30, tzinfo=timezone.utc)
+        assert _local_noon(date, -75.0) == datetime(2026, 1, 1, 17, tzinfo=timezone.utc)
+
+    def test_east_of_greenwich(self):
+        date = datetime(2026, 1, 1, tzinfo=timezone.utc)
+        assert _local_noon(date, 150.0) == datetime(2026, 1, 1, 2, tzinfo=timezone.utc)
+
+
+class TestSummarizeTrack:
+    def points(self, altitudes):
+        return [
+            {"time": (START + timedelta(minutes=30 * i)).isoformat(), "altitude": a}
+            for i, a in enumerate(altitudes)
+        ]
+
+    def test_best_time_is_in_darkness(self):
+        points = self.points([70, 50, 40, 35, 20])
+        summary = _summarize_track(points, [False, True, True, True, True], 30.0, 30)
+        assert summary["maxAltitude"] == 50
+        assert summary["bestTime"] == points[1]["time"]
+        assert summary["hoursAboveMin"] == 1.5
+        assert summary["windows"] == [{"start": points[1]["time"], "end": points[3]["time"]}]
+
+    def test_never_dark(self):
+        summary = _summarize_track(self.points([10, 40, 20]), [False] * 3, 30.0, 30)
+        assert summary["maxAltitude"] == 40
+        assert summary["hoursAboveMin"] == 0
+        assert summary["windows"] == []
@@ -0,0 +1,69 @@
+"""Tests for astra_astro.annotate pure functions."""
+
+import pytest
+
+from astra_astro.annotate import (
+    DEFAULT_OBJECT_COLOR,
+    OBJECT_COLORS,
+    format_dec,
+    format_ra,
+    grid_step,
+    grid_values,
+    object_color,
+)
+
+
+# ---------------------------------------------------------------------------
+# grid_step / grid_values
+# ---------------------------------------------------------------------------
+class TestGridStep:
+    def test_degree_field(self):
+        assert grid_step(6.0) == 1.0
+
+    def test_narrow_field(self):
+        assert grid_step(1.0) == pytest.approx(10 / 60)
+
+    def test_zero_span(self):
+        assert grid_step(0.0) == pytest.approx(1 / 60)
+
+
+class TestGridValues:
+    def test_multiples_in_range(self):
+        assert grid_values(82.3, 85.1, 1.0) == [83.0, 84.0, 85.0]
+
+    def test_negative_range(self):
+        assert grid_values(-5.9, -4.1, 0.5) == [-5.5, -5.0, -4.5]
+
+    def test_empty(self):
+        assert grid_values(1.1, 1.9, 1.0) == []
+
+
+# ---------------------------------------------------------------------------
+# format_ra / format_dec
+# ---------------------------------------------------------------------------
+class TestFormatCoordinates:
+    def test_ra_minutes(self):
+        assert format_ra(83.75) == "05h35m"
+
+    def test_ra_seconds(self):
+        assert format_ra(83.8125) == "05h35m15s"
+
+    def test_ra_wraps(self):
+        assert format_ra(360.0) == "00h00m"
+
+    def test_dec(self):
+        assert format_dec(-5.5) == "-05°30'"
+        assert format_dec(41.25) == "+41°15'"
+
+
+# ---------------------------------------------------------------------------
+# object_color
+# ---------------------------------------------------------------------------
+class TestObjectColor:
+    def test_known_types(self):
+        assert object_color("Spiral Galaxy") == OBJECT_COLORS["galaxy"]
+        assert object_color("Emission Nebula") == OBJECT_COLORS["nebula"]
+
+    def test_unknown_type(self):
+        assert object_color("Quasar") == DEFAULT_OBJECT_COLOR
+        assert object_color(None) == DEFAULT_OBJECT_COLOR
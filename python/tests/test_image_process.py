@@ -5,15 +5,19 @@ import numpy.testing as npt
 import pytest
 
 from astra_astro.image_process import (
+    ProcessingParams,
     _apply_contrast_curve,
     _apply_noise_reduction,
     _arcsinh_stretch,
     _calculate_mtf_balance,
     _color_calibrate,
+    _downsample,
     _log_stretch,
     _mtf,
+    _params_from_dict,
     _reduce_stars,
     _remove_background,
+    _remove_gradient_polynomial,
     _statistical_stretch,
 )
 
@@ -212,6 +216,37 @@ class TestRemoveBackground:
         npt.assert_allclose(result, 0.0)
 
 
+# ---------------------------------------------------------------------------
+# _remove_gradient_polynomial
+# ---------------------------------------------------------------------------
+class TestRemoveGradientPolynomial:
+    def test_removes_quadratic_gradient(self):
+        """A smooth gradient plus a flat sky should become flat."""
+        y, x = np.mgrid[0:64, 0:96] / 64.0
+        data = 0.1 + 0.2 * x + 0.1 * y**2
+        result = _remove_gradient_polynomial(data, order=2, grid=16)
+        assert np.ptp(result[2:-2, 2:-2]) < 1e-6
+
+    def test_ignores_stars(self):
+        """Bright point sources should not bend the fitted surface."""
+        y, x = np.mgrid[0:64, 0:64] / 64.0
+        data = 0.1 + 0.3 * x
+        data[10, 10] = data[40, 50] = 1.0
+        result = _remove_gradient_polynomial(data, order=1, grid=16)
+        assert np.max(result) == pytest.approx(1.0)
+        assert np.median(result) < 1e-6
+
+    def test_multichannel(self):
+        data = np.random.default_rng(42).uniform(0, 1, (40, 50, 3))
+        result = _remove_gradient_polynomial(data)
+        assert result.shape == data.shape
+
+    def test_all_zeros(self):
+        data = np.zeros((30, 30))
+        result = _remove_gradient_polynomial(data)
+        npt.assert_allclose(result, 0.0)
+
+
 # ---------------------------------------------------------------------------
 # _color_calibrate
 # ---------------------------------------------------------------------------
@@ -337,3 +372,46 @@ class TestReduceStars:
         result = _reduce_stars(data, threshold=0.8)
         # All values identical means no peaks detected, reduction map is all 1s
         npt.assert_allclose(result, data, atol=0.05)
+
+
+# ---------------------------------------------------------------------------
+# _downsample
+# ---------------------------------------------------------------------------
+class TestDownsample:
+    def test_small_image_unchanged(self):
+        data = np.ones((10, 20))
+        result, binning = _downsample(data, 32)
+        assert binning == 1
+        assert result is data
+
+    def test_fits_within_max_size(self):
+        data = np.random.default_rng(42).uniform(0, 1, (100, 250, 3))
+        result, binning = _downsample(data, 100)
+        assert binning == 3
+        assert result.shape == (33, 83, 3)
+
+    def test_averages_blocks(self):
+        data = np.arange(16, dtype=np.float64).reshape(4, 4)
+        result, binning = _downsample(data, 2)
+        assert binning == 2
+        npt.assert_allclose(result, [[2.5, 4.5], [10.5, 12.5]])
+
+
+# ---------------------------------------------------------------------------
+# _params_from_dict
+# ---------------------------------------------------------------------------
+class TestParamsFromDict:
+    def test_defaults(self):
+        assert _params_from_dict(None) == ProcessingParams()
+
+    def test_camel_case_keys(self):
+        params = _params_from_dict({"stretchFactor": 0.2, "starReduction": True, "contrast": 1})
+        assert params.stretch_factor == 0.2
+        assert params.star_reduction is True
+        assert params.contrast == 1.0
+        assert params.stretch_method == "statistical"
+
+    def test_background_method_keys(self):
+        params = _params_from_dict({"backgroundMethod": "polynomial", "gradientOrder": 3})
+        assert params.background_method == "polynomial"
+        assert params.gradient_order == 3
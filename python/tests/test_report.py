@@ -0,0 +1,48 @@
+"""Tests for astra_astro.report layout helpers."""
+
+from astra_astro.report import CELL_PADDING, column_widths, truncate_text, wrap_text
+
+# One unit per character keeps widths easy to reason about
+measure = len
+
+
+# ---------------------------------------------------------------------------
+# wrap_text
+# ---------------------------------------------------------------------------
+class TestWrapText:
+    def test_breaks_between_words(self):
+        assert wrap_text("the quick brown fox", 9, measure) == ["the quick", "brown fox"]
+
+    def test_splits_long_words(self):
+        assert wrap_text("abcdefghijkl xy", 5, measure) == ["abcde", "fghij", "kl xy"]
+
+    def test_keeps_newlines(self):
+        assert wrap_text("one\ntwo", 20, measure) == ["one", "two"]
+
+
+# ---------------------------------------------------------------------------
+# truncate_text
+# ---------------------------------------------------------------------------
+class TestTruncateText:
+    def test_fits(self):
+        assert truncate_text("M42", 5, measure) == "M42"
+
+    def test_ellipsis(self):
+        assert truncate_text("Andromeda", 5, measure) == "Andr…"
+
+
+# ---------------------------------------------------------------------------
+# column_widths
+# ---------------------------------------------------------------------------
+class TestColumnWidths:
+    def test_natural_widths_when_they_fit(self):
+        widths = column_widths(["a", "bb"], [["xxxx", "y"]], 100, measure)
+        assert widths == [4 + 2 * CELL_PADDING, 2 + 2 * CELL_PADDING]
+
+    def test_scaled_to_fit(self):
+        widths = column_widths(["a", "bb"], [["x" * 80, "y"]], 100, measure)
+        assert sum(widths) == 100
+        assert widths[0] > widths[1]
+
+    def test_no_columns(self):
+        assert column_widths([], [], 100, measure) == []
@@ -0,0 +1,90 @@
+"""Tests for the astra_astro.server JSON-RPC loop."""
+
+import io
+import json
+import math
+
+import numpy as np
+import pytest
+
+from astra_astro import server
+
+
+@pytest.fixture
+def methods(monkeypatch):
+    def echo(**params):
+        return params
+
+    def fail(**_params):
+        raise ValueError("bad input")
+
+    def slow(progress_callback, steps=2):
+        for i in range(steps):
+            progress_callback("step", (i + 1) / steps, f"{i + 1}/{steps}")
+        return {"success": True}
+
+    monkeypatch.setitem(server.METHODS, "echo", echo)
+    monkeypatch.setitem(server.METHODS, "fail", fail)
+    monkeypatch.setitem(server.METHODS, "slow", slow)
+    monkeypatch.setattr(server, "PROGRESS_METHODS", {"slow"})
+
+
+def run(*requests) -> list[dict]:
+    stdin = io.StringIO("".join(json.dumps(r) + "\n" for r in requests))
+    stdout = io.StringIO()
+    server.serve(stdin, stdout)
+    return [json.loads(line) for line in stdout.getvalue().splitlines()]
+
+
+# ---------------------------------------------------------------------------
+# _clean
+# ---------------------------------------------------------------------------
+class TestClean:
+    def test_drops_none_from_dicts(self):
+        assert server._clean({"a": 1, "b": None, "c": {"d": None}}) == {"a": 1, "c": {}}
+
+    def test_keeps_none_in_lists(self):
+        assert server._clean([1, None]) == [1, None]
+
+    def test_non_finite_floats(self):
+        assert server._clean([math.nan, math.inf, 1.5]) == [None, None, 1.5]
+
+    def test_numpy_values(self):
+        assert server._clean({"x": np.float32(2.5), "a": np.arange(3)}) == {
+            "x": 2.5,
+            "a": [0, 1, 2],
+        }
+
+
+# ---------------------------------------------------------------------------
+# serve
+# ---------------------------------------------------------------------------
+class TestServe:
+    def test_result(self, methods):
+        [response] = run({"id": 1, "method": "echo", "params": {"x": 1}})
+        assert response == {"id": 1, "result": {"x": 1}}
+
+    def test_null_params_use_defaults(self, methods):
+        [response] = run({"id": 1, "method": "echo", "params": {"x": None}})
+        assert response["result"] == {}
+
+    def test_error(self, methods):
+        [response] = run({"id": 2, "method": "fail"})
+        assert response == {"id": 2, "error": "bad input"}
+
+    def test_unknown_method(self, methods):
+        [response] = run({"id": 3, "method": "nope"})
+        assert "Unknown method" in response["error"]
+
+    def test_invalid_json_keeps_serving(self, methods):
+        stdin = io.StringIO('not json\n{"id": 4, "method": "echo"}\n')
+        stdout = io.StringIO()
+        server.serve(stdin, stdout)
+        lines = [json.loads(line) for line in stdout.getvalue().splitlines()]
+        assert lines[0]["id"] is None
+        assert lines[1] == {"id": 4, "result": {}}
+
+    def test_progress_before_result(self, methods):
+        responses = run({"id": 5, "method": "slow", "params": {"steps": 2}})
+        assert [r.get("progress", {}).get("progress") for r in responses[:2]] == [0.5, 1.0]
+        assert responses[2] == {"id": 5, "result": {"success": True}}
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS image_equipment;
+DROP TABLE IF EXISTS equipment;
@@ -0,0 +1,39 @@
+-- Equipment inventory: telescopes, cameras, filters, mounts, reducers
+CREATE TABLE equipment (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    -- One of: telescope, camera, filter, mount, reducer
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    manufacturer TEXT,
+    model TEXT,
+    -- Comma-separated FITS header values (TELESCOP/INSTRUME) this item matches
+    match_patterns TEXT,
+    focal_length_mm REAL,
+    aperture_mm REAL,
+    pixel_size_um REAL,
+    sensor_width_px INTEGER,
+    sensor_height_px INTEGER,
+    -- Focal length multiplier for reducers/barlows (e.g. 0.8)
+    reduction_factor REAL,
+    notes TEXT,
+    metadata TEXT,
+    retired BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_equipment_user_id ON equipment(user_id);
+CREATE INDEX idx_equipment_kind ON equipment(kind);
+
+-- Join table linking images to the equipment used to capture them
+CREATE TABLE image_equipment (
+    id TEXT PRIMARY KEY NOT NULL,
+    image_id TEXT NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+    equipment_id TEXT NOT NULL REFERENCES equipment(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(image_id, equipment_id)
+);
+
+CREATE INDEX idx_image_equipment_image_id ON image_equipment(image_id);
+CREATE INDEX idx_image_equipment_equipment_id ON image_equipment(equipment_id);
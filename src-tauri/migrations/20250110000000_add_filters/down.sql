@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS image_filters;
+DROP TABLE IF EXISTS filters;
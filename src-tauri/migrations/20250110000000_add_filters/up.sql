@@ -0,0 +1,26 @@
+-- Normalized filters (Ha, OIII, SII, L, R, G, B, ...) parsed from FITS FILTER headers
+CREATE TABLE filters (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    -- Canonical filter name, e.g. "Ha", "OIII", "L"
+    name TEXT NOT NULL,
+    -- One of: narrowband, dualband, broadband, other
+    band TEXT NOT NULL DEFAULT 'other',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, name)
+);
+
+-- Filter and exposure used for each image (one row per image)
+CREATE TABLE image_filters (
+    image_id TEXT PRIMARY KEY NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+    filter_id TEXT NOT NULL REFERENCES filters(id) ON DELETE CASCADE,
+    -- FILTER header value as written by the capture software
+    raw_value TEXT,
+    -- Number of frames integrated (1 for a single sub)
+    frame_count INTEGER NOT NULL DEFAULT 1,
+    -- Total integration time in seconds (sub exposure * frame_count)
+    exposure_seconds REAL NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_image_filters_filter_id ON image_filters(filter_id);
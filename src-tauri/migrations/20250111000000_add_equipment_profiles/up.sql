@@ -0,0 +1,20 @@
+-- Equipment profiles: a rig (telescope + camera + optional reducer) with
+-- plate solving scale bounds and default processing parameters
+CREATE TABLE equipment_profiles (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    telescope_id TEXT REFERENCES equipment(id) ON DELETE SET NULL,
+    camera_id TEXT REFERENCES equipment(id) ON DELETE SET NULL,
+    reducer_id TEXT REFERENCES equipment(id) ON DELETE SET NULL,
+    -- Expected image scale bounds (arcsec/pixel); derived from optics when NULL
+    scale_lower REAL,
+    scale_upper REAL,
+    -- JSON object of ProcessingParams overrides (camelCase keys)
+    processing_params TEXT,
+    notes TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_equipment_profiles_user_id ON equipment_profiles(user_id);
@@ -0,0 +1,39 @@
+DROP TRIGGER IF EXISTS sync_users_insert;
+DROP TRIGGER IF EXISTS sync_users_update;
+DROP TRIGGER IF EXISTS sync_users_delete;
+DROP TRIGGER IF EXISTS sync_astro_objects_insert;
+DROP TRIGGER IF EXISTS sync_astro_objects_update;
+DROP TRIGGER IF EXISTS sync_astro_objects_delete;
+DROP TRIGGER IF EXISTS sync_collections_insert;
+DROP TRIGGER IF EXISTS sync_collections_update;
+DROP TRIGGER IF EXISTS sync_collections_delete;
+DROP TRIGGER IF EXISTS sync_equipment_insert;
+DROP TRIGGER IF EXISTS sync_equipment_update;
+DROP TRIGGER IF EXISTS sync_equipment_delete;
+DROP TRIGGER IF EXISTS sync_equipment_profiles_insert;
+DROP TRIGGER IF EXISTS sync_equipment_profiles_update;
+DROP TRIGGER IF EXISTS sync_equipment_profiles_delete;
+DROP TRIGGER IF EXISTS sync_filters_insert;
+DROP TRIGGER IF EXISTS sync_filters_update;
+DROP TRIGGER IF EXISTS sync_filters_delete;
+DROP TRIGGER IF EXISTS sync_images_insert;
+DROP TRIGGER IF EXISTS sync_images_update;
+DROP TRIGGER IF EXISTS sync_images_delete;
+DROP TRIGGER IF EXISTS sync_collection_images_insert;
+DROP TRIGGER IF EXISTS sync_collection_images_update;
+DROP TRIGGER IF EXISTS sync_collection_images_delete;
+DROP TRIGGER IF EXISTS sync_image_equipment_insert;
+DROP TRIGGER IF EXISTS sync_image_equipment_update;
+DROP TRIGGER IF EXISTS sync_image_equipment_delete;
+DROP TRIGGER IF EXISTS sync_image_filters_insert;
+DROP TRIGGER IF EXISTS sync_image_filters_update;
+DROP TRIGGER IF EXISTS sync_image_filters_delete;
+DROP TRIGGER IF EXISTS sync_astronomy_todos_insert;
+DROP TRIGGER IF EXISTS sync_astronomy_todos_update;
+DROP TRIGGER IF EXISTS sync_astronomy_todos_delete;
+DROP TRIGGER IF EXISTS sync_observation_schedules_insert;
+DROP TRIGGER IF EXISTS sync_observation_schedules_update;
+DROP TRIGGER IF EXISTS sync_observation_schedules_delete;
+DROP TABLE IF EXISTS sync_meta;
+DROP TABLE IF EXISTS sync_imports;
+DROP TABLE IF EXISTS sync_state;
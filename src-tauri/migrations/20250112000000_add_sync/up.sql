@@ -0,0 +1,454 @@
+-- Sync change log: one change vector per synced row. Triggers keep it up to
+-- date so every insert, update, and delete is recorded, including deletions
+-- (tombstones). simbad_cache and scanned_directories are machine-local and
+-- not synced. Triggers avoid INSERT OR REPLACE because an upsert on the
+-- synced table would override its conflict resolution.
+CREATE TABLE sync_state (
+    table_name TEXT NOT NULL,
+    row_id TEXT NOT NULL,
+    -- UTC time of the last change (ISO 8601 with milliseconds)
+    updated_at TEXT NOT NULL,
+    -- Tombstone: the row was deleted
+    deleted BOOLEAN NOT NULL DEFAULT 0,
+    -- Device the last change came from; NULL for local changes
+    origin TEXT,
+    -- Local, monotonically increasing change number used for exports
+    version INTEGER NOT NULL,
+    PRIMARY KEY (table_name, row_id)
+);
+
+CREATE INDEX idx_sync_state_version ON sync_state(version);
+
+-- Change bundles already merged into this database
+CREATE TABLE sync_imports (
+    bundle_id TEXT PRIMARY KEY NOT NULL,
+    device_id TEXT NOT NULL,
+    change_count INTEGER NOT NULL,
+    imported_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Key/value sync bookkeeping (e.g. the last exported version)
+CREATE TABLE sync_meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+
+
+-- users
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'users', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM users;
+
+CREATE TRIGGER sync_users_insert AFTER INSERT ON users
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'users' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'users', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'users' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_users_update AFTER UPDATE ON users
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'users' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'users', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'users' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_users_delete AFTER DELETE ON users
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'users' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'users', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'users' AND row_id = OLD.id);
+END;
+
+
+-- astro_objects
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'astro_objects', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM astro_objects;
+
+CREATE TRIGGER sync_astro_objects_insert AFTER INSERT ON astro_objects
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astro_objects' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astro_objects', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astro_objects' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_astro_objects_update AFTER UPDATE ON astro_objects
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astro_objects' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astro_objects', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astro_objects' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_astro_objects_delete AFTER DELETE ON astro_objects
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astro_objects' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astro_objects', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astro_objects' AND row_id = OLD.id);
+END;
+
+
+-- collections
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'collections', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM collections;
+
+CREATE TRIGGER sync_collections_insert AFTER INSERT ON collections
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collections' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collections', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collections' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_collections_update AFTER UPDATE ON collections
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collections' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collections', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collections' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_collections_delete AFTER DELETE ON collections
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collections' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collections', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collections' AND row_id = OLD.id);
+END;
+
+
+-- equipment
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'equipment', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM equipment;
+
+CREATE TRIGGER sync_equipment_insert AFTER INSERT ON equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_equipment_update AFTER UPDATE ON equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_equipment_delete AFTER DELETE ON equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment' AND row_id = OLD.id);
+END;
+
+
+-- equipment_profiles
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'equipment_profiles', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM equipment_profiles;
+
+CREATE TRIGGER sync_equipment_profiles_insert AFTER INSERT ON equipment_profiles
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment_profiles' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment_profiles', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment_profiles' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_equipment_profiles_update AFTER UPDATE ON equipment_profiles
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment_profiles' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment_profiles', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment_profiles' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_equipment_profiles_delete AFTER DELETE ON equipment_profiles
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'equipment_profiles' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'equipment_profiles', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'equipment_profiles' AND row_id = OLD.id);
+END;
+
+
+-- filters
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'filters', id, strftime('%Y-%m-%dT%H:%M:%fZ', created_at), 0, NULL, 0 FROM filters;
+
+CREATE TRIGGER sync_filters_insert AFTER INSERT ON filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'filters' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'filters', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'filters' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_filters_update AFTER UPDATE ON filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'filters' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'filters', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'filters' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_filters_delete AFTER DELETE ON filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'filters' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'filters', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'filters' AND row_id = OLD.id);
+END;
+
+
+-- images
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'images', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM images;
+
+CREATE TRIGGER sync_images_insert AFTER INSERT ON images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'images' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_images_update AFTER UPDATE ON images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'images' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_images_delete AFTER DELETE ON images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'images' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'images', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'images' AND row_id = OLD.id);
+END;
+
+
+-- collection_images
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'collection_images', id, strftime('%Y-%m-%dT%H:%M:%fZ', created_at), 0, NULL, 0 FROM collection_images;
+
+CREATE TRIGGER sync_collection_images_insert AFTER INSERT ON collection_images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collection_images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collection_images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collection_images' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_collection_images_update AFTER UPDATE ON collection_images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collection_images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collection_images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collection_images' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_collection_images_delete AFTER DELETE ON collection_images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'collection_images' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'collection_images', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'collection_images' AND row_id = OLD.id);
+END;
+
+
+-- image_equipment
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'image_equipment', id, strftime('%Y-%m-%dT%H:%M:%fZ', created_at), 0, NULL, 0 FROM image_equipment;
+
+CREATE TRIGGER sync_image_equipment_insert AFTER INSERT ON image_equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_equipment' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_equipment', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_equipment' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_image_equipment_update AFTER UPDATE ON image_equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_equipment' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_equipment', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_equipment' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_image_equipment_delete AFTER DELETE ON image_equipment
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_equipment' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_equipment', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_equipment' AND row_id = OLD.id);
+END;
+
+
+-- image_filters
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'image_filters', image_id, strftime('%Y-%m-%dT%H:%M:%fZ', created_at), 0, NULL, 0 FROM image_filters;
+
+CREATE TRIGGER sync_image_filters_insert AFTER INSERT ON image_filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_filters' AND row_id = NEW.image_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_filters', NEW.image_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_filters' AND row_id = NEW.image_id);
+END;
+
+CREATE TRIGGER sync_image_filters_update AFTER UPDATE ON image_filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_filters' AND row_id = NEW.image_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_filters', NEW.image_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_filters' AND row_id = NEW.image_id);
+END;
+
+CREATE TRIGGER sync_image_filters_delete AFTER DELETE ON image_filters
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'image_filters' AND row_id = OLD.image_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'image_filters', OLD.image_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'image_filters' AND row_id = OLD.image_id);
+END;
+
+
+-- astronomy_todos
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'astronomy_todos', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM astronomy_todos;
+
+CREATE TRIGGER sync_astronomy_todos_insert AFTER INSERT ON astronomy_todos
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astronomy_todos' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astronomy_todos', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astronomy_todos' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_astronomy_todos_update AFTER UPDATE ON astronomy_todos
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astronomy_todos' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astronomy_todos', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astronomy_todos' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_astronomy_todos_delete AFTER DELETE ON astronomy_todos
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'astronomy_todos' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'astronomy_todos', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'astronomy_todos' AND row_id = OLD.id);
+END;
+
+
+-- observation_schedules
+INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+SELECT 'observation_schedules', id, strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), 0, NULL, 0 FROM observation_schedules;
+
+CREATE TRIGGER sync_observation_schedules_insert AFTER INSERT ON observation_schedules
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'observation_schedules' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'observation_schedules', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'observation_schedules' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_observation_schedules_update AFTER UPDATE ON observation_schedules
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'observation_schedules' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'observation_schedules', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'observation_schedules' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_observation_schedules_delete AFTER DELETE ON observation_schedules
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'observation_schedules' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'observation_schedules', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'observation_schedules' AND row_id = OLD.id);
+END;
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS idx_images_date_obs;
+DROP INDEX IF EXISTS idx_images_filter;
+DROP INDEX IF EXISTS idx_images_telescope;
+ALTER TABLE images DROP COLUMN exposure;
+ALTER TABLE images DROP COLUMN gain;
+ALTER TABLE images DROP COLUMN filter;
+ALTER TABLE images DROP COLUMN telescope;
+ALTER TABLE images DROP COLUMN date_obs;
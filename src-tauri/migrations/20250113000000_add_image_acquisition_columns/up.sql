@@ -0,0 +1,81 @@
+-- Structured acquisition columns so filtering and stats don't have to parse
+-- the metadata JSON. The JSON is kept as the full record of the FITS headers.
+ALTER TABLE images ADD COLUMN exposure REAL;
+ALTER TABLE images ADD COLUMN gain INTEGER;
+ALTER TABLE images ADD COLUMN filter TEXT;
+ALTER TABLE images ADD COLUMN telescope TEXT;
+ALTER TABLE images ADD COLUMN date_obs TEXT;
+
+CREATE INDEX idx_images_date_obs ON images(date_obs);
+CREATE INDEX idx_images_filter ON images(filter);
+CREATE INDEX idx_images_telescope ON images(telescope);
+
+-- Backfill from existing metadata. Bulk scan stores parsed fields
+-- ("exposure", "gain", ...) next to "raw_headers"; auto-import stores only
+-- the raw headers, whose values are fitrs debug strings such as
+-- Some(RealFloatingNumber(30.0)) or Some(CharacterString("L-Pro")).
+CREATE TEMP TABLE acquisition_backfill AS
+SELECT
+    id,
+    COALESCE(
+        json_extract(metadata, '$.exposure'),
+        json_extract(metadata, '$.EXPTIME'), json_extract(metadata, '$.raw_headers.EXPTIME'),
+        json_extract(metadata, '$.EXPOSURE'), json_extract(metadata, '$.raw_headers.EXPOSURE')
+    ) AS exposure,
+    COALESCE(
+        json_extract(metadata, '$.gain'),
+        json_extract(metadata, '$.GAIN'), json_extract(metadata, '$.raw_headers.GAIN')
+    ) AS gain,
+    COALESCE(
+        json_extract(metadata, '$.filter'),
+        json_extract(metadata, '$.FILTER'), json_extract(metadata, '$.raw_headers.FILTER')
+    ) AS filter,
+    COALESCE(
+        json_extract(metadata, '$.telescope'),
+        json_extract(metadata, '$.TELESCOP'), json_extract(metadata, '$.raw_headers.TELESCOP')
+    ) AS telescope,
+    COALESCE(
+        json_extract(metadata, '$.date_obs'),
+        json_extract(metadata, '$."DATE-OBS"'), json_extract(metadata, '$.raw_headers."DATE-OBS"')
+    ) AS date_obs
+FROM images
+WHERE metadata IS NOT NULL AND json_valid(metadata);
+
+-- Strip the fitrs wrappers down to the bare value
+UPDATE acquisition_backfill SET
+    exposure = replace(replace(replace(replace(replace(replace(
+        exposure, 'Some(', ''), 'RealFloatingNumber(', ''), 'FloatingPoint(', ''),
+        'IntegerNumber(', ''), ')', ''), ' ', ''),
+    gain = replace(replace(replace(replace(replace(replace(
+        gain, 'Some(', ''), 'RealFloatingNumber(', ''), 'FloatingPoint(', ''),
+        'IntegerNumber(', ''), ')', ''), ' ', ''),
+    filter = trim(replace(replace(replace(replace(
+        filter, 'Some(CharacterString("', ''), 'CharacterString("', ''), '"))', ''), '")', '')),
+    telescope = trim(replace(replace(replace(replace(
+        telescope, 'Some(CharacterString("', ''), 'CharacterString("', ''), '"))', ''), '")', '')),
+    date_obs = trim(replace(replace(replace(replace(
+        date_obs, 'Some(CharacterString("', ''), 'CharacterString("', ''), '"))', ''), '")', ''));
+
+-- A derived backfill is the same on every device, so it must not be
+-- recorded as a local change for folder sync
+DROP TRIGGER sync_images_update;
+
+UPDATE images SET
+    exposure = (SELECT CASE WHEN b.exposure GLOB '*[0-9]*' THEN CAST(b.exposure AS REAL) END FROM acquisition_backfill b WHERE b.id = images.id),
+    gain = (SELECT CASE WHEN b.gain GLOB '*[0-9]*' THEN CAST(CAST(b.gain AS REAL) AS INTEGER) END FROM acquisition_backfill b WHERE b.id = images.id),
+    filter = (SELECT NULLIF(b.filter, '') FROM acquisition_backfill b WHERE b.id = images.id),
+    telescope = (SELECT NULLIF(b.telescope, '') FROM acquisition_backfill b WHERE b.id = images.id),
+    date_obs = (SELECT NULLIF(b.date_obs, '') FROM acquisition_backfill b WHERE b.id = images.id)
+WHERE id IN (SELECT id FROM acquisition_backfill);
+
+CREATE TRIGGER sync_images_update AFTER UPDATE ON images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'images' AND row_id = NEW.id);
+END;
+
+DROP TABLE acquisition_backfill;
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_processing_presets_insert;
+DROP TRIGGER IF EXISTS sync_processing_presets_update;
+DROP TRIGGER IF EXISTS sync_processing_presets_delete;
+DROP TABLE IF EXISTS processing_presets;
@@ -0,0 +1,48 @@
+-- Processing presets: named, reusable sets of processing parameters
+CREATE TABLE processing_presets (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    -- Target type the preset is meant for (NULL = any target)
+    target_type TEXT,
+    -- JSON object of ProcessingParams (camelCase keys)
+    params TEXT NOT NULL,
+    -- Used by process_fits_image when no preset is given; at most one per
+    -- user and target type
+    is_default BOOLEAN NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_processing_presets_user_id ON processing_presets(user_id);
+
+-- Track changes for sync
+CREATE TRIGGER sync_processing_presets_insert AFTER INSERT ON processing_presets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'processing_presets' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'processing_presets', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'processing_presets' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_processing_presets_update AFTER UPDATE ON processing_presets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'processing_presets' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'processing_presets', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'processing_presets' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_processing_presets_delete AFTER DELETE ON processing_presets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'processing_presets' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'processing_presets', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'processing_presets' AND row_id = OLD.id);
+END;
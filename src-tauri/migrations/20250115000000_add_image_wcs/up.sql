@@ -0,0 +1,25 @@
+-- Full WCS solution from plate solving (JSON: crval, crpix, cd and optional
+-- SIP terms), so pixel <-> sky conversion doesn't have to dig through the
+-- metadata JSON.
+ALTER TABLE images ADD COLUMN wcs TEXT;
+
+-- A derived backfill is the same on every device, so it must not be
+-- recorded as a local change for folder sync
+DROP TRIGGER sync_images_update;
+
+UPDATE images
+SET wcs = json_extract(metadata, '$.plate_solve.wcs')
+WHERE json_valid(metadata)
+    AND json_type(metadata, '$.plate_solve.wcs.crval') = 'array'
+    AND json_type(metadata, '$.plate_solve.wcs.crpix') = 'array'
+    AND json_type(metadata, '$.plate_solve.wcs.cd') = 'array';
+
+CREATE TRIGGER sync_images_update AFTER UPDATE ON images
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'images' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'images', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'images' AND row_id = NEW.id);
+END;
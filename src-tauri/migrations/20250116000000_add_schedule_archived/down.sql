@@ -0,0 +1 @@
+ALTER TABLE observation_schedules DROP COLUMN archived;
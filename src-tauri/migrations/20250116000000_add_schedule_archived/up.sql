@@ -0,0 +1,3 @@
+-- Archived schedules are kept for reference but hidden from the schedule
+-- list and never active
+ALTER TABLE observation_schedules ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
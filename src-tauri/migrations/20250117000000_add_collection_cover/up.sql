@@ -0,0 +1,3 @@
+-- Image shown as the collection's cover; cleared when the image leaves the
+-- collection
+ALTER TABLE collections ADD COLUMN cover_image_id TEXT;
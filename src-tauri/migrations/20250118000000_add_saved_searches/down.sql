@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_saved_searches_insert;
+DROP TRIGGER IF EXISTS sync_saved_searches_update;
+DROP TRIGGER IF EXISTS sync_saved_searches_delete;
+DROP TABLE IF EXISTS saved_searches;
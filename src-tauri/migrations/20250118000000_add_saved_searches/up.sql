@@ -0,0 +1,45 @@
+-- Saved searches: named search criteria over images or todos, run on demand
+CREATE TABLE saved_searches (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    -- What the search runs over: 'images' or 'todos'
+    entity TEXT NOT NULL,
+    -- JSON object of SearchCriteria (camelCase keys)
+    criteria TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_saved_searches_user_id ON saved_searches(user_id);
+
+-- Track changes for sync
+CREATE TRIGGER sync_saved_searches_insert AFTER INSERT ON saved_searches
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'saved_searches' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'saved_searches', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'saved_searches' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_saved_searches_update AFTER UPDATE ON saved_searches
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'saved_searches' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'saved_searches', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'saved_searches' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_saved_searches_delete AFTER DELETE ON saved_searches
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'saved_searches' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'saved_searches', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'saved_searches' AND row_id = OLD.id);
+END;
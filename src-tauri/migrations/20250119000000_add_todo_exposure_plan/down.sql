@@ -0,0 +1 @@
+ALTER TABLE astronomy_todos DROP COLUMN exposure_plan;
@@ -0,0 +1,3 @@
+-- Exposure plan (sub length, sub count and the inputs they came from) as
+-- JSON, see commands::exposure_plan
+ALTER TABLE astronomy_todos ADD COLUMN exposure_plan TEXT;
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_locations_insert;
+DROP TRIGGER IF EXISTS sync_locations_update;
+DROP TRIGGER IF EXISTS sync_locations_delete;
+DROP TABLE IF EXISTS locations;
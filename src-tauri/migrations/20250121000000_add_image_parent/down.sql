@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_images_parent_image_id;
+ALTER TABLE images DROP COLUMN parent_image_id;
@@ -0,0 +1,5 @@
+-- Stacked image a Light frame went into. Subs linked to a stack are left out
+-- of library listings; cleared when the stack is deleted.
+ALTER TABLE images ADD COLUMN parent_image_id TEXT;
+
+CREATE INDEX idx_images_parent_image_id ON images(parent_image_id);
@@ -0,0 +1,6 @@
+-- Import triage: frames that look like failed captures or test shots are
+-- imported as "pending" and left out of library listings until reviewed.
+-- Discarded frames keep their row so rescans don't import them again.
+ALTER TABLE images ADD COLUMN triage TEXT;
+
+CREATE INDEX idx_images_triage ON images(triage);
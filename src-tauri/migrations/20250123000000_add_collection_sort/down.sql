@@ -0,0 +1,3 @@
+ALTER TABLE images DROP COLUMN rating;
+ALTER TABLE collections DROP COLUMN image_sort;
+ALTER TABLE collection_images DROP COLUMN sort_order;
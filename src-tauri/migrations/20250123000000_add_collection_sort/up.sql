@@ -0,0 +1,10 @@
+-- Manual position of an image within a collection; images without one
+-- (added since the last reorder) follow the placed ones
+ALTER TABLE collection_images ADD COLUMN sort_order INTEGER;
+
+-- Default image order for the collection: "capture_time", "name", "rating"
+-- or "manual"; newest added first when NULL
+ALTER TABLE collections ADD COLUMN image_sort TEXT;
+
+-- Star rating, 1-5; NULL when unrated
+ALTER TABLE images ADD COLUMN rating INTEGER;
@@ -0,0 +1 @@
+DROP INDEX idx_collections_auto_imported_name;
@@ -0,0 +1,40 @@
+-- Scans of overlapping directories running at the same time could each
+-- create the same session collection. Merge auto-imported collections that
+-- share a name into the oldest, then keep their names unique per user.
+
+CREATE TEMP TABLE duplicate_auto_collections AS
+SELECT
+    c.id AS id,
+    (
+        SELECT k.id FROM collections k
+        WHERE k.user_id = c.user_id
+          AND k.name = c.name
+          AND CASE WHEN json_valid(k.metadata) THEN json_extract(k.metadata, '$.auto_imported') END = 1
+        ORDER BY k.created_at, k.rowid
+        LIMIT 1
+    ) AS keep_id
+FROM collections c
+WHERE CASE WHEN json_valid(c.metadata) THEN json_extract(c.metadata, '$.auto_imported') END = 1;
+
+DELETE FROM duplicate_auto_collections WHERE id = keep_id;
+
+INSERT OR IGNORE INTO collection_images (id, collection_id, image_id, created_at)
+SELECT lower(hex(randomblob(16))), d.keep_id, ci.image_id, ci.created_at
+FROM collection_images ci
+JOIN duplicate_auto_collections d ON d.id = ci.collection_id;
+
+UPDATE images
+SET collection_id = (
+    SELECT keep_id FROM duplicate_auto_collections WHERE id = images.collection_id
+)
+WHERE collection_id IN (SELECT id FROM duplicate_auto_collections);
+
+DELETE FROM collection_images
+WHERE collection_id IN (SELECT id FROM duplicate_auto_collections);
+DELETE FROM collections
+WHERE id IN (SELECT id FROM duplicate_auto_collections);
+
+DROP TABLE duplicate_auto_collections;
+
+CREATE UNIQUE INDEX idx_collections_auto_imported_name ON collections(user_id, name)
+WHERE CASE WHEN json_valid(metadata) THEN json_extract(metadata, '$.auto_imported') END = 1;
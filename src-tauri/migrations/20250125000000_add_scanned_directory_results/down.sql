@@ -0,0 +1,2 @@
+ALTER TABLE scanned_directories DROP COLUMN last_failed;
+ALTER TABLE scanned_directories DROP COLUMN last_imported;
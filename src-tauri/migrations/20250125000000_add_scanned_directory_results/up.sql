@@ -0,0 +1,4 @@
+-- What the last scan of each directory did. image_count now holds the number
+-- of images found in the directory by that scan.
+ALTER TABLE scanned_directories ADD COLUMN last_imported INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE scanned_directories ADD COLUMN last_failed INTEGER NOT NULL DEFAULT 0;
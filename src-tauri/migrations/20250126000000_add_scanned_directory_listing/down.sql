@@ -0,0 +1 @@
+ALTER TABLE scanned_directories DROP COLUMN listing_hash;
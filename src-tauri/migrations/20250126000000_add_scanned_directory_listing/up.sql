@@ -0,0 +1,4 @@
+-- Fingerprint of the image files found in each directory (names, sizes and
+-- modification times), so additions are noticed even when the directory's
+-- own modification time doesn't change. NULL until the next scan.
+ALTER TABLE scanned_directories ADD COLUMN listing_hash TEXT;
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS sync_pinned_targets_insert;
+DROP TRIGGER IF EXISTS sync_pinned_targets_update;
+DROP TRIGGER IF EXISTS sync_pinned_targets_delete;
+DROP TABLE IF EXISTS pinned_targets;
+ALTER TABLE saved_searches DROP COLUMN pinned;
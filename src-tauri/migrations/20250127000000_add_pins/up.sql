@@ -0,0 +1,44 @@
+-- Pinned items for the Home screen. Saved searches carry their own flag;
+-- targets are named by images rather than stored, so pins keep the
+-- (canonical) target name.
+ALTER TABLE saved_searches ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE pinned_targets (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pinned_targets_user_name ON pinned_targets(user_id, name);
+
+-- Track changes for sync
+CREATE TRIGGER sync_pinned_targets_insert AFTER INSERT ON pinned_targets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'pinned_targets' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'pinned_targets', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'pinned_targets' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_pinned_targets_update AFTER UPDATE ON pinned_targets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'pinned_targets' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'pinned_targets', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'pinned_targets' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_pinned_targets_delete AFTER DELETE ON pinned_targets
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'pinned_targets' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'pinned_targets', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'pinned_targets' AND row_id = OLD.id);
+END;
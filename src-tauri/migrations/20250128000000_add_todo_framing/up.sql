@@ -0,0 +1,3 @@
+-- Chosen framing (center and camera rotation) as JSON, see
+-- commands::framing
+ALTER TABLE astronomy_todos ADD COLUMN framing TEXT;
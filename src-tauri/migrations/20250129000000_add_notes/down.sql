@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_notes_insert;
+DROP TRIGGER IF EXISTS sync_notes_update;
+DROP TRIGGER IF EXISTS sync_notes_delete;
+DROP TABLE IF EXISTS notes;
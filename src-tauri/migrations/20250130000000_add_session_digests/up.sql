@@ -0,0 +1,18 @@
+-- Nightly go/no-go digest: tonight's forecast, moon and open schedules
+-- weighed up each afternoon. One row per night (the local date the evening
+-- starts on); re-evaluating a night replaces its digest. Forecast-derived,
+-- so machine-local and not synced.
+CREATE TABLE session_digests (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    -- YYYY-MM-DD
+    night TEXT NOT NULL,
+    -- 'go', 'marginal' or 'no-go'
+    verdict TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    -- JSON NightDigest (camelCase keys)
+    details TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, night)
+);
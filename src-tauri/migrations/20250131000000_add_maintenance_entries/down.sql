@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_maintenance_entries_insert;
+DROP TRIGGER IF EXISTS sync_maintenance_entries_update;
+DROP TRIGGER IF EXISTS sync_maintenance_entries_delete;
+DROP TABLE IF EXISTS maintenance_entries;
@@ -0,0 +1,49 @@
+-- Equipment maintenance log: collimation, desiccant regeneration, firmware
+-- updates and the like. The latest entry for an item's task, plus its
+-- interval, says when the task is next due.
+CREATE TABLE maintenance_entries (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    equipment_id TEXT NOT NULL REFERENCES equipment(id),
+    task TEXT NOT NULL,
+    -- YYYY-MM-DD
+    performed_on TEXT NOT NULL,
+    -- Days until the task is due again; NULL for one-off work
+    interval_days INTEGER,
+    notes TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_maintenance_entries_equipment ON maintenance_entries(equipment_id, performed_on);
+
+-- Track changes for sync
+CREATE TRIGGER sync_maintenance_entries_insert AFTER INSERT ON maintenance_entries
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'maintenance_entries' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'maintenance_entries', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'maintenance_entries' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_maintenance_entries_update AFTER UPDATE ON maintenance_entries
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'maintenance_entries' AND row_id = NEW.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'maintenance_entries', NEW.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'maintenance_entries' AND row_id = NEW.id);
+END;
+
+CREATE TRIGGER sync_maintenance_entries_delete AFTER DELETE ON maintenance_entries
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'maintenance_entries' AND row_id = OLD.id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'maintenance_entries', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'maintenance_entries' AND row_id = OLD.id);
+END;
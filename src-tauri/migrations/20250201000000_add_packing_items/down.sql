@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_packing_items_insert;
+DROP TRIGGER IF EXISTS sync_packing_items_update;
+DROP TRIGGER IF EXISTS sync_packing_items_delete;
+DROP TABLE IF EXISTS packing_items;
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_images_capture_site_id;
+ALTER TABLE images DROP COLUMN capture_longitude;
+ALTER TABLE images DROP COLUMN capture_latitude;
+ALTER TABLE images DROP COLUMN capture_site_id;
@@ -0,0 +1,9 @@
+-- Where on Earth an image was taken: a saved location when one matches, and
+-- the site's coordinates either way, so deleting the location keeps them.
+-- Set at import from the SITELAT/SITELONG headers, the night's active
+-- schedule or the default location.
+ALTER TABLE images ADD COLUMN capture_site_id TEXT;
+ALTER TABLE images ADD COLUMN capture_latitude REAL;
+ALTER TABLE images ADD COLUMN capture_longitude REAL;
+
+CREATE INDEX idx_images_capture_site_id ON images(capture_site_id);
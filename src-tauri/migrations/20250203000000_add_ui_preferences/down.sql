@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS sync_ui_preferences_insert;
+DROP TRIGGER IF EXISTS sync_ui_preferences_update;
+DROP TRIGGER IF EXISTS sync_ui_preferences_delete;
+DROP TABLE IF EXISTS ui_preferences;
@@ -0,0 +1,40 @@
+-- UI preferences (theme, layout, default views, column choices) as one JSON
+-- object per user, kept with the library so they sync between machines
+-- instead of living only in the webview's localStorage.
+CREATE TABLE ui_preferences (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(id),
+    preferences TEXT NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Track changes for sync
+CREATE TRIGGER sync_ui_preferences_insert AFTER INSERT ON ui_preferences
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'ui_preferences' AND row_id = NEW.user_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'ui_preferences', NEW.user_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'ui_preferences' AND row_id = NEW.user_id);
+END;
+
+CREATE TRIGGER sync_ui_preferences_update AFTER UPDATE ON ui_preferences
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 0, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'ui_preferences' AND row_id = NEW.user_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'ui_preferences', NEW.user_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'ui_preferences' AND row_id = NEW.user_id);
+END;
+
+CREATE TRIGGER sync_ui_preferences_delete AFTER DELETE ON ui_preferences
+BEGIN
+    UPDATE sync_state
+    SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), deleted = 1, origin = NULL, version = (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE table_name = 'ui_preferences' AND row_id = OLD.user_id;
+    INSERT INTO sync_state (table_name, row_id, updated_at, deleted, origin, version)
+    SELECT 'ui_preferences', OLD.user_id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 1, NULL, (SELECT COALESCE(MAX(version), 0) + 1 FROM sync_state)
+    WHERE NOT EXISTS (SELECT 1 FROM sync_state WHERE table_name = 'ui_preferences' AND row_id = OLD.user_id);
+END;
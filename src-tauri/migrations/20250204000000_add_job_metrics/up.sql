@@ -0,0 +1,20 @@
+-- Timing breakdown of long-running jobs (bulk scans, auto-import cycles),
+-- one row per phase, for diagnosing slow imports. Machine-specific and
+-- never sent anywhere, so not synced.
+CREATE TABLE job_metrics (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id),
+    -- Id the job's log lines are tagged with, e.g. 'scan-1a2b3c4d'
+    job_id TEXT NOT NULL,
+    -- 'scan', 'fits_parse', 'thumbnail', 'db_insert' or 'total'
+    phase TEXT NOT NULL,
+    -- Times the phase ran (files parsed, thumbnails rendered, ...)
+    count INTEGER NOT NULL,
+    -- Summed over parallel workers, so it can exceed the job's wall time
+    total_ms BIGINT NOT NULL,
+    max_ms BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(job_id, phase)
+);
+
+CREATE INDEX idx_job_metrics_created ON job_metrics(user_id, created_at);
@@ -0,0 +1,242 @@
+//! Headless Astra: import, back up, export, plate solve and report on the
+//! library without launching the GUI.
+//!
+//! Works on the desktop app's data directory, so the GUI app should not be
+//! running while this executes (SQLite locking). Results are printed as JSON
+//! on stdout; progress goes to stderr.
+//!
+//! # Usage
+//!
+//!   cargo run --release --features cli --bin astra-cli -- scan /mnt/seestar/MyWorks
+//!   cargo run --release --features cli --bin astra-cli -- --data-dir /path/to/data stats
+//!
+//! Run with `--help` for every command and option.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use astra_lib::cli::{self, Library, SolveOptions};
+
+fn print_help() {
+    println!(
+        "astra-cli — headless operations on the Astra library\n\n\
+         USAGE:\n\
+         \x20 astra-cli [--data-dir <path>] <command> [options]\n\n\
+         COMMANDS:\n\
+         \x20 scan <directory>      Import images, as the app's bulk scan does\n\
+         \x20     --tags <tags>          Tags for every imported image (comma-separated)\n\
+         \x20     --stacked-only         Skip raw subframes\n\
+         \x20     --max-files <n>        Stop after n images\n\
+         \x20     --collection <id>      Also add imported images to this collection\n\
+         \x20 backup                Write a backup bundle to the backups/ directory\n\
+         \x20     --compression <c>      none (default), gzip or zstd\n\
+         \x20     --passphrase <text>    Encrypt the backup\n\
+         \x20 export <entity>       Export images, todos or sessions\n\
+         \x20     --format <f>           csv (default) or json\n\
+         \x20     --columns <a,b,...>    Columns to include\n\
+         \x20     --collection <id>      Images: only this collection\n\
+         \x20     --tag <tag>            Only records with this tag\n\
+         \x20     --output <path>        Write to a file instead of stdout\n\
+         \x20 solve <image-id>...   Plate solve images and record the results\n\
+         \x20     --solver <s>           tetra3 (default), astap or local\n\
+         \x20     --tetra3-db <path>     tetra3 star pattern database\n\
+         \x20     --astap <path>         ASTAP executable\n\
+         \x20     --solve-field <path>   solve-field executable\n\
+         \x20     --timeout <seconds>\n\
+         \x20 stats                 Library totals\n\n\
+         OPTIONS:\n\
+         \x20 --data-dir <path>   Override the app data dir (default: app_data_dir for com.erewhon.astra)\n\
+         \x20 -h, --help          Show this help"
+    );
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(2);
+}
+
+/// Command-line arguments, consumed front to back
+struct Args(std::vec::IntoIter<String>);
+
+impl Args {
+    fn value(&mut self, flag: &str) -> String {
+        self.0
+            .next()
+            .unwrap_or_else(|| fail(&format!("{flag} requires a value")))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, flag: &str) -> T {
+        let value = self.value(flag);
+        value
+            .parse()
+            .unwrap_or_else(|_| fail(&format!("{flag}: invalid number '{value}'")))
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(e) => fail(&format!("Failed to serialize result: {e}")),
+    }
+}
+
+fn scan(library: &Library, mut args: Args) -> Result<(), String> {
+    let mut directory = None;
+    let mut input = cli::scan_input(String::new());
+    while let Some(arg) = args.0.next() {
+        match arg.as_str() {
+            "--tags" => input.tags = Some(args.value("--tags")),
+            "--stacked-only" => input.stacked_only = true,
+            "--max-files" => input.max_files = Some(args.number("--max-files")),
+            "--collection" => input.add_to_collection = Some(args.value("--collection")),
+            other if other.starts_with('-') => fail(&format!("unknown scan option: {other}")),
+            _ => directory = Some(arg),
+        }
+    }
+    input.directory = directory.unwrap_or_else(|| fail("scan needs a directory"));
+
+    let result = library.scan(input, |progress| {
+        eprint!(
+            "\r  [{:>3}%] {:<70}",
+            progress.percent,
+            progress.current_file.chars().take(70).collect::<String>()
+        );
+        let _ = std::io::stderr().flush();
+    })?;
+    eprintln!();
+    print_json(&result);
+    Ok(())
+}
+
+fn backup(library: &Library, mut args: Args) -> Result<(), String> {
+    let mut compression = None;
+    let mut passphrase = None;
+    while let Some(arg) = args.0.next() {
+        match arg.as_str() {
+            "--compression" => compression = Some(args.value("--compression")),
+            "--passphrase" => passphrase = Some(args.value("--passphrase")),
+            other => fail(&format!("unknown backup option: {other}")),
+        }
+    }
+    let result = library.backup(compression.as_deref(), passphrase.as_deref())?;
+    if !result.success {
+        return Err(result.message);
+    }
+    print_json(&result);
+    Ok(())
+}
+
+fn export(library: &Library, mut args: Args) -> Result<(), String> {
+    let entity = args
+        .0
+        .next()
+        .unwrap_or_else(|| fail("export needs an entity"));
+    let mut input = cli::export_input(entity, "csv".to_string());
+    let mut filter = input.filter.take().unwrap_or_default();
+    while let Some(arg) = args.0.next() {
+        match arg.as_str() {
+            "--format" => input.format = args.value("--format"),
+            "--columns" => {
+                let columns = args.value("--columns");
+                input.columns = Some(columns.split(',').map(|c| c.trim().to_string()).collect());
+            }
+            "--collection" => filter.collection_id = Some(args.value("--collection")),
+            "--tag" => filter.tag = Some(args.value("--tag")),
+            "--output" => input.output_path = Some(args.value("--output")),
+            other => fail(&format!("unknown export option: {other}")),
+        }
+    }
+    input.filter = Some(filter);
+
+    let result = library.export(input)?;
+    match &result.output_path {
+        Some(path) => eprintln!("Exported {} rows to {path}", result.rows),
+        None => println!("{}", result.content),
+    }
+    Ok(())
+}
+
+fn solve(library: &Library, mut args: Args) -> Result<(), String> {
+    let mut ids = Vec::new();
+    let mut options = SolveOptions {
+        solver: "tetra3".to_string(),
+        ..Default::default()
+    };
+    while let Some(arg) = args.0.next() {
+        match arg.as_str() {
+            "--solver" => options.solver = args.value("--solver"),
+            "--tetra3-db" => options.tetra3_db_path = Some(args.value("--tetra3-db")),
+            "--astap" => options.astap_path = Some(args.value("--astap")),
+            "--solve-field" => options.solve_field_path = Some(args.value("--solve-field")),
+            "--timeout" => options.timeout = Some(args.number("--timeout")),
+            other if other.starts_with('-') => fail(&format!("unknown solve option: {other}")),
+            _ => ids.push(arg),
+        }
+    }
+    if ids.is_empty() {
+        fail("solve needs at least one image ID");
+    }
+
+    let mut failed = 0;
+    let mut results = Vec::new();
+    for (id, result) in library.plate_solve(&ids, &options)? {
+        match result {
+            Ok(response) => {
+                if !response.solve_result.success {
+                    failed += 1;
+                }
+                results.push(serde_json::json!({ "id": id, "result": response }));
+            }
+            Err(e) => {
+                failed += 1;
+                results.push(serde_json::json!({ "id": id, "error": e }));
+            }
+        }
+    }
+    print_json(&results);
+    if failed > 0 {
+        return Err(format!("{failed} of {} images failed to solve", ids.len()));
+    }
+    Ok(())
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let mut args = Args(std::env::args().skip(1).collect::<Vec<_>>().into_iter());
+    let mut data_dir: Option<PathBuf> = None;
+    let command = loop {
+        match args.0.next().as_deref() {
+            Some("--data-dir") => data_dir = Some(PathBuf::from(args.value("--data-dir"))),
+            Some("-h" | "--help") | None => {
+                print_help();
+                return;
+            }
+            Some(command) => break command.to_string(),
+        }
+    };
+
+    let library = match Library::open(data_dir) {
+        Ok(library) => library,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let result = match command.as_str() {
+        "scan" => scan(&library, args),
+        "backup" => backup(&library, args),
+        "export" => export(&library, args),
+        "solve" => solve(&library, args),
+        "stats" => library.stats().map(|stats| print_json(&stats)),
+        other => {
+            eprintln!("unknown command: {other}");
+            print_help();
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
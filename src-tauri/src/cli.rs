@@ -0,0 +1,178 @@
+//! Headless operations behind the `astra-cli` binary.
+//!
+//! Each runs against the desktop app's database and settings through the same
+//! code as the matching command, so imports can be scripted on the machine
+//! attached to the telescope. The GUI app should not be running at the same
+//! time (SQLite locking).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::commands::api_server::{library_stats, LibraryStats};
+use crate::commands::backup::{create_backup_in, BackupResult};
+use crate::commands::export::{export_records, ExportDataInput, ExportDataResult, ExportFilter};
+use crate::commands::network::network_settings_in;
+use crate::commands::plate_solve::{solve_and_record, PlateSolveInput, PlateSolveResponse};
+use crate::commands::scan::{
+    run_bulk_scan, BulkScanInput, BulkScanResult, ScanProgress, ScanSettings,
+};
+use crate::db::{self, SharedDbPool};
+use crate::network::Network;
+use crate::preview_cache::PreviewCache;
+use crate::state::AppState;
+
+/// Solvers that run without the Python bridge
+const NATIVE_SOLVERS: &[&str] = &["tetra3", "astap", "local"];
+
+/// How to plate solve from the command line
+#[derive(Debug, Default)]
+pub struct SolveOptions {
+    /// "tetra3", "astap" or "local" (solve-field)
+    pub solver: String,
+    pub tetra3_db_path: Option<String>,
+    pub astap_path: Option<String>,
+    pub solve_field_path: Option<String>,
+    /// Timeout in seconds
+    pub timeout: Option<i32>,
+}
+
+/// The library in an app data directory, opened for headless use
+pub struct Library {
+    data_dir: PathBuf,
+    state: AppState,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Library {
+    /// Open the library in `data_dir` (default: the desktop app's), running
+    /// any pending migrations
+    pub fn open(data_dir: Option<PathBuf>) -> Result<Self, String> {
+        let data_dir = data_dir.unwrap_or_else(crate::default_app_data_dir);
+        std::fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", data_dir.display(), e))?;
+        let pool = db::init_database(&data_dir.join("astra.db"))
+            .map_err(|e| format!("Failed to initialize database: {}", e))?;
+
+        // Only the database is used headless; no HoardFS, and previews go
+        // to a scratch cache. Offline mode and rate limits still apply.
+        let state = AppState::new(
+            SharedDbPool::new(pool),
+            None,
+            Network::new(network_settings_in(&data_dir), None),
+            PreviewCache::new(std::env::temp_dir().join("astra-cli-previews"), 0),
+        );
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("tokio runtime: {}", e))?;
+        Ok(Self {
+            data_dir,
+            state,
+            runtime,
+        })
+    }
+
+    /// Import the images under `directory`, as the app's bulk scan does
+    pub fn scan(
+        &self,
+        input: BulkScanInput,
+        progress: impl Fn(&ScanProgress) + Send + Sync,
+    ) -> Result<BulkScanResult, String> {
+        let settings = ScanSettings::load(&self.data_dir);
+        self.runtime.block_on(run_bulk_scan(
+            self.state.db.clone(),
+            self.state.user_id.clone(),
+            input,
+            settings,
+            progress,
+        ))
+    }
+
+    /// Write a backup bundle to the library's backups/ directory
+    pub fn backup(
+        &self,
+        compression: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> Result<BackupResult, String> {
+        create_backup_in(&self.data_dir, compression, passphrase)
+    }
+
+    /// Export images, todos or sessions as CSV or JSON
+    pub fn export(&self, input: ExportDataInput) -> Result<ExportDataResult, String> {
+        let mut conn = self.state.db.get()?;
+        export_records(&mut conn, &self.state.user_id, input)
+    }
+
+    /// Plate solve images one after another, recording each result
+    pub fn plate_solve(
+        &self,
+        image_ids: &[String],
+        options: &SolveOptions,
+    ) -> Result<Vec<(String, Result<PlateSolveResponse, String>)>, String> {
+        if !NATIVE_SOLVERS.contains(&options.solver.as_str()) {
+            return Err(format!(
+                "Solver '{}' isn't available headless (expected {})",
+                options.solver,
+                NATIVE_SOLVERS.join(", ")
+            ));
+        }
+
+        let mut scale_cache = HashMap::new();
+        let mut results = Vec::with_capacity(image_ids.len());
+        for id in image_ids {
+            let input = PlateSolveInput {
+                id: id.clone(),
+                solver: options.solver.clone(),
+                api_key: None,
+                api_url: None,
+                scale_lower: None,
+                scale_upper: None,
+                timeout: options.timeout,
+                // Catalog queries go through Python
+                query_catalogs: Some(false),
+                catalogs: None,
+                star_mag_limit: None,
+                hint_ra: None,
+                hint_dec: None,
+                hint_radius: None,
+                tetra3_db_path: options.tetra3_db_path.clone(),
+                fov_estimate: None,
+                astap_path: options.astap_path.clone(),
+                solve_field_path: options.solve_field_path.clone(),
+            };
+            let result =
+                self.runtime
+                    .block_on(solve_and_record(&self.state, input, &mut scale_cache));
+            results.push((id.clone(), result));
+        }
+        Ok(results)
+    }
+
+    /// Library totals: images, sessions, targets and integration time
+    pub fn stats(&self) -> Result<LibraryStats, String> {
+        let mut conn = self.state.db.get()?;
+        library_stats(&mut conn, &self.state.user_id).map_err(|e| e.to_string())
+    }
+}
+
+/// Scan input for `directory` with the app's defaults
+pub fn scan_input(directory: String) -> BulkScanInput {
+    BulkScanInput {
+        directory,
+        tags: None,
+        stacked_only: false,
+        max_files: None,
+        add_to_collection: None,
+    }
+}
+
+/// Export input for `entity` ("images", "todos" or "sessions")
+pub fn export_input(entity: String, format: String) -> ExportDataInput {
+    ExportDataInput {
+        entity,
+        format,
+        columns: None,
+        filter: Some(ExportFilter::default()),
+        output_path: None,
+    }
+}
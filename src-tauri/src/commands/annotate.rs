@@ -0,0 +1,243 @@
+//! Annotated previews of plate-solved images
+//!
+//! Renders the solved catalog objects, an RA/Dec grid and constellation
+//! boundaries over an image's preview. Results are kept as PNGs in the
+//! preview cache, keyed by the preview's contents, the solution and the
+//! overlay options.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tauri::State;
+
+use crate::db::repository;
+use crate::preview_cache::PreviewCache;
+use crate::python::annotate;
+use crate::state::AppState;
+
+/// Overlays to draw; all are on by default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnnotatedPreviewOptions {
+    /// RA/Dec grid
+    pub grid: bool,
+    /// Constellation boundaries and names
+    pub constellations: bool,
+    /// Catalog object circles and labels
+    pub labels: bool,
+}
+
+impl Default for AnnotatedPreviewOptions {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            constellations: true,
+            labels: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotatedPreview {
+    /// Path of the annotated PNG
+    pub path: String,
+    /// Whether an existing render was reused
+    pub cached: bool,
+}
+
+/// Cache file name for an image's annotated preview. The key covers
+/// everything drawn, so re-solving, changing the preview or changing
+/// options renders afresh.
+fn cache_file_name(
+    image_id: &str,
+    preview_hash: &str,
+    wcs: &str,
+    annotations: Option<&str>,
+    options: &AnnotatedPreviewOptions,
+) -> String {
+    let mut hasher = Sha256::new();
+    for part in [preview_hash, wcs, annotations.unwrap_or("")] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update([
+        options.grid as u8,
+        options.constellations as u8,
+        options.labels as u8,
+    ]);
+    let digest = hex::encode(hasher.finalize());
+    format!("{}-{}.png", image_id, &digest[..16])
+}
+
+/// Remove an image's cached annotated previews, except `keep`
+fn remove_cached_previews(dir: &Path, image_id: &str, keep: Option<&Path>) {
+    let prefix = format!("{}-", image_id);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(&prefix) && name.ends_with(".png") && Some(path.as_path()) != keep {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove annotated preview {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+/// Remove the cached annotated previews of a deleted image
+pub(crate) fn remove_annotated_previews(cache: &PreviewCache, image_id: &str) {
+    remove_cached_previews(cache.dir(), image_id, None);
+}
+
+/// Whether a file can be drawn on (FITS files need a rendered preview first)
+fn is_raster_preview(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    matches!(
+        extension.as_deref(),
+        Some("jpg" | "jpeg" | "png" | "tif" | "tiff" | "webp")
+    )
+}
+
+/// Get a preview of a plate-solved image with catalog objects, an RA/Dec
+/// grid and constellation boundaries drawn over it, rendering it if needed
+#[tauri::command]
+pub async fn get_annotated_preview(
+    state: State<'_, AppState>,
+    image_id: String,
+    options: Option<AnnotatedPreviewOptions>,
+) -> Result<AnnotatedPreview, String> {
+    let options = options.unwrap_or_default();
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, &image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", image_id))?;
+    drop(conn);
+
+    let wcs = image
+        .wcs
+        .ok_or_else(|| "Image has no plate solution".to_string())?;
+    // The solution is for the file that was solved, i.e. the image URL
+    let preview = image
+        .url
+        .filter(|url| is_raster_preview(url) && Path::new(url).exists())
+        .ok_or_else(|| "Image has no preview to annotate".to_string())?;
+
+    let cache = state.preview_cache.clone();
+    let preview_hash = cache.file_hash(Path::new(&preview))?;
+    let file_name = cache_file_name(
+        &image.id,
+        &preview_hash,
+        &wcs,
+        image.annotations.as_deref(),
+        &options,
+    );
+    if let Some(cached) = cache.get(&file_name) {
+        return Ok(AnnotatedPreview {
+            path: cached.to_string_lossy().to_string(),
+            cached: true,
+        });
+    }
+
+    let output = cache.path(&file_name);
+    std::fs::create_dir_all(cache.dir())
+        .map_err(|e| format!("Failed to create preview cache: {}", e))?;
+    let wcs: serde_json::Value =
+        serde_json::from_str(&wcs).map_err(|e| format!("Invalid WCS solution: {}", e))?;
+    let objects: serde_json::Value = image
+        .annotations
+        .as_deref()
+        .and_then(|a| serde_json::from_str(a).ok())
+        .unwrap_or_else(|| serde_json::json!([]));
+
+    let output_path = output.to_string_lossy().to_string();
+    let render_path = output_path.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        annotate::render_annotated_preview(
+            &preview,
+            &render_path,
+            &wcs,
+            &objects,
+            options.grid,
+            options.constellations,
+            options.labels,
+        )
+    })
+    .await
+    .map_err(|e| format!("Annotated preview failed: {}", e))??;
+
+    if !result.success {
+        return Err(result
+            .error
+            .unwrap_or_else(|| "Annotated preview failed".to_string()));
+    }
+    // Older renders for this image are stale now
+    remove_cached_previews(cache.dir(), &image.id, Some(&output));
+    cache.added(&file_name);
+
+    Ok(AnnotatedPreview {
+        path: output_path,
+        cached: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_changes_with_solution_and_options() {
+        let options = AnnotatedPreviewOptions::default();
+        let name = cache_file_name("img-1", "/p.jpg", "{}", None, &options);
+        assert!(name.starts_with("img-1-") && name.ends_with(".png"));
+        assert_eq!(
+            name,
+            cache_file_name("img-1", "/p.jpg", "{}", None, &options)
+        );
+        assert_ne!(
+            name,
+            cache_file_name("img-1", "/p.jpg", "{\"a\":1}", None, &options)
+        );
+        assert_ne!(
+            name,
+            cache_file_name("img-1", "/p.jpg", "{}", Some("[]"), &options)
+        );
+        let no_grid = AnnotatedPreviewOptions {
+            grid: false,
+            ..Default::default()
+        };
+        assert_ne!(
+            name,
+            cache_file_name("img-1", "/p.jpg", "{}", None, &no_grid)
+        );
+    }
+
+    #[test]
+    fn stale_previews_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "img-1-aaaa.png",
+            "img-1-bbbb.png",
+            "img-10-cccc.png",
+            "img-2-dddd.png",
+        ] {
+            std::fs::write(dir.path().join(name), b"png").unwrap();
+        }
+        let keep = dir.path().join("img-1-bbbb.png");
+        remove_cached_previews(dir.path(), "img-1", Some(&keep));
+
+        let mut left: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            ["img-1-bbbb.png", "img-10-cccc.png", "img-2-dddd.png"]
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! Optional local HTTP API for browsing the library without the desktop UI
+//!
+//! The server is off by default. When enabled it serves read-only JSON
+//! endpoints (plus thumbnails) and requires the configured token on every
+//! request except `/api/health`, either as `Authorization: Bearer <token>`
+//! or as a `?token=` query parameter (handy for `<img>` tags).
+//!
+//! With event streaming also turned on, `/api/events` is a websocket that
+//! pushes Astra's activity (import progress, new images, finished jobs) as
+//! JSON messages; `?events=image-added,scan-finished` limits it to those
+//! event names.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, Query, Request, State as AxumState};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use diesel::sqlite::SqliteConnection;
+use diesel::QueryResult;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::{broadcast, watch};
+
+use crate::db::models::{Collection, Image};
+use crate::db::{repository, SharedDbPool};
+use crate::events;
+use crate::state::AppState;
+
+use super::settings::{load_settings, save_settings, API_SERVER};
+
+const DEFAULT_PORT: u16 = 8765;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerConfig {
+    /// Start the server when Astra launches
+    pub enabled: bool,
+    pub port: u16,
+    /// Listen on all interfaces instead of localhost only
+    pub allow_lan: bool,
+    /// Token clients must present; generated when empty
+    pub token: String,
+    /// Stream activity events over the `/api/events` websocket
+    #[serde(default)]
+    pub events_enabled: bool,
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            allow_lan: false,
+            token: String::new(),
+            events_enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerStatus {
+    pub running: bool,
+    pub address: Option<String>,
+    pub config: ApiServerConfig,
+}
+
+/// A running server: its bound address and shutdown signal
+pub struct ApiServerHandle {
+    pub address: SocketAddr,
+    shutdown: watch::Sender<bool>,
+}
+
+impl ApiServerHandle {
+    pub fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    db: SharedDbPool,
+    user_id: String,
+    token: String,
+    events_enabled: bool,
+    /// Ends open event streams when the server stops
+    shutdown: watch::Receiver<bool>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// Image as returned by the API: the embedded thumbnail is replaced with a
+/// link to the thumbnail endpoint to keep listings small
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiImage {
+    #[serde(flatten)]
+    image: Image,
+    thumbnail_url: String,
+}
+
+impl From<Image> for ApiImage {
+    fn from(mut image: Image) -> Self {
+        image.thumbnail = None;
+        let thumbnail_url = format!("/api/images/{}/thumbnail", image.id);
+        Self {
+            image,
+            thumbnail_url,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiCollection {
+    #[serde(flatten)]
+    collection: Collection,
+    image_count: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStats {
+    image_count: i64,
+    stacked_image_count: i64,
+    collection_count: usize,
+    session_count: usize,
+    target_count: usize,
+    todo_count: usize,
+    total_exposure_seconds: f64,
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Comma-separated event names; all events when absent
+    events: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    collection_id: Option<String>,
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+/// Constant-time comparison so the token can't be guessed byte by byte
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn require_token(
+    AxumState(server): AxumState<ServerState>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let given = bearer.or(query.get("token").map(String::as_str));
+
+    match given {
+        Some(token) if token_matches(token, &server.token) => next.run(request).await,
+        _ => ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid token".to_string(),
+        )
+        .into_response(),
+    }
+}
+
+/// Run a database query on the blocking pool
+async fn query<T, F>(server: &ServerState, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut SqliteConnection, &str) -> QueryResult<T> + Send + 'static,
+{
+    let db = server.db.clone();
+    let user_id = server.user_id.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db
+            .get()
+            .map_err(|e| ApiError(StatusCode::SERVICE_UNAVAILABLE, e))?;
+        f(&mut conn, &user_id)
+            .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+fn not_found(what: &str, id: &str) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, format!("{} not found: {}", what, id))
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))
+}
+
+/// Library totals, as served at /stats
+pub(crate) fn library_stats(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<LibraryStats> {
+    let collections = repository::get_collections(conn, user_id)?;
+    let exposure = repository::get_filter_exposure_by_target(conn, user_id)?;
+    Ok(LibraryStats {
+        image_count: repository::count_images_by_user(conn, user_id)?,
+        stacked_image_count: repository::count_stacked_images_by_user(conn, user_id)?,
+        session_count: collections
+            .iter()
+            .filter(|c| c.template.as_deref() == Some("astrolog"))
+            .count(),
+        collection_count: collections.len(),
+        target_count: repository::get_targets_with_counts(conn, user_id)?.len(),
+        todo_count: repository::get_todos(conn, user_id)?.len(),
+        total_exposure_seconds: exposure.iter().map(|e| e.total_exposure_seconds).sum(),
+    })
+}
+
+async fn stats(AxumState(server): AxumState<ServerState>) -> ApiResult<LibraryStats> {
+    let stats = query(&server, library_stats).await?;
+    Ok(Json(stats))
+}
+
+async fn list_collections(
+    AxumState(server): AxumState<ServerState>,
+) -> ApiResult<Vec<ApiCollection>> {
+    let collections = query(&server, |conn, user_id| {
+        repository::get_collections(conn, user_id)?
+            .into_iter()
+            .map(|collection| {
+                let image_count = repository::get_collection_image_count(conn, &collection.id)?;
+                Ok(ApiCollection {
+                    collection,
+                    image_count,
+                })
+            })
+            .collect()
+    })
+    .await?;
+    Ok(Json(collections))
+}
+
+async fn get_collection(
+    AxumState(server): AxumState<ServerState>,
+    UrlPath(id): UrlPath<String>,
+) -> ApiResult<ApiCollection> {
+    let lookup = id.clone();
+    let collection = query(&server, move |conn, _| {
+        let Some(collection) = repository::get_collection_by_id(conn, &lookup)? else {
+            return Ok(None);
+        };
+        let image_count = repository::get_collection_image_count(conn, &collection.id)?;
+        Ok(Some(ApiCollection {
+            collection,
+            image_count,
+        }))
+    })
+    .await?;
+    collection
+        .map(Json)
+        .ok_or_else(|| not_found("Collection", &id))
+}
+
+async fn list_images(
+    AxumState(server): AxumState<ServerState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Vec<ApiImage>> {
+    let images = query(&server, move |conn, user_id| match &page.collection_id {
+        Some(collection_id) => repository::get_images_in_collection(conn, collection_id),
+        None => repository::get_images_by_user(conn, user_id),
+    })
+    .await?;
+
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    Ok(Json(
+        images
+            .into_iter()
+            .skip(page.offset.unwrap_or(0))
+            .take(limit)
+            .map(ApiImage::from)
+            .collect(),
+    ))
+}
+
+async fn get_image(
+    AxumState(server): AxumState<ServerState>,
+    UrlPath(id): UrlPath<String>,
+) -> ApiResult<ApiImage> {
+    let lookup = id.clone();
+    query(&server, move |conn, _| {
+        repository::get_image_by_id(conn, &lookup)
+    })
+    .await?
+    .map(|image| Json(ApiImage::from(image)))
+    .ok_or_else(|| not_found("Image", &id))
+}
+
+/// Split a `data:<type>;base64,<data>` URL into its content type and bytes
+fn decode_data_url(data_url: &str) -> Option<(String, Vec<u8>)> {
+    let (meta, data) = data_url.strip_prefix("data:")?.split_once(',')?;
+    let content_type = meta.strip_suffix(";base64")?;
+    let bytes = BASE64.decode(data).ok()?;
+    Some((content_type.to_string(), bytes))
+}
+
+async fn get_thumbnail(
+    AxumState(server): AxumState<ServerState>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Response, ApiError> {
+    let lookup = id.clone();
+    let image = query(&server, move |conn, _| {
+        repository::get_image_by_id(conn, &lookup)
+    })
+    .await?
+    .ok_or_else(|| not_found("Image", &id))?;
+
+    let (content_type, bytes) = image
+        .thumbnail
+        .as_deref()
+        .and_then(decode_data_url)
+        .ok_or_else(|| not_found("Thumbnail", &id))?;
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes).into_response())
+}
+
+async fn stream_events(
+    AxumState(server): AxumState<ServerState>,
+    Query(query): Query<EventsQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    if !server.events_enabled {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            "Event streaming is turned off".to_string(),
+        ));
+    }
+    let wanted: Option<Vec<String>> = query
+        .events
+        .map(|names| names.split(',').map(|n| n.trim().to_string()).collect());
+    let receiver = events::subscribe();
+    Ok(ws.on_upgrade(move |socket| forward_events(socket, receiver, wanted, server.shutdown)))
+}
+
+/// Send published events to a websocket client until it disconnects or the
+/// server stops
+async fn forward_events(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<events::ActivityEvent>,
+    wanted: Option<Vec<String>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            event = receiver.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // A slow client skips the events it missed
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        log::debug!("Event stream client fell behind by {} events", missed);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if wanted.as_ref().is_some_and(|w| !w.contains(&event.event)) {
+                    continue;
+                }
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                // Pings are answered by axum; anything else from the client
+                // is ignored
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = shutdown.wait_for(|stop| *stop) => break,
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+fn router(state: ServerState) -> Router {
+    let protected = Router::new()
+        .route("/api/stats", get(stats))
+        .route("/api/collections", get(list_collections))
+        .route("/api/collections/{id}", get(get_collection))
+        .route("/api/images", get(list_images))
+        .route("/api/images/{id}", get(get_image))
+        .route("/api/images/{id}/thumbnail", get(get_thumbnail))
+        .route("/api/events", get(stream_events))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    Router::new()
+        .route("/api/health", get(health))
+        .merge(protected)
+        .with_state(state)
+}
+
+/// Bind and start the server in the background
+pub async fn start_server(
+    db: SharedDbPool,
+    user_id: String,
+    config: &ApiServerConfig,
+) -> Result<ApiServerHandle, String> {
+    let ip = if config.allow_lan {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    };
+    let listener = tokio::net::TcpListener::bind(SocketAddr::new(ip, config.port))
+        .await
+        .map_err(|e| format!("Failed to bind port {}: {}", config.port, e))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read server address: {}", e))?;
+
+    let (shutdown, mut shutdown_rx) = watch::channel(false);
+    let app = router(ServerState {
+        db,
+        user_id,
+        token: config.token.clone(),
+        events_enabled: config.events_enabled,
+        shutdown: shutdown_rx.clone(),
+    });
+    tokio::spawn(async move {
+        let result = axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_rx.wait_for(|stop| *stop).await;
+            })
+            .await;
+        match result {
+            Ok(()) => log::info!("API server stopped"),
+            Err(e) => log::error!("API server failed: {}", e),
+        }
+    });
+
+    log::info!("API server listening on http://{}", address);
+    Ok(ApiServerHandle { address, shutdown })
+}
+
+/// Start the server at launch if it was left enabled
+pub fn start_saved_server(app: &AppHandle) {
+    let Ok(config) = load_settings::<ApiServerConfig>(app, &API_SERVER) else {
+        return;
+    };
+    if !config.enabled || config.token.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        match start_server(state.db.clone(), state.user_id.clone(), &config).await {
+            Ok(handle) => *state.api_server.lock().unwrap() = Some(handle),
+            Err(e) => log::warn!("API server not started: {}", e),
+        }
+    });
+}
+
+fn server_status(state: &AppState, config: ApiServerConfig) -> ApiServerStatus {
+    let server = state.api_server.lock().unwrap();
+    ApiServerStatus {
+        running: server.is_some(),
+        address: server.as_ref().map(|s| format!("http://{}", s.address)),
+        config,
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Get the API server config and whether it is running
+#[tauri::command]
+pub fn get_api_server_status(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ApiServerStatus, String> {
+    let config = load_settings(&app, &API_SERVER)?;
+    Ok(server_status(&state, config))
+}
+
+/// Save the API server config and start (or stop) the server to match
+#[tauri::command]
+pub async fn configure_api_server(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    mut config: ApiServerConfig,
+) -> Result<ApiServerStatus, String> {
+    if config.token.trim().is_empty() {
+        config.token = generate_token();
+    }
+    save_settings(&app, &API_SERVER, &config)?;
+
+    if let Some(server) = state.api_server.lock().unwrap().take() {
+        server.stop();
+    }
+    if config.enabled {
+        let handle = start_server(state.db.clone(), state.user_id.clone(), &config).await?;
+        *state.api_server.lock().unwrap() = Some(handle);
+    }
+
+    Ok(server_status(&state, config))
+}
+
+/// Replace the API token; clients using the old one are rejected
+#[tauri::command]
+pub async fn regenerate_api_token(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ApiServerStatus, String> {
+    let mut config: ApiServerConfig = load_settings(&app, &API_SERVER)?;
+    config.token = String::new();
+    configure_api_server(app, state, config).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_thumbnail_data_url() {
+        let url = format!("data:image/jpeg;base64,{}", BASE64.encode(b"jpeg"));
+        assert_eq!(
+            decode_data_url(&url),
+            Some(("image/jpeg".to_string(), b"jpeg".to_vec()))
+        );
+        assert_eq!(decode_data_url("/path/to/thumb.jpg"), None);
+    }
+
+    #[test]
+    fn token_must_match_exactly() {
+        assert!(token_matches("abc123", "abc123"));
+        assert!(!token_matches("abc124", "abc123"));
+        assert!(!token_matches("abc", "abc123"));
+    }
+}
@@ -1,8 +1,17 @@
 //! Astronomy commands for celestial object lookups and calculations
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use diesel::sqlite::SqliteConnection;
 use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
 
+use crate::db::models::NewSimbadCache;
+use crate::db::repository;
+use crate::network::{Network, Provider, RequestError};
 use crate::python::{altitude, simbad};
+use crate::state::AppState;
 
 /// Observer location input
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,12 +34,44 @@ impl From<LocationInput> for altitude::ObserverLocation {
     }
 }
 
-/// Look up an astronomical object in SIMBAD
+/// Look up an astronomical object in SIMBAD. When offline or SIMBAD can't
+/// be reached, the cached answer for the name is returned instead.
 #[tauri::command]
-pub fn lookup_astronomy_object(
+pub async fn lookup_astronomy_object(
+    state: State<'_, AppState>,
     name: String,
 ) -> Result<Option<simbad::SimbadObject>, String> {
-    simbad::lookup_object(&name)
+    let db = state.db.clone();
+    let network = state.network.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        lookup_object(&mut conn, &network, &name)
+    })
+    .await
+    .map_err(|e| format!("Lookup failed: {}", e))?
+}
+
+/// SIMBAD answer for a name, falling back to the cached one when SIMBAD
+/// can't be reached
+pub(crate) fn lookup_object(
+    conn: &mut SqliteConnection,
+    network: &Network,
+    name: &str,
+) -> Result<Option<simbad::SimbadObject>, String> {
+    let key = simbad_cache_key(name);
+    match network.request_blocking(Provider::Simbad, || lookup_simbad(name)) {
+        Ok(object) => {
+            store_cached_object(conn, &key, &object);
+            Ok(object)
+        }
+        Err(e) => match stored_object(conn, &key) {
+            Some((object, _)) => {
+                log::info!("Using cached SIMBAD answer for {}: {}", name, e);
+                Ok(object)
+            }
+            None => Err(e),
+        },
+    }
 }
 
 /// Calculate current altitude and azimuth for an object
@@ -68,3 +109,360 @@ pub fn get_sun_times(
 ) -> Result<altitude::SunTimes, String> {
     altitude::get_sun_times(&location.into())
 }
+
+/// Target and moon altitude, twilight and imaging windows for one night in
+/// a single call, for the altitude chart
+#[tauri::command]
+pub fn get_night_chart(
+    ra_deg: f64,
+    dec_deg: f64,
+    location: LocationInput,
+    date: Option<String>,
+    min_altitude: Option<f64>,
+    interval_minutes: Option<i32>,
+) -> Result<altitude::NightChart, String> {
+    altitude::get_night_chart(
+        ra_deg,
+        dec_deg,
+        &location.into(),
+        date.as_deref(),
+        min_altitude,
+        interval_minutes,
+    )
+}
+
+/// Altitude tracks for several targets over one night in a single call,
+/// for ordering a schedule
+#[tauri::command]
+pub fn compare_altitudes(
+    targets: Vec<altitude::AltitudeTarget>,
+    location: LocationInput,
+    date: Option<String>,
+    min_altitude: Option<f64>,
+    interval_minutes: Option<i32>,
+) -> Result<altitude::AltitudeComparison, String> {
+    if targets.is_empty() {
+        return Err("No targets to compare".to_string());
+    }
+    altitude::compare_altitudes(
+        &targets,
+        &location.into(),
+        date.as_deref(),
+        min_altitude,
+        interval_minutes,
+    )
+}
+
+// ============================================================================
+// Batch SIMBAD lookup
+// ============================================================================
+
+/// Longest backoff after failed lookups
+const MAX_LOOKUP_INTERVAL: Duration = Duration::from_secs(8);
+/// Give up on the rest of the batch after this many failures in a row
+const MAX_CONSECUTIVE_FAILURES: usize = 3;
+/// "Not found" answers are re-checked after this many days
+const NOT_FOUND_CACHE_DAYS: i64 = 7;
+
+/// Result for one requested name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchLookupEntry {
+    pub name: String,
+    pub object: Option<simbad::SimbadObject>,
+    /// Answered from simbad_cache without querying SIMBAD
+    pub cached: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchLookupResult {
+    /// One entry per requested name, in request order
+    pub results: Vec<BatchLookupEntry>,
+    /// Distinct names queried against SIMBAD
+    pub looked_up: usize,
+    /// Distinct names answered from the cache
+    pub cached: usize,
+    pub failed: usize,
+}
+
+/// Emitted as "simbad-batch-progress" after each distinct name
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchLookupProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub name: String,
+    pub cached: bool,
+}
+
+/// Cache key for an object name: whitespace collapsed, case folded,
+/// so "ngc  7000" and "NGC 7000 " share one lookup
+fn simbad_cache_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+/// Cached answer for a name, whatever its age, and when it was cached
+fn stored_object(
+    conn: &mut SqliteConnection,
+    key: &str,
+) -> Option<(Option<simbad::SimbadObject>, chrono::NaiveDateTime)> {
+    let entry = repository::get_cached_object(conn, key).ok()??;
+    let object: Option<simbad::SimbadObject> = serde_json::from_str(&entry.data).ok()?;
+    Some((object, entry.cached_at))
+}
+
+/// Cached answer for a name, if there is a usable one
+fn cached_object(conn: &mut SqliteConnection, key: &str) -> Option<Option<simbad::SimbadObject>> {
+    let (object, cached_at) = stored_object(conn, key)?;
+    let age = chrono::Utc::now().naive_utc() - cached_at;
+    if object.is_none() && age > chrono::Duration::days(NOT_FOUND_CACHE_DAYS) {
+        return None;
+    }
+    Some(object)
+}
+
+fn store_cached_object(
+    conn: &mut SqliteConnection,
+    key: &str,
+    object: &Option<simbad::SimbadObject>,
+) {
+    let cache_entry = NewSimbadCache {
+        id: uuid::Uuid::new_v4().to_string(),
+        object_name: key.to_string(),
+        data: serde_json::to_string(object).unwrap_or_else(|_| "null".into()),
+    };
+    if let Err(e) = repository::cache_object(conn, &cache_entry) {
+        log::warn!("Failed to cache SIMBAD result for {}: {}", key, e);
+    }
+}
+
+/// SIMBAD lookup for the network layer; failures are worth retrying
+fn lookup_simbad(name: &str) -> Result<Option<simbad::SimbadObject>, RequestError> {
+    simbad::lookup_object(name).map_err(RequestError::Retry)
+}
+
+/// Resolve a list of names through simbad_cache, looking up the rest one
+/// at a time with rate limiting. Lookups that fail are reported per name;
+/// after repeated failures in a row the remaining names are skipped.
+pub fn lookup_batch(
+    conn: &mut SqliteConnection,
+    names: &[String],
+    interval: Duration,
+    mut lookup: impl FnMut(&str) -> Result<Option<simbad::SimbadObject>, String>,
+    mut on_progress: impl FnMut(BatchLookupProgress),
+) -> BatchLookupResult {
+    let mut result = BatchLookupResult::default();
+
+    // Distinct names in request order, keyed for the cache
+    let mut keys: Vec<(String, &str)> = Vec::new();
+    for name in names {
+        let key = simbad_cache_key(name);
+        if !key.is_empty() && !keys.iter().any(|(k, _)| *k == key) {
+            keys.push((key, name.trim()));
+        }
+    }
+
+    let total = keys.len();
+    let mut answers: HashMap<String, BatchLookupEntry> = HashMap::new();
+    let mut delay = interval;
+    let mut last_lookup: Option<Instant> = None;
+    let mut failures = 0;
+
+    for (completed, (key, name)) in keys.into_iter().enumerate() {
+        let mut entry = BatchLookupEntry {
+            name: name.to_string(),
+            object: None,
+            cached: false,
+            error: None,
+        };
+
+        if let Some(object) = cached_object(conn, &key) {
+            entry.object = object;
+            entry.cached = true;
+            result.cached += 1;
+        } else if failures >= MAX_CONSECUTIVE_FAILURES {
+            entry.error = Some("Skipped after repeated SIMBAD failures".to_string());
+            result.failed += 1;
+        } else {
+            if let Some(last) = last_lookup {
+                std::thread::sleep(delay.saturating_sub(last.elapsed()));
+            }
+            let lookup_result = lookup(name);
+            last_lookup = Some(Instant::now());
+            result.looked_up += 1;
+
+            match lookup_result {
+                Ok(object) => {
+                    failures = 0;
+                    delay = interval;
+                    store_cached_object(conn, &key, &object);
+                    entry.object = object;
+                }
+                Err(e) => {
+                    failures += 1;
+                    delay = (delay * 2).clamp(interval, MAX_LOOKUP_INTERVAL);
+                    entry.error = Some(e);
+                    result.failed += 1;
+                }
+            }
+        }
+
+        on_progress(BatchLookupProgress {
+            completed: completed + 1,
+            total,
+            name: entry.name.clone(),
+            cached: entry.cached,
+        });
+        answers.insert(key, entry);
+    }
+
+    result.results = names
+        .iter()
+        .map(|name| match answers.get(&simbad_cache_key(name)) {
+            Some(entry) => BatchLookupEntry {
+                name: name.clone(),
+                ..entry.clone()
+            },
+            None => BatchLookupEntry {
+                name: name.clone(),
+                object: None,
+                cached: false,
+                error: Some("Empty object name".to_string()),
+            },
+        })
+        .collect();
+    result
+}
+
+/// Look up many objects at once (e.g. all targets of an import), using the
+/// SIMBAD cache where possible. Emits "simbad-batch-progress" events.
+#[tauri::command]
+pub async fn lookup_objects_batch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    names: Vec<String>,
+) -> Result<BatchLookupResult, String> {
+    let db = state.db.clone();
+    let network = state.network.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let result = lookup_batch(
+            &mut conn,
+            &names,
+            network.limit(Provider::Simbad).interval(),
+            |name| network.request_blocking(Provider::Simbad, || lookup_simbad(name)),
+            |progress| {
+                let _ = app.emit("simbad-batch-progress", &progress);
+            },
+        );
+        log::info!(
+            "Batch SIMBAD lookup: {} names, {} looked up, {} cached, {} failed",
+            names.len(),
+            result.looked_up,
+            result.cached,
+            result.failed
+        );
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Batch lookup failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+
+    fn object(name: &str) -> simbad::SimbadObject {
+        simbad::SimbadObject {
+            name: name.to_string(),
+            object_type: "G".to_string(),
+            ra: "00 42 44".to_string(),
+            dec: "+41 16 09".to_string(),
+            ra_deg: Some(10.68),
+            dec_deg: Some(41.27),
+            magnitude: None,
+            size: None,
+            common_name: None,
+            distance: None,
+            spectral_type: None,
+            alternative_names: None,
+            catalogs: None,
+        }
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn batch_dedupes_and_caches() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+
+        let mut queried = Vec::new();
+        let mut lookup = |name: &str| {
+            queried.push(name.to_string());
+            Ok((name != "Nothing").then(|| object(name)))
+        };
+        let first = lookup_batch(
+            &mut conn,
+            &names(&["M31", "m31 ", "NGC 7000", "Nothing"]),
+            Duration::ZERO,
+            &mut lookup,
+            |_| {},
+        );
+        assert_eq!(queried, vec!["M31", "NGC 7000", "Nothing"]);
+        assert_eq!(first.results.len(), 4);
+        assert_eq!(first.results[1].name, "m31 ");
+        assert_eq!(first.results[1].object.as_ref().unwrap().name, "M31");
+        assert!(first.results[3].object.is_none());
+        assert_eq!((first.looked_up, first.cached), (3, 0));
+
+        // Found and not-found answers are both served from the cache
+        let mut progress = Vec::new();
+        let second = lookup_batch(
+            &mut conn,
+            &names(&["ngc  7000", "nothing"]),
+            Duration::ZERO,
+            |_| panic!("should not query SIMBAD"),
+            |p| progress.push(p.completed),
+        );
+        assert_eq!((second.looked_up, second.cached), (0, 2));
+        assert!(second.results[0].object.is_some());
+        assert_eq!(progress, vec![1, 2]);
+    }
+
+    #[test]
+    fn batch_returns_partial_results_on_failures() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+
+        let mut calls = 0;
+        let result = lookup_batch(
+            &mut conn,
+            &names(&["M1", "M2", "M3", "M4", "M5", "M6"]),
+            Duration::ZERO,
+            |name| {
+                calls += 1;
+                if name == "M1" {
+                    Ok(Some(object(name)))
+                } else {
+                    Err("SIMBAD unavailable".to_string())
+                }
+            },
+            |_| {},
+        );
+        // M2-M4 fail, then the rest are skipped without querying
+        assert_eq!(calls, 4);
+        assert_eq!(result.failed, 5);
+        assert!(result.results[0].object.is_some());
+        let skipped = result.results[5].error.as_deref().unwrap();
+        assert!(skipped.starts_with("Skipped"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Images needing attention
+//!
+//! Library upkeep that's otherwise easy to miss: images that were never
+//! plate solved, have no thumbnail or target name, or whose files are gone
+//! from disk. The queue is computed on request rather than stored, so it
+//! always reflects the library as it is.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::images::listed_images;
+use super::plate_solve::is_plate_solved;
+use super::solar_system::is_solar_system;
+
+/// Why an image is in the attention queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttentionCategory {
+    Unsolved,
+    MissingThumbnail,
+    MissingTarget,
+    MissingFile,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionCounts {
+    pub unsolved: usize,
+    pub missing_thumbnail: usize,
+    pub missing_target: usize,
+    pub missing_file: usize,
+    /// Images in at least one category
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionItem {
+    pub image: Image,
+    pub categories: Vec<AttentionCategory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionQueue {
+    /// Counts across the whole library, whatever category was asked for
+    pub counts: AttentionCounts,
+    /// Images needing attention, newest first
+    pub items: Vec<AttentionItem>,
+}
+
+fn is_blank(value: Option<&str>) -> bool {
+    value.is_none_or(|v| v.trim().is_empty())
+}
+
+/// Categories an image falls in; `exists` checks whether a file is on disk
+fn attention_categories(image: &Image, exists: impl Fn(&Path) -> bool) -> Vec<AttentionCategory> {
+    let mut categories = Vec::new();
+    let metadata = image.metadata.as_deref();
+    // Planets and the Moon move against the stars, so there's nothing to solve
+    if !is_plate_solved(metadata) && !is_solar_system(metadata) {
+        categories.push(AttentionCategory::Unsolved);
+    }
+    if is_blank(image.thumbnail.as_deref()) {
+        categories.push(AttentionCategory::MissingThumbnail);
+    }
+    if is_blank(image.summary.as_deref()) {
+        categories.push(AttentionCategory::MissingTarget);
+    }
+    let files = [image.url.as_deref(), image.fits_url.as_deref()];
+    if files
+        .into_iter()
+        .flatten()
+        .any(|file| !exists(Path::new(file)))
+    {
+        categories.push(AttentionCategory::MissingFile);
+    }
+    categories
+}
+
+pub(super) fn attention_queue(
+    images: Vec<Image>,
+    category: Option<AttentionCategory>,
+    exists: impl Fn(&Path) -> bool,
+) -> AttentionQueue {
+    let mut counts = AttentionCounts::default();
+    let mut items = Vec::new();
+    for image in images {
+        let categories = attention_categories(&image, &exists);
+        if categories.is_empty() {
+            continue;
+        }
+        counts.total += 1;
+        for c in &categories {
+            match c {
+                AttentionCategory::Unsolved => counts.unsolved += 1,
+                AttentionCategory::MissingThumbnail => counts.missing_thumbnail += 1,
+                AttentionCategory::MissingTarget => counts.missing_target += 1,
+                AttentionCategory::MissingFile => counts.missing_file += 1,
+            }
+        }
+        if category.is_none_or(|c| categories.contains(&c)) {
+            items.push(AttentionItem { image, categories });
+        }
+    }
+    AttentionQueue { counts, items }
+}
+
+/// Library images needing attention, optionally only those in one category.
+/// Frames in import triage and Light frames under a stacked image are left
+/// out.
+#[tauri::command]
+pub async fn get_attention_queue(
+    state: State<'_, AppState>,
+    category: Option<AttentionCategory>,
+) -> Result<AttentionQueue, String> {
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+    // Checking files can be slow on network mounts
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let images =
+            repository::get_images_by_user(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        drop(conn);
+        Ok(attention_queue(
+            listed_images(images, None),
+            category,
+            |path| path.exists(),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Building attention queue failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_image(id: &str) -> Image {
+        Image {
+            url: Some(format!("/data/{}.fit", id)),
+            summary: Some("M42".to_string()),
+            metadata: Some(r#"{"plate_solve":{"ra":83.8,"dec":-5.4}}"#.to_string()),
+            thumbnail: Some("data:image/jpeg;base64,AAAA".to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn queue_counts_each_category() {
+        let unsolved = Image {
+            metadata: None,
+            summary: Some("  ".to_string()),
+            ..make_image("unsolved")
+        };
+        let missing = Image {
+            thumbnail: None,
+            fits_url: Some("/gone/missing.fit".to_string()),
+            ..make_image("missing")
+        };
+        let images = vec![make_image("fine"), unsolved, missing];
+        let exists = |path: &Path| !path.starts_with("/gone");
+
+        let queue = attention_queue(images.clone(), None, exists);
+        assert_eq!(queue.counts.total, 2);
+        assert_eq!(queue.counts.unsolved, 1);
+        assert_eq!(queue.counts.missing_target, 1);
+        assert_eq!(queue.counts.missing_thumbnail, 1);
+        assert_eq!(queue.counts.missing_file, 1);
+        assert_eq!(
+            queue.items[0].categories,
+            [
+                AttentionCategory::Unsolved,
+                AttentionCategory::MissingTarget
+            ]
+        );
+
+        let queue = attention_queue(images, Some(AttentionCategory::MissingFile), exists);
+        assert_eq!(queue.counts.total, 2);
+        let ids: Vec<_> = queue
+            .items
+            .iter()
+            .map(|item| item.image.id.as_str())
+            .collect();
+        assert_eq!(ids, ["missing"]);
+    }
+}
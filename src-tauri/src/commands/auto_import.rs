@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
+use std::time::Instant;
 use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::watch;
 use walkdir::WalkDir;
@@ -20,12 +21,25 @@ pub struct AutoImportProgress {
 
 use crate::db::models::{NewCollection, NewCollectionImage, NewImage, UpdateImage};
 use crate::db::repository;
+use crate::events::{self, ImageAdded};
+use crate::logging;
 use crate::python::image_process as py_image;
 use crate::python::plate_solve as py_plate_solve;
 use crate::state::{AppState, AutoImportStatus};
 
+use super::capture_site::{tag_capture_site, CaptureSites};
+use super::collection_naming::collection_name_template;
+use super::frame_rules::FrameType;
+use super::job_metrics::{self, JobTimings};
+use super::object_names::ObjectNames;
 use super::scan::{
-    generate_fits_thumbnail, generate_thumbnail, parse_fits_metadata, FitsMetadata,
+    generate_fits_thumbnail, generate_thumbnail, parse_fits_metadata, AcquisitionColumns,
+    FitsMetadata,
+};
+use super::solar_system::{solar_system_info, with_solar_system};
+use super::tag_rules::{merge_tags, rule_tags, tag_rules, TagFacts, TagRules};
+use super::triage::{
+    triage_reasons, triage_rules, with_triage_reasons, TriageRules, TRIAGE_PENDING,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -255,11 +269,15 @@ fn copy_supporting_files(
 
 /// Run a single scan cycle
 fn run_scan_cycle(
-    db_pool: &crate::db::DbPool,
+    db_pool: &crate::db::SharedDbPool,
     user_id: &str,
     config: &AutoImportConfig,
+    triage: &TriageRules,
+    tags: &TagRules,
+    collection_template: &str,
     preview_dir: &Path,
     progress_tx: Option<&mpsc::Sender<AutoImportProgress>>,
+    timings: &JobTimings,
 ) -> Result<(usize, Vec<String>), String> {
     let emit = |step: &str, detail: &str, name: Option<&str>, current: usize, total: usize| {
         if let Some(tx) = progress_tx {
@@ -292,6 +310,8 @@ fn run_scan_cycle(
 
     // Load existing image URLs for dedup
     let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    let object_names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    let capture_sites = CaptureSites::load(&mut conn, user_id)?;
     let existing_urls: HashSet<String> = repository::get_all_image_urls(&mut conn, user_id)
         .map_err(|e| e.to_string())?
         .into_iter()
@@ -302,7 +322,7 @@ fn run_scan_cycle(
         .collect();
     drop(conn);
 
-    // Track session collections: session_date_string → collection_id
+    // Track session collections: collection name → collection_id
     let mut session_collections: std::collections::HashMap<String, String> = std::collections::HashMap::new();
 
     emit("scanning", "Scanning sources...", None, 0, 0);
@@ -351,9 +371,10 @@ fn run_scan_cycle(
             emit("found", &format!("Found: {}", file_name), Some(&file_name), imported + 1, 0);
 
             // Parse FITS metadata
-            let metadata = match parse_fits_metadata(path) {
+            let metadata = match timings.time(job_metrics::PHASE_FITS_PARSE, || parse_fits_metadata(path)) {
                 Ok(m) => m,
                 Err(e) => {
+                    log::warn!("Failed to parse {}: {}", path.display(), e);
                     errors.push(format!("{}: {}", path.display(), e));
                     continue;
                 }
@@ -361,14 +382,16 @@ fn run_scan_cycle(
 
             // Generate thumbnail (try JPEG companion first, then FITS stretch)
             let jpeg_companion = path.with_extension("jpg");
-            let thumbnail = if jpeg_companion.exists() {
-                generate_thumbnail(&jpeg_companion).ok()
-            } else {
-                generate_fits_thumbnail(path).ok()
-            };
+            let thumbnail = timings.time(job_metrics::PHASE_THUMBNAIL, || {
+                if jpeg_companion.exists() {
+                    generate_thumbnail(&jpeg_companion).ok()
+                } else {
+                    generate_fits_thumbnail(path).ok()
+                }
+            });
 
             // Extract target name
-            let target = extract_target_name(path, &metadata);
+            let target = extract_target_name(path, &metadata).map(|t| object_names.canonical(&t));
             let summary = target
                 .clone()
                 .or_else(|| Some(path.file_stem()?.to_string_lossy().to_string()));
@@ -407,7 +430,26 @@ fn run_scan_cycle(
             };
 
             // Build metadata JSON
-            let meta_json = serde_json::to_string(&metadata.raw_headers).ok();
+            let mut meta_json = serde_json::to_string(&metadata.raw_headers).ok();
+            let acquisition = AcquisitionColumns::from(&metadata);
+
+            // Failed frames and test shots are held back for review
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let reasons = triage_reasons(triage, &stem, &metadata);
+            if !reasons.is_empty() {
+                log::info!("Holding {} for triage: {}", file_name, reasons.join(", "));
+                meta_json = with_triage_reasons(meta_json, &reasons);
+            }
+
+            // Planetary and lunar stacks get their ephemeris instead of a plate solve
+            let solar_system = solar_system_info(target.as_deref(), &stem, metadata.date_obs.as_deref());
+            let summary = match &solar_system {
+                Some(info) => {
+                    meta_json = with_solar_system(meta_json, info);
+                    target.clone().or_else(|| Some(info.body.name().to_string()))
+                }
+                None => summary,
+            };
 
             // Copy FITS to library if configured for this source
             let fits_final_path = if let Some(lib_path) = &source.library_path {
@@ -448,6 +490,18 @@ fn run_scan_cycle(
                 path_str.clone()
             };
 
+            // Auto-import only picks up stacked results
+            let auto_tags = if tags.enabled {
+                let facts = TagFacts {
+                    object: target.clone(),
+                    stacked_frames,
+                    ..TagFacts::from_metadata(&metadata, FrameType::Stacked)
+                };
+                rule_tags(tags, &facts)
+            } else {
+                Vec::new()
+            };
+
             // Create image record
             let image_id = uuid::Uuid::new_v4().to_string();
             let new_image = NewImage {
@@ -464,7 +518,7 @@ fn run_scan_cycle(
                 description,
                 content_type: Some("image/fits".to_string()),
                 favorite: false,
-                tags: None,
+                tags: merge_tags(None, &auto_tags),
                 visibility: Some("private".to_string()),
                 location: None,
                 annotations: None,
@@ -472,27 +526,68 @@ fn run_scan_cycle(
                 thumbnail,
                 fits_url: Some(fits_final_path),
                 blob_id: None,
+                exposure: acquisition.exposure,
+                gain: acquisition.gain,
+                filter: acquisition.filter,
+                telescope: acquisition.telescope,
+                date_obs: acquisition.date_obs,
+                wcs: None,
+                parent_image_id: None,
+                triage: (!reasons.is_empty()).then(|| TRIAGE_PENDING.to_string()),
             };
 
             let fits_path_str = new_image.fits_url.clone().unwrap_or_default();
 
             let mut conn = db_pool.get().map_err(|e| e.to_string())?;
-            match repository::create_image(&mut conn, &new_image) {
-                Ok(_) => {
+            match timings.time(job_metrics::PHASE_DB_INSERT, || repository::create_image(&mut conn, &new_image)) {
+                Ok(image) => {
                     imported += 1;
                     log::info!("Auto-imported: {}", new_image.filename);
 
+                    // Link to known equipment from TELESCOP/INSTRUME headers
+                    if let Err(e) = repository::auto_link_image_equipment(
+                        &mut conn,
+                        user_id,
+                        &image_id,
+                        metadata.telescope.as_deref(),
+                        metadata.instrument.as_deref(),
+                    ) {
+                        log::warn!("Failed to link equipment for {}: {}", new_image.filename, e);
+                    }
+
+                    // Tag where it was taken
+                    if let Err(e) = tag_capture_site(&mut conn, &capture_sites, &image) {
+                        log::warn!("Failed to tag capture site for {}: {}", new_image.filename, e);
+                    }
+
+                    // Track filter usage and integration time
+                    if let Err(e) = super::filters::record_image_filter(
+                        &mut conn,
+                        user_id,
+                        &image_id,
+                        metadata.filter.as_deref(),
+                        metadata.exposure,
+                        stacked_frames,
+                    ) {
+                        log::warn!("Failed to record filter for {}: {}", new_image.filename, e);
+                    }
+
                     // Add to session collection (one per observing night)
-                    if let Some(date_obs) = &metadata.date_obs {
-                        if let Some(session_date) = super::scan::get_session_date(date_obs) {
+                    let mut session_collection = None;
+                    if metadata.date_obs.is_some() {
+                        if let Some(session_date) = metadata.session_date(None) {
                             let session_key = session_date.to_string();
-                            let session_coll_id = if let Some(id) = session_collections.get(&session_key) {
+                            let coll_name = super::scan::generate_collection_name(
+                                collection_template,
+                                &session_date,
+                                target.as_deref(),
+                            );
+                            let session_coll_id = if let Some(id) = session_collections.get(&coll_name) {
                                 id.clone()
                             } else {
-                                let coll_name = super::scan::generate_collection_name(&session_date, None);
                                 match repository::get_collection_by_name(&mut conn, user_id, &coll_name) {
                                     Ok(Some(existing)) => {
-                                        session_collections.insert(session_key.clone(), existing.id.clone());
+                                        session_collections.insert(coll_name.clone(), existing.id.clone());
                                         existing.id
                                     }
                                     _ => {
@@ -500,7 +595,7 @@ fn run_scan_cycle(
                                         let new_coll = NewCollection {
                                             id: coll_id.clone(),
                                             user_id: user_id.to_string(),
-                                            name: coll_name,
+                                            name: coll_name.clone(),
                                             description: Some(format!("Observing session {}", session_key)),
                                             visibility: "private".to_string(),
                                             template: Some("astrolog".to_string()),
@@ -513,10 +608,12 @@ fn run_scan_cycle(
                                             }).to_string()),
                                             archived: false,
                                         };
-                                        match repository::create_collection(&mut conn, &new_coll) {
-                                            Ok(c) => {
-                                                log::info!("Created session collection: {} ({})", c.name, session_key);
-                                                session_collections.insert(session_key.clone(), c.id.clone());
+                                        match repository::get_or_create_auto_collection(&mut conn, &new_coll) {
+                                            Ok((c, created)) => {
+                                                if created {
+                                                    log::info!("Created session collection: {} ({})", c.name, session_key);
+                                                }
+                                                session_collections.insert(coll_name.clone(), c.id.clone());
                                                 c.id
                                             }
                                             Err(e) => {
@@ -532,10 +629,11 @@ fn run_scan_cycle(
                             if !session_coll_id.is_empty() {
                                 let entry = NewCollectionImage {
                                     id: uuid::Uuid::new_v4().to_string(),
-                                    collection_id: session_coll_id,
+                                    collection_id: session_coll_id.clone(),
                                     image_id: image_id.clone(),
                                 };
                                 let _ = repository::add_image_to_collection(&mut conn, &entry);
+                                session_collection = Some(session_coll_id);
                             }
                         }
                     }
@@ -554,6 +652,7 @@ fn run_scan_cycle(
                         sigma: config.stretch_sigma.unwrap_or(3.0),
                         gradient_removal: true,
                         autocrop: true,
+                        ..Default::default()
                     };
                     match crate::stretch::generate_preview(
                         Path::new(&fits_path_str),
@@ -584,8 +683,9 @@ fn run_scan_cycle(
                         }
                     }
 
-                    // Plate solve if enabled
-                    if config.plate_solve.unwrap_or(false) {
+                    // Plate solve if enabled (not worth it for frames in triage, and
+                    // impossible for planets)
+                    if config.plate_solve.unwrap_or(false) && reasons.is_empty() && solar_system.is_none() {
                         let solver = config.plate_solve_solver.as_deref().unwrap_or("local");
                         emit("plate-solving", &format!("Plate solving: {}", new_image.filename), Some(&new_image.filename), imported, 0);
                         log::info!("Auto plate-solving: {} with {}", new_image.filename, solver);
@@ -659,8 +759,20 @@ fn run_scan_cycle(
                             }
                         }
                     }
+
+                    // Announced once the preview and solve are in place
+                    events::publish(events::IMAGE_ADDED, &ImageAdded {
+                        id: image_id.clone(),
+                        filename: new_image.filename.clone(),
+                        object_name: new_image.summary.clone(),
+                        stacked: true,
+                        collection_id: session_collection,
+                        held_for_triage: !reasons.is_empty(),
+                        source: "auto-import",
+                    });
                 }
                 Err(e) => {
+                    log::warn!("Failed to import {}: {}", new_image.filename, e);
                     errors.push(format!("Failed to import {}: {}", new_image.filename, e));
                 }
             }
@@ -740,15 +852,40 @@ pub async fn start_auto_import(
             let cfg = config.clone();
             let app_clone = app.clone();
             let pd = pdir.clone();
+            let job_id = logging::new_job_id("auto-import");
+            let job = job_id.clone();
             let scan_result = tokio::task::spawn_blocking(move || {
+                let _job = logging::enter_job(Some(job.clone()));
+                let timings = JobTimings::new();
+                let started = Instant::now();
                 let (tx, rx) = mpsc::channel();
                 let app_fwd = app_clone.clone();
                 std::thread::spawn(move || {
                     while let Ok(progress) = rx.recv() {
                         let _ = app_fwd.emit("auto-import-progress", &progress);
+                        events::publish(events::AUTO_IMPORT_PROGRESS, &progress);
                     }
                 });
-                run_scan_cycle(&db, &uid, &cfg, &pd, Some(&tx))
+                let result = run_scan_cycle(
+                    &db,
+                    &uid,
+                    &cfg,
+                    &triage_rules(&app_clone),
+                    &tag_rules(&app_clone),
+                    &collection_name_template(&app_clone),
+                    &pd,
+                    Some(&tx),
+                    &timings,
+                );
+                if let Err(e) = &result {
+                    log::error!("Auto-import failed: {}", e);
+                }
+                // Most polls find nothing new; only keep cycles that did work
+                if timings.count(job_metrics::PHASE_FITS_PARSE) > 0 {
+                    timings.record(job_metrics::PHASE_TOTAL, started.elapsed());
+                    job_metrics::save_job_metrics(&db, &uid, &job, &timings);
+                }
+                result
             }).await;
 
             // Update status with results
@@ -756,6 +893,7 @@ pub async fn start_auto_import(
                 let mut status = status_ref.lock().unwrap();
                 status.is_scanning = false;
                 status.last_scan_time = Some(chrono::Utc::now().to_rfc3339());
+                status.last_job_id = Some(job_id);
 
                 match scan_result {
                     Ok(Ok((count, scan_errors))) => {
@@ -778,6 +916,7 @@ pub async fn start_auto_import(
             // Emit status event
             let status_snapshot = { status_ref.lock().unwrap().clone() };
             let _ = app.emit("auto-import-status", &status_snapshot);
+            events::publish(events::AUTO_IMPORT_FINISHED, &status_snapshot);
 
             // Wait for next poll or cancellation
             tokio::select! {
@@ -835,20 +974,45 @@ pub async fn scan_auto_import_now(
     }
 
     let app_clone = app.clone();
+    let job_id = logging::new_job_id("auto-import");
+    let job = job_id.clone();
     let scan_result = tokio::task::spawn_blocking(move || {
+        let _job = logging::enter_job(Some(job.clone()));
+        let timings = JobTimings::new();
+        let started = Instant::now();
         let (tx, rx) = mpsc::channel();
         let app_fwd = app_clone.clone();
         std::thread::spawn(move || {
             while let Ok(progress) = rx.recv() {
                 let _ = app_fwd.emit("auto-import-progress", &progress);
+                events::publish(events::AUTO_IMPORT_PROGRESS, &progress);
             }
         });
-        run_scan_cycle(&db_pool, &user_id, &config, &pdir, Some(&tx))
+        let result = run_scan_cycle(
+            &db_pool,
+            &user_id,
+            &config,
+            &triage_rules(&app_clone),
+            &tag_rules(&app_clone),
+            &collection_name_template(&app_clone),
+            &pdir,
+            Some(&tx),
+            &timings,
+        );
+        if let Err(e) = &result {
+            log::error!("Auto-import failed: {}", e);
+        }
+        if timings.count(job_metrics::PHASE_FITS_PARSE) > 0 {
+            timings.record(job_metrics::PHASE_TOTAL, started.elapsed());
+            job_metrics::save_job_metrics(&db_pool, &user_id, &job, &timings);
+        }
+        result
     }).await;
 
     let mut status = state.auto_import_status.lock().unwrap();
     status.is_scanning = false;
     status.last_scan_time = Some(chrono::Utc::now().to_rfc3339());
+    status.last_job_id = Some(job_id);
 
     match scan_result {
         Ok(Ok((count, scan_errors))) => {
@@ -871,6 +1035,7 @@ pub async fn scan_auto_import_now(
     drop(status);
 
     let _ = app.emit("auto-import-status", &result);
+    events::publish(events::AUTO_IMPORT_FINISHED, &result);
     Ok(result)
 }
 
@@ -1,16 +1,33 @@
 /**
- * Backup and restore commands for the database
+ * Backup and restore commands for the database.
+ *
+ * Backups are bundles (a tar archive of the database, settings files, and a
+ * manifest of the thumbnail cache), optionally compressed and encrypted.
+ * Plain `.db` files from older versions can still be restored.
  */
 
 use std::fs;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
 use chrono::Local;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Manager, State};
+use sha2::Sha256;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 use crate::state::AppState;
 
+use super::settings;
+
+/// Bundle entry holding the SQLite database
+const BUNDLE_DB_ENTRY: &str = "astra.db";
+const BUNDLE_MANIFEST_ENTRY: &str = "manifest.json";
+const BUNDLE_SETTINGS_DIR: &str = "settings/";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BackupInfo {
     pub filename: String,
@@ -32,6 +49,437 @@ pub struct RestoreResult {
     pub message: String,
 }
 
+/// A cached preview image recorded in the bundle manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailEntry {
+    pub file: String,
+    pub size_bytes: u64,
+}
+
+/// Describes the contents of a backup bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub settings: Vec<String>,
+    pub thumbnails: Vec<ThumbnailEntry>,
+}
+
+/// Decoded backup contents. Legacy `.db` backups have no manifest or settings.
+#[derive(Debug)]
+pub struct BackupBundle {
+    pub manifest: Option<BundleManifest>,
+    pub database: Vec<u8>,
+    pub settings: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableRowCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+/// Result of opening a backup's database and checking it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseReport {
+    /// "ok", or the problems reported by PRAGMA integrity_check
+    pub integrity: String,
+    /// Most recent applied migration
+    pub schema_version: Option<String>,
+    pub row_counts: Vec<TableRowCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub valid: bool,
+    pub message: String,
+    /// "bundle" or "database" (legacy plain SQLite backup)
+    pub format: Option<String>,
+    pub compressed: bool,
+    pub encrypted: bool,
+    pub manifest: Option<BundleManifest>,
+    pub database: Option<DatabaseReport>,
+    /// Thumbnails listed in the manifest that are not in the local cache
+    pub missing_thumbnails: usize,
+}
+
+/// SQLite database file header
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// Header for passphrase-encrypted backups: magic, then salt, nonce, and AES-256-GCM ciphertext
+const ENCRYPTED_MAGIC: &[u8] = b"ASTRAENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 200_000;
+
+/// Backup file extensions recognized by list/restore/delete
+const BACKUP_EXTENSIONS: &[&str] = &[
+    ".db", ".db.gz", ".db.zst", ".tar", ".tar.gz", ".tar.zst", ".enc",
+];
+
+/// On-disk encoding of a backup file, detected from its leading bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupFormat {
+    Sqlite,
+    Bundle,
+    Gzip,
+    Zstd,
+    Encrypted,
+    Unknown,
+}
+
+pub fn detect_backup_format(data: &[u8]) -> BackupFormat {
+    if data.starts_with(ENCRYPTED_MAGIC) {
+        BackupFormat::Encrypted
+    } else if data.starts_with(SQLITE_MAGIC) {
+        BackupFormat::Sqlite
+    } else if data.get(257..262) == Some(b"ustar".as_slice()) {
+        BackupFormat::Bundle
+    } else if data.starts_with(ZSTD_MAGIC) {
+        BackupFormat::Zstd
+    } else if data.starts_with(GZIP_MAGIC) {
+        BackupFormat::Gzip
+    } else {
+        BackupFormat::Unknown
+    }
+}
+
+fn is_backup_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .is_some_and(|name| BACKUP_EXTENSIONS.iter().any(|ext| name.ends_with(ext)))
+}
+
+/// File extension for a backup with the given compression and encryption
+fn backup_extension(compression: Option<&str>, encrypted: bool) -> Result<String, String> {
+    let mut ext = match compression.unwrap_or("none") {
+        "none" => "tar".to_string(),
+        "gzip" | "gz" => "tar.gz".to_string(),
+        "zstd" | "zst" => "tar.zst".to_string(),
+        other => return Err(format!("Unsupported compression '{}' (expected none, gzip, or zstd)", other)),
+    };
+    if encrypted {
+        ext.push_str(".enc");
+    }
+    Ok(ext)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+/// Compress and optionally encrypt a database or bundle
+pub fn encode_backup(
+    data: &[u8],
+    compression: Option<&str>,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let compressed = match compression.unwrap_or("none") {
+        "none" => data.to_vec(),
+        "gzip" | "gz" => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder
+                .write_all(data)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("Failed to compress backup: {}", e))?
+        }
+        "zstd" | "zst" => zstd::encode_all(data, 0)
+            .map_err(|e| format!("Failed to compress backup: {}", e))?,
+        other => return Err(format!("Unsupported compression '{}' (expected none, gzip, or zstd)", other)),
+    };
+
+    let Some(passphrase) = passphrase.filter(|p| !p.is_empty()) else {
+        return Ok(compressed);
+    };
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt);
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, compressed.as_slice())
+        .map_err(|_| "Failed to encrypt backup".to_string())?;
+
+    let mut out = Vec::with_capacity(ENCRYPTED_MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(ENCRYPTED_MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypt and decompress a backup, auto-detecting its format.
+/// Returns the payload (a SQLite database or bundle) and the layers that
+/// were removed, outermost first.
+pub fn unwrap_backup(
+    data: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, Vec<BackupFormat>), String> {
+    let mut layers = Vec::new();
+    let mut current = data.to_vec();
+
+    loop {
+        let format = detect_backup_format(&current);
+        current = match format {
+            BackupFormat::Sqlite | BackupFormat::Bundle => return Ok((current, layers)),
+            _ if layers.len() >= 2 => return Err("Unrecognized backup file format".to_string()),
+            BackupFormat::Gzip => {
+                let mut out = Vec::new();
+                flate2::read::GzDecoder::new(current.as_slice())
+                    .read_to_end(&mut out)
+                    .map_err(|e| format!("Failed to decompress backup: {}", e))?;
+                out
+            }
+            BackupFormat::Zstd => zstd::decode_all(current.as_slice())
+                .map_err(|e| format!("Failed to decompress backup: {}", e))?,
+            BackupFormat::Encrypted => {
+                let passphrase = passphrase
+                    .filter(|p| !p.is_empty())
+                    .ok_or_else(|| "Backup is encrypted; a passphrase is required".to_string())?;
+                let header = ENCRYPTED_MAGIC.len() + SALT_LEN + NONCE_LEN;
+                if current.len() < header {
+                    return Err("Encrypted backup is truncated".to_string());
+                }
+                let salt = &current[ENCRYPTED_MAGIC.len()..ENCRYPTED_MAGIC.len() + SALT_LEN];
+                let nonce = Nonce::from_slice(&current[ENCRYPTED_MAGIC.len() + SALT_LEN..header]);
+                let key = derive_key(passphrase, salt);
+                let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+                cipher
+                    .decrypt(nonce, &current[header..])
+                    .map_err(|_| "Failed to decrypt backup: wrong passphrase or corrupted file".to_string())?
+            }
+            BackupFormat::Unknown => return Err("Unrecognized backup file format".to_string()),
+        };
+        layers.push(format);
+    }
+}
+
+/// Decrypt and decompress a backup, returning the SQLite database or bundle bytes
+pub fn decode_backup(data: &[u8], passphrase: Option<&str>) -> Result<Vec<u8>, String> {
+    unwrap_backup(data, passphrase).map(|(payload, _)| payload)
+}
+
+fn append_bundle_entry(
+    builder: &mut tar::Builder<Vec<u8>>,
+    name: &str,
+    data: &[u8],
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Local::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, name, data)
+        .map_err(|e| format!("Failed to write backup bundle: {}", e))
+}
+
+/// Pack the database, settings files, and manifest into a tar bundle
+pub fn build_bundle(
+    database: &[u8],
+    manifest: &BundleManifest,
+    settings: &[(String, Vec<u8>)],
+) -> Result<Vec<u8>, String> {
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+    let mut builder = tar::Builder::new(Vec::new());
+    append_bundle_entry(&mut builder, BUNDLE_MANIFEST_ENTRY, &manifest_json)?;
+    append_bundle_entry(&mut builder, BUNDLE_DB_ENTRY, database)?;
+    for (name, data) in settings {
+        append_bundle_entry(&mut builder, &format!("{}{}", BUNDLE_SETTINGS_DIR, name), data)?;
+    }
+    builder
+        .into_inner()
+        .map_err(|e| format!("Failed to write backup bundle: {}", e))
+}
+
+/// Unpack a decoded backup. Plain SQLite payloads are treated as legacy backups.
+pub fn read_bundle(data: &[u8]) -> Result<BackupBundle, String> {
+    match detect_backup_format(data) {
+        BackupFormat::Sqlite => {
+            return Ok(BackupBundle {
+                manifest: None,
+                database: data.to_vec(),
+                settings: Vec::new(),
+            })
+        }
+        BackupFormat::Bundle => {}
+        _ => return Err("Invalid backup file format".to_string()),
+    }
+
+    let mut manifest = None;
+    let mut database = None;
+    let mut settings = Vec::new();
+
+    let mut archive = tar::Archive::new(data);
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Failed to read backup bundle: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read backup bundle: {}", e))?;
+        let name = entry
+            .path()
+            .map(|p| p.to_string_lossy().to_string())
+            .map_err(|e| format!("Failed to read backup bundle: {}", e))?;
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .map_err(|e| format!("Failed to read backup bundle: {}", e))?;
+
+        if name == BUNDLE_MANIFEST_ENTRY {
+            manifest = Some(
+                serde_json::from_slice::<BundleManifest>(&contents)
+                    .map_err(|e| format!("Invalid backup manifest: {}", e))?,
+            );
+        } else if name == BUNDLE_DB_ENTRY {
+            database = Some(contents);
+        } else if let Some(file) = name.strip_prefix(BUNDLE_SETTINGS_DIR) {
+            // Only accept known settings files so a bundle cannot write arbitrary paths
+            if settings::backup_files().any(|name| name == file) {
+                settings.push((file.to_string(), contents));
+            }
+        }
+    }
+
+    let database = database.ok_or_else(|| "Backup bundle does not contain a database".to_string())?;
+    if detect_backup_format(&database) != BackupFormat::Sqlite {
+        return Err("Backup bundle database is not a SQLite file".to_string());
+    }
+
+    Ok(BackupBundle {
+        manifest,
+        database,
+        settings,
+    })
+}
+
+#[derive(QueryableByName)]
+struct IntegrityCheckRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    integrity_check: String,
+}
+
+#[derive(QueryableByName)]
+struct TableNameRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct MigrationVersionRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    version: String,
+}
+
+fn inspect_connection(conn: &mut SqliteConnection) -> QueryResult<DatabaseReport> {
+    let problems: Vec<String> = diesel::sql_query("PRAGMA integrity_check")
+        .load::<IntegrityCheckRow>(conn)?
+        .into_iter()
+        .map(|row| row.integrity_check)
+        .collect();
+
+    let schema_version = diesel::sql_query(
+        "SELECT version FROM __diesel_schema_migrations ORDER BY version DESC LIMIT 1",
+    )
+    .get_result::<MigrationVersionRow>(conn)
+    .ok()
+    .map(|row| row.version);
+
+    let tables: Vec<String> = diesel::sql_query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' \
+         AND name NOT LIKE 'sqlite_%' AND name != '__diesel_schema_migrations' ORDER BY name",
+    )
+    .load::<TableNameRow>(conn)?
+    .into_iter()
+    .map(|row| row.name)
+    .collect();
+
+    let mut row_counts = Vec::with_capacity(tables.len());
+    for table in tables {
+        let count = diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM \"{}\"",
+            table.replace('"', "\"\"")
+        ))
+        .get_result::<CountRow>(conn)?
+        .count;
+        row_counts.push(TableRowCount { table, rows: count });
+    }
+
+    Ok(DatabaseReport {
+        integrity: problems.join("; "),
+        schema_version,
+        row_counts,
+    })
+}
+
+/// Open a copy of a backup's database and check integrity, schema version, and row counts
+pub fn inspect_database(database: &[u8]) -> Result<DatabaseReport, String> {
+    let tmp = std::env::temp_dir().join(format!("astra_verify_{}.db", uuid::Uuid::new_v4()));
+    fs::write(&tmp, database).map_err(|e| format!("Failed to stage backup database: {}", e))?;
+
+    let report = SqliteConnection::establish(&tmp.to_string_lossy())
+        .map_err(|e| format!("Failed to open backup database: {}", e))
+        .and_then(|mut conn| {
+            inspect_connection(&mut conn).map_err(|e| format!("Failed to inspect backup database: {}", e))
+        });
+
+    let _ = fs::remove_file(&tmp);
+    report
+}
+
+/// Read settings files from the app data directory for bundling
+fn collect_settings(app_data_dir: &Path) -> Vec<(String, Vec<u8>)> {
+    settings::backup_files()
+        .filter_map(|name| {
+            fs::read(app_data_dir.join(name))
+                .ok()
+                .map(|data| (name.to_string(), data))
+        })
+        .collect()
+}
+
+/// List the cached preview images (the cache itself can be regenerated)
+fn collect_thumbnail_manifest(previews_dir: &Path) -> Vec<ThumbnailEntry> {
+    let mut thumbnails: Vec<ThumbnailEntry> = fs::read_dir(previews_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+                    Some(ThumbnailEntry {
+                        file: entry.file_name().to_string_lossy().to_string(),
+                        size_bytes: metadata.len(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    thumbnails.sort_by(|a, b| a.file.cmp(&b.file));
+    thumbnails
+}
+
+/// Encode a database or bundle and write it to `dest`
+fn write_backup(
+    data: &[u8],
+    dest: &Path,
+    compression: Option<&str>,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    let encoded = encode_backup(data, compression, passphrase)?;
+    fs::write(dest, encoded).map_err(|e| format!("Failed to write backup: {}", e))
+}
+
 /// Get the backup directory path
 fn get_backup_dir(app: &AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app
@@ -52,19 +500,41 @@ fn get_backup_dir(app: &AppHandle) -> Result<PathBuf, String> {
 
 /// Get the database path
 fn get_db_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(get_app_data_dir(app)?.join("astra.db"))
+}
 
-    Ok(app_data_dir.join("astra.db"))
+fn get_app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
 }
 
-/// Create a backup of the database
+/// Create a backup bundle of the database, settings, and thumbnail manifest.
+/// `compression` is "none" (default), "gzip", or "zstd"; a non-empty
+/// `passphrase` encrypts the backup with AES-256-GCM.
 #[tauri::command]
-pub fn create_backup(app: AppHandle) -> Result<BackupResult, String> {
-    let db_path = get_db_path(&app)?;
-    let backup_dir = get_backup_dir(&app)?;
+pub fn create_backup(
+    app: AppHandle,
+    compression: Option<String>,
+    passphrase: Option<String>,
+) -> Result<BackupResult, String> {
+    create_backup_in(
+        &get_app_data_dir(&app)?,
+        compression.as_deref(),
+        passphrase.as_deref(),
+    )
+}
+
+/// Create a backup bundle of the library in `app_data_dir`, in its backups/
+pub(crate) fn create_backup_in(
+    app_data_dir: &Path,
+    compression: Option<&str>,
+    passphrase: Option<&str>,
+) -> Result<BackupResult, String> {
+    let db_path = app_data_dir.join("astra.db");
+    let backup_dir = app_data_dir.join("backups");
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
 
     // Check if database exists
     if !db_path.exists() {
@@ -77,12 +547,23 @@ pub fn create_backup(app: AppHandle) -> Result<BackupResult, String> {
 
     // Generate backup filename with timestamp
     let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let backup_filename = format!("astra_backup_{}.db", timestamp);
+    let encrypted = passphrase.is_some_and(|p| !p.is_empty());
+    let extension = backup_extension(compression, encrypted)?;
+    let backup_filename = format!("astra_backup_{}.{}", timestamp, extension);
     let backup_path = backup_dir.join(&backup_filename);
 
-    // Copy database file to backup location
-    fs::copy(&db_path, &backup_path)
-        .map_err(|e| format!("Failed to create backup: {}", e))?;
+    let database = fs::read(&db_path).map_err(|e| format!("Failed to read database: {}", e))?;
+    let settings = collect_settings(app_data_dir);
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Local::now().to_rfc3339(),
+        settings: settings.iter().map(|(name, _)| name.clone()).collect(),
+        thumbnails: collect_thumbnail_manifest(&app_data_dir.join("previews")),
+    };
+    let bundle = build_bundle(&database, &manifest, &settings)?;
+
+    write_backup(&bundle, &backup_path, compression, passphrase)?;
 
     // Get file metadata
     let metadata = fs::metadata(&backup_path)
@@ -112,7 +593,7 @@ pub fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
     if let Ok(entries) = fs::read_dir(&backup_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "db") {
+            if is_backup_file(&path) {
                 if let Ok(metadata) = fs::metadata(&path) {
                     let filename = path.file_name()
                         .map(|n| n.to_string_lossy().to_string())
@@ -142,9 +623,16 @@ pub fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
     Ok(backups)
 }
 
-/// Restore database from a backup
+/// Restore database (and bundled settings) from a backup. Compressed and
+/// encrypted backups are detected automatically; encrypted ones need the
+/// `passphrase`. The database is integrity-checked before anything is replaced
+/// and swapped in live, so no restart is needed.
 #[tauri::command]
-pub fn restore_backup(app: AppHandle, backup_path: String) -> Result<RestoreResult, String> {
+pub fn restore_backup(
+    app: AppHandle,
+    backup_path: String,
+    passphrase: Option<String>,
+) -> Result<RestoreResult, String> {
     let db_path = get_db_path(&app)?;
     let backup_file = PathBuf::from(&backup_path);
 
@@ -156,11 +644,17 @@ pub fn restore_backup(app: AppHandle, backup_path: String) -> Result<RestoreResu
         });
     }
 
-    // Validate it's a .db file
-    if backup_file.extension().map_or(true, |ext| ext != "db") {
+    let raw = fs::read(&backup_file).map_err(|e| format!("Failed to read backup: {}", e))?;
+    let bundle = match decode_backup(&raw, passphrase.as_deref()).and_then(|data| read_bundle(&data)) {
+        Ok(bundle) => bundle,
+        Err(message) => return Ok(RestoreResult { success: false, message }),
+    };
+
+    let report = inspect_database(&bundle.database)?;
+    if report.integrity != "ok" {
         return Ok(RestoreResult {
             success: false,
-            message: "Invalid backup file format".to_string(),
+            message: format!("Backup failed integrity check: {}", report.integrity),
         });
     }
 
@@ -173,13 +667,49 @@ pub fn restore_backup(app: AppHandle, backup_path: String) -> Result<RestoreResu
             .map_err(|e| format!("Failed to backup current database: {}", e))?;
     }
 
-    // Copy backup to database location
-    fs::copy(&backup_file, &db_path)
+    // Stage the decoded database next to the live one, then swap it in and
+    // reconnect the pool so the restore takes effect without a restart
+    let staged_path = db_path.with_extension("db.restoring");
+    fs::write(&staged_path, &bundle.database)
         .map_err(|e| format!("Failed to restore backup: {}", e))?;
+    let state = app.state::<AppState>();
+    if let Err(e) = crate::db::hot_swap_database(&state.db, &db_path, &staged_path) {
+        let _ = fs::remove_file(&staged_path);
+        return Ok(RestoreResult {
+            success: false,
+            message: format!("Failed to restore backup: {}", e),
+        });
+    }
+
+    let app_data_dir = get_app_data_dir(&app)?;
+    for (name, data) in &bundle.settings {
+        fs::write(app_data_dir.join(name), data)
+            .map_err(|e| format!("Failed to restore {}: {}", name, e))?;
+    }
+
+    // Check whether image files are reachable from this machine and let the
+    // frontend offer remap suggestions
+    let db = state.db.clone();
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        let check = db
+            .get()
+            .and_then(|mut conn| super::path_remap::check_paths(&mut conn, &app_data_dir, &[]));
+        match check {
+            Ok(result) => {
+                let _ = app_handle.emit("restore-path-check", &result);
+            }
+            Err(e) => log::warn!("Post-restore path check failed: {}", e),
+        }
+    });
 
     Ok(RestoreResult {
         success: true,
-        message: format!("Database restored from backup. Previous database saved as {:?}", pre_restore_backup.file_name()),
+        message: format!(
+            "Database restored from backup ({} settings files). Previous database saved as {:?}",
+            bundle.settings.len(),
+            pre_restore_backup.file_name()
+        ),
     })
 }
 
@@ -197,8 +727,8 @@ pub fn delete_backup(app: AppHandle, backup_path: String) -> Result<RestoreResul
         });
     }
 
-    // Validate it's a .db file
-    if backup_file.extension().map_or(true, |ext| ext != "db") {
+    // Validate it's a backup file
+    if !is_backup_file(&backup_file) {
         return Ok(RestoreResult {
             success: false,
             message: "Invalid backup file format".to_string(),
@@ -221,9 +751,14 @@ pub fn delete_backup(app: AppHandle, backup_path: String) -> Result<RestoreResul
     })
 }
 
-/// Export database to a custom location
+/// Export database to a custom location, optionally compressed and encrypted
 #[tauri::command]
-pub fn export_database(app: AppHandle, export_path: String) -> Result<BackupResult, String> {
+pub fn export_database(
+    app: AppHandle,
+    export_path: String,
+    compression: Option<String>,
+    passphrase: Option<String>,
+) -> Result<BackupResult, String> {
     let db_path = get_db_path(&app)?;
     let export_file = PathBuf::from(&export_path);
 
@@ -236,8 +771,8 @@ pub fn export_database(app: AppHandle, export_path: String) -> Result<BackupResu
         });
     }
 
-    // Copy database file to export location
-    fs::copy(&db_path, &export_file)
+    let database = fs::read(&db_path).map_err(|e| format!("Failed to read database: {}", e))?;
+    write_backup(&database, &export_file, compression.as_deref(), passphrase.as_deref())
         .map_err(|e| format!("Failed to export database: {}", e))?;
 
     // Get file metadata
@@ -262,8 +797,92 @@ pub fn export_database(app: AppHandle, export_path: String) -> Result<BackupResu
 
 /// Import database from a custom location
 #[tauri::command]
-pub fn import_database(app: AppHandle, import_path: String) -> Result<RestoreResult, String> {
-    restore_backup(app, import_path)
+pub fn import_database(
+    app: AppHandle,
+    import_path: String,
+    passphrase: Option<String>,
+) -> Result<RestoreResult, String> {
+    restore_backup(app, import_path, passphrase)
+}
+
+/// Open a backup and check it before trusting it for restore: decodes the
+/// archive, runs a SQLite integrity check, and reports the schema version,
+/// per-table row counts, and bundle manifest.
+#[tauri::command]
+pub fn verify_backup(
+    app: AppHandle,
+    backup_path: String,
+    passphrase: Option<String>,
+) -> Result<BackupVerification, String> {
+    let failed = |message: String| BackupVerification {
+        valid: false,
+        message,
+        format: None,
+        compressed: false,
+        encrypted: false,
+        manifest: None,
+        database: None,
+        missing_thumbnails: 0,
+    };
+
+    let raw = match fs::read(&backup_path) {
+        Ok(raw) => raw,
+        Err(e) => return Ok(failed(format!("Failed to read backup: {}", e))),
+    };
+    let (payload, layers) = match unwrap_backup(&raw, passphrase.as_deref()) {
+        Ok(decoded) => decoded,
+        Err(message) => return Ok(failed(message)),
+    };
+    let format = match detect_backup_format(&payload) {
+        BackupFormat::Bundle => "bundle",
+        _ => "database",
+    };
+    let bundle = match read_bundle(&payload) {
+        Ok(bundle) => bundle,
+        Err(message) => return Ok(failed(message)),
+    };
+    let report = match inspect_database(&bundle.database) {
+        Ok(report) => report,
+        Err(message) => return Ok(failed(message)),
+    };
+
+    let previews_dir = get_app_data_dir(&app)?.join("previews");
+    let missing_thumbnails = bundle
+        .manifest
+        .as_ref()
+        .map(|m| {
+            m.thumbnails
+                .iter()
+                .filter(|t| !previews_dir.join(&t.file).exists())
+                .count()
+        })
+        .unwrap_or(0);
+
+    let valid = report.integrity == "ok";
+    let message = if valid {
+        let rows: i64 = report.row_counts.iter().map(|t| t.rows).sum();
+        format!(
+            "Backup is valid: {} tables, {} rows, schema {}",
+            report.row_counts.len(),
+            rows,
+            report.schema_version.as_deref().unwrap_or("unknown")
+        )
+    } else {
+        format!("Integrity check failed: {}", report.integrity)
+    };
+
+    Ok(BackupVerification {
+        valid,
+        message,
+        format: Some(format.to_string()),
+        compressed: layers
+            .iter()
+            .any(|l| matches!(l, BackupFormat::Gzip | BackupFormat::Zstd)),
+        encrypted: layers.contains(&BackupFormat::Encrypted),
+        manifest: bundle.manifest,
+        database: Some(report),
+        missing_thumbnails,
+    })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -278,6 +897,17 @@ pub struct RemapResult {
     pub urls_updated: i64,
     pub fits_urls_updated: i64,
     pub message: String,
+    /// True when the remap was only previewed
+    pub dry_run: bool,
+    /// Sample of the paths that change
+    pub preview: Vec<RemapPreviewItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemapPreviewItem {
+    pub image_id: String,
+    pub old_path: String,
+    pub new_path: String,
 }
 
 /// Get common path prefixes from image URLs in the database.
@@ -345,74 +975,138 @@ pub fn get_image_path_prefixes(state: State<'_, AppState>) -> Result<Vec<PathPre
 /// Remap image file paths in the database.
 /// Replaces old_prefix with new_prefix in both url and fits_url fields.
 /// This is needed when restoring a backup from another computer where
-/// image files are stored at a different path.
+/// image files are stored at a different path. With `dry_run` the changes
+/// are only previewed; applied remaps are saved as reusable presets.
 #[tauri::command]
 pub fn remap_image_paths(
+    app: AppHandle,
     state: State<'_, AppState>,
     old_prefix: String,
     new_prefix: String,
+    dry_run: Option<bool>,
 ) -> Result<RemapResult, String> {
-    use crate::db::schema::images::dsl::*;
-
     if old_prefix.is_empty() {
         return Err("Old prefix cannot be empty".to_string());
     }
 
+    let dry_run = dry_run.unwrap_or(false);
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let result = super::path_remap::apply_path_remap(&mut conn, &old_prefix, &new_prefix, dry_run)
+        .map_err(|e| format!("Failed to remap image paths: {}", e))?;
 
-    // Get all images with URLs that start with old_prefix
-    let matching_images: Vec<(String, Option<String>, Option<String>)> = images
-        .select((id, url, fits_url))
-        .filter(url.like(format!("{}%", old_prefix)))
-        .or_filter(fits_url.like(format!("{}%", old_prefix)))
-        .load(&mut conn)
-        .map_err(|e| format!("Failed to query images: {}", e))?;
+    if !dry_run && (result.urls_updated > 0 || result.fits_urls_updated > 0) {
+        super::path_remap::record_applied_remap(&get_app_data_dir(&app)?, &old_prefix, &new_prefix)?;
+    }
 
-    let mut urls_updated: i64 = 0;
-    let mut fits_updated: i64 = 0;
+    Ok(result)
+}
 
-    for (img_id, img_url, img_fits) in &matching_images {
-        let mut update_url: Option<String> = None;
-        let mut update_fits: Option<String> = None;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if let Some(u) = img_url {
-            if u.starts_with(&old_prefix) {
-                update_url = Some(format!("{}{}", new_prefix, &u[old_prefix.len()..]));
-                urls_updated += 1;
-            }
-        }
+    fn fake_db() -> Vec<u8> {
+        let mut data = SQLITE_MAGIC.to_vec();
+        data.extend_from_slice(&[b'x'; 4096]);
+        data
+    }
 
-        if let Some(f) = img_fits {
-            if f.starts_with(&old_prefix) {
-                update_fits = Some(format!("{}{}", new_prefix, &f[old_prefix.len()..]));
-                fits_updated += 1;
-            }
+    #[test]
+    fn compressed_backups_round_trip() {
+        let db = fake_db();
+        for compression in ["none", "gzip", "zstd"] {
+            let encoded = encode_backup(&db, Some(compression), None).unwrap();
+            assert_eq!(decode_backup(&encoded, None).unwrap(), db, "{}", compression);
         }
+        let zst = encode_backup(&db, Some("zstd"), None).unwrap();
+        assert_eq!(detect_backup_format(&zst), BackupFormat::Zstd);
+        assert!(zst.len() < db.len());
+    }
 
-        if update_url.is_some() || update_fits.is_some() {
-            let target = images.filter(id.eq(img_id));
-            if let Some(new_url) = update_url {
-                diesel::update(target.clone())
-                    .set(url.eq(new_url))
-                    .execute(&mut conn)
-                    .map_err(|e| format!("Failed to update image URL: {}", e))?;
-            }
-            if let Some(new_fits) = update_fits {
-                diesel::update(target)
-                    .set(fits_url.eq(new_fits))
-                    .execute(&mut conn)
-                    .map_err(|e| format!("Failed to update FITS URL: {}", e))?;
-            }
+    #[test]
+    fn encrypted_backup_requires_passphrase() {
+        let db = fake_db();
+        let encoded = encode_backup(&db, Some("zstd"), Some("dark skies")).unwrap();
+        assert_eq!(detect_backup_format(&encoded), BackupFormat::Encrypted);
+        assert_eq!(decode_backup(&encoded, Some("dark skies")).unwrap(), db);
+        assert!(decode_backup(&encoded, None).is_err());
+        assert!(decode_backup(&encoded, Some("light pollution")).is_err());
+    }
+
+    #[test]
+    fn backup_extension_and_detection() {
+        assert_eq!(backup_extension(None, false).unwrap(), "tar");
+        assert_eq!(backup_extension(Some("zstd"), true).unwrap(), "tar.zst.enc");
+        assert!(backup_extension(Some("lz4"), false).is_err());
+        assert!(is_backup_file(Path::new("/b/astra_backup_1.db.gz")));
+        assert!(!is_backup_file(Path::new("/b/notes.txt")));
+        assert!(decode_backup(b"not a backup", None).is_err());
+    }
+
+    fn sample_manifest() -> BundleManifest {
+        BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            app_version: "test".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            settings: vec!["share-config.json".to_string()],
+            thumbnails: vec![ThumbnailEntry {
+                file: "abc.jpg".to_string(),
+                size_bytes: 10,
+            }],
         }
     }
 
-    Ok(RemapResult {
-        success: true,
-        urls_updated,
-        fits_urls_updated: fits_updated,
-        message: format!(
-            "Remapped {} image URLs and {} FITS URLs from '{}' to '{}'",
-            urls_updated, fits_updated, old_prefix, new_prefix
-        ),
-    })
+    #[test]
+    fn bundle_round_trips_through_compression() {
+        let db = fake_db();
+        let settings = vec![("share-config.json".to_string(), b"{}".to_vec())];
+        let bundle = build_bundle(&db, &sample_manifest(), &settings).unwrap();
+        assert_eq!(detect_backup_format(&bundle), BackupFormat::Bundle);
+
+        let encoded = encode_backup(&bundle, Some("gzip"), Some("pw")).unwrap();
+        let (payload, layers) = unwrap_backup(&encoded, Some("pw")).unwrap();
+        assert_eq!(layers, vec![BackupFormat::Encrypted, BackupFormat::Gzip]);
+
+        let restored = read_bundle(&payload).unwrap();
+        assert_eq!(restored.database, db);
+        assert_eq!(restored.settings, settings);
+        assert_eq!(restored.manifest.unwrap().thumbnails[0].file, "abc.jpg");
+    }
+
+    #[test]
+    fn read_bundle_accepts_legacy_db_and_ignores_unknown_settings() {
+        let db = fake_db();
+        let legacy = read_bundle(&db).unwrap();
+        assert!(legacy.manifest.is_none());
+        assert_eq!(legacy.database, db);
+
+        let settings = vec![("credentials.json".to_string(), b"x".to_vec())];
+        let bundle = build_bundle(&db, &sample_manifest(), &settings).unwrap();
+        assert!(read_bundle(&bundle).unwrap().settings.is_empty());
+    }
+
+    #[test]
+    fn inspect_database_reports_schema_and_counts() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("astra.db");
+        let mut conn = SqliteConnection::establish(&path.to_string_lossy()).unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        diesel::sql_query(
+            "INSERT INTO collections (id, user_id, name) VALUES ('c1', 'local-user', 'Session')",
+        )
+        .execute(&mut conn)
+        .unwrap();
+        drop(conn);
+
+        let report = inspect_database(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(report.integrity, "ok");
+        assert!(report.schema_version.is_some());
+        let collections = report
+            .row_counts
+            .iter()
+            .find(|t| t.table == "collections")
+            .unwrap();
+        assert_eq!(collections.rows, 1);
+        assert!(report.row_counts.iter().all(|t| t.table != "__diesel_schema_migrations"));
+    }
 }
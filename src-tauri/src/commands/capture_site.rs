@@ -0,0 +1,318 @@
+//! Capture site: where on Earth an image was taken
+//!
+//! The `location` column holds the field's RA/Dec; the capture site is the
+//! observer's. At import it's taken from the SITELAT/SITELONG headers when
+//! the capture software wrote them (matched to a saved location nearby),
+//! else from the location named by the night's active schedule, else the
+//! default location. The coordinates are stored alongside the location id
+//! so images keep their site if the location is deleted.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{Image, Location};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::dark_sites::distance_km;
+use super::scan::{image_session_date, metadata_number_value, parse_longitude};
+
+/// Header coordinates within this many km of a saved location are taken
+/// to be that location (GPS fixes and typed-in coordinates differ a little)
+const SITE_MATCH_KM: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSite {
+    /// Saved location, when one matched
+    pub location_id: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl CaptureSite {
+    fn at(location: &Location) -> Self {
+        CaptureSite {
+            location_id: Some(location.id.clone()),
+            latitude: location.latitude,
+            longitude: location.longitude,
+        }
+    }
+}
+
+/// Observer position from an image's SITELAT/SITELONG headers
+fn header_site(image: &Image) -> Option<(f64, f64)> {
+    let meta = image.metadata.as_deref()?;
+    let latitude = metadata_number_value(meta, "site_latitude", &["SITELAT"])
+        .and_then(|v| parse_longitude(&v))
+        .filter(|lat| lat.abs() <= 90.0)?;
+    let longitude = metadata_number_value(meta, "site_longitude", &["SITELONG"])
+        .and_then(|v| parse_longitude(&v))
+        .filter(|lon| lon.abs() <= 180.0)?;
+    Some((latitude, longitude))
+}
+
+/// A user's saved locations and the ones their active schedules name,
+/// loaded once per import
+#[derive(Debug, Default)]
+pub struct CaptureSites {
+    /// Saved locations, leaving out dark site suggestions
+    locations: Vec<Location>,
+    /// Location named by the active schedule of each night
+    scheduled: HashMap<NaiveDate, String>,
+}
+
+impl CaptureSites {
+    pub fn load(conn: &mut SqliteConnection, user_id: &str) -> Result<Self, String> {
+        let locations: Vec<Location> = repository::get_locations(conn, user_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|l| l.source != "candidate")
+            .collect();
+        let schedules =
+            repository::get_active_schedules(conn, user_id).map_err(|e| e.to_string())?;
+        let mut sites = CaptureSites {
+            locations,
+            scheduled: HashMap::new(),
+        };
+        for schedule in schedules {
+            let night = schedule
+                .scheduled_date
+                .as_deref()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            let location = schedule.location.as_deref().and_then(|l| sites.named(l));
+            if let Some((night, location)) = night.zip(location) {
+                let id = location.id.clone();
+                sites.scheduled.entry(night).or_insert(id);
+            }
+        }
+        Ok(sites)
+    }
+
+    /// Saved location by id or (case-insensitive) name
+    fn named(&self, name: &str) -> Option<&Location> {
+        let name = name.trim();
+        self.locations
+            .iter()
+            .find(|l| l.id == name || l.name.trim().eq_ignore_ascii_case(name))
+    }
+
+    fn by_id(&self, id: &str) -> Option<&Location> {
+        self.locations.iter().find(|l| l.id == id)
+    }
+
+    fn default_location(&self) -> Option<&Location> {
+        self.locations.iter().find(|l| l.is_default)
+    }
+
+    /// Closest saved location within `SITE_MATCH_KM`
+    fn nearest(&self, latitude: f64, longitude: f64) -> Option<&Location> {
+        self.locations
+            .iter()
+            .map(|l| (distance_km(latitude, longitude, l.latitude, l.longitude), l))
+            .filter(|(km, _)| *km <= SITE_MATCH_KM)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, l)| l)
+    }
+
+    /// Where an image was taken: its headers, then the night's active
+    /// schedule, then the default location
+    pub fn site_for(&self, image: &Image) -> Option<CaptureSite> {
+        if let Some((latitude, longitude)) = header_site(image) {
+            return Some(match self.nearest(latitude, longitude) {
+                Some(location) => CaptureSite {
+                    location_id: Some(location.id.clone()),
+                    latitude,
+                    longitude,
+                },
+                None => CaptureSite {
+                    location_id: None,
+                    latitude,
+                    longitude,
+                },
+            });
+        }
+        let default = self.default_location();
+        let scheduled = image_session_date(image, default.map(|l| l.longitude))
+            .and_then(|night| self.scheduled.get(&night))
+            .and_then(|id| self.by_id(id));
+        scheduled.or(default).map(CaptureSite::at)
+    }
+}
+
+/// Resolve and store an imported image's capture site; returns it
+pub fn tag_capture_site(
+    conn: &mut SqliteConnection,
+    sites: &CaptureSites,
+    image: &Image,
+) -> Result<Option<CaptureSite>, String> {
+    let Some(site) = sites.site_for(image) else {
+        return Ok(None);
+    };
+    repository::set_image_capture_site(
+        conn,
+        &image.user_id,
+        std::slice::from_ref(&image.id),
+        site.location_id.as_deref(),
+        Some(site.latitude),
+        Some(site.longitude),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(Some(site))
+}
+
+/// Set where images were taken: a saved location, or coordinates for a site
+/// that isn't saved. Neither clears the capture site.
+#[tauri::command]
+pub fn set_image_capture_site(
+    state: State<'_, AppState>,
+    image_ids: Vec<String>,
+    location_id: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let (latitude, longitude) = match &location_id {
+        Some(id) => {
+            let location = repository::get_location_by_id(&mut conn, id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Location not found: {}", id))?;
+            (Some(location.latitude), Some(location.longitude))
+        }
+        None => match latitude.zip(longitude) {
+            Some((lat, lon)) if lat.abs() > 90.0 || lon.abs() > 180.0 => {
+                return Err(format!("Invalid coordinates: {}, {}", lat, lon));
+            }
+            Some((lat, lon)) => (Some(lat), Some(lon)),
+            None => (None, None),
+        },
+    };
+    repository::set_image_capture_site(
+        &mut conn,
+        &state.user_id,
+        &image_ids,
+        location_id.as_deref(),
+        latitude,
+        longitude,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Tag the capture site of library images imported before it was recorded
+/// (or of every image with `overwrite`). Returns the number tagged.
+#[tauri::command]
+pub async fn backfill_capture_sites(
+    state: State<'_, AppState>,
+    overwrite: Option<bool>,
+) -> Result<usize, String> {
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+    let overwrite = overwrite.unwrap_or(false);
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        let sites = CaptureSites::load(&mut conn, &user_id)?;
+        let images =
+            repository::get_images_by_user(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        let mut tagged = 0;
+        for image in images {
+            if !overwrite && (image.capture_site_id.is_some() || image.capture_latitude.is_some()) {
+                continue;
+            }
+            if tag_capture_site(&mut conn, &sites, &image)?.is_some() {
+                tagged += 1;
+            }
+        }
+        log::info!("Tagged the capture site of {} images", tagged);
+        Ok(tagged)
+    })
+    .await
+    .map_err(|e| format!("Capture site backfill failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(id: &str, latitude: f64, longitude: f64, is_default: bool) -> Location {
+        let now = chrono::Utc::now().naive_utc();
+        Location {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            name: id.to_uppercase(),
+            latitude,
+            longitude,
+            elevation: None,
+            bortle: None,
+            source: "manual".to_string(),
+            is_default,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn image(date_obs: &str, metadata: Option<&str>) -> Image {
+        Image {
+            filename: "Light_M 42_1.fit".to_string(),
+            metadata: metadata.map(String::from),
+            date_obs: Some(date_obs.to_string()),
+            ..Image::test("img")
+        }
+    }
+
+    fn sites() -> CaptureSites {
+        let mut sites = CaptureSites {
+            locations: vec![
+                location("home", 51.50, -0.10, true),
+                location("club", 51.20, 0.30, false),
+            ],
+            scheduled: HashMap::new(),
+        };
+        sites.scheduled.insert(
+            NaiveDate::from_ymd_opt(2025, 1, 10).unwrap(),
+            "club".to_string(),
+        );
+        sites
+    }
+
+    #[test]
+    fn headers_match_a_nearby_saved_location() {
+        let near_club = r#"{"raw_headers":{"SITELAT":"Some(RealFloatingNumber(51.21))","SITELONG":"Some(RealFloatingNumber(0.31))"}}"#;
+        let site = sites()
+            .site_for(&image("2025-03-01T21:00:00", Some(near_club)))
+            .unwrap();
+        assert_eq!(site.location_id.as_deref(), Some("club"));
+        assert_eq!(site.latitude, 51.21);
+
+        let abroad = r#"{"raw_headers":{"SITELAT":"'28 45 36'","SITELONG":"'-17 53 24'"}}"#;
+        let site = sites()
+            .site_for(&image("2025-03-01T21:00:00", Some(abroad)))
+            .unwrap();
+        assert_eq!(site.location_id, None);
+        assert!((site.latitude - 28.76).abs() < 1e-9);
+        assert!((site.longitude + 17.89).abs() < 1e-9);
+    }
+
+    #[test]
+    fn without_headers_the_schedule_then_the_default_applies() {
+        let sites = sites();
+        let scheduled = sites.site_for(&image("2025-01-10T22:00:00", None)).unwrap();
+        assert_eq!(scheduled.location_id.as_deref(), Some("club"));
+        // After midnight still belongs to the scheduled night
+        let late = sites.site_for(&image("2025-01-11T02:00:00", None)).unwrap();
+        assert_eq!(late.location_id.as_deref(), Some("club"));
+
+        let other = sites.site_for(&image("2025-02-01T22:00:00", None)).unwrap();
+        assert_eq!(other.location_id.as_deref(), Some("home"));
+        assert_eq!((other.latitude, other.longitude), (51.50, -0.10));
+
+        assert!(CaptureSites::default()
+            .site_for(&image("2025-02-01T22:00:00", None))
+            .is_none());
+        assert_eq!(sites.named("Club").map(|l| l.id.as_str()), Some("club"));
+    }
+}
@@ -0,0 +1,498 @@
+//! Export a collection's original files to a folder, e.g. to hand a dataset
+//! to a stacking workstation
+//!
+//! Files are laid out as `<collection>/<object>/<night>/<filter>/` under the
+//! destination, each image with a JSON sidecar holding its library metadata.
+//! Files already exported with the same size are left alone, so an
+//! interrupted export can simply be run again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::filters::normalize_filter_name;
+use super::scan::{metadata_header_value, session_date_from_metadata, CollectProgress};
+
+/// Global cancellation flag for collection file exports
+static EXPORT_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+const PROGRESS_EVENT: &str = "collection-files-progress";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionFilesOptions {
+    /// Hard-link files instead of copying them. Falls back to copying when
+    /// the destination is on another filesystem.
+    pub hard_link: Option<bool>,
+    /// Only export FITS files, leaving out JPEGs
+    pub fits_only: Option<bool>,
+    /// Write a JSON sidecar per image (default true)
+    pub sidecars: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionFilesResult {
+    /// Folder the collection was exported into
+    pub folder: String,
+    pub files_copied: usize,
+    pub files_linked: usize,
+    /// Files already in the destination from an earlier export
+    pub files_skipped: usize,
+    pub bytes_copied: u64,
+    /// Missing source files and copy failures
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Library metadata written next to an image's files
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileSidecar<'a> {
+    id: &'a str,
+    filename: &'a str,
+    /// Names of the exported files in this folder
+    files: Vec<String>,
+    summary: Option<&'a str>,
+    description: Option<&'a str>,
+    tags: Vec<&'a str>,
+    rating: Option<i32>,
+    date_obs: Option<&'a str>,
+    exposure: Option<f64>,
+    gain: Option<i32>,
+    filter: Option<&'a str>,
+    telescope: Option<&'a str>,
+    wcs: Option<serde_json::Value>,
+    /// FITS headers and other metadata recorded at import
+    metadata: Option<serde_json::Value>,
+}
+
+/// Make a name safe to use as a file or folder name on any platform
+fn path_component(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.');
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Folder for an image under the collection folder: object, night, filter
+fn image_folder(image: &Image) -> PathBuf {
+    let meta = image.metadata.as_deref().unwrap_or("{}");
+    let object = image
+        .summary
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| metadata_header_value(meta, "object", "OBJECT"))
+        .unwrap_or_else(|| "Unknown object".to_string());
+    let night = session_date_from_metadata(meta, None)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "Undated".to_string());
+    let filter = image
+        .filter
+        .clone()
+        .or_else(|| metadata_header_value(meta, "filter", "FILTER"))
+        .and_then(|f| normalize_filter_name(&f))
+        .unwrap_or_else(|| "No filter".to_string());
+
+    [object, night, filter]
+        .iter()
+        .map(|part| path_component(part))
+        .collect()
+}
+
+/// Original files of an image on disk: the display file and the FITS file
+fn source_files(image: &Image, fits_only: bool) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for url in [image.url.as_deref(), image.fits_url.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            continue;
+        }
+        let path = PathBuf::from(url);
+        let is_fits = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e.to_lowercase().as_str(), "fit" | "fits" | "fts"));
+        if (is_fits || !fits_only) && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Where to put `file_name` in `folder`: the name itself unless a different
+/// file already has it, then "name (2).ext" and so on. Returns the path and
+/// whether an identical file (same size) is already there.
+fn destination_for(folder: &Path, file_name: &str, size: u64) -> (PathBuf, bool) {
+    let name = Path::new(file_name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|e| e.to_string_lossy());
+    for n in 1.. {
+        let candidate = match (n, &extension) {
+            (1, _) => file_name.to_string(),
+            (n, Some(ext)) => format!("{} ({}).{}", stem, n, ext),
+            (n, None) => format!("{} ({})", stem, n),
+        };
+        let path = folder.join(candidate);
+        match path.metadata() {
+            Ok(existing) if existing.len() == size => return (path, true),
+            Ok(_) => continue,
+            Err(_) => return (path, false),
+        }
+    }
+    unreachable!("ran out of file names")
+}
+
+fn write_sidecar(image: &Image, files: Vec<String>, path: &Path) -> Result<(), String> {
+    let parse = |value: Option<&str>| value.and_then(|v| serde_json::from_str(v).ok());
+    let sidecar = FileSidecar {
+        id: &image.id,
+        filename: &image.filename,
+        files,
+        summary: image.summary.as_deref(),
+        description: image.description.as_deref(),
+        tags: image
+            .tags
+            .as_deref()
+            .map(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        rating: image.rating,
+        date_obs: image.date_obs.as_deref(),
+        exposure: image.exposure,
+        gain: image.gain,
+        filter: image.filter.as_deref(),
+        telescope: image.telescope.as_deref(),
+        wcs: parse(image.wcs.as_deref()),
+        metadata: parse(image.metadata.as_deref()),
+    };
+    let json = serde_json::to_string_pretty(&sidecar).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Copy or link the images' files into `collection_folder`, reporting
+/// (done, total, file name) before each file. Stops between files once
+/// `cancelled` is set.
+fn export_files(
+    images: &[Image],
+    collection_folder: &Path,
+    options: &CollectionFilesOptions,
+    cancelled: &AtomicBool,
+    mut progress: impl FnMut(usize, usize, &str),
+) -> CollectionFilesResult {
+    let mut result = CollectionFilesResult {
+        folder: collection_folder.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    let fits_only = options.fits_only.unwrap_or(false);
+    let planned: Vec<(&Image, Vec<PathBuf>)> = images
+        .iter()
+        .map(|image| (image, source_files(image, fits_only)))
+        .filter(|(_, files)| !files.is_empty())
+        .collect();
+    let total: usize = planned.iter().map(|(_, files)| files.len()).sum();
+    let mut done = 0;
+
+    for (image, files) in planned {
+        let folder = collection_folder.join(image_folder(image));
+        if let Err(e) = fs::create_dir_all(&folder) {
+            result
+                .errors
+                .push(format!("Failed to create {}: {}", folder.display(), e));
+            done += files.len();
+            continue;
+        }
+
+        let mut exported = Vec::new();
+        for source in files {
+            if cancelled.load(Ordering::SeqCst) {
+                result.cancelled = true;
+                return result;
+            }
+            let file_name = source
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            progress(done, total, &file_name);
+            done += 1;
+
+            let Ok(size) = source.metadata().map(|m| m.len()) else {
+                result
+                    .errors
+                    .push(format!("Missing file: {}", source.display()));
+                continue;
+            };
+            let (dest, already_there) = destination_for(&folder, &file_name, size);
+            let name = dest
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if already_there {
+                result.files_skipped += 1;
+                exported.push(name);
+                continue;
+            }
+
+            if options.hard_link.unwrap_or(false) && fs::hard_link(&source, &dest).is_ok() {
+                result.files_linked += 1;
+                exported.push(name);
+                continue;
+            }
+            match fs::copy(&source, &dest) {
+                Ok(bytes) => {
+                    result.files_copied += 1;
+                    result.bytes_copied += bytes;
+                    exported.push(name);
+                }
+                Err(e) => result
+                    .errors
+                    .push(format!("Failed to copy {}: {}", source.display(), e)),
+            }
+        }
+
+        if options.sidecars.unwrap_or(true) {
+            if let Some(first) = exported.first() {
+                let stem = Path::new(first)
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                let path = folder.join(format!("{}.json", stem));
+                if let Err(e) = write_sidecar(image, exported, &path) {
+                    result.errors.push(e);
+                }
+            }
+        }
+    }
+
+    progress(total, total, "");
+    result
+}
+
+/// Cancel an ongoing collection file export
+#[tauri::command]
+pub fn cancel_collection_files_export() -> Result<(), String> {
+    EXPORT_CANCELLED.store(true, Ordering::SeqCst);
+    log::info!("Collection file export cancellation requested");
+    Ok(())
+}
+
+/// Copy (or hard-link) the original FITS and JPEG files of a collection into
+/// a folder named after it in `destination`, with a metadata sidecar per
+/// image. Progress is emitted as `collection-files-progress` events.
+#[tauri::command]
+pub async fn export_collection_files(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    collection_id: String,
+    destination: String,
+    options: Option<CollectionFilesOptions>,
+) -> Result<CollectionFilesResult, String> {
+    // Reset cancellation flag at start
+    EXPORT_CANCELLED.store(false, Ordering::SeqCst);
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collection = repository::get_collection_by_id(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Collection not found")?;
+    let images = repository::get_images_in_collection(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?;
+    drop(conn);
+
+    let folder = Path::new(&destination).join(path_component(&collection.name));
+    let options = options.unwrap_or_default();
+
+    let result = tokio::task::spawn_blocking(move || {
+        export_files(
+            &images,
+            &folder,
+            &options,
+            &EXPORT_CANCELLED,
+            |current, total, file| {
+                let _ = window.emit(
+                    PROGRESS_EVENT,
+                    &CollectProgress {
+                        current,
+                        total,
+                        current_file: file.to_string(),
+                        percent: (current * 100 / total.max(1)) as u8,
+                        cancelled: EXPORT_CANCELLED.load(Ordering::SeqCst),
+                        phase: if current < total {
+                            "copying"
+                        } else {
+                            "complete"
+                        }
+                        .to_string(),
+                    },
+                );
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Collection export failed: {}", e))?;
+
+    log::info!(
+        "Exported collection {}: {} copied, {} linked, {} skipped, {} errors",
+        collection.name,
+        result.files_copied,
+        result.files_linked,
+        result.files_skipped,
+        result.errors.len()
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(id: &str, url: Option<PathBuf>, fits_url: Option<PathBuf>, meta: &str) -> Image {
+        Image {
+            url: url.map(|p| p.to_string_lossy().to_string()),
+            tags: Some("narrowband, m31".to_string()),
+            metadata: Some(meta.to_string()),
+            fits_url: fits_url.map(|p| p.to_string_lossy().to_string()),
+            exposure: Some(10.0),
+            rating: Some(4),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn folders_are_named_after_object_night_and_filter() {
+        let meta = r#"{"object":"M 31","date_obs":"2026-01-11T02:30:00","filter":"ha"}"#;
+        let folder = image_folder(&image("a", None, None, meta));
+        assert_eq!(folder, Path::new("M 31").join("2026-01-10").join("Ha"));
+
+        let folder = image_folder(&image(
+            "b",
+            None,
+            None,
+            r#"{"object":"Sh2-129: Flying Bat"}"#,
+        ));
+        assert_eq!(
+            folder,
+            Path::new("Sh2-129_ Flying Bat")
+                .join("Undated")
+                .join("No filter")
+        );
+    }
+
+    #[test]
+    fn export_copies_files_with_sidecars_and_resumes() {
+        let source = tempfile::tempdir().unwrap();
+        let night_one = source.path().join("night1");
+        let night_two = source.path().join("night2");
+        fs::create_dir_all(&night_one).unwrap();
+        fs::create_dir_all(&night_two).unwrap();
+        // Same file name from two nights of the same target
+        fs::write(night_one.join("Light_M 31.fit"), b"first").unwrap();
+        fs::write(night_one.join("Light_M 31.jpg"), b"preview").unwrap();
+        fs::write(night_two.join("Light_M 31.fit"), b"second!").unwrap();
+
+        let meta = r#"{"object":"M 31"}"#;
+        let images = vec![
+            image(
+                "a",
+                Some(night_one.join("Light_M 31.jpg")),
+                Some(night_one.join("Light_M 31.fit")),
+                meta,
+            ),
+            image("b", Some(night_two.join("Light_M 31.fit")), None, meta),
+            image("missing", Some(source.path().join("gone.fit")), None, meta),
+        ];
+
+        let out = tempfile::tempdir().unwrap();
+        let collection = out.path().join("Andromeda");
+        let options = CollectionFilesOptions::default();
+        let mut reported = Vec::new();
+        let result = export_files(
+            &images,
+            &collection,
+            &options,
+            &AtomicBool::new(false),
+            |done, total, _| reported.push((done, total)),
+        );
+
+        assert_eq!((result.files_copied, result.files_skipped), (3, 0));
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(reported.last(), Some(&(4, 4)));
+
+        let folder = collection.join("M 31").join("Undated").join("No filter");
+        assert_eq!(fs::read(folder.join("Light_M 31.fit")).unwrap(), b"first");
+        assert_eq!(
+            fs::read(folder.join("Light_M 31 (2).fit")).unwrap(),
+            b"second!"
+        );
+        let sidecar: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(folder.join("Light_M 31.json")).unwrap())
+                .unwrap();
+        assert_eq!(sidecar["id"], "a");
+        assert_eq!(
+            sidecar["files"],
+            serde_json::json!(["Light_M 31.jpg", "Light_M 31.fit"])
+        );
+        assert_eq!(sidecar["tags"], serde_json::json!(["narrowband", "m31"]));
+        assert_eq!(sidecar["metadata"]["object"], "M 31");
+        assert!(folder.join("Light_M 31 (2).json").exists());
+
+        // Running it again finds everything already there
+        let again = export_files(
+            &images,
+            &collection,
+            &options,
+            &AtomicBool::new(false),
+            |_, _, _| {},
+        );
+        assert_eq!((again.files_copied, again.files_skipped), (0, 3));
+
+        let cancelled = export_files(
+            &images,
+            &collection,
+            &options,
+            &AtomicBool::new(true),
+            |_, _, _| {},
+        );
+        assert!(cancelled.cancelled);
+        assert_eq!(cancelled.files_skipped, 0);
+    }
+
+    #[test]
+    fn fits_only_leaves_out_jpegs() {
+        let img = image(
+            "a",
+            Some(PathBuf::from("/data/M 31/Stacked.jpg")),
+            Some(PathBuf::from("/data/M 31/Stacked.fit")),
+            "{}",
+        );
+        assert_eq!(source_files(&img, false).len(), 2);
+        assert_eq!(
+            source_files(&img, true),
+            vec![PathBuf::from("/data/M 31/Stacked.fit")]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Naming template for the session collections scan and auto-import create
+//!
+//! Templates use `{date}` (the observing night, YYYY-MM-DD) and `{object}`
+//! (the target), e.g. "{date} – {object}" or "{object}/{date}". They are
+//! evaluated by `scan::generate_collection_name`.
+
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use super::scan::generate_collection_name;
+use super::settings::{load_settings, load_settings_in, save_settings, COLLECTION_NAMING};
+
+/// One collection per night, named by its date
+pub const DEFAULT_COLLECTION_TEMPLATE: &str = "{date}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CollectionNaming {
+    pub template: String,
+}
+
+impl Default for CollectionNaming {
+    fn default() -> Self {
+        CollectionNaming {
+            template: DEFAULT_COLLECTION_TEMPLATE.to_string(),
+        }
+    }
+}
+
+/// Saved collection naming template, or the default
+pub fn collection_name_template(app: &AppHandle) -> String {
+    load_settings::<CollectionNaming>(app, &COLLECTION_NAMING)
+        .unwrap_or_default()
+        .template
+}
+
+/// Naming template saved in `data_dir`, for running without the app
+pub(crate) fn collection_name_template_in(data_dir: &Path) -> String {
+    load_settings_in::<CollectionNaming>(data_dir, &COLLECTION_NAMING).template
+}
+
+#[tauri::command]
+pub fn get_collection_naming(app: AppHandle) -> Result<CollectionNaming, String> {
+    load_settings(&app, &COLLECTION_NAMING)
+}
+
+/// Save the naming template; None restores the default
+#[tauri::command]
+pub fn set_collection_naming(
+    app: AppHandle,
+    naming: Option<CollectionNaming>,
+) -> Result<CollectionNaming, String> {
+    let naming = naming.unwrap_or_default();
+    // Without a field every import would land in one fixed collection
+    if !naming.template.contains("{date}") && !naming.template.contains("{object}") {
+        return Err("Template needs {date} or {object}".to_string());
+    }
+
+    save_settings(&app, &COLLECTION_NAMING, &naming)?;
+    Ok(naming)
+}
+
+/// Name a template would give a session, for previewing it while editing
+#[tauri::command]
+pub fn preview_collection_name(
+    template: String,
+    date: String,
+    object: Option<String>,
+) -> Result<String, String> {
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date '{}': {}", date, e))?;
+    Ok(generate_collection_name(
+        &template,
+        &date,
+        object.as_deref(),
+    ))
+}
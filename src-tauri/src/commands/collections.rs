@@ -1,12 +1,22 @@
 //! Collection commands for managing observation collections
 
+use std::collections::{BTreeMap, BTreeSet};
+
+use diesel::sqlite::SqliteConnection;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
 
-use crate::db::models::{Collection, NewCollection, UpdateCollection};
-use crate::db::repository;
+use crate::db::models::{Collection, Image, NewCollection, UpdateCollection};
+use crate::db::repository::{self, ImageSort};
 use crate::state::AppState;
 
+use super::filters::normalize_filter_name;
+use super::notes::{delete_entity_notes, NoteEntity};
+use super::scan::{
+    extract_float_value, extract_int_value, image_session_date, metadata_header_value,
+    metadata_number_value,
+};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateCollectionInput {
     pub name: String,
@@ -36,6 +46,12 @@ pub fn get_collections(state: State<'_, AppState>) -> Result<Vec<Collection>, St
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn get_archived_collections(state: State<'_, AppState>) -> Result<Vec<Collection>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_archived_collections(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_collection(
     state: State<'_, AppState>,
@@ -93,9 +109,499 @@ pub fn update_collection(
 }
 
 #[tauri::command]
-pub fn delete_collection(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+pub fn delete_collection(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<bool, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    repository::delete_collection(&mut conn, &id)
+    let deleted = repository::delete_collection(&mut conn, &id)
         .map(|count| count > 0)
+        .map_err(|e| e.to_string())?;
+    if deleted {
+        delete_entity_notes(&app, &mut conn, &state.user_id, NoteEntity::Collection, &id);
+    }
+    Ok(deleted)
+}
+
+/// Archive or restore a collection. Archived collections are hidden from the
+/// collection list but keep their images.
+#[tauri::command]
+pub fn archive_collection(
+    state: State<'_, AppState>,
+    id: String,
+    archived: bool,
+) -> Result<Collection, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_collection(&mut conn, &id)?;
+    repository::set_collection_archived(&mut conn, &id, archived).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Merge, duplicate and split
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeCollectionsResult {
+    pub collection: Collection,
+    /// Images that were only in the merged-in collection
+    pub images_moved: usize,
+    /// Images that were already in both
+    pub duplicates: usize,
+}
+
+/// How to split a collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    /// Local observing night
+    Date,
+    /// Target name (image summary)
+    Target,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitCollectionResult {
+    /// New collections, one per night or target
+    pub collections: Vec<Collection>,
+    pub images_moved: usize,
+    /// Images without a night or target, left in the original collection
+    pub images_remaining: usize,
+}
+
+fn get_existing_collection(conn: &mut SqliteConnection, id: &str) -> Result<Collection, String> {
+    repository::get_collection_by_id(conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Collection not found: {}", id))
+}
+
+/// Metadata for a copy of a collection. Copies aren't automatic session
+/// collections, so session repair leaves them alone.
+fn copied_metadata(metadata: Option<&str>) -> Option<String> {
+    let mut value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    let obj = value.as_object_mut()?;
+    obj.remove("auto_imported");
+    obj.remove("session_date");
+    (!obj.is_empty()).then(|| value.to_string())
+}
+
+/// Settings for a new collection derived from `source`
+fn derived_collection(source: &Collection, name: String) -> NewCollection {
+    NewCollection {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: source.user_id.clone(),
+        name,
+        description: source.description.clone(),
+        visibility: source.visibility.clone(),
+        template: source.template.clone(),
+        favorite: false,
+        tags: source.tags.clone(),
+        metadata: copied_metadata(source.metadata.as_deref()),
+        archived: false,
+    }
+}
+
+/// Group image ids by night or target, in key order. Images without one are
+/// left out.
+fn group_images(
+    images: &[Image],
+    by: SplitBy,
+    longitude: Option<f64>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for image in images {
+        let key = match by {
+            SplitBy::Date => image_session_date(image, longitude).map(|d| d.to_string()),
+            SplitBy::Target => image
+                .summary
+                .as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+        };
+        if let Some(key) = key {
+            groups.entry(key).or_default().push(image.id.clone());
+        }
+    }
+    groups
+}
+
+/// Merge `source_id` into `target_id`. Images in both are kept once and the
+/// source collection is deleted.
+#[tauri::command]
+pub fn merge_collections(
+    state: State<'_, AppState>,
+    target_id: String,
+    source_id: String,
+) -> Result<MergeCollectionsResult, String> {
+    if target_id == source_id {
+        return Err("Cannot merge a collection into itself".to_string());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_collection(&mut conn, &target_id)?;
+    get_existing_collection(&mut conn, &source_id)?;
+
+    let source_count = repository::get_collection_image_count(&mut conn, &source_id)
+        .map_err(|e| e.to_string())? as usize;
+    let images_moved = repository::merge_collections(&mut conn, &target_id, &source_id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(MergeCollectionsResult {
+        collection: get_existing_collection(&mut conn, &target_id)?,
+        images_moved,
+        duplicates: source_count - images_moved,
+    })
+}
+
+/// Copy a collection's settings into a new collection, e.g. to use it as a
+/// template. Images are copied only with `includeImages`.
+#[tauri::command]
+pub fn duplicate_collection(
+    state: State<'_, AppState>,
+    id: String,
+    name: Option<String>,
+    include_images: Option<bool>,
+) -> Result<Collection, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let source = get_existing_collection(&mut conn, &id)?;
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| format!("{} (copy)", source.name));
+
+    let new_collection = derived_collection(&source, name);
+    repository::duplicate_collection(
+        &mut conn,
+        &id,
+        &new_collection,
+        include_images.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Split a collection into one new collection per observing night or
+/// target. Images without a night or target stay in the original. A
+/// collection with a single night or target isn't split.
+///
+/// `longitude` is the observer's site, used to find the local night of
+/// frames without a SITELONG header.
+#[tauri::command]
+pub fn split_collection(
+    state: State<'_, AppState>,
+    id: String,
+    by: SplitBy,
+    longitude: Option<f64>,
+) -> Result<SplitCollectionResult, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let source = get_existing_collection(&mut conn, &id)?;
+    let images = repository::get_images_in_collection(&mut conn, &id).map_err(|e| e.to_string())?;
+
+    let groups = group_images(&images, by, longitude);
+    if groups.len() < 2 {
+        return Ok(SplitCollectionResult {
+            collections: Vec::new(),
+            images_moved: 0,
+            images_remaining: images.len(),
+        });
+    }
+
+    let parts: Vec<(NewCollection, Vec<String>)> = groups
+        .into_iter()
+        .map(|(key, image_ids)| {
+            let name = format!("{} - {}", source.name, key);
+            (derived_collection(&source, name), image_ids)
+        })
+        .collect();
+    let images_moved = parts.iter().map(|(_, ids)| ids.len()).sum();
+    let collections =
+        repository::split_collection(&mut conn, &id, &parts).map_err(|e| e.to_string())?;
+
+    Ok(SplitCollectionResult {
+        collections,
+        images_moved,
+        images_remaining: images.len() - images_moved,
+    })
+}
+
+// ============================================================================
+// Cover image and summary
+// ============================================================================
+
+/// Frames and integration time through one filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterIntegration {
+    pub filter: String,
+    pub frames: i64,
+    pub integration_seconds: f64,
+}
+
+/// Totals for a collection, computed from its images' metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionSummary {
+    pub collection_id: String,
+    /// The chosen cover, or the most recently added image if none is set
+    pub cover_image_id: Option<String>,
+    pub image_count: usize,
+    /// Frames captured (stacks count every integrated sub)
+    pub total_frames: i64,
+    pub total_integration_seconds: f64,
+    /// First and last observing night (see `image_session_date`)
+    pub first_night: Option<String>,
+    pub last_night: Option<String>,
+    pub nights: usize,
+    /// Distinct targets (image summaries)
+    pub targets: Vec<String>,
+    pub filters: Vec<FilterIntegration>,
+    /// TELESCOP header values
+    pub telescopes: Vec<String>,
+    /// INSTRUME header values
+    pub cameras: Vec<String>,
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Sum integration time, nights, targets, filters and equipment over a
+/// collection's images
+pub(super) fn summarize_collection(collection: &Collection, images: &[Image]) -> CollectionSummary {
+    let mut total_frames: i64 = 0;
+    let mut total_integration_seconds = 0.0;
+    let mut nights = BTreeSet::new();
+    let mut targets = BTreeSet::new();
+    let mut filters: BTreeMap<String, FilterIntegration> = BTreeMap::new();
+    let mut telescopes = BTreeSet::new();
+    let mut cameras = BTreeSet::new();
+
+    for image in images {
+        let meta = image.metadata.as_deref().unwrap_or("{}");
+        let frames = metadata_number_value(meta, "stacked_frames", &["STACKCNT", "NCOMBINE"])
+            .and_then(|v| extract_int_value(&v))
+            .filter(|f| *f > 0)
+            .unwrap_or(1) as i64;
+        let exposure = image
+            .exposure
+            .or_else(|| {
+                metadata_number_value(meta, "exposure", &["EXPTIME", "EXPOSURE"])
+                    .and_then(|v| extract_float_value(&v))
+            })
+            .unwrap_or(0.0);
+        let integration = exposure * frames as f64;
+
+        total_frames += frames;
+        total_integration_seconds += integration;
+
+        if let Some(night) = image_session_date(image, None) {
+            nights.insert(night);
+        }
+        targets.extend(non_empty(image.summary.as_deref()));
+
+        let filter = image
+            .filter
+            .clone()
+            .or_else(|| metadata_header_value(meta, "filter", "FILTER"))
+            .and_then(|f| normalize_filter_name(&f));
+        if let Some(filter) = filter {
+            let entry = filters
+                .entry(filter.clone())
+                .or_insert_with(|| FilterIntegration {
+                    filter,
+                    frames: 0,
+                    integration_seconds: 0.0,
+                });
+            entry.frames += frames;
+            entry.integration_seconds += integration;
+        }
+
+        let telescope = image
+            .telescope
+            .clone()
+            .or_else(|| metadata_header_value(meta, "telescope", "TELESCOP"));
+        telescopes.extend(non_empty(telescope.as_deref()));
+        let camera = metadata_header_value(meta, "instrument", "INSTRUME");
+        cameras.extend(non_empty(camera.as_deref()));
+    }
+
+    let cover_image_id = collection.cover_image_id.clone().or_else(|| {
+        images
+            .iter()
+            .max_by_key(|image| image.created_at)
+            .map(|image| image.id.clone())
+    });
+
+    CollectionSummary {
+        collection_id: collection.id.clone(),
+        cover_image_id,
+        image_count: images.len(),
+        total_frames,
+        total_integration_seconds,
+        first_night: nights.first().map(|d| d.to_string()),
+        last_night: nights.last().map(|d| d.to_string()),
+        nights: nights.len(),
+        targets: targets.into_iter().collect(),
+        filters: filters.into_values().collect(),
+        telescopes: telescopes.into_iter().collect(),
+        cameras: cameras.into_iter().collect(),
+    }
+}
+
+/// Set the collection's cover image, or clear it with no `imageId`. The
+/// image must be in the collection.
+#[tauri::command]
+pub fn set_collection_cover(
+    state: State<'_, AppState>,
+    id: String,
+    image_id: Option<String>,
+) -> Result<Collection, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_collection(&mut conn, &id)?;
+    if let Some(image_id) = &image_id {
+        let member = repository::is_image_in_collection(&mut conn, &id, image_id)
+            .map_err(|e| e.to_string())?;
+        if !member {
+            return Err(format!("Image {} is not in collection {}", image_id, id));
+        }
+    }
+    repository::set_collection_cover(&mut conn, &id, image_id.as_deref())
         .map_err(|e| e.to_string())
 }
+
+/// Set the default order of the collection's images, or go back to newest
+/// added first with no `sort`
+#[tauri::command]
+pub fn set_collection_image_sort(
+    state: State<'_, AppState>,
+    id: String,
+    sort: Option<ImageSort>,
+) -> Result<Collection, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_collection(&mut conn, &id)?;
+    repository::set_collection_image_sort(&mut conn, &id, sort).map_err(|e| e.to_string())
+}
+
+/// Arrange the collection's images by hand (e.g. after a drag and drop):
+/// `imageIds` in their new order, with any left out following them. The
+/// collection switches to manual sorting. Returns the images in their new
+/// order.
+#[tauri::command]
+pub fn reorder_collection_images(
+    state: State<'_, AppState>,
+    id: String,
+    image_ids: Vec<String>,
+) -> Result<Vec<Image>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_collection(&mut conn, &id)?;
+    let members: BTreeSet<String> = repository::get_collection_image_ids(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    let mut seen = BTreeSet::new();
+    for image_id in &image_ids {
+        if !members.contains(image_id) {
+            return Err(format!("Image {} is not in collection {}", image_id, id));
+        }
+        if !seen.insert(image_id) {
+            return Err(format!("Image {} is listed more than once", image_id));
+        }
+    }
+
+    repository::set_collection_image_order(&mut conn, &id, &image_ids)
+        .map_err(|e| e.to_string())?;
+    repository::get_sorted_collection_images(&mut conn, &id).map_err(|e| e.to_string())
+}
+
+/// Get total integration time, observing nights, targets, filters and
+/// equipment for a collection
+#[tauri::command]
+pub fn get_collection_summary(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<CollectionSummary, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collection = get_existing_collection(&mut conn, &id)?;
+    let images = repository::get_images_in_collection(&mut conn, &id).map_err(|e| e.to_string())?;
+    Ok(summarize_collection(&collection, &images))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_drop_session_metadata() {
+        let session = r#"{"auto_imported":true,"session_date":"2025-03-01","site":"Backyard"}"#;
+        assert_eq!(
+            copied_metadata(Some(session)).as_deref(),
+            Some(r#"{"site":"Backyard"}"#)
+        );
+        assert_eq!(copied_metadata(Some(r#"{"auto_imported":true}"#)), None);
+        assert_eq!(copied_metadata(Some("not json")), None);
+    }
+
+    fn make_image(id: &str, summary: &str, metadata: &str) -> Image {
+        Image {
+            summary: Some(summary.to_string()),
+            metadata: Some(metadata.to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn summary_totals_integration_by_filter() {
+        let now = chrono::Utc::now().naive_utc();
+        let collection = Collection {
+            id: "c1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "M42".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            created_at: now,
+            updated_at: now,
+            archived: false,
+            cover_image_id: None,
+            image_sort: None,
+        };
+        let images = vec![
+            // Stack of 60 x 300s Ha on the night of Jan 15
+            make_image(
+                "stack",
+                "M42",
+                r#"{"exposure":300.0,"stacked_frames":60,"filter":"H-alpha","date_obs":"2026-01-16T02:00:00","telescope":"RedCat 51","instrument":"ASI2600MM"}"#,
+            ),
+            // Single 120s OIII sub the next night, raw header format
+            make_image(
+                "sub",
+                " M42 ",
+                r#"{"EXPTIME":"Some(RealFloatingNumber(120.0))","FILTER":"Some(CharacterString(\"OIII\"))","DATE-OBS":"Some(CharacterString(\"2026-01-16T22:00:00\"))"}"#,
+            ),
+        ];
+
+        let summary = summarize_collection(&collection, &images);
+        assert_eq!(summary.image_count, 2);
+        assert_eq!(summary.total_frames, 61);
+        assert!((summary.total_integration_seconds - 18_120.0).abs() < 1e-9);
+        assert_eq!(summary.first_night.as_deref(), Some("2026-01-15"));
+        assert_eq!(summary.last_night.as_deref(), Some("2026-01-16"));
+        assert_eq!(summary.nights, 2);
+        assert_eq!(summary.targets, vec!["M42"]);
+        let filters: Vec<(&str, i64)> = summary
+            .filters
+            .iter()
+            .map(|f| (f.filter.as_str(), f.frames))
+            .collect();
+        assert_eq!(filters, vec![("Ha", 60), ("OIII", 1)]);
+        assert_eq!(summary.telescopes, vec!["RedCat 51"]);
+        assert_eq!(summary.cameras, vec!["ASI2600MM"]);
+        assert!(summary.cover_image_id.is_some());
+    }
+}
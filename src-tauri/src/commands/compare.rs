@@ -0,0 +1,434 @@
+//! Image comparison: aligned previews, pixel differences and metadata
+//! changes between two images (e.g. a reprocess and its original, or two
+//! nights on the same target)
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::solvers::wcs::Wcs;
+use crate::state::AppState;
+
+use super::gallery_export::load_source_image;
+use super::scan::{extract_float_value, extract_string_value};
+
+/// Default maximum preview dimension (width or height)
+const DEFAULT_PREVIEW_SIZE: u32 = 1024;
+/// Delta histogram covers -256..256 in bins of this width
+const HISTOGRAM_BIN_WIDTH: i32 = 8;
+const HISTOGRAM_MIN: i32 = -256;
+const HISTOGRAM_BINS: usize = 64;
+const CHANNEL_NAMES: [&str; 3] = ["red", "green", "blue"];
+
+/// How image B was brought into image A's frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    /// Reprojected through both plate solutions
+    Wcs,
+    /// Scaled to the same size (assumes the same framing)
+    Resize,
+}
+
+/// Pixel differences (B - A) in one channel, in 8-bit preview levels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelDelta {
+    pub channel: String,
+    pub mean: f64,
+    pub mean_abs: f64,
+    pub rms: f64,
+    /// Pixel counts per delta bin, starting at `histogramMin`
+    pub histogram: Vec<u64>,
+}
+
+/// A metadata value that differs between the two images
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataDiff {
+    pub key: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageComparison {
+    pub id_a: String,
+    pub id_b: String,
+    pub alignment: Alignment,
+    pub width: u32,
+    pub height: u32,
+    /// PNG data URLs, both in image A's frame
+    pub preview_a: String,
+    pub preview_b: String,
+    /// Mid-gray where unchanged, lighter where B is brighter
+    pub difference: String,
+    /// Fraction of A's frame that B covers
+    pub overlap: f64,
+    pub channels: Vec<ChannelDelta>,
+    pub histogram_min: i32,
+    pub histogram_bin_width: i32,
+    pub metadata: Vec<MetadataDiff>,
+}
+
+/// Plate solution and solved width in pixels, for mapping preview pixels
+/// to the sky
+fn image_solution(image: &Image) -> Option<(Wcs, f64)> {
+    let metadata: serde_json::Value = serde_json::from_str(image.metadata.as_deref()?).ok()?;
+    let solve = metadata.get("plate_solve")?;
+    let pixel_scale = solve.get("pixel_scale")?.as_f64()?;
+    let width_deg = solve.get("width_deg")?.as_f64()?;
+    if pixel_scale <= 0.0 || width_deg <= 0.0 {
+        return None;
+    }
+    let wcs = serde_json::from_str(image.wcs.as_deref()?).ok()?;
+    Some((wcs, width_deg * 3600.0 / pixel_scale))
+}
+
+/// Resample `source` into a `width` x `height` frame. `map` gives the
+/// source pixel (continuous, 0-based) for each frame pixel, or None where
+/// there is none. Returns the image and which pixels were covered.
+fn warp_into_frame(
+    source: &RgbImage,
+    width: u32,
+    height: u32,
+    map: impl Fn(f64, f64) -> Option<(f64, f64)>,
+) -> (RgbImage, Vec<bool>) {
+    let mut out = RgbImage::new(width, height);
+    let mut covered = vec![false; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let Some((sx, sy)) = map(x as f64, y as f64) else {
+                continue;
+            };
+            let (sx, sy) = (sx.round(), sy.round());
+            if sx < 0.0 || sy < 0.0 || sx >= source.width() as f64 || sy >= source.height() as f64 {
+                continue;
+            }
+            out.put_pixel(x, y, *source.get_pixel(sx as u32, sy as u32));
+            covered[(y * width + x) as usize] = true;
+        }
+    }
+    (out, covered)
+}
+
+/// Per-channel B - A statistics over covered pixels
+fn channel_deltas(a: &RgbImage, b: &RgbImage, covered: &[bool]) -> Vec<ChannelDelta> {
+    let mut sums = [0.0f64; 3];
+    let mut abs_sums = [0.0f64; 3];
+    let mut square_sums = [0.0f64; 3];
+    let mut histograms = vec![vec![0u64; HISTOGRAM_BINS]; 3];
+    let mut count = 0u64;
+
+    for ((pa, pb), &covered) in a.pixels().zip(b.pixels()).zip(covered) {
+        if !covered {
+            continue;
+        }
+        count += 1;
+        for c in 0..3 {
+            let delta = pb[c] as i32 - pa[c] as i32;
+            sums[c] += delta as f64;
+            abs_sums[c] += delta.abs() as f64;
+            square_sums[c] += (delta * delta) as f64;
+            let bin = ((delta - HISTOGRAM_MIN) / HISTOGRAM_BIN_WIDTH) as usize;
+            histograms[c][bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+    }
+
+    let n = count.max(1) as f64;
+    CHANNEL_NAMES
+        .iter()
+        .zip(histograms)
+        .enumerate()
+        .map(|(c, (name, histogram))| ChannelDelta {
+            channel: name.to_string(),
+            mean: sums[c] / n,
+            mean_abs: abs_sums[c] / n,
+            rms: (square_sums[c] / n).sqrt(),
+            histogram,
+        })
+        .collect()
+}
+
+/// Difference image centered on mid-gray; uncovered pixels are black
+fn difference_image(a: &RgbImage, b: &RgbImage, covered: &[bool]) -> RgbImage {
+    let mut out = RgbImage::new(a.width(), a.height());
+    for (i, (pixel, (pa, pb))) in out.pixels_mut().zip(a.pixels().zip(b.pixels())).enumerate() {
+        if covered[i] {
+            *pixel = Rgb(std::array::from_fn(|c| {
+                (128 + (pb[c] as i32 - pa[c] as i32) / 2).clamp(0, 255) as u8
+            }));
+        }
+    }
+    out
+}
+
+fn png_data_url(image: &RgbImage) -> Result<String, String> {
+    let mut buffer = Cursor::new(Vec::new());
+    image
+        .write_to(&mut buffer, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode preview: {}", e))?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        BASE64_STANDARD.encode(buffer.into_inner())
+    ))
+}
+
+/// Readable form of a stored metadata value (fitrs debug strings included)
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => extract_float_value(s)
+            .map(|n| n.to_string())
+            .or_else(|| extract_string_value(s))
+            .unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+/// Flatten metadata JSON into dotted keys, e.g. "plate_solve.rotation".
+/// Arrays are kept whole.
+fn flatten_metadata(prefix: &str, value: &serde_json::Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_metadata(&key, value, out);
+            }
+        }
+        serde_json::Value::Null => {}
+        other => {
+            out.insert(prefix.to_string(), display_value(other));
+        }
+    }
+}
+
+/// Acquisition columns and metadata of an image as flat key/value pairs
+fn comparable_fields(image: &Image) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    let columns = [
+        ("summary", image.summary.clone()),
+        ("exposure", image.exposure.map(|e| e.to_string())),
+        ("gain", image.gain.map(|g| g.to_string())),
+        ("filter", image.filter.clone()),
+        ("telescope", image.telescope.clone()),
+        ("date_obs", image.date_obs.clone()),
+    ];
+    for (key, value) in columns {
+        if let Some(value) = value {
+            fields.insert(key.to_string(), value);
+        }
+    }
+    if let Some(metadata) = image
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+    {
+        let mut flat = BTreeMap::new();
+        flatten_metadata("", &metadata, &mut flat);
+        for (key, value) in flat {
+            fields.entry(format!("metadata.{}", key)).or_insert(value);
+        }
+    }
+    fields
+}
+
+/// Fields whose values differ between the two images, in key order
+fn metadata_diffs(a: &Image, b: &Image) -> Vec<MetadataDiff> {
+    let a = comparable_fields(a);
+    let b = comparable_fields(b);
+    let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    keys.into_iter()
+        .filter(|key| a.get(*key) != b.get(*key))
+        .map(|key| MetadataDiff {
+            key: key.clone(),
+            a: a.get(key).cloned(),
+            b: b.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Bring `source_b` into `preview_a`'s frame: through both plate
+/// solutions when available, otherwise by resizing
+fn align(
+    image_a: &Image,
+    preview_a: &RgbImage,
+    image_b: &Image,
+    source_b: &DynamicImage,
+) -> (Alignment, RgbImage, Vec<bool>) {
+    let (width, height) = preview_a.dimensions();
+    if let (Some((wcs_a, full_a)), Some((wcs_b, full_b))) =
+        (image_solution(image_a), image_solution(image_b))
+    {
+        let source_b = source_b.to_rgb8();
+        // Preview pixels to solved (full resolution) pixels, at pixel centers
+        let scale_a = full_a / width as f64;
+        let scale_b = full_b / source_b.width() as f64;
+        let (warped, covered) = warp_into_frame(&source_b, width, height, |x, y| {
+            let (ra, dec) =
+                wcs_a.pixel_to_sky((x + 0.5) * scale_a - 0.5, (y + 0.5) * scale_a - 0.5);
+            let (bx, by) = wcs_b.sky_to_pixel(ra, dec)?;
+            Some(((bx + 0.5) / scale_b - 0.5, (by + 0.5) / scale_b - 0.5))
+        });
+        return (Alignment::Wcs, warped, covered);
+    }
+
+    let resized = source_b
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_rgb8();
+    (
+        Alignment::Resize,
+        resized,
+        vec![true; (width * height) as usize],
+    )
+}
+
+fn compare(
+    image_a: &Image,
+    source_a: &DynamicImage,
+    image_b: &Image,
+    source_b: &DynamicImage,
+    max_size: u32,
+) -> Result<ImageComparison, String> {
+    let preview_a = if source_a.width() > max_size || source_a.height() > max_size {
+        source_a.resize(max_size, max_size, FilterType::Triangle)
+    } else {
+        source_a.clone()
+    }
+    .to_rgb8();
+
+    let (alignment, preview_b, covered) = align(image_a, &preview_a, image_b, source_b);
+    let overlap = covered.iter().filter(|c| **c).count() as f64 / covered.len().max(1) as f64;
+
+    Ok(ImageComparison {
+        id_a: image_a.id.clone(),
+        id_b: image_b.id.clone(),
+        alignment,
+        width: preview_a.width(),
+        height: preview_a.height(),
+        preview_a: png_data_url(&preview_a)?,
+        preview_b: png_data_url(&preview_b)?,
+        difference: png_data_url(&difference_image(&preview_a, &preview_b, &covered))?,
+        overlap,
+        channels: channel_deltas(&preview_a, &preview_b, &covered),
+        histogram_min: HISTOGRAM_MIN,
+        histogram_bin_width: HISTOGRAM_BIN_WIDTH,
+        metadata: metadata_diffs(image_a, image_b),
+    })
+}
+
+/// Compare two images: previews aligned to image A's frame, per-channel
+/// difference statistics and the metadata values that differ
+#[tauri::command]
+pub async fn compare_images(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id_a: String,
+    id_b: String,
+    max_size: Option<u32>,
+) -> Result<ImageComparison, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut get_image = |id: &str| {
+        repository::get_image_by_id(&mut conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Image not found: {}", id))
+    };
+    let image_a = get_image(&id_a)?;
+    let image_b = get_image(&id_b)?;
+    drop(conn);
+
+    let previews_dir = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let max_size = max_size.unwrap_or(DEFAULT_PREVIEW_SIZE).max(16);
+
+    tokio::task::spawn_blocking(move || {
+        let load = |image: &Image| {
+            load_source_image(image, &previews_dir)
+                .ok_or_else(|| format!("No displayable file for {}", image.filename))
+        };
+        let source_a = load(&image_a)?;
+        let source_b = load(&image_b)?;
+        compare(&image_a, &source_a, &image_b, &source_b, max_size)
+    })
+    .await
+    .map_err(|e| format!("Image comparison failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_count_only_covered_pixels() {
+        let a = RgbImage::from_pixel(2, 1, Rgb([100, 100, 100]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, Rgb([120, 90, 100]));
+        b.put_pixel(1, 0, Rgb([0, 0, 0]));
+
+        let deltas = channel_deltas(&a, &b, &[true, false]);
+        assert_eq!(deltas[0].mean, 20.0);
+        assert_eq!(deltas[1].mean_abs, 10.0);
+        assert_eq!(deltas[2].rms, 0.0);
+        // +20 falls in the bin starting at 16, -10 in the one starting at -16
+        assert_eq!(deltas[0].histogram[(256 + 16) / 8], 1);
+        assert_eq!(deltas[1].histogram[(256 - 16) / 8], 1);
+        assert_eq!(deltas[0].histogram.iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn warp_marks_pixels_outside_source() {
+        let source = RgbImage::from_pixel(2, 2, Rgb([255, 0, 0]));
+        // Shift one pixel right: the first column has no source pixel
+        let (warped, covered) = warp_into_frame(&source, 2, 2, |x, y| Some((x - 1.0, y)));
+        assert_eq!(covered, vec![false, true, false, true]);
+        assert_eq!(warped.get_pixel(1, 0), &Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn metadata_diffs_flatten_nested_values() {
+        let image = |id: &str, metadata: &str| Image {
+            summary: Some("M42".to_string()),
+            metadata: Some(metadata.to_string()),
+            exposure: Some(300.0),
+            ..Image::test(id)
+        };
+        let a = image(
+            "a",
+            r#"{"GAIN":"Some(IntegerNumber(100))","processing":{"stretch":"mtf"}}"#,
+        );
+        let b = image(
+            "b",
+            r#"{"GAIN":"Some(IntegerNumber(100))","processing":{"stretch":"arcsinh"},"source_image_id":"a"}"#,
+        );
+
+        assert_eq!(
+            metadata_diffs(&a, &b),
+            vec![
+                MetadataDiff {
+                    key: "metadata.processing.stretch".to_string(),
+                    a: Some("mtf".to_string()),
+                    b: Some("arcsinh".to_string()),
+                },
+                MetadataDiff {
+                    key: "metadata.source_image_id".to_string(),
+                    a: None,
+                    b: Some("a".to_string()),
+                },
+            ]
+        );
+    }
+}
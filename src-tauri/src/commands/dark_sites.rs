@@ -0,0 +1,417 @@
+//! Dark-sky site finder
+//!
+//! Samples a light-pollution map on a grid around a location and suggests
+//! the darkest spots within reach. The map isn't bundled: it is read from
+//! `light-pollution/` in the app data dir as an equirectangular grayscale
+//! PNG of zenith sky brightness (`sky-brightness.png`, lighter pixels are
+//! brighter skies). A `sky-brightness.json` next to it gives the map's
+//! bounds and SQM range when it doesn't cover the world at 16-22 mag/arcsec².
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::{Location, NewLocation};
+use crate::db::repository;
+use crate::network::{Network, Provider};
+use crate::state::AppState;
+
+use super::astronomy::LocationInput;
+use super::exposure_plan::bortle_class;
+
+const MAP_DIR: &str = "light-pollution";
+const MAP_FILE: &str = "sky-brightness.png";
+const MAP_BOUNDS_FILE: &str = "sky-brightness.json";
+const OSRM_TABLE_URL: &str = "https://router.project-osrm.org/table/v1/driving";
+const EARTH_RADIUS_KM: f64 = 6371.0;
+const KM_PER_DEGREE: f64 = 111.32;
+/// Grid steps from the centre to the edge of the search area
+const GRID_STEPS: i32 = 12;
+const DEFAULT_MAX_SITES: usize = 5;
+/// Only suggest spots at least this much darker (mag/arcsec²) than the origin
+const MIN_IMPROVEMENT: f64 = 0.1;
+
+/// Area and brightness range a light-pollution map covers
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MapBounds {
+    pub west: f64,
+    pub east: f64,
+    pub north: f64,
+    pub south: f64,
+    /// Sky brightness of a white pixel
+    pub min_sqm: f64,
+    /// Sky brightness of a black pixel
+    pub max_sqm: f64,
+}
+
+impl Default for MapBounds {
+    fn default() -> Self {
+        MapBounds {
+            west: -180.0,
+            east: 180.0,
+            north: 90.0,
+            south: -90.0,
+            min_sqm: 16.0,
+            max_sqm: 22.0,
+        }
+    }
+}
+
+/// Zenith sky brightness over an area, sampled from a grayscale raster
+pub struct LightPollutionMap {
+    width: u32,
+    height: u32,
+    /// Row-major from the north-west corner, 0 (darkest) to 1
+    values: Vec<f32>,
+    bounds: MapBounds,
+}
+
+impl LightPollutionMap {
+    pub fn new(width: u32, height: u32, values: Vec<f32>, bounds: MapBounds) -> Self {
+        LightPollutionMap {
+            width,
+            height,
+            values,
+            bounds,
+        }
+    }
+
+    /// Load the map from `dir`
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(MAP_FILE);
+        if !path.exists() {
+            return Err(format!(
+                "No light-pollution map found; add {} to {}",
+                MAP_FILE,
+                dir.display()
+            ));
+        }
+        let raster = image::open(&path)
+            .map_err(|e| format!("Failed to read light-pollution map: {}", e))?
+            .to_luma16();
+
+        let bounds = match std::fs::read_to_string(dir.join(MAP_BOUNDS_FILE)) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("Invalid {}: {}", MAP_BOUNDS_FILE, e))?,
+            Err(_) => MapBounds::default(),
+        };
+        let values = raster.pixels().map(|p| p.0[0] as f32 / 65535.0).collect();
+        Ok(Self::new(raster.width(), raster.height(), values, bounds))
+    }
+
+    /// Zenith sky brightness in mag/arcsec², or None outside the map
+    pub fn sky_brightness(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        let b = &self.bounds;
+        if !(b.south..=b.north).contains(&latitude) || !(b.west..=b.east).contains(&longitude) {
+            return None;
+        }
+        let x = ((longitude - b.west) / (b.east - b.west) * self.width as f64) as u32;
+        let y = ((b.north - latitude) / (b.north - b.south) * self.height as f64) as u32;
+        let index =
+            y.min(self.height - 1) as usize * self.width as usize + x.min(self.width - 1) as usize;
+        let value = *self.values.get(index)? as f64;
+        Some(b.max_sqm - value * (b.max_sqm - b.min_sqm))
+    }
+}
+
+/// Great-circle distance in km
+pub(super) fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = phi2 - phi1;
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Initial bearing from the first position to the second, degrees east of
+/// north
+fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lambda = (lon2 - lon1).to_radians();
+    let y = d_lambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lambda.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+fn compass_point(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((bearing / 45.0).round() as usize) % 8]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DarkSite {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Zenith sky brightness in mag/arcsec²
+    pub sky_brightness: f64,
+    pub bortle: u8,
+    /// Straight-line distance from the search location
+    pub distance_km: f64,
+    pub bearing: f64,
+    /// By road; None when no route was found or routing was unavailable
+    pub driving_distance_km: Option<f64>,
+    pub driving_minutes: Option<f64>,
+    /// Candidate location saved for this site
+    pub location_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DarkSiteSearch {
+    /// Sky at the search location, when the map covers it
+    pub sky_brightness: Option<f64>,
+    pub bortle: Option<u8>,
+    /// Darkest Bortle class first, nearest first within a class
+    pub sites: Vec<DarkSite>,
+}
+
+/// Grid search of the map within `radius_km` of a position. Sites are
+/// spaced at least two grid steps apart so they aren't all the same spot.
+pub fn search_dark_sites(
+    map: &LightPollutionMap,
+    latitude: f64,
+    longitude: f64,
+    radius_km: f64,
+    max_sites: usize,
+) -> DarkSiteSearch {
+    let origin = map.sky_brightness(latitude, longitude);
+    let step_km = radius_km / GRID_STEPS as f64;
+    let km_per_lon_degree = KM_PER_DEGREE * latitude.to_radians().cos().max(0.01);
+
+    let mut points = Vec::new();
+    for i in -GRID_STEPS..=GRID_STEPS {
+        for j in -GRID_STEPS..=GRID_STEPS {
+            let lat = latitude + (j as f64 * step_km) / KM_PER_DEGREE;
+            let lon = (longitude + (i as f64 * step_km) / km_per_lon_degree + 180.0)
+                .rem_euclid(360.0)
+                - 180.0;
+            if !(-90.0..=90.0).contains(&lat) {
+                continue;
+            }
+            let distance = distance_km(latitude, longitude, lat, lon);
+            if distance > radius_km {
+                continue;
+            }
+            let Some(sky) = map.sky_brightness(lat, lon) else {
+                continue;
+            };
+            if origin.is_some_and(|origin| sky < origin + MIN_IMPROVEMENT) {
+                continue;
+            }
+            points.push((lat, lon, sky, distance));
+        }
+    }
+    points.sort_by(|a, b| {
+        bortle_class(a.2)
+            .cmp(&bortle_class(b.2))
+            .then(a.3.total_cmp(&b.3))
+    });
+
+    let mut sites: Vec<DarkSite> = Vec::new();
+    for (lat, lon, sky, distance) in points {
+        if sites.len() >= max_sites {
+            break;
+        }
+        let crowded = sites
+            .iter()
+            .any(|s| distance_km(s.latitude, s.longitude, lat, lon) < 2.0 * step_km);
+        if crowded {
+            continue;
+        }
+        sites.push(DarkSite {
+            latitude: lat,
+            longitude: lon,
+            sky_brightness: sky,
+            bortle: bortle_class(sky),
+            distance_km: distance,
+            bearing: bearing_deg(latitude, longitude, lat, lon),
+            driving_distance_km: None,
+            driving_minutes: None,
+            location_id: None,
+        });
+    }
+
+    DarkSiteSearch {
+        sky_brightness: origin,
+        bortle: origin.map(bortle_class),
+        sites,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RouteTable {
+    code: String,
+    distances: Option<Vec<Vec<Option<f64>>>>,
+    durations: Option<Vec<Vec<Option<f64>>>>,
+}
+
+/// Driving distance (km) and time (minutes) from a position to each site,
+/// in one OSRM table request
+async fn driving_routes(
+    network: &Network,
+    latitude: f64,
+    longitude: f64,
+    sites: &[DarkSite],
+) -> Result<Vec<(Option<f64>, Option<f64>)>, String> {
+    let coordinates: Vec<String> = std::iter::once((latitude, longitude))
+        .chain(sites.iter().map(|s| (s.latitude, s.longitude)))
+        .map(|(lat, lon)| format!("{:.5},{:.5}", lon, lat))
+        .collect();
+    let url = format!(
+        "{}/{}?sources=0&annotations=distance,duration",
+        OSRM_TABLE_URL,
+        coordinates.join(";")
+    );
+    let table: RouteTable = network.get_json(Provider::Routing, &url).await?;
+    if table.code != "Ok" {
+        return Err(format!("Routing failed: {}", table.code));
+    }
+
+    let row = |rows: Option<Vec<Vec<Option<f64>>>>| rows.and_then(|r| r.into_iter().next());
+    let (distances, durations) = (row(table.distances), row(table.durations));
+    // Column 0 is the origin itself
+    Ok((1..=sites.len())
+        .map(|i| {
+            let metres = distances.as_ref().and_then(|d| d.get(i).copied().flatten());
+            let seconds = durations.as_ref().and_then(|d| d.get(i).copied().flatten());
+            (metres.map(|m| m / 1000.0), seconds.map(|s| s / 60.0))
+        })
+        .collect())
+}
+
+fn map_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join(MAP_DIR))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Suggest darker spots within `radius_km` of a location, with their Bortle
+/// class and driving distance. The sites are saved as candidate locations,
+/// replacing those from the previous search.
+#[tauri::command]
+pub async fn find_dark_sites(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    location: LocationInput,
+    radius_km: f64,
+    max_sites: Option<usize>,
+) -> Result<DarkSiteSearch, String> {
+    if !radius_km.is_finite() || radius_km <= 0.0 {
+        return Err("Search radius must be greater than zero".to_string());
+    }
+    let dir = map_dir(&app)?;
+    let (latitude, longitude) = (location.latitude, location.longitude);
+    let max_sites = max_sites.unwrap_or(DEFAULT_MAX_SITES).max(1);
+
+    let mut search = tokio::task::spawn_blocking(move || {
+        let map = LightPollutionMap::load(&dir)?;
+        Ok::<_, String>(search_dark_sites(
+            &map, latitude, longitude, radius_km, max_sites,
+        ))
+    })
+    .await
+    .map_err(|e| format!("Dark site search failed: {}", e))??;
+
+    if !search.sites.is_empty() {
+        match driving_routes(&state.network, latitude, longitude, &search.sites).await {
+            Ok(routes) => {
+                for (site, (distance, minutes)) in search.sites.iter_mut().zip(routes) {
+                    site.driving_distance_km = distance;
+                    site.driving_minutes = minutes;
+                }
+            }
+            Err(e) => log::warn!("Driving distances unavailable: {}", e),
+        }
+    }
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_candidate_locations(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    for site in &mut search.sites {
+        let new_location = NewLocation {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: state.user_id.clone(),
+            name: format!(
+                "Dark site {:.0} km {} (Bortle {})",
+                site.distance_km,
+                compass_point(site.bearing),
+                site.bortle
+            ),
+            latitude: site.latitude,
+            longitude: site.longitude,
+            elevation: None,
+            bortle: Some(site.bortle as i32),
+            source: "candidate".to_string(),
+            is_default: false,
+        };
+        let saved: Location =
+            repository::create_location(&mut conn, &new_location).map_err(|e| e.to_string())?;
+        site.location_id = Some(saved.id);
+    }
+
+    Ok(search)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1°×1° map around (51.5, 0): bright city in the north-west corner,
+    /// darkening towards the south-east
+    fn gradient_map() -> LightPollutionMap {
+        let size = 100;
+        let values = (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as f32, (i / size) as f32);
+                1.0 - (x + y) / (2.0 * size as f32)
+            })
+            .collect();
+        let bounds = MapBounds {
+            west: -0.5,
+            east: 0.5,
+            north: 52.0,
+            south: 51.0,
+            ..Default::default()
+        };
+        LightPollutionMap::new(size as u32, size as u32, values, bounds)
+    }
+
+    #[test]
+    fn samples_map_within_bounds() {
+        let map = gradient_map();
+        let north_west = map.sky_brightness(51.999, -0.499).unwrap();
+        let south_east = map.sky_brightness(51.001, 0.499).unwrap();
+        assert!((north_west - 16.0).abs() < 0.1);
+        assert!((south_east - 22.0).abs() < 0.1);
+        assert!(map.sky_brightness(50.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn finds_darker_sites_spread_out() {
+        let map = gradient_map();
+        let search = search_dark_sites(&map, 51.5, 0.0, 30.0, 4);
+        let origin = search.sky_brightness.unwrap();
+        assert_eq!(search.sites.len(), 4);
+        assert!(search.sites.windows(2).all(|w| w[0].bortle <= w[1].bortle));
+        for site in &search.sites {
+            assert!(site.sky_brightness >= origin + MIN_IMPROVEMENT);
+            assert!(site.distance_km <= 30.0);
+            // Darker skies are to the south-east
+            assert!(site.bearing > 90.0 && site.bearing < 180.0);
+        }
+        let (a, b) = (&search.sites[0], &search.sites[1]);
+        assert!(distance_km(a.latitude, a.longitude, b.latitude, b.longitude) >= 5.0);
+    }
+
+    #[test]
+    fn geodesy() {
+        // London to Paris
+        let d = distance_km(51.5074, -0.1278, 48.8566, 2.3522);
+        assert!((d - 343.5).abs() < 1.0);
+        assert_eq!(compass_point(bearing_deg(51.5, 0.0, 51.0, 0.0)), "S");
+        assert_eq!(compass_point(bearing_deg(51.5, 0.0, 51.5, 1.0)), "E");
+    }
+}
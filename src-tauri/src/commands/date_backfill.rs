@@ -0,0 +1,502 @@
+//! Capture dates for images without DATE-OBS
+//!
+//! Frames without a DATE-OBS header end up in an undated "Unknown Session"
+//! collection at import. Their night can usually still be worked out from a
+//! date in the file or folder name (e.g. `2024-10-12/` or
+//! `Light_M 42_20241012-213000.fit`), from other frames in the same folder
+//! that do have a date, or failing that from the file's modification time.
+//! Backfilling proposes a date for each image so it can be previewed, then
+//! records the accepted ones and moves the images into the session
+//! collection for their night.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::models::{Collection, Image, NewCollectionImage, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::collection_naming::collection_name_template;
+use super::scan::{
+    generate_collection_name, get_session_date, get_session_date_utc, image_session_date,
+};
+use super::sessions::new_session_collection;
+use super::subframes::image_path;
+
+/// Where an inferred capture date came from, most to least reliable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateSource {
+    FileName,
+    FolderName,
+    SiblingFrames,
+    FileModified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateProposal {
+    pub image_id: String,
+    pub filename: String,
+    /// Value for the DATE-OBS column: the night (YYYY-MM-DD) when it comes
+    /// from a name or other frames, a UTC timestamp from the file time
+    pub date_obs: String,
+    /// Observing night the image will be filed under
+    pub session_date: String,
+    pub source: DateSource,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateBackfillResult {
+    pub dry_run: bool,
+    pub proposals: Vec<DateProposal>,
+    /// Undated images no date could be found for
+    pub unresolved: usize,
+    pub images_updated: usize,
+    /// Images moved out of an undated session collection
+    pub images_moved: usize,
+    pub collections_created: usize,
+}
+
+/// A plausible calendar date from its digits
+fn parse_date(year: &str, month: &str, day: &str) -> Option<NaiveDate> {
+    let date = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+    (1990..=2100).contains(&date.year()).then_some(date)
+}
+
+/// First date written in a file or folder name, as `YYYY-MM-DD` (or with `_`
+/// or `.`) or `YYYYMMDD`, with the time of day when `-HHMMSS` follows. Runs
+/// of digits longer than a date are skipped.
+fn name_timestamp(name: &str) -> Option<(NaiveDate, Option<u32>)> {
+    let bytes = name.as_bytes();
+    let digits = |from: usize, len: usize| {
+        bytes
+            .get(from..from + len)
+            .is_some_and(|s| s.iter().all(u8::is_ascii_digit))
+    };
+    let digit_at = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_digit);
+
+    for start in 0..bytes.len() {
+        if (start > 0 && digit_at(start - 1)) || !digits(start, 4) {
+            continue;
+        }
+        let separated = matches!(bytes.get(start + 4), Some(b'-' | b'_' | b'.'))
+            && bytes.get(start + 7) == bytes.get(start + 4)
+            && digits(start + 5, 2)
+            && digits(start + 8, 2);
+        let (date, end) = if separated {
+            let date = parse_date(
+                &name[start..start + 4],
+                &name[start + 5..start + 7],
+                &name[start + 8..start + 10],
+            );
+            (date, start + 10)
+        } else if digits(start, 8) {
+            let date = parse_date(
+                &name[start..start + 4],
+                &name[start + 4..start + 6],
+                &name[start + 6..start + 8],
+            );
+            (date, start + 8)
+        } else {
+            continue;
+        };
+        let Some(date) = date.filter(|_| !digit_at(end)) else {
+            continue;
+        };
+
+        // Capture software writes the local time after the date
+        let hour = if matches!(bytes.get(end), Some(b'-' | b'_'))
+            && digits(end + 1, 6)
+            && !digit_at(end + 7)
+        {
+            name[end + 1..end + 3].parse().ok().filter(|h| *h < 24)
+        } else {
+            None
+        };
+        return Some((date, hour));
+    }
+    None
+}
+
+/// Observing night for a date and optional local hour in a name: times
+/// before noon belong to the night before
+fn name_session_date(date: NaiveDate, hour: Option<u32>) -> Option<NaiveDate> {
+    match hour {
+        Some(hour) => get_session_date(&format!("{}T{:02}:00:00", date, hour)),
+        None => Some(date),
+    }
+}
+
+/// The night most dated frames in the same folder belong to
+fn sibling_date(dates: &[NaiveDate]) -> Option<NaiveDate> {
+    let mut counts: HashMap<NaiveDate, usize> = HashMap::new();
+    for date in dates {
+        *counts.entry(*date).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(date, count)| (*count, std::cmp::Reverse(*date)))
+        .map(|(date, _)| date)
+}
+
+/// Propose dates for images without one. `modified` gives a file's
+/// modification time. Returns the proposals and how many undated images
+/// nothing was found for.
+pub fn propose_dates(
+    images: &[Image],
+    longitude: Option<f64>,
+    modified: impl Fn(&Path) -> Option<SystemTime>,
+) -> (Vec<DateProposal>, usize) {
+    let mut folder_dates: HashMap<PathBuf, Vec<NaiveDate>> = HashMap::new();
+    for image in images {
+        if let (Some(date), Some(dir)) = (
+            image_session_date(image, longitude),
+            image_path(image).and_then(Path::parent),
+        ) {
+            folder_dates
+                .entry(dir.to_path_buf())
+                .or_default()
+                .push(date);
+        }
+    }
+
+    let mut proposals = Vec::new();
+    let mut unresolved = 0;
+    for image in images {
+        if image_session_date(image, longitude).is_some() {
+            continue;
+        }
+        let path = image_path(image);
+        let file_name = path
+            .and_then(Path::file_stem)
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| image.filename.clone());
+        let folders = path
+            .into_iter()
+            .flat_map(Path::ancestors)
+            .skip(1)
+            .filter_map(Path::file_name)
+            .map(|n| n.to_string_lossy().to_string());
+
+        let from_name = name_timestamp(&file_name)
+            .and_then(|(date, hour)| name_session_date(date, hour))
+            .map(|night| (night, DateSource::FileName))
+            .or_else(|| {
+                folders
+                    .filter_map(|folder| name_timestamp(&folder))
+                    .find_map(|(date, hour)| name_session_date(date, hour))
+                    .map(|night| (night, DateSource::FolderName))
+            })
+            .or_else(|| {
+                let dates = folder_dates.get(path?.parent()?)?;
+                sibling_date(dates).map(|night| (night, DateSource::SiblingFrames))
+            })
+            .map(|(night, source)| (night.to_string(), night, source));
+
+        let proposal = from_name.or_else(|| {
+            let time: DateTime<Utc> = modified(path?)?.into();
+            let date_obs = time.naive_utc().format("%Y-%m-%dT%H:%M:%S").to_string();
+            let night = get_session_date_utc(&date_obs, longitude)?;
+            Some((date_obs, night, DateSource::FileModified))
+        });
+
+        match proposal {
+            Some((date_obs, night, source)) => proposals.push(DateProposal {
+                image_id: image.id.clone(),
+                filename: image.filename.clone(),
+                date_obs,
+                session_date: night.to_string(),
+                source,
+            }),
+            None => unresolved += 1,
+        }
+    }
+    (proposals, unresolved)
+}
+
+/// An auto-imported session collection with no night of its own, like the
+/// "Unknown Session" collection undated frames are imported into. Older
+/// imports only have the night in the name.
+pub(super) fn is_undated_session(collection: &Collection) -> bool {
+    collection
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .is_some_and(|meta| {
+            meta.get("auto_imported").and_then(|v| v.as_bool()) == Some(true)
+                && meta.get("session_date").is_none()
+        })
+        && name_timestamp(&collection.name).is_none()
+}
+
+/// Record proposed dates and move the images out of undated session
+/// collections into the one for their night
+pub fn apply_dates(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    proposals: &[DateProposal],
+    template: &str,
+    result: &mut DateBackfillResult,
+) -> QueryResult<()> {
+    for proposal in proposals {
+        let Some(image) = repository::get_image_by_id(conn, &proposal.image_id)? else {
+            continue;
+        };
+        let mut metadata: serde_json::Value = image
+            .metadata
+            .as_deref()
+            .and_then(|m| serde_json::from_str(m).ok())
+            .unwrap_or_else(|| serde_json::json!({}));
+        if let Some(obj) = metadata.as_object_mut() {
+            obj.insert(
+                "date_obs_source".to_string(),
+                serde_json::json!(proposal.source),
+            );
+        }
+        repository::update_image(
+            conn,
+            &image.id,
+            &UpdateImage {
+                date_obs: Some(proposal.date_obs.clone()),
+                metadata: Some(metadata.to_string()),
+                ..Default::default()
+            },
+        )?;
+        result.images_updated += 1;
+
+        let Ok(night) = NaiveDate::parse_from_str(&proposal.session_date, "%Y-%m-%d") else {
+            continue;
+        };
+        for source in repository::get_collections_for_image(conn, &image.id)? {
+            if !is_undated_session(&source) {
+                continue;
+            }
+            let name = generate_collection_name(template, &night, image.summary.as_deref());
+            let target = match repository::get_collection_by_name(conn, user_id, &name)? {
+                Some(existing) => existing,
+                None => {
+                    let new = new_session_collection(&source, night, name);
+                    let (created, is_new) = repository::get_or_create_auto_collection(conn, &new)?;
+                    if is_new {
+                        result.collections_created += 1;
+                    }
+                    created
+                }
+            };
+
+            repository::remove_image_from_collection(conn, &source.id, &image.id)?;
+            if !repository::is_image_in_collection(conn, &target.id, &image.id)? {
+                repository::add_image_to_collection(
+                    conn,
+                    &NewCollectionImage {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        collection_id: target.id.clone(),
+                        image_id: image.id.clone(),
+                    },
+                )?;
+            }
+            result.images_moved += 1;
+            if repository::get_collection_image_count(conn, &source.id)? == 0 {
+                repository::delete_collection(conn, &source.id)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Infer capture dates for images without one. With `dryRun` the proposals
+/// are returned for review and nothing is changed; otherwise they're applied,
+/// limited to `imageIds` when given. `longitude` is the observer's site, used
+/// to find the night for file times.
+#[tauri::command]
+pub async fn backfill_dates(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    dry_run: Option<bool>,
+    image_ids: Option<Vec<String>>,
+    longitude: Option<f64>,
+) -> Result<DateBackfillResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let template = collection_name_template(&app);
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let images =
+            repository::get_images_by_user(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        let (mut proposals, unresolved) = propose_dates(&images, longitude, |path| {
+            std::fs::metadata(path).and_then(|m| m.modified()).ok()
+        });
+        if let Some(ids) = &image_ids {
+            proposals.retain(|p| ids.contains(&p.image_id));
+        }
+
+        let mut result = DateBackfillResult {
+            dry_run,
+            unresolved,
+            ..Default::default()
+        };
+        if !dry_run {
+            conn.transaction(|conn| {
+                apply_dates(conn, &user_id, &proposals, &template, &mut result)
+            })
+            .map_err(|e| format!("Failed to backfill dates: {}", e))?;
+        }
+        result.proposals = proposals;
+        Ok::<_, String>(result)
+    })
+    .await
+    .map_err(|e| format!("Date backfill failed: {}", e))??;
+
+    log::info!(
+        "Date backfill{}: {} proposed, {} unresolved, {} updated, {} moved",
+        if dry_run { " (dry run)" } else { "" },
+        result.proposals.len(),
+        result.unresolved,
+        result.images_updated,
+        result.images_moved
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{NewCollection, NewImage};
+    use std::time::Duration;
+
+    const USER: &str = "local-user";
+
+    fn new_image(id: &str, path: &str, date_obs: Option<&str>) -> NewImage {
+        NewImage {
+            user_id: USER.to_string(),
+            filename: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            url: Some(path.to_string()),
+            summary: Some("M 42".to_string()),
+            date_obs: date_obs.map(str::to_string),
+            ..NewImage::test(id)
+        }
+    }
+
+    #[test]
+    fn dates_are_read_from_names() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(
+            name_timestamp("2024-10-12"),
+            Some((date("2024-10-12"), None))
+        );
+        assert_eq!(
+            name_timestamp("night_2024_10_12"),
+            Some((date("2024-10-12"), None))
+        );
+        assert_eq!(
+            name_timestamp("Light_M 42_10.0s_IRCUT_20250116-013012"),
+            Some((date("2025-01-16"), Some(1)))
+        );
+        assert_eq!(name_timestamp("capture_0001"), None);
+        assert_eq!(name_timestamp("frame_120250116999"), None);
+        assert_eq!(name_timestamp("2024-13-40"), None);
+
+        // Early morning frames belong to the night before
+        assert_eq!(
+            name_session_date(date("2025-01-16"), Some(1)),
+            Some(date("2025-01-15"))
+        );
+    }
+
+    #[test]
+    fn dates_are_proposed_from_best_source() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        for image in [
+            new_image(
+                "dated",
+                "/astro/M42/Light_001.fit",
+                Some("2026-01-16T04:30:00"),
+            ),
+            new_image("sibling", "/astro/M42/Light_002.fit", None),
+            new_image("folder", "/astro/2024-10-12/Light_001.fit", None),
+            new_image("name", "/astro/x/Light_20250116-013012.fit", None),
+            new_image("mtime", "/astro/y/Light_001.fit", None),
+            new_image("none", "/astro/z/Light_001.fit", None),
+        ] {
+            repository::create_image(&mut conn, &image).unwrap();
+        }
+        let images = repository::get_images_by_user(&mut conn, USER).unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let (proposals, unresolved) = propose_dates(&images, Some(0.0), |path| {
+            path.starts_with("/astro/y").then_some(mtime)
+        });
+
+        let found: HashMap<&str, (&str, DateSource)> = proposals
+            .iter()
+            .map(|p| (p.image_id.as_str(), (p.session_date.as_str(), p.source)))
+            .collect();
+        assert_eq!(found["sibling"], ("2026-01-15", DateSource::SiblingFrames));
+        assert_eq!(found["folder"], ("2024-10-12", DateSource::FolderName));
+        assert_eq!(found["name"], ("2025-01-15", DateSource::FileName));
+        assert_eq!(found["mtime"], ("2023-11-14", DateSource::FileModified));
+        assert_eq!(proposals.len(), 4);
+        assert_eq!(unresolved, 1);
+    }
+
+    #[test]
+    fn applied_dates_move_images_out_of_unknown_session() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        let unknown = repository::create_collection(
+            &mut conn,
+            &NewCollection {
+                user_id: USER.to_string(),
+                name: "Unknown Session".to_string(),
+                template: Some("astrolog".to_string()),
+                metadata: Some(r#"{"auto_imported":true}"#.to_string()),
+                ..NewCollection::test("unknown")
+            },
+        )
+        .unwrap();
+        repository::create_image(&mut conn, &new_image("a", "/astro/2024-10-12/a.fit", None))
+            .unwrap();
+        repository::add_image_to_collection(
+            &mut conn,
+            &NewCollectionImage {
+                id: "ci".to_string(),
+                collection_id: unknown.id.clone(),
+                image_id: "a".to_string(),
+            },
+        )
+        .unwrap();
+
+        let images = repository::get_images_by_user(&mut conn, USER).unwrap();
+        let (proposals, _) = propose_dates(&images, None, |_| None);
+        let mut result = DateBackfillResult::default();
+        apply_dates(&mut conn, USER, &proposals, "{date} {object}", &mut result).unwrap();
+        assert_eq!((result.images_updated, result.images_moved), (1, 1));
+        assert_eq!(result.collections_created, 1);
+
+        let image = repository::get_image_by_id(&mut conn, "a")
+            .unwrap()
+            .unwrap();
+        assert_eq!(image.date_obs.as_deref(), Some("2024-10-12"));
+        let collections = repository::get_collections_for_image(&mut conn, "a").unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].name, "2024-10-12 M 42");
+        assert!(repository::get_collection_by_id(&mut conn, "unknown")
+            .unwrap()
+            .is_none());
+    }
+}
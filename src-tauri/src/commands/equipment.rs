@@ -0,0 +1,538 @@
+//! Equipment inventory commands (telescopes, cameras, filters, mounts, reducers)
+
+use chrono::NaiveDate;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::models::{
+    Equipment, EquipmentProfile, Image, NewEquipment, NewEquipmentProfile, NewImageEquipment,
+    UpdateEquipment, UpdateEquipmentProfile,
+};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::notes::{delete_entity_notes, NoteEntity};
+use super::scan::{
+    extract_float_value, extract_int_value, image_session_date, metadata_header_value,
+    metadata_number_value,
+};
+
+const EQUIPMENT_KINDS: &[&str] = &["telescope", "camera", "filter", "mount", "reducer"];
+
+/// Margin applied either side of a computed pixel scale when deriving solve bounds
+pub(crate) const SCALE_MARGIN: f64 = 0.15;
+
+fn validate_kind(kind: &str) -> Result<(), String> {
+    if EQUIPMENT_KINDS.contains(&kind) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown equipment kind '{}' (expected one of: {})",
+            kind,
+            EQUIPMENT_KINDS.join(", ")
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateEquipmentInput {
+    pub kind: String,
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub match_patterns: Option<String>,
+    pub focal_length_mm: Option<f64>,
+    pub aperture_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<i32>,
+    pub sensor_height_px: Option<i32>,
+    pub reduction_factor: Option<f64>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateEquipmentInput {
+    pub id: String,
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub match_patterns: Option<String>,
+    pub focal_length_mm: Option<f64>,
+    pub aperture_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<i32>,
+    pub sensor_height_px: Option<i32>,
+    pub reduction_factor: Option<f64>,
+    pub notes: Option<String>,
+    pub metadata: Option<String>,
+    pub retired: Option<bool>,
+}
+
+#[tauri::command]
+pub fn get_equipment(state: State<'_, AppState>) -> Result<Vec<Equipment>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_equipment(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_equipment_item(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Option<Equipment>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_equipment_by_id(&mut conn, &id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_equipment(
+    state: State<'_, AppState>,
+    input: CreateEquipmentInput,
+) -> Result<Equipment, String> {
+    validate_kind(&input.kind)?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let new_equipment = NewEquipment {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        kind: input.kind,
+        name: input.name,
+        manufacturer: input.manufacturer,
+        model: input.model,
+        match_patterns: input.match_patterns,
+        focal_length_mm: input.focal_length_mm,
+        aperture_mm: input.aperture_mm,
+        pixel_size_um: input.pixel_size_um,
+        sensor_width_px: input.sensor_width_px,
+        sensor_height_px: input.sensor_height_px,
+        reduction_factor: input.reduction_factor,
+        notes: input.notes,
+        metadata: None,
+        retired: false,
+    };
+
+    repository::create_equipment(&mut conn, &new_equipment).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_equipment(
+    state: State<'_, AppState>,
+    input: UpdateEquipmentInput,
+) -> Result<Equipment, String> {
+    if let Some(kind) = &input.kind {
+        validate_kind(kind)?;
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let update = UpdateEquipment {
+        kind: input.kind,
+        name: input.name,
+        manufacturer: input.manufacturer,
+        model: input.model,
+        match_patterns: input.match_patterns,
+        focal_length_mm: input.focal_length_mm,
+        aperture_mm: input.aperture_mm,
+        pixel_size_um: input.pixel_size_um,
+        sensor_width_px: input.sensor_width_px,
+        sensor_height_px: input.sensor_height_px,
+        reduction_factor: input.reduction_factor,
+        notes: input.notes,
+        metadata: input.metadata,
+        retired: input.retired,
+    };
+
+    repository::update_equipment(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_equipment(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let deleted = repository::delete_equipment(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())?;
+    if deleted {
+        delete_entity_notes(&app, &mut conn, &state.user_id, NoteEntity::Equipment, &id);
+    }
+    Ok(deleted)
+}
+
+/// Manually link an image to a piece of equipment
+#[tauri::command]
+pub fn link_image_equipment(
+    state: State<'_, AppState>,
+    image_id: String,
+    equipment_id: String,
+) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let entry = NewImageEquipment {
+        id: uuid::Uuid::new_v4().to_string(),
+        image_id,
+        equipment_id,
+    };
+    repository::link_image_equipment(&mut conn, &entry)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn unlink_image_equipment(
+    state: State<'_, AppState>,
+    image_id: String,
+    equipment_id: String,
+) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::unlink_image_equipment(&mut conn, &image_id, &equipment_id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Get all equipment linked to an image
+#[tauri::command]
+pub fn get_image_equipment(
+    state: State<'_, AppState>,
+    image_id: String,
+) -> Result<Vec<Equipment>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_equipment_for_image(&mut conn, &image_id).map_err(|e| e.to_string())
+}
+
+/// Get images shot with a rig (linked to all of the given equipment)
+#[tauri::command]
+pub fn get_images_by_rig(
+    state: State<'_, AppState>,
+    equipment_ids: Vec<String>,
+) -> Result<Vec<Image>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_images_by_rig(&mut conn, &state.user_id, &equipment_ids)
+        .map_err(|e| e.to_string())
+}
+
+/// Re-run TELESCOP/INSTRUME matching against every image in the library.
+///
+/// Useful after adding equipment or editing match patterns, since
+/// auto-matching during import only sees the equipment that existed then.
+/// Returns the number of new image-equipment links created.
+#[tauri::command]
+pub fn match_equipment_to_images(state: State<'_, AppState>) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = repository::get_images_by_user(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+
+    let mut linked = 0;
+    for image in images {
+        let Some(meta) = image.metadata.as_deref() else {
+            continue;
+        };
+        let telescope = metadata_header_value(meta, "telescope", "TELESCOP");
+        let instrument = metadata_header_value(meta, "instrument", "INSTRUME");
+        if telescope.is_none() && instrument.is_none() {
+            continue;
+        }
+
+        linked += repository::auto_link_image_equipment(
+            &mut conn,
+            &state.user_id,
+            &image.id,
+            telescope.as_deref(),
+            instrument.as_deref(),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    log::info!("Equipment matching linked {} images", linked);
+    Ok(linked)
+}
+
+// ============================================================================
+// Equipment profiles (rig presets for plate solving and processing)
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateEquipmentProfileInput {
+    pub name: String,
+    pub telescope_id: Option<String>,
+    pub camera_id: Option<String>,
+    pub reducer_id: Option<String>,
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    /// Partial ProcessingParams (camelCase keys) applied over target-type defaults
+    pub processing_params: Option<serde_json::Value>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateEquipmentProfileInput {
+    pub id: String,
+    pub name: Option<String>,
+    pub telescope_id: Option<String>,
+    pub camera_id: Option<String>,
+    pub reducer_id: Option<String>,
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    pub processing_params: Option<serde_json::Value>,
+    pub notes: Option<String>,
+}
+
+fn processing_params_to_string(value: Option<serde_json::Value>) -> Result<Option<String>, String> {
+    match value {
+        None => Ok(None),
+        Some(v) if v.is_object() => Ok(Some(v.to_string())),
+        Some(_) => Err("processing_params must be a JSON object".to_string()),
+    }
+}
+
+/// Image scale in arcsec/pixel for the given optics
+pub fn pixel_scale_arcsec(focal_length_mm: f64, pixel_size_um: f64, reduction_factor: f64) -> f64 {
+    206.265 * pixel_size_um / (focal_length_mm * reduction_factor)
+}
+
+/// Plate solving scale bounds (arcsec/pixel) for a profile.
+///
+/// Explicit bounds on the profile win; otherwise they are derived from the
+/// telescope focal length, reducer factor and camera pixel size.
+pub fn profile_scale_bounds(
+    conn: &mut SqliteConnection,
+    profile: &EquipmentProfile,
+) -> Result<Option<(f64, f64)>, diesel::result::Error> {
+    if let (Some(lower), Some(upper)) = (profile.scale_lower, profile.scale_upper) {
+        return Ok(Some((lower, upper)));
+    }
+
+    let mut lookup = |id: &Option<String>| -> Result<Option<Equipment>, diesel::result::Error> {
+        match id {
+            Some(id) => repository::get_equipment_by_id(conn, id),
+            None => Ok(None),
+        }
+    };
+    let telescope = lookup(&profile.telescope_id)?;
+    let camera = lookup(&profile.camera_id)?;
+    let reducer = lookup(&profile.reducer_id)?;
+
+    let focal_length = telescope.and_then(|t| t.focal_length_mm);
+    let pixel_size = camera.and_then(|c| c.pixel_size_um);
+    let reduction = reducer.and_then(|r| r.reduction_factor).unwrap_or(1.0);
+
+    Ok(match (focal_length, pixel_size) {
+        (Some(f), Some(p)) if f > 0.0 && p > 0.0 && reduction > 0.0 => {
+            let scale = pixel_scale_arcsec(f, p, reduction);
+            Some((scale * (1.0 - SCALE_MARGIN), scale * (1.0 + SCALE_MARGIN)))
+        }
+        _ => None,
+    })
+}
+
+#[tauri::command]
+pub fn get_equipment_profiles(state: State<'_, AppState>) -> Result<Vec<EquipmentProfile>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_equipment_profiles(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_equipment_profile(
+    state: State<'_, AppState>,
+    input: CreateEquipmentProfileInput,
+) -> Result<EquipmentProfile, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let new_profile = NewEquipmentProfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        name: input.name,
+        telescope_id: input.telescope_id,
+        camera_id: input.camera_id,
+        reducer_id: input.reducer_id,
+        scale_lower: input.scale_lower,
+        scale_upper: input.scale_upper,
+        processing_params: processing_params_to_string(input.processing_params)?,
+        notes: input.notes,
+    };
+
+    repository::create_equipment_profile(&mut conn, &new_profile).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_equipment_profile(
+    state: State<'_, AppState>,
+    input: UpdateEquipmentProfileInput,
+) -> Result<EquipmentProfile, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let update = UpdateEquipmentProfile {
+        name: input.name,
+        telescope_id: input.telescope_id,
+        camera_id: input.camera_id,
+        reducer_id: input.reducer_id,
+        scale_lower: input.scale_lower,
+        scale_upper: input.scale_upper,
+        processing_params: processing_params_to_string(input.processing_params)?,
+        notes: input.notes,
+    };
+
+    repository::update_equipment_profile(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_equipment_profile(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_equipment_profile(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Get the profile matching an image's linked equipment, if any
+#[tauri::command]
+pub fn get_image_equipment_profile(
+    state: State<'_, AppState>,
+    image_id: String,
+) -> Result<Option<EquipmentProfile>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::find_profile_for_image(&mut conn, &state.user_id, &image_id)
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Equipment usage metrics
+// ============================================================================
+
+/// Usage totals for one piece of equipment, derived from its linked images
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EquipmentUsage {
+    pub equipment_id: String,
+    pub name: String,
+    pub kind: String,
+    pub image_count: usize,
+    /// Frames captured (stacks count every integrated sub)
+    pub shutter_actuations: i64,
+    pub total_exposure_hours: f64,
+    /// Distinct observing nights (see `image_session_date`)
+    pub nights_used: usize,
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+}
+
+/// Sum frames, exposure and observing nights over an equipment item's images
+fn summarize_equipment_usage(item: &Equipment, images: &[Image]) -> EquipmentUsage {
+    let mut shutter_actuations: i64 = 0;
+    let mut exposure_seconds = 0.0;
+    let mut nights: std::collections::BTreeSet<NaiveDate> = std::collections::BTreeSet::new();
+
+    for image in images {
+        let meta = image.metadata.as_deref().unwrap_or("{}");
+        let frames = metadata_number_value(meta, "stacked_frames", &["STACKCNT", "NCOMBINE"])
+            .and_then(|v| extract_int_value(&v))
+            .filter(|f| *f > 0)
+            .unwrap_or(1) as i64;
+        let exposure = image
+            .exposure
+            .or_else(|| {
+                metadata_number_value(meta, "exposure", &["EXPTIME", "EXPOSURE"])
+                    .and_then(|v| extract_float_value(&v))
+            })
+            .unwrap_or(0.0);
+
+        shutter_actuations += frames;
+        exposure_seconds += exposure * frames as f64;
+
+        let night = image_session_date(image, None).unwrap_or_else(|| image.created_at.date());
+        nights.insert(night);
+    }
+
+    EquipmentUsage {
+        equipment_id: item.id.clone(),
+        name: item.name.clone(),
+        kind: item.kind.clone(),
+        image_count: images.len(),
+        shutter_actuations,
+        total_exposure_hours: exposure_seconds / 3600.0,
+        nights_used: nights.len(),
+        first_used: nights.first().map(|d| d.to_string()),
+        last_used: nights.last().map(|d| d.to_string()),
+    }
+}
+
+/// Get shutter actuations, exposure hours and nights used per equipment item.
+///
+/// Pass `equipment_id` to get usage for a single item.
+#[tauri::command]
+pub fn get_equipment_usage(
+    state: State<'_, AppState>,
+    equipment_id: Option<String>,
+) -> Result<Vec<EquipmentUsage>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let items = repository::get_equipment(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+
+    let mut usage = Vec::new();
+    for item in items {
+        if equipment_id.as_ref().is_some_and(|id| *id != item.id) {
+            continue;
+        }
+        let images = repository::get_images_by_rig(&mut conn, &state.user_id, &[item.id.clone()])
+            .map_err(|e| e.to_string())?;
+        usage.push(summarize_equipment_usage(&item, &images));
+    }
+
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_scale_arcsec_seestar_s50() {
+        // Seestar S50: 250mm focal length, 2.9um pixels -> ~2.39"/px
+        let scale = pixel_scale_arcsec(250.0, 2.9, 1.0);
+        assert!((scale - 2.393).abs() < 0.01, "{}", scale);
+    }
+
+    #[test]
+    fn pixel_scale_arcsec_with_reducer() {
+        let native = pixel_scale_arcsec(1000.0, 3.76, 1.0);
+        let reduced = pixel_scale_arcsec(1000.0, 3.76, 0.7);
+        assert!((reduced - native / 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn processing_params_must_be_object() {
+        assert_eq!(processing_params_to_string(None).unwrap(), None);
+        assert!(processing_params_to_string(Some(serde_json::json!({"contrast": 1.5}))).is_ok());
+        assert!(processing_params_to_string(Some(serde_json::json!([1, 2]))).is_err());
+    }
+
+    fn make_image(id: &str, metadata: &str) -> Image {
+        Image {
+            metadata: Some(metadata.to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn summarize_equipment_usage_counts_frames_and_nights() {
+        let camera = Equipment::test("cam", "camera", "ASI585MC");
+        let images = vec![
+            // Stack of 360 x 10s on the night of Jan 15 (after midnight)
+            make_image(
+                "stack",
+                r#"{"exposure":10.0,"stacked_frames":360,"date_obs":"2026-01-16T02:00:00"}"#,
+            ),
+            // Single 300s sub the same night, raw header format
+            make_image(
+                "sub",
+                r#"{"EXPTIME":"Some(RealFloatingNumber(300.0))","DATE-OBS":"Some(CharacterString(\"2026-01-15T22:00:00\"))"}"#,
+            ),
+        ];
+
+        let usage = summarize_equipment_usage(&camera, &images);
+        assert_eq!(usage.image_count, 2);
+        assert_eq!(usage.shutter_actuations, 361);
+        assert!((usage.total_exposure_hours - 3900.0 / 3600.0).abs() < 1e-9);
+        assert_eq!(usage.nights_used, 1);
+        assert_eq!(usage.first_used.as_deref(), Some("2026-01-15"));
+    }
+}
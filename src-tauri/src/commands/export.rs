@@ -0,0 +1,599 @@
+//! Export commands: AstroBin acquisition CSV and generic CSV/JSON data export
+
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::filters::normalize_filter_name;
+use super::scan::{
+    extract_float_value, extract_int_value, metadata_header_value, metadata_number_value,
+    session_date_from_metadata,
+};
+
+/// Column order expected by AstroBin's acquisition CSV import
+const ASTROBIN_COLUMNS: &[&str] = &[
+    "date",
+    "filter",
+    "number",
+    "duration",
+    "iso",
+    "binning",
+    "gain",
+    "sensorCooling",
+    "fNumber",
+    "darks",
+    "flats",
+    "flatDarks",
+    "bias",
+    "bortle",
+    "meanSqm",
+    "meanFwhm",
+    "temperature",
+];
+
+/// Input for AstroBin CSV export
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AstrobinExportInput {
+    /// Images to export (subs or stacks)
+    pub image_ids: Option<Vec<String>>,
+    /// Export every image in a collection instead of an explicit list
+    pub collection_id: Option<String>,
+    /// Map of normalized filter name (e.g. "Ha") to AstroBin filter ID.
+    /// Unmapped filters are written by name.
+    pub filter_ids: Option<HashMap<String, String>>,
+    /// Write the CSV to this path as well as returning it
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AstrobinExportResult {
+    pub csv: String,
+    /// Number of acquisition rows (excluding header)
+    pub rows: usize,
+    /// Number of images that contributed
+    pub images: usize,
+    pub output_path: Option<String>,
+}
+
+/// Acquisition fields pulled from one image's FITS metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Acquisition {
+    pub date: Option<String>,
+    pub filter: Option<String>,
+    pub frames: i64,
+    pub duration: Option<f64>,
+    pub binning: Option<i32>,
+    pub gain: Option<i32>,
+    pub sensor_cooling: Option<i32>,
+    pub f_number: Option<f64>,
+    pub temperature: Option<f64>,
+}
+
+fn read_float(meta: &str, field: &str, headers: &[&str]) -> Option<f64> {
+    metadata_number_value(meta, field, headers).and_then(|v| extract_float_value(&v))
+}
+
+fn read_int(meta: &str, field: &str, headers: &[&str]) -> Option<i32> {
+    metadata_number_value(meta, field, headers).and_then(|v| extract_int_value(&v))
+}
+
+pub fn acquisition_from_metadata(meta: &str) -> Acquisition {
+    let frames = read_int(meta, "stacked_frames", &["STACKCNT", "NCOMBINE"])
+        .filter(|f| *f > 0)
+        .unwrap_or(1) as i64;
+
+    let f_number = read_float(meta, "focal_ratio", &["FOCRATIO"]).or_else(|| {
+        let focal = read_float(meta, "focal_length", &["FOCALLEN"])?;
+        let aperture = read_float(meta, "aperture", &["APERTURE", "APTDIA"])?;
+        (aperture > 0.0).then(|| focal / aperture)
+    });
+
+    Acquisition {
+        date: session_date_from_metadata(meta, None).map(|d| d.to_string()),
+        filter: metadata_header_value(meta, "filter", "FILTER")
+            .and_then(|f| normalize_filter_name(&f)),
+        frames,
+        duration: read_float(meta, "exposure", &["EXPTIME", "EXPOSURE"]),
+        binning: read_int(meta, "binning", &["XBINNING"]),
+        gain: read_int(meta, "gain", &["GAIN"]),
+        sensor_cooling: read_int(meta, "sensor_temp", &["SET-TEMP", "CCD-TEMP"]),
+        f_number,
+        temperature: read_float(meta, "ambient_temp", &["AMB-TEMP", "FOCTEMP"]),
+    }
+}
+
+/// Format a number without a trailing ".0" for whole values
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Aggregate per-image acquisitions into AstroBin rows.
+///
+/// Frames sharing date, filter, duration, binning, gain and cooling are
+/// summed into one row, matching how AstroBin groups acquisition sessions.
+fn build_astrobin_rows(
+    acquisitions: &[Acquisition],
+    filter_ids: &HashMap<String, String>,
+) -> Vec<Vec<String>> {
+    let mut grouped: BTreeMap<Vec<String>, (i64, Option<f64>)> = BTreeMap::new();
+
+    for acq in acquisitions {
+        let filter = acq
+            .filter
+            .as_ref()
+            .map(|name| filter_ids.get(name).cloned().unwrap_or_else(|| name.clone()))
+            .unwrap_or_default();
+        let key = vec![
+            acq.date.clone().unwrap_or_default(),
+            filter,
+            acq.duration.map(format_number).unwrap_or_default(),
+            acq.binning.map(|b| b.to_string()).unwrap_or_default(),
+            acq.gain.map(|g| g.to_string()).unwrap_or_default(),
+            acq.sensor_cooling.map(|t| t.to_string()).unwrap_or_default(),
+            acq.f_number.map(format_number).unwrap_or_default(),
+        ];
+        let entry = grouped.entry(key).or_insert((0, None));
+        entry.0 += acq.frames;
+        if entry.1.is_none() {
+            entry.1 = acq.temperature;
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(key, (number, temperature))| {
+            let [date, filter, duration, binning, gain, cooling, f_number]: [String; 7] =
+                key.try_into().expect("astrobin row key has 7 fields");
+            vec![
+                date,
+                filter,
+                number.to_string(),
+                duration,
+                String::new(), // iso
+                binning,
+                gain,
+                cooling,
+                f_number,
+                String::new(), // darks
+                String::new(), // flats
+                String::new(), // flatDarks
+                String::new(), // bias
+                String::new(), // bortle
+                String::new(), // meanSqm
+                String::new(), // meanFwhm
+                temperature.map(format_number).unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+fn rows_to_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for row in rows {
+        let line: Vec<String> = row.iter().map(|v| csv_escape(v)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Export AstroBin acquisition CSV aggregated from the FITS metadata of the
+/// selected images (or every image in a collection)
+#[tauri::command]
+pub fn export_astrobin_csv(
+    state: State<'_, AppState>,
+    input: AstrobinExportInput,
+) -> Result<AstrobinExportResult, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let images: Vec<Image> = if let Some(ids) = &input.image_ids {
+        let mut images = Vec::new();
+        for id in ids {
+            if let Some(image) = repository::get_image_by_id(&mut conn, id).map_err(|e| e.to_string())? {
+                images.push(image);
+            }
+        }
+        images
+    } else if let Some(collection_id) = &input.collection_id {
+        repository::get_images_in_collection(&mut conn, collection_id).map_err(|e| e.to_string())?
+    } else {
+        return Err("Either imageIds or collectionId is required".to_string());
+    };
+
+    let acquisitions: Vec<Acquisition> = images
+        .iter()
+        .filter_map(|img| img.metadata.as_deref())
+        .map(acquisition_from_metadata)
+        .collect();
+
+    let rows = build_astrobin_rows(&acquisitions, &input.filter_ids.unwrap_or_default());
+    let csv = rows_to_csv(ASTROBIN_COLUMNS, &rows);
+
+    if let Some(path) = &input.output_path {
+        std::fs::write(path, &csv).map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+
+    Ok(AstrobinExportResult {
+        rows: rows.len(),
+        images: acquisitions.len(),
+        csv,
+        output_path: input.output_path,
+    })
+}
+
+// ============================================================================
+// Generic CSV/JSON export
+// ============================================================================
+
+const IMAGE_COLUMNS: &[&str] = &[
+    "id", "filename", "summary", "description", "url", "fits_url", "tags", "favorite",
+    "location", "created_at", "updated_at",
+];
+const TODO_COLUMNS: &[&str] = &[
+    "id", "name", "ra", "dec", "magnitude", "size", "object_type", "completed", "completed_at",
+    "goal_time", "flagged", "tags", "notes", "added_at",
+];
+const SESSION_COLUMNS: &[&str] = &[
+    "id", "name", "description", "image_count", "tags", "favorite", "archived", "created_at",
+];
+
+/// Optional filters for `export_data`; each applies only where the entity has the field
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFilter {
+    /// Images: only images in this collection
+    pub collection_id: Option<String>,
+    /// Substring match against the comma-separated tags field
+    pub tag: Option<String>,
+    /// Case-insensitive substring match against name/summary/filename
+    pub search: Option<String>,
+    /// Todos: completed state
+    pub completed: Option<bool>,
+    /// Inclusive lower bound on created_at (YYYY-MM-DD)
+    pub date_from: Option<String>,
+    /// Inclusive upper bound on created_at (YYYY-MM-DD)
+    pub date_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDataInput {
+    /// "images", "todos", or "sessions"
+    pub entity: String,
+    /// "csv" or "json"
+    pub format: String,
+    /// Columns to include (defaults to the entity's standard columns)
+    pub columns: Option<Vec<String>>,
+    pub filter: Option<ExportFilter>,
+    /// Write the export to this path as well as returning it
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDataResult {
+    pub content: String,
+    pub rows: usize,
+    pub columns: Vec<String>,
+    pub output_path: Option<String>,
+}
+
+fn default_columns(entity: &str) -> Result<&'static [&'static str], String> {
+    match entity {
+        "images" => Ok(IMAGE_COLUMNS),
+        "todos" => Ok(TODO_COLUMNS),
+        "sessions" => Ok(SESSION_COLUMNS),
+        _ => Err(format!(
+            "Unknown export entity '{}' (expected images, todos, or sessions)",
+            entity
+        )),
+    }
+}
+
+/// Check a serialized record against the filter
+fn record_matches(record: &serde_json::Value, filter: &ExportFilter) -> bool {
+    let text = |key: &str| record.get(key).and_then(|v| v.as_str()).unwrap_or("");
+
+    if let Some(tag) = &filter.tag {
+        if !text("tags").to_lowercase().contains(&tag.to_lowercase()) {
+            return false;
+        }
+    }
+    if let Some(search) = &filter.search {
+        let needle = search.to_lowercase();
+        let hit = ["name", "summary", "filename"]
+            .iter()
+            .any(|key| text(key).to_lowercase().contains(&needle));
+        if !hit {
+            return false;
+        }
+    }
+    if let Some(completed) = filter.completed {
+        if record.get("completed").and_then(|v| v.as_bool()) != Some(completed) {
+            return false;
+        }
+    }
+    // created_at serializes as "YYYY-MM-DDTHH:MM:SS", so the date prefix compares lexically
+    let created = text("created_at").get(..10).unwrap_or("");
+    if let Some(from) = &filter.date_from {
+        if created < from.as_str() {
+            return false;
+        }
+    }
+    if let Some(to) = &filter.date_to {
+        if created > to.as_str() {
+            return false;
+        }
+    }
+    true
+}
+
+fn csv_cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Render records as CSV or JSON with only the selected columns
+fn render_records(
+    records: &[serde_json::Value],
+    columns: &[String],
+    format: &str,
+) -> Result<String, String> {
+    match format {
+        "csv" => {
+            let header: Vec<&str> = columns.iter().map(String::as_str).collect();
+            let rows: Vec<Vec<String>> = records
+                .iter()
+                .map(|record| columns.iter().map(|col| csv_cell(record.get(col))).collect())
+                .collect();
+            Ok(rows_to_csv(&header, &rows))
+        }
+        "json" => {
+            let selected: Vec<serde_json::Value> = records
+                .iter()
+                .map(|record| {
+                    let obj: serde_json::Map<String, serde_json::Value> = columns
+                        .iter()
+                        .map(|col| {
+                            (col.clone(), record.get(col).cloned().unwrap_or(serde_json::Value::Null))
+                        })
+                        .collect();
+                    serde_json::Value::Object(obj)
+                })
+                .collect();
+            serde_json::to_string_pretty(&selected).map_err(|e| e.to_string())
+        }
+        _ => Err(format!("Unknown export format '{}' (expected csv or json)", format)),
+    }
+}
+
+/// List the default columns available for an export entity
+#[tauri::command]
+pub fn get_export_columns(entity: String) -> Result<Vec<String>, String> {
+    Ok(default_columns(&entity)?.iter().map(|c| c.to_string()).collect())
+}
+
+/// Export images, todos, or sessions (session collections) as CSV or JSON
+#[tauri::command]
+pub fn export_data(
+    state: State<'_, AppState>,
+    input: ExportDataInput,
+) -> Result<ExportDataResult, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    export_records(&mut conn, &state.user_id, input)
+}
+
+/// The export behind `export_data`
+pub(crate) fn export_records(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    input: ExportDataInput,
+) -> Result<ExportDataResult, String> {
+    let defaults = default_columns(&input.entity)?;
+    let columns: Vec<String> = match input.columns.filter(|c| !c.is_empty()) {
+        Some(columns) => columns,
+        None => defaults.iter().map(|c| c.to_string()).collect(),
+    };
+    let filter = input.filter.unwrap_or_default();
+
+    let records: Vec<serde_json::Value> = match input.entity.as_str() {
+        "images" => {
+            let images = match &filter.collection_id {
+                Some(collection_id) => repository::get_images_in_collection(conn, collection_id),
+                None => repository::get_images_by_user(conn, user_id),
+            }
+            .map_err(|e| e.to_string())?;
+            images
+                .iter()
+                .filter_map(|img| serde_json::to_value(img).ok())
+                .collect()
+        }
+        "todos" => repository::get_todos(conn, user_id)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .filter_map(|todo| serde_json::to_value(todo).ok())
+            .collect(),
+        "sessions" => {
+            let sessions: Vec<_> = repository::get_all_collections(conn, user_id)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|c| c.template.as_deref() == Some("astrolog"))
+                .collect();
+            let mut records = Vec::with_capacity(sessions.len());
+            for session in sessions {
+                let count = repository::get_collection_image_count(conn, &session.id)
+                    .map_err(|e| e.to_string())?;
+                if let Ok(mut value) = serde_json::to_value(&session) {
+                    value["image_count"] = serde_json::json!(count);
+                    records.push(value);
+                }
+            }
+            records
+        }
+        _ => unreachable!("entity validated by default_columns"),
+    };
+
+    let records: Vec<serde_json::Value> = records
+        .into_iter()
+        .filter(|record| record_matches(record, &filter))
+        .collect();
+    let content = render_records(&records, &columns, &input.format)?;
+
+    if let Some(path) = &input.output_path {
+        std::fs::write(path, &content).map_err(|e| format!("Failed to write export: {}", e))?;
+    }
+
+    Ok(ExportDataResult {
+        content,
+        rows: records.len(),
+        columns,
+        output_path: input.output_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquisition_from_scan_metadata() {
+        let meta = r#"{"date_obs":"2026-01-16T02:00:00","filter":"Ha 7nm","exposure":300.0,
+            "gain":100,"stacked_frames":12,"focal_length":400.0,"aperture":80.0,"raw_headers":{}}"#;
+        let acq = acquisition_from_metadata(meta);
+        assert_eq!(acq.date.as_deref(), Some("2026-01-15"));
+        assert_eq!(acq.filter.as_deref(), Some("Ha"));
+        assert_eq!(acq.frames, 12);
+        assert_eq!(acq.duration, Some(300.0));
+        assert_eq!(acq.gain, Some(100));
+        assert_eq!(acq.f_number, Some(5.0));
+    }
+
+    #[test]
+    fn acquisition_from_raw_headers() {
+        let meta = r#"{"EXPTIME":"Some(RealFloatingNumber(10.0))","XBINNING":"Some(IntegerNumber(2))",
+            "SET-TEMP":"Some(RealFloatingNumber(-10.0))"}"#;
+        let acq = acquisition_from_metadata(meta);
+        assert_eq!(acq.frames, 1);
+        assert_eq!(acq.duration, Some(10.0));
+        assert_eq!(acq.binning, Some(2));
+        assert_eq!(acq.sensor_cooling, Some(-10));
+    }
+
+    #[test]
+    fn build_astrobin_rows_groups_matching_frames() {
+        let sub = Acquisition {
+            date: Some("2026-01-15".to_string()),
+            filter: Some("Ha".to_string()),
+            frames: 1,
+            duration: Some(300.0),
+            gain: Some(100),
+            ..Default::default()
+        };
+        let mut oiii = sub.clone();
+        oiii.filter = Some("OIII".to_string());
+
+        let mut ids = HashMap::new();
+        ids.insert("Ha".to_string(), "4321".to_string());
+
+        let rows = build_astrobin_rows(&[sub.clone(), sub, oiii], &ids);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][1], "4321");
+        assert_eq!(rows[0][2], "2");
+        assert_eq!(rows[0][3], "300");
+        assert_eq!(rows[1][1], "OIII");
+        assert_eq!(rows[1].len(), ASTROBIN_COLUMNS.len());
+    }
+
+    #[test]
+    fn csv_escape_quotes_when_needed() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn format_number_trims_trailing_zeros() {
+        assert_eq!(format_number(300.0), "300");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(4.123), "4.12");
+    }
+
+    #[test]
+    fn record_matches_applies_filters() {
+        let record = serde_json::json!({
+            "name": "M42 Orion",
+            "tags": "nebula, winter",
+            "completed": true,
+            "created_at": "2026-01-15T20:00:00",
+        });
+        assert!(record_matches(&record, &ExportFilter::default()));
+        assert!(record_matches(
+            &record,
+            &ExportFilter {
+                tag: Some("Winter".to_string()),
+                search: Some("orion".to_string()),
+                completed: Some(true),
+                date_from: Some("2026-01-01".to_string()),
+                date_to: Some("2026-01-15".to_string()),
+                ..Default::default()
+            }
+        ));
+        assert!(!record_matches(
+            &record,
+            &ExportFilter {
+                date_from: Some("2026-01-16".to_string()),
+                ..Default::default()
+            }
+        ));
+        assert!(!record_matches(
+            &record,
+            &ExportFilter {
+                completed: Some(false),
+                ..Default::default()
+            }
+        ));
+    }
+
+    #[test]
+    fn render_records_selects_columns() {
+        let records = vec![serde_json::json!({"id": "a", "name": "M31, Andromeda", "extra": 1})];
+        let columns = vec!["id".to_string(), "name".to_string(), "missing".to_string()];
+
+        let csv = render_records(&records, &columns, "csv").unwrap();
+        assert_eq!(csv, "id,name,missing\na,\"M31, Andromeda\",\n");
+
+        let json = render_records(&records, &columns, "json").unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["name"], "M31, Andromeda");
+        assert!(parsed[0].get("extra").is_none());
+        assert!(parsed[0]["missing"].is_null());
+
+        assert!(render_records(&records, &columns, "xml").is_err());
+    }
+}
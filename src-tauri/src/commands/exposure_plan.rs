@@ -0,0 +1,543 @@
+//! Acquisition planning: sub-exposure length and sub count for a target
+//!
+//! Sub length is chosen so the sky background swamps the camera's read
+//! noise (the read noise adds at most a few percent to the total noise).
+//! The number of subs then follows from the SNR wanted on the target's
+//! average surface brightness. Fluxes come from a V-band zero point, so
+//! the results are estimates to plan a night with, not exact figures.
+
+use std::f64::consts::PI;
+
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{AstronomyTodo, Equipment, UpdateAstronomyTodo};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::equipment::pixel_scale_arcsec;
+use super::filters::{filter_band, normalize_filter_name};
+
+/// Photons s⁻¹ cm⁻² nm⁻¹ from a magnitude 0 source (V band)
+const PHOTONS_MAG0: f64 = 1.0e4;
+const DEFAULT_QE: f64 = 0.8;
+const DEFAULT_TARGET_SNR: f64 = 30.0;
+const DEFAULT_MAX_SUB_EXPOSURE: f64 = 600.0;
+/// Read noise may add this fraction to the sky-limited noise
+const DEFAULT_READ_NOISE_CONTRIBUTION: f64 = 0.05;
+/// Sub lengths offered by capture software, in seconds
+const SUB_STEPS: [f64; 20] = [
+    1.0, 2.0, 3.0, 5.0, 10.0, 15.0, 20.0, 30.0, 45.0, 60.0, 90.0, 120.0, 180.0, 240.0, 300.0,
+    420.0, 600.0, 900.0, 1200.0, 1800.0,
+];
+
+/// Zenith sky brightness (mag/arcsec²) for a Bortle class
+pub fn bortle_sky_brightness(bortle: u8) -> Option<f64> {
+    match bortle {
+        1 => Some(21.85),
+        2 => Some(21.6),
+        3 => Some(21.4),
+        4 => Some(20.85),
+        5 => Some(19.75),
+        6 => Some(18.8),
+        7 => Some(18.25),
+        8 => Some(17.8),
+        9 => Some(17.5),
+        _ => None,
+    }
+}
+
+/// Bortle class whose typical sky brightness is closest to `sky_brightness`
+pub fn bortle_class(sky_brightness: f64) -> u8 {
+    (1..=9)
+        .min_by(|a, b| {
+            let distance = |class| (bortle_sky_brightness(class).unwrap() - sky_brightness).abs();
+            distance(*a).total_cmp(&distance(*b))
+        })
+        .unwrap()
+}
+
+/// Typical bandwidth in nm for a canonical filter name; no filter (or an
+/// unknown one) is treated as a luminance/OSC bandpass
+pub fn default_bandwidth_nm(filter: Option<&str>) -> f64 {
+    match filter {
+        Some("R" | "G" | "B") => 100.0,
+        Some(name) => match filter_band(name) {
+            "narrowband" => 7.0,
+            // Two ~7nm lines
+            "dualband" => 14.0,
+            _ => 300.0,
+        },
+        None => 300.0,
+    }
+}
+
+/// Parse a todo's size ("85.0′ × 60.0′", "30.0″ × 20.0″", "12'") into
+/// major and minor axes in arcsec. Values without a unit are arcmin.
+pub fn parse_size_arcsec(size: &str) -> Option<(f64, f64)> {
+    let axes: Vec<f64> = size
+        .split(['×', 'x', 'X'])
+        .map(|part| {
+            let part = part.trim();
+            let (number, scale) = if let Some(n) = part.strip_suffix(['″', '"']) {
+                (n, 1.0)
+            } else if let Some(n) = part.strip_suffix(['′', '\'']) {
+                (n, 60.0)
+            } else {
+                (part, 60.0)
+            };
+            number.trim().parse::<f64>().ok().map(|n| n * scale)
+        })
+        .collect::<Option<_>>()?;
+    match axes.as_slice() {
+        [d] if *d > 0.0 => Some((*d, *d)),
+        [major, minor] if *major > 0.0 && *minor > 0.0 => Some((*major, *minor)),
+        _ => None,
+    }
+}
+
+/// Average surface brightness (mag/arcsec²) of an object of total
+/// magnitude `magnitude` spread over an ellipse with these axes
+pub fn surface_brightness(magnitude: f64, major_arcsec: f64, minor_arcsec: f64) -> f64 {
+    magnitude + 2.5 * (PI / 4.0 * major_arcsec * minor_arcsec).log10()
+}
+
+/// Electrons per pixel per second from a source of the given surface
+/// brightness
+fn electron_rate(
+    surface_brightness: f64,
+    bandwidth_nm: f64,
+    aperture_mm: f64,
+    pixel_scale: f64,
+    qe: f64,
+) -> f64 {
+    let area_cm2 = PI * (aperture_mm / 20.0).powi(2);
+    PHOTONS_MAG0
+        * 10f64.powf(-0.4 * surface_brightness)
+        * bandwidth_nm
+        * area_cm2
+        * pixel_scale.powi(2)
+        * qe
+}
+
+/// Resolved inputs to the exposure calculation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExposureParams {
+    pub filter: Option<String>,
+    pub bandwidth_nm: f64,
+    /// Sky brightness in mag/arcsec²
+    pub sky_brightness: f64,
+    pub aperture_mm: f64,
+    /// Effective focal length (with any reducer)
+    pub focal_length_mm: f64,
+    pub pixel_size_um: f64,
+    /// Read noise in e-
+    pub read_noise: f64,
+    /// e-/ADU
+    pub gain: Option<f64>,
+    pub qe: f64,
+    /// Dark current in e-/pixel/s
+    pub dark_current: f64,
+    pub target_surface_brightness: Option<f64>,
+    pub target_snr: f64,
+    pub max_sub_exposure: f64,
+    pub read_noise_contribution: f64,
+}
+
+/// A sub-exposure plan, as stored on the todo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExposurePlan {
+    pub filter: Option<String>,
+    pub bandwidth_nm: f64,
+    pub sky_brightness: f64,
+    /// arcsec/pixel
+    pub pixel_scale: f64,
+    /// Sky background in e-/pixel/s
+    pub sky_rate: f64,
+    pub read_noise: f64,
+    /// Sky-limited sub length before rounding to a common step
+    pub ideal_sub_exposure: f64,
+    pub sub_exposure: f64,
+    /// False when `maxSubExposure` cut the sub short of the sky limit
+    pub sky_limited: bool,
+    /// Sky background per sub in ADU, when the gain is known
+    pub sky_adu: Option<f64>,
+    pub target_surface_brightness: Option<f64>,
+    /// Target signal in e-/pixel/s
+    pub target_rate: Option<f64>,
+    pub target_snr: f64,
+    pub snr_per_sub: Option<f64>,
+    /// Subs (and integration) needed to reach `targetSnr`; unknown without
+    /// a target brightness
+    pub subs: Option<u32>,
+    pub total_integration_seconds: Option<f64>,
+    pub calculated_at: String,
+}
+
+/// Compute sub length and count from resolved parameters
+pub fn plan_exposure(params: &ExposureParams) -> Result<ExposurePlan, String> {
+    let positive = [
+        ("aperture", params.aperture_mm),
+        ("focal length", params.focal_length_mm),
+        ("pixel size", params.pixel_size_um),
+        ("bandwidth", params.bandwidth_nm),
+        ("quantum efficiency", params.qe),
+        ("read noise contribution", params.read_noise_contribution),
+        ("maximum sub exposure", params.max_sub_exposure),
+        ("target SNR", params.target_snr),
+    ];
+    if let Some((name, _)) = positive.iter().find(|(_, value)| *value <= 0.0) {
+        return Err(format!("The {} must be greater than zero", name));
+    }
+
+    let pixel_scale = pixel_scale_arcsec(params.focal_length_mm, params.pixel_size_um, 1.0);
+    let rate = |sb: f64| {
+        electron_rate(
+            sb,
+            params.bandwidth_nm,
+            params.aperture_mm,
+            pixel_scale,
+            params.qe,
+        )
+    };
+    let sky_rate = rate(params.sky_brightness);
+    let background = sky_rate + params.dark_current.max(0.0);
+
+    // Read noise adds `contribution` to the noise when the background per
+    // sub is `factor` times the read noise variance
+    let factor = 1.0 / ((1.0 + params.read_noise_contribution).powi(2) - 1.0);
+    let ideal_sub_exposure = factor * params.read_noise.powi(2) / background;
+    let step = SUB_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step >= ideal_sub_exposure)
+        .unwrap_or(ideal_sub_exposure.ceil());
+    let sub_exposure = step.min(params.max_sub_exposure);
+    let sky_limited = sub_exposure >= ideal_sub_exposure;
+
+    let target_rate = params.target_surface_brightness.map(rate);
+    let snr_per_sub = target_rate.map(|signal| {
+        signal * sub_exposure
+            / ((signal + background) * sub_exposure + params.read_noise.powi(2)).sqrt()
+    });
+    let subs = snr_per_sub
+        .filter(|snr| *snr > 0.0)
+        .map(|snr| ((params.target_snr / snr).powi(2).ceil() as u32).max(1));
+
+    Ok(ExposurePlan {
+        filter: params.filter.clone(),
+        bandwidth_nm: params.bandwidth_nm,
+        sky_brightness: params.sky_brightness,
+        pixel_scale,
+        sky_rate,
+        read_noise: params.read_noise,
+        ideal_sub_exposure,
+        sub_exposure,
+        sky_limited,
+        sky_adu: params
+            .gain
+            .filter(|g| *g > 0.0)
+            .map(|gain| sky_rate * sub_exposure / gain),
+        target_surface_brightness: params.target_surface_brightness,
+        target_rate,
+        target_snr: params.target_snr,
+        snr_per_sub,
+        subs,
+        total_integration_seconds: subs.map(|n| n as f64 * sub_exposure),
+        calculated_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Inputs to `calculate_exposure_plan`; explicit values win over those
+/// from the equipment profile and todo
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExposurePlanInput {
+    /// Todo to take the target from and store the plan on
+    pub todo_id: Option<String>,
+    /// Equipment profile for the telescope, reducer and camera
+    pub profile_id: Option<String>,
+    pub aperture_mm: Option<f64>,
+    /// Effective focal length, including any reducer
+    pub focal_length_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    /// Read noise in e- (default: "read_noise" in the camera's metadata)
+    pub read_noise: Option<f64>,
+    /// Gain in e-/ADU (default: "gain" in the camera's metadata)
+    pub gain: Option<f64>,
+    /// Quantum efficiency including optics, 0-1 (default 0.8; about a third
+    /// of that per channel for one-shot-color cameras)
+    pub qe: Option<f64>,
+    /// Dark current in e-/pixel/s (default 0, i.e. a cooled camera)
+    pub dark_current: Option<f64>,
+    /// Bortle class of the imaging site
+    pub bortle: Option<u8>,
+    /// Measured sky brightness in mag/arcsec², instead of the Bortle class
+    pub sky_brightness: Option<f64>,
+    pub filter: Option<String>,
+    /// Filter bandwidth in nm (default: typical for the filter)
+    pub bandwidth_nm: Option<f64>,
+    /// Target total magnitude and diameter (default: from the todo)
+    pub target_magnitude: Option<f64>,
+    pub target_size_arcmin: Option<f64>,
+    /// Target surface brightness in mag/arcsec², instead of magnitude and
+    /// size (e.g. for emission nebulae through narrowband filters, which
+    /// a visual magnitude underestimates)
+    pub target_surface_brightness: Option<f64>,
+    /// SNR wanted on the target's average surface brightness (default 30)
+    pub target_snr: Option<f64>,
+    /// Longest sub the mount and guiding allow (default 600s)
+    pub max_sub_exposure: Option<f64>,
+    /// Fraction read noise may add to the total noise (default 0.05)
+    pub read_noise_contribution: Option<f64>,
+}
+
+/// A number from an equipment item's metadata JSON
+fn metadata_number(equipment: Option<&Equipment>, key: &str) -> Option<f64> {
+    let metadata: serde_json::Value = serde_json::from_str(equipment?.metadata.as_deref()?).ok()?;
+    metadata.get(key)?.as_f64()
+}
+
+/// Target surface brightness from explicit input, falling back to the
+/// todo's magnitude and size
+fn target_surface_brightness(
+    input: &ExposurePlanInput,
+    todo: Option<&AstronomyTodo>,
+) -> Option<f64> {
+    if input.target_surface_brightness.is_some() {
+        return input.target_surface_brightness;
+    }
+    let magnitude = input
+        .target_magnitude
+        .or_else(|| todo?.magnitude.trim().parse().ok())?;
+    let (major, minor) = match input.target_size_arcmin {
+        Some(size) if size > 0.0 => (size * 60.0, size * 60.0),
+        _ => parse_size_arcsec(&todo?.size)?,
+    };
+    Some(surface_brightness(magnitude, major, minor))
+}
+
+/// Telescope, camera and reducer of an equipment profile
+#[derive(Debug, Default)]
+pub(super) struct ProfileEquipment {
+    pub telescope: Option<Equipment>,
+    pub camera: Option<Equipment>,
+    pub reducer: Option<Equipment>,
+}
+
+/// Look up the equipment in a profile; no profile gives no equipment
+pub(super) fn profile_equipment(
+    conn: &mut SqliteConnection,
+    profile_id: Option<&str>,
+) -> Result<ProfileEquipment, String> {
+    let Some(id) = profile_id else {
+        return Ok(ProfileEquipment::default());
+    };
+    let profile = repository::get_equipment_profile_by_id(conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Equipment profile not found: {}", id))?;
+    let mut lookup = |id: Option<&String>| -> Result<Option<Equipment>, String> {
+        match id {
+            Some(id) => repository::get_equipment_by_id(conn, id).map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    };
+    Ok(ProfileEquipment {
+        telescope: lookup(profile.telescope_id.as_ref())?,
+        camera: lookup(profile.camera_id.as_ref())?,
+        reducer: lookup(profile.reducer_id.as_ref())?,
+    })
+}
+
+fn resolve_params(
+    conn: &mut SqliteConnection,
+    input: &ExposurePlanInput,
+    todo: Option<&AstronomyTodo>,
+) -> Result<ExposureParams, String> {
+    let ProfileEquipment {
+        telescope,
+        camera,
+        reducer,
+    } = profile_equipment(conn, input.profile_id.as_deref())?;
+
+    let reduction = reducer
+        .as_ref()
+        .and_then(|r| r.reduction_factor)
+        .unwrap_or(1.0);
+    let aperture_mm = input
+        .aperture_mm
+        .or_else(|| telescope.as_ref()?.aperture_mm)
+        .ok_or("Telescope aperture is required")?;
+    let focal_length_mm = input
+        .focal_length_mm
+        .or_else(|| Some(telescope.as_ref()?.focal_length_mm? * reduction))
+        .ok_or("Telescope focal length is required")?;
+    let pixel_size_um = input
+        .pixel_size_um
+        .or_else(|| camera.as_ref()?.pixel_size_um)
+        .ok_or("Camera pixel size is required")?;
+    let read_noise = input
+        .read_noise
+        .or_else(|| metadata_number(camera.as_ref(), "read_noise"))
+        .ok_or("Camera read noise is required")?;
+
+    let sky_brightness = match (input.sky_brightness, input.bortle) {
+        (Some(sqm), _) => sqm,
+        (None, Some(bortle)) => bortle_sky_brightness(bortle)
+            .ok_or_else(|| format!("Invalid Bortle class: {}", bortle))?,
+        (None, None) => return Err("A Bortle class or sky brightness is required".to_string()),
+    };
+
+    let filter = input.filter.as_deref().and_then(normalize_filter_name);
+    let bandwidth_nm = input
+        .bandwidth_nm
+        .unwrap_or_else(|| default_bandwidth_nm(filter.as_deref()));
+
+    Ok(ExposureParams {
+        filter,
+        bandwidth_nm,
+        sky_brightness,
+        aperture_mm,
+        focal_length_mm,
+        pixel_size_um,
+        read_noise,
+        gain: input
+            .gain
+            .or_else(|| metadata_number(camera.as_ref(), "gain")),
+        qe: input
+            .qe
+            .or_else(|| metadata_number(camera.as_ref(), "qe"))
+            .unwrap_or(DEFAULT_QE),
+        dark_current: input.dark_current.unwrap_or(0.0),
+        target_surface_brightness: target_surface_brightness(input, todo),
+        target_snr: input.target_snr.unwrap_or(DEFAULT_TARGET_SNR),
+        max_sub_exposure: input.max_sub_exposure.unwrap_or(DEFAULT_MAX_SUB_EXPOSURE),
+        read_noise_contribution: input
+            .read_noise_contribution
+            .unwrap_or(DEFAULT_READ_NOISE_CONTRIBUTION),
+    })
+}
+
+/// Estimate the sky-limited sub length and the number of subs needed for a
+/// target SNR. With a `todoId` the target comes from the todo and the plan
+/// is stored on it.
+#[tauri::command]
+pub fn calculate_exposure_plan(
+    state: State<'_, AppState>,
+    input: ExposurePlanInput,
+) -> Result<ExposurePlan, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let todo = match &input.todo_id {
+        Some(id) => Some(
+            repository::get_todo_by_id(&mut conn, id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Todo not found: {}", id))?,
+        ),
+        None => None,
+    };
+
+    let params = resolve_params(&mut conn, &input, todo.as_ref())?;
+    let plan = plan_exposure(&params)?;
+
+    if let Some(todo) = todo {
+        let update = UpdateAstronomyTodo {
+            exposure_plan: Some(serde_json::to_string(&plan).map_err(|e| e.to_string())?),
+            last_updated: Some(chrono::Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+        repository::update_todo(&mut conn, &todo.id, &update).map_err(|e| e.to_string())?;
+    }
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> ExposureParams {
+        ExposureParams {
+            filter: Some("L".to_string()),
+            bandwidth_nm: 300.0,
+            sky_brightness: 20.0,
+            aperture_mm: 80.0,
+            focal_length_mm: 480.0,
+            pixel_size_um: 3.76,
+            read_noise: 1.5,
+            gain: Some(0.25),
+            qe: 0.8,
+            dark_current: 0.0,
+            target_surface_brightness: Some(22.0),
+            target_snr: 30.0,
+            max_sub_exposure: 600.0,
+            read_noise_contribution: 0.05,
+        }
+    }
+
+    #[test]
+    fn bortle_class_inverts_sky_brightness() {
+        for class in 1..=9 {
+            assert_eq!(bortle_class(bortle_sky_brightness(class).unwrap()), class);
+        }
+        assert_eq!(bortle_class(22.0), 1);
+        assert_eq!(bortle_class(16.0), 9);
+    }
+
+    #[test]
+    fn parses_todo_sizes() {
+        assert_eq!(parse_size_arcsec("85.0′ × 60.0′"), Some((5100.0, 3600.0)));
+        assert_eq!(parse_size_arcsec("30.0″ × 20.0″"), Some((30.0, 20.0)));
+        assert_eq!(parse_size_arcsec("12'"), Some((720.0, 720.0)));
+        assert_eq!(parse_size_arcsec("N/A"), None);
+    }
+
+    #[test]
+    fn narrowband_needs_longer_subs() {
+        let broadband = plan_exposure(&params()).unwrap();
+        assert!(broadband.sky_limited);
+        // Background per sub swamps read noise: ~9.8 × RN²
+        let background = broadband.sky_rate * broadband.ideal_sub_exposure;
+        assert!((background / 1.5f64.powi(2) - 9.76).abs() < 0.01);
+        assert!(broadband.sub_exposure >= broadband.ideal_sub_exposure);
+
+        let narrowband = plan_exposure(&ExposureParams {
+            filter: Some("Ha".to_string()),
+            bandwidth_nm: 7.0,
+            ..params()
+        })
+        .unwrap();
+        assert!(narrowband.ideal_sub_exposure > broadband.ideal_sub_exposure * 40.0);
+        assert!(narrowband.sky_limited);
+
+        let capped = plan_exposure(&ExposureParams {
+            bandwidth_nm: 7.0,
+            max_sub_exposure: 120.0,
+            ..params()
+        })
+        .unwrap();
+        assert_eq!(capped.sub_exposure, 120.0);
+        assert!(!capped.sky_limited);
+    }
+
+    #[test]
+    fn sub_count_reaches_target_snr() {
+        let plan = plan_exposure(&params()).unwrap();
+        let subs = plan.subs.unwrap();
+        let stacked = plan.snr_per_sub.unwrap() * (subs as f64).sqrt();
+        assert!(stacked >= 30.0);
+        assert!(plan.snr_per_sub.unwrap() * ((subs - 1) as f64).sqrt() < 30.0);
+        assert_eq!(
+            plan.total_integration_seconds,
+            Some(subs as f64 * plan.sub_exposure)
+        );
+
+        let no_target = plan_exposure(&ExposureParams {
+            target_surface_brightness: None,
+            ..params()
+        })
+        .unwrap();
+        assert_eq!(no_target.subs, None);
+    }
+}
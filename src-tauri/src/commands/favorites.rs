@@ -0,0 +1,173 @@
+//! Favorites and pins for the Home screen
+//!
+//! Images and collections are favorited and todos flagged on the records
+//! themselves; saved searches and targets are pinned here. Targets have no
+//! record of their own (they're the object names on images), so a pin keeps
+//! the canonical name and the Home screen gets the same counts as the target
+//! browser.
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{
+    AstronomyTodo, Collection, Image, NewPinnedTarget, PinnedTarget, SavedSearch, UpdateSavedSearch,
+};
+use crate::db::repository::{self, TargetWithCount};
+use crate::state::AppState;
+
+use super::object_names::ObjectNames;
+
+/// Everything favorited, flagged or pinned, for the Home screen
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorites {
+    pub images: Vec<Image>,
+    /// Favorite collections that aren't archived
+    pub collections: Vec<Collection>,
+    /// Flagged todos that are still open
+    pub todos: Vec<AstronomyTodo>,
+    /// Pinned targets in the order they were pinned, with their image counts
+    pub targets: Vec<TargetWithCount>,
+    pub saved_searches: Vec<SavedSearch>,
+}
+
+/// Counts for pinned targets, keeping the pin order. A target pinned before
+/// any of its images were imported is listed with no images.
+fn pinned_target_counts(
+    pins: Vec<PinnedTarget>,
+    counts: Vec<TargetWithCount>,
+) -> Vec<TargetWithCount> {
+    let mut counts: std::collections::HashMap<String, TargetWithCount> = counts
+        .into_iter()
+        .map(|target| (target.name.clone(), target))
+        .collect();
+    pins.into_iter()
+        .map(|pin| {
+            counts.remove(&pin.name).unwrap_or(TargetWithCount {
+                name: pin.name,
+                image_count: 0,
+                latest_image_id: None,
+                latest_thumbnail: None,
+            })
+        })
+        .collect()
+}
+
+/// Get favorite images and collections, flagged todos, and pinned targets
+/// and saved searches in one call
+#[tauri::command]
+pub fn get_favorites(state: State<'_, AppState>) -> Result<Favorites, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let user_id = &state.user_id;
+
+    let pins = repository::get_pinned_targets(&mut conn, user_id).map_err(|e| e.to_string())?;
+    // Counting targets reads every image, so skip it when nothing is pinned
+    let targets = if pins.is_empty() {
+        Vec::new()
+    } else {
+        let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+        let counts = repository::get_targets_with_counts_by(&mut conn, user_id, |name| {
+            names.canonical(name)
+        })
+        .map_err(|e| e.to_string())?;
+        pinned_target_counts(pins, counts)
+    };
+
+    Ok(Favorites {
+        images: repository::get_favorite_images(&mut conn, user_id).map_err(|e| e.to_string())?,
+        collections: repository::get_favorite_collections(&mut conn, user_id)
+            .map_err(|e| e.to_string())?,
+        todos: repository::get_flagged_todos(&mut conn, user_id).map_err(|e| e.to_string())?,
+        targets,
+        saved_searches: repository::get_pinned_saved_searches(&mut conn, user_id)
+            .map_err(|e| e.to_string())?,
+    })
+}
+
+/// Pin a target under its canonical name. Returns false if it was already
+/// pinned.
+#[tauri::command]
+pub fn pin_target(state: State<'_, AppState>, name: String) -> Result<bool, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Target name cannot be empty".to_string());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+
+    let new_pin = NewPinnedTarget {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        name: names.canonical(name),
+    };
+    repository::pin_target(&mut conn, &new_pin).map_err(|e| e.to_string())
+}
+
+/// Unpin a target by any of its names. Returns false if it wasn't pinned.
+#[tauri::command]
+pub fn unpin_target(state: State<'_, AppState>, name: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    repository::unpin_target(&mut conn, &state.user_id, &names.canonical(name.trim()))
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+fn set_saved_search_pinned(
+    state: State<'_, AppState>,
+    id: &str,
+    pinned: bool,
+) -> Result<SavedSearch, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let update = UpdateSavedSearch {
+        pinned: Some(pinned),
+        ..Default::default()
+    };
+    repository::update_saved_search(&mut conn, id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn pin_saved_search(state: State<'_, AppState>, id: String) -> Result<SavedSearch, String> {
+    set_saved_search_pinned(state, &id, true)
+}
+
+#[tauri::command]
+pub fn unpin_saved_search(state: State<'_, AppState>, id: String) -> Result<SavedSearch, String> {
+    set_saved_search_pinned(state, &id, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pin(name: &str) -> PinnedTarget {
+        PinnedTarget {
+            id: name.to_string(),
+            user_id: "user".to_string(),
+            name: name.to_string(),
+            created_at: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn pinned_targets_keep_their_order_and_need_no_images() {
+        let counts = ["M 31", "M 42", "NGC 7000"]
+            .iter()
+            .map(|name| TargetWithCount {
+                name: name.to_string(),
+                image_count: 3,
+                latest_image_id: Some(format!("{} image", name)),
+                latest_thumbnail: None,
+            })
+            .collect();
+        let targets =
+            pinned_target_counts(vec![pin("NGC 7000"), pin("Sh2-129"), pin("M 31")], counts);
+
+        let listed: Vec<_> = targets
+            .iter()
+            .map(|t| (t.name.as_str(), t.image_count))
+            .collect();
+        assert_eq!(listed, vec![("NGC 7000", 3), ("Sh2-129", 0), ("M 31", 3)]);
+        assert!(targets[1].latest_image_id.is_none());
+    }
+}
@@ -0,0 +1,200 @@
+//! Filter usage tracking: normalize FITS FILTER headers and summarize exposure per filter
+
+use diesel::sqlite::SqliteConnection;
+use tauri::State;
+
+use crate::db::models::{Filter, NewImageFilter};
+use crate::db::repository::{self, FilterExposureSummary, TargetFilterExposure};
+use crate::state::AppState;
+
+use super::scan::{
+    extract_float_value, extract_int_value, metadata_header_value, metadata_number_value,
+};
+
+/// Normalize a FITS FILTER header value to a canonical filter name.
+///
+/// Capture software is inconsistent ("H-alpha", "Ha 7nm", "HA", "Lum"...), so
+/// strip punctuation and bandwidth suffixes before mapping to a known name.
+/// Returns None for empty values and explicit "no filter" markers.
+pub fn normalize_filter_name(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    let compact: String = trimmed
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == 'α')
+        .collect();
+
+    // Drop bandwidth suffix, e.g. "ha7nm" -> "ha", "oiii3nm" -> "oiii"
+    let base = compact
+        .strip_suffix("nm")
+        .map(|s| s.trim_end_matches(|c: char| c.is_ascii_digit()))
+        .unwrap_or(&compact);
+
+    let name = match base {
+        "" | "none" | "na" | "clear" | "open" => return None,
+        "ha" | "halpha" | "hα" => "Ha",
+        "oiii" | "o3" => "OIII",
+        "sii" | "s2" => "SII",
+        "hb" | "hbeta" => "Hb",
+        "l" | "lum" | "luminance" => "L",
+        "r" | "red" => "R",
+        "g" | "green" => "G",
+        "b" | "blue" => "B",
+        "ircut" | "uvir" | "uvircut" | "uvirblock" => "UV/IR Cut",
+        "lp" | "duo" | "duoband" | "dualband" | "lenhance" | "lextreme" | "lultimate" | "alpt" => {
+            "Dual-band"
+        }
+        _ => return Some(trimmed.to_string()),
+    };
+    Some(name.to_string())
+}
+
+/// Classify a canonical filter name by bandpass
+pub fn filter_band(name: &str) -> &'static str {
+    match name {
+        "Ha" | "OIII" | "SII" | "Hb" => "narrowband",
+        "Dual-band" => "dualband",
+        "L" | "R" | "G" | "B" | "UV/IR Cut" => "broadband",
+        _ => "other",
+    }
+}
+
+/// Record the filter and integration time for an image.
+///
+/// `exposure` is the sub exposure in seconds; for stacks it is multiplied by
+/// `frames` to give total integration. Does nothing if no filter is present.
+pub fn record_image_filter(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_id: &str,
+    raw_filter: Option<&str>,
+    exposure: Option<f64>,
+    frames: Option<i32>,
+) -> Result<bool, diesel::result::Error> {
+    let Some(name) = raw_filter.and_then(normalize_filter_name) else {
+        return Ok(false);
+    };
+
+    let filter = repository::get_or_create_filter(conn, user_id, &name, filter_band(&name))?;
+    let frame_count = frames.filter(|f| *f > 0).unwrap_or(1);
+    repository::set_image_filter(
+        conn,
+        &NewImageFilter {
+            image_id: image_id.to_string(),
+            filter_id: filter.id,
+            raw_value: raw_filter.map(|s| s.trim().to_string()),
+            frame_count,
+            exposure_seconds: exposure.unwrap_or(0.0) * frame_count as f64,
+        },
+    )?;
+    Ok(true)
+}
+
+/// Get all filters seen in the library
+#[tauri::command]
+pub fn get_filters(state: State<'_, AppState>) -> Result<Vec<Filter>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_filters(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+/// Get exposure totals per filter for a target (e.g. Ha/OIII/SII balance)
+#[tauri::command]
+pub fn get_filter_summary(
+    state: State<'_, AppState>,
+    target: String,
+) -> Result<Vec<FilterExposureSummary>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_filter_summary_for_target(&mut conn, &state.user_id, &target)
+        .map_err(|e| e.to_string())
+}
+
+/// Get exposure totals per filter for every target
+#[tauri::command]
+pub fn get_filter_exposure_by_target(
+    state: State<'_, AppState>,
+) -> Result<Vec<TargetFilterExposure>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_filter_exposure_by_target(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Rebuild filter usage for all images from their stored FITS metadata.
+/// Returns the number of images with a recognized filter.
+#[tauri::command]
+pub fn rebuild_filter_usage(state: State<'_, AppState>) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = repository::get_images_by_user(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+
+    let mut recorded = 0;
+    for image in images {
+        let meta = image.metadata.as_deref().unwrap_or("{}");
+        let filter = image
+            .filter
+            .clone()
+            .or_else(|| metadata_header_value(meta, "filter", "FILTER"));
+        if filter.is_none() {
+            continue;
+        }
+        let exposure = image.exposure.or_else(|| {
+            metadata_number_value(meta, "exposure", &["EXPTIME", "EXPOSURE"])
+                .and_then(|v| extract_float_value(&v))
+        });
+        let frames = metadata_number_value(meta, "stacked_frames", &["STACKCNT", "NCOMBINE"])
+            .and_then(|v| extract_int_value(&v));
+
+        if record_image_filter(
+            &mut conn,
+            &state.user_id,
+            &image.id,
+            filter.as_deref(),
+            exposure,
+            frames,
+        )
+        .map_err(|e| e.to_string())?
+        {
+            recorded += 1;
+        }
+    }
+
+    log::info!("Rebuilt filter usage for {} images", recorded);
+    Ok(recorded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_filter_name_narrowband_variants() {
+        for raw in ["Ha", "H-alpha", "HA 7nm", "Halpha", "h_alpha"] {
+            assert_eq!(normalize_filter_name(raw).as_deref(), Some("Ha"), "{}", raw);
+        }
+        assert_eq!(normalize_filter_name("OIII 3nm").as_deref(), Some("OIII"));
+        assert_eq!(normalize_filter_name("S-II").as_deref(), Some("SII"));
+    }
+
+    #[test]
+    fn normalize_filter_name_broadband_and_duo() {
+        assert_eq!(normalize_filter_name("Lum").as_deref(), Some("L"));
+        assert_eq!(normalize_filter_name("Red").as_deref(), Some("R"));
+        assert_eq!(normalize_filter_name("IRCUT").as_deref(), Some("UV/IR Cut"));
+        assert_eq!(normalize_filter_name("LP").as_deref(), Some("Dual-band"));
+        assert_eq!(normalize_filter_name("L-eXtreme").as_deref(), Some("Dual-band"));
+    }
+
+    #[test]
+    fn normalize_filter_name_empty_and_unknown() {
+        assert_eq!(normalize_filter_name(""), None);
+        assert_eq!(normalize_filter_name("None"), None);
+        assert_eq!(normalize_filter_name(" Custom CH4 "), Some("Custom CH4".to_string()));
+    }
+
+    #[test]
+    fn filter_band_classification() {
+        assert_eq!(filter_band("Ha"), "narrowband");
+        assert_eq!(filter_band("Dual-band"), "dualband");
+        assert_eq!(filter_band("L"), "broadband");
+        assert_eq!(filter_band("Custom CH4"), "other");
+    }
+}
@@ -0,0 +1,410 @@
+//! Sub-frame quality over a session: star counts and sky background
+//!
+//! Each light frame's stars are counted and its background level measured
+//! once, then kept in the image metadata under `frame_metrics`. Across a
+//! session, frames whose star count falls well below what the clear frames
+//! of the night managed (clouds, dew, a lost guide star) or whose background
+//! jumps (cloud lit by light pollution) are flagged, with the reason kept
+//! alongside the metrics so a quality chart and stacking tools can pick
+//! them up.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, State};
+
+use crate::db::models::{Image, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::plate_solve::read_fits_pixels;
+use super::scan::{parse_utc_timestamp, CollectProgress};
+use super::subframes::image_path;
+
+/// Key of a frame's measurements in its metadata JSON
+pub const METADATA_KEY: &str = "frame_metrics";
+
+const PROGRESS_EVENT: &str = "session-quality-progress";
+
+/// Global cancellation flag for measuring a session's frames
+static QUALITY_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Frames with fewer stars than this fraction of the session's typical
+/// clear frame are flagged
+const STAR_DROP_FRACTION: f64 = 0.5;
+/// Percentile of the session's star counts taken as a typical clear frame,
+/// high enough that a cloudy half of the night doesn't lower the bar
+const REFERENCE_PERCENTILE: f64 = 0.75;
+/// Backgrounds more than this many robust standard deviations above the
+/// session median are flagged
+const BACKGROUND_SIGMA: f64 = 5.0;
+/// Fewer frames than this don't make a timeline worth judging
+const MIN_FRAMES: usize = 4;
+
+/// What was measured on one frame, plus the session's verdict on it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameMetrics {
+    pub star_count: usize,
+    /// Median pixel value, in the file's own units (ADU for FITS)
+    pub background: f64,
+    /// Why the frame was flagged as clouded or otherwise poor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityPoint {
+    pub image_id: String,
+    pub filename: String,
+    pub date_obs: Option<String>,
+    pub star_count: usize,
+    pub background: f64,
+    /// Star count relative to the session's typical clear frame
+    pub relative_stars: f64,
+    pub rejected: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionQualityTimeline {
+    /// Frames in capture order
+    pub points: Vec<QualityPoint>,
+    /// Star count of a typical clear frame this session
+    pub reference_stars: f64,
+    pub median_background: f64,
+    pub rejected: usize,
+    /// Frames that couldn't be measured
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// A frame's stored measurements, if it's been measured
+pub fn frame_metrics(metadata: Option<&str>) -> Option<FrameMetrics> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get(METADATA_KEY)?.clone()).ok()
+}
+
+/// Record a frame's measurements in its metadata JSON
+pub fn with_frame_metrics(metadata: Option<String>, metrics: &FrameMetrics) -> Option<String> {
+    let mut value = metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    value[METADATA_KEY] = serde_json::json!(metrics);
+    Some(value.to_string())
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Value at fraction `p` of the way through the sorted values
+fn percentile(values: &[f64], p: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted
+        .get(((sorted.len() - 1) as f64 * p).round() as usize)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Session reference levels and a rejection reason per frame, for frames
+/// given as (star count, background) in capture order
+fn score_frames(frames: &[(usize, f64)]) -> (f64, f64, Vec<Option<String>>) {
+    if frames.is_empty() {
+        return (0.0, 0.0, Vec::new());
+    }
+    let stars: Vec<f64> = frames.iter().map(|(s, _)| *s as f64).collect();
+    let reference = percentile(&stars, REFERENCE_PERCENTILE);
+    let mut backgrounds: Vec<f64> = frames.iter().map(|(_, b)| *b).collect();
+    let median_background = median(&mut backgrounds);
+    let mut deviations: Vec<f64> = frames
+        .iter()
+        .map(|(_, b)| (b - median_background).abs())
+        .collect();
+    let sigma = median(&mut deviations) * 1.4826;
+
+    if frames.len() < MIN_FRAMES {
+        return (reference, median_background, vec![None; frames.len()]);
+    }
+    let reasons = frames
+        .iter()
+        .map(|&(star_count, background)| {
+            let mut reasons = Vec::new();
+            if (star_count as f64) < reference * STAR_DROP_FRACTION {
+                reasons.push(format!(
+                    "{} stars against {:.0} on a clear frame",
+                    star_count, reference
+                ));
+            }
+            if sigma > 0.0 && background - median_background > BACKGROUND_SIGMA * sigma {
+                reasons.push(format!(
+                    "Background {:.0} well above the session's {:.0}",
+                    background, median_background
+                ));
+            }
+            (!reasons.is_empty()).then(|| reasons.join("; "))
+        })
+        .collect();
+    (reference, median_background, reasons)
+}
+
+/// Build the timeline for measured frames in capture order
+fn build_timeline(frames: Vec<(Image, FrameMetrics)>) -> SessionQualityTimeline {
+    let measured: Vec<(usize, f64)> = frames
+        .iter()
+        .map(|(_, m)| (m.star_count, m.background))
+        .collect();
+    let (reference_stars, median_background, reasons) = score_frames(&measured);
+
+    let points: Vec<QualityPoint> = frames
+        .into_iter()
+        .zip(reasons)
+        .map(|((image, metrics), rejected)| QualityPoint {
+            image_id: image.id,
+            filename: image.filename,
+            date_obs: image.date_obs,
+            star_count: metrics.star_count,
+            background: metrics.background,
+            relative_stars: if reference_stars > 0.0 {
+                metrics.star_count as f64 / reference_stars
+            } else {
+                0.0
+            },
+            rejected,
+        })
+        .collect();
+    SessionQualityTimeline {
+        rejected: points.iter().filter(|p| p.rejected.is_some()).count(),
+        points,
+        reference_stars,
+        median_background,
+        ..Default::default()
+    }
+}
+
+/// Count stars and measure the background of a frame's pixels
+fn measure_pixels(pixels: &[f32], width: u32, height: u32) -> Result<FrameMetrics, String> {
+    let config = tetra3::CentroidExtractionConfig {
+        sigma_threshold: 5.0,
+        min_pixels: 3,
+        max_pixels: 500,
+        max_centroids: None,
+        sigma_clip_iterations: 3,
+        sigma_clip_factor: 3.0,
+        use_8_connectivity: true,
+        local_bg_block_size: None,
+        max_elongation: None,
+        matched_filter_sigma: None,
+    };
+    let centroids = tetra3::extract_centroids_from_raw(pixels, width, height, &config)
+        .map_err(|e| format!("Failed to find stars: {}", e))?;
+
+    Ok(FrameMetrics {
+        star_count: centroids.centroids.len(),
+        background: background_level(pixels),
+        rejected: None,
+    })
+}
+
+/// Sky background of a frame: the median pixel value
+pub(super) fn background_level(pixels: &[f32]) -> f64 {
+    // Every 7th pixel is plenty for a median
+    let mut sample: Vec<f64> = pixels.iter().step_by(7).map(|&v| v as f64).collect();
+    median(&mut sample)
+}
+
+/// Measure the frame an image was imported from, preferring its FITS data
+fn measure_frame(image: &Image) -> Result<FrameMetrics, String> {
+    let path = image_path(image)
+        .filter(|path| path.exists())
+        .and_then(Path::to_str)
+        .ok_or_else(|| format!("{}: file not found", image.filename))?;
+    let lower = path.to_lowercase();
+    if lower.ends_with(".fit") || lower.ends_with(".fits") {
+        let (pixels, width, height) = read_fits_pixels(path)?;
+        return measure_pixels(&pixels, width, height);
+    }
+    let luma = image::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", image.filename, e))?
+        .to_luma32f();
+    measure_pixels(luma.as_raw(), luma.width(), luma.height())
+}
+
+/// Light frames of a session: the subs of its stacked images, or the
+/// collection's own images when nothing's stacked
+fn session_frames(conn: &mut SqliteConnection, collection_id: &str) -> Result<Vec<Image>, String> {
+    let images =
+        repository::get_images_in_collection(conn, collection_id).map_err(|e| e.to_string())?;
+    let mut subs = Vec::new();
+    for image in &images {
+        subs.extend(repository::get_image_subframes(conn, &image.id).map_err(|e| e.to_string())?);
+    }
+    let mut frames = if subs.is_empty() { images } else { subs };
+    frames.sort_by_cached_key(|image| {
+        let time = image.date_obs.as_deref().and_then(parse_utc_timestamp);
+        (time.is_none(), time, image.filename.clone())
+    });
+    Ok(frames)
+}
+
+#[tauri::command]
+pub fn cancel_session_quality() -> Result<(), String> {
+    QUALITY_CANCELLED.store(true, Ordering::SeqCst);
+    log::info!("Session quality measurement cancellation requested");
+    Ok(())
+}
+
+/// Star count and background of every light frame in a session, in capture
+/// order, with clouded frames flagged. Frames are measured the first time
+/// (emitting `session-quality-progress` events) and the results and flags
+/// kept in their metadata.
+#[tauri::command]
+pub async fn get_session_quality_timeline(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    collection_id: String,
+) -> Result<SessionQualityTimeline, String> {
+    // Reset cancellation flag at start
+    QUALITY_CANCELLED.store(false, Ordering::SeqCst);
+
+    let db = state.db.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        let frames = session_frames(&mut conn, &collection_id)?;
+        let total = frames.len();
+
+        let mut measured = Vec::new();
+        let mut errors = Vec::new();
+        let mut cancelled = false;
+        for (i, image) in frames.into_iter().enumerate() {
+            if let Some(metrics) = frame_metrics(image.metadata.as_deref()) {
+                measured.push((image, metrics));
+                continue;
+            }
+            // Once cancelled, only frames measured before are charted
+            if QUALITY_CANCELLED.load(Ordering::SeqCst) {
+                cancelled = true;
+                continue;
+            }
+            let _ = window.emit(
+                PROGRESS_EVENT,
+                &CollectProgress {
+                    current: i,
+                    total,
+                    current_file: image.filename.clone(),
+                    percent: (i * 100 / total.max(1)) as u8,
+                    cancelled: false,
+                    phase: "measuring".to_string(),
+                },
+            );
+            match measure_frame(&image) {
+                Ok(metrics) => measured.push((image, metrics)),
+                Err(e) => {
+                    log::warn!("Session quality: {}", e);
+                    errors.push(e);
+                }
+            }
+        }
+
+        let mut timeline = build_timeline(measured.clone());
+        // Store new measurements and changed verdicts
+        for ((image, metrics), point) in measured.into_iter().zip(&timeline.points) {
+            let stored = frame_metrics(image.metadata.as_deref());
+            let metrics = FrameMetrics {
+                rejected: point.rejected.clone(),
+                ..metrics
+            };
+            if stored.as_ref() == Some(&metrics) {
+                continue;
+            }
+            let update = UpdateImage {
+                metadata: with_frame_metrics(image.metadata, &metrics),
+                ..Default::default()
+            };
+            repository::update_image(&mut conn, &image.id, &update).map_err(|e| e.to_string())?;
+        }
+
+        let _ = window.emit(
+            PROGRESS_EVENT,
+            &CollectProgress {
+                current: total,
+                total,
+                current_file: String::new(),
+                percent: 100,
+                cancelled,
+                phase: "complete".to_string(),
+            },
+        );
+        timeline.errors = errors;
+        timeline.cancelled = cancelled;
+        Ok(timeline)
+    })
+    .await
+    .map_err(|e| format!("Session quality failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clouded_frames_are_flagged_against_the_clear_ones() {
+        // A clear start, cloud rolling in for a few frames, then clearing
+        // with the target sinking a little
+        let frames = [
+            (210, 1200.0),
+            (205, 1190.0),
+            (198, 1210.0),
+            (60, 1900.0),
+            (12, 2400.0),
+            (150, 1230.0),
+            (190, 1205.0),
+            (180, 1215.0),
+        ];
+        let (reference, median_background, reasons) = score_frames(&frames);
+        assert_eq!(reference, 198.0);
+        assert_eq!(median_background, 1212.5);
+        let flagged: Vec<_> = reasons.iter().map(Option::is_some).collect();
+        assert_eq!(
+            flagged,
+            [false, false, false, true, true, false, false, false]
+        );
+        assert_eq!(
+            reasons[4].as_deref(),
+            Some("12 stars against 198 on a clear frame; Background 2400 well above the session's 1212")
+        );
+
+        // Too few frames to judge
+        let (_, _, reasons) = score_frames(&frames[3..6]);
+        assert!(reasons.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn metrics_are_kept_in_the_metadata() {
+        let metrics = FrameMetrics {
+            star_count: 42,
+            background: 1234.5,
+            rejected: Some("12 stars".to_string()),
+        };
+        let metadata = with_frame_metrics(Some(r#"{"exposure": 10.0}"#.to_string()), &metrics);
+        assert_eq!(frame_metrics(metadata.as_deref()), Some(metrics));
+        let value: serde_json::Value = serde_json::from_str(&metadata.unwrap()).unwrap();
+        assert_eq!(value["exposure"], 10.0);
+        assert_eq!(frame_metrics(Some(r#"{"exposure": 10.0}"#)), None);
+        assert_eq!(frame_metrics(None), None);
+    }
+}
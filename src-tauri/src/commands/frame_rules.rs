@@ -0,0 +1,296 @@
+//! Frame type rules: how scans tell stacked results from raw Light frames
+//!
+//! File names are checked first against configurable prefixes and regular
+//! expressions (Seestar's `Stacked_*` / `Light_*` by default). Names that
+//! match no rule fall back to the FITS headers: NCOMBINE or STACKCNT above 1
+//! marks a stack, and IMAGETYP gives the frame type written by the capture
+//! software.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use super::scan::{extract_int_value, extract_string_value};
+use super::settings::{load_settings, load_settings_in, save_settings, FRAME_RULES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameType {
+    Stacked,
+    Light,
+    Dark,
+    Flat,
+    Bias,
+    /// Nothing matched (processed exports, snapshots...)
+    Unknown,
+}
+
+impl FrameType {
+    /// Raw frames left out of a stacked-only import
+    pub fn is_raw(self) -> bool {
+        matches!(
+            self,
+            FrameType::Light | FrameType::Dark | FrameType::Flat | FrameType::Bias
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FrameRules {
+    /// File name prefixes of stacked images (case-insensitive)
+    pub stacked_prefixes: Vec<String>,
+    /// File name prefixes of raw Light frames (case-insensitive)
+    pub light_prefixes: Vec<String>,
+    /// Regular expressions matched against the file name without extension
+    /// (case-insensitive)
+    pub stacked_patterns: Vec<String>,
+    pub light_patterns: Vec<String>,
+    /// Classify by the IMAGETYP header when no name rule matches
+    pub use_imagetyp: bool,
+    /// Treat NCOMBINE / STACKCNT above 1 as a stack when no name rule matches
+    pub use_ncombine: bool,
+}
+
+impl Default for FrameRules {
+    fn default() -> Self {
+        FrameRules {
+            stacked_prefixes: vec!["stacked".to_string()],
+            light_prefixes: vec!["light".to_string()],
+            stacked_patterns: Vec::new(),
+            light_patterns: Vec::new(),
+            use_imagetyp: true,
+            use_ncombine: true,
+        }
+    }
+}
+
+/// Frame rules with their patterns compiled
+#[derive(Debug, Clone)]
+pub struct FrameClassifier {
+    rules: FrameRules,
+    stacked_patterns: Vec<Regex>,
+    light_patterns: Vec<Regex>,
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            RegexBuilder::new(p)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid pattern '{}': {}", p, e))
+        })
+        .collect()
+}
+
+fn has_prefix(name: &str, prefixes: &[String]) -> bool {
+    prefixes
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .any(|p| !p.is_empty() && name.starts_with(&p))
+}
+
+/// Frame type named by an IMAGETYP value ("Light Frame", "MASTER DARK"...).
+/// A master light is a stack.
+fn imagetyp_frame_type(value: &str) -> Option<FrameType> {
+    let value = value.to_lowercase();
+    let frame_type = if value.contains("light") || value.contains("object") {
+        if value.contains("master") {
+            FrameType::Stacked
+        } else {
+            FrameType::Light
+        }
+    } else if value.contains("dark") {
+        FrameType::Dark
+    } else if value.contains("flat") {
+        FrameType::Flat
+    } else if value.contains("bias") || value.contains("offset") || value.contains("zero") {
+        FrameType::Bias
+    } else {
+        return None;
+    };
+    Some(frame_type)
+}
+
+impl FrameClassifier {
+    pub fn new(rules: FrameRules) -> Result<Self, String> {
+        Ok(FrameClassifier {
+            stacked_patterns: compile_patterns(&rules.stacked_patterns)?,
+            light_patterns: compile_patterns(&rules.light_patterns)?,
+            rules,
+        })
+    }
+
+    /// Frame type from the file name (without extension) alone
+    pub fn classify_name(&self, stem: &str) -> Option<FrameType> {
+        let name = stem.to_lowercase();
+        if has_prefix(&name, &self.rules.stacked_prefixes)
+            || self.stacked_patterns.iter().any(|p| p.is_match(stem))
+        {
+            Some(FrameType::Stacked)
+        } else if has_prefix(&name, &self.rules.light_prefixes)
+            || self.light_patterns.iter().any(|p| p.is_match(stem))
+        {
+            Some(FrameType::Light)
+        } else {
+            None
+        }
+    }
+
+    /// Frame type from FITS headers (raw values as stored by the scan)
+    pub fn classify_headers(&self, headers: &HashMap<String, String>) -> Option<FrameType> {
+        if self.rules.use_ncombine {
+            let combined = ["NCOMBINE", "STACKCNT"]
+                .iter()
+                .filter_map(|key| headers.get(*key))
+                .filter_map(|value| extract_int_value(value))
+                .any(|count| count > 1);
+            if combined {
+                return Some(FrameType::Stacked);
+            }
+        }
+        if self.rules.use_imagetyp {
+            return headers
+                .get("IMAGETYP")
+                .and_then(|value| extract_string_value(value))
+                .and_then(|value| imagetyp_frame_type(&value));
+        }
+        None
+    }
+
+    /// Name rules first, then headers when they're available
+    pub fn classify(&self, stem: &str, headers: Option<&HashMap<String, String>>) -> FrameType {
+        self.classify_name(stem)
+            .or_else(|| headers.and_then(|h| self.classify_headers(h)))
+            .unwrap_or(FrameType::Unknown)
+    }
+}
+
+impl Default for FrameClassifier {
+    fn default() -> Self {
+        FrameClassifier::new(FrameRules::default()).expect("default frame rules compile")
+    }
+}
+
+fn classifier_for(rules: FrameRules) -> FrameClassifier {
+    FrameClassifier::new(rules).unwrap_or_else(|e| {
+        log::warn!("Ignoring saved frame rules: {}", e);
+        FrameClassifier::default()
+    })
+}
+
+/// Saved frame rules, falling back to the defaults if they can't be used
+pub fn frame_classifier(app: &AppHandle) -> FrameClassifier {
+    classifier_for(load_settings(app, &FRAME_RULES).unwrap_or_default())
+}
+
+/// Frame rules saved in `data_dir`, for running without the app
+pub(crate) fn frame_classifier_in(data_dir: &Path) -> FrameClassifier {
+    classifier_for(load_settings_in(data_dir, &FRAME_RULES))
+}
+
+#[tauri::command]
+pub fn get_frame_rules(app: AppHandle) -> Result<FrameRules, String> {
+    load_settings(&app, &FRAME_RULES)
+}
+
+/// Save frame rules; None restores the defaults
+#[tauri::command]
+pub fn set_frame_rules(app: AppHandle, rules: Option<FrameRules>) -> Result<FrameRules, String> {
+    let rules = rules.unwrap_or_default();
+    // Reject patterns that don't compile rather than silently ignoring them
+    FrameClassifier::new(rules.clone())?;
+
+    save_settings(&app, &FRAME_RULES, &rules)?;
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn default_rules_match_seestar_names() {
+        let classifier = FrameClassifier::default();
+        assert_eq!(
+            classifier.classify("Stacked_210_M 42_10.0s_IRCUT_20250115-203012", None),
+            FrameType::Stacked
+        );
+        assert_eq!(
+            classifier.classify("Light_M 42_10.0s_IRCUT_20250115-203012", None),
+            FrameType::Light
+        );
+        assert_eq!(classifier.classify("M42_final", None), FrameType::Unknown);
+    }
+
+    #[test]
+    fn headers_classify_other_software() {
+        let classifier = FrameClassifier::default();
+        // Siril stack
+        let siril = headers(&[
+            ("NCOMBINE", "Some(IntegerNumber(48))"),
+            ("IMAGETYP", "Some(CharacterString(\"Light Frame\"))"),
+        ]);
+        assert_eq!(
+            classifier.classify("r_pp_M42_stacked", Some(&siril)),
+            FrameType::Stacked
+        );
+        // N.I.N.A. sub
+        let nina = headers(&[("IMAGETYP", "Some(CharacterString(\"LIGHT\"))")]);
+        assert_eq!(
+            classifier.classify("2025-01-15_M42_0001", Some(&nina)),
+            FrameType::Light
+        );
+        let master = headers(&[("IMAGETYP", "Some(CharacterString(\"Master Dark\"))")]);
+        assert_eq!(
+            classifier.classify("dark_300s", Some(&master)),
+            FrameType::Dark
+        );
+
+        let names_only = FrameClassifier::new(FrameRules {
+            use_imagetyp: false,
+            use_ncombine: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            names_only.classify("r_pp_M42", Some(&siril)),
+            FrameType::Unknown
+        );
+    }
+
+    #[test]
+    fn custom_patterns() {
+        let classifier = FrameClassifier::new(FrameRules {
+            stacked_prefixes: vec!["integration".to_string()],
+            stacked_patterns: vec!["_stacked$".to_string()],
+            light_patterns: vec!["^frame_".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            classifier.classify("Integration_RGB", None),
+            FrameType::Stacked
+        );
+        assert_eq!(classifier.classify("M31_STACKED", None), FrameType::Stacked);
+        assert_eq!(classifier.classify("frame_0001", None), FrameType::Light);
+        assert!(FrameClassifier::new(FrameRules {
+            light_patterns: vec!["(".to_string()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,501 @@
+//! Framing assistant: a survey cutout with the camera's field drawn at a
+//! chosen rotation
+//!
+//! The cutout (see `sky_cutout`) is a tangent projection centered on the
+//! framing center, so the camera's field, projected the same way, is a
+//! rectangle around the image center turned by the rotation. Rotation is the
+//! angle of the sensor's top edge east of north, the convention plate
+//! solutions use. The chosen center and rotation are kept on the todo, and
+//! on a schedule item when one is given, for setting up on the night.
+
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::{DynamicImage, Rgb, RgbaImage};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::bitmap_font::{draw_text, text_height, text_width};
+use crate::db::models::{
+    AstronomyTodo, Framing, ScheduleItem, UpdateAstronomyTodo, UpdateObservationSchedule,
+};
+use crate::db::repository;
+use crate::skymap::{tangent_offsets, Color, Scene};
+use crate::state::AppState;
+
+use super::equipment::pixel_scale_arcsec;
+use super::exposure_plan::{profile_equipment, ProfileEquipment};
+use super::plate_solve::parse_angle;
+use super::sky_cutout::{fetch_cutout, CutoutOptions};
+
+/// Default cutout width as a multiple of the field's diagonal
+const FIELD_MARGIN: f64 = 1.6;
+const FOOTPRINT: Color = Color::rgb(0x14b8a6);
+const TARGET: Color = Color::rgb(0xf97316);
+const LABEL_BACKGROUND: Color = Color::rgb(0x0b1020);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FramingInput {
+    /// Todo for the target; the framing is stored on it
+    pub todo_id: String,
+    /// Equipment profile for the telescope, reducer and camera
+    pub profile_id: Option<String>,
+    /// Effective focal length, including any reducer
+    pub focal_length_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<u32>,
+    pub sensor_height_px: Option<u32>,
+    /// Camera angle in degrees east of north (default: the saved framing's,
+    /// else 0)
+    pub rotation: Option<f64>,
+    /// Field center in degrees (default: the saved framing's, else the
+    /// target)
+    pub center_ra: Option<f64>,
+    pub center_dec: Option<f64>,
+    /// Cutout width in degrees (default: 1.6x the field's diagonal)
+    pub fov: Option<f64>,
+    /// Cutout size and survey
+    pub cutout: Option<CutoutOptions>,
+    /// Schedule item to store the framing on as well
+    pub schedule_id: Option<String>,
+    pub item_id: Option<String>,
+    /// Render without saving, e.g. while adjusting the rotation
+    pub preview: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FramingPlan {
+    pub framing: Framing,
+    /// JPEG of the cutout with the field drawn on it
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    /// Cutout width in degrees
+    pub fov: f64,
+    pub survey: String,
+    /// Cutout served from the cache rather than downloaded
+    pub cached: bool,
+    pub saved: bool,
+}
+
+/// Width and height in degrees of a camera's field
+pub fn camera_field(
+    focal_length_mm: f64,
+    pixel_size_um: f64,
+    sensor_width_px: u32,
+    sensor_height_px: u32,
+) -> (f64, f64) {
+    let scale = pixel_scale_arcsec(focal_length_mm, pixel_size_um, 1.0) / 3600.0;
+    (
+        sensor_width_px as f64 * scale,
+        sensor_height_px as f64 * scale,
+    )
+}
+
+/// Tangent plane offsets in degrees (east, north) of the corners of a
+/// `width` x `height` field turned `rotation` degrees east of north, top
+/// edge first
+fn field_corners(width: f64, height: f64, rotation: f64) -> [(f64, f64); 4] {
+    let (w, h) = (width / 2.0, height / 2.0);
+    [(w, h), (-w, h), (-w, -h), (w, -h)].map(|offset| turn(offset, rotation))
+}
+
+/// Turn an offset given with north up by `rotation` degrees east of north
+fn turn((x, y): (f64, f64), rotation: f64) -> (f64, f64) {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    (x * cos + y * sin, y * cos - x * sin)
+}
+
+/// Pixel position of a tangent plane offset on a north-up, east-left image
+/// with its center at `center`
+fn offset_to_pixel(
+    (east, north): (f64, f64),
+    center: (f64, f64),
+    pixels_per_degree: f64,
+) -> (f64, f64) {
+    (
+        center.0 - east * pixels_per_degree,
+        center.1 - north * pixels_per_degree,
+    )
+}
+
+/// Draw the field, an arrow off its top edge and the target on a cutout
+fn draw_framing(
+    cutout: &DynamicImage,
+    framing: &Framing,
+    fov: f64,
+    target: (f64, f64),
+) -> image::RgbImage {
+    let mut canvas: RgbaImage = cutout.to_rgba8();
+    let (width, height) = canvas.dimensions();
+    let center = (width as f64 / 2.0, height as f64 / 2.0);
+    let pixels_per_degree = width as f64 / fov;
+    let line = (width as f64 / 400.0).max(2.0);
+    let to_pixel = |offset| offset_to_pixel(offset, center, pixels_per_degree);
+
+    let mut scene = Scene::new(width, height, LABEL_BACKGROUND);
+    let corners =
+        field_corners(framing.field_width, framing.field_height, framing.rotation).map(to_pixel);
+    for (from, to) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+        scene.line(*from, *to, FOOTPRINT, line);
+    }
+
+    // Which way is up on the sensor
+    let arrow =
+        (framing.field_width.min(framing.field_height) / 12.0).max(line * 3.0 / pixels_per_degree);
+    let top = framing.field_height / 2.0;
+    let tip = [(0.0, top + arrow), (arrow / 2.0, top), (-arrow / 2.0, top)]
+        .map(|offset| to_pixel(turn(offset, framing.rotation)));
+    scene.polygon(&tip, FOOTPRINT);
+
+    if let Some(offset) = tangent_offsets(framing.center_ra, framing.center_dec, target.0, target.1)
+    {
+        let at = to_pixel((offset.0.to_degrees(), offset.1.to_degrees()));
+        let radius = line * 4.0;
+        scene.circle(at, radius, TARGET, line);
+        for (dx, dy) in [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)] {
+            let tick = |length: f64| (at.0 + dx * length, at.1 + dy * length);
+            scene.line(tick(radius), tick(radius * 2.0), TARGET, line);
+        }
+    }
+
+    let label = format!(
+        "{:.1}' × {:.1}'  PA {:.0}°",
+        framing.field_width * 60.0,
+        framing.field_height * 60.0,
+        framing.rotation
+    );
+    let scale = (width / 400).max(1);
+    let padding = 4 * scale;
+    let (label_width, label_height) = (text_width(&label, scale), text_height(scale));
+    let box_max = ((label_width + padding * 2) as f64, height as f64);
+    let box_min = (
+        0.0,
+        height.saturating_sub(label_height + padding * 2) as f64,
+    );
+    scene.polygon(
+        &[
+            box_min,
+            (box_max.0, box_min.1),
+            box_max,
+            (box_min.0, box_max.1),
+        ],
+        LABEL_BACKGROUND.alpha(0.6),
+    );
+    scene.draw_onto(&mut canvas);
+
+    let mut image = DynamicImage::ImageRgba8(canvas).to_rgb8();
+    draw_text(
+        &mut image,
+        (padding as i64, box_min.1 as i64 + padding as i64),
+        &label,
+        scale,
+        Rgb([255, 255, 255]),
+        0.9,
+    );
+    image
+}
+
+/// The todo's saved framing, if any
+fn saved_framing(todo: &AstronomyTodo) -> Option<Framing> {
+    serde_json::from_str(todo.framing.as_deref()?).ok()
+}
+
+/// Center, rotation and field for the input, from explicit values, the
+/// saved framing, the target and the equipment profile
+fn resolve_framing(
+    input: &FramingInput,
+    todo: &AstronomyTodo,
+    equipment: &ProfileEquipment,
+) -> Result<Framing, String> {
+    let saved = saved_framing(todo);
+    let reduction = equipment
+        .reducer
+        .as_ref()
+        .and_then(|r| r.reduction_factor)
+        .unwrap_or(1.0);
+    let focal_length_mm = input
+        .focal_length_mm
+        .or_else(|| Some(equipment.telescope.as_ref()?.focal_length_mm? * reduction))
+        .ok_or("Telescope focal length is required")?;
+    let camera = equipment.camera.as_ref();
+    let pixel_size_um = input
+        .pixel_size_um
+        .or_else(|| camera?.pixel_size_um)
+        .ok_or("Camera pixel size is required")?;
+    let sensor = |explicit: Option<u32>, stored: Option<i32>| {
+        explicit.or_else(|| u32::try_from(stored?).ok())
+    };
+    let sensor_width = sensor(
+        input.sensor_width_px,
+        camera.and_then(|c| c.sensor_width_px),
+    );
+    let sensor_height = sensor(
+        input.sensor_height_px,
+        camera.and_then(|c| c.sensor_height_px),
+    );
+    let (Some(sensor_width), Some(sensor_height)) = (sensor_width, sensor_height) else {
+        return Err("Camera sensor size is required".to_string());
+    };
+    if focal_length_mm <= 0.0 || pixel_size_um <= 0.0 || sensor_width == 0 || sensor_height == 0 {
+        return Err(
+            "Focal length, pixel size and sensor size must be greater than zero".to_string(),
+        );
+    }
+    let (field_width, field_height) =
+        camera_field(focal_length_mm, pixel_size_um, sensor_width, sensor_height);
+
+    let center = match (input.center_ra, input.center_dec) {
+        (Some(ra), Some(dec)) => (ra, dec),
+        (None, None) => match &saved {
+            Some(saved) => (saved.center_ra, saved.center_dec),
+            None => parse_angle(&todo.ra, 15.0)
+                .zip(parse_angle(&todo.dec, 1.0))
+                .ok_or_else(|| format!("Todo has no usable coordinates: {}", todo.name))?,
+        },
+        _ => return Err("Both centerRa and centerDec are required".to_string()),
+    };
+    if !(-90.0..=90.0).contains(&center.1) {
+        return Err(format!("Invalid declination: {}", center.1));
+    }
+    let rotation = input
+        .rotation
+        .or_else(|| saved.as_ref().map(|s| s.rotation))
+        .unwrap_or(0.0);
+    if !rotation.is_finite() {
+        return Err(format!("Invalid rotation: {}", rotation));
+    }
+
+    Ok(Framing {
+        center_ra: center.0.rem_euclid(360.0),
+        center_dec: center.1,
+        rotation: rotation.rem_euclid(360.0),
+        field_width,
+        field_height,
+        profile_id: input.profile_id.clone(),
+        planned_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Store the framing on a schedule item for the same target
+fn save_to_schedule_item(
+    conn: &mut diesel::SqliteConnection,
+    schedule_id: &str,
+    item_id: &str,
+    todo_id: &str,
+    framing: &Framing,
+) -> Result<(), String> {
+    let schedule = repository::get_schedule_by_id(conn, schedule_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Schedule not found".to_string())?;
+    let mut items: Vec<ScheduleItem> = serde_json::from_str(&schedule.items).unwrap_or_default();
+    let item = items
+        .iter_mut()
+        .find(|i| i.id == item_id)
+        .ok_or_else(|| format!("Schedule item not found: {}", item_id))?;
+    if item.todo_id != todo_id {
+        return Err("Schedule item is for a different target".to_string());
+    }
+    item.framing = Some(framing.clone());
+
+    let update = UpdateObservationSchedule {
+        items: Some(serde_json::to_string(&items).map_err(|e| e.to_string())?),
+        ..Default::default()
+    };
+    repository::update_schedule(conn, schedule_id, &update).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Draw the camera's field at a rotation on a survey cutout of a todo's
+/// target. Unless `preview` is set, the framing (center and rotation) is
+/// saved on the todo, and on the schedule item when one is given.
+#[tauri::command]
+pub async fn plan_framing(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    input: FramingInput,
+) -> Result<FramingPlan, String> {
+    let (todo, framing) = {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        let todo = repository::get_todo_by_id(&mut conn, &input.todo_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Todo not found: {}", input.todo_id))?;
+        let equipment = profile_equipment(&mut conn, input.profile_id.as_deref())?;
+        let framing = resolve_framing(&input, &todo, &equipment)?;
+        (todo, framing)
+    };
+    if input.schedule_id.is_some() != input.item_id.is_some() {
+        return Err("Both scheduleId and itemId are required".to_string());
+    }
+
+    let fov = input
+        .fov
+        .unwrap_or_else(|| framing.field_width.hypot(framing.field_height) * FIELD_MARGIN);
+    let options = input.cutout.clone().unwrap_or_default();
+    let (cutout, data) = fetch_cutout(
+        &app,
+        &state.network,
+        framing.center_ra,
+        framing.center_dec,
+        fov,
+        &options,
+    )
+    .await?;
+
+    let target = parse_angle(&todo.ra, 15.0)
+        .zip(parse_angle(&todo.dec, 1.0))
+        .unwrap_or((framing.center_ra, framing.center_dec));
+    let image =
+        image::load_from_memory(&data).map_err(|e| format!("Invalid cutout image: {}", e))?;
+    let rendered = draw_framing(&image, &framing, cutout.fov, target);
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut Cursor::new(&mut jpeg), 90)
+        .encode_image(&rendered)
+        .map_err(|e| format!("Failed to encode framing preview: {}", e))?;
+
+    if !input.preview {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        let update = UpdateAstronomyTodo {
+            framing: Some(serde_json::to_string(&framing).map_err(|e| e.to_string())?),
+            last_updated: Some(chrono::Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+        repository::update_todo(&mut conn, &todo.id, &update).map_err(|e| e.to_string())?;
+        if let (Some(schedule_id), Some(item_id)) = (&input.schedule_id, &input.item_id) {
+            save_to_schedule_item(&mut conn, schedule_id, item_id, &todo.id, &framing)?;
+        }
+    }
+
+    Ok(FramingPlan {
+        framing,
+        data_url: format!("data:image/jpeg;base64,{}", BASE64.encode(&jpeg)),
+        width: rendered.width(),
+        height: rendered.height(),
+        fov: cutout.fov,
+        survey: cutout.survey,
+        cached: cutout.cached,
+        saved: !input.preview,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framing(rotation: f64) -> Framing {
+        Framing {
+            center_ra: 83.82,
+            center_dec: -5.39,
+            rotation,
+            field_width: 1.0,
+            field_height: 0.5,
+            profile_id: None,
+            planned_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn camera_field_from_optics() {
+        // 3.76µm pixels at 400mm: 1.94"/px
+        let (width, height) = camera_field(400.0, 3.76, 6248, 4176);
+        assert!((width - 3.366).abs() < 0.01, "{}", width);
+        assert!((height - 2.250).abs() < 0.01, "{}", height);
+    }
+
+    #[test]
+    fn corners_turn_east_of_north() {
+        let close =
+            |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9;
+        let upright = field_corners(2.0, 1.0, 0.0);
+        assert!(close(upright[0], (1.0, 0.5)));
+        assert!(close(upright[2], (-1.0, -0.5)));
+        // At 90° the sensor's top edge faces east
+        let turned = field_corners(2.0, 1.0, 90.0);
+        assert!(close(turned[0], (0.5, -1.0)));
+        assert!(close(turned[1], (0.5, 1.0)));
+        // East is to the left on a north-up image
+        assert_eq!(
+            offset_to_pixel((0.5, 0.25), (100.0, 100.0), 100.0),
+            (50.0, 75.0)
+        );
+    }
+
+    #[test]
+    fn draws_field_and_target() {
+        let cutout = DynamicImage::new_rgb8(400, 400);
+        let framing = framing(0.0);
+        // 2° wide cutout: 200px per degree, so the field spans x 100..300
+        // and y 150..250
+        let image = draw_framing(&cutout, &framing, 2.0, (83.82, -5.39));
+        let lit = |x: u32, y: u32| image.get_pixel(x, y).0 != [0, 0, 0];
+        assert!(lit(100, 200));
+        assert!(lit(300, 200));
+        assert!(lit(200, 150));
+        assert!(!lit(200, 120));
+        // Target marker ring at the center
+        assert!(lit(200, 192));
+        assert!(!lit(150, 200));
+        // Label box in the bottom left
+        assert!(lit(2, 398));
+    }
+
+    #[test]
+    fn resolves_center_and_rotation() {
+        let todo = AstronomyTodo {
+            id: "todo-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "M42".to_string(),
+            ra: "05:35:17".to_string(),
+            dec: "-05:23:28".to_string(),
+            magnitude: "4.0".to_string(),
+            size: "85' × 60'".to_string(),
+            object_type: None,
+            added_at: String::new(),
+            completed: false,
+            completed_at: None,
+            goal_time: None,
+            notes: None,
+            flagged: false,
+            last_updated: None,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+            tags: None,
+            exposure_plan: None,
+            framing: None,
+        };
+        let input = FramingInput {
+            todo_id: todo.id.clone(),
+            focal_length_mm: Some(400.0),
+            pixel_size_um: Some(3.76),
+            sensor_width_px: Some(6248),
+            sensor_height_px: Some(4176),
+            rotation: Some(-30.0),
+            ..Default::default()
+        };
+        let equipment = ProfileEquipment::default();
+        let resolved = resolve_framing(&input, &todo, &equipment).unwrap();
+        assert!((resolved.center_ra - 83.8208).abs() < 1e-3);
+        assert!((resolved.center_dec + 5.3911).abs() < 1e-3);
+        assert_eq!(resolved.rotation, 330.0);
+
+        // A saved framing is the default for center and rotation
+        let saved_todo = AstronomyTodo {
+            framing: Some(serde_json::to_string(&framing(45.0)).unwrap()),
+            ..todo.clone()
+        };
+        let input = FramingInput {
+            rotation: None,
+            ..input
+        };
+        let resolved = resolve_framing(&input, &saved_todo, &equipment).unwrap();
+        assert_eq!((resolved.center_ra, resolved.rotation), (83.82, 45.0));
+
+        let missing = FramingInput {
+            pixel_size_um: None,
+            ..input
+        };
+        assert!(resolve_framing(&missing, &todo, &equipment).is_err());
+    }
+}
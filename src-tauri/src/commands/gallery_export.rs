@@ -0,0 +1,450 @@
+//! Static HTML gallery export for publishing a collection to any web host
+//!
+//! Produces a self-contained folder: `index.html` with a thumbnail grid, one
+//! page per image with a full preview and acquisition details, and the
+//! stylesheet and images they reference. No JavaScript or server needed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::{Collection, Image};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::export::{acquisition_from_metadata, format_number};
+use super::scan::metadata_header_value;
+
+const THUMB_SIZE: u32 = 400;
+/// Longest side of the full previews; keeps pages fast to load
+pub(super) const PREVIEW_SIZE: u32 = 2560;
+
+const GALLERY_CSS: &str = r#":root { color-scheme: dark; }
+* { box-sizing: border-box; }
+body { margin: 0; background: #0b0d12; color: #d8dbe2; font: 15px/1.5 system-ui, sans-serif; }
+a { color: #8ab4f8; text-decoration: none; }
+a:hover { text-decoration: underline; }
+header, main, footer { max-width: 1400px; margin: 0 auto; padding: 1rem 1.5rem; }
+h1 { margin: 0 0 .25rem; font-weight: 600; }
+.meta { color: #8b90a0; display: flex; gap: 1.25rem; flex-wrap: wrap; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(220px, 1fr)); gap: .75rem; }
+.card { background: #141821; border-radius: 6px; overflow: hidden; display: block; color: inherit; }
+.card img { width: 100%; aspect-ratio: 1; object-fit: cover; display: block; }
+.card .caption { padding: .5rem .75rem; font-size: 13px; }
+.card .caption small { display: block; color: #8b90a0; }
+.preview img { max-width: 100%; height: auto; display: block; margin: 0 auto; border-radius: 4px; }
+.nav { display: flex; justify-content: space-between; gap: 1rem; margin: 1rem 0; }
+table { border-collapse: collapse; margin-top: 1rem; }
+th, td { text-align: left; padding: .3rem 1.5rem .3rem 0; border-bottom: 1px solid #1f2430; }
+th { color: #8b90a0; font-weight: 500; }
+footer { color: #5d6272; font-size: 13px; }
+"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GalleryExportResult {
+    pub index_path: String,
+    pub images: usize,
+    /// Images without a readable file or thumbnail
+    pub skipped: usize,
+}
+
+/// One image as it appears in the gallery
+#[derive(Debug, Clone, PartialEq)]
+pub struct GalleryEntry {
+    pub id: String,
+    pub title: String,
+    pub date: Option<String>,
+    /// Acquisition details as label/value rows
+    pub details: Vec<(String, String)>,
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub(super) fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as i64;
+    match (total / 3600, (total % 3600) / 60, total % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Build the gallery entry for an image from its FITS metadata
+pub fn gallery_entry(image: &Image) -> GalleryEntry {
+    let title = image
+        .summary
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| image.filename.clone());
+    let meta = image.metadata.as_deref().unwrap_or("{}");
+    let acq = acquisition_from_metadata(meta);
+
+    let mut details = Vec::new();
+    let mut add = |label: &str, value: Option<String>| {
+        if let Some(value) = value {
+            details.push((label.to_string(), value));
+        }
+    };
+    add("Object", metadata_header_value(meta, "object", "OBJECT"));
+    add("Date", acq.date.clone());
+    add(
+        "Telescope",
+        metadata_header_value(meta, "telescope", "TELESCOP"),
+    );
+    add("Camera", metadata_header_value(meta, "camera", "INSTRUME"));
+    add("Filter", acq.filter.clone());
+    add(
+        "Exposure",
+        acq.duration.map(|d| {
+            if acq.frames > 1 {
+                format!("{} × {}s", acq.frames, format_number(d))
+            } else {
+                format!("{}s", format_number(d))
+            }
+        }),
+    );
+    add(
+        "Integration",
+        acq.duration
+            .filter(|_| acq.frames > 1)
+            .map(|d| format_duration(d * acq.frames as f64)),
+    );
+    add("Gain", acq.gain.map(|g| g.to_string()));
+    add("Binning", acq.binning.map(|b| format!("{0}×{0}", b)));
+    add(
+        "Sensor temperature",
+        acq.sensor_cooling.map(|t| format!("{} °C", t)),
+    );
+    add(
+        "Focal ratio",
+        acq.f_number.map(|f| format!("f/{}", format_number(f))),
+    );
+    add(
+        "Ambient temperature",
+        acq.temperature.map(|t| format!("{} °C", format_number(t))),
+    );
+    add("File", Some(image.filename.clone()));
+
+    GalleryEntry {
+        id: image.id.clone(),
+        title,
+        date: acq.date,
+        details,
+    }
+}
+
+fn page(title: &str, css_path: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n{}\
+         <footer>Made with Astra</footer>\n</body>\n</html>\n",
+        escape_html(title),
+        css_path,
+        body
+    )
+}
+
+/// Render `index.html`: collection header and thumbnail grid
+pub fn render_index(collection: &Collection, entries: &[GalleryEntry]) -> String {
+    let mut dates: Vec<&str> = entries.iter().filter_map(|e| e.date.as_deref()).collect();
+    dates.sort_unstable();
+
+    let mut meta = vec![format!(
+        "<span>{} image{}</span>",
+        entries.len(),
+        if entries.len() == 1 { "" } else { "s" }
+    )];
+    match (dates.first(), dates.last()) {
+        (Some(first), Some(last)) if first != last => {
+            meta.push(format!("<span>{} – {}</span>", first, last))
+        }
+        (Some(first), _) => meta.push(format!("<span>{}</span>", first)),
+        _ => {}
+    }
+
+    let mut body = format!("<header>\n<h1>{}</h1>\n", escape_html(&collection.name));
+    if let Some(description) = collection.description.as_deref().filter(|d| !d.is_empty()) {
+        body.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+    }
+    body.push_str(&format!(
+        "<div class=\"meta\">{}</div>\n</header>\n<main class=\"grid\">\n",
+        meta.join("")
+    ));
+    for entry in entries {
+        body.push_str(&format!(
+            "<a class=\"card\" href=\"images/{id}.html\"><img src=\"thumbs/{id}.jpg\" alt=\"{title}\" loading=\"lazy\">\
+             <div class=\"caption\">{title}<small>{date}</small></div></a>\n",
+            id = escape_html(&entry.id),
+            title = escape_html(&entry.title),
+            date = escape_html(entry.date.as_deref().unwrap_or("")),
+        ));
+    }
+    body.push_str("</main>\n");
+
+    page(&collection.name, "assets/style.css", &body)
+}
+
+/// Render the page for one image with links to its neighbours
+pub fn render_image_page(
+    collection: &Collection,
+    entry: &GalleryEntry,
+    prev: Option<&GalleryEntry>,
+    next: Option<&GalleryEntry>,
+) -> String {
+    let link = |target: Option<&GalleryEntry>, label: &str| {
+        target
+            .map(|t| format!("<a href=\"{}.html\">{}</a>", escape_html(&t.id), label))
+            .unwrap_or_else(|| "<span></span>".to_string())
+    };
+
+    let mut body = format!(
+        "<header>\n<a href=\"../index.html\">← {}</a>\n<h1>{}</h1>\n</header>\n<main>\n",
+        escape_html(&collection.name),
+        escape_html(&entry.title)
+    );
+    body.push_str(&format!(
+        "<div class=\"preview\"><a href=\"../previews/{id}.jpg\"><img src=\"../previews/{id}.jpg\" alt=\"{title}\"></a></div>\n",
+        id = escape_html(&entry.id),
+        title = escape_html(&entry.title),
+    ));
+    body.push_str(&format!(
+        "<div class=\"nav\">{}{}</div>\n<table>\n",
+        link(prev, "← Previous"),
+        link(next, "Next →")
+    ));
+    for (label, value) in &entry.details {
+        body.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            escape_html(label),
+            escape_html(value)
+        ));
+    }
+    body.push_str("</table>\n</main>\n");
+
+    page(&entry.title, "../assets/style.css", &body)
+}
+
+/// Find displayable image data: the file itself, a generated JPEG preview
+/// (for FITS files), or the stored thumbnail as a last resort
+pub(super) fn load_source_image(image: &Image, previews_dir: &Path) -> Option<image::DynamicImage> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(url) = &image.url {
+        let path = Path::new(url);
+        candidates.push(path.to_path_buf());
+        if let Some(stem) = path.file_stem() {
+            candidates.push(path.with_file_name(format!("{}_preview.jpg", stem.to_string_lossy())));
+        }
+    }
+    candidates.push(previews_dir.join(format!("{}.jpg", image.id)));
+
+    candidates
+        .iter()
+        .filter(|p| p.exists())
+        .find_map(|p| image::open(p).ok())
+        .or_else(|| {
+            let thumbnail = image.thumbnail.as_deref()?;
+            let (_, data) = thumbnail.split_once(";base64,")?;
+            image::load_from_memory(&BASE64.decode(data).ok()?).ok()
+        })
+}
+
+pub(super) fn write_jpeg(
+    img: &image::DynamicImage,
+    max_size: u32,
+    path: &Path,
+) -> Result<(), String> {
+    let resized = if img.width() > max_size || img.height() > max_size {
+        img.thumbnail(max_size, max_size)
+    } else {
+        img.clone()
+    };
+    resized
+        .to_rgb8()
+        .save_with_format(path, image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Write the gallery for `collection` into `out_dir`
+pub fn write_gallery(
+    collection: &Collection,
+    images: &[Image],
+    previews_dir: &Path,
+    out_dir: &Path,
+) -> Result<GalleryExportResult, String> {
+    for dir in ["assets", "images", "thumbs", "previews"] {
+        fs::create_dir_all(out_dir.join(dir))
+            .map_err(|e| format!("Failed to create gallery folder: {}", e))?;
+    }
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for image in images {
+        let Some(source) = load_source_image(image, previews_dir) else {
+            log::warn!("Gallery export: no displayable file for {}", image.filename);
+            skipped += 1;
+            continue;
+        };
+        write_jpeg(
+            &source,
+            THUMB_SIZE,
+            &out_dir.join("thumbs").join(format!("{}.jpg", image.id)),
+        )?;
+        write_jpeg(
+            &source,
+            PREVIEW_SIZE,
+            &out_dir.join("previews").join(format!("{}.jpg", image.id)),
+        )?;
+        entries.push(gallery_entry(image));
+    }
+    // Oldest first, so the gallery reads like the night unfolded
+    entries.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.title.cmp(&b.title)));
+
+    let write = |path: PathBuf, content: &str| {
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+    write(out_dir.join("assets").join("style.css"), GALLERY_CSS)?;
+    for (i, entry) in entries.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|p| entries.get(p));
+        let html = render_image_page(collection, entry, prev, entries.get(i + 1));
+        write(
+            out_dir.join("images").join(format!("{}.html", entry.id)),
+            &html,
+        )?;
+    }
+    let index_path = out_dir.join("index.html");
+    write(index_path.clone(), &render_index(collection, &entries))?;
+
+    Ok(GalleryExportResult {
+        index_path: index_path.to_string_lossy().to_string(),
+        images: entries.len(),
+        skipped,
+    })
+}
+
+/// Export a collection as a static HTML gallery into the folder at `path`
+#[tauri::command]
+pub async fn export_gallery(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    collection_id: String,
+    path: String,
+) -> Result<GalleryExportResult, String> {
+    let previews_dir = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collection = repository::get_collection_by_id(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Collection not found")?;
+    let images = repository::get_images_in_collection(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?;
+    drop(conn);
+
+    tokio::task::spawn_blocking(move || {
+        write_gallery(&collection, &images, &previews_dir, Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Gallery export failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, title: &str, date: Option<&str>) -> GalleryEntry {
+        GalleryEntry {
+            id: id.to_string(),
+            title: title.to_string(),
+            date: date.map(String::from),
+            details: vec![("Filter".to_string(), "Ha".to_string())],
+        }
+    }
+
+    fn collection() -> Collection {
+        Collection {
+            id: "c1".to_string(),
+            user_id: "local-user".to_string(),
+            name: "M42 <night 1>".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+            archived: false,
+            cover_image_id: None,
+            image_sort: None,
+        }
+    }
+
+    #[test]
+    fn index_links_pages_and_escapes_text() {
+        let entries = vec![
+            entry("a", "Orion & friends", Some("2026-01-10")),
+            entry("b", "Running Man", Some("2026-01-12")),
+        ];
+        let html = render_index(&collection(), &entries);
+        assert!(html.contains("<h1>M42 &lt;night 1&gt;</h1>"));
+        assert!(html.contains("href=\"images/a.html\""));
+        assert!(html.contains("src=\"thumbs/b.jpg\""));
+        assert!(html.contains("Orion &amp; friends"));
+        assert!(html.contains("2026-01-10 – 2026-01-12"));
+    }
+
+    #[test]
+    fn image_page_shows_details_and_neighbours() {
+        let (a, b) = (entry("a", "A", None), entry("b", "B", None));
+        let html = render_image_page(&collection(), &b, Some(&a), None);
+        assert!(html.contains("<tr><th>Filter</th><td>Ha</td></tr>"));
+        assert!(html.contains("href=\"a.html\">← Previous"));
+        assert!(!html.contains("Next →"));
+        assert!(html.contains("../previews/b.jpg"));
+    }
+
+    #[test]
+    fn entry_reads_acquisition_from_metadata() {
+        let meta =
+            r#"{"object":"M42","filter":"Ha","exposure":300.0,"stacked_frames":24,"gain":100}"#;
+        let image = Image {
+            filename: "m42.fits".to_string(),
+            metadata: Some(meta.to_string()),
+            ..Image::test("i")
+        };
+        let entry = gallery_entry(&image);
+        assert_eq!(entry.title, "m42.fits");
+        let detail = |label: &str| {
+            entry
+                .details
+                .iter()
+                .find(|(l, _)| l == label)
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(detail("Object").as_deref(), Some("M42"));
+        assert_eq!(detail("Exposure").as_deref(), Some("24 × 300s"));
+        assert_eq!(detail("Integration").as_deref(), Some("2h 0m"));
+        assert_eq!(detail("Gain").as_deref(), Some("100"));
+    }
+}
@@ -209,6 +209,14 @@ pub async fn import_images_hoardfs(
                         None
                     },
                     blob_id,
+                    exposure: None,
+                    gain: None,
+                    filter: None,
+                    telescope: None,
+                    date_obs: None,
+                    wcs: None,
+                    parent_image_id: None,
+                    triage: None,
                 };
 
                 match repository::create_image(&mut conn, &new_image) {
@@ -474,7 +482,7 @@ pub async fn migrate_images_to_hoardfs(
 /// context (e.g. `tokio::task::spawn_blocking`). `on_progress(current, total,
 /// filename)` fires once per image, before it is processed.
 pub fn migrate_library_core(
-    db: &crate::db::DbPool,
+    db: &crate::db::SharedDbPool,
     hoardfs: &std::sync::Arc<std::sync::Mutex<hoardfs_volume::HoardFs>>,
     rt: &tokio::runtime::Handle,
     user_id: &str,
@@ -0,0 +1,543 @@
+//! Image sharing bundles: a zip of images with their files, previews and
+//! metadata, for handing observations to another Astra user without a full
+//! database backup
+//!
+//! The manifest carries each image's descriptive fields (target, metadata,
+//! annotations, acquisition values, rating) but nothing tied to the
+//! sender's library such as file paths, collections or the user. Images keep
+//! their ids, so importing a bundle twice doesn't duplicate them.
+
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::db::models::{Image, NewCollection, NewCollectionImage, NewImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::gallery_export::{load_source_image, PREVIEW_SIZE};
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// An image as recorded in a bundle manifest. File fields are entry names
+/// inside the zip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledImage {
+    pub id: String,
+    pub filename: String,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub content_type: Option<String>,
+    pub favorite: bool,
+    pub tags: Option<String>,
+    pub annotations: Option<String>,
+    pub metadata: Option<String>,
+    pub thumbnail: Option<String>,
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    pub date_obs: Option<String>,
+    pub wcs: Option<String>,
+    pub rating: Option<i32>,
+    /// Only when the parent is in the same bundle
+    pub parent_image_id: Option<String>,
+    pub file: Option<String>,
+    pub fits_file: Option<String>,
+    pub preview: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageBundleManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub images: Vec<BundledImage>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageBundleExportResult {
+    pub path: String,
+    pub images: usize,
+    /// Image and FITS files included
+    pub files: usize,
+    /// Files the images refer to that aren't on disk
+    pub missing_files: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageBundleImportResult {
+    /// Collection holding the imported images; None when nothing was new
+    pub collection_id: Option<String>,
+    pub images_imported: usize,
+    /// Images already in the library
+    pub images_skipped: usize,
+    pub files_extracted: usize,
+    pub errors: Vec<String>,
+}
+
+/// Ids become folder and entry names, so only plain ones are accepted
+fn is_safe_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn zip_error(e: zip::result::ZipError) -> String {
+    format!("Failed to write image bundle: {}", e)
+}
+
+/// Add a file from disk as `files/<image id>/<file name>`, returning the
+/// entry name, or None when the file is missing
+fn add_file<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    image_id: &str,
+    path: &str,
+    result: &mut ImageBundleExportResult,
+) -> Result<Option<String>, String> {
+    let source = Path::new(path);
+    let (Some(name), Ok(mut file)) = (source.file_name(), File::open(source)) else {
+        result.missing_files.push(path.to_string());
+        return Ok(None);
+    };
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let entry = format!("files/{}/{}", image_id, name.to_string_lossy());
+    let options = SimpleFileOptions::default().large_file(size >= u32::MAX as u64);
+    zip.start_file(entry.as_str(), options).map_err(zip_error)?;
+    io::copy(&mut file, zip).map_err(|e| format!("Failed to add {}: {}", path, e))?;
+    result.files += 1;
+    Ok(Some(entry))
+}
+
+/// JPEG preview of an image, as the gallery export makes them
+fn preview_jpeg(image: &Image, previews_dir: &Path) -> Option<Vec<u8>> {
+    let source = load_source_image(image, previews_dir)?;
+    let mut data = Cursor::new(Vec::new());
+    source
+        .thumbnail(PREVIEW_SIZE, PREVIEW_SIZE)
+        .to_rgb8()
+        .write_to(&mut data, image::ImageFormat::Jpeg)
+        .ok()?;
+    Some(data.into_inner())
+}
+
+/// Write `images` with their files and previews as a bundle
+pub fn write_image_bundle<W: Write + Seek>(
+    images: &[Image],
+    previews_dir: &Path,
+    writer: W,
+) -> Result<ImageBundleExportResult, String> {
+    let mut zip = ZipWriter::new(writer);
+    let mut result = ImageBundleExportResult::default();
+    let mut bundled = Vec::new();
+
+    for image in images {
+        if !is_safe_id(&image.id) {
+            return Err(format!("Image id '{}' can't be bundled", image.id));
+        }
+        let file = match image.url.as_deref() {
+            Some(url) => add_file(&mut zip, &image.id, url, &mut result)?,
+            None => None,
+        };
+        let fits_file = match image
+            .fits_url
+            .as_deref()
+            .filter(|f| Some(*f) != image.url.as_deref())
+        {
+            Some(fits) => add_file(&mut zip, &image.id, fits, &mut result)?,
+            None => None,
+        };
+        let preview = match preview_jpeg(image, previews_dir) {
+            Some(jpeg) => {
+                let entry = format!("previews/{}.jpg", image.id);
+                zip.start_file(entry.as_str(), SimpleFileOptions::default())
+                    .map_err(zip_error)?;
+                zip.write_all(&jpeg).map_err(|e| e.to_string())?;
+                Some(entry)
+            }
+            None => None,
+        };
+        let parent_image_id = image
+            .parent_image_id
+            .clone()
+            .filter(|parent| images.iter().any(|i| i.id == *parent));
+
+        bundled.push(BundledImage {
+            id: image.id.clone(),
+            filename: image.filename.clone(),
+            summary: image.summary.clone(),
+            description: image.description.clone(),
+            content_type: image.content_type.clone(),
+            favorite: image.favorite,
+            tags: image.tags.clone(),
+            annotations: image.annotations.clone(),
+            metadata: image.metadata.clone(),
+            thumbnail: image.thumbnail.clone(),
+            exposure: image.exposure,
+            gain: image.gain,
+            filter: image.filter.clone(),
+            telescope: image.telescope.clone(),
+            date_obs: image.date_obs.clone(),
+            wcs: image.wcs.clone(),
+            rating: image.rating,
+            parent_image_id,
+            file,
+            fits_file,
+            preview,
+        });
+    }
+
+    let manifest = ImageBundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        images: bundled,
+    };
+    zip.start_file(MANIFEST_ENTRY, SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+    zip.finish().map_err(zip_error)?;
+
+    result.images = manifest.images.len();
+    Ok(result)
+}
+
+/// Read and check a bundle's manifest
+pub fn read_bundle_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<ImageBundleManifest, String> {
+    let entry = archive
+        .by_name(MANIFEST_ENTRY)
+        .map_err(|_| "Not an image bundle: manifest.json is missing".to_string())?;
+    let manifest: ImageBundleManifest =
+        serde_json::from_reader(entry).map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format {} needs a newer version of Astra",
+            manifest.format_version
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Extract a bundle entry into `dir`, keeping only its file name
+fn extract_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entry: &str,
+    dir: &Path,
+) -> Result<PathBuf, String> {
+    let name = Path::new(entry)
+        .file_name()
+        .ok_or_else(|| format!("Invalid bundle entry '{}'", entry))?;
+    let mut source = archive
+        .by_name(entry)
+        .map_err(|e| format!("Bundle entry '{}' unreadable: {}", entry, e))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(name);
+    let mut file =
+        File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    io::copy(&mut source, &mut file).map_err(|e| format!("Failed to extract {}: {}", entry, e))?;
+    Ok(path)
+}
+
+/// Import a bundle's images into a new collection called `collection_name`,
+/// extracting their files under `destination/<image id>/`
+pub fn import_image_bundle_into<R: Read + Seek>(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    archive: &mut ZipArchive<R>,
+    destination: &Path,
+    collection_name: &str,
+) -> Result<ImageBundleImportResult, String> {
+    let manifest = read_bundle_manifest(archive)?;
+    let mut result = ImageBundleImportResult::default();
+
+    for bundled in &manifest.images {
+        if !is_safe_id(&bundled.id) {
+            result
+                .errors
+                .push(format!("Skipped image with invalid id '{}'", bundled.id));
+            continue;
+        }
+        if repository::get_image_by_id(conn, &bundled.id)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            result.images_skipped += 1;
+            continue;
+        }
+
+        let dir = destination.join(&bundled.id);
+        let mut extract = |entry: &Option<String>| -> Option<String> {
+            let path = match extract_entry(archive, entry.as_deref()?, &dir) {
+                Ok(path) => path,
+                Err(e) => {
+                    result.errors.push(e);
+                    return None;
+                }
+            };
+            result.files_extracted += 1;
+            Some(path.to_string_lossy().to_string())
+        };
+        let file = extract(&bundled.file);
+        let fits_file = extract(&bundled.fits_file);
+        let preview = extract(&bundled.preview);
+
+        let collection_id = match &result.collection_id {
+            Some(id) => id.clone(),
+            None => {
+                let collection = repository::create_collection(
+                    conn,
+                    &NewCollection {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        user_id: user_id.to_string(),
+                        name: collection_name.to_string(),
+                        description: Some("Imported from an image bundle".to_string()),
+                        visibility: "private".to_string(),
+                        template: None,
+                        favorite: false,
+                        tags: None,
+                        metadata: None,
+                        archived: false,
+                    },
+                )
+                .map_err(|e| format!("Failed to create collection: {}", e))?;
+                result.collection_id = Some(collection.id.clone());
+                collection.id
+            }
+        };
+
+        let image = repository::create_image(
+            conn,
+            &NewImage {
+                id: bundled.id.clone(),
+                user_id: user_id.to_string(),
+                collection_id: Some(collection_id.clone()),
+                filename: bundled.filename.clone(),
+                // Without the display file, the preview stands in for it
+                url: file.or(preview).or_else(|| fits_file.clone()),
+                summary: bundled.summary.clone(),
+                description: bundled.description.clone(),
+                content_type: bundled.content_type.clone(),
+                favorite: bundled.favorite,
+                tags: bundled.tags.clone(),
+                visibility: Some("private".to_string()),
+                location: None,
+                annotations: bundled.annotations.clone(),
+                metadata: bundled.metadata.clone(),
+                thumbnail: bundled.thumbnail.clone(),
+                fits_url: fits_file,
+                blob_id: None,
+                exposure: bundled.exposure,
+                gain: bundled.gain,
+                filter: bundled.filter.clone(),
+                telescope: bundled.telescope.clone(),
+                date_obs: bundled.date_obs.clone(),
+                wcs: bundled.wcs.clone(),
+                parent_image_id: bundled.parent_image_id.clone(),
+                triage: None,
+            },
+        )
+        .map_err(|e| format!("Failed to import {}: {}", bundled.filename, e))?;
+        if bundled.rating.is_some() {
+            repository::set_image_rating(conn, &image.id, bundled.rating)
+                .map_err(|e| e.to_string())?;
+        }
+        repository::add_image_to_collection(
+            conn,
+            &NewCollectionImage {
+                id: uuid::Uuid::new_v4().to_string(),
+                collection_id,
+                image_id: image.id,
+            },
+        )
+        .map_err(|e| e.to_string())?;
+        result.images_imported += 1;
+    }
+    Ok(result)
+}
+
+/// Export images with their files, previews and metadata as a zip at `path`
+#[tauri::command]
+pub async fn export_image_bundle(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    image_ids: Vec<String>,
+    path: String,
+) -> Result<ImageBundleExportResult, String> {
+    if image_ids.is_empty() {
+        return Err("No images to export".to_string());
+    }
+    let previews_dir = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let db = state.db.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let mut images = Vec::new();
+        for id in &image_ids {
+            let image = repository::get_image_by_id(&mut conn, id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Image not found: {}", id))?;
+            images.push(image);
+        }
+        drop(conn);
+
+        let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        let mut result = write_image_bundle(&images, &previews_dir, file)?;
+        result.path = path;
+        log::info!(
+            "Exported {} images ({} files) to {}",
+            result.images,
+            result.files,
+            result.path
+        );
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Bundle export failed: {}", e))?
+}
+
+/// Import a bundle made by `export_image_bundle` into a new collection named
+/// after the bundle. Files are extracted into `destination` (default: a
+/// folder under the app data directory).
+#[tauri::command]
+pub async fn import_image_bundle(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    destination: Option<String>,
+) -> Result<ImageBundleImportResult, String> {
+    let name = Path::new(&path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Shared images".to_string());
+    let destination = match destination {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .app_data_dir()
+            .map(|d| d.join("bundles").join(&name))
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?,
+    };
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| format!("Not an image bundle: {}", e))?;
+        let mut conn = db.get()?;
+        let result =
+            import_image_bundle_into(&mut conn, &user_id, &mut archive, &destination, &name)?;
+        log::info!(
+            "Imported {} images from {} ({} already in the library)",
+            result.images_imported,
+            path,
+            result.images_skipped
+        );
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Bundle import failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::Connection;
+
+    const USER: &str = "local-user";
+
+    fn make_image(id: &str, url: Option<String>, fits_url: Option<String>) -> Image {
+        Image {
+            user_id: "someone-else".to_string(),
+            collection_id: Some("their-collection".to_string()),
+            url,
+            summary: Some("M 42".to_string()),
+            favorite: true,
+            tags: Some("processed".to_string()),
+            annotations: Some(r#"[{"name":"M 42"}]"#.to_string()),
+            metadata: Some(r#"{"filter":"Ha"}"#.to_string()),
+            fits_url,
+            exposure: Some(300.0),
+            gain: Some(100),
+            filter: Some("Ha".to_string()),
+            date_obs: Some("2025-01-10T22:00:00".to_string()),
+            rating: Some(4),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn bundles_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let fits = source.path().join("m42.fit");
+        fs::write(&fits, b"SIMPLE  =                    T").unwrap();
+        let fits = Some(fits.to_string_lossy().to_string());
+        let missing = Some("/nowhere/gone.fit".to_string());
+        let images = [
+            make_image("image-1", None, fits),
+            Image {
+                parent_image_id: Some("image-1".to_string()),
+                ..make_image("image-2", missing, None)
+            },
+        ];
+
+        let mut bundle = Cursor::new(Vec::new());
+        let exported = write_image_bundle(&images, source.path(), &mut bundle).unwrap();
+        assert_eq!(exported.images, 2);
+        assert_eq!(exported.files, 1);
+        assert_eq!(exported.missing_files, ["/nowhere/gone.fit"]);
+
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let mut archive = ZipArchive::new(bundle).unwrap();
+        let imported =
+            import_image_bundle_into(&mut conn, USER, &mut archive, destination.path(), "Shared")
+                .unwrap();
+        assert_eq!(imported.images_imported, 2);
+        assert_eq!(imported.files_extracted, 1);
+
+        let image = repository::get_image_by_id(&mut conn, "image-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(image.user_id, USER);
+        assert_eq!(image.rating, Some(4));
+        assert_eq!(image.annotations.as_deref(), Some(r#"[{"name":"M 42"}]"#));
+        let extracted = destination.path().join("image-1").join("m42.fit");
+        assert_eq!(
+            image.fits_url,
+            Some(extracted.to_string_lossy().to_string())
+        );
+        assert!(extracted.exists());
+        let sub = repository::get_image_by_id(&mut conn, "image-2")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sub.parent_image_id.as_deref(), Some("image-1"));
+        assert_eq!(sub.collection_id, imported.collection_id);
+
+        let again =
+            import_image_bundle_into(&mut conn, USER, &mut archive, destination.path(), "Shared")
+                .unwrap();
+        assert_eq!(again.images_imported, 0);
+        assert_eq!(again.images_skipped, 2);
+        assert!(again.collection_id.is_none());
+    }
+}
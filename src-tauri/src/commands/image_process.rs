@@ -6,14 +6,27 @@ use base64::prelude::*;
 use image::imageops::FilterType;
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use tauri::{AppHandle, Emitter, Manager, State, Window};
 
-use crate::db::{models::{NewCollection, NewCollectionImage, NewImage, UpdateImage}, repository};
-use crate::python::image_process::{self, ProcessingParams, ProcessingProgress, ProcessingResult, TargetInfo};
+use crate::db::{
+    models::{
+        Image, NewCollection, NewCollectionImage, NewImage, NewProcessingPreset,
+        ProcessingPreset, UpdateImage, UpdateProcessingPreset,
+    },
+    repository,
+};
+use crate::python::image_process::{
+    self, PreviewResult, ProcessingParams, ProcessingProgress, ProcessingResult, ProgressSender,
+    TargetInfo,
+};
+use crate::stretch::{StretchMethod, StretchParams, StretchedImage};
 use crate::state::AppState;
 
+use super::settings::{load_settings, save_settings, PROCESSING};
+use super::target_types;
+
 /// Name of the collection for processed images
 const PROCESSED_COLLECTION_NAME: &str = "Processed";
 
@@ -21,6 +34,8 @@ const PROCESSED_COLLECTION_NAME: &str = "Processed";
 const THUMBNAIL_SIZE: u32 = 300;
 /// JPEG quality for thumbnails (0-100)
 const THUMBNAIL_QUALITY: u8 = 80;
+/// JPEG quality for processed JPEG outputs unless configured (1-100)
+const DEFAULT_JPEG_QUALITY: u8 = 92;
 
 /// Generate a base64-encoded JPEG thumbnail from an image file
 fn generate_thumbnail(image_path: &Path) -> Result<String, String> {
@@ -109,6 +124,43 @@ pub struct ProcessImageInput {
     pub noise_reduction: Option<f64>,
     /// Contrast adjustment (optional, defaults to 1.3 for Seestar-like output)
     pub contrast: Option<f64>,
+    /// Background removal method, "median" or "polynomial" (optional, defaults to "median")
+    pub background_method: Option<String>,
+    /// Polynomial order for gradient removal (optional, defaults to 2)
+    pub gradient_order: Option<u32>,
+    /// Processing preset to start from (optional, defaults to the default
+    /// preset for the target type); the fields above override it
+    pub preset_id: Option<String>,
+    /// Formats written alongside the processed FITS and PNG preview
+    /// (optional, defaults to the processing settings)
+    pub output_formats: Option<Vec<OutputFormat>>,
+}
+
+/// Additional output of a processed image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// 16-bit TIFF for further editing
+    Tiff,
+    /// High-quality JPEG for sharing
+    Jpeg,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Tiff => "tif",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// Key of the output's path in the processing metadata
+    fn metadata_key(self) -> &'static str {
+        match self {
+            OutputFormat::Tiff => "output_tiff",
+            OutputFormat::Jpeg => "output_jpeg",
+        }
+    }
 }
 
 /// Response from image processing
@@ -118,6 +170,12 @@ pub struct ProcessImageResponse {
     /// Processing result
     #[serde(flatten)]
     pub result: ProcessingResult,
+    /// 16-bit TIFF written alongside, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tiff_path: Option<String>,
+    /// JPEG written alongside, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_jpeg_path: Option<String>,
 }
 
 /// Find companion FITS file for a given image URL (same logic as in images.rs)
@@ -144,19 +202,13 @@ fn find_fits_companion(url: &str) -> Option<String> {
     None
 }
 
-/// Process a FITS image with stretch and enhancements
-#[tauri::command]
-pub async fn process_fits_image(
-    state: State<'_, AppState>,
-    window: Window,
-    input: ProcessImageInput,
-) -> Result<ProcessImageResponse, String> {
-    // Get the image from the database
-    let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    let mut image = repository::get_image_by_id(&mut conn, &input.id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| format!("Image not found: {}", input.id))?;
-
+/// FITS file to process for an image: its fits_url (discovered from a
+/// companion file next to url and saved when missing), or url itself if
+/// that is a FITS file
+fn resolve_fits_path(
+    conn: &mut diesel::SqliteConnection,
+    image: &mut Image,
+) -> Result<String, String> {
     // Lazy populate fits_url if missing
     if image.fits_url.is_none() {
         if let Some(url) = &image.url {
@@ -166,10 +218,10 @@ pub async fn process_fits_image(
                     fits_url: Some(fits_path.clone()),
                     ..Default::default()
                 };
-                if let Err(e) = repository::update_image(&mut conn, &input.id, &update) {
-                    log::warn!("Failed to update fits_url for image {}: {}", input.id, e);
+                if let Err(e) = repository::update_image(conn, &image.id, &update) {
+                    log::warn!("Failed to update fits_url for image {}: {}", image.id, e);
                 } else {
-                    log::info!("Lazily populated fits_url for image {}: {}", input.id, fits_path);
+                    log::info!("Lazily populated fits_url for image {}: {}", image.id, fits_path);
                     image.fits_url = Some(fits_path);
                 }
             }
@@ -196,34 +248,89 @@ pub async fn process_fits_image(
         return Err(format!("FITS file not found: {}", file_path));
     }
 
-    // Determine output directory (create 'processed' subdirectory alongside original)
-    let output_dir = path
-        .parent()
-        .unwrap_or(Path::new("."))
-        .join("processed")
-        .to_string_lossy()
-        .to_string();
+    Ok(file_path)
+}
 
-    // Get object name from existing metadata for auto-classification
-    let object_name: Option<String> = image
+/// Object name for auto-classification: from the image metadata, falling
+/// back to its summary
+fn image_object_name(image: &Image) -> Option<String> {
+    image
         .metadata
         .as_ref()
         .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
-        .and_then(|v| v.get("object_name").and_then(|n| n.as_str().map(String::from)));
+        .and_then(|v| v.get("object_name").and_then(|n| n.as_str().map(String::from)))
+        .or_else(|| image.summary.clone())
+}
 
-    // Also check summary/filename for object name
-    let object_name = object_name.or_else(|| image.summary.clone());
+/// Process a FITS image with stretch and enhancements
+#[tauri::command]
+pub async fn process_fits_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    window: Window,
+    input: ProcessImageInput,
+) -> Result<ProcessImageResponse, String> {
+    process_image_record(&app, &state, &window, input)
+        .await
+        .map(|(response, _)| response)
+}
 
-    // Build processing parameters
+/// Process an image and import the result into the "Processed" collection.
+/// Also returns the ID of the imported processed image, if it was created.
+async fn process_image_record(
+    app: &AppHandle,
+    state: &AppState,
+    window: &Window,
+    input: ProcessImageInput,
+) -> Result<(ProcessImageResponse, Option<String>), String> {
+    // Get the image from the database
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut image = repository::get_image_by_id(&mut conn, &input.id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", input.id))?;
+
+    let file_path = resolve_fits_path(&mut conn, &mut image)?;
+    let object_name = image_object_name(&image);
+
+    // Determine output directory: the managed output root when configured,
+    // otherwise a 'processed' subdirectory alongside the original
+    let settings: ProcessingSettings = load_settings(app, &PROCESSING)?;
+    let output_dir = processing_output_dir(
+        settings.output_root.as_deref().map(Path::new),
+        Path::new(&file_path),
+        object_name.as_deref(),
+        image.date_obs.as_deref(),
+    )
+    .to_string_lossy()
+    .to_string();
+
+    // Build processing parameters: explicit inputs win over the preset
+    let preset = match &input.preset_id {
+        Some(preset_id) => Some(
+            repository::get_processing_preset_by_id(&mut conn, preset_id)
+                .map_err(|e| e.to_string())?
+                .filter(|p| p.user_id == state.user_id)
+                .ok_or_else(|| format!("Processing preset not found: {}", preset_id))?,
+        ),
+        None => repository::get_default_processing_preset(
+            &mut conn,
+            &state.user_id,
+            input.target_type.as_deref(),
+        )
+        .map_err(|e| e.to_string())?,
+    };
+    let base = preset.map(|p| preset_params(&p)).unwrap_or_default();
     let params = ProcessingParams {
-        target_type: input.target_type.unwrap_or_else(|| "auto".to_string()),
-        stretch_method: input.stretch_method.unwrap_or_else(|| "statistical".to_string()),
-        stretch_factor: input.stretch_factor.unwrap_or(0.15),
-        background_removal: input.background_removal.unwrap_or(true),
-        star_reduction: input.star_reduction.unwrap_or(false),
-        color_calibration: input.color_calibration.unwrap_or(true),
-        noise_reduction: input.noise_reduction.unwrap_or(0.0),
-        contrast: input.contrast.unwrap_or(1.3),
+        target_type: input.target_type.unwrap_or(base.target_type),
+        stretch_method: input.stretch_method.unwrap_or(base.stretch_method),
+        stretch_factor: input.stretch_factor.unwrap_or(base.stretch_factor),
+        background_removal: input.background_removal.unwrap_or(base.background_removal),
+        star_reduction: input.star_reduction.unwrap_or(base.star_reduction),
+        color_calibration: input.color_calibration.unwrap_or(base.color_calibration),
+        noise_reduction: input.noise_reduction.unwrap_or(base.noise_reduction),
+        contrast: input.contrast.unwrap_or(base.contrast),
+        background_method: input.background_method.unwrap_or(base.background_method),
+        gradient_order: input.gradient_order.unwrap_or(base.gradient_order),
     };
 
     // Create progress channel
@@ -247,17 +354,42 @@ pub async fn process_fits_image(
     });
 
     // Process the image with progress reporting
-    let result = image_process::process_image_with_progress(
-        &file_path,
-        &output_dir,
-        &params,
-        object_name.as_deref(),
-        progress_tx,
-    )?;
+    let result = if python_unavailable() {
+        log::info!("astra_astro not loaded, processing {} natively", file_path);
+        process_image_natively(&file_path, &output_dir, &params, progress_tx)?
+    } else {
+        image_process::process_image_with_progress(
+            &file_path,
+            &output_dir,
+            &params,
+            object_name.as_deref(),
+            progress_tx,
+        )?
+    };
+
+    // Derivatives are written from the processed FITS, whichever processor
+    // produced it
+    let output_formats = input.output_formats.unwrap_or(settings.output_formats);
+    let derivatives = if result.success {
+        write_derivatives(
+            Path::new(&result.output_fits_path),
+            &output_formats,
+            settings.jpeg_quality.unwrap_or(DEFAULT_JPEG_QUALITY),
+        )
+    } else {
+        Vec::new()
+    };
+    let derivative_path = |format: OutputFormat| {
+        derivatives
+            .iter()
+            .find(|(f, _)| *f == format)
+            .map(|(_, path)| path.clone())
+    };
 
     // Update image metadata and import processed image
+    let mut processed_image_id = None;
     if result.success {
-        let processing_metadata = serde_json::json!({
+        let mut processing_metadata = serde_json::json!({
             "processing": {
                 "processed_at": chrono::Utc::now().to_rfc3339(),
                 "target_type": result.target_type,
@@ -271,6 +403,9 @@ pub async fn process_fits_image(
                 "processing_time": result.processing_time,
             }
         });
+        for (format, path) in &derivatives {
+            processing_metadata["processing"][format.metadata_key()] = path.clone().into();
+        }
 
         // Merge with existing metadata
         let new_metadata = if let Some(existing) = &image.metadata {
@@ -329,9 +464,15 @@ pub async fn process_fits_image(
                     .or_else(|| Some(format!("{} (Processed)", filename.replace("_processed.fits", ""))));
 
                 // Build metadata for processed image
+                // Output files are tracked so deleting the record cleans them up
+                let output_files = [&result.output_fits_path, &result.output_preview_path]
+                    .into_iter()
+                    .chain(derivatives.iter().map(|(_, path)| path))
+                    .collect::<Vec<_>>();
                 let processed_metadata = serde_json::json!({
                     "source_image_id": image.id,
                     "processing": processing_metadata["processing"],
+                    "output_files": output_files,
                 });
 
                 // Create new image entry for the processed file
@@ -359,6 +500,15 @@ pub async fn process_fits_image(
                     thumbnail,
                     fits_url: Some(result.output_fits_path.clone()),
                     blob_id: None,
+                    // Same capture as the source, so keep its acquisition values
+                    exposure: image.exposure,
+                    gain: image.gain,
+                    filter: image.filter.clone(),
+                    telescope: image.telescope.clone(),
+                    date_obs: image.date_obs.clone(),
+                    wcs: None,
+                    parent_image_id: None,
+                    triage: None,
                 };
 
                 match repository::create_image(&mut conn, &new_image) {
@@ -376,6 +526,7 @@ pub async fn process_fits_image(
                             "Imported processed image {} into 'Processed' collection",
                             created_image.id
                         );
+                        processed_image_id = Some(created_image.id);
                     }
                     Err(e) => {
                         log::error!("Failed to import processed image: {}", e);
@@ -388,21 +539,587 @@ pub async fn process_fits_image(
         }
     }
 
-    Ok(ProcessImageResponse { result })
+    let response = ProcessImageResponse {
+        output_tiff_path: derivative_path(OutputFormat::Tiff),
+        output_jpeg_path: derivative_path(OutputFormat::Jpeg),
+        result,
+    };
+    Ok((response, processed_image_id))
+}
+
+/// Write the requested derivatives of a processed FITS next to it, named
+/// after it. A derivative that fails is logged and left out.
+fn write_derivatives(
+    processed_fits: &Path,
+    formats: &[OutputFormat],
+    jpeg_quality: u8,
+) -> Vec<(OutputFormat, String)> {
+    if formats.is_empty() {
+        return Vec::new();
+    }
+    let stretched = match StretchedImage::read_fits(processed_fits) {
+        Ok(stretched) => stretched,
+        Err(e) => {
+            log::warn!("Failed to read {:?} for output formats: {}", processed_fits, e);
+            return Vec::new();
+        }
+    };
+
+    let mut written: Vec<(OutputFormat, String)> = Vec::new();
+    for &format in formats {
+        if written.iter().any(|(f, _)| *f == format) {
+            continue;
+        }
+        let path = processed_fits.with_extension(format.extension());
+        let result = match format {
+            OutputFormat::Tiff => stretched.write_tiff16(&path),
+            OutputFormat::Jpeg => stretched.write_jpeg(&path, jpeg_quality),
+        };
+        match result {
+            Ok(()) => written.push((format, path.to_string_lossy().to_string())),
+            Err(e) => log::warn!("Failed to write {:?}: {}", path, e),
+        }
+    }
+    written
+}
+
+// ============================================================================
+// Reprocessing
+// ============================================================================
+
+/// Input for reprocessing an image
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReprocessImageInput {
+    /// Processing options; `id` may be the source image or any processed
+    /// version of it
+    #[serde(flatten)]
+    pub process: ProcessImageInput,
+    /// Move the superseded version's output files into an `archive/` folder
+    #[serde(default)]
+    pub archive_previous: bool,
+}
+
+/// Reprocess an image, superseding its current processed version.
+///
+/// The new processed image records its source as `parent_image_id` and the
+/// version it replaces as `supersedes`; the replaced version gets
+/// `superseded_by`. Only the newest version is flagged `current`.
+#[tauri::command]
+pub async fn reprocess_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    window: Window,
+    input: ReprocessImageInput,
+) -> Result<ProcessImageResponse, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, &input.process.id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", input.process.id))?;
+
+    let source_id = metadata_value(image.metadata.as_deref(), "source_image_id")
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or(image.id);
+    let versions = repository::get_processed_versions(&mut conn, &source_id)
+        .map_err(|e| e.to_string())?;
+    // Versions are newest first; older images without the flag count as current
+    let previous_id = versions
+        .iter()
+        .find(|v| {
+            metadata_value(v.metadata.as_deref(), "current")
+                .and_then(|c| c.as_bool())
+                .unwrap_or(true)
+        })
+        .map(|v| v.id.clone());
+    drop(conn);
+
+    let mut process = input.process;
+    process.id = source_id.clone();
+    let (response, new_id) = process_image_record(&app, &state, &window, process).await?;
+    let Some(new_id) = new_id else {
+        return Ok(response);
+    };
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    for version in &versions {
+        let is_previous = previous_id.as_deref() == Some(version.id.as_str());
+        let mut update = UpdateImage {
+            metadata: edit_metadata(version.metadata.as_deref(), |obj| {
+                obj.insert("current".to_string(), false.into());
+                if is_previous {
+                    obj.insert("superseded_by".to_string(), new_id.clone().into());
+                }
+            }),
+            ..Default::default()
+        };
+        if is_previous && input.archive_previous {
+            archive_version_outputs(version, &mut update);
+        }
+        if let Err(e) = repository::update_image(&mut conn, &version.id, &update) {
+            log::error!("Failed to mark processed image {} as superseded: {}", version.id, e);
+        }
+    }
+
+    let new_image = repository::get_image_by_id(&mut conn, &new_id).map_err(|e| e.to_string())?;
+    if let Some(new_image) = new_image {
+        let update = UpdateImage {
+            metadata: edit_metadata(new_image.metadata.as_deref(), |obj| {
+                obj.insert("parent_image_id".to_string(), source_id.clone().into());
+                obj.insert("supersedes".to_string(), previous_id.clone().into());
+                obj.insert("current".to_string(), true.into());
+            }),
+            ..Default::default()
+        };
+        repository::update_image(&mut conn, &new_id, &update).map_err(|e| e.to_string())?;
+    }
+
+    Ok(response)
+}
+
+/// Top-level value from an image's metadata JSON
+fn metadata_value(metadata: Option<&str>, key: &str) -> Option<serde_json::Value> {
+    serde_json::from_str::<serde_json::Value>(metadata?)
+        .ok()?
+        .get(key)
+        .cloned()
 }
 
-/// Get target type classification for an object
+/// Apply `edit` to an image's metadata object (starting a new object if
+/// there is none or it isn't valid JSON) and serialize it back
+fn edit_metadata(
+    metadata: Option<&str>,
+    edit: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>),
+) -> Option<String> {
+    let mut obj = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default();
+    edit(&mut obj);
+    serde_json::to_string(&obj).ok()
+}
+
+/// Move a processed version's output files into an `archive/` folder next
+/// to them, updating its paths in `update` for the files that moved
+fn archive_version_outputs(version: &Image, update: &mut UpdateImage) {
+    let mut moved = Vec::new();
+    for file in processed_output_files(version.metadata.as_deref()) {
+        let (Some(dir), Some(name)) = (file.parent(), file.file_name()) else {
+            continue;
+        };
+        let archived = dir.join("archive").join(name);
+        let result = std::fs::create_dir_all(dir.join("archive"))
+            .and_then(|_| std::fs::rename(&file, &archived));
+        match result {
+            Ok(()) => moved.push((file.to_string_lossy().to_string(), archived)),
+            Err(e) => log::warn!("Failed to archive processed output {:?}: {}", file, e),
+        }
+    }
+    if moved.is_empty() {
+        return;
+    }
+
+    let archived_path = |path: &str| {
+        moved
+            .iter()
+            .find(|(old, _)| old == path)
+            .map(|(_, new)| new.to_string_lossy().to_string())
+    };
+    update.url = version.url.as_deref().and_then(archived_path);
+    update.fits_url = version.fits_url.as_deref().and_then(archived_path);
+    update.metadata = edit_metadata(update.metadata.as_deref(), |obj| {
+        if let Some(files) = obj.get_mut("output_files").and_then(|f| f.as_array_mut()) {
+            for file in files.iter_mut() {
+                if let Some(new) = file.as_str().and_then(archived_path) {
+                    *file = new.into();
+                }
+            }
+        }
+        if let Some(processing) = obj.get_mut("processing").and_then(|p| p.as_object_mut()) {
+            for key in ["output_fits", "output_preview", "output_tiff", "output_jpeg"] {
+                let new = processing.get(key).and_then(|v| v.as_str()).and_then(archived_path);
+                if let Some(new) = new {
+                    processing.insert(key.to_string(), new.into());
+                }
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Output directories
+// ============================================================================
+
+/// Where processed outputs are written
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingSettings {
+    /// Root directory for processed outputs (None = a `processed/` folder
+    /// next to each original)
+    pub output_root: Option<String>,
+    /// Formats written alongside the processed FITS and PNG preview
+    #[serde(default)]
+    pub output_formats: Vec<OutputFormat>,
+    /// Quality of JPEG outputs, 1-100 (None = 92)
+    #[serde(default)]
+    pub jpeg_quality: Option<u8>,
+}
+
+/// Output directory for a processed image.
+///
+/// With an output root this is `<root>/<target>/<date>`, using the capture
+/// date when known. Without one, outputs go next to the original.
+fn processing_output_dir(
+    output_root: Option<&Path>,
+    source: &Path,
+    object_name: Option<&str>,
+    date_obs: Option<&str>,
+) -> PathBuf {
+    let Some(root) = output_root else {
+        return source.parent().unwrap_or(Path::new(".")).join("processed");
+    };
+
+    let target = object_name
+        .map(output_folder_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Unknown".to_string());
+    let date = date_obs
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d.get(..10)?, "%Y-%m-%d").ok())
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    root.join(target).join(date.format("%Y-%m-%d").to_string())
+}
+
+/// Target name made safe for use as a single path component
+fn output_folder_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || " -_+.".contains(c) { c } else { '_' })
+        .collect::<String>()
+        .trim_matches(|c: char| c == ' ' || c == '.')
+        .to_string()
+}
+
+/// Output files recorded on a processed image, removed with its record
+pub(crate) fn processed_output_files(metadata: Option<&str>) -> Vec<PathBuf> {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|v| {
+            v.get("output_files")?.as_array().map(|files| {
+                files.iter().filter_map(|f| f.as_str()).map(PathBuf::from).collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Get the processing output settings
+#[tauri::command]
+pub fn get_processing_settings(app: AppHandle) -> Result<ProcessingSettings, String> {
+    load_settings(&app, &PROCESSING)
+}
+
+/// Set (or clear) the root directory processed outputs are written under
 #[tauri::command]
-pub fn classify_target_type(object_name: String) -> Result<TargetInfo, String> {
-    image_process::classify_target(&object_name)
+pub fn set_processing_output_root(
+    app: AppHandle,
+    output_root: Option<String>,
+) -> Result<ProcessingSettings, String> {
+    let output_root = output_root.filter(|p| !p.trim().is_empty());
+    if let Some(p) = &output_root {
+        if !Path::new(p).is_dir() {
+            return Err(format!("Directory not found: {}", p));
+        }
+    }
+
+    let settings = ProcessingSettings {
+        output_root,
+        ..load_settings(&app, &PROCESSING)?
+    };
+    save_settings(&app, &PROCESSING, &settings)?;
+    Ok(settings)
 }
 
-/// Get default processing parameters for a target type
+/// Set the formats written alongside each processed image, and the JPEG
+/// quality (None = the default)
 #[tauri::command]
-pub fn get_processing_defaults(target_type: String) -> Result<ProcessingParams, String> {
+pub fn set_processing_output_formats(
+    app: AppHandle,
+    output_formats: Vec<OutputFormat>,
+    jpeg_quality: Option<u8>,
+) -> Result<ProcessingSettings, String> {
+    if let Some(quality) = jpeg_quality {
+        if !(1..=100).contains(&quality) {
+            return Err(format!("JPEG quality must be 1-100, got {}", quality));
+        }
+    }
+
+    let settings = ProcessingSettings {
+        output_formats,
+        jpeg_quality,
+        ..load_settings(&app, &PROCESSING)?
+    };
+    save_settings(&app, &PROCESSING, &settings)?;
+    Ok(settings)
+}
+
+/// Process a downsampled preview of an image with the given parameters.
+///
+/// Nothing is written to disk or the database, so the UI can call this
+/// repeatedly while tuning parameters before running process_fits_image.
+#[tauri::command]
+pub async fn preview_processing(
+    state: State<'_, AppState>,
+    image_id: String,
+    params: ProcessingParams,
+    max_size: Option<u32>,
+) -> Result<PreviewResult, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut image = repository::get_image_by_id(&mut conn, &image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", image_id))?;
+    let file_path = resolve_fits_path(&mut conn, &mut image)?;
+    let object_name = image_object_name(&image);
+    drop(conn);
+
+    let result = tokio::task::spawn_blocking(move || {
+        if python_unavailable() {
+            preview_image_natively(&file_path, &params, max_size)
+        } else {
+            image_process::preview_image(&file_path, &params, object_name.as_deref(), max_size)
+        }
+    })
+    .await
+    .map_err(|e| format!("Preview processing failed: {}", e))??;
+
+    if !result.success {
+        let error = result.error_message.unwrap_or_else(|| "Unknown error".to_string());
+        return Err(format!("Preview processing failed: {}", error));
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// Native fallback (when astra_astro is unavailable)
+// ============================================================================
+
+fn python_unavailable() -> bool {
+    !crate::python::python_status().module_loaded
+}
+
+/// Approximate processing parameters with the native stretch pipeline.
+///
+/// The stretch factor becomes the target background level, background
+/// removal always uses the polynomial gradient fit and the log stretch uses
+/// the arcsinh curve. Star reduction, noise reduction and contrast need
+/// Python and are skipped.
+fn native_stretch_params(params: &ProcessingParams) -> StretchParams {
+    StretchParams {
+        bg_percent: params.stretch_factor,
+        gradient_removal: params.background_removal,
+        gradient_order: params.gradient_order.clamp(1, 4) as usize,
+        method: match params.stretch_method.as_str() {
+            "arcsinh" | "log" => StretchMethod::Arcsinh,
+            _ => StretchMethod::Mtf,
+        },
+        ..Default::default()
+    }
+}
+
+/// Target type reported by native processing; classification needs Python
+fn native_target_type(params: &ProcessingParams) -> String {
+    match params.target_type.as_str() {
+        "auto" => "unknown".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Process an image with the native stretch pipeline, writing the same
+/// outputs as the Python processor (processed FITS plus PNG preview)
+fn process_image_natively(
+    input_fits_path: &str,
+    output_dir: &str,
+    params: &ProcessingParams,
+    progress_tx: ProgressSender,
+) -> Result<ProcessingResult, String> {
+    let start = std::time::Instant::now();
+    let report = |step: &str, progress: f64, message: &str| {
+        let _ = progress_tx.send(ProcessingProgress {
+            step: step.to_string(),
+            progress,
+            message: message.to_string(),
+        });
+    };
+
+    report("init", 0.0, "Initializing native processing");
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    // Same naming as the Python processor
+    let base_name = Path::new(input_fits_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("image");
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let output_fits_path =
+        Path::new(output_dir).join(format!("{}_processed_{}.fits", base_name, timestamp));
+    let output_preview_path =
+        Path::new(output_dir).join(format!("{}_preview_{}.png", base_name, timestamp));
+
+    report("stretch", 0.1, "Stretching image");
+    let stretched =
+        crate::stretch::stretch_fits(Path::new(input_fits_path), &native_stretch_params(params))?;
+
+    report("saving", 0.9, "Saving processed FITS");
+    stretched.write_fits(&output_fits_path)?;
+
+    report("saving", 0.95, "Generating preview image");
+    stretched
+        .to_rgb_image()?
+        .save(&output_preview_path)
+        .map_err(|e| format!("Failed to save preview: {}", e))?;
+    report("complete", 1.0, "Processing complete");
+
+    Ok(ProcessingResult {
+        success: true,
+        output_fits_path: output_fits_path.to_string_lossy().to_string(),
+        output_preview_path: output_preview_path.to_string_lossy().to_string(),
+        target_type: native_target_type(params),
+        processing_params: serde_json::to_value(params).unwrap_or_default(),
+        processing_time: start.elapsed().as_secs_f64(),
+        error_message: None,
+    })
+}
+
+/// Preview processing with the native stretch pipeline
+fn preview_image_natively(
+    input_fits_path: &str,
+    params: &ProcessingParams,
+    max_size: Option<u32>,
+) -> Result<PreviewResult, String> {
+    let start = std::time::Instant::now();
+    let stretched =
+        crate::stretch::stretch_fits(Path::new(input_fits_path), &native_stretch_params(params))?;
+
+    let max_size = max_size.unwrap_or(1024);
+    let mut preview = image::DynamicImage::ImageRgb8(stretched.to_rgb_image()?);
+    if preview.width() > max_size || preview.height() > max_size {
+        preview = preview.resize(max_size, max_size, FilterType::Triangle);
+    }
+
+    let mut buffer = Cursor::new(Vec::new());
+    preview
+        .write_to(&mut buffer, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode preview: {}", e))?;
+
+    Ok(PreviewResult {
+        success: true,
+        preview_data: format!(
+            "data:image/png;base64,{}",
+            BASE64_STANDARD.encode(buffer.into_inner())
+        ),
+        width: preview.width(),
+        height: preview.height(),
+        target_type: native_target_type(params),
+        processing_params: serde_json::to_value(params).unwrap_or_default(),
+        processing_time: start.elapsed().as_secs_f64(),
+        error_message: None,
+    })
+}
+
+/// Get target type classification for an object.
+///
+/// Classifications are cached on the object. Uncached names are classified
+/// locally and only go to SIMBAD (through Python) when the local result isn't
+/// confident.
+#[tauri::command]
+pub fn classify_target_type(
+    state: State<'_, AppState>,
+    object_name: String,
+) -> Result<TargetInfo, String> {
+    let mut conn = state.db.get()?;
+    let canonical = target_types::canonical_name(&mut conn, &object_name)
+        .map_err(|e| e.to_string())?;
+    if let Some(cached) = target_types::cached_target_type(&mut conn, &object_name, &canonical)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(cached);
+    }
+
+    let local = target_types::local_target_type(&object_name, &canonical);
+    let info = match local {
+        Some(local) if local.confidence >= target_types::CONFIDENT => local,
+        local => {
+            // Don't hold a connection through the network round trip
+            drop(conn);
+            let remote = image_process::classify_target(&object_name);
+            conn = state.db.get()?;
+            match (remote, local) {
+                (Ok(remote), Some(local)) if local.confidence > remote.confidence => local,
+                (Ok(remote), _) => remote,
+                (Err(e), Some(local)) => {
+                    log::warn!("SIMBAD classification of {} failed: {}", object_name, e);
+                    local
+                }
+                (Err(e), None) => return Err(e),
+            }
+        }
+    };
+
+    if info.confidence > 0.0 {
+        if let Err(e) = target_types::cache_target_type(&mut conn, &canonical, &info) {
+            log::warn!("Failed to cache target type of {}: {}", canonical, e);
+        }
+    }
+    Ok(info)
+}
+
+/// Get default processing parameters for a target type.
+///
+/// If `image_id` is given and the image's equipment matches a profile, the
+/// profile's processing overrides are applied on top of the target defaults.
+#[tauri::command]
+pub fn get_processing_defaults(
+    state: State<'_, AppState>,
+    target_type: String,
+    image_id: Option<String>,
+) -> Result<ProcessingParams, String> {
+    let mut params = target_processing_defaults(&target_type);
+
+    if let Some(image_id) = image_id {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        let profile = repository::find_profile_for_image(&mut conn, &state.user_id, &image_id)
+            .map_err(|e| e.to_string())?;
+        if let Some(overrides) = profile.and_then(|p| p.processing_params) {
+            params = apply_processing_overrides(params, &overrides);
+        }
+    }
+
+    Ok(params)
+}
+
+/// Merge a JSON object of ProcessingParams overrides onto a base set of params.
+/// Invalid or unknown overrides are ignored.
+fn apply_processing_overrides(base: ProcessingParams, overrides: &str) -> ProcessingParams {
+    let (Ok(mut merged), Ok(serde_json::Value::Object(overrides))) = (
+        serde_json::to_value(&base),
+        serde_json::from_str::<serde_json::Value>(overrides),
+    ) else {
+        return base;
+    };
+
+    if let Some(obj) = merged.as_object_mut() {
+        for (key, value) in overrides {
+            if obj.contains_key(&key) {
+                obj.insert(key, value);
+            }
+        }
+    }
+
+    serde_json::from_value(merged).unwrap_or(base)
+}
+
+fn target_processing_defaults(target_type: &str) -> ProcessingParams {
     let mut params = ProcessingParams::default();
 
-    match target_type.as_str() {
+    match target_type {
         "emission_nebula" => {
             params.stretch_factor = 0.18;
             params.star_reduction = true;
@@ -431,8 +1148,112 @@ pub fn get_processing_defaults(target_type: String) -> Result<ProcessingParams,
         }
     }
 
-    params.target_type = target_type;
-    Ok(params)
+    params.target_type = target_type.to_string();
+    params
+}
+
+// ============================================================================
+// Processing presets
+// ============================================================================
+
+/// Full processing parameters for a preset: its stored params over the
+/// built-in defaults, with the preset's target type unless the params set one
+fn preset_params(preset: &ProcessingPreset) -> ProcessingParams {
+    let mut base = ProcessingParams::default();
+    if let Some(target_type) = &preset.target_type {
+        base.target_type = target_type.clone();
+    }
+    apply_processing_overrides(base, &preset.params)
+}
+
+fn preset_params_to_string(value: serde_json::Value) -> Result<String, String> {
+    if value.is_object() {
+        Ok(value.to_string())
+    } else {
+        Err("params must be a JSON object".to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProcessingPresetInput {
+    pub name: String,
+    /// Target type the preset is meant for (None = any target)
+    pub target_type: Option<String>,
+    /// ProcessingParams (camelCase keys); missing keys use the built-in defaults
+    pub params: serde_json::Value,
+    /// Make this the default preset for its target type
+    pub is_default: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProcessingPresetInput {
+    pub id: String,
+    pub name: Option<String>,
+    pub target_type: Option<String>,
+    pub params: Option<serde_json::Value>,
+}
+
+#[tauri::command]
+pub fn get_processing_presets(state: State<'_, AppState>) -> Result<Vec<ProcessingPreset>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_processing_presets(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_processing_preset(
+    state: State<'_, AppState>,
+    input: CreateProcessingPresetInput,
+) -> Result<ProcessingPreset, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let new_preset = NewProcessingPreset {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        name: input.name,
+        target_type: input.target_type,
+        params: preset_params_to_string(input.params)?,
+        is_default: input.is_default.unwrap_or(false),
+    };
+
+    repository::create_processing_preset(&mut conn, &new_preset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_processing_preset(
+    state: State<'_, AppState>,
+    input: UpdateProcessingPresetInput,
+) -> Result<ProcessingPreset, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let update = UpdateProcessingPreset {
+        name: input.name,
+        target_type: input.target_type,
+        params: input.params.map(preset_params_to_string).transpose()?,
+    };
+
+    repository::update_processing_preset(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_processing_preset(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_processing_preset(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Make a preset the default for its target type, or unset it
+#[tauri::command]
+pub fn set_default_processing_preset(
+    state: State<'_, AppState>,
+    id: String,
+    is_default: bool,
+) -> Result<ProcessingPreset, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::set_default_processing_preset(&mut conn, &id, is_default)
+        .map_err(|e| e.to_string())
 }
 
 /// Regenerate preview JPEG and thumbnail for a FITS image
@@ -494,6 +1315,7 @@ pub async fn regenerate_preview(
                 sigma: sigma.unwrap_or(3.0),
                 gradient_removal: true,
                 autocrop: true,
+                ..Default::default()
             };
             let result = crate::stretch::generate_preview(
                 std::path::Path::new(&fits),
@@ -619,6 +1441,7 @@ pub async fn bulk_regenerate_previews(
                         sigma: sig,
                         gradient_removal: true,
                         autocrop: true,
+                        ..Default::default()
                     };
                     let r = crate::stretch::generate_preview(
                         std::path::Path::new(&fits),
@@ -684,3 +1507,171 @@ pub async fn bulk_regenerate_previews(
         "failed": failed,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::settings::load_settings_in;
+
+    fn make_preset(target_type: Option<&str>, params: &str) -> ProcessingPreset {
+        ProcessingPreset {
+            id: "preset".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Preset".to_string(),
+            target_type: target_type.map(String::from),
+            params: params.to_string(),
+            is_default: false,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn preset_params_fill_in_defaults() {
+        let params = preset_params(&make_preset(Some("galaxy"), r#"{"contrast": 1.8, "bogus": 1}"#));
+        assert_eq!(params.target_type, "galaxy");
+        assert_eq!(params.contrast, 1.8);
+        assert_eq!(params.stretch_factor, ProcessingParams::default().stretch_factor);
+
+        // Params naming a target type win over the preset's
+        let params = preset_params(&make_preset(Some("galaxy"), r#"{"targetType": "auto"}"#));
+        assert_eq!(params.target_type, "auto");
+    }
+
+    #[test]
+    fn native_stretch_params_follow_processing_params() {
+        let params = ProcessingParams {
+            stretch_method: "log".to_string(),
+            stretch_factor: 0.2,
+            background_removal: false,
+            ..Default::default()
+        };
+        let native = native_stretch_params(&params);
+        assert_eq!(native.method, StretchMethod::Arcsinh);
+        assert_eq!(native.gradient_order, 2);
+        assert_eq!(native.bg_percent, 0.2);
+        assert!(!native.gradient_removal);
+
+        assert_eq!(native_stretch_params(&ProcessingParams::default()).method, StretchMethod::Mtf);
+        assert_eq!(native_target_type(&ProcessingParams::default()), "unknown");
+    }
+
+    #[test]
+    fn preset_params_must_be_object() {
+        assert!(preset_params_to_string(serde_json::json!({"contrast": 1.5})).is_ok());
+        assert!(preset_params_to_string(serde_json::json!("fast")).is_err());
+    }
+
+    #[test]
+    fn output_dir_uses_target_and_capture_date_under_root() {
+        let source = Path::new("/archive/2024/M42/light_001.fits");
+        assert_eq!(
+            processing_output_dir(None, source, Some("M 42"), None),
+            PathBuf::from("/archive/2024/M42/processed")
+        );
+        assert_eq!(
+            processing_output_dir(
+                Some(Path::new("/astro/out")),
+                source,
+                Some("M 42/Orion"),
+                Some("2024-01-15T22:10:05"),
+            ),
+            PathBuf::from("/astro/out/M 42_Orion/2024-01-15")
+        );
+        let unknown =
+            processing_output_dir(Some(Path::new("/astro/out")), source, Some(".."), None);
+        assert!(unknown.starts_with("/astro/out/Unknown"));
+    }
+
+    #[test]
+    fn settings_without_output_formats_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROCESSING.name);
+        std::fs::write(&path, r#"{"outputRoot":"/astro/out"}"#).unwrap();
+        let settings: ProcessingSettings = load_settings_in(dir.path(), &PROCESSING);
+        assert_eq!(settings.output_root.as_deref(), Some("/astro/out"));
+        assert!(settings.output_formats.is_empty());
+
+        let settings: ProcessingSettings =
+            serde_json::from_str(r#"{"outputRoot":null,"outputFormats":["tiff","jpeg"]}"#)
+                .unwrap();
+        assert_eq!(
+            settings.output_formats,
+            vec![OutputFormat::Tiff, OutputFormat::Jpeg]
+        );
+    }
+
+    #[test]
+    fn output_files_read_from_metadata() {
+        let metadata = r#"{"source_image_id":"a","output_files":["/o/x.fits","/o/x.png"]}"#;
+        assert_eq!(
+            processed_output_files(Some(metadata)),
+            vec![PathBuf::from("/o/x.fits"), PathBuf::from("/o/x.png")]
+        );
+        assert!(processed_output_files(Some(r#"{"processing":{}}"#)).is_empty());
+        assert!(processed_output_files(None).is_empty());
+    }
+
+    #[test]
+    fn edit_metadata_keeps_existing_keys() {
+        let metadata = edit_metadata(Some(r#"{"source_image_id":"src"}"#), |obj| {
+            obj.insert("current".to_string(), false.into());
+        });
+        assert_eq!(
+            metadata_value(metadata.as_deref(), "source_image_id"),
+            Some(serde_json::json!("src"))
+        );
+        assert_eq!(metadata_value(metadata.as_deref(), "current"), Some(false.into()));
+
+        // Invalid metadata is replaced rather than failing the update
+        let metadata = edit_metadata(Some("not json"), |obj| {
+            obj.insert("current".to_string(), true.into());
+        });
+        assert_eq!(metadata.as_deref(), Some(r#"{"current":true}"#));
+    }
+
+    fn make_image(id: &str, metadata: &str) -> Image {
+        Image {
+            filename: format!("{}.png", id),
+            metadata: Some(metadata.to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn archive_moves_outputs_and_rewrites_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let fits = dir.path().join("m42_processed.fits");
+        let png = dir.path().join("m42_preview.png");
+        std::fs::write(&fits, b"fits").unwrap();
+        std::fs::write(&png, b"png").unwrap();
+        let (fits_str, png_str) = (fits.to_string_lossy(), png.to_string_lossy());
+
+        let metadata = serde_json::json!({
+            "output_files": [fits_str, png_str],
+            "processing": { "output_fits": fits_str, "output_preview": png_str },
+        });
+        let version = Image {
+            url: Some(png_str.to_string()),
+            fits_url: Some(fits_str.to_string()),
+            ..make_image("proc-1", &metadata.to_string())
+        };
+        let mut update = UpdateImage {
+            metadata: version.metadata.clone(),
+            ..Default::default()
+        };
+        archive_version_outputs(&version, &mut update);
+
+        let archived_fits = dir.path().join("archive/m42_processed.fits");
+        let archived_png = dir.path().join("archive/m42_preview.png");
+        assert!(archived_fits.exists() && archived_png.exists() && !fits.exists());
+        assert_eq!(update.fits_url.as_deref(), archived_fits.to_str());
+        assert_eq!(update.url.as_deref(), archived_png.to_str());
+        assert_eq!(
+            processed_output_files(update.metadata.as_deref()),
+            vec![archived_fits.clone(), archived_png]
+        );
+        let processing = metadata_value(update.metadata.as_deref(), "processing").unwrap();
+        assert_eq!(processing["output_fits"].as_str(), archived_fits.to_str());
+    }
+}
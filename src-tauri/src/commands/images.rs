@@ -1,14 +1,22 @@
 //! Image commands for managing astronomical images
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use diesel::sqlite::SqliteConnection;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
-use tauri::{Manager, State};
+use tauri::{AppHandle, Manager, State};
 
 use crate::db::models::{Collection, Image, NewCollectionImage, NewImage, UpdateImage};
 use crate::db::repository;
+use crate::preview_cache::PreviewCache;
 use crate::state::AppState;
+use crate::stretch::{stretch_fits, StretchParams};
+
+use super::image_process::processed_output_files;
+use super::notes::{delete_entity_notes, NoteEntity};
+use super::scan::AcquisitionColumns;
+use super::sidecars::update_sidecars;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateImageInput {
@@ -44,10 +52,25 @@ pub struct UpdateImageInput {
     pub thumbnail: Option<String>,
 }
 
+/// Leave out frames waiting in (or discarded by) import triage, and Light
+/// frames linked under a stacked image unless they're asked for
+pub(crate) fn listed_images(images: Vec<Image>, include_subframes: Option<bool>) -> Vec<Image> {
+    let include_subframes = include_subframes.unwrap_or(false);
+    images
+        .into_iter()
+        .filter(|image| image.triage.is_none())
+        .filter(|image| include_subframes || image.parent_image_id.is_none())
+        .collect()
+}
+
 #[tauri::command]
-pub fn get_images(state: State<'_, AppState>) -> Result<Vec<Image>, String> {
+pub fn get_images(
+    state: State<'_, AppState>,
+    include_subframes: Option<bool>,
+) -> Result<Vec<Image>, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
     repository::get_images_by_user(&mut conn, &state.user_id)
+        .map(|images| listed_images(images, include_subframes))
         .map_err(|e| e.to_string())
 }
 
@@ -55,11 +78,13 @@ pub fn get_images(state: State<'_, AppState>) -> Result<Vec<Image>, String> {
 pub fn get_collection_images(
     state: State<'_, AppState>,
     collection_id: String,
+    include_subframes: Option<bool>,
 ) -> Result<Vec<Image>, String> {
     log::info!("get_collection_images called with collection_id: {}", collection_id);
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    // Use the many-to-many join table to get images
-    let result = repository::get_images_in_collection(&mut conn, &collection_id);
+    // Use the many-to-many join table to get images, in the collection's order
+    let result = repository::get_sorted_collection_images(&mut conn, &collection_id)
+        .map(|images| listed_images(images, include_subframes));
     match &result {
         Ok(images) => log::info!("get_collection_images returning {} images", images.len()),
         Err(e) => log::error!("get_collection_images error: {}", e),
@@ -80,6 +105,7 @@ pub fn create_image(
     input: CreateImageInput,
 ) -> Result<Image, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let acquisition = AcquisitionColumns::from_metadata(input.metadata.as_deref());
 
     let new_image = NewImage {
         id: uuid::Uuid::new_v4().to_string(),
@@ -99,6 +125,14 @@ pub fn create_image(
         thumbnail: input.thumbnail,
         fits_url: None,
         blob_id: None,
+        exposure: acquisition.exposure,
+        gain: acquisition.gain,
+        filter: acquisition.filter,
+        telescope: acquisition.telescope,
+        date_obs: acquisition.date_obs,
+        wcs: None,
+        parent_image_id: None,
+        triage: None,
     };
 
     repository::create_image(&mut conn, &new_image)
@@ -107,10 +141,13 @@ pub fn create_image(
 
 #[tauri::command]
 pub fn update_image(
+    app: AppHandle,
     state: State<'_, AppState>,
     input: UpdateImageInput,
 ) -> Result<Image, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    // Unchanged metadata leaves the acquisition columns untouched
+    let acquisition = AcquisitionColumns::from_metadata(input.metadata.as_deref());
 
     let update = UpdateImage {
         collection_id: input.collection_id,
@@ -128,18 +165,73 @@ pub fn update_image(
         thumbnail: input.thumbnail,
         fits_url: None,
         blob_id: None,
+        exposure: acquisition.exposure,
+        gain: acquisition.gain,
+        filter: acquisition.filter,
+        telescope: acquisition.telescope,
+        date_obs: acquisition.date_obs,
+        wcs: None,
     };
 
-    repository::update_image(&mut conn, &input.id, &update)
-        .map_err(|e| e.to_string())
+    let image = repository::update_image(&mut conn, &input.id, &update)
+        .map_err(|e| e.to_string())?;
+    update_sidecars(&app, &image);
+    Ok(image)
 }
 
+/// Rate an image 1-5 stars, or clear its rating with no `rating`
 #[tauri::command]
-pub fn delete_image(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+pub fn set_image_rating(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    rating: Option<i32>,
+) -> Result<Image, String> {
+    if let Some(rating) = rating {
+        if !(1..=5).contains(&rating) {
+            return Err(format!("Rating must be 1-5 stars, got {}", rating));
+        }
+    }
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    repository::delete_image(&mut conn, &id)
+    repository::get_image_by_id(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", id))?;
+    let image = repository::set_image_rating(&mut conn, &id, rating).map_err(|e| e.to_string())?;
+    update_sidecars(&app, &image);
+    Ok(image)
+}
+
+#[tauri::command]
+pub fn delete_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let output_files = repository::get_image_by_id(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .map(|image| processed_output_files(image.metadata.as_deref()))
+        .unwrap_or_default();
+
+    let deleted = repository::delete_image(&mut conn, &id)
         .map(|count| count > 0)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    // Clean up files written by processing, and their folder once empty
+    if deleted {
+        for file in &output_files {
+            if let Err(e) = fs::remove_file(file) {
+                log::warn!("Failed to remove processed output {:?}: {}", file, e);
+            }
+            if let Some(dir) = file.parent() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+        super::annotate::remove_annotated_previews(&state.preview_cache, &id);
+        delete_entity_notes(&app, &mut conn, &state.user_id, NoteEntity::Image, &id);
+    }
+
+    Ok(deleted)
 }
 
 // ============================================================================
@@ -214,13 +306,161 @@ pub fn get_collection_image_count(
 // Image Data Serving Commands
 // ============================================================================
 
-/// Get the full image data as a base64 data URL
+/// JPEG quality used when `ImageDataOptions::quality` isn't given
+const DEFAULT_DISPLAY_QUALITY: u8 = 90;
+
+/// Conversion applied by `get_image_data`. Without options, the file (or
+/// its stored preview) is returned as is.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDataOptions {
+    /// "jpeg" (default) or "png"
+    pub format: Option<String>,
+    /// Auto-stretch FITS sources from their pixel data instead of using
+    /// the stored preview (default true)
+    pub stretch: Option<bool>,
+    /// Largest width or height; bigger images are scaled down
+    pub max_dimension: Option<u32>,
+    /// JPEG quality, 1-100
+    pub quality: Option<u8>,
+}
+
+/// Output format for converted image data
+fn display_format(options: &ImageDataOptions) -> Result<image::ImageFormat, String> {
+    match options.format.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("jpeg") | Some("jpg") => Ok(image::ImageFormat::Jpeg),
+        Some("png") => Ok(image::ImageFormat::Png),
+        Some(other) => Err(format!("Unsupported image format: {}", other)),
+    }
+}
+
+/// Scale an image to fit `max_dimension` and encode it
+fn encode_for_display(
+    img: image::DynamicImage,
+    options: &ImageDataOptions,
+) -> Result<Vec<u8>, String> {
+    let img = match options.max_dimension {
+        Some(max) if img.width() > max || img.height() > max => img.resize(
+            max.max(1),
+            max.max(1),
+            image::imageops::FilterType::Lanczos3,
+        ),
+        _ => img,
+    };
+
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    match display_format(options)? {
+        image::ImageFormat::Png => {
+            img.to_rgb8()
+                .write_to(&mut buffer, image::ImageFormat::Png)
+                .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+        }
+        _ => {
+            let quality = options
+                .quality
+                .unwrap_or(DEFAULT_DISPLAY_QUALITY)
+                .clamp(1, 100);
+            let rgb = img.to_rgb8();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality)
+                .encode(
+                    rgb.as_raw(),
+                    rgb.width(),
+                    rgb.height(),
+                    image::ExtendedColorType::Rgb8,
+                )
+                .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+        }
+    }
+    Ok(buffer.into_inner())
+}
+
+/// Data URL for image data encoded with `options`
+fn display_data_url(options: &ImageDataOptions, data: &[u8]) -> String {
+    let content_type = match display_format(options) {
+        Ok(image::ImageFormat::Png) => "image/png",
+        _ => "image/jpeg",
+    };
+    format!("data:{};base64,{}", content_type, BASE64.encode(data))
+}
+
+/// Convert `source` for display, reusing the cached result of an earlier
+/// conversion with the same options when there is one
+fn convert_for_display(
+    cache: Option<&PreviewCache>,
+    source: &Path,
+    options: &ImageDataOptions,
+    load: impl FnOnce() -> Result<image::DynamicImage, String>,
+) -> Result<String, String> {
+    let extension = match display_format(options)? {
+        image::ImageFormat::Png => "png",
+        _ => "jpg",
+    };
+    let params = format!(
+        "display:{}:{:?}:{:?}:{:?}",
+        extension, options.stretch, options.max_dimension, options.quality
+    );
+    let key = cache.and_then(|cache| {
+        cache
+            .key(source, &params, extension)
+            .map_err(|e| log::warn!("Preview cache unavailable: {}", e))
+            .ok()
+            .map(|key| (cache, key))
+    });
+    if let Some(data) = key.as_ref().and_then(|(cache, key)| cache.read(key)) {
+        return Ok(display_data_url(options, &data));
+    }
+
+    let data = encode_for_display(load()?, options)?;
+    if let Some((cache, key)) = &key {
+        if let Err(e) = cache.insert(key, &data) {
+            log::warn!("{}", e);
+        }
+    }
+    Ok(display_data_url(options, &data))
+}
+
+/// Stretched display image straight from a FITS file's pixel data
+fn stretch_for_display(path: &Path) -> Result<image::DynamicImage, String> {
+    // No autocrop, so the result keeps the file's pixel coordinates
+    // (annotations and WCS overlays line up)
+    let params = StretchParams {
+        autocrop: false,
+        ..Default::default()
+    };
+    let stretched = stretch_fits(path, &params)?;
+    Ok(image::DynamicImage::ImageRgb8(stretched.to_rgb_image()?))
+}
+
+/// Get the full image data as a base64 data URL. With `options`, the image
+/// is converted in the backend (FITS sources stretched, scaled down and
+/// re-encoded) so the viewer gets display-ready data for any file type.
 #[tauri::command]
-pub fn get_image_data(state: State<'_, AppState>, id: String) -> Result<String, String> {
-    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+pub async fn get_image_data(
+    state: State<'_, AppState>,
+    id: String,
+    options: Option<ImageDataOptions>,
+) -> Result<String, String> {
+    if let Some(options) = &options {
+        display_format(options)?;
+    }
+    let db = state.db.clone();
+    let cache = state.preview_cache.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        read_image_data(&mut conn, &id, options.as_ref(), Some(&cache))
+    })
+    .await
+    .map_err(|e| format!("Loading image data failed: {}", e))?
+}
 
+fn read_image_data(
+    conn: &mut SqliteConnection,
+    id: &str,
+    options: Option<&ImageDataOptions>,
+    cache: Option<&PreviewCache>,
+) -> Result<String, String> {
     // Get the image record
-    let image = repository::get_image_by_id(&mut conn, &id)
+    let image = repository::get_image_by_id(conn, id)
         .map_err(|e| e.to_string())?
         .ok_or_else(|| format!("Image not found: {}", id))?;
 
@@ -229,11 +469,26 @@ pub fn get_image_data(state: State<'_, AppState>, id: String) -> Result<String,
         .ok_or_else(|| "Image has no file path".to_string())?;
 
     let orig_path = Path::new(file_path);
+    let is_fits = orig_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| {
+            let l = e.to_lowercase();
+            l == "fit" || l == "fits"
+        })
+        .unwrap_or(false);
+
+    // Converting a FITS file: stretch its pixels rather than use the preview
+    if let Some(options) = options {
+        if is_fits && orig_path.exists() && options.stretch.unwrap_or(true) {
+            return convert_for_display(cache, orig_path, options, || {
+                stretch_for_display(orig_path)
+            });
+        }
+    }
 
     // If URL points to a FITS file or doesn't exist, look for a preview JPEG
-    let path = if !orig_path.exists() || orig_path.extension().and_then(|e| e.to_str())
-        .map(|e| { let l = e.to_lowercase(); l == "fit" || l == "fits" }).unwrap_or(false)
-    {
+    let path = if !orig_path.exists() || is_fits {
         // Check local previews dir first (survives unmounting)
         let local_preview = dirs::data_dir()
             .map(|d| d.join("com.erewhon.astra").join("previews").join(format!("{}.jpg", id)))
@@ -260,12 +515,30 @@ pub fn get_image_data(state: State<'_, AppState>, id: String) -> Result<String,
         // Last resort: return the embedded thumbnail if available
         if let Some(thumb) = &image.thumbnail {
             if !thumb.is_empty() {
-                return Ok(thumb.clone());
+                let Some(options) = options else {
+                    return Ok(thumb.clone());
+                };
+                let (_, data) = thumb
+                    .split_once(";base64,")
+                    .ok_or_else(|| "Stored thumbnail is not a data URL".to_string())?;
+                let data = BASE64
+                    .decode(data)
+                    .map_err(|e| format!("Failed to decode thumbnail: {}", e))?;
+                let img = image::load_from_memory(&data)
+                    .map_err(|e| format!("Failed to decode thumbnail: {}", e))?;
+                let data = encode_for_display(img, options)?;
+                return Ok(display_data_url(options, &data));
             }
         }
         return Err(format!("Image file not found: {}", path.display()));
     }
 
+    if let Some(options) = options {
+        return convert_for_display(cache, &path, options, || {
+            image::open(&path).map_err(|e| format!("Failed to decode {}: {}", path.display(), e))
+        });
+    }
+
     // Read the file
     let data = fs::read(&path)
         .map_err(|e| format!("Failed to read image file: {}", e))?;
@@ -305,7 +578,7 @@ pub fn get_image_thumbnail(state: State<'_, AppState>, id: String) -> Result<Str
     }
 
     // Otherwise, return full image data as fallback
-    get_image_data(state, id)
+    read_image_data(&mut conn, &id, None, None)
 }
 
 // ============================================================================
@@ -579,6 +852,26 @@ pub fn get_unique_cameras(state: State<'_, AppState>) -> Result<Vec<String>, Str
     Ok(unique.into_iter().collect())
 }
 
+/// Find images by telescope, filter, gain, exposure or observation date
+#[tauri::command]
+pub fn search_images_by_acquisition(
+    state: State<'_, AppState>,
+    query: repository::AcquisitionQuery,
+) -> Result<Vec<Image>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::search_images_by_acquisition(&mut conn, &state.user_id, &query)
+        .map_err(|e| e.to_string())
+}
+
+/// Get the distinct telescopes, filters and gains in the library
+#[tauri::command]
+pub fn get_acquisition_options(
+    state: State<'_, AppState>,
+) -> Result<repository::AcquisitionOptions, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_acquisition_options(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -656,4 +949,64 @@ mod tests {
         let result = find_fits_companion(fit_path.to_str().unwrap());
         assert!(result.is_none());
     }
+
+    #[test]
+    fn encode_for_display_scales_and_converts() {
+        let img = image::DynamicImage::new_rgb8(400, 200);
+        let options = ImageDataOptions {
+            format: Some("png".to_string()),
+            max_dimension: Some(100),
+            ..Default::default()
+        };
+        let data = encode_for_display(img.clone(), &options).unwrap();
+        assert_eq!(image::guess_format(&data).unwrap(), image::ImageFormat::Png);
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+        assert!(display_data_url(&options, &data).starts_with("data:image/png;base64,"));
+
+        let jpeg = ImageDataOptions::default();
+        let data = encode_for_display(img, &jpeg).unwrap();
+        assert!(display_data_url(&jpeg, &data).starts_with("data:image/jpeg;base64,"));
+    }
+
+    #[test]
+    fn display_format_rejects_unknown_formats() {
+        let options = |format: &str| ImageDataOptions {
+            format: Some(format.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            display_format(&options("JPG")).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert!(display_format(&options("tiff")).is_err());
+    }
+
+    #[test]
+    fn conversions_are_cached() {
+        let dir = TempDir::new().unwrap();
+        let cache = PreviewCache::new(dir.path().join("cache"), 1 << 20);
+        let source = dir.path().join("m31.png");
+        image::DynamicImage::new_rgb8(64, 32).save(&source).unwrap();
+        let options = ImageDataOptions {
+            max_dimension: Some(16),
+            ..Default::default()
+        };
+        let load = || image::open(&source).map_err(|e| e.to_string());
+
+        let first = convert_for_display(Some(&cache), &source, &options, load).unwrap();
+        assert_eq!(cache.usage().1, 1);
+        let cached = convert_for_display(Some(&cache), &source, &options, || {
+            Err("should not be loaded again".to_string())
+        })
+        .unwrap();
+        assert_eq!(first, cached);
+
+        let png = ImageDataOptions {
+            format: Some("png".to_string()),
+            ..options
+        };
+        convert_for_display(Some(&cache), &source, &png, load).unwrap();
+        assert_eq!(cache.usage().1, 2);
+    }
 }
@@ -0,0 +1,344 @@
+//! Import journal
+//!
+//! A bulk scan parses headers and renders thumbnails for a whole batch before
+//! writing any of it to the database. Each processed batch is spooled to
+//! `import_journal/` first and removed once committed; batches left behind by
+//! a crash are replayed on the next launch instead of being processed again.
+
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::state::AppState;
+
+use super::frame_rules::{frame_classifier, FrameClassifier};
+use super::scan::{
+    commit_processed_image, BulkScanInput, BulkScanResult, ImportContext, ProcessedImage,
+};
+use super::tag_rules::{tag_rules, TagRules};
+use super::triage::{triage_rules, TriageRules};
+
+const JOURNAL_DIR: &str = "import_journal";
+
+/// A processed batch as written to the journal
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingBatch<'a> {
+    user_id: &'a str,
+    input: &'a BulkScanInput,
+    collection_template: &'a str,
+    images: &'a [ProcessedImage],
+}
+
+/// A processed batch read back from the journal
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JournalBatch {
+    user_id: String,
+    input: BulkScanInput,
+    collection_template: String,
+    images: Vec<ProcessedImage>,
+}
+
+/// Journal directory under an app data directory
+pub(crate) fn journal_dir_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(JOURNAL_DIR)
+}
+
+/// Write an entry atomically, so a crash mid-write never leaves a partial one
+fn write_entry(dir: &Path, batch: &PendingBatch) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create import journal directory: {}", e))?;
+    // Timestamped names replay in the order the batches were processed
+    let name = format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+        uuid::Uuid::new_v4()
+    );
+    let path = dir.join(format!("{}.json", name));
+    let tmp = dir.join(format!("{}.tmp", name));
+    let json = serde_json::to_vec(batch).map_err(|e| e.to_string())?;
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write import journal: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write import journal: {}", e))?;
+    Ok(path)
+}
+
+fn read_entry(path: &Path) -> Result<JournalBatch, String> {
+    let json = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&json).map_err(|e| e.to_string())
+}
+
+/// Complete journal entries, oldest first; unfinished writes are discarded
+fn pending_entries(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut pending = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => pending.push(path),
+            Some("tmp") => {
+                let _ = std::fs::remove_file(&path);
+            }
+            _ => {}
+        }
+    }
+    pending.sort();
+    pending
+}
+
+/// Spool a processed batch before committing it; returns the entry to pass
+/// to [`remove_batch`] once the batch is in the database
+pub(super) fn write_batch(
+    dir: &Path,
+    ctx: &ImportContext,
+    images: &[ProcessedImage],
+) -> Result<PathBuf, String> {
+    let batch = PendingBatch {
+        user_id: &ctx.user_id,
+        input: &ctx.input,
+        collection_template: &ctx.collection_template,
+        images,
+    };
+    write_entry(dir, &batch)
+}
+
+pub(super) fn remove_batch(entry: &Path) {
+    if let Err(e) = std::fs::remove_file(entry) {
+        log::warn!("Failed to remove import journal {}: {}", entry.display(), e);
+    }
+}
+
+/// Commit a journaled batch. Images a crashed scan already committed are
+/// recognized by URL and skipped, so replaying is safe to repeat.
+fn replay_batch(
+    conn: &mut SqliteConnection,
+    batch: JournalBatch,
+    classifier: FrameClassifier,
+    triage: TriageRules,
+    tags: TagRules,
+) -> Result<BulkScanResult, String> {
+    let mut ctx = ImportContext::load(
+        conn,
+        batch.user_id,
+        batch.input,
+        classifier,
+        triage,
+        tags,
+        batch.collection_template,
+    )?;
+    let mut result = BulkScanResult::default();
+    for processed in batch.images {
+        commit_processed_image(conn, &mut ctx, processed, &mut result);
+    }
+    if result.images_imported > 0 {
+        result.subframes_linked = super::subframes::link_subframes(conn, &ctx.user_id)
+            .map_err(|e| format!("Failed to link subframes: {}", e))?;
+    }
+    Ok(result)
+}
+
+/// Commit batches left behind by an import that didn't finish; called at
+/// startup once the database is open
+pub fn replay_pending(app: &AppHandle) {
+    let Ok(data_dir) = app.path().app_data_dir() else {
+        return;
+    };
+    let entries = pending_entries(&journal_dir_in(&data_dir));
+    if entries.is_empty() {
+        return;
+    }
+    log::info!("Replaying {} interrupted import batches", entries.len());
+
+    let db = app.state::<AppState>().db.clone();
+    let classifier = frame_classifier(app);
+    let triage = triage_rules(app);
+    let tags = tag_rules(app);
+    for entry in entries {
+        let batch = match read_entry(&entry) {
+            Ok(batch) => batch,
+            Err(e) => {
+                log::warn!(
+                    "Discarding unreadable import journal {}: {}",
+                    entry.display(),
+                    e
+                );
+                remove_batch(&entry);
+                continue;
+            }
+        };
+        // Entries that fail to commit are kept and retried next launch
+        let replayed = db.get().and_then(|mut conn| {
+            replay_batch(
+                &mut conn,
+                batch,
+                classifier.clone(),
+                triage.clone(),
+                tags.clone(),
+            )
+        });
+        match replayed {
+            Ok(result) => {
+                log::info!(
+                    "Replayed import journal {}: {} imported, {} skipped, {} errors",
+                    entry.display(),
+                    result.images_imported,
+                    result.images_skipped,
+                    result.errors.len()
+                );
+                remove_batch(&entry);
+            }
+            Err(e) => log::warn!("Failed to replay import journal {}: {}", entry.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::scan::{DiscoveredImage, FitsMetadata};
+    use crate::commands::sidecars::SidecarData;
+    use crate::db::repository;
+    use diesel::Connection;
+    use tempfile::TempDir;
+
+    fn processed(name: &str) -> ProcessedImage {
+        ProcessedImage {
+            discovered: DiscoveredImage {
+                base_name: name.to_string(),
+                directory: PathBuf::from("/data/M 31"),
+                fits_path: Some(PathBuf::from(format!("/data/M 31/{}.fit", name))),
+                jpeg_path: Some(PathBuf::from(format!("/data/M 31/{}.jpg", name))),
+                is_stacked: true,
+            },
+            metadata: Some(FitsMetadata {
+                object_name: Some("M 31".to_string()),
+                date_obs: Some("2024-10-05T22:00:00".to_string()),
+                exposure: Some(10.0),
+                ..Default::default()
+            }),
+            thumbnail: Some("data:image/jpeg;base64,AAAA".to_string()),
+            error: None,
+            sidecar: None,
+        }
+    }
+
+    #[test]
+    fn journaled_batch_is_replayed_once() {
+        let dir = TempDir::new().unwrap();
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+
+        let input = BulkScanInput {
+            directory: "/data".to_string(),
+            tags: Some("andromeda".to_string()),
+            stacked_only: false,
+            max_files: None,
+            add_to_collection: None,
+        };
+        let images = vec![
+            processed("Stacked_30_M 31_10.0s"),
+            processed("Stacked_60_M 31_10.0s"),
+        ];
+        let entry = write_entry(
+            dir.path(),
+            &PendingBatch {
+                user_id: "local-user",
+                input: &input,
+                collection_template: "{date} – {object}",
+                images: &images,
+            },
+        )
+        .unwrap();
+        assert_eq!(pending_entries(dir.path()), vec![entry.clone()]);
+
+        let batch = read_entry(&entry).unwrap();
+        let result = replay_batch(
+            &mut conn,
+            batch,
+            FrameClassifier::default(),
+            TriageRules::default(),
+            TagRules::default(),
+        )
+        .unwrap();
+        assert_eq!(result.images_imported, 2);
+        assert_eq!(result.collections_created, 1);
+
+        let imported = repository::get_images_by_user(&mut conn, "local-user").unwrap();
+        assert_eq!(imported.len(), 2);
+        assert!(imported.iter().all(|img| img.thumbnail.is_some()));
+
+        // A second replay (e.g. crash before the entry was removed) is a no-op
+        let batch = read_entry(&entry).unwrap();
+        let result = replay_batch(
+            &mut conn,
+            batch,
+            FrameClassifier::default(),
+            TriageRules::default(),
+            TagRules::default(),
+        )
+        .unwrap();
+        assert_eq!(result.images_imported, 0);
+        assert_eq!(result.images_skipped, 2);
+        assert_eq!(
+            repository::get_images_by_user(&mut conn, "local-user")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn sidecars_fill_in_imported_images() {
+        let dir = TempDir::new().unwrap();
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+
+        let input = BulkScanInput {
+            directory: "/data".to_string(),
+            tags: Some("andromeda".to_string()),
+            stacked_only: false,
+            max_files: None,
+            add_to_collection: None,
+        };
+        let mut image = processed("Stacked_30_M 31_10.0s");
+        image.metadata.as_mut().unwrap().object_name = None;
+        image.sidecar = Some(SidecarData {
+            title: Some("Andromeda".to_string()),
+            description: Some("First light".to_string()),
+            tags: vec!["Andromeda".to_string(), "favorite".to_string()],
+            rating: Some(5),
+            ra: Some(10.68),
+            dec: Some(41.27),
+        });
+        let entry = write_entry(
+            dir.path(),
+            &PendingBatch {
+                user_id: "local-user",
+                input: &input,
+                collection_template: "{date}",
+                images: &[image],
+            },
+        )
+        .unwrap();
+
+        let result = replay_batch(
+            &mut conn,
+            read_entry(&entry).unwrap(),
+            FrameClassifier::default(),
+            TriageRules::default(),
+            TagRules::default(),
+        )
+        .unwrap();
+        assert_eq!(result.images_imported, 1);
+
+        let imported = repository::get_images_by_user(&mut conn, "local-user").unwrap();
+        let image = &imported[0];
+        assert_eq!(image.summary.as_deref(), Some("Andromeda"));
+        assert_eq!(image.description.as_deref(), Some("First light"));
+        assert_eq!(image.tags.as_deref(), Some("andromeda, stacked, favorite"));
+        assert_eq!(image.rating, Some(5));
+        assert_eq!(image.location.as_deref(), Some("10.68, 41.27"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Memory ceiling for bulk scans
+//!
+//! Each image a scan processes reserves its estimated peak memory from a
+//! budget before it starts, so a batch of large FITS files waits for memory
+//! rather than running all at once.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::settings::{load_settings, load_settings_in, save_settings, IMPORT_MEMORY};
+
+const DEFAULT_MAX_MEMORY_MB: u32 = 2048;
+const MIN_MEMORY_MB: u32 = 256;
+const MAX_MEMORY_MB: u32 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImportMemorySettings {
+    /// Memory image processing may use at once during a scan
+    pub max_memory_mb: u32,
+}
+
+impl Default for ImportMemorySettings {
+    fn default() -> Self {
+        Self {
+            max_memory_mb: DEFAULT_MAX_MEMORY_MB,
+        }
+    }
+}
+
+/// Memory ceiling for scans saved in `data_dir`, in MB
+pub(crate) fn import_memory_budget_in(data_dir: &Path) -> u32 {
+    load_settings_in::<ImportMemorySettings>(data_dir, &IMPORT_MEMORY).max_memory_mb
+}
+
+/// Memory reserved by images being processed, in MB
+pub struct MemoryBudget {
+    available: Arc<Semaphore>,
+    max_mb: u32,
+}
+
+impl MemoryBudget {
+    pub fn new(max_mb: u32) -> Self {
+        let max_mb = max_mb.max(1);
+        Self {
+            available: Arc::new(Semaphore::new(max_mb as usize)),
+            max_mb,
+        }
+    }
+
+    /// Wait until `mb` is free and hold it until the permit is dropped. An
+    /// image larger than the whole budget waits for everything else instead.
+    pub async fn reserve(&self, mb: u32) -> OwnedSemaphorePermit {
+        self.available
+            .clone()
+            .acquire_many_owned(mb.clamp(1, self.max_mb))
+            .await
+            .unwrap()
+    }
+}
+
+#[tauri::command]
+pub fn get_import_memory_settings(app: AppHandle) -> Result<ImportMemorySettings, String> {
+    load_settings(&app, &IMPORT_MEMORY)
+}
+
+/// Save the memory ceiling; None restores the default
+#[tauri::command]
+pub fn set_import_memory_settings(
+    app: AppHandle,
+    settings: Option<ImportMemorySettings>,
+) -> Result<ImportMemorySettings, String> {
+    let settings = settings.unwrap_or_default();
+    if !(MIN_MEMORY_MB..=MAX_MEMORY_MB).contains(&settings.max_memory_mb) {
+        return Err(format!(
+            "Import memory must be between {} and {} MB",
+            MIN_MEMORY_MB, MAX_MEMORY_MB
+        ));
+    }
+
+    save_settings(&app, &IMPORT_MEMORY, &settings)?;
+    Ok(settings)
+}
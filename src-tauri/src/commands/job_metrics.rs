@@ -0,0 +1,226 @@
+//! Local timing of import jobs
+//!
+//! Bulk scans and auto-import cycles add up how long they spend in each
+//! phase of the pipeline and store the totals under their job id, so a slow
+//! import on someone's machine can be narrowed down to directory scanning,
+//! FITS parsing, thumbnails or database writes. Nothing leaves the machine;
+//! only the most recent jobs are kept.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{JobMetric, NewJobMetric};
+use crate::db::{repository, SharedDbPool};
+use crate::state::AppState;
+
+/// Walking the directory tree for images
+pub const PHASE_SCAN: &str = "scan";
+/// Reading FITS headers
+pub const PHASE_FITS_PARSE: &str = "fits_parse";
+/// Generating thumbnails from JPEGs or FITS data
+pub const PHASE_THUMBNAIL: &str = "thumbnail";
+/// Writing images, collections and links to the database
+pub const PHASE_DB_INSERT: &str = "db_insert";
+/// The whole job, start to finish
+pub const PHASE_TOTAL: &str = "total";
+
+/// Phases in pipeline order, which is how they're reported
+const PHASES: &[&str] = &[
+    PHASE_SCAN,
+    PHASE_FITS_PARSE,
+    PHASE_THUMBNAIL,
+    PHASE_DB_INSERT,
+    PHASE_TOTAL,
+];
+
+/// Jobs whose metrics are kept
+const KEEP_JOBS: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PhaseTiming {
+    count: u32,
+    total: Duration,
+    max: Duration,
+}
+
+/// Time spent in each phase of one job. Shared between the job's workers,
+/// so parallel phases add up to more than the job's wall time.
+#[derive(Debug, Default)]
+pub struct JobTimings {
+    phases: Mutex<HashMap<&'static str, PhaseTiming>>,
+}
+
+impl JobTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, phase: &'static str, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        let timing = phases.entry(phase).or_default();
+        timing.count += 1;
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+
+    /// Run `f`, recording how long it took under `phase`
+    pub fn time<T>(&self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(phase, start.elapsed());
+        result
+    }
+
+    /// How many times `phase` was recorded
+    pub fn count(&self, phase: &str) -> u32 {
+        let phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        phases.get(phase).map_or(0, |timing| timing.count)
+    }
+
+    fn to_metrics(&self, user_id: &str, job_id: &str) -> Vec<NewJobMetric> {
+        let phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        phases
+            .iter()
+            .map(|(phase, timing)| NewJobMetric {
+                id: uuid::Uuid::new_v4().to_string(),
+                user_id: user_id.to_string(),
+                job_id: job_id.to_string(),
+                phase: phase.to_string(),
+                count: timing.count.min(i32::MAX as u32) as i32,
+                total_ms: timing.total.as_millis() as i64,
+                max_ms: timing.max.as_millis() as i64,
+            })
+            .collect()
+    }
+}
+
+/// Store a finished job's timings. Failing to is only logged; metrics
+/// shouldn't fail an import.
+pub fn save_job_metrics(db: &SharedDbPool, user_id: &str, job_id: &str, timings: &JobTimings) {
+    let metrics = timings.to_metrics(user_id, job_id);
+    if metrics.is_empty() {
+        return;
+    }
+    let saved = db.get().and_then(|mut conn| {
+        repository::save_job_metrics(&mut conn, user_id, &metrics, KEEP_JOBS)
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = saved {
+        log::warn!("Failed to save metrics for job {}: {}", job_id, e);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseMetrics {
+    pub phase: String,
+    /// Files (or runs, for scan and total) timed
+    pub count: i32,
+    pub total_ms: i64,
+    /// Slowest single file or run
+    pub max_ms: i64,
+    pub mean_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobMetrics {
+    pub job_id: String,
+    /// When the job finished, UTC
+    pub recorded_at: String,
+    /// In pipeline order
+    pub phases: Vec<PhaseMetrics>,
+}
+
+fn job_metrics(job_id: &str, stored: Vec<JobMetric>) -> JobMetrics {
+    let recorded_at = stored
+        .iter()
+        .map(|m| m.created_at)
+        .max()
+        .map(|at| at.and_utc().to_rfc3339())
+        .unwrap_or_default();
+    let mut phases: Vec<PhaseMetrics> = stored
+        .into_iter()
+        .map(|m| PhaseMetrics {
+            mean_ms: if m.count > 0 {
+                m.total_ms as f64 / m.count as f64
+            } else {
+                0.0
+            },
+            phase: m.phase,
+            count: m.count,
+            total_ms: m.total_ms,
+            max_ms: m.max_ms,
+        })
+        .collect();
+    // Unknown phases go last
+    phases.sort_by_key(|p| {
+        PHASES
+            .iter()
+            .position(|&phase| phase == p.phase)
+            .unwrap_or(PHASES.len())
+    });
+    JobMetrics {
+        job_id: job_id.to_string(),
+        recorded_at,
+        phases,
+    }
+}
+
+/// Phase timings of a scan or auto-import job, by the id its log lines and
+/// result carry
+#[tauri::command]
+pub fn get_job_metrics(state: State<'_, AppState>, job_id: String) -> Result<JobMetrics, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let stored = repository::get_job_metrics(&mut conn, &state.user_id, &job_id)
+        .map_err(|e| e.to_string())?;
+    if stored.is_empty() {
+        return Err(format!("No metrics recorded for job {}", job_id));
+    }
+    Ok(job_metrics(&job_id, stored))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timings_add_up_per_phase_in_pipeline_order() {
+        let timings = JobTimings::new();
+        timings.record(PHASE_FITS_PARSE, Duration::from_millis(40));
+        timings.record(PHASE_FITS_PARSE, Duration::from_millis(60));
+        timings.record(PHASE_TOTAL, Duration::from_millis(500));
+        let value = timings.time(PHASE_SCAN, || 7);
+        assert_eq!(value, 7);
+        assert_eq!(timings.count(PHASE_FITS_PARSE), 2);
+        assert_eq!(timings.count(PHASE_THUMBNAIL), 0);
+
+        let now = chrono::Utc::now().naive_utc();
+        let stored: Vec<JobMetric> = timings
+            .to_metrics("user-1", "scan-1")
+            .into_iter()
+            .map(|m| JobMetric {
+                id: m.id,
+                user_id: m.user_id,
+                job_id: m.job_id,
+                phase: m.phase,
+                count: m.count,
+                total_ms: m.total_ms,
+                max_ms: m.max_ms,
+                created_at: now,
+            })
+            .collect();
+        let metrics = job_metrics("scan-1", stored);
+
+        let phases: Vec<_> = metrics.phases.iter().map(|p| p.phase.as_str()).collect();
+        assert_eq!(phases, [PHASE_SCAN, PHASE_FITS_PARSE, PHASE_TOTAL]);
+        let parse = &metrics.phases[1];
+        assert_eq!((parse.count, parse.total_ms, parse.max_ms), (2, 100, 60));
+        assert_eq!(parse.mean_ms, 50.0);
+        assert!(!metrics.recorded_at.is_empty());
+    }
+}
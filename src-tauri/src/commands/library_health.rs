@@ -0,0 +1,219 @@
+//! Library health: one summary for a status screen
+//!
+//! Brings together what's otherwise spread over several screens: images
+//! whose files are gone or that were never plate solved (as counted by the
+//! attention queue), undated session collections waiting on a date
+//! backfill, solves still pending on astrometry.net, how long since the last
+//! backup, and how much disk the database and caches take. Python's status
+//! is included so a broken environment shows up alongside the rest.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use walkdir::WalkDir;
+
+use crate::db::repository;
+use crate::python::{self, PythonStatus};
+use crate::state::AppState;
+
+use super::attention::attention_queue;
+use super::backup::list_backups;
+use super::date_backfill::is_undated_session;
+use super::images::listed_images;
+use super::nova_jobs::nova_job;
+
+/// The database file and SQLite's write-ahead log beside it
+const DATABASE_FILES: &[&str] = &["astra.db", "astra.db-wal", "astra.db-shm"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    /// Cache directory name, e.g. "previews" or "sky-cutouts"
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryHealth {
+    /// Library images, leaving out frames in triage and stacked subframes
+    pub images: usize,
+    /// Images whose image or FITS file is no longer on disk
+    pub images_missing_files: usize,
+    /// Images never plate solved (solar system images aren't counted)
+    pub unsolved_images: usize,
+    pub collections: usize,
+    /// Session collections with no night, like "Unknown Session"
+    pub collections_without_dates: usize,
+    /// Solves submitted to astrometry.net and not yet finished
+    pub pending_jobs: usize,
+    /// RFC 3339; None when there are no backups
+    pub last_backup_at: Option<String>,
+    /// Whole days since the last backup
+    pub backup_age_days: Option<i64>,
+    /// Database file size, including its write-ahead log
+    pub database_bytes: u64,
+    /// Whether astra_astro is loaded and the Python functions are usable
+    pub python_available: bool,
+    pub python: PythonStatus,
+    /// Each directory under the app cache dir, largest first
+    pub caches: Vec<CacheUsage>,
+    pub cache_bytes: u64,
+}
+
+/// Total size and number of files under `dir` (zero if it doesn't exist)
+fn dir_usage(dir: &Path) -> (u64, usize) {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(bytes, files), meta| {
+            (bytes + meta.len(), files + 1)
+        })
+}
+
+/// Usage of each cache kept under the app cache dir
+fn cache_usage(cache_dir: &Path) -> Vec<CacheUsage> {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return Vec::new();
+    };
+    let mut caches: Vec<CacheUsage> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let path = entry.path();
+            let (bytes, files) = dir_usage(&path);
+            CacheUsage {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                bytes,
+                files,
+            }
+        })
+        .collect();
+    caches.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    caches
+}
+
+fn database_bytes(app_data_dir: &Path) -> u64 {
+    DATABASE_FILES
+        .iter()
+        .filter_map(|name| std::fs::metadata(app_data_dir.join(name)).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Whole days between a backup's RFC 3339 creation time and `now`
+fn backup_age_days(created_at: &str, now: DateTime<Local>) -> Option<i64> {
+    let created = DateTime::parse_from_rfc3339(created_at).ok()?;
+    Some((now - created.with_timezone(&Local)).num_days().max(0))
+}
+
+/// Summary of the library's state for a status screen
+#[tauri::command]
+pub async fn get_library_health(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<LibraryHealth, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let cache_dir = app.path().app_cache_dir().ok();
+    // Backups are listed newest first
+    let last_backup_at = list_backups(app)?
+        .into_iter()
+        .next()
+        .map(|backup| backup.created_at);
+    let python = python::python_status();
+
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+    // Checking files can be slow on network mounts
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let images =
+            repository::get_images_by_user(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        let collections =
+            repository::get_collections(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        let pending_jobs = repository::get_images_with_nova_jobs(&mut conn, &user_id)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .filter(|image| nova_job(image.metadata.as_deref()).is_some())
+            .count();
+        drop(conn);
+
+        let images = listed_images(images, None);
+        let image_count = images.len();
+        let attention = attention_queue(images, None, |path| path.exists()).counts;
+        let caches = cache_dir.as_deref().map(cache_usage).unwrap_or_default();
+
+        Ok(LibraryHealth {
+            images: image_count,
+            images_missing_files: attention.missing_file,
+            unsolved_images: attention.unsolved,
+            collections: collections.len(),
+            collections_without_dates: collections.iter().filter(|c| is_undated_session(c)).count(),
+            pending_jobs,
+            backup_age_days: last_backup_at
+                .as_deref()
+                .and_then(|at| backup_age_days(at, Local::now())),
+            last_backup_at,
+            database_bytes: database_bytes(&app_data_dir),
+            python_available: python.module_loaded,
+            python,
+            cache_bytes: caches.iter().map(|c| c.bytes).sum(),
+            caches,
+        })
+    })
+    .await
+    .map_err(|e| format!("Checking library health failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn caches_are_measured_largest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let previews = dir.path().join("previews");
+        std::fs::create_dir_all(previews.join("ab")).unwrap();
+        std::fs::write(previews.join("ab").join("one.jpg"), [0u8; 300]).unwrap();
+        std::fs::write(previews.join("two.jpg"), [0u8; 200]).unwrap();
+        std::fs::create_dir_all(dir.path().join("network")).unwrap();
+        std::fs::write(dir.path().join("network").join("a.json"), "{}").unwrap();
+        // Loose files aren't a cache
+        std::fs::write(dir.path().join("stray.txt"), "x").unwrap();
+
+        let caches = cache_usage(dir.path());
+        let summary: Vec<_> = caches
+            .iter()
+            .map(|c| (c.name.as_str(), c.bytes, c.files))
+            .collect();
+        assert_eq!(summary, [("previews", 500, 2), ("network", 2, 1)]);
+        assert!(cache_usage(&dir.path().join("missing")).is_empty());
+
+        std::fs::write(dir.path().join("astra.db"), [0u8; 4096]).unwrap();
+        std::fs::write(dir.path().join("astra.db-wal"), [0u8; 1024]).unwrap();
+        assert_eq!(database_bytes(dir.path()), 5120);
+    }
+
+    #[test]
+    fn backup_age_is_in_whole_days() {
+        let now = Local.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap();
+        let created = |days: i64, hours: i64| {
+            (now - chrono::Duration::days(days) - chrono::Duration::hours(hours)).to_rfc3339()
+        };
+        assert_eq!(backup_age_days(&created(0, 3), now), Some(0));
+        assert_eq!(backup_age_days(&created(6, 23), now), Some(6));
+        assert_eq!(backup_age_days(&created(30, 0), now), Some(30));
+        assert_eq!(backup_age_days("Unknown", now), None);
+    }
+}
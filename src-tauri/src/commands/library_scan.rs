@@ -79,9 +79,23 @@ pub struct ImageStats {
 }
 
 /// Get aggregate counts for the user's image library.
+///
+/// When `equipment_ids` is given, only images shot with that rig are counted.
 #[tauri::command]
-pub async fn get_image_stats(state: State<'_, AppState>) -> Result<ImageStats, String> {
+pub async fn get_image_stats(
+    state: State<'_, AppState>,
+    equipment_ids: Option<Vec<String>>,
+) -> Result<ImageStats, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    if let Some(ids) = equipment_ids.filter(|ids| !ids.is_empty()) {
+        let (total_images, stacked_images) =
+            repository::count_images_by_rig(&mut conn, &state.user_id, &ids)
+                .map_err(|e| e.to_string())?;
+        return Ok(ImageStats {
+            total_images,
+            stacked_images,
+        });
+    }
     let total_images = repository::count_images_by_user(&mut conn, &state.user_id)
         .map_err(|e| e.to_string())?;
     let stacked_images = repository::count_stacked_images_by_user(&mut conn, &state.user_id)
@@ -0,0 +1,133 @@
+//! Observing location commands, including capturing the device's position
+
+use serde::Deserialize;
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, State};
+use tauri_plugin_geolocation::{GeolocationExt, PermissionType, PositionOptions};
+
+use crate::db::models::{Location, NewLocation};
+use crate::db::repository;
+use crate::network::{Network, Provider};
+use crate::state::AppState;
+
+/// Copernicus DEM elevation lookup (no API key needed)
+const ELEVATION_API_URL: &str = "https://api.open-meteo.com/v1/elevation";
+const POSITION_TIMEOUT_MS: u32 = 30_000;
+
+#[derive(Debug, Deserialize)]
+struct ElevationResponse {
+    elevation: Vec<f64>,
+}
+
+/// Ground elevation in metres at a position from a DEM lookup
+async fn lookup_elevation(network: &Network, latitude: f64, longitude: f64) -> Result<f64, String> {
+    let url = format!(
+        "{}?latitude={}&longitude={}",
+        ELEVATION_API_URL, latitude, longitude
+    );
+    let body: ElevationResponse = network.get_json(Provider::Elevation, &url).await?;
+    body.elevation
+        .first()
+        .copied()
+        .filter(|e| e.is_finite())
+        .ok_or_else(|| "No elevation for this position".to_string())
+}
+
+/// Read the device position, asking for permission first if needed.
+/// Returns latitude, longitude and the GPS altitude when there is one.
+fn current_position(app: &AppHandle) -> Result<(f64, f64, Option<f64>), String> {
+    let geolocation = app.geolocation();
+    let status = geolocation.check_permissions().map_err(|e| e.to_string())?;
+    if !matches!(status.location, PermissionState::Granted) {
+        let status = geolocation
+            .request_permissions(Some(vec![PermissionType::Location]))
+            .map_err(|e| e.to_string())?;
+        if !matches!(status.location, PermissionState::Granted) {
+            return Err("Location permission denied".to_string());
+        }
+    }
+
+    let position = geolocation
+        .get_current_position(Some(PositionOptions {
+            enable_high_accuracy: true,
+            timeout: POSITION_TIMEOUT_MS,
+            maximum_age: 0,
+        }))
+        .map_err(|e| format!("Failed to read position: {}", e))?;
+    let coords = position.coords;
+    // Platforms without a location service report an empty position
+    if coords.latitude == 0.0 && coords.longitude == 0.0 && coords.accuracy == 0.0 {
+        return Err("Position is not available on this device".to_string());
+    }
+    Ok((coords.latitude, coords.longitude, coords.altitude))
+}
+
+#[tauri::command]
+pub fn get_locations(state: State<'_, AppState>) -> Result<Vec<Location>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_locations(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+/// Save the device's current position as a location. Elevation comes from
+/// a DEM lookup, falling back to the GPS altitude when offline.
+#[tauri::command]
+pub async fn capture_current_location(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+    set_default: Option<bool>,
+) -> Result<Location, String> {
+    let (latitude, longitude, altitude) =
+        tokio::task::spawn_blocking(move || current_position(&app))
+            .await
+            .map_err(|e| format!("Location capture failed: {}", e))??;
+
+    let elevation = match lookup_elevation(&state.network, latitude, longitude).await {
+        Ok(elevation) => Some(elevation),
+        Err(e) => {
+            log::warn!("Elevation lookup failed, using GPS altitude: {}", e);
+            altitude
+        }
+    };
+
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("Current location ({:.4}, {:.4})", latitude, longitude));
+    let new_location = NewLocation {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        name,
+        latitude,
+        longitude,
+        elevation,
+        bortle: None,
+        source: "gps".to_string(),
+        is_default: set_default.unwrap_or(false),
+    };
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::create_location(&mut conn, &new_location).map_err(|e| e.to_string())
+}
+
+/// Make a location the default, or clear its default flag
+#[tauri::command]
+pub fn set_default_location(
+    state: State<'_, AppState>,
+    id: String,
+    is_default: bool,
+) -> Result<Location, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_location_by_id(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Location not found".to_string())?;
+    repository::set_default_location(&mut conn, &id, is_default).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_location(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_location(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
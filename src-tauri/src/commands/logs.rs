@@ -0,0 +1,201 @@
+//! Log viewing and export for support requests
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::Local;
+use log::Level;
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::logging::{self, LogEntry};
+
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 5000;
+/// Where exports go when no path is given, under the app data dir
+const EXPORT_DIR: &str = "log-exports";
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    match logging::log_dir() {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => app
+            .path()
+            .app_data_dir()
+            .map(|d| d.join("logs"))
+            .map_err(|e| format!("Failed to get app data dir: {}", e)),
+    }
+}
+
+/// The last `limit` entries at `level` or more severe (and in `job_id`),
+/// oldest first
+fn recent_entries(
+    files: &[PathBuf],
+    level: Level,
+    job_id: Option<&str>,
+    limit: usize,
+) -> Vec<LogEntry> {
+    let mut recent = Vec::new();
+    // Files are newest first
+    for path in files {
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        let matching = logging::parse_entries(&text).into_iter().rev().filter(|e| {
+            Level::from_str(&e.level).is_ok_and(|l| l <= level)
+                && job_id.is_none_or(|id| e.job_id.as_deref() == Some(id))
+        });
+        recent.extend(matching.take(limit - recent.len()));
+        if recent.len() >= limit {
+            break;
+        }
+    }
+    recent.reverse();
+    recent
+}
+
+/// Recent log entries, oldest first. `level` is the least severe level to
+/// include (default "info"); `job_id` keeps only one job's entries.
+#[tauri::command]
+pub fn get_recent_logs(
+    app: AppHandle,
+    level: Option<String>,
+    limit: Option<usize>,
+    job_id: Option<String>,
+) -> Result<Vec<LogEntry>, String> {
+    let level = match level.as_deref() {
+        Some(level) => {
+            Level::from_str(level).map_err(|_| format!("Unknown log level: {}", level))?
+        }
+        None => Level::Info,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let files = logging::log_files(&log_dir(&app)?);
+    Ok(recent_entries(&files, level, job_id.as_deref(), limit))
+}
+
+/// Zip the log files with a note of the app version and platform
+fn write_export(files: &[PathBuf], dest: &Path) -> Result<(), String> {
+    let file =
+        File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let zip_error = |e: zip::result::ZipError| format!("Failed to write log export: {}", e);
+
+    zip.start_file("about.txt", SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    let about = format!(
+        "Astra {}\n{} {}\nExported {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        Local::now().to_rfc3339()
+    );
+    zip.write_all(about.as_bytes())
+        .map_err(|e| format!("Failed to write log export: {}", e))?;
+
+    for path in files {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let mut log =
+            File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        zip.start_file(name.to_string_lossy(), SimpleFileOptions::default())
+            .map_err(zip_error)?;
+        io::copy(&mut log, &mut zip)
+            .map_err(|e| format!("Failed to add {}: {}", path.display(), e))?;
+    }
+    zip.finish().map_err(zip_error)?;
+    Ok(())
+}
+
+/// Export the log files as a zip, to `path` or the app data dir's
+/// log-exports/. Returns where it was written.
+#[tauri::command]
+pub fn export_logs(app: AppHandle, path: Option<String>) -> Result<String, String> {
+    log::logger().flush();
+    let files = logging::log_files(&log_dir(&app)?);
+    if files.is_empty() {
+        return Err("No log files to export".to_string());
+    }
+
+    let dest = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = app
+                .path()
+                .app_data_dir()
+                .map(|d| d.join(EXPORT_DIR))
+                .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            dir.join(format!(
+                "astra-logs-{}.zip",
+                Local::now().format("%Y%m%d_%H%M%S")
+            ))
+        }
+    };
+    write_export(&files, &dest)?;
+    log::info!("Exported logs to {}", dest.display());
+    Ok(dest.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(minute: u32, level: &str, job: Option<&str>, message: &str) -> String {
+        let job = job.map(|id| format!(" (job {})", id)).unwrap_or_default();
+        format!(
+            "2025-01-12T21:{:02}:00.000+00:00 {:<5} [astra_lib]{} {}\n",
+            minute, level, job, message
+        )
+    }
+
+    #[test]
+    fn recent_entries_filter_and_span_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("astra.log.1");
+        let current = dir.path().join("astra.log");
+        fs::write(
+            &older,
+            line(1, "ERROR", Some("scan-a"), "old failure") + &line(2, "DEBUG", None, "noise"),
+        )
+        .unwrap();
+        fs::write(
+            &current,
+            line(3, "INFO", Some("scan-b"), "started")
+                + &line(4, "WARN", Some("scan-b"), "bad header")
+                + &line(5, "INFO", None, "idle"),
+        )
+        .unwrap();
+        let files = vec![current, older];
+        let messages =
+            |entries: Vec<LogEntry>| entries.into_iter().map(|e| e.message).collect::<Vec<_>>();
+
+        assert_eq!(
+            messages(recent_entries(&files, Level::Info, None, 10)),
+            ["old failure", "started", "bad header", "idle"]
+        );
+        assert_eq!(
+            messages(recent_entries(&files, Level::Warn, None, 10)),
+            ["old failure", "bad header"]
+        );
+        assert_eq!(
+            messages(recent_entries(&files, Level::Trace, Some("scan-b"), 10)),
+            ["started", "bad header"]
+        );
+        assert_eq!(
+            messages(recent_entries(&files, Level::Trace, None, 2)),
+            ["bad header", "idle"]
+        );
+
+        let export = dir.path().join("logs.zip");
+        write_export(&files, &export).unwrap();
+        let archive = zip::ZipArchive::new(File::open(&export).unwrap()).unwrap();
+        let mut names: Vec<_> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["about.txt", "astra.log", "astra.log.1"]);
+    }
+}
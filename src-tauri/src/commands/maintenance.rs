@@ -0,0 +1,346 @@
+//! Equipment maintenance log and reminders
+//!
+//! Each entry records work done on an item (collimation, desiccant
+//! regeneration, a firmware update) and, for recurring tasks, how many days
+//! until it's due again. The latest entry for an item's task decides when
+//! it's next due; retired equipment is never due. Due tasks are announced
+//! once a day as "maintenance-due" and on the activity feed.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::models::{Equipment, MaintenanceEntry, NewMaintenanceEntry, UpdateMaintenanceEntry};
+use crate::db::repository;
+use crate::events;
+use crate::state::AppState;
+
+/// How often the reminder task checks whether today's reminder is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Reminders also cover tasks coming due in the next few days, so there's
+/// time to order desiccant before it's needed
+const REMINDER_LOOKAHEAD_DAYS: i64 = 3;
+
+static REMINDERS_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMaintenanceInput {
+    pub equipment_id: String,
+    pub task: String,
+    /// YYYY-MM-DD; defaults to today
+    pub performed_on: Option<String>,
+    /// Days until the task is due again; None for one-off work
+    pub interval_days: Option<i32>,
+    pub notes: Option<String>,
+}
+
+/// Replaces an entry's editable fields
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMaintenanceInput {
+    pub id: String,
+    pub task: String,
+    pub performed_on: String,
+    pub interval_days: Option<i32>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DueMaintenance {
+    /// Latest entry for the task
+    pub entry_id: String,
+    pub equipment_id: String,
+    pub equipment_name: String,
+    pub equipment_kind: String,
+    pub task: String,
+    pub last_performed_on: String,
+    pub interval_days: i32,
+    pub due_on: String,
+    /// Days past due; negative while the task is still coming up
+    pub days_overdue: i64,
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}' (expected YYYY-MM-DD)", value))
+}
+
+fn validate_task(task: &str) -> Result<String, String> {
+    let task = task.trim();
+    if task.is_empty() {
+        return Err("Maintenance task is required".to_string());
+    }
+    Ok(task.to_string())
+}
+
+fn validate_interval(interval_days: Option<i32>) -> Result<(), String> {
+    match interval_days {
+        Some(days) if days <= 0 => Err("Interval must be at least one day".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn blank_to_none(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Recurring tasks due by `until`, most overdue first. Only the latest entry
+/// for each item's task counts, so logging the work clears the reminder.
+fn due_maintenance(
+    entries: &[MaintenanceEntry],
+    equipment: &[Equipment],
+    today: NaiveDate,
+    until: NaiveDate,
+) -> Vec<DueMaintenance> {
+    let mut latest: HashMap<(&str, String), &MaintenanceEntry> = HashMap::new();
+    for entry in entries {
+        let key = (
+            entry.equipment_id.as_str(),
+            entry.task.trim().to_lowercase(),
+        );
+        let newer = latest.get(&key).is_none_or(|current| {
+            (&entry.performed_on, entry.created_at) > (&current.performed_on, current.created_at)
+        });
+        if newer {
+            latest.insert(key, entry);
+        }
+    }
+
+    let mut due: Vec<DueMaintenance> = latest
+        .into_values()
+        .filter_map(|entry| {
+            let item = equipment
+                .iter()
+                .find(|e| e.id == entry.equipment_id && !e.retired)?;
+            let interval_days = entry.interval_days?;
+            let performed_on = parse_date(&entry.performed_on).ok()?;
+            let due_on = performed_on + chrono::Duration::days(interval_days as i64);
+            (due_on <= until).then(|| DueMaintenance {
+                entry_id: entry.id.clone(),
+                equipment_id: item.id.clone(),
+                equipment_name: item.name.clone(),
+                equipment_kind: item.kind.clone(),
+                task: entry.task.clone(),
+                last_performed_on: entry.performed_on.clone(),
+                interval_days,
+                due_on: due_on.to_string(),
+                days_overdue: (today - due_on).num_days(),
+            })
+        })
+        .collect();
+    due.sort_by(|a, b| {
+        b.days_overdue
+            .cmp(&a.days_overdue)
+            .then_with(|| a.equipment_name.cmp(&b.equipment_name))
+            .then_with(|| a.task.cmp(&b.task))
+    });
+    due
+}
+
+fn load_due_maintenance(
+    state: &AppState,
+    today: NaiveDate,
+    within_days: i64,
+) -> Result<Vec<DueMaintenance>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let entries = repository::get_maintenance_entries(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+    let equipment =
+        repository::get_equipment(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    let until = today + chrono::Duration::days(within_days.max(0));
+    Ok(due_maintenance(&entries, &equipment, today, until))
+}
+
+/// Start the daily reminder task; called once the database is open
+pub fn start_reminders(app: &AppHandle) {
+    if REMINDERS_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut reminded_on: Option<NaiveDate> = None;
+        loop {
+            let today = Local::now().date_naive();
+            if reminded_on != Some(today) {
+                let state = app.state::<AppState>();
+                match load_due_maintenance(&state, today, REMINDER_LOOKAHEAD_DAYS) {
+                    Ok(due) => {
+                        reminded_on = Some(today);
+                        if !due.is_empty() {
+                            log::info!("{} maintenance task(s) due", due.len());
+                            let _ = app.emit(events::MAINTENANCE_DUE, &due);
+                            events::publish(events::MAINTENANCE_DUE, &due);
+                        }
+                    }
+                    // Tried again at the next check
+                    Err(e) => log::warn!("Maintenance reminder check failed: {}", e),
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// The maintenance log, for one item or all equipment, most recent first
+#[tauri::command]
+pub fn get_maintenance_log(
+    state: State<'_, AppState>,
+    equipment_id: Option<String>,
+) -> Result<Vec<MaintenanceEntry>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    match equipment_id {
+        Some(id) => repository::get_maintenance_for_equipment(&mut conn, &id),
+        None => repository::get_maintenance_entries(&mut conn, &state.user_id),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn add_maintenance_entry(
+    state: State<'_, AppState>,
+    input: CreateMaintenanceInput,
+) -> Result<MaintenanceEntry, String> {
+    let task = validate_task(&input.task)?;
+    validate_interval(input.interval_days)?;
+    let performed_on = match input.performed_on.as_deref() {
+        Some(date) => parse_date(date)?,
+        None => Local::now().date_naive(),
+    };
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_equipment_by_id(&mut conn, &input.equipment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Equipment not found: {}", input.equipment_id))?;
+
+    let new_entry = NewMaintenanceEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        equipment_id: input.equipment_id,
+        task,
+        performed_on: performed_on.to_string(),
+        interval_days: input.interval_days,
+        notes: blank_to_none(input.notes),
+    };
+    repository::create_maintenance_entry(&mut conn, &new_entry).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_maintenance_entry(
+    state: State<'_, AppState>,
+    input: UpdateMaintenanceInput,
+) -> Result<MaintenanceEntry, String> {
+    let task = validate_task(&input.task)?;
+    validate_interval(input.interval_days)?;
+    let performed_on = parse_date(&input.performed_on)?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_maintenance_entry_by_id(&mut conn, &input.id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Maintenance entry not found: {}", input.id))?;
+
+    let update = UpdateMaintenanceEntry {
+        task: Some(task),
+        performed_on: Some(performed_on.to_string()),
+        interval_days: Some(input.interval_days),
+        notes: Some(blank_to_none(input.notes)),
+    };
+    repository::update_maintenance_entry(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_maintenance_entry(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_maintenance_entry(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Recurring tasks that are overdue or due within `within_days` (default
+/// today), most overdue first
+#[tauri::command]
+pub fn get_due_maintenance(
+    state: State<'_, AppState>,
+    within_days: Option<i64>,
+) -> Result<Vec<DueMaintenance>, String> {
+    load_due_maintenance(&state, Local::now().date_naive(), within_days.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        parse_date(value).unwrap()
+    }
+
+    fn make_entry(
+        id: &str,
+        equipment_id: &str,
+        task: &str,
+        performed_on: &str,
+        interval_days: Option<i32>,
+    ) -> MaintenanceEntry {
+        let now = chrono::Utc::now().naive_utc();
+        MaintenanceEntry {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            equipment_id: equipment_id.to_string(),
+            task: task.to_string(),
+            performed_on: performed_on.to_string(),
+            interval_days,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn latest_entry_per_task_decides_when_due() {
+        let equipment = [
+            Equipment::test("c8", "telescope", "C8"),
+            Equipment {
+                retired: true,
+                ..Equipment::test("old", "telescope", "Old refractor")
+            },
+        ];
+        let entries = [
+            make_entry("m-1", "c8", "Collimation", "2025-01-01", Some(30)),
+            // Logged again since, so only this one counts
+            make_entry("m-2", "c8", "collimation ", "2025-02-20", Some(30)),
+            make_entry(
+                "m-3",
+                "c8",
+                "Desiccant regeneration",
+                "2025-01-15",
+                Some(45),
+            ),
+            make_entry("m-4", "c8", "Firmware update", "2024-01-01", None),
+            make_entry("m-5", "old", "Collimation", "2024-01-01", Some(30)),
+        ];
+        let today = date("2025-03-10");
+
+        let due = due_maintenance(&entries, &equipment, today, today);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].entry_id, "m-3");
+        assert_eq!(due[0].due_on, "2025-03-01");
+        assert_eq!(due[0].days_overdue, 9);
+
+        let upcoming = due_maintenance(&entries, &equipment, today, date("2025-03-25"));
+        let ids: Vec<&str> = upcoming.iter().map(|d| d.entry_id.as_str()).collect();
+        assert_eq!(ids, ["m-3", "m-2"]);
+        assert_eq!(upcoming[1].days_overdue, -12);
+    }
+
+    #[test]
+    fn inputs_are_validated() {
+        assert!(validate_task("  ").is_err());
+        assert_eq!(validate_task(" Collimation ").unwrap(), "Collimation");
+        assert!(validate_interval(Some(0)).is_err());
+        assert!(validate_interval(None).is_ok());
+        assert!(parse_date("2025-02-30").is_err());
+    }
+}
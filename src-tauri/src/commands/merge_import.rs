@@ -0,0 +1,641 @@
+//! Merge import: combine collections and images from another Astra database
+//! into the current library (e.g. libraries kept on two computers)
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{Collection, Image, NewCollection, NewCollectionImage, NewImage, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::scan;
+
+/// How to handle an incoming image that already exists in the library
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing image untouched
+    #[default]
+    Skip,
+    /// Replace the existing image's fields with the incoming ones
+    Overwrite,
+    /// Replace only if the incoming image was updated more recently
+    Newer,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeImportInput {
+    /// Path to the other astra.db
+    pub source_path: String,
+    /// Source collections to import (all when omitted)
+    pub collection_ids: Option<Vec<String>>,
+    /// Inclusive session date range (YYYY-MM-DD) applied to images
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Report what would change without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSummary {
+    pub collections_created: usize,
+    pub collections_matched: usize,
+    pub images_added: usize,
+    pub images_updated: usize,
+    pub images_skipped: usize,
+    pub links_added: usize,
+    pub dry_run: bool,
+}
+
+/// A collection in the source database, for picking what to import
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSourceCollection {
+    pub id: String,
+    pub name: String,
+    pub template: Option<String>,
+    pub image_count: i64,
+    /// Whether a collection with the same id or name already exists locally
+    pub exists: bool,
+}
+
+/// A working copy of the source database, migrated to the current schema.
+/// The copy is removed when dropped so the source file is never modified.
+struct StagedDatabase {
+    conn: SqliteConnection,
+    path: PathBuf,
+}
+
+impl StagedDatabase {
+    fn open(source: &Path) -> Result<Self, String> {
+        if !source.exists() {
+            return Err(format!("Source database not found: {}", source.display()));
+        }
+        let path = std::env::temp_dir().join(format!("astra_merge_{}.db", uuid::Uuid::new_v4()));
+        fs::copy(source, &path).map_err(|e| format!("Failed to copy source database: {}", e))?;
+
+        let staged = SqliteConnection::establish(&path.to_string_lossy())
+            .map_err(|e| format!("Failed to open source database: {}", e))
+            .and_then(|mut conn| {
+                crate::db::run_migrations(&mut conn)
+                    .map_err(|e| format!("Failed to migrate source database: {}", e))?;
+                Ok(conn)
+            });
+
+        match staged {
+            Ok(conn) => Ok(Self { conn, path }),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for StagedDatabase {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Observation night for an image, falling back to when it was added
+fn image_session_date(image: &Image) -> NaiveDate {
+    scan::image_session_date(image, None).unwrap_or_else(|| image.created_at.date())
+}
+
+/// Inclusive session date bounds
+type DateRange = (Option<NaiveDate>, Option<NaiveDate>);
+
+fn parse_date_range(input: &MergeImportInput) -> Result<DateRange, String> {
+    let parse = |value: Option<&str>| {
+        value
+            .map(|d| {
+                NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| format!("Invalid date '{}'", d))
+            })
+            .transpose()
+    };
+    Ok((parse(input.date_from.as_deref())?, parse(input.date_to.as_deref())?))
+}
+
+/// Find the local image an incoming one duplicates: by id, then content hash, then path
+fn find_existing_image(conn: &mut SqliteConnection, image: &Image) -> QueryResult<Option<Image>> {
+    if let Some(existing) = repository::get_image_by_id(conn, &image.id)? {
+        return Ok(Some(existing));
+    }
+    if let Some(blob_id) = &image.blob_id {
+        if let Some(existing) = repository::get_image_by_blob_id(conn, blob_id)? {
+            return Ok(Some(existing));
+        }
+    }
+    match &image.url {
+        Some(url) => repository::get_image_by_url(conn, url),
+        None => Ok(None),
+    }
+}
+
+fn find_existing_collection(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    collection: &Collection,
+) -> QueryResult<Option<Collection>> {
+    match repository::get_collection_by_id(conn, &collection.id)? {
+        Some(existing) => Ok(Some(existing)),
+        None => repository::get_collection_by_name(conn, user_id, &collection.name),
+    }
+}
+
+/// Copy the columns `NewImage` and `UpdateImage` leave out: the star rating
+/// and capture site. A site id is only kept if that location exists here;
+/// the coordinates are kept either way.
+fn copy_rating_and_site(
+    dst: &mut SqliteConnection,
+    user_id: &str,
+    image: &Image,
+    image_id: &str,
+) -> QueryResult<()> {
+    repository::set_image_rating(dst, image_id, image.rating)?;
+    let site_id = match &image.capture_site_id {
+        Some(id) => repository::get_location_by_id(dst, id)?
+            .filter(|location| location.user_id == user_id)
+            .map(|location| location.id),
+        None => None,
+    };
+    repository::set_image_capture_site(
+        dst,
+        user_id,
+        &[image_id.to_string()],
+        site_id.as_deref(),
+        image.capture_latitude,
+        image.capture_longitude,
+    )?;
+    Ok(())
+}
+
+/// Merge the selected part of `src` into `dst`.
+///
+/// Collections are matched by id, then name; images by id, then blob_id
+/// (content hash), then url. Matched images follow the conflict policy and
+/// new ones keep their source ids. Membership links are added for every
+/// imported image/collection pair.
+pub fn merge_library(
+    src: &mut SqliteConnection,
+    dst: &mut SqliteConnection,
+    user_id: &str,
+    input: &MergeImportInput,
+    (date_from, date_to): DateRange,
+) -> QueryResult<MergeSummary> {
+    let has_date_filter = date_from.is_some() || date_to.is_some();
+    let in_range = |image: &Image| {
+        let date = image_session_date(image);
+        date_from.is_none_or(|from| date >= from) && date_to.is_none_or(|to| date <= to)
+    };
+
+    let mut collections = repository::get_all_collections(src, user_id)?;
+    if let Some(ids) = &input.collection_ids {
+        collections.retain(|c| ids.contains(&c.id));
+    }
+
+    // Select images and the memberships that connect them to selected collections
+    let mut images: HashMap<String, Image> = HashMap::new();
+    let mut links: Vec<(String, String)> = Vec::new();
+    for collection in &collections {
+        for image in repository::get_images_in_collection(src, &collection.id)? {
+            if in_range(&image) {
+                links.push((collection.id.clone(), image.id.clone()));
+                images.insert(image.id.clone(), image);
+            }
+        }
+    }
+    if input.collection_ids.is_none() {
+        for image in repository::get_images_by_user(src, user_id)? {
+            if in_range(&image) {
+                images.entry(image.id.clone()).or_insert(image);
+            }
+        }
+    }
+
+    // With a date filter, skip collections that end up with no images
+    let linked: HashSet<&str> = links.iter().map(|(c, _)| c.as_str()).collect();
+    collections.retain(|c| !has_date_filter || linked.contains(c.id.as_str()));
+
+    let mut summary = MergeSummary {
+        dry_run: input.dry_run,
+        ..Default::default()
+    };
+
+    let mut collection_map: HashMap<String, String> = HashMap::new();
+    for collection in &collections {
+        match find_existing_collection(dst, user_id, collection)? {
+            Some(existing) => {
+                summary.collections_matched += 1;
+                collection_map.insert(collection.id.clone(), existing.id);
+            }
+            None => {
+                summary.collections_created += 1;
+                if !input.dry_run {
+                    repository::create_collection(
+                        dst,
+                        &NewCollection {
+                            id: collection.id.clone(),
+                            user_id: user_id.to_string(),
+                            name: collection.name.clone(),
+                            description: collection.description.clone(),
+                            visibility: collection.visibility.clone(),
+                            template: collection.template.clone(),
+                            favorite: collection.favorite,
+                            tags: collection.tags.clone(),
+                            metadata: collection.metadata.clone(),
+                            archived: collection.archived,
+                        },
+                    )
+                    ?;
+                }
+                collection_map.insert(collection.id.clone(), collection.id.clone());
+            }
+        }
+    }
+
+    let mut image_ids: Vec<&String> = images.keys().collect();
+    image_ids.sort();
+    let mut image_map: HashMap<String, String> = HashMap::new();
+    for id in image_ids {
+        let image = &images[id];
+        let collection_id = image
+            .collection_id
+            .as_ref()
+            .and_then(|c| collection_map.get(c))
+            .cloned();
+
+        match find_existing_image(dst, image)? {
+            Some(existing) => {
+                let replace = match input.conflict_policy {
+                    ConflictPolicy::Skip => false,
+                    ConflictPolicy::Overwrite => true,
+                    ConflictPolicy::Newer => image.updated_at > existing.updated_at,
+                };
+                if replace {
+                    summary.images_updated += 1;
+                    if !input.dry_run {
+                        repository::update_image(
+                            dst,
+                            &existing.id,
+                            &UpdateImage {
+                                collection_id,
+                                filename: Some(image.filename.clone()),
+                                url: image.url.clone(),
+                                summary: image.summary.clone(),
+                                description: image.description.clone(),
+                                content_type: image.content_type.clone(),
+                                favorite: Some(image.favorite),
+                                tags: image.tags.clone(),
+                                visibility: image.visibility.clone(),
+                                location: image.location.clone(),
+                                annotations: image.annotations.clone(),
+                                metadata: image.metadata.clone(),
+                                thumbnail: image.thumbnail.clone(),
+                                fits_url: image.fits_url.clone(),
+                                blob_id: image.blob_id.clone(),
+                                exposure: image.exposure,
+                                gain: image.gain,
+                                filter: image.filter.clone(),
+                                telescope: image.telescope.clone(),
+                                date_obs: image.date_obs.clone(),
+                                wcs: image.wcs.clone(),
+                            },
+                        )
+                        ?;
+                        copy_rating_and_site(dst, user_id, image, &existing.id)?;
+                    }
+                } else {
+                    summary.images_skipped += 1;
+                }
+                image_map.insert(image.id.clone(), existing.id);
+            }
+            None => {
+                summary.images_added += 1;
+                if !input.dry_run {
+                    repository::create_image(
+                        dst,
+                        &NewImage {
+                            id: image.id.clone(),
+                            user_id: user_id.to_string(),
+                            collection_id,
+                            filename: image.filename.clone(),
+                            url: image.url.clone(),
+                            summary: image.summary.clone(),
+                            description: image.description.clone(),
+                            content_type: image.content_type.clone(),
+                            favorite: image.favorite,
+                            tags: image.tags.clone(),
+                            visibility: image.visibility.clone(),
+                            location: image.location.clone(),
+                            annotations: image.annotations.clone(),
+                            metadata: image.metadata.clone(),
+                            thumbnail: image.thumbnail.clone(),
+                            fits_url: image.fits_url.clone(),
+                            blob_id: image.blob_id.clone(),
+                            exposure: image.exposure,
+                            gain: image.gain,
+                            filter: image.filter.clone(),
+                            telescope: image.telescope.clone(),
+                            date_obs: image.date_obs.clone(),
+                            wcs: image.wcs.clone(),
+                            parent_image_id: None,
+                            triage: image.triage.clone(),
+                        },
+                    )
+                    ?;
+                    copy_rating_and_site(dst, user_id, image, &image.id)?;
+                }
+                image_map.insert(image.id.clone(), image.id.clone());
+            }
+        }
+    }
+
+    // Link subs to their stacks once every image has been merged
+    if !input.dry_run {
+        for image in images.values() {
+            let parent = image.parent_image_id.as_ref().and_then(|p| image_map.get(p));
+            if let (Some(sub), Some(parent)) = (image_map.get(&image.id), parent) {
+                repository::set_image_parent(dst, &[sub.clone()], Some(parent))?;
+            }
+        }
+    }
+
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    for (collection_id, image_id) in links {
+        let (Some(c), Some(i)) = (collection_map.get(&collection_id), image_map.get(&image_id)) else {
+            continue;
+        };
+        if !seen.insert((c.clone(), i.clone())) {
+            continue;
+        }
+        if repository::is_image_in_collection(dst, c, i)? {
+            continue;
+        }
+        summary.links_added += 1;
+        if !input.dry_run {
+            repository::add_image_to_collection(
+                dst,
+                &NewCollectionImage {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    collection_id: c.clone(),
+                    image_id: i.clone(),
+                },
+            )
+            ?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// List the collections in another Astra database so the user can pick what to merge
+#[tauri::command]
+pub fn get_merge_source_collections(
+    state: State<'_, AppState>,
+    source_path: String,
+) -> Result<Vec<MergeSourceCollection>, String> {
+    let mut staged = StagedDatabase::open(Path::new(&source_path))?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let collections = repository::get_all_collections(&mut staged.conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+    let mut result = Vec::with_capacity(collections.len());
+    for collection in collections {
+        let image_count = repository::get_collection_image_count(&mut staged.conn, &collection.id)
+            .map_err(|e| e.to_string())?;
+        let exists = find_existing_collection(&mut conn, &state.user_id, &collection)
+            .map_err(|e| e.to_string())?
+            .is_some();
+        result.push(MergeSourceCollection {
+            id: collection.id,
+            name: collection.name,
+            template: collection.template,
+            image_count,
+            exists,
+        });
+    }
+    Ok(result)
+}
+
+/// Merge collections and images from another Astra database into this library.
+/// The merge runs in a single transaction; with `dryRun` nothing is written.
+#[tauri::command]
+pub fn merge_import_database(
+    state: State<'_, AppState>,
+    input: MergeImportInput,
+) -> Result<MergeSummary, String> {
+    let dates = parse_date_range(&input)?;
+    let mut staged = StagedDatabase::open(Path::new(&input.source_path))?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let summary = if input.dry_run {
+        merge_library(&mut staged.conn, &mut conn, &state.user_id, &input, dates)
+    } else {
+        conn.transaction(|dst| merge_library(&mut staged.conn, dst, &state.user_id, &input, dates))
+    }
+    .map_err(|e| format!("Merge import failed: {}", e))?;
+
+    log::info!(
+        "Merge import from {}: {} images added, {} updated, {} skipped, {} collections created",
+        input.source_path,
+        summary.images_added,
+        summary.images_updated,
+        summary.images_skipped,
+        summary.collections_created
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: &str = "local-user";
+
+    fn migrated_db() -> SqliteConnection {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    fn add_image(conn: &mut SqliteConnection, id: &str, blob_id: Option<&str>, date_obs: &str) {
+        repository::create_image(
+            conn,
+            &NewImage {
+                user_id: USER.to_string(),
+                filename: format!("{}.fits", id),
+                url: Some(format!("/data/{}.jpg", id)),
+                summary: Some("M42".to_string()),
+                metadata: Some(format!(r#"{{"date_obs": "{}"}}"#, date_obs)),
+                blob_id: blob_id.map(String::from),
+                date_obs: Some(date_obs.to_string()),
+                ..NewImage::test(id)
+            },
+        )
+        .unwrap();
+    }
+
+    fn add_collection(conn: &mut SqliteConnection, id: &str, name: &str, image_ids: &[&str]) {
+        repository::create_collection(
+            conn,
+            &NewCollection {
+                user_id: USER.to_string(),
+                name: name.to_string(),
+                template: Some("astrolog".to_string()),
+                ..NewCollection::test(id)
+            },
+        )
+        .unwrap();
+        for image_id in image_ids {
+            repository::add_image_to_collection(
+                conn,
+                &NewCollectionImage {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    collection_id: id.to_string(),
+                    image_id: image_id.to_string(),
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    fn merge(src: &mut SqliteConnection, dst: &mut SqliteConnection, input: &MergeImportInput) -> MergeSummary {
+        merge_library(src, dst, USER, input, parse_date_range(input).unwrap()).unwrap()
+    }
+
+    fn source_library() -> SqliteConnection {
+        let mut src = migrated_db();
+        add_image(&mut src, "a", Some("hash-a"), "2026-01-10T22:00:00");
+        add_image(&mut src, "b", Some("hash-b"), "2026-02-10T22:00:00");
+        add_collection(&mut src, "s1", "2026-01-10", &["a"]);
+        add_collection(&mut src, "s2", "2026-02-10", &["b"]);
+        src
+    }
+
+    #[test]
+    fn merge_dedupes_by_content_hash_and_matches_collections_by_name() {
+        let mut src = source_library();
+        repository::set_image_rating(&mut src, "b", Some(5)).unwrap();
+        // Taken at a location only the other library has
+        repository::set_image_capture_site(
+            &mut src,
+            USER,
+            &["b".to_string()],
+            Some("their-site"),
+            Some(51.5),
+            Some(-0.1),
+        )
+        .unwrap();
+        let mut dst = migrated_db();
+        // Same file already imported on this machine under a different id
+        add_image(&mut dst, "local-a", Some("hash-a"), "2026-01-10T22:00:00");
+        add_collection(&mut dst, "local-s1", "2026-01-10", &[]);
+
+        let input = MergeImportInput::default();
+        let summary = merge(&mut src, &mut dst, &input);
+
+        assert_eq!(summary.images_added, 1);
+        assert_eq!(summary.images_skipped, 1);
+        assert_eq!(summary.collections_matched, 1);
+        assert_eq!(summary.collections_created, 1);
+        assert_eq!(summary.links_added, 2);
+        assert!(repository::is_image_in_collection(&mut dst, "local-s1", "local-a").unwrap());
+        assert!(repository::is_image_in_collection(&mut dst, "s2", "b").unwrap());
+        let merged = repository::get_image_by_id(&mut dst, "b").unwrap().unwrap();
+        assert_eq!(merged.rating, Some(5));
+        assert_eq!(merged.capture_site_id, None);
+        assert_eq!(
+            (merged.capture_latitude, merged.capture_longitude),
+            (Some(51.5), Some(-0.1))
+        );
+
+        // Merging again is a no-op
+        let again = merge(&mut src, &mut dst, &input);
+        assert_eq!(again.images_added, 0);
+        assert_eq!(again.links_added, 0);
+    }
+
+    #[test]
+    fn merge_respects_date_range_and_dry_run() {
+        let mut src = source_library();
+        let mut dst = migrated_db();
+
+        let input = MergeImportInput {
+            date_from: Some("2026-02-01".to_string()),
+            dry_run: true,
+            ..Default::default()
+        };
+        let summary = merge(&mut src, &mut dst, &input);
+        assert_eq!(summary.images_added, 1);
+        assert_eq!(summary.collections_created, 1);
+        assert!(repository::get_images_by_user(&mut dst, USER).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_overwrite_policy_updates_existing_image() {
+        let mut src = source_library();
+        repository::set_image_rating(&mut src, "a", Some(3)).unwrap();
+        repository::set_image_capture_site(
+            &mut src,
+            USER,
+            &["a".to_string()],
+            Some("home"),
+            Some(40.0),
+            Some(-105.0),
+        )
+        .unwrap();
+        let mut dst = migrated_db();
+        // Both libraries have the same saved location
+        repository::create_location(
+            &mut dst,
+            &crate::db::models::NewLocation {
+                id: "home".to_string(),
+                user_id: USER.to_string(),
+                name: "Home".to_string(),
+                latitude: 40.0,
+                longitude: -105.0,
+                elevation: None,
+                bortle: None,
+                source: "manual".to_string(),
+                is_default: true,
+            },
+        )
+        .unwrap();
+        add_image(&mut dst, "a", Some("hash-a"), "2026-01-10T22:00:00");
+        repository::update_image(
+            &mut dst,
+            "a",
+            &UpdateImage {
+                summary: Some("Old".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let input = MergeImportInput {
+            collection_ids: Some(vec!["s1".to_string()]),
+            conflict_policy: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        let summary = merge(&mut src, &mut dst, &input);
+        assert_eq!(summary.images_updated, 1);
+        assert_eq!(summary.images_added, 0);
+        let image = repository::get_image_by_id(&mut dst, "a").unwrap().unwrap();
+        assert_eq!(image.summary.as_deref(), Some("M42"));
+        assert_eq!(image.rating, Some(3));
+        assert_eq!(image.capture_site_id.as_deref(), Some("home"));
+    }
+}
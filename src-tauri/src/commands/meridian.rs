@@ -0,0 +1,320 @@
+//! Meridian transit, meridian flip and polar alignment helpers
+//!
+//! These only need sidereal time, so unlike the altitude calculations they
+//! are done here rather than through the Python sidecar. Coordinates are
+//! J2000 and precessed to the date before use.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::astronomy::LocationInput;
+
+/// Sidereal degrees per solar day (the rate hour angles increase)
+const SIDEREAL_RATE: f64 = 360.985_647_366_29;
+const J2000: f64 = 2_451_545.0;
+
+/// Pole stars for polar scope alignment (name, J2000 RA and Dec in degrees)
+const POLARIS: (&str, f64, f64) = ("Polaris", 37.954_542, 89.264_111);
+const SIGMA_OCTANTIS: (&str, f64, f64) = ("Sigma Octantis", 317.195_250, -88.956_500);
+
+fn julian_date(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5
+}
+
+/// Greenwich mean sidereal time in degrees
+pub fn gmst_deg(time: DateTime<Utc>) -> f64 {
+    let d = julian_date(time) - J2000;
+    let t = d / 36_525.0;
+    (280.460_618_37 + SIDEREAL_RATE * d + 0.000_387_933 * t * t - t * t * t / 38_710_000.0)
+        .rem_euclid(360.0)
+}
+
+/// Precess J2000 coordinates (degrees) to the equinox of `time` (IAU 1976)
+pub fn precess_from_j2000(ra_deg: f64, dec_deg: f64, time: DateTime<Utc>) -> (f64, f64) {
+    let t = (julian_date(time) - J2000) / 36_525.0;
+    let arcsec = |a: f64, b: f64, c: f64| ((a + (b + c * t) * t) * t / 3600.0).to_radians();
+    let zeta = arcsec(2306.2181, 0.30188, 0.017998);
+    let z = arcsec(2306.2181, 1.09468, 0.018203);
+    let theta = arcsec(2004.3109, -0.42665, -0.041833);
+
+    let (ra, dec) = (ra_deg.to_radians() + zeta, dec_deg.to_radians());
+    let a = dec.cos() * ra.sin();
+    let b = theta.cos() * dec.cos() * ra.cos() - theta.sin() * dec.sin();
+    let c = theta.sin() * dec.cos() * ra.cos() + theta.cos() * dec.sin();
+    (
+        (a.atan2(b) + z).to_degrees().rem_euclid(360.0),
+        c.clamp(-1.0, 1.0).asin().to_degrees(),
+    )
+}
+
+/// Local hour angle in degrees, -180..180 (negative east of the meridian)
+pub fn hour_angle_deg(ra_deg: f64, longitude: f64, time: DateTime<Utc>) -> f64 {
+    (gmst_deg(time) + longitude - ra_deg + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// First time at or after `after` that the hour angle reaches `target_deg`
+fn next_hour_angle(
+    ra_deg: f64,
+    longitude: f64,
+    after: DateTime<Utc>,
+    target_deg: f64,
+) -> DateTime<Utc> {
+    let to_go = (target_deg - hour_angle_deg(ra_deg, longitude, after)).rem_euclid(360.0);
+    after + Duration::milliseconds((to_go / SIDEREAL_RATE * 86_400_000.0).round() as i64)
+}
+
+/// Next upper transit of a J2000 position at or after `after`
+pub fn next_transit(
+    ra_deg: f64,
+    dec_deg: f64,
+    longitude: f64,
+    after: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let (ra, _) = precess_from_j2000(ra_deg, dec_deg, after);
+    next_hour_angle(ra, longitude, after, 0.0)
+}
+
+/// Altitude at upper transit; negative when the object stays below the
+/// horizon there
+pub fn transit_altitude(dec_deg: f64, latitude: f64) -> f64 {
+    90.0 - (latitude - dec_deg).abs()
+}
+
+/// Next time at or after `after` that a J2000 position sinks to
+/// `altitude_deg`. None for objects that never get that low; objects that
+/// never get that high are treated as already set.
+pub fn next_setting(
+    ra_deg: f64,
+    dec_deg: f64,
+    latitude: f64,
+    longitude: f64,
+    after: DateTime<Utc>,
+    altitude_deg: f64,
+) -> Option<DateTime<Utc>> {
+    let (ra, dec) = precess_from_j2000(ra_deg, dec_deg, after);
+    let (lat, dec) = (latitude.to_radians(), dec.to_radians());
+    let cos_h = (altitude_deg.to_radians().sin() - lat.sin() * dec.sin()) / (lat.cos() * dec.cos());
+    if cos_h < -1.0 {
+        return None;
+    }
+    if cos_h > 1.0 {
+        return Some(after);
+    }
+    Some(next_hour_angle(
+        ra,
+        longitude,
+        after,
+        cos_h.acos().to_degrees(),
+    ))
+}
+
+/// First meridian flip at or after `after`, for a mount that flips
+/// `delay_minutes` after the target crosses the meridian
+pub fn next_flip(
+    ra_deg: f64,
+    dec_deg: f64,
+    longitude: f64,
+    after: DateTime<Utc>,
+    delay_minutes: f64,
+) -> DateTime<Utc> {
+    let delay = Duration::milliseconds((delay_minutes * 60_000.0).round() as i64);
+    next_transit(ra_deg, dec_deg, longitude, after - delay) + delay
+}
+
+/// Parse an RFC 3339 time, defaulting to now
+fn parse_time(time: Option<&str>) -> Result<DateTime<Utc>, String> {
+    match time {
+        Some(time) => DateTime::parse_from_rfc3339(time)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid time '{}': {}", time, e)),
+        None => Ok(Utc::now()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeridianTransit {
+    pub time: DateTime<Utc>,
+    /// Hours, negative while the target is still rising in the east
+    pub hour_angle: f64,
+    pub previous_transit: DateTime<Utc>,
+    pub next_transit: DateTime<Utc>,
+    /// Degrees; negative when the target is below the horizon at transit
+    pub transit_altitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeridianFlip {
+    pub time: DateTime<Utc>,
+    /// Transit that the next flip follows (in the past while the mount is
+    /// tracking past the meridian)
+    pub transit_time: DateTime<Utc>,
+    pub flip_time: DateTime<Utc>,
+    pub remaining_seconds: i64,
+    /// Target has crossed the meridian but the flip is still to come
+    pub past_meridian: bool,
+    pub hour_angle: f64,
+    pub transit_altitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolarAlignment {
+    pub time: DateTime<Utc>,
+    /// Polaris in the northern hemisphere, Sigma Octantis in the southern
+    pub star: String,
+    /// Precessed to the date, in degrees
+    pub ra: f64,
+    pub dec: f64,
+    /// Hours, 0..24
+    pub hour_angle: f64,
+    /// Where the star sits around the pole on a 12-hour clock face as seen
+    /// through an inverting polar scope (12 at the top)
+    pub clock_position: f64,
+    pub pole_distance_arcmin: f64,
+}
+
+/// When a target crosses the meridian, around `time` (default now)
+#[tauri::command]
+pub fn get_meridian_transit(
+    ra_deg: f64,
+    dec_deg: f64,
+    location: LocationInput,
+    time: Option<String>,
+) -> Result<MeridianTransit, String> {
+    let time = parse_time(time.as_deref())?;
+    let (ra, dec) = precess_from_j2000(ra_deg, dec_deg, time);
+    let next_transit = next_transit(ra_deg, dec_deg, location.longitude, time);
+    let previous_transit =
+        next_transit - Duration::milliseconds((86_400_000.0 * 360.0 / SIDEREAL_RATE) as i64);
+
+    Ok(MeridianTransit {
+        time,
+        hour_angle: hour_angle_deg(ra, location.longitude, time) / 15.0,
+        previous_transit,
+        next_transit,
+        transit_altitude: transit_altitude(dec, location.latitude),
+    })
+}
+
+/// Time left before the mount has to flip, for a session tracking a target.
+/// `flip_delay_minutes` is how long the mount tracks past the meridian
+/// before flipping (default 0).
+#[tauri::command]
+pub fn get_meridian_flip(
+    ra_deg: f64,
+    dec_deg: f64,
+    location: LocationInput,
+    time: Option<String>,
+    flip_delay_minutes: Option<f64>,
+) -> Result<MeridianFlip, String> {
+    let time = parse_time(time.as_deref())?;
+    let delay = flip_delay_minutes.unwrap_or(0.0).max(0.0);
+    let (ra, dec) = precess_from_j2000(ra_deg, dec_deg, time);
+    let flip_time = next_flip(ra_deg, dec_deg, location.longitude, time, delay);
+    let transit_time = flip_time - Duration::milliseconds((delay * 60_000.0).round() as i64);
+
+    Ok(MeridianFlip {
+        time,
+        transit_time,
+        flip_time,
+        remaining_seconds: (flip_time - time).num_seconds(),
+        past_meridian: transit_time <= time,
+        hour_angle: hour_angle_deg(ra, location.longitude, time) / 15.0,
+        transit_altitude: transit_altitude(dec, location.latitude),
+    })
+}
+
+/// Pole star position for aligning a polar scope at `time` (default now)
+#[tauri::command]
+pub fn get_polar_alignment(
+    location: LocationInput,
+    time: Option<String>,
+) -> Result<PolarAlignment, String> {
+    let time = parse_time(time.as_deref())?;
+    let north = location.latitude >= 0.0;
+    let (star, ra, dec) = if north { POLARIS } else { SIGMA_OCTANTIS };
+    let (ra, dec) = precess_from_j2000(ra, dec, time);
+    let hour_angle = hour_angle_deg(ra, location.longitude, time).rem_euclid(360.0) / 15.0;
+
+    // Stars circle the north pole anticlockwise and the south pole clockwise;
+    // the scope turns the view upside down
+    let naked_eye = if north {
+        -hour_angle / 2.0
+    } else {
+        hour_angle / 2.0
+    };
+
+    Ok(PolarAlignment {
+        time,
+        star: star.to_string(),
+        ra,
+        dec,
+        hour_angle,
+        clock_position: (naked_eye + 6.0).rem_euclid(12.0),
+        pole_distance_arcmin: (90.0 - dec.abs()) * 60.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn sidereal_time_matches_reference() {
+        // Meeus, example 12.a: 13h10m46.3668s on 1987-04-10 0h UT
+        let gmst = gmst_deg(utc("1987-04-10T00:00:00Z"));
+        assert!((gmst - 197.693_195).abs() < 1e-4);
+    }
+
+    #[test]
+    fn precesses_polaris() {
+        // Polaris at the 2025.0 equinox: 3h 04m, +89° 22′
+        let (ra, dec) = precess_from_j2000(POLARIS.1, POLARIS.2, utc("2025-01-01T00:00:00Z"));
+        assert!((ra / 15.0 - 3.07).abs() < 0.01, "ra {}", ra / 15.0);
+        assert!((dec - 89.37).abs() < 0.01, "dec {}", dec);
+    }
+
+    #[test]
+    fn flip_follows_transit() {
+        let now = utc("2025-01-15T20:00:00Z");
+        let (ra, dec, lon) = (83.82, -5.39, -2.0);
+        let transit = next_transit(ra, dec, lon, now);
+        assert!(transit >= now && transit - now < Duration::hours(24));
+        let (apparent, _) = precess_from_j2000(ra, dec, transit);
+        assert!(hour_angle_deg(apparent, lon, transit).abs() < 0.01);
+
+        // While tracking past the meridian the pending flip is the last
+        // transit's
+        let after = transit + Duration::minutes(5);
+        let flip = next_flip(ra, dec, lon, after, 10.0);
+        assert!(
+            (flip - (transit + Duration::minutes(10)))
+                .num_seconds()
+                .abs()
+                <= 1
+        );
+    }
+
+    #[test]
+    fn setting_is_where_the_hour_angle_meets_the_altitude() {
+        let now = utc("2025-01-15T18:00:00Z");
+        let (lat, lon) = (51.5, -0.1);
+        // M42 from London sets roughly 6h after transit
+        let set = next_setting(83.82, -5.39, lat, lon, now, 0.0).unwrap();
+        let transit = next_transit(83.82, -5.39, lon, now);
+        let hours = (set - transit).num_minutes() as f64 / 60.0;
+        assert!((hours - 5.6).abs() < 0.3, "{} h after transit", hours);
+
+        // Circumpolar at the horizon, but not above 60°
+        assert_eq!(next_setting(POLARIS.1, POLARIS.2, lat, lon, now, 0.0), None);
+        assert_eq!(next_setting(83.82, -5.39, lat, lon, now, 60.0), Some(now));
+    }
+}
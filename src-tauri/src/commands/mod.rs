@@ -1,36 +1,151 @@
 //! Tauri command handlers for Astra
 
+pub mod annotate;
+pub mod api_server;
+pub mod attention;
 pub mod astronomy;
 pub mod auto_import;
 pub mod backup;
+pub mod capture_site;
+pub mod collection_files;
+pub mod collection_naming;
 pub mod collections;
+pub mod compare;
+pub mod dark_sites;
+pub mod date_backfill;
+pub mod equipment;
+pub mod export;
+pub mod exposure_plan;
+pub mod favorites;
+pub mod frame_quality;
+pub mod filters;
+pub mod frame_rules;
+pub mod framing;
+pub mod gallery_export;
+pub mod image_bundle;
+pub mod import_journal;
+pub mod import_memory;
 pub mod image_process;
 pub mod images;
+pub mod job_metrics;
+pub mod library_health;
 pub mod library_scan;
+pub mod locations;
+pub mod logs;
+pub mod maintenance;
+pub mod meridian;
+pub mod merge_import;
+pub mod network;
+pub mod night_digest;
+pub mod nova_jobs;
+pub mod notes;
+pub mod object_names;
+pub mod open_with;
+pub mod packing;
+pub mod path_remap;
 pub mod plate_solve;
+pub mod presentation;
+pub mod preview_cache;
+pub mod python_env;
+pub mod quick_search;
+pub mod saved_searches;
 pub mod scan;
 pub mod schedules;
+pub mod session_report;
+pub mod sessions;
+pub mod settings;
+pub mod sidecars;
+pub mod siril_script;
+pub mod sky_brightness;
+pub mod sky_cutout;
 pub mod skymap;
+pub mod solar_system;
+pub mod stacking_set;
+pub mod startup;
+pub mod subframes;
+pub mod sync;
+pub mod tag_rules;
+pub mod target_profile;
+pub mod target_types;
 pub mod targets;
 pub mod tetra3_db;
+pub mod timelapse;
+pub mod triage;
+pub mod ui_preferences;
 pub mod hoardfs;
 pub mod share;
 pub mod todos;
 
 // Re-export all commands
+pub use annotate::*;
+pub use api_server::*;
+pub use attention::*;
 pub use astronomy::*;
 pub use auto_import::*;
 pub use backup::*;
+pub use capture_site::*;
+pub use collection_files::*;
+pub use collection_naming::*;
 pub use collections::*;
+pub use compare::*;
+pub use dark_sites::*;
+pub use date_backfill::*;
+pub use equipment::*;
+pub use export::*;
+pub use exposure_plan::*;
+pub use favorites::*;
+pub use filters::*;
+pub use frame_quality::*;
+pub use frame_rules::*;
+pub use framing::*;
+pub use gallery_export::*;
 pub use hoardfs::*;
+pub use image_bundle::*;
+pub use import_memory::*;
 pub use image_process::*;
 pub use images::*;
+pub use job_metrics::*;
+pub use library_health::*;
 pub use library_scan::*;
+pub use locations::*;
+pub use logs::*;
+pub use maintenance::*;
+pub use meridian::*;
+pub use merge_import::*;
+pub use network::*;
+pub use night_digest::*;
+pub use nova_jobs::*;
+pub use notes::*;
+pub use object_names::*;
+pub use open_with::*;
+pub use packing::*;
+pub use path_remap::*;
 pub use plate_solve::*;
+pub use presentation::*;
+pub use preview_cache::*;
+pub use python_env::*;
+pub use quick_search::*;
+pub use saved_searches::*;
 pub use scan::*;
 pub use schedules::*;
+pub use session_report::*;
+pub use sessions::*;
+pub use sidecars::*;
+pub use siril_script::*;
 pub use share::*;
+pub use sky_brightness::*;
+pub use sky_cutout::*;
 pub use skymap::*;
+pub use solar_system::*;
+pub use stacking_set::*;
+pub use startup::*;
+pub use subframes::*;
+pub use sync::*;
+pub use tag_rules::*;
+pub use target_profile::*;
 pub use targets::*;
 pub use tetra3_db::*;
+pub use timelapse::*;
+pub use triage::*;
+pub use ui_preferences::*;
 pub use todos::*;
@@ -0,0 +1,77 @@
+//! Network settings: offline mode and per-provider rate limits
+
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::network::{Network, NetworkSettings};
+use crate::state::AppState;
+
+use super::settings::{load_settings_in, save_settings, NETWORK};
+
+/// Cached responses, under the app cache dir
+const CACHE_DIR: &str = "network";
+
+/// Longest spacing that can be set between requests to one provider
+const MAX_INTERVAL_MS: u64 = 60_000;
+const MAX_RETRIES: u32 = 10;
+
+/// Network settings saved in `data_dir`, for running without the app
+pub(crate) fn network_settings_in(data_dir: &Path) -> NetworkSettings {
+    load_settings_in(data_dir, &NETWORK)
+}
+
+/// Network service with the saved settings, for the app state
+pub fn load_network(app: &AppHandle) -> Network {
+    let settings = app
+        .path()
+        .app_data_dir()
+        .map(|d| network_settings_in(&d))
+        .unwrap_or_default();
+    let cache_dir = app.path().app_cache_dir().ok().map(|d| d.join(CACHE_DIR));
+    Network::new(settings, cache_dir)
+}
+
+#[tauri::command]
+pub fn get_network_settings(state: State<'_, AppState>) -> Result<NetworkSettings, String> {
+    Ok(state.network.settings())
+}
+
+/// Save network settings; None restores the defaults
+#[tauri::command]
+pub fn set_network_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: Option<NetworkSettings>,
+) -> Result<NetworkSettings, String> {
+    let settings = settings.unwrap_or_default();
+    for (provider, limit) in &settings.limits {
+        if limit.min_interval_ms > MAX_INTERVAL_MS || limit.max_retries > MAX_RETRIES {
+            return Err(format!(
+                "{} limits must be at most {} ms apart and {} retries",
+                provider.name(),
+                MAX_INTERVAL_MS,
+                MAX_RETRIES
+            ));
+        }
+    }
+
+    save_settings(&app, &NETWORK, &settings)?;
+    state.network.set_settings(settings.clone());
+    Ok(settings)
+}
+
+/// Turn offline mode on or off, keeping the other settings
+#[tauri::command]
+pub fn set_offline_mode(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    offline: bool,
+) -> Result<NetworkSettings, String> {
+    let mut settings = state.network.settings();
+    settings.offline = offline;
+    save_settings(&app, &NETWORK, &settings)?;
+    state.network.set_settings(settings.clone());
+    log::info!("Offline mode {}", if offline { "on" } else { "off" });
+    Ok(settings)
+}
@@ -0,0 +1,873 @@
+//! Nightly go/no-go digest
+//!
+//! Each afternoon the forecast for tonight's dark hours, the moon and the
+//! open items on tonight's schedules are weighed up into a verdict (go,
+//! marginal or no-go) with the reasons for it. The digest is stored per
+//! night in `session_digests`, emitted as "night-digest" for the UI and
+//! published to the activity feed.
+//!
+//! Forecasts come from Open-Meteo; darkness and the moon from the night
+//! chart. Cloud is judged over the scheduled slots when there are any, since
+//! a clear evening doesn't help a target planned for 3am.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::models::{Location, NewSessionDigest, ScheduleItem, SessionDigest};
+use crate::db::repository;
+use crate::events;
+use crate::network::{Network, Provider};
+use crate::python::altitude::{self, NightChart, ObserverLocation};
+use crate::state::AppState;
+
+use super::schedules::parse_item_time;
+use super::settings::{load_settings, save_settings, NIGHT_DIGEST};
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+/// How often the background evaluator checks whether tonight's digest is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Local hour tonight's digest is evaluated from
+const DEFAULT_HOUR: u32 = 15;
+const DEFAULT_HISTORY: i64 = 30;
+
+/// Cloud cover (%) at which a night is marginal, and no-go
+const MARGINAL_CLOUD: f64 = 35.0;
+const NO_GO_CLOUD: f64 = 75.0;
+/// Chance of precipitation (%)
+const MARGINAL_PRECIPITATION: f64 = 30.0;
+const NO_GO_PRECIPITATION: f64 = 60.0;
+/// Wind speed (km/h)
+const MARGINAL_WIND: f64 = 25.0;
+const NO_GO_WIND: f64 = 40.0;
+/// Relative humidity (%) at which to expect dew
+const DEW_HUMIDITY: f64 = 95.0;
+/// Moon illumination that washes out broadband imaging when it's up for at
+/// least half the dark hours
+const BRIGHT_MOON: f64 = 0.75;
+/// Less darkness than this isn't worth setting up for
+const MIN_DARK_HOURS: f64 = 1.0;
+
+static SCHEDULER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    Go,
+    Marginal,
+    NoGo,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Go => "go",
+            Verdict::Marginal => "marginal",
+            Verdict::NoGo => "no-go",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Verdict::Go => "Go",
+            Verdict::Marginal => "Marginal",
+            Verdict::NoGo => "No-go",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NightDigestConfig {
+    /// Evaluate tonight automatically each afternoon
+    pub enabled: bool,
+    /// Local hour (0-23) from which tonight is evaluated
+    pub hour: u32,
+    /// Location to evaluate; the default location when unset
+    pub location_id: Option<String>,
+}
+
+impl Default for NightDigestConfig {
+    fn default() -> Self {
+        NightDigestConfig {
+            enabled: true,
+            hour: DEFAULT_HOUR,
+            location_id: None,
+        }
+    }
+}
+
+/// Conditions over tonight's dark hours
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NightConditions {
+    /// Hours of astronomical darkness, or of astronomical twilight on nights
+    /// that never get fully dark
+    pub dark_hours: f64,
+    /// Dark hours the forecast covers
+    pub forecast_hours: f64,
+    /// Mean cloud cover (%) over the dark hours
+    pub cloud_cover: Option<f64>,
+    /// Dark hours forecast to be mostly clear
+    pub clear_hours: f64,
+    pub max_precipitation_probability: Option<f64>,
+    /// km/h
+    pub max_wind_speed: Option<f64>,
+    pub max_humidity: Option<f64>,
+    /// Illuminated fraction of the moon
+    pub moon_illumination: f64,
+    /// Dark hours with the moon above the horizon
+    pub moon_up_hours: f64,
+    /// Mean cloud cover (%) over the open schedule slots
+    pub scheduled_cloud_cover: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NightDigest {
+    /// Local date the evening starts on (YYYY-MM-DD)
+    pub night: String,
+    pub verdict: Verdict,
+    /// One line, for notifications
+    pub summary: String,
+    /// What holds the night back from a clear go, worst first
+    pub reasons: Vec<String>,
+    pub location_id: String,
+    pub location_name: String,
+    /// Dark hours (RFC 3339, UTC)
+    pub dark_start: Option<String>,
+    pub dark_end: Option<String>,
+    pub conditions: NightConditions,
+    /// Open items on the night's active schedules
+    pub scheduled_targets: Vec<String>,
+    pub evaluated_at: String,
+}
+
+/// One hour of forecast, starting at `time`
+#[derive(Debug, Clone, PartialEq)]
+struct ForecastHour {
+    time: DateTime<Utc>,
+    cloud_cover: Option<f64>,
+    humidity: Option<f64>,
+    wind_speed: Option<f64>,
+    precipitation_probability: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    hourly: HourlyForecast,
+}
+
+/// Open-Meteo hourly series; times are UTC without an offset
+#[derive(Debug, Deserialize)]
+struct HourlyForecast {
+    time: Vec<String>,
+    #[serde(default)]
+    cloud_cover: Vec<Option<f64>>,
+    #[serde(default)]
+    relative_humidity_2m: Vec<Option<f64>>,
+    #[serde(default)]
+    wind_speed_10m: Vec<Option<f64>>,
+    #[serde(default)]
+    precipitation_probability: Vec<Option<f64>>,
+}
+
+impl HourlyForecast {
+    fn hours(&self) -> Vec<ForecastHour> {
+        let at = |series: &[Option<f64>], i: usize| series.get(i).copied().flatten();
+        self.time
+            .iter()
+            .enumerate()
+            .filter_map(|(i, time)| {
+                let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok()?;
+                Some(ForecastHour {
+                    time: time.and_utc(),
+                    cloud_cover: at(&self.cloud_cover, i),
+                    humidity: at(&self.relative_humidity_2m, i),
+                    wind_speed: at(&self.wind_speed_10m, i),
+                    precipitation_probability: at(&self.precipitation_probability, i),
+                })
+            })
+            .collect()
+    }
+}
+
+type Span = (DateTime<Utc>, DateTime<Utc>);
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Hours of `start`..`end` that fall inside `spans`
+fn overlap_hours(start: DateTime<Utc>, end: DateTime<Utc>, spans: &[Span]) -> f64 {
+    spans
+        .iter()
+        .map(|&(span_start, span_end)| {
+            let seconds = (end.min(span_end) - start.max(span_start)).num_seconds();
+            seconds.max(0) as f64 / 3600.0
+        })
+        .sum()
+}
+
+/// The night's dark hours: astronomical darkness, or astronomical twilight
+/// when it never gets fully dark (summer at high latitudes)
+fn dark_spans(chart: &NightChart) -> Vec<Span> {
+    let darkness: Vec<Span> = chart
+        .darkness
+        .iter()
+        .filter_map(|i| Some((parse_time(&i.start)?, parse_time(&i.end)?)))
+        .collect();
+    if !darkness.is_empty() {
+        return darkness;
+    }
+    chart
+        .twilight
+        .iter()
+        .filter(|i| i.phase == "astronomical")
+        .filter_map(|i| Some((parse_time(&i.start)?, parse_time(&i.end)?)))
+        .collect()
+}
+
+/// Dark hours with the moon up, from the chart's moon track
+fn moon_up_hours(chart: &NightChart, dark: &[Span]) -> f64 {
+    chart
+        .moon
+        .windows(2)
+        .filter(|pair| pair[0].altitude > 0.0)
+        .filter_map(|pair| {
+            let start = parse_time(&pair[0].time)?;
+            let end = parse_time(&pair[1].time)?;
+            Some(overlap_hours(start, end, dark))
+        })
+        .sum()
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ForecastSummary {
+    hours: f64,
+    cloud_cover: Option<f64>,
+    clear_hours: f64,
+    max_precipitation_probability: Option<f64>,
+    max_wind_speed: Option<f64>,
+    max_humidity: Option<f64>,
+}
+
+/// The forecast over `spans`, each hour weighted by how much of it they cover
+fn summarize_forecast(forecast: &[ForecastHour], spans: &[Span]) -> ForecastSummary {
+    let mut summary = ForecastSummary::default();
+    let mut cloud_sum = 0.0;
+    let mut cloud_hours = 0.0;
+    let max = |current: Option<f64>, value: Option<f64>| match (current, value) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    };
+
+    for hour in forecast {
+        let weight = overlap_hours(hour.time, hour.time + chrono::Duration::hours(1), spans);
+        if weight <= 0.0 {
+            continue;
+        }
+        summary.hours += weight;
+        if let Some(cloud) = hour.cloud_cover {
+            cloud_sum += cloud * weight;
+            cloud_hours += weight;
+            if cloud < MARGINAL_CLOUD {
+                summary.clear_hours += weight;
+            }
+        }
+        summary.max_precipitation_probability = max(
+            summary.max_precipitation_probability,
+            hour.precipitation_probability,
+        );
+        summary.max_wind_speed = max(summary.max_wind_speed, hour.wind_speed);
+        summary.max_humidity = max(summary.max_humidity, hour.humidity);
+    }
+    summary.cloud_cover = (cloud_hours > 0.0).then(|| cloud_sum / cloud_hours);
+    summary
+}
+
+fn night_conditions(
+    chart: &NightChart,
+    dark: &[Span],
+    forecast: &[ForecastHour],
+    slots: &[Span],
+) -> NightConditions {
+    let night = summarize_forecast(forecast, dark);
+    let scheduled = summarize_forecast(forecast, slots);
+    NightConditions {
+        dark_hours: dark.iter().map(|&(s, e)| overlap_hours(s, e, dark)).sum(),
+        forecast_hours: night.hours,
+        cloud_cover: night.cloud_cover,
+        clear_hours: night.clear_hours,
+        max_precipitation_probability: night.max_precipitation_probability,
+        max_wind_speed: night.max_wind_speed,
+        max_humidity: night.max_humidity,
+        moon_illumination: chart.moon_illumination,
+        moon_up_hours: moon_up_hours(chart, dark),
+        scheduled_cloud_cover: scheduled.cloud_cover,
+    }
+}
+
+/// The verdict and the reasons for it, worst first
+fn classify(conditions: &NightConditions) -> (Verdict, Vec<String>) {
+    let mut reasons: Vec<(Verdict, String)> = Vec::new();
+    let mut flag = |verdict: Verdict, reason: String| reasons.push((verdict, reason));
+    let grade = |value: f64, marginal: f64, no_go: f64| {
+        if value >= no_go {
+            Some(Verdict::NoGo)
+        } else if value >= marginal {
+            Some(Verdict::Marginal)
+        } else {
+            None
+        }
+    };
+
+    if conditions.dark_hours < MIN_DARK_HOURS {
+        flag(
+            Verdict::NoGo,
+            format!("Only {:.1} h of darkness", conditions.dark_hours),
+        );
+    }
+    if conditions.forecast_hours <= 0.0 {
+        flag(
+            Verdict::Marginal,
+            "No forecast for tonight's dark hours".to_string(),
+        );
+    }
+
+    let cloud = match (conditions.scheduled_cloud_cover, conditions.cloud_cover) {
+        (Some(cloud), _) => Some((cloud, "during the scheduled slots")),
+        (None, Some(cloud)) => Some((cloud, "after dark")),
+        (None, None) => None,
+    };
+    if let Some((cloud, when)) = cloud {
+        if let Some(verdict) = grade(cloud, MARGINAL_CLOUD, NO_GO_CLOUD) {
+            flag(verdict, format!("{:.0}% cloud {}", cloud, when));
+        }
+    }
+    if let Some(chance) = conditions.max_precipitation_probability {
+        if let Some(verdict) = grade(chance, MARGINAL_PRECIPITATION, NO_GO_PRECIPITATION) {
+            flag(verdict, format!("{:.0}% chance of rain", chance));
+        }
+    }
+    if let Some(wind) = conditions.max_wind_speed {
+        if let Some(verdict) = grade(wind, MARGINAL_WIND, NO_GO_WIND) {
+            flag(verdict, format!("Wind up to {:.0} km/h", wind));
+        }
+    }
+    if conditions.max_humidity.is_some_and(|h| h >= DEW_HUMIDITY) {
+        flag(
+            Verdict::Marginal,
+            "Humidity high enough for dew".to_string(),
+        );
+    }
+    if conditions.moon_illumination >= BRIGHT_MOON
+        && conditions.moon_up_hours >= conditions.dark_hours / 2.0
+        && conditions.dark_hours > 0.0
+    {
+        flag(
+            Verdict::Marginal,
+            format!(
+                "{:.0}% moon up for {:.1} of {:.1} dark hours",
+                conditions.moon_illumination * 100.0,
+                conditions.moon_up_hours,
+                conditions.dark_hours
+            ),
+        );
+    }
+
+    // Stable, so equally bad reasons keep the order above
+    reasons.sort_by_key(|(verdict, _)| std::cmp::Reverse(*verdict));
+    let verdict = reasons.first().map_or(Verdict::Go, |(verdict, _)| *verdict);
+    (
+        verdict,
+        reasons.into_iter().map(|(_, reason)| reason).collect(),
+    )
+}
+
+fn summary_line(verdict: Verdict, conditions: &NightConditions, reasons: &[String]) -> String {
+    match reasons.first() {
+        Some(reason) => format!("{}: {}", verdict.label(), reason),
+        None => format!(
+            "{}: {:.1} h dark, {:.0}% cloud, {:.0}% moon",
+            verdict.label(),
+            conditions.dark_hours,
+            conditions
+                .scheduled_cloud_cover
+                .or(conditions.cloud_cover)
+                .unwrap_or(0.0),
+            conditions.moon_illumination * 100.0
+        ),
+    }
+}
+
+/// The night in progress or coming up: until local noon it's still last
+/// night
+fn night_of(now: NaiveDateTime) -> NaiveDate {
+    let date = now.date();
+    if now.hour() < 12 {
+        date.pred_opt().unwrap_or(date)
+    } else {
+        date
+    }
+}
+
+fn parse_night(night: Option<&str>) -> Result<NaiveDate, String> {
+    match night {
+        Some(night) => NaiveDate::parse_from_str(night.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid night '{}'", night)),
+        None => Ok(night_of(Local::now().naive_local())),
+    }
+}
+
+/// Three days of hourly forecast, so a cached response still covers tonight
+/// when offline the next day
+fn forecast_url(latitude: f64, longitude: f64) -> String {
+    format!(
+        "{}?latitude={:.3}&longitude={:.3}&hourly=cloud_cover,relative_humidity_2m,wind_speed_10m,precipitation_probability&forecast_days=3&timezone=GMT",
+        FORECAST_URL, latitude, longitude
+    )
+}
+
+async fn fetch_forecast(
+    network: &Network,
+    latitude: f64,
+    longitude: f64,
+) -> Result<Vec<ForecastHour>, String> {
+    let url = forecast_url(latitude, longitude);
+    let response: ForecastResponse = network.get_json(Provider::Weather, &url).await?;
+    Ok(response.hourly.hours())
+}
+
+/// The location to evaluate: the given one, else the default
+fn digest_location(
+    conn: &mut diesel::SqliteConnection,
+    user_id: &str,
+    location_id: Option<&str>,
+) -> Result<Option<Location>, String> {
+    match location_id {
+        Some(id) => repository::get_location_by_id(conn, id)
+            .map_err(|e| e.to_string())?
+            .map(Some)
+            .ok_or_else(|| format!("Location not found: {}", id)),
+        None => repository::get_default_location(conn, user_id).map_err(|e| e.to_string()),
+    }
+}
+
+/// Open items on the night's active schedules
+fn scheduled_items(
+    conn: &mut diesel::SqliteConnection,
+    user_id: &str,
+    night: &str,
+) -> Result<Vec<ScheduleItem>, String> {
+    let schedules = repository::get_active_schedules_for_night(conn, user_id, night)
+        .map_err(|e| e.to_string())?;
+    Ok(schedules
+        .iter()
+        .flat_map(|s| serde_json::from_str::<Vec<ScheduleItem>>(&s.items).unwrap_or_default())
+        .filter(|item| !item.completed)
+        .collect())
+}
+
+fn stored_digest(digest: SessionDigest) -> Option<NightDigest> {
+    serde_json::from_str(&digest.details)
+        .map_err(|e| log::warn!("Invalid digest for {}: {}", digest.night, e))
+        .ok()
+}
+
+/// Evaluate a night, store its digest and announce it
+async fn evaluate(
+    app: &AppHandle,
+    night: NaiveDate,
+    location: Location,
+) -> Result<NightDigest, String> {
+    let state = app.state::<AppState>();
+    let night = night.to_string();
+    let items = {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        scheduled_items(&mut conn, &state.user_id, &night)?
+    };
+
+    let observer = ObserverLocation {
+        latitude: location.latitude,
+        longitude: location.longitude,
+        elevation: location.elevation.unwrap_or(0.0),
+        name: Some(location.name.clone()),
+    };
+    let date = night.clone();
+    // Only the sun and moon are used, so the target is the celestial pole
+    let chart = tokio::task::spawn_blocking(move || {
+        altitude::get_night_chart(0.0, 90.0, &observer, Some(&date), None, None)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let forecast = fetch_forecast(&state.network, location.latitude, location.longitude).await?;
+
+    let slots: Vec<Span> = items
+        .iter()
+        .filter_map(|item| {
+            let start = parse_item_time(&item.start_time)?;
+            let end = parse_item_time(&item.end_time)?;
+            (end > start).then_some((start, end))
+        })
+        .collect();
+    let dark = dark_spans(&chart);
+    let conditions = night_conditions(&chart, &dark, &forecast, &slots);
+    let (verdict, reasons) = classify(&conditions);
+
+    let digest = NightDigest {
+        summary: summary_line(verdict, &conditions, &reasons),
+        night,
+        verdict,
+        reasons,
+        location_id: location.id,
+        location_name: location.name,
+        dark_start: dark.first().map(|(start, _)| start.to_rfc3339()),
+        dark_end: dark.last().map(|(_, end)| end.to_rfc3339()),
+        conditions,
+        scheduled_targets: items.into_iter().map(|item| item.object_name).collect(),
+        evaluated_at: Utc::now().to_rfc3339(),
+    };
+
+    let record = NewSessionDigest {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        night: digest.night.clone(),
+        verdict: digest.verdict.as_str().to_string(),
+        summary: digest.summary.clone(),
+        details: serde_json::to_string(&digest).map_err(|e| e.to_string())?,
+    };
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::save_session_digest(&mut conn, &record).map_err(|e| e.to_string())?;
+
+    let _ = app.emit(events::NIGHT_DIGEST, &digest);
+    events::publish(events::NIGHT_DIGEST, &digest);
+    Ok(digest)
+}
+
+/// Evaluate tonight if it's past the configured hour and not done yet
+async fn evaluate_if_due(app: &AppHandle) {
+    let config: NightDigestConfig = load_settings(app, &NIGHT_DIGEST).unwrap_or_default();
+    let now = Local::now().naive_local();
+    if !config.enabled || now.hour() < config.hour {
+        return;
+    }
+    let night = now.date();
+
+    let state = app.state::<AppState>();
+    let location = {
+        let Ok(mut conn) = state.db.get() else {
+            return;
+        };
+        let done = repository::get_session_digest(&mut conn, &state.user_id, &night.to_string());
+        if !matches!(done, Ok(None)) {
+            return;
+        }
+        digest_location(&mut conn, &state.user_id, config.location_id.as_deref())
+    };
+    // Nothing to evaluate until there's a location
+    let location = match location {
+        Ok(Some(location)) => location,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Night digest skipped: {}", e);
+            return;
+        }
+    };
+
+    match evaluate(app, night, location).await {
+        Ok(digest) => log::info!("Night digest for {}: {}", digest.night, digest.summary),
+        // Tried again at the next check
+        Err(e) => log::warn!("Night digest for {} failed: {}", night, e),
+    }
+}
+
+/// Start the background evaluator; called once the database is open
+pub fn start_scheduler(app: &AppHandle) {
+    if SCHEDULER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            evaluate_if_due(&app).await;
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// The stored digest for a night (default tonight), if it has been evaluated
+#[tauri::command]
+pub fn get_night_digest(
+    state: State<'_, AppState>,
+    night: Option<String>,
+) -> Result<Option<NightDigest>, String> {
+    let night = parse_night(night.as_deref())?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let digest = repository::get_session_digest(&mut conn, &state.user_id, &night.to_string())
+        .map_err(|e| e.to_string())?;
+    Ok(digest.and_then(stored_digest))
+}
+
+/// Recent digests, newest night first
+#[tauri::command]
+pub fn get_night_digests(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+) -> Result<Vec<NightDigest>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let digests = repository::get_session_digests(
+        &mut conn,
+        &state.user_id,
+        limit.unwrap_or(DEFAULT_HISTORY).max(1),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(digests.into_iter().filter_map(stored_digest).collect())
+}
+
+/// Evaluate a night (default tonight) now, at a location (default the
+/// configured one), replacing its stored digest
+#[tauri::command]
+pub async fn evaluate_night_digest(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    night: Option<String>,
+    location_id: Option<String>,
+) -> Result<NightDigest, String> {
+    let night = parse_night(night.as_deref())?;
+    let location_id = location_id.or_else(|| {
+        load_settings::<NightDigestConfig>(&app, &NIGHT_DIGEST)
+            .ok()?
+            .location_id
+    });
+    let location = {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        digest_location(&mut conn, &state.user_id, location_id.as_deref())?
+    }
+    .ok_or_else(|| "No observing location; add one or set a default".to_string())?;
+    evaluate(&app, night, location).await
+}
+
+#[tauri::command]
+pub fn get_night_digest_config(app: AppHandle) -> Result<NightDigestConfig, String> {
+    load_settings(&app, &NIGHT_DIGEST)
+}
+
+#[tauri::command]
+pub fn set_night_digest_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    config: NightDigestConfig,
+) -> Result<NightDigestConfig, String> {
+    if config.hour > 23 {
+        return Err("Hour must be between 0 and 23".to_string());
+    }
+    if let Some(id) = &config.location_id {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        digest_location(&mut conn, &state.user_id, Some(id))?;
+    }
+    save_settings(&app, &NIGHT_DIGEST, &config)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::python::altitude::{AltitudePoint, TimeInterval, TwilightInterval};
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        parse_time(time).unwrap()
+    }
+
+    fn hour(time: &str, cloud: f64) -> ForecastHour {
+        ForecastHour {
+            time: utc(time),
+            cloud_cover: Some(cloud),
+            humidity: Some(70.0),
+            wind_speed: Some(10.0),
+            precipitation_probability: Some(0.0),
+        }
+    }
+
+    fn clear_night() -> NightConditions {
+        NightConditions {
+            dark_hours: 8.0,
+            forecast_hours: 8.0,
+            cloud_cover: Some(10.0),
+            clear_hours: 8.0,
+            max_precipitation_probability: Some(0.0),
+            max_wind_speed: Some(10.0),
+            max_humidity: Some(70.0),
+            moon_illumination: 0.1,
+            moon_up_hours: 1.0,
+            scheduled_cloud_cover: None,
+        }
+    }
+
+    #[test]
+    fn nights_start_at_local_noon() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(night_of(at("2025-01-10 15:00")), date("2025-01-10"));
+        assert_eq!(night_of(at("2025-01-11 02:30")), date("2025-01-10"));
+        assert_eq!(night_of(at("2025-01-11 12:00")), date("2025-01-11"));
+    }
+
+    #[test]
+    fn open_meteo_hours_parse_with_gaps() {
+        let json = r#"{"hourly":{
+            "time":["2025-01-10T20:00","2025-01-10T21:00","bad"],
+            "cloud_cover":[5,null,50],
+            "relative_humidity_2m":[80,85,90],
+            "wind_speed_10m":[12.5,14.0,3.0]
+        }}"#;
+        let response: ForecastResponse = serde_json::from_str(json).unwrap();
+        let hours = response.hourly.hours();
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].time, utc("2025-01-10T20:00:00Z"));
+        assert_eq!(hours[0].cloud_cover, Some(5.0));
+        assert_eq!(hours[1].cloud_cover, None);
+        assert_eq!(hours[1].precipitation_probability, None);
+    }
+
+    #[test]
+    fn forecast_is_weighted_by_dark_time() {
+        let forecast = [
+            hour("2025-01-10T17:00:00Z", 100.0),
+            hour("2025-01-10T18:00:00Z", 0.0),
+            hour("2025-01-10T19:00:00Z", 60.0),
+        ];
+        // Dark from 18:30 to 20:00: half of the clear hour, all of the 60% one
+        let dark = [(utc("2025-01-10T18:30:00Z"), utc("2025-01-10T20:00:00Z"))];
+        let summary = summarize_forecast(&forecast, &dark);
+        assert_eq!(summary.hours, 1.5);
+        assert!((summary.cloud_cover.unwrap() - 40.0).abs() < 1e-9);
+        assert_eq!(summary.clear_hours, 0.5);
+        assert_eq!(
+            summarize_forecast(&forecast, &[]),
+            ForecastSummary::default()
+        );
+    }
+
+    #[test]
+    fn darkness_falls_back_to_astronomical_twilight() {
+        let point = |time: &str, altitude: f64| AltitudePoint {
+            time: time.to_string(),
+            altitude,
+            azimuth: 0.0,
+            compass_direction: "N".to_string(),
+        };
+        let mut chart = NightChart {
+            start: "2025-06-20T19:00:00+00:00".to_string(),
+            end: "2025-06-21T05:00:00+00:00".to_string(),
+            sunset: None,
+            sunrise: None,
+            min_altitude: 30.0,
+            target: Vec::new(),
+            moon: vec![
+                point("2025-06-20T23:00:00+00:00", 5.0),
+                point("2025-06-21T00:00:00+00:00", -2.0),
+                point("2025-06-21T01:00:00+00:00", -5.0),
+            ],
+            moon_illumination: 0.9,
+            twilight: vec![
+                TwilightInterval {
+                    start: "2025-06-20T22:30:00+00:00".to_string(),
+                    end: "2025-06-21T00:30:00+00:00".to_string(),
+                    phase: "astronomical".to_string(),
+                },
+                TwilightInterval {
+                    start: "2025-06-21T00:30:00+00:00".to_string(),
+                    end: "2025-06-21T02:00:00+00:00".to_string(),
+                    phase: "nautical".to_string(),
+                },
+            ],
+            darkness: Vec::new(),
+            imaging_windows: Vec::new(),
+        };
+        let dark = dark_spans(&chart);
+        assert_eq!(
+            dark,
+            vec![(utc("2025-06-20T22:30:00Z"), utc("2025-06-21T00:30:00Z"))]
+        );
+        assert_eq!(moon_up_hours(&chart, &dark), 1.0);
+
+        chart.darkness = vec![TimeInterval {
+            start: "2025-06-20T23:30:00+00:00".to_string(),
+            end: "2025-06-21T00:00:00+00:00".to_string(),
+        }];
+        assert_eq!(dark_spans(&chart).len(), 1);
+        assert_eq!(moon_up_hours(&chart, &dark_spans(&chart)), 0.5);
+    }
+
+    #[test]
+    fn classification_takes_the_worst_reason() {
+        let (verdict, reasons) = classify(&clear_night());
+        assert_eq!(verdict, Verdict::Go);
+        assert!(reasons.is_empty());
+        assert_eq!(
+            summary_line(verdict, &clear_night(), &reasons),
+            "Go: 8.0 h dark, 10% cloud, 10% moon"
+        );
+
+        let bright = NightConditions {
+            moon_illumination: 0.95,
+            moon_up_hours: 6.0,
+            max_humidity: Some(97.0),
+            ..clear_night()
+        };
+        let (verdict, reasons) = classify(&bright);
+        assert_eq!(verdict, Verdict::Marginal);
+        assert_eq!(reasons.len(), 2);
+
+        let stormy = NightConditions {
+            cloud_cover: Some(50.0),
+            max_wind_speed: Some(45.0),
+            ..clear_night()
+        };
+        let (verdict, reasons) = classify(&stormy);
+        assert_eq!(verdict, Verdict::NoGo);
+        assert_eq!(reasons[0], "Wind up to 45 km/h");
+        assert_eq!(reasons[1], "50% cloud after dark");
+        assert_eq!(
+            summary_line(verdict, &stormy, &reasons),
+            "No-go: Wind up to 45 km/h"
+        );
+    }
+
+    #[test]
+    fn scheduled_slots_decide_the_cloud_verdict() {
+        let late_clear = NightConditions {
+            cloud_cover: Some(80.0),
+            scheduled_cloud_cover: Some(5.0),
+            ..clear_night()
+        };
+        assert_eq!(classify(&late_clear).0, Verdict::Go);
+
+        let no_forecast = NightConditions {
+            forecast_hours: 0.0,
+            cloud_cover: None,
+            ..clear_night()
+        };
+        assert_eq!(classify(&no_forecast).0, Verdict::Marginal);
+
+        let summer = NightConditions {
+            dark_hours: 0.5,
+            ..clear_night()
+        };
+        assert_eq!(classify(&summer).0, Verdict::NoGo);
+        assert_eq!(
+            serde_json::to_value(Verdict::NoGo).unwrap(),
+            serde_json::json!("no-go")
+        );
+    }
+}
@@ -0,0 +1,475 @@
+//! Markdown observation notes on images, collections, sessions, targets and
+//! equipment
+//!
+//! Notes are keyed by entity type and id. Sessions have no record of their
+//! own, so their notes are keyed by the night (YYYY-MM-DD); targets are keyed
+//! by canonical object name, the same way pins are, so a note made on "M42"
+//! shows up on "M 42". Attachments are copied into the app data dir, one
+//! folder per note, and listed on the note as JSON.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::{NewNote, Note, UpdateNote};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::object_names::ObjectNames;
+use super::share::mime_for_path;
+
+/// Attachment folders, under the app data dir
+const ATTACHMENTS_DIR: &str = "note-attachments";
+const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// What a note is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteEntity {
+    Image,
+    Collection,
+    Session,
+    Target,
+    Equipment,
+}
+
+impl NoteEntity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoteEntity::Image => "image",
+            NoteEntity::Collection => "collection",
+            NoteEntity::Session => "session",
+            NoteEntity::Target => "target",
+            NoteEntity::Equipment => "equipment",
+        }
+    }
+}
+
+/// A file attached to a note, stored in the note's attachment folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteAttachment {
+    pub id: String,
+    /// Original file name
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+    pub added_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateNoteInput {
+    pub entity_type: NoteEntity,
+    pub entity_id: String,
+    pub body: String,
+    /// Files to copy in as attachments
+    pub attachments: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateNoteInput {
+    pub id: String,
+    pub body: String,
+}
+
+/// First line of a note's markdown, without heading markers, for lists and
+/// search results
+pub(crate) fn note_title(body: &str) -> String {
+    body.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn parse_attachments(note: &Note) -> Vec<NoteAttachment> {
+    note.attachments
+        .as_deref()
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+fn attachments_json(attachments: &[NoteAttachment]) -> Result<Option<String>, String> {
+    if attachments.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(attachments)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Stored name of an attachment: its id, with the original extension if it
+/// has a plain one
+fn attachment_file_name(attachment: &NoteAttachment) -> String {
+    let ext = Path::new(&attachment.file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(str::to_lowercase);
+    match ext {
+        Some(ext) => format!("{}.{}", attachment.id, ext),
+        None => attachment.id.clone(),
+    }
+}
+
+fn note_dir(app: &AppHandle, note_id: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join(ATTACHMENTS_DIR).join(note_id))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Copy a file into a note's attachment folder
+fn copy_attachment(dir: &Path, source: &str) -> Result<NoteAttachment, String> {
+    let source = Path::new(source);
+    let metadata =
+        std::fs::metadata(source).map_err(|e| format!("Cannot read {:?}: {}", source, e))?;
+    if !metadata.is_file() {
+        return Err(format!("Not a file: {:?}", source));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "Attachments are limited to {} MB",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+    let attachment = NoteAttachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        file_name: source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "attachment".to_string()),
+        content_type: mime_for_path(source).to_string(),
+        size: metadata.len(),
+        added_at: chrono::Utc::now().to_rfc3339(),
+    };
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    std::fs::copy(source, dir.join(attachment_file_name(&attachment)))
+        .map_err(|e| format!("Failed to copy {:?}: {}", source, e))?;
+    Ok(attachment)
+}
+
+fn remove_note_dir(dir: &Path) {
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            log::warn!("Failed to remove note attachments {:?}: {}", dir, e);
+        }
+    }
+}
+
+/// The id a note on `entity_id` is stored under, checking that the entity
+/// exists: the night for sessions, the canonical name for targets
+fn resolve_entity_id(
+    conn: &mut SqliteConnection,
+    entity: NoteEntity,
+    entity_id: &str,
+) -> Result<String, String> {
+    let entity_id = entity_id.trim();
+    if entity_id.is_empty() {
+        return Err(format!("Missing {} for note", entity.as_str()));
+    }
+    let exists = match entity {
+        NoteEntity::Image => repository::get_image_by_id(conn, entity_id)
+            .map_err(|e| e.to_string())?
+            .is_some(),
+        NoteEntity::Collection => repository::get_collection_by_id(conn, entity_id)
+            .map_err(|e| e.to_string())?
+            .is_some(),
+        NoteEntity::Equipment => repository::get_equipment_by_id(conn, entity_id)
+            .map_err(|e| e.to_string())?
+            .is_some(),
+        NoteEntity::Session => {
+            return NaiveDate::parse_from_str(entity_id, "%Y-%m-%d")
+                .map(|night| night.to_string())
+                .map_err(|_| format!("Invalid session night '{}'", entity_id));
+        }
+        NoteEntity::Target => {
+            let names = ObjectNames::load(conn).map_err(|e| e.to_string())?;
+            return Ok(names.canonical(entity_id));
+        }
+    };
+    if exists {
+        Ok(entity_id.to_string())
+    } else {
+        Err(format!("{} not found: {}", entity.as_str(), entity_id))
+    }
+}
+
+fn get_existing_note(conn: &mut SqliteConnection, id: &str) -> Result<Note, String> {
+    repository::get_note_by_id(conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Note not found: {}", id))
+}
+
+/// Remove the notes on a deleted entity, with their attachments
+pub(crate) fn delete_entity_notes(
+    app: &AppHandle,
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    entity: NoteEntity,
+    entity_id: &str,
+) {
+    match repository::delete_notes_for_entity(conn, user_id, entity.as_str(), entity_id) {
+        Ok(ids) => {
+            for id in ids {
+                if let Ok(dir) = note_dir(app, &id) {
+                    remove_note_dir(&dir);
+                }
+            }
+        }
+        Err(e) => log::warn!(
+            "Failed to remove notes on {} {}: {}",
+            entity.as_str(),
+            entity_id,
+            e
+        ),
+    }
+}
+
+/// Notes on an entity, newest first. Target notes are matched by canonical
+/// name, so notes made under any of a target's names are included.
+#[tauri::command]
+pub fn get_notes(
+    state: State<'_, AppState>,
+    entity_type: NoteEntity,
+    entity_id: String,
+) -> Result<Vec<Note>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    if entity_type != NoteEntity::Target {
+        return repository::get_notes_for_entity(
+            &mut conn,
+            &state.user_id,
+            entity_type.as_str(),
+            entity_id.trim(),
+        )
+        .map_err(|e| e.to_string());
+    }
+
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    let canonical = names.canonical(entity_id.trim());
+    let notes = repository::get_notes_by_type(&mut conn, &state.user_id, entity_type.as_str())
+        .map_err(|e| e.to_string())?;
+    Ok(notes
+        .into_iter()
+        .filter(|note| names.canonical(&note.entity_id) == canonical)
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_note(state: State<'_, AppState>, id: String) -> Result<Option<Note>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_note_by_id(&mut conn, &id).map_err(|e| e.to_string())
+}
+
+/// Add a note, copying in any attachments
+#[tauri::command]
+pub fn create_note(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    input: CreateNoteInput,
+) -> Result<Note, String> {
+    let paths = input.attachments.unwrap_or_default();
+    if input.body.trim().is_empty() && paths.is_empty() {
+        return Err("Note cannot be empty".to_string());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let entity_id = resolve_entity_id(&mut conn, input.entity_type, &input.entity_id)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dir = note_dir(&app, &id)?;
+    let attachments = paths
+        .iter()
+        .map(|path| copy_attachment(&dir, path))
+        .collect::<Result<Vec<_>, _>>()
+        .inspect_err(|_| remove_note_dir(&dir))?;
+
+    let new_note = NewNote {
+        id,
+        user_id: state.user_id.clone(),
+        entity_type: input.entity_type.as_str().to_string(),
+        entity_id,
+        body: input.body,
+        attachments: attachments_json(&attachments)?,
+    };
+    repository::create_note(&mut conn, &new_note).map_err(|e| {
+        remove_note_dir(&dir);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn update_note(state: State<'_, AppState>, input: UpdateNoteInput) -> Result<Note, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    get_existing_note(&mut conn, &input.id)?;
+    let update = UpdateNote {
+        body: Some(input.body),
+        ..Default::default()
+    };
+    repository::update_note(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+/// Delete a note and its attachments
+#[tauri::command]
+pub fn delete_note(app: AppHandle, state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let deleted = repository::delete_note(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())?;
+    if deleted {
+        remove_note_dir(&note_dir(&app, &id)?);
+    }
+    Ok(deleted)
+}
+
+/// Copy a file onto an existing note
+#[tauri::command]
+pub fn add_note_attachment(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    note_id: String,
+    path: String,
+) -> Result<Note, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let note = get_existing_note(&mut conn, &note_id)?;
+    let dir = note_dir(&app, &note_id)?;
+
+    let mut attachments = parse_attachments(&note);
+    let attachment = copy_attachment(&dir, &path)?;
+    let stored = dir.join(attachment_file_name(&attachment));
+    attachments.push(attachment);
+
+    let update = UpdateNote {
+        attachments: Some(attachments_json(&attachments)?),
+        ..Default::default()
+    };
+    repository::update_note(&mut conn, &note_id, &update).map_err(|e| {
+        let _ = std::fs::remove_file(&stored);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn remove_note_attachment(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    note_id: String,
+    attachment_id: String,
+) -> Result<Note, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let note = get_existing_note(&mut conn, &note_id)?;
+
+    let mut attachments = parse_attachments(&note);
+    let index = attachments
+        .iter()
+        .position(|a| a.id == attachment_id)
+        .ok_or_else(|| format!("Attachment not found: {}", attachment_id))?;
+    let removed = attachments.remove(index);
+
+    let update = UpdateNote {
+        attachments: Some(attachments_json(&attachments)?),
+        ..Default::default()
+    };
+    let updated =
+        repository::update_note(&mut conn, &note_id, &update).map_err(|e| e.to_string())?;
+
+    let path = note_dir(&app, &note_id)?.join(attachment_file_name(&removed));
+    if let Err(e) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove note attachment {:?}: {}", path, e);
+    }
+    Ok(updated)
+}
+
+/// Path of an attachment's file, for opening or previewing it
+#[tauri::command]
+pub fn get_note_attachment_path(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    note_id: String,
+    attachment_id: String,
+) -> Result<String, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let note = get_existing_note(&mut conn, &note_id)?;
+    let attachment = parse_attachments(&note)
+        .into_iter()
+        .find(|a| a.id == attachment_id)
+        .ok_or_else(|| format!("Attachment not found: {}", attachment_id))?;
+    let path = note_dir(&app, &note_id)?.join(attachment_file_name(&attachment));
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Notes containing `query`, optionally on one type of entity, most
+/// recently changed first
+#[tauri::command]
+pub fn search_notes(
+    state: State<'_, AppState>,
+    query: String,
+    entity_type: Option<NoteEntity>,
+) -> Result<Vec<Note>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::search_notes(
+        &mut conn,
+        &state.user_id,
+        query,
+        entity_type.map(NoteEntity::as_str),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_skip_blank_lines_and_heading_markers() {
+        assert_eq!(note_title("\n## Seeing  \nPoor after midnight"), "Seeing");
+        assert_eq!(note_title("Dew on the corrector"), "Dew on the corrector");
+        assert_eq!(note_title("  \n"), "");
+    }
+
+    #[test]
+    fn attachments_round_trip_and_keep_plain_extensions() {
+        let attachment = |file_name: &str| NoteAttachment {
+            id: "a1".to_string(),
+            file_name: file_name.to_string(),
+            content_type: "image/jpeg".to_string(),
+            size: 10,
+            added_at: String::new(),
+        };
+        assert_eq!(attachment_file_name(&attachment("Guide Log.JPG")), "a1.jpg");
+        assert_eq!(attachment_file_name(&attachment("notes")), "a1");
+        assert_eq!(attachment_file_name(&attachment("x.t?t")), "a1");
+
+        let json = attachments_json(&[attachment("flat.png")]).unwrap();
+        assert!(json
+            .as_deref()
+            .unwrap()
+            .contains("\"fileName\":\"flat.png\""));
+        assert_eq!(attachments_json(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn copied_attachments_record_type_and_size() {
+        let dir = std::env::temp_dir().join(format!("astra-notes-{}", uuid::Uuid::new_v4()));
+        let source = std::env::temp_dir().join(format!("{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&source, b"not really a png").unwrap();
+
+        let attachment = copy_attachment(&dir, source.to_str().unwrap()).unwrap();
+        assert_eq!(attachment.content_type, "image/png");
+        assert_eq!(attachment.size, 16);
+        assert!(dir.join(attachment_file_name(&attachment)).is_file());
+        assert!(copy_attachment(&dir, dir.to_str().unwrap()).is_err());
+
+        let _ = std::fs::remove_file(&source);
+        remove_note_dir(&dir);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,395 @@
+//! Asynchronous nova.astrometry.net solves
+//!
+//! `plate_solve_image` with the "nova" solver waits for the job to finish,
+//! which can take a long time when the nova queue is busy. Instead,
+//! `submit_nova_solve` uploads the image, records the submission under
+//! "nova_job" in the image's metadata and returns straight away. A background
+//! poller checks the pending jobs until each is solved or fails, then records
+//! the result as `plate_solve_image` would. Jobs outlive the app: startup
+//! calls `resume_pending_solves` to pick them up again.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::models::{Image, UpdateImage};
+use crate::db::repository;
+use crate::events;
+use crate::python::plate_solve::PlateSolveResult;
+use crate::solvers::nova::{self, JobStatus, SubmitOptions};
+use crate::state::AppState;
+
+use super::plate_solve::{record_solve_result, resolve_hints, PlateSolveInput, ResolvedHints};
+
+/// Time between checks on pending jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// Jobs still unsolved this long after submission are given up on
+const JOB_TIMEOUT_HOURS: i64 = 24;
+
+/// Set while the poller task is running, so there's only ever one
+static POLLER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// A submitted solve, stored under "nova_job" in the image's metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NovaJob {
+    pub submission_id: u64,
+    /// Set once nova has started a job for the submission
+    pub job_id: Option<u64>,
+    /// API base URL the image was submitted to
+    pub api_url: String,
+    pub submitted_at: DateTime<Utc>,
+    /// Catalog query options applied once solved
+    pub query_catalogs: bool,
+    pub catalogs: Option<Vec<String>>,
+    pub star_mag_limit: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NovaJobStatus {
+    pub image_id: String,
+    pub filename: String,
+    pub submission_id: u64,
+    pub job_id: Option<u64>,
+    /// "queued" until nova starts a job, then "solving"
+    pub status: String,
+    pub submitted_at: String,
+}
+
+impl NovaJobStatus {
+    fn new(image: &Image, job: &NovaJob) -> Self {
+        NovaJobStatus {
+            image_id: image.id.clone(),
+            filename: image.filename.clone(),
+            submission_id: job.submission_id,
+            job_id: job.job_id,
+            status: if job.job_id.is_some() {
+                "solving"
+            } else {
+                "queued"
+            }
+            .to_string(),
+            submitted_at: job.submitted_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Emitted as "nova-job-finished" when a pending job is solved or fails
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NovaJobFinished {
+    pub image_id: String,
+    pub success: bool,
+    pub error_message: Option<String>,
+}
+
+/// Pending job recorded in an image's metadata
+pub(super) fn nova_job(metadata: Option<&str>) -> Option<NovaJob> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get("nova_job")?.clone()).ok()
+}
+
+/// Metadata JSON with the pending job set, or removed when `job` is None
+fn with_nova_job(metadata: Option<&str>, job: Option<&NovaJob>) -> Option<String> {
+    let mut value = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    let object = value.as_object_mut()?;
+    match job {
+        Some(job) => {
+            object.insert("nova_job".to_string(), serde_json::to_value(job).ok()?);
+        }
+        None => {
+            object.remove("nova_job");
+        }
+    }
+    Some(value.to_string())
+}
+
+fn save_nova_job(state: &AppState, image: &Image, job: Option<&NovaJob>) -> Result<(), String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let update = UpdateImage {
+        metadata: with_nova_job(image.metadata.as_deref(), job),
+        ..Default::default()
+    };
+    repository::update_image(&mut conn, &image.id, &update)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Upload an image to nova.astrometry.net (or `apiUrl`) and return without
+/// waiting for the solve. The result is recorded on the image when the job
+/// finishes, and "nova-job-finished" is emitted.
+#[tauri::command]
+pub async fn submit_nova_solve(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    input: PlateSolveInput,
+) -> Result<NovaJobStatus, String> {
+    let api_key = input
+        .api_key
+        .clone()
+        .filter(|key| !key.trim().is_empty())
+        .ok_or_else(|| "API key required for nova.astrometry.net".to_string())?;
+
+    let (image, hints) = {
+        let mut conn = state.db.get().map_err(|e| e.to_string())?;
+        let image = repository::get_image_by_id(&mut conn, &input.id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Image not found: {}", input.id))?;
+        if let Some(job) = nova_job(image.metadata.as_deref()) {
+            return Err(format!(
+                "{} is already waiting on astrometry.net (submission {})",
+                image.filename, job.submission_id
+            ));
+        }
+        let hints = resolve_hints(
+            &mut conn,
+            &state.user_id,
+            &image,
+            &input,
+            &mut Default::default(),
+        );
+        (image, hints)
+    };
+
+    let file_path = image
+        .url
+        .clone()
+        .ok_or_else(|| "Image has no file path".to_string())?;
+    if !Path::new(&file_path).exists() {
+        return Err(format!("Image file not found: {}", file_path));
+    }
+
+    let ResolvedHints {
+        scale_lower,
+        scale_upper,
+        hint_ra,
+        hint_dec,
+    } = hints;
+    let options = SubmitOptions {
+        scale_lower,
+        scale_upper,
+        hint_ra,
+        hint_dec,
+        search_radius: input.hint_radius,
+    };
+    let api_url = nova::api_base(input.api_url.as_deref());
+    let submission_id = nova::submit(
+        &state.network,
+        &api_url,
+        api_key.trim(),
+        Path::new(&file_path),
+        &options,
+    )
+    .await?;
+
+    let job = NovaJob {
+        submission_id,
+        job_id: None,
+        api_url,
+        submitted_at: Utc::now(),
+        query_catalogs: input.query_catalogs.unwrap_or(true),
+        catalogs: input.catalogs,
+        star_mag_limit: input.star_mag_limit,
+    };
+    save_nova_job(&state, &image, Some(&job))?;
+    start_poller(&app);
+    Ok(NovaJobStatus::new(&image, &job))
+}
+
+/// Solves waiting on astrometry.net, oldest first
+#[tauri::command]
+pub fn get_nova_jobs(state: State<'_, AppState>) -> Result<Vec<NovaJobStatus>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = repository::get_images_with_nova_jobs(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+    Ok(images
+        .iter()
+        .filter_map(|image| {
+            nova_job(image.metadata.as_deref()).map(|job| NovaJobStatus::new(image, &job))
+        })
+        .collect())
+}
+
+/// Pick up solves submitted before the app was last closed. Returns how
+/// many are pending.
+pub fn resume_pending_solves(app: &AppHandle) -> usize {
+    let pending = pending_jobs(app)
+        .map(|jobs| jobs.len())
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load pending astrometry.net jobs: {}", e);
+            0
+        });
+    if pending > 0 {
+        log::info!("Resuming {} pending astrometry.net solves", pending);
+        start_poller(app);
+    }
+    pending
+}
+
+fn pending_jobs(app: &AppHandle) -> Result<Vec<(Image, NovaJob)>, String> {
+    let state = app.state::<AppState>();
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = repository::get_images_with_nova_jobs(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())?;
+    Ok(images
+        .into_iter()
+        .filter_map(|image| {
+            let job = nova_job(image.metadata.as_deref())?;
+            Some((image, job))
+        })
+        .collect())
+}
+
+fn start_poller(app: &AppHandle) {
+    if POLLER_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if poll_jobs(&app).await > 0 {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+            POLLER_RUNNING.store(false, Ordering::SeqCst);
+            // A job submitted while the last poll finished would otherwise
+            // wait for the next start
+            let pending = pending_jobs(&app).map(|jobs| !jobs.is_empty());
+            if !pending.unwrap_or(false) || POLLER_RUNNING.swap(true, Ordering::SeqCst) {
+                break;
+            }
+        }
+    });
+}
+
+/// Check every pending job once. Returns how many are still pending.
+async fn poll_jobs(app: &AppHandle) -> usize {
+    let jobs = match pending_jobs(app) {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            log::warn!("Failed to load pending astrometry.net jobs: {}", e);
+            return 0;
+        }
+    };
+    let state = app.state::<AppState>();
+    let mut pending = 0;
+
+    for (image, job) in jobs {
+        let elapsed = Utc::now() - job.submitted_at;
+        let solve_time = elapsed.num_milliseconds() as f64 / 1000.0;
+        let status = nova::check(&state.network, &job.api_url, job.submission_id, solve_time).await;
+
+        let job_id = match status {
+            Ok(JobStatus::Finished(_, result)) => {
+                finish_job(app, image, job, result).await;
+                continue;
+            }
+            Ok(JobStatus::Queued) => None,
+            Ok(JobStatus::Solving(job_id)) => Some(job_id),
+            Err(e) => {
+                log::warn!(
+                    "Failed to check astrometry.net submission {}: {}",
+                    job.submission_id,
+                    e
+                );
+                job.job_id
+            }
+        };
+
+        if elapsed > chrono::Duration::hours(JOB_TIMEOUT_HOURS) {
+            let message = format!(
+                "Gave up on astrometry.net after {} hours",
+                JOB_TIMEOUT_HOURS
+            );
+            let result = crate::solvers::failure("nova", solve_time, &message);
+            finish_job(app, image, job, result).await;
+            continue;
+        }
+        if job_id.is_some() && job_id != job.job_id {
+            let job = NovaJob { job_id, ..job };
+            if let Err(e) = save_nova_job(&state, &image, Some(&job)) {
+                log::warn!("Failed to record astrometry.net job {:?}: {}", job_id, e);
+            }
+        }
+        pending += 1;
+    }
+    pending
+}
+
+/// Record a finished job's result on its image and drop the pending job
+async fn finish_job(app: &AppHandle, image: Image, job: NovaJob, result: PlateSolveResult) {
+    let db = app.state::<AppState>().db.clone();
+    let event = NovaJobFinished {
+        image_id: image.id.clone(),
+        success: result.success,
+        error_message: result.error_message.clone(),
+    };
+
+    let recorded = tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        // Reload in case the metadata changed while the job was pending
+        let mut image = repository::get_image_by_id(&mut conn, &image.id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Image not found: {}", image.id))?;
+        image.metadata = with_nova_job(image.metadata.as_deref(), None);
+        record_solve_result(
+            &mut conn,
+            &image,
+            &result,
+            job.query_catalogs,
+            job.catalogs,
+            job.star_mag_limit,
+        );
+        Ok::<_, String>(())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    if let Err(e) = recorded {
+        log::error!("Failed to record astrometry.net solve: {}", e);
+        return;
+    }
+
+    log::info!(
+        "astrometry.net submission {} finished for {}: {}",
+        job.submission_id,
+        event.image_id,
+        if event.success { "solved" } else { "failed" }
+    );
+    let _ = app.emit("nova-job-finished", &event);
+    events::publish(events::NOVA_JOB_FINISHED, &event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nova_job_round_trips_through_metadata() {
+        let job = NovaJob {
+            submission_id: 1234,
+            job_id: None,
+            api_url: nova::DEFAULT_API_URL.to_string(),
+            submitted_at: Utc::now(),
+            query_catalogs: true,
+            catalogs: None,
+            star_mag_limit: None,
+        };
+        let metadata = with_nova_job(Some(r#"{"EXPTIME":"10"}"#), Some(&job)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(value["EXPTIME"], "10");
+        assert_eq!(nova_job(Some(&metadata)).unwrap().submission_id, 1234);
+
+        let cleared = with_nova_job(Some(&metadata), None).unwrap();
+        assert!(nova_job(Some(&cleared)).is_none());
+        assert!(cleared.contains("EXPTIME"));
+        assert!(nova_job(None).is_none());
+    }
+}
@@ -0,0 +1,370 @@
+//! Object name normalization
+//!
+//! The same object turns up as "M 31", "M31", "NGC224" or "Andromeda
+//! Galaxy" depending on the capture software and who typed it. Names are
+//! resolved to one canonical name using the cross-identifications in the
+//! embedded deep sky catalogs (Messier designations win), then aliases
+//! recorded in `astro_objects`, which take precedence. Imports, new observing
+//! list entries and the target browser all go through this, and
+//! `merge_targets` records new aliases and renames existing records.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{AstroObject, NewAstroObject};
+use crate::db::repository;
+use crate::skymap;
+use crate::state::AppState;
+
+/// Spellings of catalog prefixes, longest first so "Messier" isn't read as
+/// "M" followed by junk
+const DESIGNATIONS: [(&str, &str); 16] = [
+    ("collinder", "Cr"),
+    ("caldwell", "C"),
+    ("barnard", "B"),
+    ("messier", "M"),
+    ("melotte", "Mel"),
+    ("sh 2", "Sh2"),
+    ("ngc", "NGC"),
+    ("ldn", "LDN"),
+    ("lbn", "LBN"),
+    ("sh2", "Sh2"),
+    ("mel", "Mel"),
+    ("ic", "IC"),
+    ("cr", "Cr"),
+    ("m", "M"),
+    ("b", "B"),
+    ("c", "C"),
+];
+
+/// A catalog designation in its usual form ("m31" and "Messier 31" become
+/// "M 31", "NGC0224" becomes "NGC 224", "sh2 155" becomes "Sh2-155"). Other
+/// names are only trimmed, with runs of spaces collapsed.
+pub fn normalize_designation(name: &str) -> String {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = name.to_lowercase();
+    for (prefix, canonical) in DESIGNATIONS {
+        let Some(rest) = lower.strip_prefix(prefix) else {
+            continue;
+        };
+        let rest = rest.trim_start_matches([' ', '-', '_']);
+        let digits = rest.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let suffix = &rest[digits.len()..];
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) || suffix.len() > 1 {
+            continue;
+        }
+        let number = digits.trim_start_matches('0');
+        let number = if number.is_empty() { "0" } else { number };
+        let separator = if canonical == "Sh2" { "-" } else { " " };
+        return format!(
+            "{}{}{}{}",
+            canonical,
+            separator,
+            number,
+            suffix.to_uppercase()
+        );
+    }
+    name
+}
+
+/// Lookup key: the normalized name without case, spaces or punctuation
+fn name_key(name: &str) -> String {
+    normalize_designation(name)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Canonical names for every name in the embedded catalogs, by key. An
+/// object with a Messier designation is known by it.
+fn catalog_names() -> &'static HashMap<String, String> {
+    static NAMES: OnceLock<HashMap<String, String>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut names = HashMap::new();
+        for object in skymap::deep_sky_objects() {
+            let all = || std::iter::once(&object.name).chain(&object.common_names);
+            let canonical = all()
+                .map(|name| normalize_designation(name))
+                .find(|name| name.starts_with("M "))
+                .unwrap_or_else(|| normalize_designation(&object.name));
+            for name in all() {
+                names
+                    .entry(name_key(name))
+                    .or_insert_with(|| canonical.clone());
+            }
+        }
+        names
+    })
+}
+
+/// Aliases recorded on an object, stored as a JSON array
+fn object_aliases(object: &AstroObject) -> Vec<String> {
+    object
+        .aliases
+        .as_deref()
+        .and_then(|a| serde_json::from_str(a).ok())
+        .unwrap_or_default()
+}
+
+/// Resolves object names to their canonical form
+#[derive(Debug, Default)]
+pub struct ObjectNames {
+    /// Canonical names from `astro_objects`, by key
+    recorded: HashMap<String, String>,
+    /// Aliases from `astro_objects`, by canonical name
+    aliases: HashMap<String, Vec<String>>,
+}
+
+impl ObjectNames {
+    pub fn load(conn: &mut SqliteConnection) -> QueryResult<Self> {
+        Ok(Self::from_objects(&repository::get_astro_objects(conn)?))
+    }
+
+    fn from_objects(objects: &[AstroObject]) -> Self {
+        let mut recorded = HashMap::new();
+        let mut aliases = HashMap::new();
+        for object in objects {
+            recorded.insert(name_key(&object.name), object.name.clone());
+            let names = object_aliases(object);
+            for alias in &names {
+                recorded.insert(name_key(alias), object.name.clone());
+            }
+            aliases.insert(object.name.clone(), names);
+        }
+        Self { recorded, aliases }
+    }
+
+    /// Canonical name for `name`; unknown names come back normalized
+    pub fn canonical(&self, name: &str) -> String {
+        let key = name_key(name);
+        if key.is_empty() {
+            return name.trim().to_string();
+        }
+        self.recorded
+            .get(&key)
+            .or_else(|| catalog_names().get(&key))
+            .cloned()
+            .unwrap_or_else(|| normalize_designation(name))
+    }
+
+    /// Known names of the object `name` refers to, canonical name first
+    pub fn aliases(&self, name: &str) -> Vec<String> {
+        let canonical = self.canonical(name);
+        let mut aliases = vec![canonical.clone()];
+        let mut add = |alias: &str| {
+            if !aliases.iter().any(|a| name_key(a) == name_key(alias)) {
+                aliases.push(alias.to_string());
+            }
+        };
+        for object in skymap::deep_sky_objects() {
+            let mut names = std::iter::once(&object.name).chain(&object.common_names);
+            if names.any(|n| self.canonical(n) == canonical) {
+                add(&object.name);
+                object.common_names.iter().for_each(|n| add(n));
+            }
+        }
+        self.aliases
+            .get(&canonical)
+            .into_iter()
+            .flatten()
+            .for_each(|alias| add(alias));
+        aliases
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectName {
+    pub name: String,
+    /// Every known name, the canonical one first
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeTargetsResult {
+    /// Name both targets are known by now
+    pub name: String,
+    pub images_updated: usize,
+    pub todos_updated: usize,
+}
+
+/// Make `alias` (and every other name of its object) another name of
+/// `name`, renaming images and observing list entries to match
+pub fn merge_object_names(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    name: &str,
+    alias: &str,
+) -> QueryResult<MergeTargetsResult> {
+    let names = ObjectNames::load(conn)?;
+    let canonical = names.canonical(name);
+    let merged = names.canonical(alias);
+
+    let existing = repository::get_astro_object_by_name(conn, &canonical)?;
+    let mut aliases = existing.as_ref().map(object_aliases).unwrap_or_default();
+    // All of the merged object's names, catalog ones included, come along
+    let mut renamed = names.aliases(alias);
+    renamed.push(alias.trim().to_string());
+    if merged != canonical {
+        repository::delete_astro_object(conn, &merged)?;
+    }
+    for alias in &renamed {
+        if name_key(alias) != name_key(&canonical) && !aliases.contains(alias) {
+            aliases.push(alias.clone());
+        }
+    }
+    repository::save_astro_object(
+        conn,
+        &NewAstroObject {
+            id: existing
+                .map(|o| o.id)
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: canonical.clone(),
+            display_name: canonical.clone(),
+            object_type: None,
+            seq: None,
+            aliases: Some(serde_json::to_string(&aliases).unwrap_or_default()),
+            notes: None,
+            metadata: None,
+        },
+    )?;
+
+    // Every spelling that now resolves to the merged object takes its name
+    let names = ObjectNames::load(conn)?;
+    let mut result = MergeTargetsResult {
+        name: canonical.clone(),
+        ..Default::default()
+    };
+    for target in repository::get_image_summaries(conn, user_id)? {
+        if target != canonical && names.canonical(&target) == canonical {
+            result.images_updated +=
+                repository::rename_image_target(conn, user_id, &target, &canonical)?;
+        }
+    }
+    let mut todos: Vec<String> = repository::get_todos(conn, user_id)?
+        .into_iter()
+        .map(|todo| todo.name)
+        .collect();
+    todos.sort();
+    todos.dedup();
+    for todo in todos {
+        if todo != canonical && names.canonical(&todo) == canonical {
+            result.todos_updated +=
+                repository::rename_todo_target(conn, user_id, &todo, &canonical)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Canonical name and known aliases of an object
+#[tauri::command]
+pub fn normalize_object_name(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<ObjectName, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    Ok(ObjectName {
+        name: names.canonical(&name),
+        aliases: names.aliases(&name),
+    })
+}
+
+/// Unify two targets: `nameB` becomes an alias of `nameA`, and images and
+/// observing list entries under either name are renamed to `nameA`'s
+/// canonical name
+#[tauri::command]
+pub fn merge_targets(
+    state: State<'_, AppState>,
+    name_a: String,
+    name_b: String,
+) -> Result<MergeTargetsResult, String> {
+    if name_a.trim().is_empty() || name_b.trim().is_empty() {
+        return Err("Target names can't be empty".to_string());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let result = conn
+        .transaction(|conn| merge_object_names(conn, &state.user_id, &name_a, &name_b))
+        .map_err(|e| format!("Failed to merge targets: {}", e))?;
+    log::info!(
+        "Merged target '{}' into '{}': {} images, {} todos renamed",
+        name_b,
+        result.name,
+        result.images_updated,
+        result.todos_updated
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewImage;
+
+    const USER: &str = "local-user";
+
+    #[test]
+    fn designations_are_normalized() {
+        assert_eq!(normalize_designation("m31"), "M 31");
+        assert_eq!(normalize_designation("Messier 031"), "M 31");
+        assert_eq!(normalize_designation("NGC0224"), "NGC 224");
+        assert_eq!(normalize_designation("ngc 2237a"), "NGC 2237A");
+        assert_eq!(normalize_designation("sh2 155"), "Sh2-155");
+        assert_eq!(normalize_designation("SH2-155"), "Sh2-155");
+        assert_eq!(normalize_designation("Cr 399"), "Cr 399");
+        assert_eq!(normalize_designation("  Mars  "), "Mars");
+        assert_eq!(normalize_designation("Comet C/2023 A3"), "Comet C/2023 A3");
+    }
+
+    #[test]
+    fn catalog_aliases_resolve_to_messier() {
+        let names = ObjectNames::default();
+        assert_eq!(names.canonical("M31"), "M 31");
+        assert_eq!(names.canonical("NGC224"), "M 31");
+        assert_eq!(names.canonical("andromeda galaxy"), "M 31");
+        assert_eq!(names.canonical("NGC 7000"), "NGC 7000");
+        assert_eq!(names.canonical("My Backyard"), "My Backyard");
+
+        let aliases = names.aliases("Andromeda Galaxy");
+        assert_eq!(aliases[0], "M 31");
+        assert!(aliases.contains(&"NGC 224".to_string()));
+    }
+
+    #[test]
+    fn merged_targets_share_a_name() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        for (id, summary) in [("a", "Heart Nebula"), ("b", "Heart"), ("c", "IC1805")] {
+            repository::create_image(
+                &mut conn,
+                &NewImage {
+                    user_id: USER.to_string(),
+                    summary: Some(summary.to_string()),
+                    ..NewImage::test(id)
+                },
+            )
+            .unwrap();
+        }
+
+        // "Heart Nebula" is already a catalog name of IC 1805
+        let result = merge_object_names(&mut conn, USER, "IC 1805", "Heart").unwrap();
+        assert_eq!(result.name, "IC 1805");
+        assert_eq!(result.images_updated, 3);
+
+        let names = ObjectNames::load(&mut conn).unwrap();
+        assert_eq!(names.canonical("heart"), "IC 1805");
+        assert_eq!(names.canonical("Heart Nebula"), "IC 1805");
+        let targets =
+            repository::get_targets_with_counts_by(&mut conn, USER, |n| names.canonical(n))
+                .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].image_count, 3);
+    }
+}
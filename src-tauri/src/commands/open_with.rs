@@ -0,0 +1,190 @@
+//! Open an image's files outside Astra
+//!
+//! Reveals an image in the system file manager or hands it to an external
+//! editor such as PixInsight or Siril. Editors get the FITS file unless
+//! they're set up to take the display preview; reveal shows the FITS file
+//! when it's on disk.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::settings::{load_settings, save_settings, EXTERNAL_EDITORS};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEditor {
+    pub id: String,
+    pub name: String,
+    /// Application name on macOS (opened with `open -a`), otherwise the
+    /// executable path or a command on PATH
+    pub program: String,
+    /// Open the FITS file rather than the display preview
+    pub use_fits: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExternalEditorSettings {
+    pub editors: Vec<ExternalEditor>,
+    /// Editor used when none is asked for; None opens files with the
+    /// system's default application
+    pub default_editor: Option<String>,
+}
+
+impl Default for ExternalEditorSettings {
+    fn default() -> Self {
+        let (pixinsight, siril) = if cfg!(target_os = "macos") {
+            ("PixInsight", "Siril")
+        } else if cfg!(windows) {
+            (
+                r"C:\Program Files\PixInsight\bin\PixInsight.exe",
+                r"C:\Program Files\Siril\bin\siril.exe",
+            )
+        } else {
+            ("/opt/PixInsight/bin/PixInsight.sh", "siril")
+        };
+        let editor = |id: &str, name: &str, program: &str| ExternalEditor {
+            id: id.to_string(),
+            name: name.to_string(),
+            program: program.to_string(),
+            use_fits: true,
+        };
+        ExternalEditorSettings {
+            editors: vec![
+                editor("pixinsight", "PixInsight", pixinsight),
+                editor("siril", "Siril", siril),
+            ],
+            default_editor: None,
+        }
+    }
+}
+
+/// File on disk behind an image: the FITS file or the display preview,
+/// whichever is preferred, falling back to the other one
+fn image_file(fits_url: Option<&str>, url: Option<&str>, prefer_fits: bool) -> Option<PathBuf> {
+    let candidates = if prefer_fits {
+        [fits_url, url]
+    } else {
+        [url, fits_url]
+    };
+    candidates
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+fn load_image_file(
+    state: &State<'_, AppState>,
+    id: &str,
+    prefer_fits: bool,
+) -> Result<PathBuf, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image: Image = repository::get_image_by_id(&mut conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", id))?;
+    image_file(image.fits_url.as_deref(), image.url.as_deref(), prefer_fits)
+        .ok_or_else(|| format!("No file on disk for {}", image.filename))
+}
+
+#[tauri::command]
+pub fn get_external_editors(handle: AppHandle) -> Result<ExternalEditorSettings, String> {
+    load_settings(&handle, &EXTERNAL_EDITORS)
+}
+
+/// Save external editors; None restores the defaults for this platform
+#[tauri::command]
+pub fn set_external_editors(
+    handle: AppHandle,
+    settings: Option<ExternalEditorSettings>,
+) -> Result<ExternalEditorSettings, String> {
+    let settings = settings.unwrap_or_default();
+    if let Some(default) = &settings.default_editor {
+        if !settings.editors.iter().any(|e| &e.id == default) {
+            return Err(format!("Unknown default editor: {}", default));
+        }
+    }
+
+    save_settings(&handle, &EXTERNAL_EDITORS, &settings)?;
+    Ok(settings)
+}
+
+/// Show an image's file in the system file manager. Returns the path shown.
+#[tauri::command]
+pub fn reveal_image_in_file_manager(
+    handle: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<String, String> {
+    let path = load_image_file(&state, &id, true)?;
+    handle
+        .opener()
+        .reveal_item_in_dir(&path)
+        .map_err(|e| format!("Failed to reveal {}: {}", path.display(), e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Open an image in an external editor, by id (or the default editor). With
+/// no editor configured the system's default application is used. Returns
+/// the path opened.
+#[tauri::command]
+pub fn open_image_in_external_editor(
+    handle: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    app: Option<String>,
+) -> Result<String, String> {
+    let settings: ExternalEditorSettings = load_settings(&handle, &EXTERNAL_EDITORS)?;
+    let editor = match app.as_ref().or(settings.default_editor.as_ref()) {
+        Some(editor_id) => Some(
+            settings
+                .editors
+                .iter()
+                .find(|e| &e.id == editor_id)
+                .ok_or_else(|| format!("Unknown editor: {}", editor_id))?,
+        ),
+        None => None,
+    };
+
+    let path = load_image_file(&state, &id, editor.map_or(true, |e| e.use_fits))?;
+    let path_str = path.to_string_lossy().to_string();
+    handle
+        .opener()
+        .open_path(path_str.clone(), editor.map(|e| e.program.clone()))
+        .map_err(|e| match editor {
+            Some(editor) => format!("Failed to open {} in {}: {}", path_str, editor.name, e),
+            None => format!("Failed to open {}: {}", path_str, e),
+        })?;
+    Ok(path_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn image_file_prefers_fits_and_falls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let fits = dir.path().join("Stacked_M 42.fit");
+        let jpeg = dir.path().join("Stacked_M 42.jpg");
+        fs::write(&fits, b"").unwrap();
+        fs::write(&jpeg, b"").unwrap();
+        let fits_url = fits.to_str();
+        let url = jpeg.to_str();
+
+        assert_eq!(image_file(fits_url, url, true), Some(fits.clone()));
+        assert_eq!(image_file(fits_url, url, false), Some(jpeg.clone()));
+
+        let missing = dir.path().join("moved.fit");
+        assert_eq!(image_file(missing.to_str(), url, true), Some(jpeg));
+        assert_eq!(image_file(None, missing.to_str(), false), None);
+    }
+}
@@ -0,0 +1,373 @@
+//! Field-kit packing lists
+//!
+//! Each schedule gets a checklist of what to pack for the night. It's
+//! generated the first time it's opened, from the schedule's equipment
+//! profile: the optics and camera with the dew band and cables they need,
+//! the mount when there's only one, and the batteries and other essentials
+//! that are easiest to leave behind. Items can be ticked off, added or
+//! removed, and the list regenerated when the rig changes.
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{
+    Equipment, EquipmentProfile, NewPackingItem, ObservationSchedule, PackingItem,
+};
+use crate::db::repository;
+use crate::state::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackingCategory {
+    Optics,
+    Camera,
+    Mount,
+    Power,
+    Dew,
+    Cables,
+    Consumables,
+    Other,
+}
+
+impl PackingCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PackingCategory::Optics => "optics",
+            PackingCategory::Camera => "camera",
+            PackingCategory::Mount => "mount",
+            PackingCategory::Power => "power",
+            PackingCategory::Dew => "dew",
+            PackingCategory::Cables => "cables",
+            PackingCategory::Consumables => "consumables",
+            PackingCategory::Other => "other",
+        }
+    }
+}
+
+/// Packed for every night out, whatever the rig
+const ESSENTIALS: &[(&str, PackingCategory)] = &[
+    (
+        "Field battery or power bank (charged)",
+        PackingCategory::Power,
+    ),
+    ("Spare batteries", PackingCategory::Power),
+    ("Power cables and adapters", PackingCategory::Cables),
+    ("Desiccant packs", PackingCategory::Consumables),
+    ("Red flashlight", PackingCategory::Other),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackingList {
+    pub schedule_id: String,
+    /// Equipment profile the list was generated from, if any
+    pub profile_id: Option<String>,
+    pub profile_name: Option<String>,
+    pub items: Vec<PackingItem>,
+    pub packed: usize,
+    pub total: usize,
+}
+
+/// What to pack for a profile, in checklist order: name, category and the
+/// equipment item it's for
+fn kit_for_profile(
+    profile: Option<&EquipmentProfile>,
+    equipment: &[Equipment],
+) -> Vec<(String, PackingCategory, Option<String>)> {
+    let find = |id: Option<&String>| {
+        id.and_then(|id| equipment.iter().find(|e| &e.id == id && !e.retired))
+    };
+    let mut kit = Vec::new();
+
+    if let Some(profile) = profile {
+        if let Some(scope) = find(profile.telescope_id.as_ref()) {
+            kit.push((
+                scope.name.clone(),
+                PackingCategory::Optics,
+                Some(scope.id.clone()),
+            ));
+            kit.push((
+                format!("Dew heater band for {}", scope.name),
+                PackingCategory::Dew,
+                Some(scope.id.clone()),
+            ));
+            kit.push((
+                "Dew heater controller".to_string(),
+                PackingCategory::Dew,
+                None,
+            ));
+        }
+        if let Some(reducer) = find(profile.reducer_id.as_ref()) {
+            kit.push((
+                reducer.name.clone(),
+                PackingCategory::Optics,
+                Some(reducer.id.clone()),
+            ));
+        }
+        if let Some(camera) = find(profile.camera_id.as_ref()) {
+            kit.push((
+                camera.name.clone(),
+                PackingCategory::Camera,
+                Some(camera.id.clone()),
+            ));
+            kit.push((
+                format!("USB cable for {}", camera.name),
+                PackingCategory::Cables,
+                Some(camera.id.clone()),
+            ));
+        }
+    }
+
+    // Profiles don't name a mount, so only an unambiguous one is listed
+    let mut mounts = equipment.iter().filter(|e| e.kind == "mount" && !e.retired);
+    if let (Some(mount), None) = (mounts.next(), mounts.next()) {
+        kit.push((
+            mount.name.clone(),
+            PackingCategory::Mount,
+            Some(mount.id.clone()),
+        ));
+    }
+
+    kit.extend(
+        ESSENTIALS
+            .iter()
+            .map(|(name, category)| (name.to_string(), *category, None)),
+    );
+    kit
+}
+
+/// The profile a schedule's list is built from: the one asked for, else the
+/// schedule's own equipment when that's a profile
+fn schedule_profile(
+    conn: &mut diesel::SqliteConnection,
+    schedule: &ObservationSchedule,
+    profile_id: Option<&str>,
+) -> Result<Option<EquipmentProfile>, String> {
+    if let Some(id) = profile_id {
+        return repository::get_equipment_profile_by_id(conn, id)
+            .map_err(|e| e.to_string())?
+            .map(Some)
+            .ok_or_else(|| format!("Equipment profile not found: {}", id));
+    }
+    match schedule.equipment_id.as_deref() {
+        Some(id) => repository::get_equipment_profile_by_id(conn, id).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn find_schedule(
+    conn: &mut diesel::SqliteConnection,
+    schedule_id: &str,
+) -> Result<ObservationSchedule, String> {
+    repository::get_schedule_by_id(conn, schedule_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Schedule not found: {}", schedule_id))
+}
+
+/// Build the list from the profile, keeping ticks on items already packed
+fn generate_packing_list(
+    conn: &mut diesel::SqliteConnection,
+    user_id: &str,
+    schedule: &ObservationSchedule,
+    profile: Option<&EquipmentProfile>,
+) -> Result<Vec<PackingItem>, String> {
+    let previous = repository::get_packing_items(conn, &schedule.id).map_err(|e| e.to_string())?;
+    let equipment = repository::get_equipment(conn, user_id).map_err(|e| e.to_string())?;
+    let was_packed = |name: &str| {
+        previous
+            .iter()
+            .any(|item| item.packed && item.name.eq_ignore_ascii_case(name))
+    };
+
+    let items: Vec<NewPackingItem> = kit_for_profile(profile, &equipment)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, category, equipment_id))| NewPackingItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            schedule_id: schedule.id.clone(),
+            packed: was_packed(&name),
+            name,
+            category: category.as_str().to_string(),
+            equipment_id,
+            sort_order: i as i32,
+        })
+        .collect();
+    repository::replace_packing_items(conn, &schedule.id, &items).map_err(|e| e.to_string())
+}
+
+fn packing_list(
+    schedule: &ObservationSchedule,
+    profile: Option<&EquipmentProfile>,
+    items: Vec<PackingItem>,
+) -> PackingList {
+    PackingList {
+        schedule_id: schedule.id.clone(),
+        profile_id: profile.map(|p| p.id.clone()),
+        profile_name: profile.map(|p| p.name.clone()),
+        packed: items.iter().filter(|item| item.packed).count(),
+        total: items.len(),
+        items,
+    }
+}
+
+/// A schedule's packing list, generated from its equipment profile (or
+/// `profile_id`) the first time
+#[tauri::command]
+pub fn get_packing_list(
+    state: State<'_, AppState>,
+    schedule_id: String,
+    profile_id: Option<String>,
+) -> Result<PackingList, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let schedule = find_schedule(&mut conn, &schedule_id)?;
+    let profile = schedule_profile(&mut conn, &schedule, profile_id.as_deref())?;
+    let mut items =
+        repository::get_packing_items(&mut conn, &schedule.id).map_err(|e| e.to_string())?;
+    if items.is_empty() {
+        items = generate_packing_list(&mut conn, &state.user_id, &schedule, profile.as_ref())?;
+    }
+    Ok(packing_list(&schedule, profile.as_ref(), items))
+}
+
+/// Rebuild a schedule's packing list, e.g. after changing its rig. Items
+/// added by hand are dropped; anything already packed stays ticked.
+#[tauri::command]
+pub fn regenerate_packing_list(
+    state: State<'_, AppState>,
+    schedule_id: String,
+    profile_id: Option<String>,
+) -> Result<PackingList, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let schedule = find_schedule(&mut conn, &schedule_id)?;
+    let profile = schedule_profile(&mut conn, &schedule, profile_id.as_deref())?;
+    let items = generate_packing_list(&mut conn, &state.user_id, &schedule, profile.as_ref())?;
+    Ok(packing_list(&schedule, profile.as_ref(), items))
+}
+
+#[tauri::command]
+pub fn add_packing_item(
+    state: State<'_, AppState>,
+    schedule_id: String,
+    name: String,
+    category: Option<PackingCategory>,
+) -> Result<PackingItem, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Item name is required".to_string());
+    }
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let schedule = find_schedule(&mut conn, &schedule_id)?;
+    let last = repository::get_packing_items(&mut conn, &schedule.id)
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|item| item.sort_order)
+        .max();
+
+    let new_item = NewPackingItem {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        schedule_id: schedule.id,
+        name: name.to_string(),
+        category: category
+            .unwrap_or(PackingCategory::Other)
+            .as_str()
+            .to_string(),
+        equipment_id: None,
+        packed: false,
+        sort_order: last.map_or(0, |order| order + 1),
+    };
+    repository::create_packing_item(&mut conn, &new_item).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_packing_item_packed(
+    state: State<'_, AppState>,
+    id: String,
+    packed: bool,
+) -> Result<PackingItem, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_packing_item_by_id(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Packing item not found: {}", id))?;
+    repository::set_packing_item_packed(&mut conn, &id, packed).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_packing_item(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_packing_item(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_profile() -> EquipmentProfile {
+        let now = chrono::Utc::now().naive_utc();
+        EquipmentProfile {
+            id: "rig".to_string(),
+            user_id: "user-1".to_string(),
+            name: "RedCat rig".to_string(),
+            telescope_id: Some("scope".to_string()),
+            camera_id: Some("cam".to_string()),
+            reducer_id: None,
+            scale_lower: None,
+            scale_upper: None,
+            processing_params: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn kit_covers_profile_gear_and_essentials() {
+        let mut equipment = vec![
+            Equipment::test("scope", "telescope", "RedCat 51"),
+            Equipment::test("cam", "camera", "ASI2600MC"),
+            Equipment::test("eq", "mount", "AM5"),
+        ];
+        let kit = kit_for_profile(Some(&make_profile()), &equipment);
+        let names: Vec<&str> = kit.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(
+            &names[..6],
+            [
+                "RedCat 51",
+                "Dew heater band for RedCat 51",
+                "Dew heater controller",
+                "ASI2600MC",
+                "USB cable for ASI2600MC",
+                "AM5",
+            ]
+        );
+        assert!(names.contains(&"Spare batteries"));
+        assert_eq!(kit[1].2.as_deref(), Some("scope"));
+
+        // With two mounts there's no telling which one is going
+        equipment.push(Equipment::test("eq6", "mount", "EQ6-R"));
+        let kit = kit_for_profile(None, &equipment);
+        assert_eq!(kit.len(), ESSENTIALS.len());
+        assert!(kit
+            .iter()
+            .all(|(_, _, equipment_id)| equipment_id.is_none()));
+    }
+
+    #[test]
+    fn retired_gear_is_left_out() {
+        let scope = Equipment {
+            retired: true,
+            ..Equipment::test("scope", "telescope", "RedCat 51")
+        };
+        let kit = kit_for_profile(Some(&make_profile()), &[scope]);
+        assert!(kit
+            .iter()
+            .all(|(_, category, _)| *category != PackingCategory::Dew));
+        assert_eq!(
+            serde_json::to_value(PackingCategory::Consumables).unwrap(),
+            serde_json::json!("consumables")
+        );
+    }
+}
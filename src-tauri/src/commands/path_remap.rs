@@ -0,0 +1,584 @@
+//! Image path health checks, remap suggestions, and saved remap presets.
+//!
+//! After restoring a backup from another computer, image paths usually point
+//! at the old machine's directories. These helpers find how many files are
+//! unreachable, probe the filesystem for where they moved, and remember
+//! applied remaps so they can be reused on the next restore.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::state::AppState;
+
+use super::backup::{RemapPreviewItem, RemapResult};
+
+/// Presets live outside the database so a restore does not overwrite them
+const PRESETS_FILE: &str = "path-remap-presets.json";
+/// Number of missing files probed when looking for a new location
+const SUGGESTION_SAMPLE: usize = 200;
+const MAX_SUGGESTIONS: usize = 5;
+/// Number of changed paths returned with a remap preview
+const REMAP_PREVIEW_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathRemapPreset {
+    pub id: String,
+    pub name: String,
+    pub old_prefix: String,
+    pub new_prefix: String,
+    pub created_at: String,
+    pub last_applied_at: Option<String>,
+    pub times_applied: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemapSuggestion {
+    pub old_prefix: String,
+    pub new_prefix: String,
+    /// Sampled missing files found under the new prefix
+    pub matched: usize,
+    /// Saved preset this suggestion comes from, if any
+    pub preset_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathCheckResult {
+    /// Images with a local file path
+    pub total: usize,
+    /// Images whose file path does not exist
+    pub missing: usize,
+    pub missing_fraction: f64,
+    pub sample_missing: Vec<String>,
+    pub suggestions: Vec<RemapSuggestion>,
+}
+
+// ============================================================================
+// Presets
+// ============================================================================
+
+fn load_presets(data_dir: &Path) -> Result<Vec<PathRemapPreset>, String> {
+    let path = data_dir.join(PRESETS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read remap presets: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse remap presets: {}", e))
+}
+
+fn save_presets(data_dir: &Path, presets: &[PathRemapPreset]) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(presets)
+        .map_err(|e| format!("Failed to serialize remap presets: {}", e))?;
+    fs::write(data_dir.join(PRESETS_FILE), data)
+        .map_err(|e| format!("Failed to write remap presets: {}", e))
+}
+
+/// Record an applied remap, creating a preset for new prefix pairs
+pub fn record_applied_remap(
+    data_dir: &Path,
+    old_prefix: &str,
+    new_prefix: &str,
+) -> Result<PathRemapPreset, String> {
+    let mut presets = load_presets(data_dir)?;
+    let now = Local::now().to_rfc3339();
+
+    let index = match presets
+        .iter()
+        .position(|p| p.old_prefix == old_prefix && p.new_prefix == new_prefix)
+    {
+        Some(index) => index,
+        None => {
+            presets.push(PathRemapPreset {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: format!("{} → {}", old_prefix, new_prefix),
+                old_prefix: old_prefix.to_string(),
+                new_prefix: new_prefix.to_string(),
+                created_at: now.clone(),
+                last_applied_at: None,
+                times_applied: 0,
+            });
+            presets.len() - 1
+        }
+    };
+    presets[index].last_applied_at = Some(now);
+    presets[index].times_applied += 1;
+
+    let preset = presets[index].clone();
+    save_presets(data_dir, &presets)?;
+    Ok(preset)
+}
+
+// ============================================================================
+// Remapping
+// ============================================================================
+
+fn remap_path(path: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    path.strip_prefix(old_prefix)
+        .map(|rest| format!("{}{}", new_prefix, rest))
+}
+
+/// Replace `old_prefix` with `new_prefix` in image url and fits_url fields.
+/// All updates run in one transaction; with `dry_run` nothing is written and
+/// the result only previews the changes.
+pub fn apply_path_remap(
+    conn: &mut SqliteConnection,
+    old_prefix: &str,
+    new_prefix: &str,
+    dry_run: bool,
+) -> QueryResult<RemapResult> {
+    use crate::db::schema::images::dsl::*;
+
+    let pattern = format!("{}%", old_prefix);
+    let matching: Vec<(String, Option<String>, Option<String>)> = images
+        .select((id, url, fits_url))
+        .filter(url.like(&pattern))
+        .or_filter(fits_url.like(&pattern))
+        .load(conn)?;
+
+    let mut updates: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+    let mut preview = Vec::new();
+    let mut urls_updated: i64 = 0;
+    let mut fits_updated: i64 = 0;
+
+    for (img_id, img_url, img_fits) in &matching {
+        let new_url = img_url
+            .as_deref()
+            .and_then(|u| remap_path(u, old_prefix, new_prefix));
+        let new_fits = img_fits
+            .as_deref()
+            .and_then(|f| remap_path(f, old_prefix, new_prefix));
+        if new_url.is_some() {
+            urls_updated += 1;
+        }
+        if new_fits.is_some() {
+            fits_updated += 1;
+        }
+
+        let changed = [(img_url, &new_url), (img_fits, &new_fits)];
+        for (old_path, new_path) in changed {
+            if let (Some(old_path), Some(new_path)) = (old_path, new_path) {
+                if preview.len() < REMAP_PREVIEW_LIMIT {
+                    preview.push(RemapPreviewItem {
+                        image_id: img_id.clone(),
+                        old_path: old_path.clone(),
+                        new_path: new_path.clone(),
+                    });
+                }
+            }
+        }
+
+        if new_url.is_some() || new_fits.is_some() {
+            updates.push((img_id.clone(), new_url, new_fits));
+        }
+    }
+
+    if !dry_run {
+        conn.transaction(|conn| {
+            for (img_id, new_url, new_fits) in &updates {
+                let target = images.filter(id.eq(img_id));
+                if let Some(new_url) = new_url {
+                    diesel::update(target).set(url.eq(new_url)).execute(conn)?;
+                }
+                if let Some(new_fits) = new_fits {
+                    diesel::update(target)
+                        .set(fits_url.eq(new_fits))
+                        .execute(conn)?;
+                }
+            }
+            QueryResult::Ok(())
+        })?;
+    }
+
+    let verb = if dry_run { "Would remap" } else { "Remapped" };
+    Ok(RemapResult {
+        success: true,
+        urls_updated,
+        fits_urls_updated: fits_updated,
+        message: format!(
+            "{} {} image URLs and {} FITS URLs from '{}' to '{}'",
+            verb, urls_updated, fits_updated, old_prefix, new_prefix
+        ),
+        dry_run,
+        preview,
+    })
+}
+
+// ============================================================================
+// Path checks and suggestions
+// ============================================================================
+
+/// Find a root under which the tail of `path` exists.
+/// Longer tails are tried first so the most specific match wins.
+fn probe_remap(
+    path: &Path,
+    roots: &[PathBuf],
+    exists: &impl Fn(&Path) -> bool,
+) -> Option<(String, String)> {
+    let components: Vec<Component> = path.components().collect();
+    for split in 1..components.len() {
+        let tail: PathBuf = components[split..].iter().collect();
+        for root in roots {
+            if exists(&root.join(&tail)) {
+                let old: PathBuf = components[..split].iter().collect();
+                let old = old.to_string_lossy().to_string();
+                let new = root.to_string_lossy().to_string();
+                return (old != new).then_some((old, new));
+            }
+        }
+    }
+    None
+}
+
+/// Suggest old→new prefix pairs for missing files.
+///
+/// Saved presets that resolve at least one missing file come first, followed
+/// by prefixes discovered by probing `roots`, ranked by how many sampled
+/// files they resolve.
+pub fn suggest_remaps(
+    missing: &[String],
+    roots: &[PathBuf],
+    presets: &[PathRemapPreset],
+    exists: impl Fn(&Path) -> bool,
+) -> Vec<RemapSuggestion> {
+    let sample = &missing[..missing.len().min(SUGGESTION_SAMPLE)];
+    let mut suggestions = Vec::new();
+
+    let mut resolved: Vec<bool> = vec![false; sample.len()];
+
+    for preset in presets {
+        let mut matched = 0;
+        for (path, resolved) in sample.iter().zip(resolved.iter_mut()) {
+            let remapped = remap_path(path, &preset.old_prefix, &preset.new_prefix);
+            if remapped.is_some_and(|p| exists(Path::new(&p))) {
+                matched += 1;
+                *resolved = true;
+            }
+        }
+        if matched > 0 {
+            suggestions.push(RemapSuggestion {
+                old_prefix: preset.old_prefix.clone(),
+                new_prefix: preset.new_prefix.clone(),
+                matched,
+                preset_id: Some(preset.id.clone()),
+            });
+        }
+    }
+
+    // Only probe for files no preset already accounts for
+    let mut probed: HashMap<(String, String), usize> = HashMap::new();
+    for (path, _) in sample
+        .iter()
+        .zip(&resolved)
+        .filter(|(_, resolved)| !**resolved)
+    {
+        if let Some(pair) = probe_remap(Path::new(path), roots, &exists) {
+            *probed.entry(pair).or_insert(0) += 1;
+        }
+    }
+    let mut probed: Vec<_> = probed.into_iter().collect();
+    probed.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    suggestions.sort_by_key(|s| std::cmp::Reverse(s.matched));
+    suggestions.extend(
+        probed
+            .into_iter()
+            .map(|((old_prefix, new_prefix), matched)| RemapSuggestion {
+                old_prefix,
+                new_prefix,
+                matched,
+                preset_id: None,
+            }),
+    );
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Places a moved library is likely to be: the home directory and mounted volumes
+fn default_search_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = dirs::home_dir().into_iter().collect();
+    let child_dirs = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for base in ["/Volumes", "/mnt", "/media"] {
+        for dir in child_dirs(Path::new(base)) {
+            // /media/<user>/<volume> on most Linux desktops
+            if base == "/media" {
+                roots.extend(child_dirs(&dir));
+            }
+            roots.push(dir);
+        }
+    }
+    roots
+}
+
+/// Count unreachable image files and suggest remaps for them
+pub fn check_paths(
+    conn: &mut SqliteConnection,
+    data_dir: &Path,
+    extra_roots: &[PathBuf],
+) -> Result<PathCheckResult, String> {
+    use crate::db::schema::images::dsl::*;
+
+    let paths: Vec<(Option<String>, Option<String>)> = images
+        .select((url, fits_url))
+        .load(conn)
+        .map_err(|e| format!("Failed to query images: {}", e))?;
+
+    let mut total = 0;
+    let mut missing = Vec::new();
+    for (img_url, img_fits) in paths {
+        // Prefer the FITS original; ignore remote URLs
+        let Some(path) = img_fits.or(img_url).filter(|p| Path::new(p).is_absolute()) else {
+            continue;
+        };
+        total += 1;
+        if !Path::new(&path).exists() {
+            missing.push(path);
+        }
+    }
+
+    let mut roots = extra_roots.to_vec();
+    roots.extend(default_search_roots());
+    let presets = load_presets(data_dir)?;
+    let suggestions = suggest_remaps(&missing, &roots, &presets, |p| p.exists());
+
+    Ok(PathCheckResult {
+        total,
+        missing: missing.len(),
+        missing_fraction: if total > 0 {
+            missing.len() as f64 / total as f64
+        } else {
+            0.0
+        },
+        sample_missing: missing.into_iter().take(10).collect(),
+        suggestions,
+    })
+}
+
+fn get_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Check how many image files are unreachable and suggest prefix remaps.
+/// `search_roots` are probed in addition to the home directory and mounted volumes.
+#[tauri::command]
+pub async fn check_image_paths(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    search_roots: Option<Vec<String>>,
+) -> Result<PathCheckResult, String> {
+    let db = state.db.clone();
+    let data_dir = get_data_dir(&app)?;
+    let roots: Vec<PathBuf> = search_roots
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        check_paths(&mut conn, &data_dir, &roots)
+    })
+    .await
+    .map_err(|e| format!("Path check failed: {}", e))?
+}
+
+/// List saved path remap presets
+#[tauri::command]
+pub fn get_path_remap_presets(app: AppHandle) -> Result<Vec<PathRemapPreset>, String> {
+    load_presets(&get_data_dir(&app)?)
+}
+
+/// Save (or rename) a path remap preset
+#[tauri::command]
+pub fn save_path_remap_preset(
+    app: AppHandle,
+    name: String,
+    old_prefix: String,
+    new_prefix: String,
+) -> Result<PathRemapPreset, String> {
+    if old_prefix.is_empty() {
+        return Err("Old prefix cannot be empty".to_string());
+    }
+    let data_dir = get_data_dir(&app)?;
+    let mut presets = load_presets(&data_dir)?;
+
+    let preset = match presets
+        .iter_mut()
+        .find(|p| p.old_prefix == old_prefix && p.new_prefix == new_prefix)
+    {
+        Some(existing) => {
+            existing.name = name;
+            existing.clone()
+        }
+        None => {
+            let preset = PathRemapPreset {
+                id: uuid::Uuid::new_v4().to_string(),
+                name,
+                old_prefix,
+                new_prefix,
+                created_at: Local::now().to_rfc3339(),
+                last_applied_at: None,
+                times_applied: 0,
+            };
+            presets.push(preset.clone());
+            preset
+        }
+    };
+
+    save_presets(&data_dir, &presets)?;
+    Ok(preset)
+}
+
+/// Delete a path remap preset
+#[tauri::command]
+pub fn delete_path_remap_preset(app: AppHandle, preset_id: String) -> Result<(), String> {
+    let data_dir = get_data_dir(&app)?;
+    let mut presets = load_presets(&data_dir)?;
+    presets.retain(|p| p.id != preset_id);
+    save_presets(&data_dir, &presets)
+}
+
+/// Apply a saved preset (or preview it with `dry_run`)
+#[tauri::command]
+pub fn apply_path_remap_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    preset_id: String,
+    dry_run: Option<bool>,
+) -> Result<RemapResult, String> {
+    let data_dir = get_data_dir(&app)?;
+    let preset = load_presets(&data_dir)?
+        .into_iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| format!("Remap preset not found: {}", preset_id))?;
+
+    let dry_run = dry_run.unwrap_or(false);
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let result = apply_path_remap(&mut conn, &preset.old_prefix, &preset.new_prefix, dry_run)
+        .map_err(|e| format!("Failed to remap image paths: {}", e))?;
+
+    if !dry_run {
+        record_applied_remap(&data_dir, &preset.old_prefix, &preset.new_prefix)?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn exists_in(files: &[&str]) -> impl Fn(&Path) -> bool {
+        let files: HashSet<PathBuf> = files.iter().map(PathBuf::from).collect();
+        move |p: &Path| files.contains(p)
+    }
+
+    #[test]
+    fn probe_finds_moved_library_under_root() {
+        let missing = vec![
+            "/Users/alice/Astro/2026/M42/light_001.fits".to_string(),
+            "/Users/alice/Astro/2026/M42/light_002.fits".to_string(),
+        ];
+        let exists = exists_in(&[
+            "/home/bob/Astro/2026/M42/light_001.fits",
+            "/home/bob/Astro/2026/M42/light_002.fits",
+        ]);
+        let roots = vec![PathBuf::from("/mnt/nas"), PathBuf::from("/home/bob")];
+
+        let suggestions = suggest_remaps(&missing, &roots, &[], exists);
+        assert_eq!(
+            suggestions,
+            vec![RemapSuggestion {
+                old_prefix: "/Users/alice".to_string(),
+                new_prefix: "/home/bob".to_string(),
+                matched: 2,
+                preset_id: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn presets_are_suggested_first() {
+        let missing = vec!["/old/lib/a.fits".to_string()];
+        let preset = PathRemapPreset {
+            id: "p1".to_string(),
+            name: "NAS".to_string(),
+            old_prefix: "/old/lib".to_string(),
+            new_prefix: "/mnt/nas/lib".to_string(),
+            created_at: String::new(),
+            last_applied_at: None,
+            times_applied: 1,
+        };
+        let exists = exists_in(&["/mnt/nas/lib/a.fits"]);
+
+        let suggestions = suggest_remaps(&missing, &[PathBuf::from("/mnt/nas")], &[preset], exists);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].preset_id.as_deref(), Some("p1"));
+    }
+
+    #[test]
+    fn record_applied_remap_upserts_preset() {
+        let dir = tempfile::TempDir::new().unwrap();
+        record_applied_remap(dir.path(), "/a", "/b").unwrap();
+        let again = record_applied_remap(dir.path(), "/a", "/b").unwrap();
+        assert_eq!(again.times_applied, 2);
+        assert_eq!(load_presets(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn apply_path_remap_dry_run_then_apply() {
+        use crate::db::models::NewImage;
+        use crate::db::repository;
+
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        repository::create_image(
+            &mut conn,
+            &NewImage {
+                user_id: "local-user".to_string(),
+                filename: "a.fits".to_string(),
+                url: Some("/old/lib/a.jpg".to_string()),
+                fits_url: Some("/old/lib/a.fits".to_string()),
+                ..NewImage::test("img")
+            },
+        )
+        .unwrap();
+
+        let preview = apply_path_remap(&mut conn, "/old/lib", "/new/lib", true).unwrap();
+        assert_eq!((preview.urls_updated, preview.fits_urls_updated), (1, 1));
+        assert_eq!(preview.preview.len(), 2);
+        let image = repository::get_image_by_id(&mut conn, "img")
+            .unwrap()
+            .unwrap();
+        assert_eq!(image.url.as_deref(), Some("/old/lib/a.jpg"));
+
+        apply_path_remap(&mut conn, "/old/lib", "/new/lib", false).unwrap();
+        let image = repository::get_image_by_id(&mut conn, "img")
+            .unwrap()
+            .unwrap();
+        assert_eq!(image.url.as_deref(), Some("/new/lib/a.jpg"));
+        assert_eq!(image.fits_url.as_deref(), Some("/new/lib/a.fits"));
+    }
+}
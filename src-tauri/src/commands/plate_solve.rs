@@ -1,11 +1,20 @@
 //! Plate solving commands for astronomical images
 
+use diesel::SqliteConnection;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tauri::State;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
 
-use crate::db::{models::UpdateImage, repository};
+use super::scan::{extract_float_value, metadata_header_value, metadata_number_value};
+use super::solar_system::is_solar_system;
+use crate::db::models::{Image, UpdateCollection, UpdateImage};
+use crate::db::repository;
 use crate::python::plate_solve::{self, CatalogObject, PlateSolveResult, SolveHints, SolverInfo};
+use crate::skymap::Footprint;
+use crate::solvers::wcs::Wcs;
+use crate::solvers::{astap, astrometry_net};
 use crate::state::AppState;
 
 /// Input for plate solving an image
@@ -20,7 +29,8 @@ pub struct PlateSolveInput {
     pub api_key: Option<String>,
     /// Custom API URL for local astrometry.net instance (optional, defaults to nova.astrometry.net)
     pub api_url: Option<String>,
-    /// Lower bound of expected image scale (arcsec/pixel)
+    /// Lower bound of expected image scale (arcsec/pixel). Without bounds,
+    /// the equipment profile's or the FITS headers' scale is used.
     pub scale_lower: Option<f64>,
     /// Upper bound of expected image scale (arcsec/pixel)
     pub scale_upper: Option<f64>,
@@ -32,7 +42,8 @@ pub struct PlateSolveInput {
     pub catalogs: Option<Vec<String>>,
     /// Magnitude limit for bright stars
     pub star_mag_limit: Option<f64>,
-    /// Hint RA in degrees (to speed up solving)
+    /// Hint RA in degrees (to speed up solving). Defaults to the RA/DEC or
+    /// OBJCTRA/OBJCTDEC headers.
     pub hint_ra: Option<f64>,
     /// Hint Dec in degrees (to speed up solving)
     pub hint_dec: Option<f64>,
@@ -44,6 +55,12 @@ pub struct PlateSolveInput {
     /// FOV estimate in degrees for tetra3 solver (horizontal field of view).
     /// If not specified, will be estimated from scale_lower/scale_upper and image dimensions.
     pub fov_estimate: Option<f64>,
+    /// Path to the ASTAP executable for the "astap" solver (optional, defaults
+    /// to the first ASTAP found on the system)
+    pub astap_path: Option<String>,
+    /// Path to solve-field for the "local" solver (optional, defaults to the
+    /// first solve-field found on the system)
+    pub solve_field_path: Option<String>,
 }
 
 /// Combined result from plate solving and catalog query
@@ -302,6 +319,111 @@ fn solve_with_tetra3(
     }
 }
 
+/// Scale bounds from the image's equipment profile, memoized by profile ID
+fn cached_scale_bounds(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_id: &str,
+    cache: &mut HashMap<String, Option<(f64, f64)>>,
+) -> Result<Option<(f64, f64)>, diesel::result::Error> {
+    let Some(profile) = repository::find_profile_for_image(conn, user_id, image_id)? else {
+        return Ok(None);
+    };
+    if let Some(bounds) = cache.get(&profile.id) {
+        return Ok(*bounds);
+    }
+    let bounds = super::equipment::profile_scale_bounds(conn, &profile)?;
+    cache.insert(profile.id, bounds);
+    Ok(bounds)
+}
+
+/// Solve hints derived from the FITS headers stored in an image's metadata
+#[derive(Debug, Default, PartialEq)]
+struct HeaderHints {
+    /// Scale bounds (arcsec/pixel) from FOCALLEN and the pixel size
+    scale: Option<(f64, f64)>,
+    /// RA/Dec in degrees from the mount coordinates
+    position: Option<(f64, f64)>,
+}
+
+impl HeaderHints {
+    fn scale_bounds(&self, filename: &str) -> (Option<f64>, Option<f64>) {
+        match self.scale {
+            Some((lower, upper)) => {
+                log::info!(
+                    "Using FITS header scale bounds {:.2}-{:.2}\"/px for {}",
+                    lower,
+                    upper,
+                    filename
+                );
+                (Some(lower), Some(upper))
+            }
+            None => (None, None),
+        }
+    }
+}
+
+/// Read solve hints from stored metadata: FOCALLEN with XPIXSZ (which
+/// includes binning) or PIXSIZE1 for the scale, and RA/DEC or
+/// OBJCTRA/OBJCTDEC for the position
+fn header_solve_hints(metadata: &str) -> HeaderHints {
+    let number = |field: &str, headers: &[&str]| {
+        metadata_number_value(metadata, field, headers)
+            .and_then(|v| extract_float_value(&v))
+            .filter(|v| *v > 0.0)
+    };
+    let focal_length = number("focal_length", &["FOCALLEN", "FOCAL", "FOCALLNG"]);
+    let pixel_size = number("pixel_size", &["XPIXSZ", "PIXSIZE1", "PIXSIZE"]);
+    let scale = focal_length.zip(pixel_size).map(|(focal_length, pixel_size)| {
+        let scale = super::equipment::pixel_scale_arcsec(focal_length, pixel_size, 1.0);
+        let margin = super::equipment::SCALE_MARGIN;
+        (scale * (1.0 - margin), scale * (1.0 + margin))
+    });
+
+    // RA is in degrees when decimal and in hours when sexagesimal
+    let angle = |field: &str, header: &str, hours: bool| {
+        metadata_header_value(metadata, field, header)
+            .and_then(|v| parse_angle(&v, if hours { 15.0 } else { 1.0 }))
+    };
+    let ra = angle("ra", "RA", true).or_else(|| angle("objctra", "OBJCTRA", true));
+    let dec = angle("dec", "DEC", false).or_else(|| angle("objctdec", "OBJCTDEC", false));
+    let position = ra
+        .zip(dec)
+        .filter(|(ra, dec)| (0.0..360.0).contains(ra) && (-90.0..=90.0).contains(dec));
+
+    HeaderHints { scale, position }
+}
+
+/// Parse a decimal or sexagesimal ("dd mm ss", "hh:mm:ss", "12h34m56s")
+/// angle, scaling sexagesimal values by `sexagesimal_factor`
+pub(super) fn parse_angle(text: &str, sexagesimal_factor: f64) -> Option<f64> {
+    let parts: Vec<f64> = text
+        .split(|c: char| c.is_whitespace() || ":hmsd°'\"".contains(c))
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let (whole, minutes, seconds) = match parts.as_slice() {
+        [value] => return Some(*value),
+        [whole, minutes] => (*whole, *minutes, 0.0),
+        [whole, minutes, seconds] => (*whole, *minutes, *seconds),
+        _ => return None,
+    };
+    let sign = if text.trim_start().starts_with('-') { -1.0 } else { 1.0 };
+    Some(sign * (whole.abs() + minutes / 60.0 + seconds / 3600.0) * sexagesimal_factor)
+}
+
+/// Image dimensions, falling back to the FITS headers since the `image`
+/// crate doesn't read FITS. Returns 0x0 when neither works.
+fn image_dimensions(path: &Path) -> (u32, u32) {
+    ::image::image_dimensions(path)
+        .ok()
+        .or_else(|| read_fits_dimensions(path).ok())
+        .unwrap_or_else(|| {
+            log::warn!("Could not read image dimensions from file, using 0x0");
+            (0, 0)
+        })
+}
+
 /// Read image dimensions from a FITS file's NAXIS1/NAXIS2 headers.
 fn read_fits_dimensions(path: &Path) -> Result<(u32, u32), String> {
     use fitrs::Fits;
@@ -335,7 +457,7 @@ fn read_fits_dimensions(path: &Path) -> Result<(u32, u32), String> {
 
 /// Read pixel data from a FITS file as f32 values for centroid extraction.
 /// Returns (pixels, width, height) where pixels is a flat array in row-major order.
-fn read_fits_pixels(fits_path: &str) -> Result<(Vec<f32>, u32, u32), String> {
+pub(crate) fn read_fits_pixels(fits_path: &str) -> Result<(Vec<f32>, u32, u32), String> {
     use fitrs::Fits;
 
     let fits =
@@ -418,12 +540,25 @@ fn read_fits_pixels(fits_path: &str) -> Result<(Vec<f32>, u32, u32), String> {
 pub async fn plate_solve_image(
     state: State<'_, AppState>,
     input: PlateSolveInput,
+) -> Result<PlateSolveResponse, String> {
+    solve_and_record(&state, input, &mut HashMap::new()).await
+}
+
+/// Solve an image and record the result (or failure) in its metadata.
+/// Equipment profile scale bounds are memoized in `scale_cache` by profile ID.
+pub(crate) async fn solve_and_record(
+    state: &AppState,
+    input: PlateSolveInput,
+    scale_cache: &mut HashMap<String, Option<(f64, f64)>>,
 ) -> Result<PlateSolveResponse, String> {
     // Get the image from the database
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
     let image = repository::get_image_by_id(&mut conn, &input.id)
         .map_err(|e| e.to_string())?
         .ok_or_else(|| format!("Image not found: {}", input.id))?;
+    if is_solar_system(image.metadata.as_deref()) {
+        return Err("Solar system images can't be plate solved".to_string());
+    }
 
     // Get the file path
     let file_path = image
@@ -436,21 +571,17 @@ pub async fn plate_solve_image(
         return Err(format!("Image file not found: {}", file_path));
     }
 
-    // Plate solve the image — dispatch to tetra3 native solver or Python bridge
+    let ResolvedHints {
+        scale_lower,
+        scale_upper,
+        hint_ra,
+        hint_dec,
+    } = resolve_hints(&mut conn, &state.user_id, &image, &input, scale_cache);
+
+    // Plate solve the image — dispatch to the native tetra3, ASTAP and
+    // solve-field runners or the Python bridge
     let solve_result = if input.solver == "tetra3" {
-        // Get image dimensions for tetra3 (try to read from the image file).
-        // The `image` crate doesn't support FITS, so we fall back to reading
-        // FITS dimensions via fitrs if the standard image read fails.
-        let (img_w, img_h) = ::image::image_dimensions(path)
-            .ok()
-            .or_else(|| {
-                // Try reading dimensions from FITS headers
-                read_fits_dimensions(path).ok()
-            })
-            .unwrap_or_else(|| {
-                log::warn!("Could not read image dimensions from file, using 0x0");
-                (0, 0)
-            });
+        let (img_w, img_h) = image_dimensions(path);
 
         // Resolve tetra3 database path
         let db_path = input.tetra3_db_path
@@ -465,31 +596,157 @@ pub async fn plate_solve_image(
             file_path,
             &db_path,
             input.fov_estimate,
-            input.scale_lower,
-            input.scale_upper,
+            scale_lower,
+            scale_upper,
             img_w,
             img_h,
             timeout_ms,
         )?
+    } else if input.solver == "astap" {
+        // ASTAP takes the field height; estimate it from the scale hints
+        let fov_deg = match (scale_lower, scale_upper) {
+            (Some(lower), upper) => {
+                let (_, img_h) = image_dimensions(path);
+                let scale = (lower + upper.unwrap_or(lower)) / 2.0;
+                Some(scale * img_h as f64 / 3600.0).filter(|fov| *fov > 0.0)
+            }
+            _ => None,
+        };
+        let options = astap::AstapOptions {
+            binary: input.astap_path.as_ref().map(PathBuf::from),
+            fov_deg,
+            hint_ra,
+            hint_dec,
+            search_radius: input.hint_radius,
+            timeout: input.timeout.map(|t| Duration::from_secs(t.max(1) as u64)),
+        };
+        let image_path = file_path.clone();
+        tokio::task::spawn_blocking(move || astap::solve(&image_path, &options))
+            .await
+            .map_err(|e| format!("ASTAP solve failed: {}", e))??
+    } else if input.solver == "local" {
+        let options = astrometry_net::SolveFieldOptions {
+            binary: input.solve_field_path.as_ref().map(PathBuf::from),
+            scale_lower,
+            scale_upper,
+            hint_ra,
+            hint_dec,
+            search_radius: input.hint_radius,
+            downsample: None,
+            timeout: input.timeout.map(|t| Duration::from_secs(t.max(1) as u64)),
+        };
+        let image_path = file_path.clone();
+        tokio::task::spawn_blocking(move || astrometry_net::solve(&image_path, &options))
+            .await
+            .map_err(|e| format!("solve-field failed: {}", e))??
     } else {
         plate_solve::solve_image(
             file_path,
             &input.solver,
             input.api_key.as_deref(),
             input.api_url.as_deref(),
-            input.scale_lower,
-            input.scale_upper,
+            scale_lower,
+            scale_upper,
             input.timeout,
-            input.hint_ra,
-            input.hint_dec,
+            hint_ra,
+            hint_dec,
             input.hint_radius,
         )?
     };
 
+    let objects = record_solve_result(
+        &mut conn,
+        &image,
+        &solve_result,
+        input.query_catalogs.unwrap_or(true),
+        input.catalogs,
+        input.star_mag_limit,
+    );
+
+    Ok(PlateSolveResponse {
+        solve_result,
+        objects,
+    })
+}
+
+/// Scale and position hints for a solve
+pub(super) struct ResolvedHints {
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    pub hint_ra: Option<f64>,
+    pub hint_dec: Option<f64>,
+}
+
+/// Hints for solving an image: the caller's, else the equipment profile's
+/// scale bounds, else those from the stored FITS headers
+pub(super) fn resolve_hints(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image: &Image,
+    input: &PlateSolveInput,
+    scale_cache: &mut HashMap<String, Option<(f64, f64)>>,
+) -> ResolvedHints {
+    // Hints from the FITS headers stored at import, used for whatever the
+    // caller and equipment profile don't supply
+    let header_hints = image
+        .metadata
+        .as_deref()
+        .map(header_solve_hints)
+        .unwrap_or_default();
+
+    // Fall back to the matched equipment profile's scale bounds when the
+    // caller didn't supply any, then to the header scale
+    let (scale_lower, scale_upper) = if input.scale_lower.is_none() && input.scale_upper.is_none() {
+        match cached_scale_bounds(conn, user_id, &image.id, scale_cache) {
+            Ok(Some((lower, upper))) => {
+                log::info!(
+                    "Using equipment profile scale bounds {:.2}-{:.2}\"/px for {}",
+                    lower, upper, image.filename
+                );
+                (Some(lower), Some(upper))
+            }
+            Ok(None) => header_hints.scale_bounds(&image.filename),
+            Err(e) => {
+                log::warn!("Failed to look up equipment profile: {}", e);
+                header_hints.scale_bounds(&image.filename)
+            }
+        }
+    } else {
+        (input.scale_lower, input.scale_upper)
+    };
+
+    // Position hint from the mount coordinates in the headers
+    let (hint_ra, hint_dec) = match (input.hint_ra, input.hint_dec, header_hints.position) {
+        (None, None, Some((ra, dec))) => (Some(ra), Some(dec)),
+        (hint_ra, hint_dec, _) => (hint_ra, hint_dec),
+    };
+    if scale_lower.is_none() && hint_ra.is_none() {
+        log::info!("No scale or position hints for {}, solving blind", image.filename);
+    }
+
+    ResolvedHints {
+        scale_lower,
+        scale_upper,
+        hint_ra,
+        hint_dec,
+    }
+}
+
+/// Query catalogs for a successful solve (when asked to) and record the
+/// solution, or the failure, in the image's metadata. Returns the objects
+/// found in the field.
+pub(super) fn record_solve_result(
+    conn: &mut SqliteConnection,
+    image: &Image,
+    solve_result: &PlateSolveResult,
+    query_catalogs: bool,
+    catalogs: Option<Vec<String>>,
+    star_mag_limit: Option<f64>,
+) -> Vec<CatalogObject> {
     let mut objects = Vec::new();
 
     // If solve was successful and catalog query is requested, query catalogs
-    if solve_result.success && input.query_catalogs.unwrap_or(true) {
+    if solve_result.success && query_catalogs {
         // Use FITS file for WCS pixel positions (preview JPEG has no WCS headers)
         let fits_for_wcs = image.fits_url.as_deref()
             .or_else(|| {
@@ -505,10 +762,10 @@ pub async fn plate_solve_image(
             solve_result.center_dec,
             solve_result.width_deg,
             solve_result.height_deg,
-            input.catalogs,
-            input.star_mag_limit,
+            catalogs,
+            star_mag_limit,
             fits_for_wcs,
-            Some(&solve_result),
+            Some(solve_result),
         )
         .unwrap_or_else(|e| {
             log::warn!("Failed to query catalogs: {}", e);
@@ -566,15 +823,18 @@ pub async fn plate_solve_image(
             solve_result.center_ra, solve_result.center_dec
         );
 
+        let wcs = solution_wcs(solve_result).and_then(|wcs| serde_json::to_string(&wcs).ok());
+
         // Update the image in database
         let update = UpdateImage {
             location: Some(location),
             annotations: annotations_json,
             metadata: new_metadata,
+            wcs,
             ..Default::default()
         };
 
-        if let Err(e) = repository::update_image(&mut conn, &input.id, &update) {
+        if let Err(e) = repository::update_image(conn, &image.id, &update) {
             log::error!("Failed to update image after plate solve: {}", e);
         }
     } else {
@@ -610,21 +870,558 @@ pub async fn plate_solve_image(
             ..Default::default()
         };
 
-        if let Err(e) = repository::update_image(&mut conn, &input.id, &update) {
+        if let Err(e) = repository::update_image(conn, &image.id, &update) {
             log::error!("Failed to update image after plate solve failure: {}", e);
         }
     }
 
-    Ok(PlateSolveResponse {
-        solve_result,
-        objects,
+    objects
+}
+
+/// Full WCS of a successful solve: the solver's own solution when it has
+/// one, otherwise a TAN approximation from the center, scale and rotation
+fn solution_wcs(result: &PlateSolveResult) -> Option<Wcs> {
+    if let Some(wcs) = result
+        .wcs
+        .clone()
+        .and_then(|value| serde_json::from_value::<Wcs>(value).ok())
+    {
+        return Some(wcs);
+    }
+    (result.pixel_scale > 0.0 && result.image_width > 0 && result.image_height > 0).then(|| {
+        Wcs::from_center(
+            result.center_ra,
+            result.center_dec,
+            result.pixel_scale,
+            result.rotation,
+            result.image_width,
+            result.image_height,
+        )
+    })
+}
+
+// ============================================================================
+// Pixel <-> sky conversion
+// ============================================================================
+
+/// Sky position in degrees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkyPosition {
+    pub ra: f64,
+    pub dec: f64,
+}
+
+/// 0-based pixel position, in the same frame as annotation `pixelX`/`pixelY`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PixelPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Load an image's stored WCS solution
+fn load_image_wcs(state: &AppState, image_id: &str) -> Result<Wcs, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", image_id))?;
+    let wcs = image
+        .wcs
+        .ok_or_else(|| "Image has no plate solution".to_string())?;
+    serde_json::from_str(&wcs).map_err(|e| format!("Invalid WCS solution: {}", e))
+}
+
+/// Convert a pixel position in an image to RA/Dec using its plate solution
+#[tauri::command]
+pub fn pixel_to_sky(
+    state: State<'_, AppState>,
+    image_id: String,
+    x: f64,
+    y: f64,
+) -> Result<SkyPosition, String> {
+    let wcs = load_image_wcs(&state, &image_id)?;
+    let (ra, dec) = wcs.pixel_to_sky(x, y);
+    Ok(SkyPosition { ra, dec })
+}
+
+/// Convert RA/Dec to a pixel position in an image using its plate solution.
+/// Returns None for positions on the far side of the sky from the image.
+#[tauri::command]
+pub fn sky_to_pixel(
+    state: State<'_, AppState>,
+    image_id: String,
+    ra: f64,
+    dec: f64,
+) -> Result<Option<PixelPosition>, String> {
+    let wcs = load_image_wcs(&state, &image_id)?;
+    Ok(wcs.sky_to_pixel(ra, dec).map(|(x, y)| PixelPosition { x, y }))
+}
+
+// ============================================================================
+// Batch plate solving
+// ============================================================================
+
+/// Solver options for `plate_solve_collection`, applied to every image.
+/// Scale bounds come from each image's equipment profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlateSolveCollectionOptions {
+    /// Solver type: "nova", "local", "astap", or "tetra3"
+    pub solver: String,
+    pub api_key: Option<String>,
+    pub api_url: Option<String>,
+    /// Timeout in seconds, per image
+    pub timeout: Option<i32>,
+    pub query_catalogs: Option<bool>,
+    pub catalogs: Option<Vec<String>>,
+    pub star_mag_limit: Option<f64>,
+    pub tetra3_db_path: Option<String>,
+    pub fov_estimate: Option<f64>,
+    pub astap_path: Option<String>,
+    pub solve_field_path: Option<String>,
+}
+
+impl PlateSolveCollectionOptions {
+    fn input_for(&self, image_id: &str) -> PlateSolveInput {
+        PlateSolveInput {
+            id: image_id.to_string(),
+            solver: self.solver.clone(),
+            api_key: self.api_key.clone(),
+            api_url: self.api_url.clone(),
+            scale_lower: None,
+            scale_upper: None,
+            timeout: self.timeout,
+            query_catalogs: self.query_catalogs,
+            catalogs: self.catalogs.clone(),
+            star_mag_limit: self.star_mag_limit,
+            hint_ra: None,
+            hint_dec: None,
+            hint_radius: None,
+            tetra3_db_path: self.tetra3_db_path.clone(),
+            fov_estimate: self.fov_estimate,
+            astap_path: self.astap_path.clone(),
+            solve_field_path: self.solve_field_path.clone(),
+        }
+    }
+}
+
+/// An image that failed to solve in a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSolveFailure {
+    pub image_id: String,
+    pub filename: String,
+    pub error: String,
+}
+
+/// Outcome of `plate_solve_collection`, also stored in the collection's
+/// metadata under "plate_solve_batch"
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlateSolveBatchSummary {
+    pub total: usize,
+    pub solved: usize,
+    pub failed: usize,
+    /// Images that already had a plate solution, or show a solar system
+    /// body and can't have one
+    pub skipped: usize,
+    pub failures: Vec<BatchSolveFailure>,
+}
+
+/// Emitted as "plate-solve-batch-progress" after each image is attempted
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlateSolveBatchProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub image_id: String,
+    pub filename: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Whether an image's metadata already holds a successful plate solution
+pub(crate) fn is_plate_solved(metadata: Option<&str>) -> bool {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .is_some_and(|m| m.get("plate_solve").is_some_and(|v| v.is_object()))
+}
+
+/// Plate solve every unsolved image in a collection, one at a time.
+/// Emits "plate-solve-batch-progress" events and records the summary in
+/// the collection's metadata.
+#[tauri::command]
+pub async fn plate_solve_collection(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    collection_id: String,
+    options: PlateSolveCollectionOptions,
+) -> Result<PlateSolveBatchSummary, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collection = repository::get_collection_by_id(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Collection not found: {}", collection_id))?;
+    let images = repository::get_images_in_collection(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?;
+    drop(conn);
+
+    let (solved, pending): (Vec<_>, Vec<_>) = images.into_iter().partition(|image| {
+        let metadata = image.metadata.as_deref();
+        is_plate_solved(metadata) || is_solar_system(metadata)
+    });
+    let mut summary = PlateSolveBatchSummary {
+        total: solved.len() + pending.len(),
+        skipped: solved.len(),
+        ..Default::default()
+    };
+
+    let mut scale_cache = HashMap::new();
+    for (completed, image) in pending.iter().enumerate() {
+        let input = options.input_for(&image.id);
+        let error = match solve_and_record(&state, input, &mut scale_cache).await {
+            Ok(response) if response.solve_result.success => None,
+            Ok(response) => Some(
+                response
+                    .solve_result
+                    .error_message
+                    .unwrap_or_else(|| "No solution found".to_string()),
+            ),
+            Err(e) => Some(e),
+        };
+
+        match &error {
+            None => summary.solved += 1,
+            Some(error) => {
+                summary.failed += 1;
+                summary.failures.push(BatchSolveFailure {
+                    image_id: image.id.clone(),
+                    filename: image.filename.clone(),
+                    error: error.clone(),
+                });
+            }
+        }
+
+        let _ = app.emit(
+            "plate-solve-batch-progress",
+            &PlateSolveBatchProgress {
+                completed: completed + 1,
+                total: pending.len(),
+                image_id: image.id.clone(),
+                filename: image.filename.clone(),
+                success: error.is_none(),
+                error,
+            },
+        );
+    }
+
+    log::info!(
+        "Batch plate solve of collection {}: {} images, {} solved, {} failed, {} skipped",
+        collection_id,
+        summary.total,
+        summary.solved,
+        summary.failed,
+        summary.skipped
+    );
+
+    let mut metadata = collection
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|m| m.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    let mut record = serde_json::to_value(&summary).map_err(|e| e.to_string())?;
+    record["solver"] = serde_json::json!(options.solver);
+    record["finishedAt"] = serde_json::json!(chrono::Utc::now().to_rfc3339());
+    metadata["plate_solve_batch"] = record;
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let update = UpdateCollection {
+        metadata: Some(metadata.to_string()),
+        ..Default::default()
+    };
+    if let Err(e) = repository::update_collection(&mut conn, &collection_id, &update) {
+        log::error!("Failed to record plate solve summary for collection: {}", e);
+    }
+
+    Ok(summary)
+}
+
+// ============================================================================
+// Target identification
+// ============================================================================
+
+/// Options for `identify_target`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentifyTargetOptions {
+    /// Solver options used when the image has no plate solution yet
+    pub solve: Option<PlateSolveCollectionOptions>,
+    /// Also name the image's collection after the target
+    #[serde(default)]
+    pub rename_collection: bool,
+}
+
+/// Outcome of `identify_target`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentifiedTarget {
+    pub image_id: String,
+    /// Most prominent object in the field, if any was found
+    pub target: Option<CatalogObject>,
+    /// Whether the image had to be plate solved first
+    pub solved: bool,
+    /// New collection name, when the collection was renamed
+    pub collection_name: Option<String>,
+}
+
+/// Center, size and rotation of a solved field, in degrees
+#[derive(Debug, Clone, Copy)]
+struct SolvedField {
+    ra: f64,
+    dec: f64,
+    width: f64,
+    height: f64,
+    rotation: f64,
+}
+
+impl SolvedField {
+    /// Field from the "plate_solve" entry of an image's metadata
+    fn from_metadata(metadata: Option<&str>) -> Option<Self> {
+        let metadata: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+        let solve = metadata.get("plate_solve")?;
+        let number = |key: &str| solve.get(key).and_then(|v| v.as_f64());
+        Some(Self {
+            ra: number("center_ra")?,
+            dec: number("center_dec")?,
+            width: number("width_deg").unwrap_or(0.0),
+            height: number("height_deg").unwrap_or(0.0),
+            rotation: number("rotation").unwrap_or(0.0),
+        })
+    }
+
+    fn from_result(result: &PlateSolveResult) -> Self {
+        Self {
+            ra: result.center_ra,
+            dec: result.center_dec,
+            width: result.width_deg,
+            height: result.height_deg,
+            rotation: result.rotation,
+        }
+    }
+}
+
+/// Where an image is on the sky, for coverage maps: its solved field, or
+/// the mount position from its headers with no size
+pub(super) fn image_footprint(image: &Image) -> Option<Footprint> {
+    let metadata = image.metadata.as_deref()?;
+    let label = image
+        .summary
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    if let Some(field) = SolvedField::from_metadata(Some(metadata)) {
+        return Some(Footprint {
+            ra: field.ra,
+            dec: field.dec,
+            width: field.width,
+            height: field.height,
+            rotation: field.rotation,
+            label,
+        });
+    }
+    let (ra, dec) = header_solve_hints(metadata).position?;
+    Some(Footprint {
+        ra,
+        dec,
+        width: 0.0,
+        height: 0.0,
+        rotation: 0.0,
+        label,
+    })
+}
+
+/// How much an object stands out as the subject of an image: named
+/// catalogs first, then apparent size relative to the field, closeness to
+/// the center and brightness. Stars are never the target.
+fn target_prominence(object: &CatalogObject, field: &SolvedField) -> Option<f64> {
+    if object.catalog == "Bright Stars" || object.object_type.starts_with("Star") {
+        return None;
+    }
+    let catalog = match object.catalog.as_str() {
+        "Messier" => 3.0,
+        "NGC" | "IC" => 2.0,
+        "Sharpless" | "LBN" | "Barnard" | "LDN" | "Abell" => 1.0,
+        "PGC" => 0.0,
+        _ => 0.5,
+    };
+
+    let diagonal = field.width.hypot(field.height).max(1e-6);
+    let size = object
+        .size_arcmin
+        .map(|size| (size / 60.0 / diagonal).min(1.0) * 2.0)
+        .unwrap_or(0.0);
+
+    // Angular separation from the field center
+    let (ra1, dec1) = (field.ra.to_radians(), field.dec.to_radians());
+    let (ra2, dec2) = (object.ra.to_radians(), object.dec.to_radians());
+    let cos_sep = dec1.sin() * dec2.sin() + dec1.cos() * dec2.cos() * (ra1 - ra2).cos();
+    let separation = cos_sep.clamp(-1.0, 1.0).acos().to_degrees();
+    let centered = (1.0 - separation / (diagonal / 2.0)).clamp(0.0, 1.0);
+
+    let brightness = object
+        .magnitude
+        .map(|mag| ((15.0 - mag) / 15.0).clamp(0.0, 1.0) * 0.5)
+        .unwrap_or(0.0);
+
+    Some(catalog + size + centered + brightness)
+}
+
+/// The most prominent non-stellar object in a field
+fn most_prominent(objects: &[CatalogObject], field: &SolvedField) -> Option<CatalogObject> {
+    objects
+        .iter()
+        .filter_map(|object| Some((target_prominence(object, field)?, object)))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, object)| object.clone())
+}
+
+/// Collection name after identifying its target: replaces the name of
+/// images with no session date, otherwise adds the target to it
+fn collection_name_with_target(name: &str, target: &str) -> String {
+    if name == "Unknown Session" || name.trim().is_empty() {
+        target.to_string()
+    } else if name.contains(target) {
+        name.to_string()
+    } else {
+        format!("{} - {}", name, target)
+    }
+}
+
+/// Identify the subject of an image whose OBJECT header is missing: plate
+/// solve it if needed, pick the most prominent catalog object in the field
+/// and use it as the image's target (summary)
+#[tauri::command]
+pub async fn identify_target(
+    state: State<'_, AppState>,
+    image_id: String,
+    options: Option<IdentifyTargetOptions>,
+) -> Result<IdentifiedTarget, String> {
+    let options = options.unwrap_or_default();
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, &image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", image_id))?;
+    drop(conn);
+
+    let (field, objects, solved) = match SolvedField::from_metadata(image.metadata.as_deref()) {
+        Some(field) => {
+            let stored: Vec<CatalogObject> = image
+                .annotations
+                .as_deref()
+                .and_then(|a| serde_json::from_str(a).ok())
+                .unwrap_or_default();
+            let objects = if stored.is_empty() {
+                plate_solve::query_objects_in_fov(
+                    field.ra,
+                    field.dec,
+                    field.width,
+                    field.height,
+                    None,
+                    None,
+                    None,
+                    None,
+                )?
+            } else {
+                stored
+            };
+            (field, objects, false)
+        }
+        None => {
+            let solve = options.solve.as_ref().ok_or_else(|| {
+                "Image has no plate solution; solver options are needed to solve it".to_string()
+            })?;
+            let mut input = solve.input_for(&image.id);
+            input.query_catalogs = Some(true);
+            let response = solve_and_record(&state, input, &mut HashMap::new()).await?;
+            if !response.solve_result.success {
+                return Err(response
+                    .solve_result
+                    .error_message
+                    .unwrap_or_else(|| "No solution found".to_string()));
+            }
+            let field = SolvedField::from_result(&response.solve_result);
+            (field, response.objects, true)
+        }
+    };
+
+    let Some(target) = most_prominent(&objects, &field) else {
+        log::info!("No catalog object found to identify {}", image.filename);
+        return Ok(IdentifiedTarget {
+            image_id,
+            target: None,
+            solved,
+            collection_name: None,
+        });
+    };
+    log::info!("Identified {} as {}", image.filename, target.name);
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let update = UpdateImage {
+        summary: Some(target.name.clone()),
+        ..Default::default()
+    };
+    repository::update_image(&mut conn, &image_id, &update).map_err(|e| e.to_string())?;
+
+    let mut collection_name = None;
+    if options.rename_collection {
+        if let Some(collection_id) = &image.collection_id {
+            let collection = repository::get_collection_by_id(&mut conn, collection_id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Collection not found: {}", collection_id))?;
+            let name = collection_name_with_target(&collection.name, &target.name);
+            if name != collection.name {
+                let update = UpdateCollection {
+                    name: Some(name.clone()),
+                    ..Default::default()
+                };
+                repository::update_collection(&mut conn, collection_id, &update)
+                    .map_err(|e| e.to_string())?;
+                collection_name = Some(name);
+            }
+        }
+    }
+
+    Ok(IdentifiedTarget {
+        image_id,
+        target: Some(target),
+        solved,
+        collection_name,
     })
 }
 
 /// Detect which plate solvers are installed on the system
 #[tauri::command]
-pub fn detect_plate_solvers() -> Result<std::collections::HashMap<String, SolverInfo>, String> {
-    plate_solve::detect_solvers()
+pub fn detect_plate_solvers(
+    astap_path: Option<String>,
+    solve_field_path: Option<String>,
+) -> Result<std::collections::HashMap<String, SolverInfo>, String> {
+    // ASTAP and solve-field run natively, so report them even when Python
+    // is unavailable
+    let mut solvers = plate_solve::detect_solvers().unwrap_or_else(|e| {
+        log::warn!("Python solver detection failed: {}", e);
+        Default::default()
+    });
+    solvers.insert(
+        "astap".to_string(),
+        astap::detect(astap_path.as_deref().map(Path::new)),
+    );
+    solvers.insert(
+        "local".to_string(),
+        astrometry_net::detect(solve_field_path.as_deref().map(Path::new)),
+    );
+    Ok(solvers)
 }
 
 /// Extract plate solving hints from a FITS file's headers
@@ -672,3 +1469,148 @@ pub fn query_sky_region(
         None,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solved_images_are_detected_from_metadata() {
+        let solved = r#"{"plate_solve":{"center_ra":10.5}}"#;
+        let failed = r#"{"plate_solve_failed":{"solver":"astap"}}"#;
+        assert!(is_plate_solved(Some(solved)));
+        assert!(!is_plate_solved(Some(failed)));
+        assert!(!is_plate_solved(Some("not json")));
+        assert!(!is_plate_solved(None));
+    }
+
+    #[test]
+    fn header_hints_from_raw_headers() {
+        let metadata = r#"{"raw_headers":{
+            "FOCALLEN":"Some(RealFloatingNumber(250.0))",
+            "XPIXSZ":"Some(RealFloatingNumber(3.76))",
+            "OBJCTRA":"Some(CharacterString(\"05 35 17.30\"))",
+            "OBJCTDEC":"Some(CharacterString(\"-05 23 28.0\"))"}}"#;
+        let hints = header_solve_hints(metadata);
+        let (lower, upper) = hints.scale.unwrap();
+        let scale = 206.265 * 3.76 / 250.0;
+        assert!((lower - scale * 0.85).abs() < 1e-9 && (upper - scale * 1.15).abs() < 1e-9);
+        let (ra, dec) = hints.position.unwrap();
+        assert!((ra - 83.822083).abs() < 1e-5, "{}", ra);
+        assert!((dec + 5.391111).abs() < 1e-5, "{}", dec);
+    }
+
+    #[test]
+    fn header_hints_prefer_decimal_ra_dec() {
+        let metadata = r#"{"ra":"83.820000","dec":"-5.390000","OBJCTRA":"00 00 00",
+            "focal_length":250.0}"#;
+        let hints = header_solve_hints(metadata);
+        assert_eq!(hints.position, Some((83.82, -5.39)));
+        // No pixel size, so no scale
+        assert_eq!(hints.scale, None);
+        assert_eq!(header_solve_hints("{}"), HeaderHints::default());
+    }
+
+    #[test]
+    fn parses_sexagesimal_angles() {
+        assert_eq!(parse_angle("12:30:00", 15.0), Some(187.5));
+        assert_eq!(parse_angle("12h30m00s", 15.0), Some(187.5));
+        assert_eq!(parse_angle("-00 30 00", 1.0), Some(-0.5));
+        assert_eq!(parse_angle("+41 15", 1.0), Some(41.25));
+        assert_eq!(parse_angle("83.82", 15.0), Some(83.82));
+        assert_eq!(parse_angle("north", 1.0), None);
+    }
+
+    #[test]
+    fn solution_wcs_falls_back_to_center_and_scale() {
+        let mut result = PlateSolveResult {
+            success: true,
+            center_ra: 83.8,
+            center_dec: -5.4,
+            pixel_scale: 3.6,
+            rotation: 0.0,
+            width_deg: 0.1,
+            height_deg: 0.05,
+            image_width: 100,
+            image_height: 50,
+            solver: "nova".to_string(),
+            solve_time: 1.0,
+            error_message: None,
+            wcs: None,
+        };
+        let wcs = solution_wcs(&result).unwrap();
+        assert_eq!(wcs.crval, [83.8, -5.4]);
+        assert_eq!(wcs.crpix, [50.5, 25.5]);
+
+        result.wcs = Some(serde_json::json!({
+            "crval": [83.9, -5.3],
+            "crpix": [10.0, 20.0],
+            "cd": [[-1e-3, 0.0], [0.0, 1e-3]],
+        }));
+        assert_eq!(solution_wcs(&result).unwrap().crpix, [10.0, 20.0]);
+    }
+
+    fn catalog_object(name: &str, catalog: &str, object_type: &str, ra: f64) -> CatalogObject {
+        CatalogObject {
+            name: name.to_string(),
+            catalog: catalog.to_string(),
+            object_type: object_type.to_string(),
+            ra,
+            dec: 0.0,
+            magnitude: None,
+            size: None,
+            size_arcmin: None,
+            common_name: None,
+            pixel_x: None,
+            pixel_y: None,
+            radius_px: None,
+        }
+    }
+
+    #[test]
+    fn most_prominent_prefers_large_named_objects_over_stars() {
+        let field = SolvedField {
+            ra: 10.0,
+            dec: 0.0,
+            width: 2.0,
+            height: 1.5,
+            rotation: 0.0,
+        };
+        let mut star = catalog_object("Betelgeuse", "Bright Stars", "Star (M1)", 10.0);
+        star.magnitude = Some(0.5);
+        let galaxy = catalog_object("PGC 12345", "PGC", "Sb", 10.0);
+        let mut ngc = catalog_object("NGC 1977", "NGC", "Bright Nebula", 10.5);
+        ngc.size_arcmin = Some(20.0);
+        let mut messier = catalog_object("M42", "Messier", "Nebula", 10.2);
+        messier.size_arcmin = Some(65.0);
+
+        let objects = vec![star.clone(), galaxy.clone(), ngc, messier];
+        assert_eq!(most_prominent(&objects, &field).unwrap().name, "M42");
+        let fallback = most_prominent(&[star.clone(), galaxy], &field).unwrap();
+        assert_eq!(fallback.name, "PGC 12345");
+        assert!(most_prominent(&[star], &field).is_none());
+    }
+
+    #[test]
+    fn collection_name_gains_the_target() {
+        assert_eq!(collection_name_with_target("Unknown Session", "M42"), "M42");
+        assert_eq!(
+            collection_name_with_target("2024-01-15", "M42"),
+            "2024-01-15 - M42"
+        );
+        assert_eq!(
+            collection_name_with_target("2024-01-15 - M42", "M42"),
+            "2024-01-15 - M42"
+        );
+    }
+
+    #[test]
+    fn solved_field_from_metadata() {
+        let metadata = r#"{"plate_solve":{"center_ra":83.8,"center_dec":-5.4,"width_deg":1.2}}"#;
+        let field = SolvedField::from_metadata(Some(metadata)).unwrap();
+        assert_eq!((field.ra, field.dec), (83.8, -5.4));
+        assert_eq!((field.width, field.height), (1.2, 0.0));
+        assert!(SolvedField::from_metadata(Some(r#"{"plate_solve_failed":{}}"#)).is_none());
+        assert!(SolvedField::from_metadata(None).is_none());
+    }
+}
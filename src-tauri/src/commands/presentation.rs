@@ -0,0 +1,379 @@
+//! Share-ready JPEG export with a caption bar and watermark
+//!
+//! The caption (target, date, exposure summary, equipment) goes in a bar
+//! under the image rather than over it, and the watermark is drawn in a
+//! corner. Both use the built-in bitmap font. The output keeps the source's
+//! embedded ICC profile so its colors don't shift; sources without one are
+//! taken as sRGB, as Astra's own previews are, and tagged accordingly.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageReader, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::bitmap_font::{draw_text, text_height, text_width};
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+use crate::stretch::srgb_icc_profile;
+
+use super::gallery_export::{gallery_entry, load_source_image};
+
+const CAPTION_BACKGROUND: Rgb<u8> = Rgb([0x0b, 0x0d, 0x12]);
+const CAPTION_TITLE: Rgb<u8> = Rgb([0xf2, 0xf3, 0xf5]);
+const CAPTION_DETAILS: Rgb<u8> = Rgb([0x8b, 0x90, 0xa0]);
+const WATERMARK: Rgb<u8> = Rgb([0xff, 0xff, 0xff]);
+const WATERMARK_SHADOW: Rgb<u8> = Rgb([0x00, 0x00, 0x00]);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PresentationOptions {
+    /// Longest side of the image in pixels; full size when unset
+    pub max_size: Option<u32>,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+    /// Add a caption bar under the image
+    pub caption: bool,
+    /// Text drawn over the image, e.g. a name or copyright notice
+    pub watermark: Option<String>,
+    pub watermark_position: WatermarkPosition,
+    /// 0 (invisible) to 1 (solid)
+    pub watermark_opacity: f32,
+}
+
+impl Default for PresentationOptions {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            quality: 92,
+            caption: true,
+            watermark: None,
+            watermark_position: WatermarkPosition::default(),
+            watermark_opacity: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationExportResult {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Caption lines for an image: target and date, then the exposure summary,
+/// then equipment. Lines with nothing to show are left out.
+pub fn caption_lines(image: &Image) -> Vec<String> {
+    let entry = gallery_entry(image);
+    let detail = |label: &str| {
+        entry
+            .details
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, value)| value.clone())
+    };
+    let join =
+        |parts: Vec<Option<String>>| parts.into_iter().flatten().collect::<Vec<_>>().join(" · ");
+
+    let title = join(vec![Some(entry.title.clone()), entry.date.clone()]);
+    let exposure = join(vec![
+        detail("Exposure"),
+        detail("Integration").map(|total| format!("{} total", total)),
+        detail("Filter"),
+        detail("Gain").map(|gain| format!("gain {}", gain)),
+    ]);
+    let equipment = join(vec![detail("Telescope"), detail("Camera")]);
+
+    [title, exposure, equipment]
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// `text` shortened with "..." to fit in `max_width` pixels at `scale`
+fn fit_text(text: &str, max_width: u32, scale: u32) -> String {
+    if text_width(text, scale) <= max_width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && text_width(&format!("{}...", fitted), scale) > max_width {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+/// Font scale for text on an image `size` pixels across
+fn text_scale(size: u32, per_scale: u32) -> u32 {
+    ((size as f32 / per_scale as f32).round() as u32).max(1)
+}
+
+fn draw_watermark(image: &mut RgbImage, text: &str, position: WatermarkPosition, opacity: f32) {
+    let scale = text_scale(image.width().min(image.height()), 400);
+    let margin = 6 * scale;
+    let text = fit_text(text, image.width().saturating_sub(2 * margin), scale);
+    let (width, height) = (text_width(&text, scale), text_height(scale));
+    let (image_width, image_height) = (image.width(), image.height());
+
+    let left = margin as i64;
+    let right = image_width as i64 - margin as i64 - width as i64;
+    let top = margin as i64;
+    let bottom = image_height as i64 - margin as i64 - height as i64;
+    let at = match position {
+        WatermarkPosition::TopLeft => (left, top),
+        WatermarkPosition::TopRight => (right, top),
+        WatermarkPosition::BottomLeft => (left, bottom),
+        WatermarkPosition::BottomRight => (right, bottom),
+        WatermarkPosition::Center => (
+            (image_width as i64 - width as i64) / 2,
+            (image_height as i64 - height as i64) / 2,
+        ),
+    };
+    // A soft shadow keeps light text readable over bright nebulosity
+    let shadow = (at.0 + scale as i64, at.1 + scale as i64);
+    draw_text(image, shadow, &text, scale, WATERMARK_SHADOW, opacity * 0.6);
+    draw_text(image, at, &text, scale, WATERMARK, opacity);
+}
+
+/// `image` with a caption bar of `lines` added under it
+fn add_caption(image: RgbImage, lines: &[String]) -> RgbImage {
+    let scale = text_scale(image.width(), 600);
+    let padding = 6 * scale;
+    let line_height = text_height(scale) + 4 * scale;
+    let bar_height = 2 * padding + lines.len() as u32 * line_height - 4 * scale;
+
+    let mut captioned = RgbImage::from_pixel(
+        image.width(),
+        image.height() + bar_height,
+        CAPTION_BACKGROUND,
+    );
+    image::imageops::replace(&mut captioned, &image, 0, 0);
+
+    let max_width = image.width().saturating_sub(2 * padding);
+    for (i, line) in lines.iter().enumerate() {
+        let color = if i == 0 {
+            CAPTION_TITLE
+        } else {
+            CAPTION_DETAILS
+        };
+        let top = image.height() + padding + i as u32 * line_height;
+        draw_text(
+            &mut captioned,
+            (padding as i64, top as i64),
+            &fit_text(line, max_width, scale),
+            scale,
+            color,
+            1.0,
+        );
+    }
+    captioned
+}
+
+/// Render the presentation image: resized, watermarked, then captioned
+pub fn render_presentation(
+    source: &DynamicImage,
+    caption: &[String],
+    options: &PresentationOptions,
+) -> RgbImage {
+    let mut image = match options.max_size {
+        Some(max) if source.width() > max || source.height() > max => {
+            source.resize(max, max, image::imageops::FilterType::Lanczos3)
+        }
+        _ => source.clone(),
+    }
+    .to_rgb8();
+
+    if let Some(text) = options.watermark.as_deref().map(str::trim) {
+        if !text.is_empty() {
+            draw_watermark(
+                &mut image,
+                text,
+                options.watermark_position,
+                options.watermark_opacity,
+            );
+        }
+    }
+    if options.caption && !caption.is_empty() {
+        image = add_caption(image, caption);
+    }
+    image
+}
+
+/// Open `path` along with its embedded ICC profile, if it has one
+fn open_with_profile(path: &Path) -> Option<(DynamicImage, Option<Vec<u8>>)> {
+    let mut decoder = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let profile = decoder.icc_profile().ok().flatten();
+    let image = DynamicImage::from_decoder(decoder).ok()?;
+    Some((image, profile))
+}
+
+/// The image's own file with its profile, or else whatever preview
+/// `load_source_image` finds (which is sRGB)
+fn load_source(image: &Image, previews_dir: &Path) -> Option<(DynamicImage, Option<Vec<u8>>)> {
+    image
+        .url
+        .as_deref()
+        .map(Path::new)
+        .filter(|path| path.exists())
+        .and_then(open_with_profile)
+        .or_else(|| load_source_image(image, previews_dir).map(|source| (source, None)))
+}
+
+/// Write an 8-bit JPEG tagged with `profile`, or sRGB without one
+pub fn write_presentation_jpeg(
+    image: &RgbImage,
+    profile: Option<&[u8]>,
+    quality: u8,
+    path: &Path,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create JPEG: {}", e))?;
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+        BufWriter::new(file),
+        quality.clamp(1, 100),
+    );
+    encoder
+        .set_icc_profile(profile.unwrap_or(srgb_icc_profile()).to_vec())
+        .map_err(|e| format!("Failed to embed ICC profile: {}", e))?;
+    encoder
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| format!("Failed to write JPEG: {}", e))
+}
+
+/// Export an image as a share-ready JPEG at `path`
+#[tauri::command]
+pub async fn export_presentation_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    image_id: String,
+    path: String,
+    options: Option<PresentationOptions>,
+) -> Result<PresentationExportResult, String> {
+    let options = options.unwrap_or_default();
+    let previews_dir = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, &image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Image not found: {}", image_id))?;
+    drop(conn);
+
+    tokio::task::spawn_blocking(move || {
+        let (source, profile) = load_source(&image, &previews_dir)
+            .ok_or_else(|| format!("No displayable file for {}", image.filename))?;
+        let rendered = render_presentation(&source, &caption_lines(&image), &options);
+        let path = PathBuf::from(path);
+        write_presentation_jpeg(&rendered, profile.as_deref(), options.quality, &path)?;
+        Ok(PresentationExportResult {
+            path: path.to_string_lossy().to_string(),
+            width: rendered.width(),
+            height: rendered.height(),
+        })
+    })
+    .await
+    .map_err(|e| format!("Presentation export failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    fn image_with_metadata(metadata: &str) -> Image {
+        Image {
+            filename: "Stacked_M 31.fit".to_string(),
+            summary: Some("M 31".to_string()),
+            metadata: Some(metadata.to_string()),
+            ..Image::test("img")
+        }
+    }
+
+    #[test]
+    fn caption_summarizes_target_exposure_and_equipment() {
+        let image = image_with_metadata(
+            r#"{"date_obs": "2024-10-05T23:10:00", "exposure": 10.0, "stacked_frames": 120,
+                "filter": "Ha", "gain": 80, "telescope": "Seestar S50",
+                "raw_headers": {"INSTRUME": "IMX462"}}"#,
+        );
+        let lines = caption_lines(&image);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("M 31 · 2024-10-0"));
+        assert_eq!(lines[1], "120 × 10s · 20m 0s total · Ha · gain 80");
+        assert_eq!(lines[2], "Seestar S50 · IMX462");
+
+        // No acquisition details leaves just the title
+        assert_eq!(caption_lines(&image_with_metadata("{}")), vec!["M 31"]);
+    }
+
+    #[test]
+    fn long_text_is_shortened_to_fit() {
+        assert_eq!(fit_text("M 31", 100, 1), "M 31");
+        let fitted = fit_text("Andromeda Galaxy", 59, 1);
+        assert_eq!(fitted, "Androme...");
+        assert!(text_width(&fitted, 1) <= 59);
+    }
+
+    #[test]
+    fn presentation_adds_caption_and_watermark_and_keeps_the_profile() {
+        let source = DynamicImage::ImageRgb8(RgbImage::from_pixel(1200, 800, Rgb([40, 40, 40])));
+        let options = PresentationOptions {
+            max_size: Some(600),
+            watermark: Some("© Astra".to_string()),
+            watermark_opacity: 1.0,
+            ..Default::default()
+        };
+        let caption = vec!["M 31 · 2024-10-05".to_string(), "120 × 10s".to_string()];
+        let rendered = render_presentation(&source, &caption, &options);
+
+        // Resized to 600×400 with a bar for two lines at scale 1 under it
+        assert_eq!(rendered.width(), 600);
+        assert_eq!(rendered.height(), 400 + 2 * 6 + 2 * 11 - 4);
+        assert_eq!(*rendered.get_pixel(599, 429), CAPTION_BACKGROUND);
+        // The watermark is drawn in white somewhere in the bottom-right corner
+        let watermarked = (400..600)
+            .flat_map(|x| (300..400).map(move |y| (x, y)))
+            .any(|(x, y)| *rendered.get_pixel(x, y) == WATERMARK);
+        assert!(watermarked);
+        assert_eq!(*rendered.get_pixel(10, 10), Rgb([40, 40, 40]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("share.jpg");
+        let profile = b"not really a profile".to_vec();
+        write_presentation_jpeg(&rendered, Some(&profile), 90, &path).unwrap();
+        let mut decoder =
+            image::codecs::jpeg::JpegDecoder::new(BufReader::new(File::open(&path).unwrap()))
+                .unwrap();
+        assert_eq!(decoder.icc_profile().unwrap(), Some(profile));
+        assert_eq!(decoder.dimensions(), (600, 430));
+
+        let (_, read_back) = open_with_profile(&path).unwrap();
+        assert_eq!(read_back.as_deref(), Some(&b"not really a profile"[..]));
+    }
+}
@@ -0,0 +1,113 @@
+//! Preview cache settings and maintenance
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::preview_cache::PreviewCache;
+use crate::state::AppState;
+
+use super::settings::{load_settings, save_settings, PREVIEW_CACHE};
+
+/// Cached previews, under the app cache dir
+const CACHE_DIR: &str = "previews";
+/// Where annotated previews were kept before they moved into the cache
+const LEGACY_ANNOTATED_DIR: &str = "annotated";
+
+const DEFAULT_MAX_SIZE_MB: u64 = 1024;
+const MAX_SIZE_MB: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreviewCacheSettings {
+    /// Size cap; least recently used previews are removed past it
+    pub max_size_mb: u64,
+}
+
+impl Default for PreviewCacheSettings {
+    fn default() -> Self {
+        Self {
+            max_size_mb: DEFAULT_MAX_SIZE_MB,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewCacheStatus {
+    pub max_size_mb: u64,
+    pub used_bytes: u64,
+    pub files: usize,
+    pub path: String,
+}
+
+/// Preview cache with the saved size cap, for the app state
+pub fn load_preview_cache(app: &AppHandle) -> PreviewCache {
+    let settings: PreviewCacheSettings = load_settings(app, &PREVIEW_CACHE).unwrap_or_default();
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map(|d| d.join(CACHE_DIR))
+        .unwrap_or_else(|_| std::env::temp_dir().join("astra-previews"));
+
+    // Annotated previews now live in the cache; drop the old renders
+    if let Ok(legacy) = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join(LEGACY_ANNOTATED_DIR))
+    {
+        if legacy.is_dir() {
+            if let Err(e) = fs::remove_dir_all(&legacy) {
+                log::warn!("Failed to remove old annotated previews: {}", e);
+            }
+        }
+    }
+
+    PreviewCache::new(dir, settings.max_size_mb * 1024 * 1024)
+}
+
+fn cache_status(cache: &PreviewCache) -> PreviewCacheStatus {
+    let (used_bytes, files) = cache.usage();
+    PreviewCacheStatus {
+        max_size_mb: cache.max_bytes() / (1024 * 1024),
+        used_bytes,
+        files,
+        path: cache.dir().to_string_lossy().to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_preview_cache_status(state: State<'_, AppState>) -> Result<PreviewCacheStatus, String> {
+    Ok(cache_status(&state.preview_cache))
+}
+
+/// Save the preview cache settings; None restores the defaults
+#[tauri::command]
+pub fn set_preview_cache_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: Option<PreviewCacheSettings>,
+) -> Result<PreviewCacheStatus, String> {
+    let settings = settings.unwrap_or_default();
+    if settings.max_size_mb > MAX_SIZE_MB {
+        return Err(format!(
+            "Preview cache size must be at most {} MB",
+            MAX_SIZE_MB
+        ));
+    }
+
+    save_settings(&app, &PREVIEW_CACHE, &settings)?;
+    state
+        .preview_cache
+        .set_max_bytes(settings.max_size_mb * 1024 * 1024);
+    Ok(cache_status(&state.preview_cache))
+}
+
+/// Remove every cached preview
+#[tauri::command]
+pub fn clear_preview_cache(state: State<'_, AppState>) -> Result<PreviewCacheStatus, String> {
+    state.preview_cache.clear()?;
+    log::info!("Cleared preview cache");
+    Ok(cache_status(&state.preview_cache))
+}
@@ -0,0 +1,113 @@
+//! Python environment settings: which virtualenv the bundled astra_astro
+//! module loads its dependencies from, whether it runs embedded or in a
+//! subprocess, and re-initializing after a change
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::python::{self, PythonBackend, PythonConfig, PythonStatus};
+
+use super::settings::{load_settings, save_settings, PYTHON_ENV};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PythonSettings {
+    /// Virtualenv root or site-packages directory to use instead of discovery
+    pub venv_path: Option<String>,
+    #[serde(default)]
+    pub backend: PythonBackend,
+    /// Interpreter for the subprocess backend (default: the venv's python)
+    #[serde(default)]
+    pub python_executable: Option<String>,
+}
+
+impl PythonSettings {
+    fn config(&self) -> PythonConfig {
+        PythonConfig {
+            venv_path: self.venv_path.as_ref().map(PathBuf::from),
+            backend: self.backend,
+            python_executable: self.python_executable.as_ref().map(PathBuf::from),
+        }
+    }
+}
+
+/// Saved Python configuration, used at startup
+pub fn saved_python_config(app: &AppHandle) -> PythonConfig {
+    load_settings::<PythonSettings>(app, &PYTHON_ENV)
+        .map(|settings| settings.config())
+        .unwrap_or_default()
+}
+
+async fn reinit(config: PythonConfig) -> Result<PythonStatus, String> {
+    tokio::task::spawn_blocking(move || python::reinit_python(&config))
+        .await
+        .map_err(|e| format!("Task panicked: {}", e))
+}
+
+/// Status of the Python environment from the last initialization
+#[tauri::command]
+pub fn get_python_status() -> PythonStatus {
+    python::python_status()
+}
+
+/// Get the saved Python settings
+#[tauri::command]
+pub fn get_python_settings(app: AppHandle) -> Result<PythonSettings, String> {
+    load_settings(&app, &PYTHON_ENV)
+}
+
+/// Set (or clear) the virtualenv path and re-initialize Python with it
+#[tauri::command]
+pub async fn set_python_venv_path(
+    app: AppHandle,
+    venv_path: Option<String>,
+) -> Result<PythonStatus, String> {
+    let venv_path = venv_path.filter(|p| !p.trim().is_empty());
+    if let Some(p) = &venv_path {
+        if !Path::new(p).is_dir() {
+            return Err(format!("Directory not found: {}", p));
+        }
+    }
+
+    let settings = PythonSettings {
+        venv_path,
+        ..load_settings(&app, &PYTHON_ENV)?
+    };
+    save_settings(&app, &PYTHON_ENV, &settings)?;
+
+    reinit(settings.config()).await
+}
+
+/// Choose the Python backend (and optionally the interpreter the subprocess
+/// backend runs) and re-initialize Python with it
+#[tauri::command]
+pub async fn set_python_backend(
+    app: AppHandle,
+    backend: PythonBackend,
+    python_executable: Option<String>,
+) -> Result<PythonStatus, String> {
+    let python_executable = python_executable.filter(|p| !p.trim().is_empty());
+    // A bare command name ("python3.12") is looked up on PATH when started
+    if let Some(p) = &python_executable {
+        if Path::new(p).is_absolute() && !Path::new(p).is_file() {
+            return Err(format!("File not found: {}", p));
+        }
+    }
+
+    let settings = PythonSettings {
+        backend,
+        python_executable,
+        ..load_settings(&app, &PYTHON_ENV)?
+    };
+    save_settings(&app, &PYTHON_ENV, &settings)?;
+
+    reinit(settings.config()).await
+}
+
+/// Re-run Python discovery, e.g. after installing the virtualenv
+#[tauri::command]
+pub async fn reinitialize_python(app: AppHandle) -> Result<PythonStatus, String> {
+    reinit(saved_python_config(&app)).await
+}
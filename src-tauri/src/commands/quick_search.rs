@@ -0,0 +1,230 @@
+//! Quick search across the library for a launcher-style search box
+//!
+//! One query is matched against targets, collections, images, observing
+//! list entries, notes and the embedded deep sky catalogs. Matching ignores
+//! case and spaces, so "m42" finds "M 42"; results are ranked by how well
+//! they match, then by kind.
+
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::repository;
+use crate::skymap;
+use crate::state::AppState;
+
+use super::images::listed_images;
+use super::notes::note_title;
+
+const DEFAULT_LIMIT: usize = 20;
+
+/// What a quick search result refers to. Ties in relevance are ordered by
+/// kind, in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuickSearchKind {
+    Target,
+    Collection,
+    Image,
+    Todo,
+    Note,
+    CatalogObject,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickSearchResult {
+    pub kind: QuickSearchKind,
+    /// Record id; the name for targets and catalog objects
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub thumbnail: Option<String>,
+    /// Higher is a better match
+    pub score: u32,
+}
+
+/// Lowercase with whitespace removed
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// How well `text` matches a normalized query: an exact match beats a
+/// prefix, which beats a word starting with the query, which beats any
+/// other substring
+fn match_score(query: &str, text: &str) -> Option<u32> {
+    let normalized = normalize(text);
+    if normalized == query {
+        Some(100)
+    } else if normalized.starts_with(query) {
+        Some(75)
+    } else if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| normalize(word).starts_with(query))
+    {
+        Some(50)
+    } else if normalized.contains(query) {
+        Some(25)
+    } else {
+        None
+    }
+}
+
+/// Best score of any of `texts`
+fn best_score<'a>(query: &str, texts: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+    texts
+        .into_iter()
+        .filter_map(|text| match_score(query, text))
+        .max()
+}
+
+/// Best matches first; equal scores by kind, then shorter titles
+fn rank(results: &mut Vec<QuickSearchResult>, limit: usize) {
+    results.sort_by_key(|r| (Reverse(r.score), r.kind, r.title.len()));
+    results.truncate(limit);
+}
+
+fn catalog_results(query: &str) -> Vec<QuickSearchResult> {
+    skymap::deep_sky_objects()
+        .iter()
+        .filter_map(|object| {
+            let names = std::iter::once(object.name.as_str())
+                .chain(object.common_names.iter().map(String::as_str));
+            let score = best_score(query, names)?;
+            let mut subtitle: Vec<&str> = object.object_type.iter().map(String::as_str).collect();
+            subtitle.extend(object.common_names.iter().map(String::as_str));
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::CatalogObject,
+                id: object.name.clone(),
+                title: object.name.clone(),
+                subtitle: (!subtitle.is_empty()).then(|| subtitle.join(", ")),
+                thumbnail: None,
+                score,
+            })
+        })
+        .collect()
+}
+
+/// Search targets, collections, images, the observing list, notes and
+/// catalog objects at once, best matches first
+#[tauri::command]
+pub async fn quick_search(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickSearchResult>, String> {
+    let query = normalize(&query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let mut results = Vec::new();
+
+        let targets =
+            repository::get_targets_with_counts(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        results.extend(targets.into_iter().filter_map(|target| {
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::Target,
+                score: match_score(&query, &target.name)?,
+                id: target.name.clone(),
+                subtitle: Some(format!("{} images", target.image_count)),
+                title: target.name,
+                thumbnail: target.latest_thumbnail,
+            })
+        }));
+
+        let collections =
+            repository::get_collections(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        results.extend(collections.into_iter().filter_map(|collection| {
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::Collection,
+                score: match_score(&query, &collection.name)?,
+                id: collection.id,
+                title: collection.name,
+                subtitle: collection.description,
+                thumbnail: None,
+            })
+        }));
+
+        let images =
+            repository::get_images_by_user(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        results.extend(listed_images(images, None).into_iter().filter_map(|image| {
+            let names = std::iter::once(image.filename.as_str()).chain(image.summary.as_deref());
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::Image,
+                score: best_score(&query, names)?,
+                id: image.id,
+                title: image.filename,
+                subtitle: image.summary,
+                thumbnail: image.thumbnail,
+            })
+        }));
+
+        let todos = repository::get_todos(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        let notes = repository::get_notes(&mut conn, &user_id).map_err(|e| e.to_string())?;
+        drop(conn);
+        results.extend(todos.into_iter().filter_map(|todo| {
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::Todo,
+                score: match_score(&query, &todo.name)?,
+                id: todo.id,
+                title: todo.name,
+                subtitle: todo.object_type,
+                thumbnail: None,
+            })
+        }));
+
+        results.extend(notes.into_iter().filter_map(|note| {
+            Some(QuickSearchResult {
+                kind: QuickSearchKind::Note,
+                score: best_score(&query, note.body.lines())?,
+                id: note.id,
+                title: note_title(&note.body),
+                subtitle: Some(format!("{} {}", note.entity_type, note.entity_id)),
+                thumbnail: None,
+            })
+        }));
+
+        results.extend(catalog_results(&query));
+        rank(&mut results, limit);
+        Ok(results)
+    })
+    .await
+    .map_err(|e| format!("Quick search failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_ignore_case_and_spaces() {
+        assert_eq!(match_score("m42", "M 42"), Some(100));
+        assert_eq!(match_score("m4", "M 42"), Some(75));
+        assert_eq!(match_score("orion", "Great Orion Nebula"), Some(50));
+        assert_eq!(match_score("rion", "Orion Nebula"), Some(25));
+        assert_eq!(match_score("m31", "M 42"), None);
+    }
+
+    #[test]
+    fn catalog_objects_match_common_names() {
+        let results = catalog_results(&normalize("Orion Nebula"));
+        let orion = results.iter().find(|r| r.id == "NGC 1976").unwrap();
+        assert_eq!(orion.score, 100);
+        assert!(orion.subtitle.as_deref().unwrap().contains("M42"));
+
+        let mut results = catalog_results("m42");
+        rank(&mut results, 3);
+        assert_eq!(results[0].title, "M 42");
+        assert!(results.len() <= 3);
+    }
+}
@@ -0,0 +1,488 @@
+//! Saved search commands: named image and todo queries that can be re-run
+
+use std::collections::HashSet;
+
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{AstronomyTodo, Image, NewSavedSearch, SavedSearch, UpdateSavedSearch};
+use crate::db::repository::{self, AcquisitionQuery};
+use crate::state::AppState;
+
+use super::filters::{filter_band, normalize_filter_name};
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// What a search runs over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchEntity {
+    Images,
+    Todos,
+}
+
+impl SearchEntity {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchEntity::Images => "images",
+            SearchEntity::Todos => "todos",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "images" => Ok(SearchEntity::Images),
+            "todos" => Ok(SearchEntity::Todos),
+            _ => Err(format!("Unknown search entity: {}", value)),
+        }
+    }
+}
+
+/// Date range ending today, so a saved "this year" stays current
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchPeriod {
+    Last7Days,
+    Last30Days,
+    ThisMonth,
+    ThisYear,
+}
+
+impl SearchPeriod {
+    /// First day of the period ending `today`
+    fn start(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            SearchPeriod::Last7Days => today - Duration::days(6),
+            SearchPeriod::Last30Days => today - Duration::days(29),
+            SearchPeriod::ThisMonth => today.with_day(1).unwrap_or(today),
+            SearchPeriod::ThisYear => NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today),
+        }
+    }
+}
+
+/// Criteria for a search; unset fields match anything. Image-only fields
+/// are ignored for todos and vice versa.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCriteria {
+    /// Case-insensitive text in an image's summary, filename or description,
+    /// or a todo's name or notes
+    pub text: Option<String>,
+    /// Tags that must all be present
+    pub tags: Option<Vec<String>>,
+    /// Only images observed (todos added) in this period
+    pub period: Option<SearchPeriod>,
+    /// Telescope, filter, gain, exposure and date bounds (images)
+    #[serde(flatten)]
+    pub acquisition: AcquisitionQuery,
+    /// Filter band: "narrowband", "broadband" or "dualband" (images)
+    pub band: Option<String>,
+    pub favorite: Option<bool>,
+    pub collection_id: Option<String>,
+    /// Processed outputs only, or only originals without a processed
+    /// version (images)
+    pub processed: Option<bool>,
+    pub completed: Option<bool>,
+    pub flagged: Option<bool>,
+    pub object_type: Option<String>,
+}
+
+/// One page of search results. Only the list for the search's entity is
+/// filled.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub entity: SearchEntity,
+    /// Matches across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub images: Vec<Image>,
+    pub todos: Vec<AstronomyTodo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSavedSearchInput {
+    pub name: String,
+    pub entity: SearchEntity,
+    pub criteria: SearchCriteria,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSavedSearchInput {
+    pub id: String,
+    pub name: Option<String>,
+    pub entity: Option<SearchEntity>,
+    pub criteria: Option<SearchCriteria>,
+}
+
+fn search_name(name: String) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Search name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+fn criteria_to_string(criteria: &SearchCriteria) -> Result<String, String> {
+    serde_json::to_string(criteria).map_err(|e| e.to_string())
+}
+
+fn contains_text(text: &str, fields: &[Option<&str>]) -> bool {
+    let text = text.trim().to_lowercase();
+    text.is_empty()
+        || fields
+            .iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(&text))
+}
+
+/// Whether every wanted tag is present, ignoring case
+fn has_tags(have: &[String], want: &[String]) -> bool {
+    want.iter()
+        .all(|tag| have.iter().any(|t| t.eq_ignore_ascii_case(tag.trim())))
+}
+
+/// Image tags are stored comma-separated
+fn image_tags(image: &Image) -> Vec<String> {
+    image
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Todo tags are stored as a JSON array
+fn todo_tags(todo: &AstronomyTodo) -> Vec<String> {
+    todo.tags
+        .as_deref()
+        .and_then(|t| serde_json::from_str(t).ok())
+        .unwrap_or_default()
+}
+
+/// Whether an image is the output of processing another image
+fn is_processed_output(image: &Image) -> bool {
+    let from_source = image
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .is_some_and(|m| m.get("source_image_id").is_some());
+    from_source || image_tags(image).iter().any(|t| t == "processed")
+}
+
+/// Rows an image search needs beyond the images themselves
+struct ImageSearchContext {
+    /// Members of `collection_id`, if the criteria name one
+    collection: Option<HashSet<String>>,
+    /// Images with at least one processed version
+    processed_sources: HashSet<String>,
+}
+
+/// Acquisition query for an image search, with the period folded into the
+/// date bounds
+fn image_query(criteria: &SearchCriteria, today: NaiveDate) -> AcquisitionQuery {
+    let mut query = criteria.acquisition.clone();
+    if let Some(period) = criteria.period {
+        let start = period.start(today).to_string();
+        query.date_from = Some(match query.date_from {
+            Some(from) if from > start => from,
+            _ => start,
+        });
+    }
+    query
+}
+
+fn image_matches(image: &Image, criteria: &SearchCriteria, context: &ImageSearchContext) -> bool {
+    if let Some(text) = &criteria.text {
+        let fields = [
+            image.summary.as_deref(),
+            Some(image.filename.as_str()),
+            image.description.as_deref(),
+        ];
+        if !contains_text(text, &fields) {
+            return false;
+        }
+    }
+    if let Some(tags) = &criteria.tags {
+        if !has_tags(&image_tags(image), tags) {
+            return false;
+        }
+    }
+    if let Some(band) = &criteria.band {
+        let image_band = image
+            .filter
+            .as_deref()
+            .and_then(normalize_filter_name)
+            .map(|name| filter_band(&name));
+        if image_band != Some(band.as_str()) {
+            return false;
+        }
+    }
+    if criteria.favorite.is_some_and(|f| f != image.favorite) {
+        return false;
+    }
+    if let Some(members) = &context.collection {
+        if !members.contains(&image.id) {
+            return false;
+        }
+    }
+    match criteria.processed {
+        Some(true) => is_processed_output(image),
+        Some(false) => {
+            !is_processed_output(image) && !context.processed_sources.contains(&image.id)
+        }
+        None => true,
+    }
+}
+
+fn todo_matches(todo: &AstronomyTodo, criteria: &SearchCriteria, today: NaiveDate) -> bool {
+    if let Some(text) = &criteria.text {
+        if !contains_text(text, &[Some(todo.name.as_str()), todo.notes.as_deref()]) {
+            return false;
+        }
+    }
+    if let Some(tags) = &criteria.tags {
+        if !has_tags(&todo_tags(todo), tags) {
+            return false;
+        }
+    }
+    if let Some(period) = criteria.period {
+        // added_at is an RFC 3339 timestamp, so its date prefix compares as text
+        let start = period.start(today).to_string();
+        if todo
+            .added_at
+            .get(..10)
+            .is_none_or(|date| date < start.as_str())
+        {
+            return false;
+        }
+    }
+    if criteria.completed.is_some_and(|c| c != todo.completed)
+        || criteria.flagged.is_some_and(|f| f != todo.flagged)
+    {
+        return false;
+    }
+    match &criteria.object_type {
+        Some(object_type) => todo
+            .object_type
+            .as_deref()
+            .is_some_and(|t| t.eq_ignore_ascii_case(object_type)),
+        None => true,
+    }
+}
+
+/// Run a search and return the requested page
+fn run_search(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    entity: SearchEntity,
+    criteria: &SearchCriteria,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<SearchResults, String> {
+    let today = Local::now().date_naive();
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut results = SearchResults {
+        entity,
+        total: 0,
+        offset,
+        limit,
+        images: Vec::new(),
+        todos: Vec::new(),
+    };
+
+    match entity {
+        SearchEntity::Images => {
+            let query = image_query(criteria, today);
+            let images = repository::search_images_by_acquisition(conn, user_id, &query)
+                .map_err(|e| e.to_string())?;
+            let collection = match &criteria.collection_id {
+                Some(id) => Some(
+                    repository::get_collection_image_ids(conn, id)
+                        .map_err(|e| e.to_string())?
+                        .into_iter()
+                        .collect(),
+                ),
+                None => None,
+            };
+            let processed_sources = match criteria.processed {
+                Some(false) => repository::get_processed_source_ids(conn, user_id)
+                    .map_err(|e| e.to_string())?,
+                _ => HashSet::new(),
+            };
+            let context = ImageSearchContext {
+                collection,
+                processed_sources,
+            };
+
+            let matches: Vec<Image> = images
+                .into_iter()
+                .filter(|image| image_matches(image, criteria, &context))
+                .collect();
+            results.total = matches.len();
+            results.images = matches.into_iter().skip(offset).take(limit).collect();
+        }
+        SearchEntity::Todos => {
+            let todos = repository::get_todos(conn, user_id).map_err(|e| e.to_string())?;
+            let matches: Vec<AstronomyTodo> = todos
+                .into_iter()
+                .filter(|todo| todo_matches(todo, criteria, today))
+                .collect();
+            results.total = matches.len();
+            results.todos = matches.into_iter().skip(offset).take(limit).collect();
+        }
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn get_saved_searches(state: State<'_, AppState>) -> Result<Vec<SavedSearch>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_saved_searches(&mut conn, &state.user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_saved_search(
+    state: State<'_, AppState>,
+    input: CreateSavedSearchInput,
+) -> Result<SavedSearch, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let new_search = NewSavedSearch {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: state.user_id.clone(),
+        name: search_name(input.name)?,
+        entity: input.entity.as_str().to_string(),
+        criteria: criteria_to_string(&input.criteria)?,
+    };
+
+    repository::create_saved_search(&mut conn, &new_search).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_saved_search(
+    state: State<'_, AppState>,
+    input: UpdateSavedSearchInput,
+) -> Result<SavedSearch, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let update = UpdateSavedSearch {
+        name: input.name.map(search_name).transpose()?,
+        entity: input.entity.map(|e| e.as_str().to_string()),
+        criteria: input
+            .criteria
+            .as_ref()
+            .map(criteria_to_string)
+            .transpose()?,
+        pinned: None,
+    };
+
+    repository::update_saved_search(&mut conn, &input.id, &update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_saved_search(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::delete_saved_search(&mut conn, &id)
+        .map(|count| count > 0)
+        .map_err(|e| e.to_string())
+}
+
+/// Run a saved search and return one page of its results
+#[tauri::command]
+pub fn execute_saved_search(
+    state: State<'_, AppState>,
+    id: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<SearchResults, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let search = repository::get_saved_search_by_id(&mut conn, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Saved search not found: {}", id))?;
+
+    let entity = SearchEntity::parse(&search.entity)?;
+    let criteria: SearchCriteria = serde_json::from_str(&search.criteria)
+        .map_err(|e| format!("Invalid criteria for saved search {}: {}", search.name, e))?;
+    run_search(&mut conn, &state.user_id, entity, &criteria, offset, limit)
+}
+
+/// Run search criteria without saving them, e.g. for a quick filter or to
+/// preview a search before saving it
+#[tauri::command]
+pub fn execute_search(
+    state: State<'_, AppState>,
+    entity: SearchEntity,
+    criteria: SearchCriteria,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<SearchResults, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    run_search(&mut conn, &state.user_id, entity, &criteria, offset, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_image(id: &str, filter: &str, tags: &str) -> Image {
+        Image {
+            summary: Some("M42".to_string()),
+            tags: Some(tags.to_string()),
+            filter: Some(filter.to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn periods_end_today() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+        let start = |period: SearchPeriod| period.start(today).to_string();
+        assert_eq!(start(SearchPeriod::Last7Days), "2026-03-09");
+        assert_eq!(start(SearchPeriod::ThisMonth), "2026-03-01");
+        assert_eq!(start(SearchPeriod::ThisYear), "2026-01-01");
+
+        let criteria = SearchCriteria {
+            period: Some(SearchPeriod::ThisYear),
+            acquisition: AcquisitionQuery {
+                date_from: Some("2026-02-01".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            image_query(&criteria, today).date_from.as_deref(),
+            Some("2026-02-01")
+        );
+    }
+
+    #[test]
+    fn unprocessed_narrowband_images() {
+        let criteria: SearchCriteria =
+            serde_json::from_str(r#"{"band":"narrowband","processed":false,"text":"m42"}"#)
+                .unwrap();
+        let context = ImageSearchContext {
+            collection: None,
+            processed_sources: HashSet::from(["done".to_string()]),
+        };
+
+        let matches = |image: Image| image_matches(&image, &criteria, &context);
+
+        assert!(matches(make_image("new", "Ha 7nm", "stacked")));
+        // Broadband, already processed, or a processed output itself
+        assert!(!matches(make_image("lum", "L", "")));
+        assert!(!matches(make_image("done", "OIII", "")));
+        assert!(!matches(make_image("out", "SII", "processed")));
+    }
+}
@@ -1,27 +1,59 @@
 //! Bulk scan commands for importing images from directories
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use chrono::{FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike};
+use diesel::sqlite::SqliteConnection;
 use image::imageops::FilterType;
+use image::DynamicImage;
+use jpeg_decoder::PixelFormat;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
-use std::io::Cursor;
+use std::fs::File;
+use std::io::{BufReader, Cursor};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tauri::{Emitter, State};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::Semaphore;
 use walkdir::WalkDir;
 
 /// Global cancellation flag for scan operations
 static SCAN_CANCELLED: AtomicBool = AtomicBool::new(false);
 
-use crate::db::models::{NewCollection, NewCollectionImage, NewImage, NewScannedDirectory};
+/// Global pause flag for scan operations
+static SCAN_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Job id of the bulk scan in progress; pausing and resuming must name it
+static SCAN_JOB: Mutex<Option<String>> = Mutex::new(None);
+
+/// How often a paused scan checks whether it was resumed or cancelled
+const PAUSE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+use crate::db::models::{
+    Image, NewCollection, NewCollectionImage, NewImage, NewScannedDirectory, ScannedDirectory,
+};
 use crate::db::repository;
+use crate::db::SharedDbPool;
+use crate::events::{self, ImageAdded};
+use crate::logging;
 use crate::state::AppState;
 
-/// Get the modification time of a directory as Unix timestamp
-fn get_dir_mtime(path: &Path) -> Option<i64> {
+use super::capture_site::{tag_capture_site, CaptureSites};
+use super::collection_naming::collection_name_template_in;
+use super::frame_rules::{frame_classifier, frame_classifier_in, FrameClassifier, FrameType};
+use super::import_journal;
+use super::import_memory::{import_memory_budget_in, MemoryBudget};
+use super::job_metrics::{self, JobTimings};
+use super::object_names::ObjectNames;
+use super::sidecars::{read_sidecar, SidecarData};
+use super::solar_system::{solar_system_info, with_solar_system};
+use super::tag_rules::{merge_tags, rule_tags, tag_rules_in, TagFacts, TagRules};
+use super::triage::{triage_reasons, triage_rules_in, with_triage_reasons, TriageRules, TRIAGE_PENDING};
+
+/// Get the modification time of a file or directory as Unix timestamp
+fn get_mtime(path: &Path) -> Option<i64> {
     path.metadata()
         .ok()
         .and_then(|m| m.modified().ok())
@@ -43,11 +75,54 @@ pub const THUMBNAIL_SIZE: u32 = 300;
 /// JPEG quality for thumbnails (0-100)
 pub const THUMBNAIL_QUALITY: u8 = 80;
 
+/// Decoded size reserved for a scaled JPEG thumbnail (at most 1/8 of a
+/// large frame), on top of the file itself
+const JPEG_DECODE_MB: u64 = 16;
+
+fn is_jpeg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+}
+
+/// Decode a JPEG at the smallest DCT scale (1/8 up to 1) that still covers
+/// `size`, so a 60MP frame is never held at full resolution
+fn decode_jpeg_scaled(image_path: &Path, size: u32) -> Result<DynamicImage, String> {
+    let file = File::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(file));
+    let side = size.min(u16::MAX as u32) as u16;
+    let (width, height) = decoder
+        .scale(side, side)
+        .map_err(|e| format!("Failed to read JPEG: {}", e))?;
+    let (width, height) = (width as u32, height as u32);
+
+    // 16-bit and CMYK JPEGs are rare enough to leave to the full decoder
+    let format = decoder.info().map(|info| info.pixel_format);
+    if !matches!(format, Some(PixelFormat::L8 | PixelFormat::RGB24)) {
+        return Err("Unsupported JPEG pixel format for scaled decoding".to_string());
+    }
+    let pixels = decoder
+        .decode()
+        .map_err(|e| format!("Failed to decode JPEG: {}", e))?;
+    let img = match format {
+        Some(PixelFormat::L8) => {
+            image::GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        }
+        _ => image::RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+    };
+    img.ok_or_else(|| "JPEG pixel data doesn't match its size".to_string())
+}
+
 /// Generate a base64-encoded JPEG thumbnail from an image file
 pub fn generate_thumbnail(image_path: &Path) -> Result<String, String> {
-    // Load the image
-    let img = image::open(image_path)
-        .map_err(|e| format!("Failed to open image: {}", e))?;
+    // Load the image, scaled down while decoding where possible
+    let scaled = is_jpeg(image_path)
+        .then(|| decode_jpeg_scaled(image_path, THUMBNAIL_SIZE).ok())
+        .flatten();
+    let img = match scaled {
+        Some(img) => img,
+        None => image::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?,
+    };
 
     // Resize to thumbnail, maintaining aspect ratio
     let thumbnail = img.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3);
@@ -209,7 +284,7 @@ pub struct BulkScanInput {
 }
 
 /// Result of a bulk scan operation
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct BulkScanResult {
     /// Number of images imported
     pub images_imported: usize,
@@ -217,8 +292,15 @@ pub struct BulkScanResult {
     pub collections_created: usize,
     /// Number of images skipped (already exist or errors)
     pub images_skipped: usize,
+    /// Number of Light frames linked under their stacked image
+    pub subframes_linked: usize,
+    /// Number of imported frames held back for triage review
+    pub images_triaged: usize,
     /// Any errors encountered
     pub errors: Vec<String>,
+    /// Id the scan's log lines are tagged with
+    #[serde(default)]
+    pub job_id: Option<String>,
 }
 
 /// Progress event payload for scan operations
@@ -248,6 +330,60 @@ pub fn cancel_scan() -> Result<(), String> {
     Ok(())
 }
 
+fn running_scan_job() -> Option<String> {
+    SCAN_JOB.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Check that `job_id` is the scan in progress, so a stale request meant
+/// for an earlier import doesn't act on the current one
+fn check_scan_job(job_id: &str) -> Result<(), String> {
+    match running_scan_job() {
+        Some(running) if running == job_id => Ok(()),
+        Some(running) => Err(format!(
+            "Scan {} is not running (the running scan is {})",
+            job_id, running
+        )),
+        None => Err(format!("Scan {} is not running", job_id)),
+    }
+}
+
+/// Job id of the bulk scan in progress, for pausing and resuming it
+#[tauri::command]
+pub fn get_running_scan_job() -> Result<Option<String>, String> {
+    Ok(running_scan_job())
+}
+
+/// Pause an ongoing scan. Images already being processed are finished and
+/// committed; no new ones are started until the scan is resumed.
+#[tauri::command]
+pub fn pause_scan(job_id: String) -> Result<(), String> {
+    check_scan_job(&job_id)?;
+    SCAN_PAUSED.store(true, Ordering::SeqCst);
+    log::info!("Scan pause requested for {}", job_id);
+    Ok(())
+}
+
+/// Resume a paused scan where it left off
+#[tauri::command]
+pub fn resume_scan(job_id: String) -> Result<(), String> {
+    check_scan_job(&job_id)?;
+    SCAN_PAUSED.store(false, Ordering::SeqCst);
+    log::info!("Scan resume requested for {}", job_id);
+    Ok(())
+}
+
+/// Wait while the scan is paused, calling `on_pause` once if it is. A paused
+/// scan can still be cancelled, so this returns as soon as it is.
+async fn wait_while_paused(on_pause: impl FnOnce()) {
+    if !SCAN_PAUSED.load(Ordering::SeqCst) {
+        return;
+    }
+    on_pause();
+    while SCAN_PAUSED.load(Ordering::SeqCst) && !SCAN_CANCELLED.load(Ordering::SeqCst) {
+        tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+    }
+}
+
 /// Metadata extracted from a FITS file
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FitsMetadata {
@@ -272,7 +408,7 @@ pub struct FitsMetadata {
 }
 
 /// Represents a discovered image (potentially with both .fit and .jpg)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiscoveredImage {
     /// Base name without extension
     pub base_name: String,
@@ -287,16 +423,19 @@ pub struct DiscoveredImage {
 }
 
 /// Result of preprocessing a single image (FITS parsing + thumbnail generation)
-#[derive(Debug)]
-struct ProcessedImage {
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct ProcessedImage {
     /// Original discovered image info
-    discovered: DiscoveredImage,
+    pub(super) discovered: DiscoveredImage,
     /// Parsed FITS metadata (if successful)
-    metadata: Option<FitsMetadata>,
+    pub(super) metadata: Option<FitsMetadata>,
     /// Generated thumbnail (if successful)
-    thumbnail: Option<String>,
+    pub(super) thumbnail: Option<String>,
     /// Error message if processing failed
-    error: Option<String>,
+    pub(super) error: Option<String>,
+    /// XMP or JSON sidecar found next to the files
+    #[serde(default)]
+    pub(super) sidecar: Option<SidecarData>,
 }
 
 /// Parse FITS header to extract metadata
@@ -453,8 +592,86 @@ pub fn extract_int_value(value: &str) -> Option<i32> {
     trimmed.parse().ok()
 }
 
-/// Determine session date from observation timestamp
-/// Images after midnight but before noon are considered part of the previous day's session
+/// Read a header value from an image's stored metadata JSON.
+///
+/// Bulk scan stores the full `FitsMetadata` (parsed `field` plus `raw_headers`),
+/// while auto-import stores the raw header map directly, so check all three.
+pub fn metadata_header_value(metadata_json: &str, field: &str, header: &str) -> Option<String> {
+    let meta: serde_json::Value = serde_json::from_str(metadata_json).ok()?;
+
+    if let Some(value) = meta.get(field).and_then(|v| v.as_str()) {
+        return Some(value.to_string());
+    }
+
+    meta.get(header)
+        .or_else(|| meta.get("raw_headers").and_then(|h| h.get(header)))
+        .and_then(|v| v.as_str())
+        .and_then(extract_string_value)
+        .filter(|v| !v.is_empty())
+}
+
+/// Read a numeric header from stored metadata as a string for the extract_* helpers.
+///
+/// Like `metadata_header_value`, but the parsed `field` is a JSON number and
+/// several header aliases may apply (e.g. EXPTIME/EXPOSURE).
+pub fn metadata_number_value(metadata_json: &str, field: &str, headers: &[&str]) -> Option<String> {
+    let meta: serde_json::Value = serde_json::from_str(metadata_json).ok()?;
+    if let Some(n) = meta.get(field).and_then(|v| v.as_f64()) {
+        return Some(n.to_string());
+    }
+    headers.iter().find_map(|header| {
+        meta.get(*header)
+            .or_else(|| meta.get("raw_headers").and_then(|h| h.get(*header)))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    })
+}
+
+/// Acquisition values that are stored in their own `images` columns
+/// (the metadata JSON keeps the full header set)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AcquisitionColumns {
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    pub date_obs: Option<String>,
+}
+
+impl AcquisitionColumns {
+    /// Read the columns from stored metadata JSON, either the parsed
+    /// `FitsMetadata` fields or bare raw headers
+    pub fn from_metadata(metadata_json: Option<&str>) -> Self {
+        let Some(meta) = metadata_json else {
+            return Self::default();
+        };
+        Self {
+            exposure: metadata_number_value(meta, "exposure", &["EXPTIME", "EXPOSURE"])
+                .and_then(|v| extract_float_value(&v)),
+            gain: metadata_number_value(meta, "gain", &["GAIN"])
+                .and_then(|v| extract_int_value(&v)),
+            filter: metadata_header_value(meta, "filter", "FILTER"),
+            telescope: metadata_header_value(meta, "telescope", "TELESCOP"),
+            date_obs: metadata_header_value(meta, "date_obs", "DATE-OBS"),
+        }
+    }
+}
+
+impl From<&FitsMetadata> for AcquisitionColumns {
+    fn from(metadata: &FitsMetadata) -> Self {
+        Self {
+            exposure: metadata.exposure,
+            gain: metadata.gain,
+            filter: metadata.filter.clone(),
+            telescope: metadata.telescope.clone(),
+            date_obs: metadata.date_obs.clone(),
+        }
+    }
+}
+
+/// Determine session date from a local observation timestamp
+/// Images after midnight but before noon are considered part of the previous day's session.
+/// DATE-OBS is UTC; use `get_session_date_utc` or `session_date_from_metadata` for it.
 pub fn get_session_date(date_obs: &str) -> Option<NaiveDate> {
     // Try parsing various date formats
     let datetime = if let Ok(dt) = NaiveDateTime::parse_from_str(date_obs, "%Y-%m-%dT%H:%M:%S%.f") {
@@ -477,9 +694,183 @@ pub fn get_session_date(date_obs: &str) -> Option<NaiveDate> {
     })
 }
 
-/// Generate collection name from session date (one collection per night)
-pub fn generate_collection_name(session_date: &NaiveDate, _object_name: Option<&str>) -> String {
-    session_date.format("%Y-%m-%d").to_string()
+/// Parse a timestamp as UTC. An explicit `Z` or `+hh:mm` suffix is honoured;
+/// bare timestamps are UTC per the FITS standard for DATE-OBS.
+pub fn parse_utc_timestamp(date_obs: &str) -> Option<NaiveDateTime> {
+    let trimmed = date_obs.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(trimmed) {
+        return Some(dt.naive_utc());
+    }
+    let bare = trimmed.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(bare, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(bare, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+}
+
+/// Observer's offset from UTC at a given instant.
+///
+/// A site longitude gives local mean solar time (15° per hour east), which is
+/// what the noon cut-off is about; without one the system timezone is used,
+/// including any daylight saving in effect on that date.
+pub fn observer_utc_offset(utc: &NaiveDateTime, longitude: Option<f64>) -> FixedOffset {
+    match longitude {
+        Some(lon) if lon.is_finite() => {
+            let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+            FixedOffset::east_opt((lon / 15.0 * 3600.0).round() as i32)
+                .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+        }
+        _ => Local.from_utc_datetime(utc).offset().fix(),
+    }
+}
+
+/// Session date for a UTC timestamp, converted to the observer's local time
+pub fn get_session_date_utc(date_obs: &str, longitude: Option<f64>) -> Option<NaiveDate> {
+    match parse_utc_timestamp(date_obs) {
+        Some(utc) => {
+            let local = utc + observer_utc_offset(&utc, longitude);
+            get_session_date(&local.format("%Y-%m-%dT%H:%M:%S").to_string())
+        }
+        // Date-only values carry no time to convert
+        None => get_session_date(date_obs),
+    }
+}
+
+/// Parse a SITELONG header: decimal degrees or sexagesimal "dd mm ss"
+pub fn parse_longitude(value: &str) -> Option<f64> {
+    if let Some(degrees) = extract_float_value(value) {
+        return Some(degrees);
+    }
+    let text = extract_string_value(value)?;
+    let parts: Vec<f64> = text
+        .split([' ', ':'])
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let (degrees, minutes, seconds) = match parts.as_slice() {
+        [d] => (*d, 0.0, 0.0),
+        [d, m] => (*d, *m, 0.0),
+        [d, m, s] => (*d, *m, *s),
+        _ => return None,
+    };
+    let sign = if text.trim_start().starts_with('-') { -1.0 } else { 1.0 };
+    Some(sign * (degrees.abs() + minutes / 60.0 + seconds / 3600.0))
+}
+
+/// Session date from the observation headers.
+///
+/// Prefers DATE-LOC (local time written by e.g. N.I.N.A.); otherwise converts
+/// DATE-OBS from UTC using the SITELONG header, then `longitude`, then the
+/// system timezone.
+fn session_date_from_headers(
+    date_obs: Option<&str>,
+    date_loc: Option<&str>,
+    site_longitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Option<NaiveDate> {
+    date_loc
+        .and_then(get_session_date)
+        .or_else(|| date_obs.and_then(|d| get_session_date_utc(d, site_longitude.or(longitude))))
+}
+
+/// Session date for an image from its stored metadata JSON
+pub fn session_date_from_metadata(metadata_json: &str, longitude: Option<f64>) -> Option<NaiveDate> {
+    session_date_from_headers(
+        metadata_header_value(metadata_json, "date_obs", "DATE-OBS").as_deref(),
+        metadata_header_value(metadata_json, "date_loc", "DATE-LOC").as_deref(),
+        metadata_number_value(metadata_json, "site_longitude", &["SITELONG"])
+            .and_then(|v| parse_longitude(&v)),
+        longitude,
+    )
+}
+
+/// Session date for a stored image, falling back to the DATE-OBS column
+pub fn image_session_date(image: &Image, longitude: Option<f64>) -> Option<NaiveDate> {
+    image
+        .metadata
+        .as_deref()
+        .and_then(|meta| session_date_from_metadata(meta, longitude))
+        .or_else(|| {
+            image
+                .date_obs
+                .as_deref()
+                .and_then(|d| get_session_date_utc(d, longitude))
+        })
+}
+
+impl FitsMetadata {
+    /// Observing night this frame belongs to (see `session_date_from_metadata`)
+    pub fn session_date(&self, longitude: Option<f64>) -> Option<NaiveDate> {
+        let header = |key: &str| self.raw_headers.get(key);
+        session_date_from_headers(
+            self.date_obs.as_deref(),
+            header("DATE-LOC").and_then(|v| extract_string_value(v)).as_deref(),
+            header("SITELONG").and_then(|v| parse_longitude(v)),
+            longitude,
+        )
+    }
+}
+
+/// Generate a session collection name from a naming template.
+///
+/// `{date}` is the session date (YYYY-MM-DD) and `{object}` the target. A
+/// missing field is left out together with the text joining it to the rest
+/// of the name; when nothing is left the date is used.
+pub fn generate_collection_name(
+    template: &str,
+    session_date: &NaiveDate,
+    object_name: Option<&str>,
+) -> String {
+    let date = session_date.format("%Y-%m-%d").to_string();
+    let object = object_name.map(str::trim).filter(|o| !o.is_empty());
+
+    // (text before the field, field value) for each known field
+    let mut fields: Vec<(String, Option<&str>)> = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = if rest.starts_with("{date}") {
+            Some(Some(date.as_str()))
+        } else if rest.starts_with("{object}") {
+            Some(object)
+        } else {
+            None
+        };
+        match value {
+            Some(value) => {
+                let end = rest.find('}').unwrap_or(0) + 1;
+                fields.push((std::mem::take(&mut text), value));
+                rest = &rest[end..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    let mut name = String::new();
+    for (i, (before, value)) in fields.iter().enumerate() {
+        if let Some(value) = value {
+            // Text after a missing leading field would dangle at the start
+            if i == 0 || !name.is_empty() {
+                name.push_str(before);
+            }
+            name.push_str(value);
+        }
+    }
+    if fields.last().is_some_and(|(_, value)| value.is_some()) {
+        name.push_str(&text);
+    }
+
+    let name = name.trim();
+    if name.is_empty() {
+        date
+    } else {
+        name.to_string()
+    }
 }
 
 /// Scan a directory for image files with progress callback
@@ -487,6 +878,7 @@ pub fn generate_collection_name(session_date: &NaiveDate, _object_name: Option<&
 fn scan_directory_with_progress<F>(
     directory: &Path,
     stacked_only: bool,
+    classifier: &FrameClassifier,
     max_files: Option<usize>,
     cancelled: &AtomicBool,
     mut on_progress: F,
@@ -558,12 +950,13 @@ where
             continue;
         }
 
-        // Check if this is a stacked image or raw subframe
-        let is_stacked = stem.to_lowercase().starts_with("stacked");
-        let is_light = stem.to_lowercase().starts_with("light");
+        // Check if this is a stacked image or raw subframe by name; headers
+        // are checked once the FITS file is parsed
+        let frame_type = classifier.classify_name(&stem);
+        let is_stacked = frame_type == Some(FrameType::Stacked);
 
         // Skip raw subframes if stacked_only is true
-        if stacked_only && is_light {
+        if stacked_only && frame_type.is_some_and(FrameType::is_raw) {
             continue;
         }
 
@@ -607,23 +1000,43 @@ where
     result
 }
 
+/// Rough peak memory for processing an image, in MB. A JPEG is decoded
+/// scaled down; a FITS thumbnail holds the raw data plus an f64 copy, which
+/// for the usual 16-bit data is four times its size.
+fn estimate_processing_mb(discovered: &DiscoveredImage) -> u32 {
+    let file_mb = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .map_or(0, |m| m.len() / (1024 * 1024))
+    };
+    let mb = if discovered.jpeg_path.is_some() {
+        file_mb(&discovered.jpeg_path) + JPEG_DECODE_MB
+    } else {
+        file_mb(&discovered.fits_path) * 5
+    };
+    mb.clamp(1, u32::MAX as u64) as u32
+}
+
 /// Process a single image: parse FITS metadata and generate thumbnail
 /// This runs in a blocking task for CPU-intensive operations
-async fn process_single_image(discovered: DiscoveredImage) -> ProcessedImage {
+async fn process_single_image(discovered: DiscoveredImage, timings: Arc<JobTimings>) -> ProcessedImage {
     let discovered_clone = discovered.clone();
+    let job_id = logging::current_job();
 
     // Run CPU-intensive work in a blocking task
     tokio::task::spawn_blocking(move || {
+        let _job = logging::enter_job(job_id);
         let mut processed = ProcessedImage {
             discovered: discovered_clone,
             metadata: None,
             thumbnail: None,
             error: None,
+            sidecar: None,
         };
 
         // Parse FITS metadata if we have a FITS file
         if let Some(fits_path) = &processed.discovered.fits_path {
-            match parse_fits_metadata(fits_path) {
+            match timings.time(job_metrics::PHASE_FITS_PARSE, || parse_fits_metadata(fits_path)) {
                 Ok(m) => processed.metadata = Some(m),
                 Err(e) => {
                     processed.error = Some(format!(
@@ -636,9 +1049,19 @@ async fn process_single_image(discovered: DiscoveredImage) -> ProcessedImage {
             }
         }
 
+        let originals: Vec<&Path> = [
+            &processed.discovered.fits_path,
+            &processed.discovered.jpeg_path,
+        ]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::as_path)
+        .collect();
+        processed.sidecar = read_sidecar(&originals);
+
         // Generate thumbnail from JPEG if available
         if let Some(jpeg_path) = &processed.discovered.jpeg_path {
-            match generate_thumbnail(jpeg_path) {
+            match timings.time(job_metrics::PHASE_THUMBNAIL, || generate_thumbnail(jpeg_path)) {
                 Ok(thumb) => processed.thumbnail = Some(thumb),
                 Err(e) => {
                     log::warn!("Failed to generate thumbnail for {}: {}", jpeg_path.display(), e);
@@ -649,7 +1072,7 @@ async fn process_single_image(discovered: DiscoveredImage) -> ProcessedImage {
         // Fall back to FITS thumbnail if no JPEG thumbnail was generated
         if processed.thumbnail.is_none() {
             if let Some(fits_path) = &processed.discovered.fits_path {
-                match generate_fits_thumbnail(fits_path) {
+                match timings.time(job_metrics::PHASE_THUMBNAIL, || generate_fits_thumbnail(fits_path)) {
                     Ok(thumb) => processed.thumbnail = Some(thumb),
                     Err(e) => {
                         log::warn!("Failed to generate FITS thumbnail for {}: {}", fits_path.display(), e);
@@ -666,6 +1089,7 @@ async fn process_single_image(discovered: DiscoveredImage) -> ProcessedImage {
         metadata: None,
         thumbnail: None,
         error: Some(format!("Task panicked: {}", e)),
+        sidecar: None,
     })
 }
 
@@ -676,12 +1100,127 @@ pub async fn bulk_scan_directory(
     state: State<'_, AppState>,
     input: BulkScanInput,
 ) -> Result<BulkScanResult, String> {
-    // Reset cancellation flag at start
+    let settings = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map(|dir| ScanSettings::load(&dir))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let result = run_bulk_scan(
+        state.db.clone(),
+        state.user_id.clone(),
+        input,
+        settings,
+        |progress| {
+            let _ = window.emit("scan-progress", progress);
+            events::publish(events::SCAN_PROGRESS, progress);
+        },
+    )
+    .await?;
+    events::publish(events::SCAN_FINISHED, &result);
+    Ok(result)
+}
+
+/// Settings a scan reads from the app data directory
+pub(crate) struct ScanSettings {
+    pub classifier: FrameClassifier,
+    pub triage: TriageRules,
+    pub tag_rules: TagRules,
+    pub collection_template: String,
+    pub memory_budget_mb: u32,
+    /// Where processed batches are journaled until they're committed
+    pub journal_dir: PathBuf,
+}
+
+impl ScanSettings {
+    pub fn load(data_dir: &Path) -> Self {
+        Self {
+            classifier: frame_classifier_in(data_dir),
+            triage: triage_rules_in(data_dir),
+            tag_rules: tag_rules_in(data_dir),
+            collection_template: collection_name_template_in(data_dir),
+            memory_budget_mb: import_memory_budget_in(data_dir),
+            journal_dir: import_journal::journal_dir_in(data_dir),
+        }
+    }
+}
+
+/// The scan behind `bulk_scan_directory`, reporting progress through
+/// `progress` instead of window events so it can run without the GUI
+pub(crate) async fn run_bulk_scan(
+    db_pool: SharedDbPool,
+    user_id: String,
+    input: BulkScanInput,
+    settings: ScanSettings,
+    progress: impl Fn(&ScanProgress) + Send + Sync,
+) -> Result<BulkScanResult, String> {
+    let job_id = logging::new_job_id("scan");
+    let timings = Arc::new(JobTimings::new());
+    let scan = async {
+        log::info!("Scanning {}", input.directory);
+        let started = Instant::now();
+        let result = scan_and_import(
+            db_pool.clone(),
+            user_id.clone(),
+            input,
+            settings,
+            progress,
+            timings.clone(),
+        )
+        .await;
+        timings.record(job_metrics::PHASE_TOTAL, started.elapsed());
+        job_metrics::save_job_metrics(&db_pool, &user_id, &job_id, &timings);
+        match &result {
+            Ok(result) => {
+                // Errors are returned rather than logged as they happen
+                for error in &result.errors {
+                    log::warn!("{}", error);
+                }
+                log::info!(
+                    "Scan finished: {} imported, {} skipped, {} errors",
+                    result.images_imported,
+                    result.images_skipped,
+                    result.errors.len()
+                );
+            }
+            Err(e) => log::error!("Scan failed: {}", e),
+        }
+        result
+    };
+    *SCAN_JOB.lock().unwrap_or_else(|e| e.into_inner()) = Some(job_id.clone());
+    let result = logging::in_job(Some(job_id.clone()), scan).await;
+    {
+        // A scan started meanwhile has taken over
+        let mut running = SCAN_JOB.lock().unwrap_or_else(|e| e.into_inner());
+        if running.as_deref() == Some(job_id.as_str()) {
+            *running = None;
+        }
+    }
+    let mut result = result?;
+    result.job_id = Some(job_id);
+    Ok(result)
+}
+
+async fn scan_and_import(
+    db_pool: SharedDbPool,
+    user_id: String,
+    input: BulkScanInput,
+    settings: ScanSettings,
+    progress: impl Fn(&ScanProgress) + Send + Sync,
+    timings: Arc<JobTimings>,
+) -> Result<BulkScanResult, String> {
+    // Reset cancellation and pause flags at start
     SCAN_CANCELLED.store(false, Ordering::SeqCst);
+    SCAN_PAUSED.store(false, Ordering::SeqCst);
 
-    // Clone what we need for the async block
-    let db_pool = state.db.clone();
-    let user_id = state.user_id.clone();
+    let ScanSettings {
+        classifier,
+        triage,
+        tag_rules,
+        collection_template,
+        memory_budget_mb,
+        journal_dir,
+    } = settings;
 
     let directory = PathBuf::from(&input.directory);
     if !directory.exists() {
@@ -692,11 +1231,14 @@ pub async fn bulk_scan_directory(
         images_imported: 0,
         collections_created: 0,
         images_skipped: 0,
+        subframes_linked: 0,
+        images_triaged: 0,
         errors: Vec::new(),
+        job_id: None,
     };
 
     // Emit "Scanning directory" progress
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: 0,
         current_file: format!("Scanning: {}...", directory.display()),
@@ -706,14 +1248,15 @@ pub async fn bulk_scan_directory(
     });
 
     // Scan directory for images with progress updates
-    let window_clone = window.clone();
+    let scan_started = Instant::now();
     let discovered_images = scan_directory_with_progress(
         &directory,
         input.stacked_only,
+        &classifier,
         input.max_files,
         &SCAN_CANCELLED,
         |files_scanned, images_found| {
-            let _ = window_clone.emit("scan-progress", &ScanProgress {
+            progress(&ScanProgress {
                 current: 0,
                 total: 0,
                 current_file: format!("Scanned {} files, found {} images...", files_scanned, images_found),
@@ -723,10 +1266,11 @@ pub async fn bulk_scan_directory(
             });
         },
     );
+    timings.record(job_metrics::PHASE_SCAN, scan_started.elapsed());
     let total_discovered = discovered_images.len();
 
     if total_discovered == 0 {
-        let _ = window.emit("scan-progress", &ScanProgress {
+        progress(&ScanProgress {
             current: 0,
             total: 0,
             current_file: "No images found".to_string(),
@@ -738,7 +1282,7 @@ pub async fn bulk_scan_directory(
     }
 
     // === DIRECTORY CACHE CHECK: Skip unchanged directories ===
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: "Checking directory cache...".to_string(),
@@ -747,14 +1291,16 @@ pub async fn bulk_scan_directory(
         cancelled: false,
     });
 
-    // Group images by directory and get modification times
+    // Group images by directory and get modification times. Every directory
+    // level holding images is checked on its own, so changes in nested
+    // folders (e.g. a session's Stacked/ subfolder) are caught too.
     let mut dir_images: HashMap<PathBuf, Vec<DiscoveredImage>> = HashMap::new();
     let mut dir_mtimes: HashMap<PathBuf, i64> = HashMap::new();
 
     for img in discovered_images {
         let dir = img.directory.clone();
         if !dir_mtimes.contains_key(&dir) {
-            if let Some(mtime) = get_dir_mtime(&dir) {
+            if let Some(mtime) = get_mtime(&dir) {
                 dir_mtimes.insert(dir.clone(), mtime);
             }
         }
@@ -764,7 +1310,7 @@ pub async fn bulk_scan_directory(
     // Load cached directory info and filter out unchanged directories
     let mut discovered_images: Vec<DiscoveredImage> = Vec::new();
     let mut unchanged_dirs: Vec<PathBuf> = Vec::new();
-    let mut changed_dirs: HashMap<PathBuf, i64> = HashMap::new();
+    let mut changed_dirs: HashMap<PathBuf, DirectoryScan> = HashMap::new();
     let mut skipped_from_cache: usize = 0;
 
     {
@@ -773,10 +1319,14 @@ pub async fn bulk_scan_directory(
         for (dir, images) in dir_images {
             let dir_path_str = dir.to_string_lossy().to_string();
             let current_mtime = dir_mtimes.get(&dir).copied().unwrap_or(0);
+            let current_listing = listing_hash(&images);
 
-            // Check cache for this directory
+            // Check cache for this directory. Directories cached before
+            // listings were recorded have no hash and are rescanned once.
             if let Ok(Some(cached)) = repository::get_scanned_directory(&mut conn, &user_id, &dir_path_str) {
-                if cached.fs_modified_at == current_mtime {
+                if cached.fs_modified_at == current_mtime
+                    && cached.listing_hash.as_deref() == Some(current_listing.as_str())
+                {
                     // Directory unchanged - skip these images
                     skipped_from_cache += images.len();
                     unchanged_dirs.push(dir);
@@ -785,7 +1335,15 @@ pub async fn bulk_scan_directory(
             }
 
             // Directory is new or changed - include its images
-            changed_dirs.insert(dir, current_mtime);
+            changed_dirs.insert(
+                dir,
+                DirectoryScan {
+                    mtime: current_mtime,
+                    listing_hash: Some(current_listing),
+                    found: images.len(),
+                    ..Default::default()
+                },
+            );
             discovered_images.extend(images);
         }
     }
@@ -794,7 +1352,7 @@ pub async fn bulk_scan_directory(
     result.images_skipped += skipped_from_cache;
 
     // Emit progress after cache check
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: if skipped_from_cache > 0 {
@@ -815,7 +1373,7 @@ pub async fn bulk_scan_directory(
 
     // If all directories are unchanged, we're done
     if total_after_cache == 0 {
-        let _ = window.emit("scan-progress", &ScanProgress {
+        progress(&ScanProgress {
             current: total_discovered,
             total: total_discovered,
             current_file: format!("All {} directories unchanged since last scan", unchanged_dirs.len()),
@@ -827,7 +1385,7 @@ pub async fn bulk_scan_directory(
     }
 
     // Emit "Found images" progress
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: format!("Loading database ({} images to check)...", total_after_cache),
@@ -851,7 +1409,7 @@ pub async fn bulk_scan_directory(
     };
 
     // Emit progress after loading URLs
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: format!("Loaded {} existing image records...", existing_urls.len()),
@@ -886,7 +1444,7 @@ pub async fn bulk_scan_directory(
     };
 
     // Emit "Filtering duplicates" progress
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: "Checking for duplicates...".to_string(),
@@ -929,7 +1487,7 @@ pub async fn bulk_scan_directory(
     let total_to_process = new_images.len();
 
     // Emit initial progress showing duplicates already skipped
-    let _ = window.emit("scan-progress", &ScanProgress {
+    progress(&ScanProgress {
         current: 0,
         total: total_discovered,
         current_file: format!("Found {} new images ({} duplicates skipped)", total_to_process, skipped_duplicates),
@@ -940,7 +1498,9 @@ pub async fn bulk_scan_directory(
 
     // If all images are duplicates, we're done
     if total_to_process == 0 {
-        let _ = window.emit("scan-progress", &ScanProgress {
+        let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+        record_scanned_directories(&mut conn, &user_id, changed_dirs);
+        progress(&ScanProgress {
             current: total_discovered,
             total: total_discovered,
             current_file: "All images already exist".to_string(),
@@ -953,7 +1513,7 @@ pub async fn bulk_scan_directory(
 
     // Check for cancellation
     if SCAN_CANCELLED.load(Ordering::SeqCst) {
-        let _ = window.emit("scan-progress", &ScanProgress {
+        progress(&ScanProgress {
             current: 0,
             total: total_discovered,
             current_file: "Cancelled".to_string(),
@@ -966,16 +1526,43 @@ pub async fn bulk_scan_directory(
 
     // === BATCH PROCESSING: Process images in batches to manage memory ===
     let semaphore = Arc::new(Semaphore::new(MAX_PARALLEL_PROCESSING));
+    let memory = MemoryBudget::new(memory_budget_mb);
     let mut conn = db_pool.get().map_err(|e| e.to_string())?;
-    let mut session_collections: HashMap<String, String> = HashMap::new();
+    let mut ctx = ImportContext {
+        user_id: user_id.clone(),
+        input,
+        classifier,
+        triage,
+        tag_rules,
+        collection_template,
+        object_names: ObjectNames::load(&mut conn).map_err(|e| e.to_string())?,
+        capture_sites: CaptureSites::load(&mut conn, &user_id)?,
+        existing_urls,
+        url_to_image_id,
+        existing_collection_images,
+        session_collections: HashMap::new(),
+    };
     let mut images_processed: usize = 0;
     let total_batches = (total_to_process + BATCH_SIZE - 1) / BATCH_SIZE;
+    let job_id = logging::current_job();
 
     // Process images in batches
     for (batch_idx, batch) in new_images.chunks(BATCH_SIZE).enumerate() {
+        let report_paused = || {
+            progress(&ScanProgress {
+                current: skipped_duplicates + images_processed,
+                total: total_discovered,
+                current_file: "Paused".to_string(),
+                percent: ((skipped_duplicates + images_processed) * 100 / total_discovered.max(1)) as u8,
+                skipped: result.images_skipped,
+                cancelled: false,
+            });
+        };
+        wait_while_paused(report_paused).await;
+
         // Check for cancellation at start of each batch
         if SCAN_CANCELLED.load(Ordering::SeqCst) {
-            let _ = window.emit("scan-progress", &ScanProgress {
+            progress(&ScanProgress {
                 current: skipped_duplicates + images_processed,
                 total: total_discovered,
                 current_file: "Cancelled".to_string(),
@@ -987,7 +1574,7 @@ pub async fn bulk_scan_directory(
         }
 
         let batch_size = batch.len();
-        let _ = window.emit("scan-progress", &ScanProgress {
+        progress(&ScanProgress {
             current: skipped_duplicates + images_processed,
             total: total_discovered,
             current_file: format!("Processing batch {}/{} ({} images)...", batch_idx + 1, total_batches, batch_size),
@@ -1000,17 +1587,22 @@ pub async fn bulk_scan_directory(
         let mut processing_tasks = Vec::with_capacity(batch_size);
 
         for discovered in batch {
+            // Pausing stops new work from starting; what's running finishes
+            wait_while_paused(report_paused).await;
             if SCAN_CANCELLED.load(Ordering::SeqCst) {
                 break;
             }
 
             let discovered_clone = discovered.clone();
             let permit = semaphore.clone().acquire_owned().await.unwrap();
-            let task = tokio::spawn(async move {
-                let result = process_single_image(discovered_clone).await;
+            let reserved = memory.reserve(estimate_processing_mb(discovered)).await;
+            let timings = timings.clone();
+            let task = tokio::spawn(logging::in_job(job_id.clone(), async move {
+                let result = process_single_image(discovered_clone, timings).await;
                 drop(permit);
+                drop(reserved);
                 result
-            });
+            }));
             processing_tasks.push(task);
         }
 
@@ -1033,13 +1625,24 @@ pub async fn bulk_scan_directory(
             }
         }
 
+        // Spool the batch before committing it, so a crash part way through
+        // doesn't throw away the processing
+        let journal_entry =
+            match import_journal::write_batch(&journal_dir, &ctx, &batch_processed) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Failed to journal import batch: {}", e);
+                    None
+                }
+            };
+
         // PHASE 3: Database operations for this batch
         for processed in batch_processed {
             images_processed += 1;
 
             // Emit progress
             let progress_current = skipped_duplicates + images_processed;
-            let _ = window.emit("scan-progress", &ScanProgress {
+            progress(&ScanProgress {
                 current: progress_current,
                 total: total_discovered,
                 current_file: processed.discovered.base_name.clone(),
@@ -1048,291 +1651,544 @@ pub async fn bulk_scan_directory(
                 cancelled: false,
             });
 
-            // Skip if processing failed
-            if let Some(error) = processed.error {
-                result.errors.push(error);
-                result.images_skipped += 1;
-                continue;
+            let directory = processed.discovered.directory.clone();
+            let (imported, errors) = (result.images_imported, result.errors.len());
+            timings.time(job_metrics::PHASE_DB_INSERT, || {
+                commit_processed_image(&mut conn, &mut ctx, processed, &mut result)
+            });
+            if let Some(scan) = changed_dirs.get_mut(&directory) {
+                scan.imported += result.images_imported - imported;
+                if result.errors.len() > errors {
+                    scan.failed += 1;
+                }
             }
+        } // End of inner loop (for each processed image in batch)
 
-            // We need metadata to proceed
-            let Some(metadata) = processed.metadata else {
-                result.images_skipped += 1;
-                continue;
-            };
+        if let Some(entry) = journal_entry {
+            import_journal::remove_batch(&entry);
+        }
+    } // End of batch loop
 
-            // Build URL (prefer JPEG for display, fallback to FITS)
-            let url = processed.discovered
-                .jpeg_path
-                .as_ref()
-                .or(processed.discovered.fits_path.as_ref())
-                .map(|p| p.to_string_lossy().to_string());
-
-            // Store FITS path separately for processing
-            let fits_url = processed.discovered
-                .fits_path
-                .as_ref()
-                .map(|p| p.to_string_lossy().to_string());
-
-        // Determine session date and get/create collection
-        let session_date = metadata
-            .date_obs
-            .as_ref()
-            .and_then(|d| get_session_date(d));
+    // === LINK SUBFRAMES ===
+    // Light frames imported alongside their stack (Seestar `_sub` folders)
+    // are listed under it rather than on their own
+    if result.images_imported > 0 {
+        let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+        match super::subframes::link_subframes(&mut conn, &user_id) {
+            Ok(linked) => result.subframes_linked = linked,
+            Err(e) => result.errors.push(format!("Failed to link subframes: {}", e)),
+        }
+    }
 
-        let collection_id = if let Some(date) = session_date {
-            let collection_key = format!("{}", date);
+    // === UPDATE DIRECTORY CACHE ===
+    // Save the modification times for all directories that were processed
+    if !changed_dirs.is_empty() {
+        progress(&ScanProgress {
+            current: total_discovered,
+            total: total_discovered,
+            current_file: format!("Updating cache for {} directories...", changed_dirs.len()),
+            percent: 99,
+            skipped: result.images_skipped,
+            cancelled: false,
+        });
 
-            if let Some(id) = session_collections.get(&collection_key) {
-                id.clone()
-            } else {
-                let collection_name =
-                    generate_collection_name(&date, metadata.object_name.as_deref());
+        let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+        record_scanned_directories(&mut conn, &user_id, changed_dirs);
+    }
 
-                match repository::get_collection_by_name(&mut conn, &user_id, &collection_name) {
-                    Ok(Some(existing)) => {
-                        session_collections.insert(collection_key, existing.id.clone());
-                        existing.id
-                    }
-                    Ok(None) => {
-                        let new_collection = NewCollection {
-                            id: uuid::Uuid::new_v4().to_string(),
-                            user_id: user_id.clone(),
-                            name: collection_name,
-                            description: Some(format!(
-                                "Auto-imported from {}",
-                                directory.display()
-                            )),
-                            visibility: "private".to_string(),
-                            template: Some("astrolog".to_string()),
-                            favorite: false,
-                            tags: input.tags.clone(),
-                            metadata: Some(
-                                serde_json::json!({
-                                    "session_date": date.to_string(),
-                                    "auto_imported": true,
-                                    "source_directory": directory.to_string_lossy(),
-                                })
-                                .to_string(),
-                            ),
-                            archived: false,
-                        };
-
-                        match repository::create_collection(&mut conn, &new_collection) {
-                            Ok(c) => {
+    Ok(result)
+}
+
+/// What a scan found in one directory and what it did with it
+#[derive(Debug, Default)]
+struct DirectoryScan {
+    /// Directory modification time when the scan started
+    mtime: i64,
+    /// Fingerprint of the image files found, see `listing_hash`
+    listing_hash: Option<String>,
+    /// Images found
+    found: usize,
+    imported: usize,
+    failed: usize,
+}
+
+/// Save the modification times of the directories a scan went through, so
+/// the next scan skips them while unchanged, with what the scan found
+fn record_scanned_directories(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    directories: HashMap<PathBuf, DirectoryScan>,
+) {
+    let now = chrono::Utc::now().to_rfc3339();
+    for (dir_path, scan) in directories {
+        let entry = NewScannedDirectory {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            path: dir_path.to_string_lossy().to_string(),
+            fs_modified_at: scan.mtime,
+            last_scanned_at: now.clone(),
+            image_count: scan.found as i32,
+            last_imported: scan.imported as i32,
+            last_failed: scan.failed as i32,
+            listing_hash: scan.listing_hash,
+        };
+
+        if let Err(e) = repository::upsert_scanned_directory(conn, &entry) {
+            log::warn!("Failed to update directory cache: {}", e);
+        }
+    }
+}
+
+/// Fingerprint of the image files a scan found in one directory: their
+/// names, sizes and modification times.
+///
+/// A directory's own modification time only changes when entries are added
+/// or removed directly in it, and not at all on some filesystems (FAT on
+/// camera cards, certain network shares). The listing also changes when a
+/// previous scan stopped early or skipped subframes, so those files are
+/// picked up by the next full scan.
+fn listing_hash(images: &[DiscoveredImage]) -> String {
+    let mut files: Vec<String> = images
+        .iter()
+        .flat_map(|img| [img.fits_path.as_deref(), img.jpeg_path.as_deref()])
+        .flatten()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+            let mtime = get_mtime(path).unwrap_or(0);
+            format!("{}\t{}\t{}", name, size, mtime)
+        })
+        .collect();
+    files.sort();
+    hex::encode(Sha256::digest(files.join("\n").as_bytes()))
+}
+
+/// Everything committing a processed image needs besides the connection
+pub(super) struct ImportContext {
+    pub(super) user_id: String,
+    pub(super) input: BulkScanInput,
+    pub(super) classifier: FrameClassifier,
+    pub(super) triage: TriageRules,
+    pub(super) tag_rules: TagRules,
+    pub(super) collection_template: String,
+    pub(super) object_names: ObjectNames,
+    /// Saved locations, for tagging where images were taken
+    pub(super) capture_sites: CaptureSites,
+    /// URLs already in the library, with the IDs of their images
+    pub(super) existing_urls: HashSet<String>,
+    pub(super) url_to_image_id: HashMap<String, String>,
+    pub(super) existing_collection_images: HashSet<(String, String)>,
+    /// Session collections found or created so far, by name
+    pub(super) session_collections: HashMap<String, String>,
+}
+
+impl ImportContext {
+    /// Load the library state needed to commit images outside a scan
+    pub(super) fn load(
+        conn: &mut SqliteConnection,
+        user_id: String,
+        input: BulkScanInput,
+        classifier: FrameClassifier,
+        triage: TriageRules,
+        tag_rules: TagRules,
+        collection_template: String,
+    ) -> Result<Self, String> {
+        let images = repository::get_images_by_user(conn, &user_id).map_err(|e| e.to_string())?;
+        let existing_urls = images.iter().filter_map(|img| img.url.clone()).collect();
+        let url_to_image_id = images
+            .into_iter()
+            .filter_map(|img| img.url.map(|url| (url, img.id)))
+            .collect();
+        let existing_collection_images = repository::get_all_collection_image_pairs(conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+        Ok(Self {
+            object_names: ObjectNames::load(conn).map_err(|e| e.to_string())?,
+            capture_sites: CaptureSites::load(conn, &user_id)?,
+            user_id,
+            input,
+            classifier,
+            triage,
+            tag_rules,
+            collection_template,
+            existing_urls,
+            url_to_image_id,
+            existing_collection_images,
+            session_collections: HashMap::new(),
+        })
+    }
+}
+
+/// Write one processed image to the database: find or create its session
+/// collection, then insert it (or link it if it already exists)
+pub(super) fn commit_processed_image(
+    conn: &mut SqliteConnection,
+    ctx: &mut ImportContext,
+    processed: ProcessedImage,
+    result: &mut BulkScanResult,
+) {
+    // Skip if processing failed
+    if let Some(error) = processed.error {
+        result.errors.push(error);
+        result.images_skipped += 1;
+        return;
+    }
+
+    // We need metadata to proceed
+    let Some(metadata) = processed.metadata else {
+        result.images_skipped += 1;
+        return;
+    };
+
+    // Names the rules didn't recognize are classified by their headers
+    let frame_type = ctx
+        .classifier
+        .classify(&processed.discovered.base_name, Some(&metadata.raw_headers));
+    if ctx.input.stacked_only && frame_type.is_raw() {
+        result.images_skipped += 1;
+        return;
+    }
+
+    // Build URL (prefer JPEG for display, fallback to FITS)
+    let url = processed
+        .discovered
+        .jpeg_path
+        .as_ref()
+        .or(processed.discovered.fits_path.as_ref())
+        .map(|p| p.to_string_lossy().to_string());
+
+    // Store FITS path separately for processing
+    let fits_url = processed
+        .discovered
+        .fits_path
+        .as_ref()
+        .map(|p| p.to_string_lossy().to_string());
+
+    // Determine session date and get/create collection
+    let session_date = metadata.session_date(None);
+
+    let collection_id = if let Some(date) = session_date {
+        let collection_name = generate_collection_name(
+            &ctx.collection_template,
+            &date,
+            metadata.object_name.as_deref(),
+        );
+
+        if let Some(id) = ctx.session_collections.get(&collection_name) {
+            id.clone()
+        } else {
+            match repository::get_collection_by_name(conn, &ctx.user_id, &collection_name) {
+                Ok(Some(existing)) => {
+                    ctx.session_collections
+                        .insert(collection_name, existing.id.clone());
+                    existing.id
+                }
+                Ok(None) => {
+                    let new_collection = NewCollection {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        user_id: ctx.user_id.clone(),
+                        name: collection_name.clone(),
+                        description: Some(format!("Auto-imported from {}", ctx.input.directory)),
+                        visibility: "private".to_string(),
+                        template: Some("astrolog".to_string()),
+                        favorite: false,
+                        tags: ctx.input.tags.clone(),
+                        metadata: Some(
+                            serde_json::json!({
+                                "session_date": date.to_string(),
+                                "auto_imported": true,
+                                "source_directory": ctx.input.directory,
+                            })
+                            .to_string(),
+                        ),
+                        archived: false,
+                    };
+
+                    match repository::get_or_create_auto_collection(conn, &new_collection) {
+                        Ok((c, created)) => {
+                            if created {
                                 result.collections_created += 1;
-                                session_collections.insert(collection_key, c.id.clone());
-                                c.id
-                            }
-                            Err(e) => {
-                                result.errors.push(format!("Failed to create collection: {}", e));
-                                continue;
                             }
+                            ctx.session_collections
+                                .insert(collection_name, c.id.clone());
+                            c.id
+                        }
+                        Err(e) => {
+                            result
+                                .errors
+                                .push(format!("Failed to create collection: {}", e));
+                            return;
                         }
-                    }
-                    Err(e) => {
-                        result.errors.push(format!("Failed to check for existing collection: {}", e));
-                        continue;
                     }
                 }
+                Err(e) => {
+                    result
+                        .errors
+                        .push(format!("Failed to check for existing collection: {}", e));
+                    return;
+                }
             }
+        }
+    } else {
+        // No date - use a generic "Unknown Session" collection
+        let unknown_key = "Unknown Session".to_string();
+        if let Some(id) = ctx.session_collections.get(&unknown_key) {
+            id.clone()
         } else {
-            // No date - use a generic "Unknown Session" collection
-            let unknown_key = "unknown".to_string();
-            if let Some(id) = session_collections.get(&unknown_key) {
-                id.clone()
-            } else {
-                let collection_name = "Unknown Session".to_string();
+            let collection_name = "Unknown Session".to_string();
 
-                match repository::get_collection_by_name(&mut conn, &user_id, &collection_name) {
-                    Ok(Some(existing)) => {
-                        session_collections.insert(unknown_key, existing.id.clone());
-                        existing.id
-                    }
-                    Ok(None) => {
-                        let new_collection = NewCollection {
-                            id: uuid::Uuid::new_v4().to_string(),
-                            user_id: user_id.clone(),
-                            name: collection_name,
-                            description: Some(format!(
-                                "Auto-imported from {} (no date metadata)",
-                                directory.display()
-                            )),
-                            visibility: "private".to_string(),
-                            template: Some("astrolog".to_string()),
-                            favorite: false,
-                            tags: input.tags.clone(),
-                            metadata: Some(
-                                serde_json::json!({
-                                    "auto_imported": true,
-                                    "source_directory": directory.to_string_lossy(),
-                                })
-                                .to_string(),
-                            ),
-                            archived: false,
-                        };
-
-                        match repository::create_collection(&mut conn, &new_collection) {
-                            Ok(c) => {
+            match repository::get_collection_by_name(conn, &ctx.user_id, &collection_name) {
+                Ok(Some(existing)) => {
+                    ctx.session_collections
+                        .insert(unknown_key, existing.id.clone());
+                    existing.id
+                }
+                Ok(None) => {
+                    let new_collection = NewCollection {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        user_id: ctx.user_id.clone(),
+                        name: collection_name,
+                        description: Some(format!(
+                            "Auto-imported from {} (no date metadata)",
+                            ctx.input.directory
+                        )),
+                        visibility: "private".to_string(),
+                        template: Some("astrolog".to_string()),
+                        favorite: false,
+                        tags: ctx.input.tags.clone(),
+                        metadata: Some(
+                            serde_json::json!({
+                                "auto_imported": true,
+                                "source_directory": ctx.input.directory,
+                            })
+                            .to_string(),
+                        ),
+                        archived: false,
+                    };
+
+                    match repository::get_or_create_auto_collection(conn, &new_collection) {
+                        Ok((c, created)) => {
+                            if created {
                                 result.collections_created += 1;
-                                session_collections.insert(unknown_key, c.id.clone());
-                                c.id
-                            }
-                            Err(e) => {
-                                result.errors.push(format!("Failed to create collection: {}", e));
-                                continue;
                             }
+                            ctx.session_collections.insert(unknown_key, c.id.clone());
+                            c.id
+                        }
+                        Err(e) => {
+                            result
+                                .errors
+                                .push(format!("Failed to create collection: {}", e));
+                            return;
                         }
                     }
-                    Err(e) => {
-                        result.errors.push(format!("Failed to check for existing collection: {}", e));
-                        continue;
-                    }
+                }
+                Err(e) => {
+                    result
+                        .errors
+                        .push(format!("Failed to check for existing collection: {}", e));
+                    return;
                 }
             }
-        };
+        }
+    };
 
-        // Check if image already exists using pre-loaded data (O(1) lookup)
-        if let Some(ref url_str) = url {
-            if existing_urls.contains(url_str) {
-                // Image exists, check if it needs to be added to collection
-                if let Some(image_id) = url_to_image_id.get(url_str) {
-                    let pair = (collection_id.clone(), image_id.clone());
-                    if !existing_collection_images.contains(&pair) {
-                        let collection_image = NewCollectionImage {
-                            id: uuid::Uuid::new_v4().to_string(),
-                            collection_id: collection_id.clone(),
-                            image_id: image_id.clone(),
-                        };
-                        let _ = repository::add_image_to_collection(&mut conn, &collection_image);
-                    }
+    // Check if image already exists using pre-loaded data (O(1) lookup)
+    if let Some(ref url_str) = url {
+        if ctx.existing_urls.contains(url_str) {
+            // Image exists, check if it needs to be added to collection
+            if let Some(image_id) = ctx.url_to_image_id.get(url_str) {
+                let pair = (collection_id.clone(), image_id.clone());
+                if !ctx.existing_collection_images.contains(&pair) {
+                    let collection_image = NewCollectionImage {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        collection_id: collection_id.clone(),
+                        image_id: image_id.clone(),
+                    };
+                    let _ = repository::add_image_to_collection(conn, &collection_image);
                 }
-                result.images_skipped += 1;
-                continue;
             }
+            result.images_skipped += 1;
+            return;
         }
+    }
 
-        // Build image record
-        let filename = processed.discovered.base_name.clone();
-        let summary = metadata.object_name.clone();
-        let description = build_description(&metadata);
-
-        // Combine user tags with auto-detected tags
-        let mut all_tags = Vec::new();
-        if let Some(user_tags) = &input.tags {
-            all_tags.push(user_tags.clone());
-        }
-        if processed.discovered.is_stacked {
-            all_tags.push("stacked".to_string());
-        }
-        if metadata.telescope.as_ref().map(|t| t.to_lowercase().contains("seestar")).unwrap_or(false) {
-            all_tags.push("seestar".to_string());
-        }
-        let tags_str = if all_tags.is_empty() {
-            None
-        } else {
-            Some(all_tags.join(", "))
-        };
+    // Build image record. A sidecar's title only stands in for a missing
+    // object name, but its description, tags and rating are the user's own.
+    let sidecar = processed.sidecar.unwrap_or_default();
+    let filename = processed.discovered.base_name.clone();
+    let summary = metadata
+        .object_name
+        .as_deref()
+        .map(|name| ctx.object_names.canonical(name))
+        .or(sidecar.title);
+    let description = sidecar
+        .description
+        .unwrap_or_else(|| build_description(&metadata));
+
+    // Combine user tags with the ones the tag rules assign
+    let auto_tags = if ctx.tag_rules.enabled {
+        rule_tags(&ctx.tag_rules, &TagFacts::from_metadata(&metadata, frame_type))
+    } else {
+        Vec::new()
+    };
+    let tags_str = merge_tags(ctx.input.tags.as_deref(), &auto_tags);
+    let tags_str = merge_tags(tags_str.as_deref(), &sidecar.tags);
 
-        let metadata_json = serde_json::to_string(&metadata).ok();
+    let mut metadata_json = serde_json::to_string(&metadata).ok();
+    let acquisition = AcquisitionColumns::from(&metadata);
 
-        let new_image = NewImage {
-            id: uuid::Uuid::new_v4().to_string(),
-            user_id: user_id.clone(),
-            collection_id: None,
-            filename,
-            url,
-            summary,
-            description: Some(description),
-            content_type: Some("image/jpeg".to_string()),
-            favorite: false,
-            tags: tags_str,
-            visibility: Some("private".to_string()),
-            location: metadata.ra.as_ref().zip(metadata.dec.as_ref()).map(|(ra, dec)| format!("{}, {}", ra, dec)),
-            annotations: None,
-            metadata: metadata_json,
-            thumbnail: processed.thumbnail,
-            fits_url,
-            blob_id: None,
-        };
+    // Failed frames and test shots are imported for review, not listed
+    let reasons = triage_reasons(&ctx.triage, &processed.discovered.base_name, &metadata);
+    if !reasons.is_empty() {
+        metadata_json = with_triage_reasons(metadata_json, &reasons);
+    }
 
-        // Insert image
-        let image = match repository::create_image(&mut conn, &new_image) {
-            Ok(img) => img,
-            Err(e) => {
-                result.errors.push(format!(
-                    "Failed to create image {}: {}",
-                    processed.discovered.base_name, e
-                ));
-                result.images_skipped += 1;
-                continue;
-            }
-        };
+    // Planetary and lunar captures get their ephemeris instead of a plate solve
+    let solar_system = solar_system_info(
+        metadata.object_name.as_deref(),
+        &processed.discovered.base_name,
+        metadata.date_obs.as_deref(),
+    );
+    let summary = match &solar_system {
+        Some(info) => {
+            metadata_json = with_solar_system(metadata_json, info);
+            summary.or_else(|| Some(info.body.name().to_string()))
+        }
+        None => summary,
+    };
 
-        // Add image to collection via join table
-        let collection_image = NewCollectionImage {
-            id: uuid::Uuid::new_v4().to_string(),
-            collection_id: collection_id.clone(),
-            image_id: image.id.clone(),
-        };
+    let new_image = NewImage {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: ctx.user_id.clone(),
+        collection_id: None,
+        filename,
+        url,
+        summary,
+        description: Some(description),
+        content_type: Some("image/jpeg".to_string()),
+        favorite: false,
+        tags: tags_str,
+        visibility: Some("private".to_string()),
+        location: metadata
+            .ra
+            .as_ref()
+            .zip(metadata.dec.as_ref())
+            .map(|(ra, dec)| format!("{}, {}", ra, dec))
+            .or_else(|| {
+                sidecar
+                    .ra
+                    .zip(sidecar.dec)
+                    .map(|(ra, dec)| format!("{}, {}", ra, dec))
+            }),
+        annotations: None,
+        metadata: metadata_json,
+        thumbnail: processed.thumbnail,
+        fits_url,
+        blob_id: None,
+        exposure: acquisition.exposure,
+        gain: acquisition.gain,
+        filter: acquisition.filter,
+        telescope: acquisition.telescope,
+        date_obs: acquisition.date_obs,
+        wcs: None,
+        parent_image_id: None,
+        triage: (!reasons.is_empty()).then(|| TRIAGE_PENDING.to_string()),
+    };
 
-        if let Err(e) = repository::add_image_to_collection(&mut conn, &collection_image) {
+    // Insert image
+    let image = match repository::create_image(conn, &new_image) {
+        Ok(img) => img,
+        Err(e) => {
             result.errors.push(format!(
-                "Failed to add image to collection: {}",
-                e
+                "Failed to create image {}: {}",
+                processed.discovered.base_name, e
             ));
+            result.images_skipped += 1;
+            return;
         }
-
-        // Also add to the user-specified target collection if provided
-        if let Some(ref target_coll_id) = input.add_to_collection {
-            let target_ci = NewCollectionImage {
-                id: uuid::Uuid::new_v4().to_string(),
-                collection_id: target_coll_id.clone(),
-                image_id: image.id.clone(),
-            };
-            if let Err(e) = repository::add_image_to_collection(&mut conn, &target_ci) {
-                // Not fatal — might already be in the collection
-                log::warn!("Failed to add image to target collection: {}", e);
-            }
+    };
+    let image = match sidecar.rating {
+        Some(rating) => {
+            repository::set_image_rating(conn, &image.id, Some(rating)).unwrap_or_else(|e| {
+                log::warn!("Failed to set rating for {}: {}", image.filename, e);
+                image
+            })
         }
+        None => image,
+    };
 
-            result.images_imported += 1;
-        } // End of inner loop (for each processed image in batch)
-    } // End of batch loop
-
-    // === UPDATE DIRECTORY CACHE ===
-    // Save the modification times for all directories that were processed
-    if !changed_dirs.is_empty() {
-        let _ = window.emit("scan-progress", &ScanProgress {
-            current: total_discovered,
-            total: total_discovered,
-            current_file: format!("Updating cache for {} directories...", changed_dirs.len()),
-            percent: 99,
-            skipped: result.images_skipped,
-            cancelled: false,
-        });
-
-        let now = chrono::Utc::now().to_rfc3339();
-        let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    // Link to known equipment from TELESCOP/INSTRUME headers
+    if let Err(e) = repository::auto_link_image_equipment(
+        conn,
+        &ctx.user_id,
+        &image.id,
+        metadata.telescope.as_deref(),
+        metadata.instrument.as_deref(),
+    ) {
+        log::warn!("Failed to link equipment for {}: {}", image.filename, e);
+    }
+
+    // Tag where it was taken
+    if let Err(e) = tag_capture_site(conn, &ctx.capture_sites, &image) {
+        log::warn!("Failed to tag capture site for {}: {}", image.filename, e);
+    }
+
+    // Track filter usage and integration time
+    if let Err(e) = super::filters::record_image_filter(
+        conn,
+        &ctx.user_id,
+        &image.id,
+        metadata.filter.as_deref(),
+        metadata.exposure,
+        metadata.stacked_frames,
+    ) {
+        log::warn!("Failed to record filter for {}: {}", image.filename, e);
+    }
+
+    // Add image to collection via join table
+    let collection_image = NewCollectionImage {
+        id: uuid::Uuid::new_v4().to_string(),
+        collection_id: collection_id.clone(),
+        image_id: image.id.clone(),
+    };
 
-        for (dir_path, mtime) in changed_dirs {
-            let dir_path_str = dir_path.to_string_lossy().to_string();
-            let entry = NewScannedDirectory {
-                id: uuid::Uuid::new_v4().to_string(),
-                user_id: user_id.clone(),
-                path: dir_path_str,
-                fs_modified_at: mtime,
-                last_scanned_at: now.clone(),
-                image_count: 0, // We don't track exact count per directory
-            };
+    if let Err(e) = repository::add_image_to_collection(conn, &collection_image) {
+        result
+            .errors
+            .push(format!("Failed to add image to collection: {}", e));
+    }
 
-            if let Err(e) = repository::upsert_scanned_directory(&mut conn, &entry) {
-                log::warn!("Failed to update directory cache: {}", e);
-            }
+    // Also add to the user-specified target collection if provided
+    if let Some(ref target_coll_id) = ctx.input.add_to_collection {
+        let target_ci = NewCollectionImage {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_id: target_coll_id.clone(),
+            image_id: image.id.clone(),
+        };
+        if let Err(e) = repository::add_image_to_collection(conn, &target_ci) {
+            // Not fatal — might already be in the collection
+            log::warn!("Failed to add image to target collection: {}", e);
         }
     }
 
-    Ok(result)
+    events::publish(
+        events::IMAGE_ADDED,
+        &ImageAdded {
+            id: image.id.clone(),
+            filename: image.filename.clone(),
+            object_name: image.summary.clone(),
+            stacked: frame_type == FrameType::Stacked,
+            collection_id: Some(collection_id),
+            held_for_triage: !reasons.is_empty(),
+            source: "scan",
+        },
+    );
+
+    result.images_imported += 1;
+    if !reasons.is_empty() {
+        result.images_triaged += 1;
+    }
 }
 
 /// Build a description string from FITS metadata
@@ -1377,6 +2233,7 @@ fn build_description(metadata: &FitsMetadata) -> String {
 /// Preview scan results without importing
 #[tauri::command]
 pub fn preview_bulk_scan(
+    app: AppHandle,
     input: BulkScanInput,
 ) -> Result<BulkScanPreview, String> {
     let directory = PathBuf::from(&input.directory);
@@ -1386,9 +2243,11 @@ pub fn preview_bulk_scan(
 
     // Use the progress version with a no-op callback and a dummy cancellation flag
     let cancelled = AtomicBool::new(false);
+    let classifier = frame_classifier(&app);
     let discovered_images = scan_directory_with_progress(
         &directory,
         input.stacked_only,
+        &classifier,
         input.max_files,
         &cancelled,
         |_, _| {}, // No-op progress callback for preview
@@ -1452,6 +2311,61 @@ pub struct PreviewFile {
     pub is_stacked: bool,
 }
 
+// =============================================================================
+// Directory Scan Cache
+// =============================================================================
+
+/// Directories bulk scans have gone through, with what the last scan of each
+/// found. With `parent`, only that directory and the ones under it.
+#[tauri::command]
+pub fn get_scanned_directories(
+    state: State<'_, AppState>,
+    parent: Option<String>,
+) -> Result<Vec<ScannedDirectory>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let directories = match &parent {
+        Some(parent) => repository::get_scanned_subdirectories(&mut conn, &state.user_id, parent)
+            .map(|dirs| within_directory(dirs, parent)),
+        None => repository::get_scanned_directories(&mut conn, &state.user_id),
+    };
+    directories.map_err(|e| e.to_string())
+}
+
+/// Forget the scan cache for a directory (and, with `recursive`, the ones
+/// under it), so the next scan looks at every image in it again. Returns the
+/// number of directories forgotten.
+#[tauri::command]
+pub fn forget_scanned_directory(
+    state: State<'_, AppState>,
+    path: String,
+    recursive: Option<bool>,
+) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    if !recursive.unwrap_or(false) {
+        return repository::delete_scanned_directory(&mut conn, &state.user_id, &path)
+            .map_err(|e| e.to_string());
+    }
+
+    let directories = repository::get_scanned_subdirectories(&mut conn, &state.user_id, &path)
+        .map_err(|e| e.to_string())?;
+    let mut forgotten = 0;
+    for directory in within_directory(directories, &path) {
+        forgotten +=
+            repository::delete_scanned_directory(&mut conn, &state.user_id, &directory.path)
+                .map_err(|e| e.to_string())?;
+    }
+    Ok(forgotten)
+}
+
+/// Entries for `parent` itself and directories under it. The repository
+/// matches by string prefix, which also takes in siblings like `parent-2`.
+fn within_directory(directories: Vec<ScannedDirectory>, parent: &str) -> Vec<ScannedDirectory> {
+    directories
+        .into_iter()
+        .filter(|d| Path::new(&d.path).starts_with(parent))
+        .collect()
+}
+
 // =============================================================================
 // Raw File Collection
 // =============================================================================
@@ -1509,7 +2423,7 @@ pub fn cancel_collect() -> Result<(), String> {
 
 /// Derive the _sub directory path from a stacked image path
 /// Example: /data/SomeTarget/Stacked_*.jpg -> /data/SomeTarget_sub/
-fn get_sub_directory(stacked_path: &Path) -> Option<PathBuf> {
+pub(super) fn get_sub_directory(stacked_path: &Path) -> Option<PathBuf> {
     let parent = stacked_path.parent()?;
     let parent_name = parent.file_name()?.to_str()?;
 
@@ -1717,6 +2631,7 @@ pub async fn collect_raw_files(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::collection_naming::DEFAULT_COLLECTION_TEMPLATE;
 
     // ========================================================================
     // extract_string_value tests
@@ -1923,6 +2838,97 @@ mod tests {
         assert_eq!(date, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
     }
 
+    #[test]
+    fn get_session_date_utc_uses_site_longitude() {
+        // 04:30 UTC is 20:30 the previous evening at 120°W (UTC-8)
+        let date = get_session_date_utc("2026-01-16T04:30:00", Some(-120.0)).unwrap();
+        assert_eq!(date, NaiveDate::from_ymd_opt(2026, 1, 15).unwrap());
+        // ...and 07:30 the next morning at 150°E (UTC+10), still the same night
+        let date = get_session_date_utc("2026-01-15T21:30:00Z", Some(150.0)).unwrap();
+        assert_eq!(date, NaiveDate::from_ymd_opt(2026, 1, 15).unwrap());
+    }
+
+    #[test]
+    fn utc_frames_either_side_of_utc_midnight_share_a_night() {
+        // An evening at 75°W spans 23:00–05:00 UTC
+        let before = get_session_date_utc("2026-03-10T23:00:00", Some(-75.0));
+        let after = get_session_date_utc("2026-03-11T05:00:00", Some(-75.0));
+        assert_eq!(before, after);
+        assert_eq!(before, NaiveDate::from_ymd_opt(2026, 3, 10));
+    }
+
+    #[test]
+    fn session_date_prefers_date_loc_then_sitelong() {
+        let json = r#"{"DATE-OBS":"Some(CharacterString(\"2026-01-16T04:30:00\"))","DATE-LOC":"Some(CharacterString(\"2026-01-16T13:30:00\"))"}"#;
+        assert_eq!(
+            session_date_from_metadata(json, Some(-120.0)),
+            NaiveDate::from_ymd_opt(2026, 1, 16)
+        );
+        let json = r#"{"date_obs":"2026-01-16T04:30:00","raw_headers":{"SITELONG":"Some(RealFloatingNumber(-120.0))"}}"#;
+        assert_eq!(
+            session_date_from_metadata(json, Some(150.0)),
+            NaiveDate::from_ymd_opt(2026, 1, 15)
+        );
+    }
+
+    #[test]
+    fn parse_longitude_formats() {
+        assert_eq!(parse_longitude("Some(RealFloatingNumber(-71.5))"), Some(-71.5));
+        assert_eq!(parse_longitude("Some(CharacterString(\"-071 30 00\"))"), Some(-71.5));
+        assert_eq!(parse_longitude("Some(CharacterString(\"12:15:00\"))"), Some(12.25));
+        assert_eq!(parse_longitude("Some(CharacterString(\"east\"))"), None);
+    }
+
+    // ========================================================================
+    // metadata_header_value tests
+    // ========================================================================
+
+    #[test]
+    fn metadata_header_value_from_parsed_field() {
+        let json = r#"{"telescope":"Seestar S50","raw_headers":{}}"#;
+        assert_eq!(
+            metadata_header_value(json, "telescope", "TELESCOP"),
+            Some("Seestar S50".to_string())
+        );
+    }
+
+    #[test]
+    fn metadata_header_value_from_raw_headers() {
+        let json = r#"{"INSTRUME":"Some(CharacterString(\"ZWO ASI585MC\"))"}"#;
+        assert_eq!(
+            metadata_header_value(json, "instrument", "INSTRUME"),
+            Some("ZWO ASI585MC".to_string())
+        );
+    }
+
+    #[test]
+    fn metadata_header_value_missing() {
+        assert_eq!(metadata_header_value("{}", "telescope", "TELESCOP"), None);
+        assert_eq!(metadata_header_value("not json", "telescope", "TELESCOP"), None);
+    }
+
+    #[test]
+    fn acquisition_columns_from_parsed_fields() {
+        let json = r#"{"exposure":10.0,"gain":80,"filter":"LP","telescope":"Seestar S50","date_obs":"2025-01-01T20:00:00","raw_headers":{}}"#;
+        let acq = AcquisitionColumns::from_metadata(Some(json));
+        assert_eq!(acq.exposure, Some(10.0));
+        assert_eq!(acq.gain, Some(80));
+        assert_eq!(acq.filter.as_deref(), Some("LP"));
+        assert_eq!(acq.telescope.as_deref(), Some("Seestar S50"));
+        assert_eq!(acq.date_obs.as_deref(), Some("2025-01-01T20:00:00"));
+    }
+
+    #[test]
+    fn acquisition_columns_from_raw_headers() {
+        let json = r#"{"EXPTIME":"Some(RealFloatingNumber(30.0))","GAIN":"Some(IntegerNumber(100))","FILTER":"Some(CharacterString(\"Ha\"))"}"#;
+        let acq = AcquisitionColumns::from_metadata(Some(json));
+        assert_eq!(acq.exposure, Some(30.0));
+        assert_eq!(acq.gain, Some(100));
+        assert_eq!(acq.filter.as_deref(), Some("Ha"));
+        assert_eq!(acq.telescope, None);
+        assert_eq!(AcquisitionColumns::from_metadata(None), AcquisitionColumns::default());
+    }
+
     // ========================================================================
     // generate_collection_name tests
     // ========================================================================
@@ -1930,16 +2936,206 @@ mod tests {
     #[test]
     fn generate_collection_name_formats_date() {
         let date = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
-        assert_eq!(generate_collection_name(&date, None), "2026-01-15");
+        assert_eq!(
+            generate_collection_name(DEFAULT_COLLECTION_TEMPLATE, &date, None),
+            "2026-01-15"
+        );
     }
 
     #[test]
     fn generate_collection_name_ignores_object_name() {
         let date = NaiveDate::from_ymd_opt(2026, 3, 28).unwrap();
-        // The current implementation ignores the object_name parameter
+        // The default template only uses the date
+        assert_eq!(
+            generate_collection_name(DEFAULT_COLLECTION_TEMPLATE, &date, Some("M42")),
+            "2026-03-28"
+        );
+    }
+
+    #[test]
+    fn generate_collection_name_fills_template() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 28).unwrap();
         assert_eq!(
-            generate_collection_name(&date, Some("M42")),
+            generate_collection_name("{date} – {object}", &date, Some("M42")),
+            "2026-03-28 – M42"
+        );
+        assert_eq!(
+            generate_collection_name("{object}/{date}", &date, Some(" M 42 ")),
+            "M 42/2026-03-28"
+        );
+        assert_eq!(
+            generate_collection_name("Night of {date} ({object})", &date, Some("M42")),
+            "Night of 2026-03-28 (M42)"
+        );
+    }
+
+    #[test]
+    fn generate_collection_name_drops_missing_fields() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 28).unwrap();
+        assert_eq!(
+            generate_collection_name("{date} – {object}", &date, None),
+            "2026-03-28"
+        );
+        assert_eq!(
+            generate_collection_name("{object}/{date}", &date, Some("  ")),
             "2026-03-28"
         );
+        assert_eq!(
+            generate_collection_name("Night of {date} ({object})", &date, None),
+            "Night of 2026-03-28"
+        );
+        assert_eq!(
+            generate_collection_name("{object}", &date, None),
+            "2026-03-28"
+        );
+        // Unknown fields are kept as text
+        assert_eq!(
+            generate_collection_name("{date} {filter}", &date, None),
+            "2026-03-28 {filter}"
+        );
+    }
+
+    #[test]
+    fn scanned_directories_record_what_the_scan_found() {
+        use diesel::Connection;
+
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        let scans = [
+            ("/astro/M 31", 12, 3, 1),
+            ("/astro/M 31/sub", 40, 0, 0),
+            ("/astro/M 31-2", 5, 5, 0),
+        ];
+        let directories = scans
+            .iter()
+            .map(|&(path, found, imported, failed)| {
+                let scan = DirectoryScan {
+                    mtime: 1_700_000_000,
+                    listing_hash: Some(format!("listing of {}", path)),
+                    found,
+                    imported,
+                    failed,
+                };
+                (PathBuf::from(path), scan)
+            })
+            .collect();
+        record_scanned_directories(&mut conn, "user", directories);
+
+        let recorded = repository::get_scanned_directory(&mut conn, "user", "/astro/M 31")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (recorded.image_count, recorded.last_imported, recorded.last_failed),
+            (12, 3, 1)
+        );
+        assert_eq!(
+            recorded.listing_hash.as_deref(),
+            Some("listing of /astro/M 31")
+        );
+
+        let under =
+            repository::get_scanned_subdirectories(&mut conn, "user", "/astro/M 31").unwrap();
+        assert_eq!(under.len(), 3);
+        let mut within: Vec<_> = within_directory(under, "/astro/M 31")
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        within.sort();
+        assert_eq!(within, vec!["/astro/M 31", "/astro/M 31/sub"]);
+    }
+
+    #[test]
+    fn nested_listings_change_when_files_are_added() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let stacked = dir.path().join("M 31").join("Stacked");
+        std::fs::create_dir_all(&stacked).unwrap();
+        std::fs::write(dir.path().join("M 31").join("Light_M 31_001.fit"), b"sub").unwrap();
+        std::fs::write(stacked.join("Stacked_10_M 31.fit"), b"stack").unwrap();
+
+        let listings = || -> HashMap<PathBuf, String> {
+            let images = scan_directory_with_progress(
+                dir.path(),
+                false,
+                &FrameClassifier::default(),
+                None,
+                &AtomicBool::new(false),
+                |_, _| {},
+            );
+            let mut by_dir: HashMap<PathBuf, Vec<DiscoveredImage>> = HashMap::new();
+            for img in images {
+                by_dir.entry(img.directory.clone()).or_default().push(img);
+            }
+            by_dir
+                .into_iter()
+                .map(|(dir, images)| (dir, listing_hash(&images)))
+                .collect()
+        };
+
+        let before = listings();
+        assert_eq!(before.len(), 2);
+        assert_eq!(listings(), before);
+
+        std::fs::write(stacked.join("Stacked_20_M 31.fit"), b"stack").unwrap();
+        let after = listings();
+        assert_ne!(after[&stacked], before[&stacked]);
+        let session = dir.path().join("M 31");
+        assert_eq!(after[&session], before[&session]);
+    }
+
+    #[test]
+    fn paused_scans_wait_until_resumed_or_cancelled() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            *SCAN_JOB.lock().unwrap() = Some("scan-test".to_string());
+            assert!(pause_scan("scan-other".to_string()).is_err());
+            assert!(!SCAN_PAUSED.load(Ordering::SeqCst));
+            pause_scan("scan-test".to_string()).unwrap();
+            let reported = Arc::new(AtomicBool::new(false));
+            let flag = reported.clone();
+            let waiting = tokio::spawn(wait_while_paused(move || {
+                flag.store(true, Ordering::SeqCst);
+            }));
+            tokio::time::sleep(PAUSE_POLL_INTERVAL * 2).await;
+            assert!(!waiting.is_finished());
+            assert!(reported.load(Ordering::SeqCst));
+
+            assert!(resume_scan("scan-other".to_string()).is_err());
+            resume_scan("scan-test".to_string()).unwrap();
+            waiting.await.unwrap();
+
+            pause_scan("scan-test".to_string()).unwrap();
+            let waiting = tokio::spawn(wait_while_paused(|| {}));
+            cancel_scan().unwrap();
+            waiting.await.unwrap();
+            SCAN_CANCELLED.store(false, Ordering::SeqCst);
+            resume_scan("scan-test".to_string()).unwrap();
+
+            *SCAN_JOB.lock().unwrap() = None;
+            assert!(pause_scan("scan-test".to_string()).is_err());
+        });
+    }
+
+    #[test]
+    fn jpeg_thumbnails_are_decoded_scaled_down() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("Stacked_M 31.jpg");
+        image::RgbImage::from_pixel(2400, 1600, image::Rgb([40, 60, 90]))
+            .save(&path)
+            .unwrap();
+
+        // 1/8 scale is the smallest that still covers the thumbnail
+        let img = decode_jpeg_scaled(&path, THUMBNAIL_SIZE).unwrap();
+        assert_eq!((img.width(), img.height()), (300, 200));
+        assert!(generate_thumbnail(&path)
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+
+        // Other formats still go through the full decoder
+        let png = dir.path().join("Stacked_M 31.png");
+        image::RgbImage::from_pixel(64, 64, image::Rgb([0, 0, 0]))
+            .save(&png)
+            .unwrap();
+        assert!(decode_jpeg_scaled(&png, THUMBNAIL_SIZE).is_err());
+        assert!(generate_thumbnail(&png).is_ok());
     }
 }
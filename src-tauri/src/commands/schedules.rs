@@ -1,5 +1,6 @@
 //! Schedule commands for managing observation schedules
 
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
@@ -7,6 +8,10 @@ use crate::db::models::{NewObservationSchedule, ObservationSchedule, ScheduleIte
 use crate::db::repository;
 use crate::state::AppState;
 
+use super::astronomy::LocationInput;
+use super::meridian::{next_flip, next_setting, precess_from_j2000, transit_altitude};
+use super::plate_solve::parse_angle;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateScheduleInput {
     pub name: String,
@@ -27,6 +32,7 @@ pub struct UpdateScheduleInput {
     pub items: Option<Vec<ScheduleItem>>,
     pub is_active: Option<bool>,
     pub equipment_id: Option<String>,
+    pub archived: Option<bool>,
 }
 
 #[tauri::command]
@@ -54,6 +60,26 @@ pub fn get_active_schedules(
         .map_err(|e| e.to_string())
 }
 
+/// Get the active schedules for one night (YYYY-MM-DD), one per equipment set
+#[tauri::command]
+pub fn get_active_schedules_for_night(
+    state: State<'_, AppState>,
+    date: String,
+) -> Result<Vec<ObservationSchedule>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_active_schedules_for_night(&mut conn, &state.user_id, &date)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_archived_schedules(
+    state: State<'_, AppState>,
+) -> Result<Vec<ObservationSchedule>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_archived_schedules(&mut conn, &state.user_id)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_schedule(
     state: State<'_, AppState>,
@@ -81,6 +107,7 @@ pub fn create_schedule(
         items: "[]".to_string(),
         is_active: input.is_active.unwrap_or(false),
         equipment_id: input.equipment_id,
+        archived: false,
     };
 
     repository::create_schedule(&mut conn, &new_schedule)
@@ -106,6 +133,7 @@ pub fn update_schedule(
         items: items_json,
         is_active: input.is_active,
         equipment_id: input.equipment_id,
+        archived: input.archived,
     };
 
     repository::update_schedule(&mut conn, &input.id, &update)
@@ -120,6 +148,31 @@ pub fn delete_schedule(state: State<'_, AppState>, id: String) -> Result<bool, S
         .map_err(|e| e.to_string())
 }
 
+/// Archive or restore a schedule. Archiving also deactivates it.
+#[tauri::command]
+pub fn archive_schedule(
+    state: State<'_, AppState>,
+    id: String,
+    archived: bool,
+) -> Result<ObservationSchedule, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::set_schedule_archived(&mut conn, &id, archived)
+        .map_err(|e| e.to_string())
+}
+
+/// Archive schedules for nights before `before` (YYYY-MM-DD, default
+/// today). Returns how many were archived.
+#[tauri::command]
+pub fn archive_past_schedules(
+    state: State<'_, AppState>,
+    before: Option<String>,
+) -> Result<usize, String> {
+    let before = before.unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::archive_schedules_before(&mut conn, &state.user_id, &before)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn add_schedule_item(
     state: State<'_, AppState>,
@@ -136,6 +189,7 @@ pub fn add_schedule_item(
     // Parse existing items and add new one
     let mut items: Vec<ScheduleItem> =
         serde_json::from_str(&schedule.items).unwrap_or_default();
+    let item = place_item(item, &items);
     items.push(item);
 
     // Sort by start time
@@ -176,3 +230,385 @@ pub fn remove_schedule_item(
     repository::update_schedule(&mut conn, &schedule_id, &update)
         .map_err(|e| e.to_string())
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleWarningKind {
+    MeridianFlip,
+    /// The target sets before a shifted slot ends
+    TargetSets,
+}
+
+/// Something about a schedule item to know before the night
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleWarning {
+    pub item_id: String,
+    pub object_name: String,
+    pub kind: ScheduleWarningKind,
+    pub time: DateTime<Utc>,
+    pub message: String,
+}
+
+/// Schedule item times are local wall-clock times ("2025-01-15T21:30")
+pub(super) fn parse_item_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Schedule item times are written back in the same wall-clock form
+fn format_item_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%dT%H:%M")
+        .to_string()
+}
+
+/// When the last of an item's dependencies ends
+fn dependencies_end(item: &ScheduleItem, items: &[ScheduleItem]) -> Option<DateTime<Utc>> {
+    items
+        .iter()
+        .filter(|other| item.depends_on.contains(&other.id))
+        .filter_map(|other| parse_item_time(&other.end_time))
+        .max()
+}
+
+/// Fill in a new item's start from its dependencies and its end from its
+/// duration
+fn place_item(mut item: ScheduleItem, items: &[ScheduleItem]) -> ScheduleItem {
+    if let Some(start) = dependencies_end(&item, items) {
+        item.start_time = format_item_time(start);
+    }
+    if let (Some(minutes), Some(start)) = (item.duration_minutes, parse_item_time(&item.start_time))
+    {
+        item.end_time = format_item_time(start + Duration::minutes(minutes));
+    }
+    item
+}
+
+/// Move `from_item`'s end by `delta_minutes` and recompute the open items
+/// after it. Items with dependencies start when the last of them ends;
+/// others keep their start unless the item before now runs into it. Moved
+/// items keep their duration (or length), but end early if `sets_at` says
+/// their target sets first.
+fn shift_items(
+    items: &mut [ScheduleItem],
+    from_item: &str,
+    delta_minutes: i64,
+    sets_at: impl Fn(&ScheduleItem) -> Option<DateTime<Utc>>,
+) -> Result<Vec<ScheduleWarning>, String> {
+    items.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    let from = items
+        .iter()
+        .position(|item| item.id == from_item)
+        .ok_or_else(|| format!("Schedule item not found: {}", from_item))?;
+    let (Some(from_start), Some(from_end)) = (
+        parse_item_time(&items[from].start_time),
+        parse_item_time(&items[from].end_time),
+    ) else {
+        return Err(format!("{} has no valid times", items[from].object_name));
+    };
+
+    let mut previous_end = (from_end + Duration::minutes(delta_minutes)).max(from_start);
+    items[from].end_time = format_item_time(previous_end);
+    if items[from].duration_minutes.is_some() {
+        items[from].duration_minutes = Some((previous_end - from_start).num_minutes());
+    }
+
+    let mut warnings = Vec::new();
+    for i in from + 1..items.len() {
+        let item = &items[i];
+        if item.completed {
+            continue;
+        }
+        let (Some(start), Some(end)) = (
+            parse_item_time(&item.start_time),
+            parse_item_time(&item.end_time),
+        ) else {
+            continue;
+        };
+
+        let earliest = if item.depends_on.is_empty() {
+            start
+        } else {
+            dependencies_end(item, items).unwrap_or(start)
+        };
+        let new_start = earliest.max(previous_end);
+        let mut new_end = new_start
+            + item
+                .duration_minutes
+                .map(Duration::minutes)
+                .unwrap_or(end - start);
+        if new_start == start && new_end == end {
+            previous_end = end;
+            continue;
+        }
+
+        if let Some(set) = sets_at(item).filter(|&set| set < new_end) {
+            let set_local = set.with_timezone(&Local).format("%H:%M");
+            let message = if set <= new_start {
+                format!("Sets at {}, before the shifted slot starts", set_local)
+            } else {
+                format!(
+                    "Sets at {}, {} min before the shifted slot would end",
+                    set_local,
+                    (new_end - set).num_minutes()
+                )
+            };
+            new_end = set.max(new_start);
+            warnings.push(ScheduleWarning {
+                item_id: item.id.clone(),
+                object_name: item.object_name.clone(),
+                kind: ScheduleWarningKind::TargetSets,
+                time: set,
+                message,
+            });
+        }
+
+        items[i].start_time = format_item_time(new_start);
+        items[i].end_time = format_item_time(new_end);
+        previous_end = new_end;
+    }
+    Ok(warnings)
+}
+
+/// Warning for an item whose slot straddles the target's meridian flip
+fn meridian_flip_warning(
+    item: &ScheduleItem,
+    ra_deg: f64,
+    dec_deg: f64,
+    location: &LocationInput,
+    delay_minutes: f64,
+) -> Option<ScheduleWarning> {
+    let start = parse_item_time(&item.start_time)?;
+    let end = parse_item_time(&item.end_time)?;
+    // Targets below the horizon at upper transit never need a flip
+    let (_, dec) = precess_from_j2000(ra_deg, dec_deg, start);
+    if transit_altitude(dec, location.latitude) <= 0.0 {
+        return None;
+    }
+
+    let flip = next_flip(ra_deg, dec_deg, location.longitude, start, delay_minutes);
+    if flip <= start || flip >= end {
+        return None;
+    }
+    Some(ScheduleWarning {
+        item_id: item.id.clone(),
+        object_name: item.object_name.clone(),
+        kind: ScheduleWarningKind::MeridianFlip,
+        time: flip,
+        message: format!(
+            "Meridian flip at {}, {} min into the slot",
+            flip.with_timezone(&Local).format("%H:%M"),
+            (flip - start).num_minutes()
+        ),
+    })
+}
+
+/// Warnings for a schedule's open items: currently meridian flips that fall
+/// inside an item's time slot. `flip_delay_minutes` is how long the mount
+/// tracks past the meridian (default 0).
+#[tauri::command]
+pub fn get_schedule_warnings(
+    state: State<'_, AppState>,
+    schedule_id: String,
+    location: LocationInput,
+    flip_delay_minutes: Option<f64>,
+) -> Result<Vec<ScheduleWarning>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let schedule = repository::get_schedule_by_id(&mut conn, &schedule_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Schedule not found".to_string())?;
+    let items: Vec<ScheduleItem> = serde_json::from_str(&schedule.items).unwrap_or_default();
+    let delay = flip_delay_minutes.unwrap_or(0.0).max(0.0);
+
+    let mut warnings = Vec::new();
+    for item in items.iter().filter(|i| !i.completed) {
+        let Some(todo) =
+            repository::get_todo_by_id(&mut conn, &item.todo_id).map_err(|e| e.to_string())?
+        else {
+            continue;
+        };
+        let position = parse_angle(&todo.ra, 15.0).zip(parse_angle(&todo.dec, 1.0));
+        if let Some((ra, dec)) = position {
+            warnings.extend(meridian_flip_warning(item, ra, dec, &location, delay));
+        }
+    }
+    Ok(warnings)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShiftedSchedule {
+    pub schedule: ObservationSchedule,
+    /// Items cut short, or left with no time, because their target sets
+    pub warnings: Vec<ScheduleWarning>,
+}
+
+/// Push back (or, with a negative delta, pull in) a schedule after
+/// `from_item` ran over by `delta_minutes`. Later items are recomputed from
+/// their dependencies and durations, and end no later than their target
+/// sets below `min_altitude` (default 0°) at `location_id` (default: the
+/// default location; without a location set times aren't checked).
+#[tauri::command]
+pub fn shift_schedule(
+    state: State<'_, AppState>,
+    schedule_id: String,
+    from_item: String,
+    delta_minutes: i64,
+    location_id: Option<String>,
+    min_altitude: Option<f64>,
+) -> Result<ShiftedSchedule, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let schedule = repository::get_schedule_by_id(&mut conn, &schedule_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Schedule not found".to_string())?;
+    let mut items: Vec<ScheduleItem> = serde_json::from_str(&schedule.items).unwrap_or_default();
+
+    let location = match location_id {
+        Some(id) => Some(
+            repository::get_location_by_id(&mut conn, &id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Location not found: {}", id))?,
+        ),
+        None => repository::get_default_location(&mut conn, &state.user_id)
+            .map_err(|e| e.to_string())?,
+    };
+    // Set times as of each item's planned start
+    let mut set_times = std::collections::HashMap::new();
+    if let Some(location) = &location {
+        for item in items.iter().filter(|i| !i.completed) {
+            let Some(start) = parse_item_time(&item.start_time) else {
+                continue;
+            };
+            let Some(todo) =
+                repository::get_todo_by_id(&mut conn, &item.todo_id).map_err(|e| e.to_string())?
+            else {
+                continue;
+            };
+            let position = parse_angle(&todo.ra, 15.0).zip(parse_angle(&todo.dec, 1.0));
+            if let Some((ra, dec)) = position {
+                let set = next_setting(
+                    ra,
+                    dec,
+                    location.latitude,
+                    location.longitude,
+                    start,
+                    min_altitude.unwrap_or(0.0),
+                );
+                set_times.insert(item.id.clone(), set);
+            }
+        }
+    }
+
+    let warnings = shift_items(&mut items, &from_item, delta_minutes, |item| {
+        set_times.get(&item.id).copied().flatten()
+    })?;
+
+    let update = UpdateObservationSchedule {
+        items: Some(serde_json::to_string(&items).unwrap_or_else(|_| "[]".to_string())),
+        ..Default::default()
+    };
+    let schedule =
+        repository::update_schedule(&mut conn, &schedule_id, &update).map_err(|e| e.to_string())?;
+    Ok(ShiftedSchedule { schedule, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, start: &str, end: &str) -> ScheduleItem {
+        ScheduleItem {
+            id: id.to_string(),
+            todo_id: format!("todo-{}", id),
+            object_name: id.to_uppercase(),
+            start_time: start.to_string(),
+            end_time: end.to_string(),
+            priority: 1,
+            notes: None,
+            completed: false,
+            framing: None,
+            duration_minutes: None,
+            depends_on: Vec::new(),
+        }
+    }
+
+    fn times(items: &[ScheduleItem]) -> Vec<(&str, &str, &str)> {
+        items
+            .iter()
+            .map(|i| (i.id.as_str(), i.start_time.as_str(), i.end_time.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn shifting_pushes_later_items_and_respects_set_times() {
+        let mut items = vec![
+            item("m42", "2025-01-15T20:00", "2025-01-15T21:00"),
+            item("m45", "2025-01-15T21:00", "2025-01-15T22:00"),
+            // Fixed, with slack before it
+            item("m1", "2025-01-15T22:30", "2025-01-15T23:30"),
+            ScheduleItem {
+                duration_minutes: Some(90),
+                depends_on: vec!["m1".to_string()],
+                ..item("m81", "2025-01-15T23:30", "2025-01-16T01:00")
+            },
+        ];
+        let m81_sets = parse_item_time("2025-01-16T01:20").unwrap();
+        let sets_at = |i: &ScheduleItem| (i.id == "m81").then_some(m81_sets);
+
+        let warnings = shift_items(&mut items, "m42", 45, sets_at).unwrap();
+        assert_eq!(
+            times(&items),
+            [
+                ("m42", "2025-01-15T20:00", "2025-01-15T21:45"),
+                ("m45", "2025-01-15T21:45", "2025-01-15T22:45"),
+                ("m1", "2025-01-15T22:45", "2025-01-15T23:45"),
+                ("m81", "2025-01-15T23:45", "2025-01-16T01:15"),
+            ]
+        );
+        assert!(warnings.is_empty());
+
+        // Running over again pushes M81 past its setting
+        let warnings = shift_items(&mut items, "m45", 30, sets_at).unwrap();
+        assert_eq!(items[3].start_time, "2025-01-16T00:15");
+        assert_eq!(items[3].end_time, "2025-01-16T01:20");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, ScheduleWarningKind::TargetSets);
+
+        // Finishing early pulls in dependent items but not fixed ones
+        let warnings = shift_items(&mut items, "m1", -30, sets_at).unwrap();
+        assert_eq!(items[2].end_time, "2025-01-15T23:45");
+        assert_eq!(
+            (items[3].start_time.as_str(), items[3].end_time.as_str()),
+            ("2025-01-15T23:45", "2025-01-16T01:15")
+        );
+        assert!(warnings.is_empty());
+
+        assert!(shift_items(&mut items, "missing", 10, sets_at).is_err());
+    }
+
+    #[test]
+    fn new_items_follow_their_dependencies_and_duration() {
+        let items = vec![item("m42", "2025-01-15T20:00", "2025-01-15T21:10")];
+        let placed = place_item(
+            ScheduleItem {
+                duration_minutes: Some(50),
+                depends_on: vec!["m42".to_string()],
+                ..item("m45", "2025-01-15T20:00", "")
+            },
+            &items,
+        );
+        assert_eq!(placed.start_time, "2025-01-15T21:10");
+        assert_eq!(placed.end_time, "2025-01-15T22:00");
+    }
+}
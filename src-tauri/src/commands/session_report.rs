@@ -0,0 +1,638 @@
+//! Observing session reports for sharing, e.g. with an astronomy club
+//!
+//! A report covers one collection (usually an automatic per-night session
+//! collection): an overview, equipment, conditions from the image headers,
+//! a sky chart of the imaged fields, thumbnails and an acquisition table.
+//! It is written as Markdown next to the images it references, and
+//! optionally rendered to PDF from the same content.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::{Collection, Image, Location};
+use crate::db::repository;
+use crate::python::report;
+use crate::skymap::{self, SkymapFormat};
+use crate::state::AppState;
+
+use super::collections::{summarize_collection, CollectionSummary};
+use super::export::{acquisition_from_metadata, format_number};
+use super::filters::normalize_filter_name;
+use super::gallery_export::{format_duration, load_source_image, write_jpeg};
+use super::scan::{image_session_date, metadata_number_value, parse_longitude};
+
+const THUMB_SIZE: u32 = 400;
+const MARKDOWN_FILE: &str = "report.md";
+const PDF_FILE: &str = "report.pdf";
+const SKY_CHART_FILE: &str = "sky-chart.png";
+
+/// A piece of report content, rendered to Markdown here and to PDF by
+/// `astra_astro.report`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReportBlock {
+    Heading {
+        level: u8,
+        text: String,
+    },
+    Paragraph {
+        text: String,
+    },
+    List {
+        items: Vec<String>,
+    },
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Images {
+        images: Vec<ReportImage>,
+    },
+}
+
+/// An image in the report, relative to the report folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportImage {
+    pub path: String,
+    pub caption: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReportResult {
+    pub markdown_path: String,
+    pub markdown: String,
+    pub pdf_path: Option<String>,
+    pub pdf_pages: Option<usize>,
+    pub images: usize,
+    /// Images without a readable file or thumbnail
+    pub skipped: usize,
+}
+
+/// Escape Markdown syntax in plain text
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Render report blocks as Markdown
+pub fn render_markdown(blocks: &[ReportBlock]) -> String {
+    let mut md = String::new();
+    for block in blocks {
+        match block {
+            ReportBlock::Heading { level, text } => {
+                md.push_str(&format!(
+                    "{} {}\n\n",
+                    "#".repeat((*level).clamp(1, 6) as usize),
+                    escape_markdown(text)
+                ));
+            }
+            ReportBlock::Paragraph { text } => {
+                md.push_str(&format!("{}\n\n", escape_markdown(text)));
+            }
+            ReportBlock::List { items } => {
+                for item in items {
+                    md.push_str(&format!("- {}\n", escape_markdown(item)));
+                }
+                md.push('\n');
+            }
+            ReportBlock::Table { header, rows } => {
+                let row = |cells: &[String]| {
+                    let cells: Vec<String> = cells.iter().map(|c| escape_markdown(c)).collect();
+                    format!("| {} |\n", cells.join(" | "))
+                };
+                md.push_str(&row(header));
+                md.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+                for cells in rows {
+                    md.push_str(&row(cells));
+                }
+                md.push('\n');
+            }
+            ReportBlock::Images { images } => {
+                // Consecutive lines form one paragraph, so thumbnails flow
+                // side by side
+                let lines: Vec<String> = images
+                    .iter()
+                    .map(|image| format!("![{}]({})", escape_markdown(&image.caption), image.path))
+                    .collect();
+                md.push_str(&lines.join("\n"));
+                md.push_str("\n\n");
+            }
+        }
+    }
+    md
+}
+
+/// Acquisition values of one image, preferring the indexed columns
+struct ImageAcquisition {
+    night: Option<String>,
+    target: String,
+    filter: Option<String>,
+    frames: i64,
+    exposure: Option<f64>,
+    gain: Option<i32>,
+    sensor_temp: Option<i32>,
+    ambient_temp: Option<f64>,
+}
+
+fn image_acquisition(image: &Image) -> ImageAcquisition {
+    let acq = acquisition_from_metadata(image.metadata.as_deref().unwrap_or("{}"));
+    ImageAcquisition {
+        night: image_session_date(image, None).map(|d| d.to_string()),
+        target: image
+            .summary
+            .clone()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| image.filename.clone()),
+        filter: image
+            .filter
+            .as_deref()
+            .and_then(normalize_filter_name)
+            .or(acq.filter),
+        frames: acq.frames,
+        exposure: image.exposure.or(acq.duration),
+        gain: image.gain.or(acq.gain),
+        sensor_temp: acq.sensor_cooling,
+        ambient_temp: acq.temperature,
+    }
+}
+
+/// Acquisition table rows: frames with the same night, target, filter,
+/// exposure, gain and sensor temperature are summed into one row
+fn acquisition_rows(acquisitions: &[ImageAcquisition]) -> Vec<Vec<String>> {
+    let mut grouped: BTreeMap<Vec<String>, (i64, f64)> = BTreeMap::new();
+    for acq in acquisitions {
+        let key = vec![
+            acq.night.clone().unwrap_or_default(),
+            acq.target.clone(),
+            acq.filter.clone().unwrap_or_default(),
+            acq.exposure
+                .map(|e| format!("{}s", format_number(e)))
+                .unwrap_or_default(),
+            acq.gain.map(|g| g.to_string()).unwrap_or_default(),
+            acq.sensor_temp
+                .map(|t| format!("{} °C", t))
+                .unwrap_or_default(),
+        ];
+        let entry = grouped.entry(key).or_insert((0, 0.0));
+        entry.0 += acq.frames;
+        entry.1 += acq.exposure.unwrap_or(0.0) * acq.frames as f64;
+    }
+
+    grouped
+        .into_iter()
+        .map(|(key, (frames, integration))| {
+            let [night, target, filter, exposure, gain, sensor]: [String; 6] =
+                key.try_into().expect("acquisition row key has 6 fields");
+            let integration = if integration > 0.0 {
+                format_duration(integration)
+            } else {
+                String::new()
+            };
+            vec![
+                night,
+                target,
+                filter,
+                frames.to_string(),
+                exposure,
+                integration,
+                gain,
+                sensor,
+            ]
+        })
+        .collect()
+}
+
+/// Conditions recorded in the image headers
+/// Distinct capture sites of the images, named when they're saved locations
+fn capture_sites(images: &[Image], sites: &[Location]) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for image in images {
+        let location = image
+            .capture_site_id
+            .as_deref()
+            .and_then(|id| sites.iter().find(|l| l.id == id));
+        let site = match (
+            location,
+            image.capture_latitude.zip(image.capture_longitude),
+        ) {
+            (Some(location), _) => format!(
+                "{} ({:.2}°, {:.2}°)",
+                location.name, location.latitude, location.longitude
+            ),
+            (None, Some((lat, lon))) => format!("{:.2}°, {:.2}°", lat, lon),
+            (None, None) => continue,
+        };
+        if !found.contains(&site) {
+            found.push(site);
+        }
+    }
+    found
+}
+
+fn conditions(
+    images: &[Image],
+    acquisitions: &[ImageAcquisition],
+    sites: &[Location],
+) -> Vec<String> {
+    let mut items = Vec::new();
+
+    let temps: Vec<f64> = acquisitions.iter().filter_map(|a| a.ambient_temp).collect();
+    let min = temps.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = temps.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if !temps.is_empty() {
+        items.push(if min == max {
+            format!("Ambient temperature: {} °C", format_number(min))
+        } else {
+            format!(
+                "Ambient temperature: {} to {} °C",
+                format_number(min),
+                format_number(max)
+            )
+        });
+    }
+
+    let sensor: BTreeSet<i32> = acquisitions.iter().filter_map(|a| a.sensor_temp).collect();
+    if !sensor.is_empty() {
+        let temps: Vec<String> = sensor.iter().map(|t| format!("{} °C", t)).collect();
+        items.push(format!("Sensor temperature: {}", temps.join(", ")));
+    }
+
+    let captured = capture_sites(images, sites);
+    // Images imported before capture sites were recorded still have headers
+    let site = images.iter().find_map(|image| {
+        let meta = image.metadata.as_deref()?;
+        let lat = metadata_number_value(meta, "site_latitude", &["SITELAT"])
+            .and_then(|v| parse_longitude(&v))?;
+        let lon = metadata_number_value(meta, "site_longitude", &["SITELONG"])
+            .and_then(|v| parse_longitude(&v))?;
+        Some((lat, lon))
+    });
+    if !captured.is_empty() {
+        items.push(format!("Site: {}", captured.join("; ")));
+    } else if let Some((lat, lon)) = site {
+        items.push(format!("Site: {:.2}°, {:.2}°", lat, lon));
+    }
+
+    items
+}
+
+fn night_range(summary: &CollectionSummary) -> Option<String> {
+    match (&summary.first_night, &summary.last_night) {
+        (Some(first), Some(last)) if first == last => Some(first.clone()),
+        (Some(first), Some(last)) => {
+            Some(format!("{} to {} ({} nights)", first, last, summary.nights))
+        }
+        _ => None,
+    }
+}
+
+/// Build the report content. `thumbnails` are the images that have one,
+/// with paths relative to the report folder.
+pub fn report_blocks(
+    collection: &Collection,
+    summary: &CollectionSummary,
+    images: &[Image],
+    sites: &[Location],
+    thumbnails: &[(String, String)],
+    sky_chart: Option<&str>,
+) -> Vec<ReportBlock> {
+    let heading = |level: u8, text: &str| ReportBlock::Heading {
+        level,
+        text: text.to_string(),
+    };
+    let mut blocks = vec![heading(1, &collection.name)];
+    if let Some(description) = collection
+        .description
+        .as_deref()
+        .filter(|d| !d.trim().is_empty())
+    {
+        blocks.push(ReportBlock::Paragraph {
+            text: description.to_string(),
+        });
+    }
+
+    let mut overview = Vec::new();
+    if let Some(nights) = night_range(summary) {
+        let label = if summary.nights > 1 {
+            "Nights"
+        } else {
+            "Night"
+        };
+        overview.push(format!("{}: {}", label, nights));
+    }
+    if summary.total_integration_seconds > 0.0 {
+        overview.push(format!(
+            "Total integration: {} over {} frames",
+            format_duration(summary.total_integration_seconds),
+            summary.total_frames
+        ));
+    }
+    if !summary.targets.is_empty() {
+        overview.push(format!("Targets: {}", summary.targets.join(", ")));
+    }
+    overview.push(format!("Images: {}", summary.image_count));
+    blocks.push(heading(2, "Overview"));
+    blocks.push(ReportBlock::List { items: overview });
+
+    let mut equipment = Vec::new();
+    if !summary.telescopes.is_empty() {
+        equipment.push(format!("Telescope: {}", summary.telescopes.join(", ")));
+    }
+    if !summary.cameras.is_empty() {
+        equipment.push(format!("Camera: {}", summary.cameras.join(", ")));
+    }
+    if !summary.filters.is_empty() {
+        let filters: Vec<String> = summary
+            .filters
+            .iter()
+            .map(|f| format!("{} ({})", f.filter, format_duration(f.integration_seconds)))
+            .collect();
+        equipment.push(format!("Filters: {}", filters.join(", ")));
+    }
+    if !equipment.is_empty() {
+        blocks.push(heading(2, "Equipment"));
+        blocks.push(ReportBlock::List { items: equipment });
+    }
+
+    let acquisitions: Vec<ImageAcquisition> = images.iter().map(image_acquisition).collect();
+    let conditions = conditions(images, &acquisitions, sites);
+    if !conditions.is_empty() {
+        blocks.push(heading(2, "Conditions"));
+        blocks.push(ReportBlock::List { items: conditions });
+    }
+
+    if let Some(path) = sky_chart {
+        blocks.push(heading(2, "Sky chart"));
+        blocks.push(ReportBlock::Images {
+            images: vec![ReportImage {
+                path: path.to_string(),
+                caption: "Imaged fields".to_string(),
+            }],
+        });
+    }
+
+    if !thumbnails.is_empty() {
+        let by_id: BTreeMap<&str, &ImageAcquisition> = images
+            .iter()
+            .map(|image| image.id.as_str())
+            .zip(&acquisitions)
+            .collect();
+        let images = thumbnails
+            .iter()
+            .map(|(id, path)| {
+                let caption = by_id
+                    .get(id.as_str())
+                    .map(|acq| match &acq.filter {
+                        Some(filter) => format!("{} ({})", acq.target, filter),
+                        None => acq.target.clone(),
+                    })
+                    .unwrap_or_default();
+                ReportImage {
+                    path: path.clone(),
+                    caption,
+                }
+            })
+            .collect();
+        blocks.push(heading(2, "Images"));
+        blocks.push(ReportBlock::Images { images });
+    }
+
+    let rows = acquisition_rows(&acquisitions);
+    if !rows.is_empty() {
+        let header = [
+            "Night",
+            "Target",
+            "Filter",
+            "Frames",
+            "Exposure",
+            "Integration",
+            "Gain",
+            "Sensor",
+        ];
+        blocks.push(heading(2, "Acquisition"));
+        blocks.push(ReportBlock::Table {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows,
+        });
+    }
+
+    blocks.push(ReportBlock::Paragraph {
+        text: format!(
+            "Generated by Astra on {}",
+            chrono::Local::now().format("%Y-%m-%d")
+        ),
+    });
+    blocks
+}
+
+/// Write the report for `collection` into `out_dir`: `report.md` with its
+/// thumbnails and sky chart, plus `report.pdf` when `pdf` is set
+pub fn write_report(
+    collection: &Collection,
+    images: &[Image],
+    sites: &[Location],
+    previews_dir: &Path,
+    out_dir: &Path,
+    pdf: bool,
+) -> Result<SessionReportResult, String> {
+    fs::create_dir_all(out_dir.join("thumbs"))
+        .map_err(|e| format!("Failed to create report folder: {}", e))?;
+
+    // Oldest first, so the report reads like the night unfolded
+    let mut images = images.to_vec();
+    images.sort_by_key(|image| (image.date_obs.clone(), image.created_at));
+
+    let mut thumbnails = Vec::new();
+    let mut skipped = 0;
+    for image in &images {
+        let Some(source) = load_source_image(image, previews_dir) else {
+            log::warn!("Session report: no displayable file for {}", image.filename);
+            skipped += 1;
+            continue;
+        };
+        let path = format!("thumbs/{}.jpg", image.id);
+        write_jpeg(&source, THUMB_SIZE, &out_dir.join(&path))?;
+        thumbnails.push((image.id.clone(), path));
+    }
+
+    let footprints: Vec<_> = images
+        .iter()
+        .filter_map(super::plate_solve::image_footprint)
+        .collect();
+    // A chart that fails to render leaves the rest of the report intact
+    let sky_chart = if footprints.is_empty() {
+        None
+    } else {
+        match skymap::render_coverage(&footprints, SkymapFormat::Png) {
+            Ok(png) => {
+                fs::write(out_dir.join(SKY_CHART_FILE), png)
+                    .map_err(|e| format!("Failed to write sky chart: {}", e))?;
+                Some(SKY_CHART_FILE)
+            }
+            Err(e) => {
+                log::warn!("Session report: sky chart failed: {}", e);
+                None
+            }
+        }
+    };
+
+    let summary = summarize_collection(collection, &images);
+    let blocks = report_blocks(collection, &summary, &images, sites, &thumbnails, sky_chart);
+    let markdown = render_markdown(&blocks);
+    let markdown_path = out_dir.join(MARKDOWN_FILE);
+    fs::write(&markdown_path, &markdown)
+        .map_err(|e| format!("Failed to write {}: {}", markdown_path.display(), e))?;
+
+    let (pdf_path, pdf_pages) = if pdf {
+        let pdf_path = out_dir.join(PDF_FILE).to_string_lossy().to_string();
+        let blocks = serde_json::to_value(&blocks).map_err(|e| e.to_string())?;
+        let result = report::render_report_pdf(&pdf_path, &blocks, &out_dir.to_string_lossy())?;
+        if !result.success {
+            return Err(result
+                .error
+                .unwrap_or_else(|| "Report PDF failed".to_string()));
+        }
+        (Some(pdf_path), result.pages)
+    } else {
+        (None, None)
+    };
+
+    Ok(SessionReportResult {
+        markdown_path: markdown_path.to_string_lossy().to_string(),
+        markdown,
+        pdf_path,
+        pdf_pages,
+        images: thumbnails.len(),
+        skipped,
+    })
+}
+
+/// Write an observing report for a collection (e.g. a night's session
+/// collection) into the folder at `path`, as Markdown and optionally PDF
+#[tauri::command]
+pub async fn generate_session_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    collection_id: String,
+    path: String,
+    pdf: Option<bool>,
+) -> Result<SessionReportResult, String> {
+    let previews_dir = app
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collection = repository::get_collection_by_id(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Collection not found")?;
+    let images = repository::get_images_in_collection(&mut conn, &collection_id)
+        .map_err(|e| e.to_string())?;
+    let sites = repository::get_locations(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    drop(conn);
+
+    tokio::task::spawn_blocking(move || {
+        write_report(
+            &collection,
+            &images,
+            &sites,
+            &previews_dir,
+            Path::new(&path),
+            pdf.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Session report failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acquisition(night: &str, filter: &str, frames: i64, exposure: f64) -> ImageAcquisition {
+        ImageAcquisition {
+            night: Some(night.to_string()),
+            target: "M42".to_string(),
+            filter: Some(filter.to_string()),
+            frames,
+            exposure: Some(exposure),
+            gain: Some(100),
+            sensor_temp: Some(-10),
+            ambient_temp: None,
+        }
+    }
+
+    #[test]
+    fn markdown_escapes_text_and_lays_out_tables() {
+        let blocks = vec![
+            ReportBlock::Heading {
+                level: 1,
+                text: "M42 *first light*".to_string(),
+            },
+            ReportBlock::Table {
+                header: vec!["Target".to_string(), "Filter".to_string()],
+                rows: vec![vec!["M42 | M43".to_string(), "Ha".to_string()]],
+            },
+            ReportBlock::Images {
+                images: vec![
+                    ReportImage {
+                        path: "thumbs/a.jpg".to_string(),
+                        caption: "M42 [Ha]".to_string(),
+                    },
+                    ReportImage {
+                        path: "thumbs/b.jpg".to_string(),
+                        caption: "M43".to_string(),
+                    },
+                ],
+            },
+        ];
+
+        assert_eq!(
+            render_markdown(&blocks),
+            "# M42 \\*first light\\*\n\n\
+             | Target | Filter |\n| --- | --- |\n| M42 \\| M43 | Ha |\n\n\
+             ![M42 \\[Ha\\]](thumbs/a.jpg)\n![M43](thumbs/b.jpg)\n\n"
+        );
+    }
+
+    #[test]
+    fn acquisition_rows_sum_matching_frames() {
+        let rows = acquisition_rows(&[
+            acquisition("2026-01-10", "Ha", 10, 300.0),
+            acquisition("2026-01-10", "Ha", 2, 300.0),
+            acquisition("2026-01-10", "OIII", 1, 120.0),
+        ]);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][..4], ["2026-01-10", "M42", "Ha", "12"]);
+        assert_eq!(rows[0][4..], ["300s", "1h 0m", "100", "-10 °C"]);
+        assert_eq!(rows[1][2..6], ["OIII", "1", "120s", "2m 0s"]);
+    }
+
+    #[test]
+    fn block_types_serialize_for_the_pdf_renderer() {
+        let block = ReportBlock::Heading {
+            level: 2,
+            text: "Overview".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({"type": "heading", "level": 2, "text": "Overview"})
+        );
+    }
+}
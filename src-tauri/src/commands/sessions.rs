@@ -0,0 +1,362 @@
+//! Repair of the per-night session collections created by scan and auto-import
+//!
+//! Older imports bucketed frames by the naive DATE-OBS timestamp, which is
+//! UTC, so a single night could be split across two collections. This
+//! recomputes each image's night in the observer's local time and moves it
+//! to the right session collection.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{Collection, NewCollection, NewCollectionImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::scan::image_session_date;
+
+/// An image that belongs to a different night than its session collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionMove {
+    pub image_id: String,
+    pub filename: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRepairResult {
+    pub dry_run: bool,
+    pub images_moved: usize,
+    pub collections_created: usize,
+    /// Session collections left empty by the move and deleted
+    pub collections_removed: usize,
+    pub moves: Vec<SessionMove>,
+}
+
+/// The night of an automatic session collection: auto-imported, with its
+/// session date (from the metadata, or the name of older imports) still in
+/// the name. Collections the user created or renamed are never touched.
+fn session_collection_date(collection: &Collection) -> Option<NaiveDate> {
+    let meta: serde_json::Value = serde_json::from_str(collection.metadata.as_deref()?).ok()?;
+    if !meta
+        .get("auto_imported")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    let date = meta
+        .get("session_date")
+        .and_then(|d| d.as_str())
+        .unwrap_or(&collection.name);
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    collection.name.contains(&date.to_string()).then_some(date)
+}
+
+/// Name of the session collection for another night: the same name with the
+/// date swapped, so the naming template carries over
+fn session_name_for(collection: &Collection, date: NaiveDate, night: NaiveDate) -> String {
+    collection
+        .name
+        .replacen(&date.to_string(), &night.to_string(), 1)
+}
+
+/// Settings for a new session collection, copied from the one the image left
+pub(crate) fn new_session_collection(
+    source: &Collection,
+    date: NaiveDate,
+    name: String,
+) -> NewCollection {
+    let mut metadata: serde_json::Value = source
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str(m).ok())
+        .unwrap_or_else(|| serde_json::json!({ "auto_imported": true }));
+    if let Some(obj) = metadata.as_object_mut() {
+        obj.insert("session_date".to_string(), date.to_string().into());
+    }
+
+    NewCollection {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: source.user_id.clone(),
+        name,
+        description: Some(format!("Observing session {}", date)),
+        visibility: source.visibility.clone(),
+        template: source.template.clone(),
+        favorite: false,
+        tags: source.tags.clone(),
+        metadata: Some(metadata.to_string()),
+        archived: false,
+    }
+}
+
+/// Move images to the session collection for their local observing night.
+///
+/// `longitude` is the observer's site, used for frames without a SITELONG
+/// header; without either the system timezone is assumed.
+pub fn rebucket_sessions(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    longitude: Option<f64>,
+    dry_run: bool,
+) -> QueryResult<SessionRepairResult> {
+    let mut result = SessionRepairResult {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut sessions: Vec<(NaiveDate, Collection)> =
+        repository::get_all_collections(conn, user_id)?
+            .into_iter()
+            .filter_map(|c| session_collection_date(&c).map(|d| (d, c)))
+            .collect();
+    sessions.sort_by_key(|(date, _)| *date);
+
+    // Target collection id by name (None while only planned in a dry run)
+    let mut by_name: HashMap<String, Option<String>> = HashMap::new();
+    let mut remaining: HashMap<String, i64> = HashMap::new();
+    for (_, collection) in &sessions {
+        by_name
+            .entry(collection.name.clone())
+            .or_insert_with(|| Some(collection.id.clone()));
+        remaining.insert(
+            collection.id.clone(),
+            repository::get_collection_image_count(conn, &collection.id)?,
+        );
+    }
+
+    for (date, collection) in &sessions {
+        for image in repository::get_images_in_collection(conn, &collection.id)? {
+            let Some(night) = image_session_date(&image, longitude) else {
+                continue;
+            };
+            if night == *date {
+                continue;
+            }
+
+            let name = session_name_for(collection, *date, night);
+            let target = match by_name.get(&name) {
+                Some(target) => target.clone(),
+                None => {
+                    result.collections_created += 1;
+                    let created = if dry_run {
+                        None
+                    } else {
+                        let new = new_session_collection(collection, night, name.clone());
+                        Some(repository::get_or_create_auto_collection(conn, &new)?.0.id)
+                    };
+                    by_name.insert(name, created.clone());
+                    created
+                }
+            };
+
+            if let Some(target_id) = &target {
+                if !dry_run {
+                    repository::remove_image_from_collection(conn, &collection.id, &image.id)?;
+                    if !repository::is_image_in_collection(conn, target_id, &image.id)? {
+                        repository::add_image_to_collection(
+                            conn,
+                            &NewCollectionImage {
+                                id: uuid::Uuid::new_v4().to_string(),
+                                collection_id: target_id.clone(),
+                                image_id: image.id.clone(),
+                            },
+                        )?;
+                    }
+                }
+                if let Some(count) = remaining.get_mut(target_id) {
+                    *count += 1;
+                }
+            }
+            if let Some(count) = remaining.get_mut(&collection.id) {
+                *count -= 1;
+            }
+
+            result.images_moved += 1;
+            result.moves.push(SessionMove {
+                image_id: image.id,
+                filename: image.filename,
+                from: date.to_string(),
+                to: night.to_string(),
+            });
+        }
+    }
+
+    let moved_from: std::collections::HashSet<&str> =
+        result.moves.iter().map(|m| m.from.as_str()).collect();
+    for (date, collection) in &sessions {
+        if remaining.get(&collection.id) == Some(&0)
+            && moved_from.contains(date.to_string().as_str())
+        {
+            if !dry_run {
+                repository::delete_collection(conn, &collection.id)?;
+            }
+            result.collections_removed += 1;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Re-bucket images in automatic session collections by local observing night.
+/// With `dryRun` the moves are reported but nothing is changed.
+#[tauri::command]
+pub fn repair_session_collections(
+    state: State<'_, AppState>,
+    longitude: Option<f64>,
+    dry_run: Option<bool>,
+) -> Result<SessionRepairResult, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    let result = if dry_run {
+        rebucket_sessions(&mut conn, &state.user_id, longitude, true)
+    } else {
+        conn.transaction(|conn| rebucket_sessions(conn, &state.user_id, longitude, false))
+    }
+    .map_err(|e| format!("Failed to repair session collections: {}", e))?;
+
+    log::info!(
+        "Session repair{}: {} images moved, {} collections created, {} removed",
+        if dry_run { " (dry run)" } else { "" },
+        result.images_moved,
+        result.collections_created,
+        result.collections_removed
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewImage;
+
+    const USER: &str = "local-user";
+
+    fn add_session(conn: &mut SqliteConnection, name: &str, images: &[(&str, &str)]) {
+        let collection = repository::create_collection(
+            conn,
+            &NewCollection {
+                user_id: USER.to_string(),
+                name: name.to_string(),
+                template: Some("astrolog".to_string()),
+                metadata: Some(r#"{"auto_imported":true}"#.to_string()),
+                ..NewCollection::test(&format!("c-{}", name))
+            },
+        )
+        .unwrap();
+        for (id, date_obs) in images {
+            repository::create_image(
+                conn,
+                &NewImage {
+                    user_id: USER.to_string(),
+                    metadata: Some(format!(
+                        r#"{{"date_obs":"{}","raw_headers":{{"SITELONG":"Some(RealFloatingNumber(-120.0))"}}}}"#,
+                        date_obs
+                    )),
+                    date_obs: Some(date_obs.to_string()),
+                    ..NewImage::test(id)
+                },
+            )
+            .unwrap();
+            repository::add_image_to_collection(
+                conn,
+                &NewCollectionImage {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    collection_id: collection.id.clone(),
+                    image_id: id.to_string(),
+                },
+            )
+            .unwrap();
+        }
+    }
+
+    fn session_names(conn: &mut SqliteConnection) -> Vec<String> {
+        let mut names: Vec<String> = repository::get_collections(conn, USER)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn utc_split_night_is_merged() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        // One evening at 120°W, bucketed by UTC date into two collections
+        add_session(&mut conn, "2026-01-15", &[("a", "2026-01-15T23:30:00")]);
+        add_session(&mut conn, "2026-01-16", &[("b", "2026-01-16T04:30:00")]);
+
+        let preview = rebucket_sessions(&mut conn, USER, None, true).unwrap();
+        assert_eq!(preview.images_moved, 1);
+        assert_eq!(preview.collections_removed, 1);
+        assert_eq!(session_names(&mut conn), vec!["2026-01-15", "2026-01-16"]);
+
+        let result = rebucket_sessions(&mut conn, USER, None, false).unwrap();
+        assert_eq!(result.moves[0].to, "2026-01-15");
+        assert_eq!(result.collections_created, 0);
+        assert_eq!(session_names(&mut conn), vec!["2026-01-15"]);
+        assert_eq!(
+            repository::get_collection_image_count(&mut conn, "c-2026-01-15").unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn templated_session_names_carry_over() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        add_session(
+            &mut conn,
+            "M 42 – 2026-01-15",
+            &[("a", "2026-01-15T23:30:00")],
+        );
+        add_session(
+            &mut conn,
+            "M 42 – 2026-01-16",
+            &[("b", "2026-01-16T04:30:00")],
+        );
+        add_session(
+            &mut conn,
+            "NGC 7000 – 2026-01-16",
+            &[("c", "2026-01-16T05:00:00")],
+        );
+        diesel::sql_query(
+            r#"UPDATE collections SET metadata =
+                '{"auto_imported":true,"session_date":"' || substr(name, -10) || '"}'"#,
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        let result = rebucket_sessions(&mut conn, USER, None, false).unwrap();
+        assert_eq!(result.images_moved, 2);
+        assert_eq!(result.collections_created, 1);
+        assert_eq!(
+            session_names(&mut conn),
+            vec!["M 42 – 2026-01-15", "NGC 7000 – 2026-01-15"]
+        );
+    }
+
+    #[test]
+    fn user_collections_are_left_alone() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        add_session(&mut conn, "2026-01-16", &[("b", "2026-01-16T04:30:00")]);
+        diesel::sql_query("UPDATE collections SET metadata = NULL")
+            .execute(&mut conn)
+            .unwrap();
+
+        let result = rebucket_sessions(&mut conn, USER, None, false).unwrap();
+        assert_eq!(result.images_moved, 0);
+        assert_eq!(session_names(&mut conn), vec!["2026-01-16"]);
+    }
+}
@@ -0,0 +1,182 @@
+//! JSON settings files in the app data directory
+//!
+//! Every settings file is listed here and read and written through the same
+//! helpers, so a missing or unreadable file always falls back to the defaults
+//! and backups know which files to include.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// A settings file in the app data directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettingsFile {
+    pub name: &'static str,
+    /// Included in backup bundles. Credentials and machine-specific setup
+    /// (interpreter paths, the API server token) are left out.
+    pub backup: bool,
+}
+
+pub const SHARE_CONFIG: SettingsFile = SettingsFile {
+    name: "share-config.json",
+    backup: true,
+};
+pub const PROCESSING: SettingsFile = SettingsFile {
+    name: "processing-settings.json",
+    backup: true,
+};
+pub const FRAME_RULES: SettingsFile = SettingsFile {
+    name: "frame-rules.json",
+    backup: true,
+};
+pub const TRIAGE_RULES: SettingsFile = SettingsFile {
+    name: "triage-rules.json",
+    backup: true,
+};
+pub const TAG_RULES: SettingsFile = SettingsFile {
+    name: "tag-rules.json",
+    backup: true,
+};
+pub const EXTERNAL_EDITORS: SettingsFile = SettingsFile {
+    name: "external-editors.json",
+    backup: true,
+};
+pub const COLLECTION_NAMING: SettingsFile = SettingsFile {
+    name: "collection-naming.json",
+    backup: true,
+};
+pub const NETWORK: SettingsFile = SettingsFile {
+    name: "network-settings.json",
+    backup: true,
+};
+pub const PREVIEW_CACHE: SettingsFile = SettingsFile {
+    name: "preview-cache.json",
+    backup: true,
+};
+pub const IMPORT_MEMORY: SettingsFile = SettingsFile {
+    name: "import-memory.json",
+    backup: true,
+};
+pub const SIDECARS: SettingsFile = SettingsFile {
+    name: "sidecar-settings.json",
+    backup: true,
+};
+pub const NIGHT_DIGEST: SettingsFile = SettingsFile {
+    name: "night-digest.json",
+    backup: true,
+};
+pub const API_SERVER: SettingsFile = SettingsFile {
+    name: "api-server.json",
+    backup: false,
+};
+pub const PYTHON_ENV: SettingsFile = SettingsFile {
+    name: "python-env.json",
+    backup: false,
+};
+
+/// Every settings file the app writes
+pub const SETTINGS_FILES: &[SettingsFile] = &[
+    SHARE_CONFIG,
+    PROCESSING,
+    FRAME_RULES,
+    TRIAGE_RULES,
+    TAG_RULES,
+    EXTERNAL_EDITORS,
+    COLLECTION_NAMING,
+    NETWORK,
+    PREVIEW_CACHE,
+    IMPORT_MEMORY,
+    SIDECARS,
+    NIGHT_DIGEST,
+    API_SERVER,
+    PYTHON_ENV,
+];
+
+/// Names of the settings files included in backup bundles
+pub fn backup_files() -> impl Iterator<Item = &'static str> {
+    SETTINGS_FILES
+        .iter()
+        .filter(|file| file.backup)
+        .map(|file| file.name)
+}
+
+pub(crate) fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Settings saved in `data_dir`, or the defaults if the file is missing or
+/// can't be parsed
+pub(crate) fn load_settings_in<T: DeserializeOwned + Default>(
+    data_dir: &Path,
+    file: &SettingsFile,
+) -> T {
+    fs::read_to_string(data_dir.join(file.name))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Saved settings; errors only if the app data dir is unavailable
+pub(crate) fn load_settings<T: DeserializeOwned + Default>(
+    app: &AppHandle,
+    file: &SettingsFile,
+) -> Result<T, String> {
+    Ok(load_settings_in(&app_data_dir(app)?, file))
+}
+
+pub(crate) fn save_settings_in<T: Serialize>(
+    data_dir: &Path,
+    file: &SettingsFile,
+    settings: &T,
+) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize {}: {}", file.name, e))?;
+    fs::write(data_dir.join(file.name), data)
+        .map_err(|e| format!("Failed to save {}: {}", file.name, e))
+}
+
+pub(crate) fn save_settings<T: Serialize>(
+    app: &AppHandle,
+    file: &SettingsFile,
+    settings: &T,
+) -> Result<(), String> {
+    save_settings_in(&app_data_dir(app)?, file, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+    struct Example {
+        value: u32,
+    }
+
+    #[test]
+    fn missing_or_corrupt_files_load_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded: Example = load_settings_in(dir.path(), &FRAME_RULES);
+        assert_eq!(loaded, Example::default());
+
+        save_settings_in(dir.path(), &FRAME_RULES, &Example { value: 7 }).unwrap();
+        let loaded: Example = load_settings_in(dir.path(), &FRAME_RULES);
+        assert_eq!(loaded, Example { value: 7 });
+
+        fs::write(dir.path().join(FRAME_RULES.name), "not json").unwrap();
+        let loaded: Example = load_settings_in(dir.path(), &FRAME_RULES);
+        assert_eq!(loaded, Example::default());
+    }
+
+    #[test]
+    fn credentials_and_machine_setup_are_not_backed_up() {
+        let files: Vec<_> = backup_files().collect();
+        assert!(files.contains(&"night-digest.json"));
+        assert!(!files.contains(&API_SERVER.name));
+        assert!(!files.contains(&PYTHON_ENV.name));
+    }
+}
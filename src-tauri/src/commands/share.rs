@@ -661,7 +661,7 @@ fn generate_thumbnail(image_data: &[u8]) -> Result<Vec<u8>, String> {
     Ok(buf.into_inner())
 }
 
-fn mime_for_path(path: &std::path::Path) -> &'static str {
+pub(super) fn mime_for_path(path: &std::path::Path) -> &'static str {
     let ext = path
         .extension()
         .and_then(|e| e.to_str())
@@ -673,6 +673,8 @@ fn mime_for_path(path: &std::path::Path) -> &'static str {
         "gif" => "image/gif",
         "webp" => "image/webp",
         "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" | "md" | "log" => "text/plain",
         _ => "application/octet-stream",
     }
 }
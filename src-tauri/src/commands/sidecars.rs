@@ -0,0 +1,544 @@
+//! XMP and JSON sidecars next to original files
+//!
+//! Sidecars carry an image's title, description, tags, rating and solved
+//! coordinates so photo managers such as Lightroom and digiKam see the same
+//! metadata as Astra. Writing an XMP sidecar that already exists only
+//! replaces the properties Astra manages, keeping everything else (develop
+//! settings, other tools' metadata) intact. Sidecars found next to files at
+//! import are read back in.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::solvers::wcs::Wcs;
+use crate::state::AppState;
+
+use super::settings::{load_settings, save_settings, SIDECARS};
+
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_XMP: &str = "http://ns.adobe.com/xap/1.0/";
+const NS_AVM: &str = "http://www.communicatingastronomy.org/avm/1.0/";
+
+/// An XMP packet with nothing in it, filled in when no sidecar exists yet
+const EMPTY_XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="Astra">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarFormat {
+    Xmp,
+    Json,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SidecarSettings {
+    /// Keep an XMP sidecar next to each original up to date as images are
+    /// edited
+    pub write_xmp: bool,
+    /// Same, for the JSON variant
+    pub write_json: bool,
+}
+
+impl SidecarSettings {
+    fn formats(&self) -> Vec<SidecarFormat> {
+        let mut formats = Vec::new();
+        if self.write_xmp {
+            formats.push(SidecarFormat::Xmp);
+        }
+        if self.write_json {
+            formats.push(SidecarFormat::Json);
+        }
+        formats
+    }
+}
+
+/// The metadata a sidecar carries. This is also the JSON variant's layout;
+/// `summary` is accepted for the title so collection file exports read back.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidecarData {
+    #[serde(alias = "summary")]
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// 1-5 stars
+    pub rating: Option<i32>,
+    /// Solved center in degrees (ICRS)
+    pub ra: Option<f64>,
+    pub dec: Option<f64>,
+}
+
+impl SidecarData {
+    pub fn from_image(image: &Image) -> Self {
+        let wcs = image
+            .wcs
+            .as_deref()
+            .and_then(|wcs| serde_json::from_str::<Wcs>(wcs).ok());
+        Self {
+            title: image.summary.clone(),
+            description: image.description.clone(),
+            tags: image
+                .tags
+                .as_deref()
+                .unwrap_or("")
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            rating: image.rating,
+            ra: wcs.as_ref().map(|w| w.crval[0]),
+            dec: wcs.as_ref().map(|w| w.crval[1]),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Keep only values Astra can store, so a bad sidecar can't bring in a
+    /// rejected (-1) rating or coordinates off the sky
+    fn sanitized(mut self) -> Self {
+        let blank = |value: &Option<String>| value.as_deref().is_some_and(|v| v.trim().is_empty());
+        if blank(&self.title) {
+            self.title = None;
+        }
+        if blank(&self.description) {
+            self.description = None;
+        }
+        self.tags.retain(|t| !t.trim().is_empty());
+        self.rating = self.rating.filter(|r| (1..=5).contains(r));
+        let on_sky = matches!(
+            (self.ra, self.dec),
+            (Some(ra), Some(dec)) if (0.0..360.0).contains(&ra) && (-90.0..=90.0).contains(&dec)
+        );
+        if !on_sky {
+            self.ra = None;
+            self.dec = None;
+        }
+        self
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn regex(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("valid sidecar pattern")
+}
+
+/// Text of each `rdf:li` in `xml`
+fn list_items(xml: &str) -> Vec<String> {
+    regex(r"(?s)<rdf:li(?:\s[^>]*)?>(.*?)</rdf:li>")
+        .captures_iter(xml)
+        .map(|c| unescape_xml(c[1].trim()))
+        .collect()
+}
+
+/// Contents of the `<name>` element, if there is one
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let name = regex::escape(name);
+    regex(&format!(r"(?s)<{}(?:\s[^>]*)?>(.*?)</{}>", name, name))
+        .captures(xml)
+        .map(|c| c.get(1).unwrap().as_str())
+}
+
+/// Read the properties Astra manages from an XMP packet
+pub fn parse_xmp(xml: &str) -> SidecarData {
+    let first = |name: &str| element(xml, name).and_then(|e| list_items(e).into_iter().next());
+    let rating = element(xml, "xmp:Rating")
+        .map(str::to_string)
+        .or_else(|| {
+            regex(r#"xmp:Rating\s*=\s*["']([^"']*)["']"#)
+                .captures(xml)
+                .map(|c| c[1].to_string())
+        })
+        .and_then(|r| r.trim().parse::<f64>().ok())
+        .map(|r| r.round() as i32);
+    let coordinates: Vec<f64> = element(xml, "avm:Spatial.ReferenceValue")
+        .map(list_items)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+
+    SidecarData {
+        title: first("dc:title"),
+        description: first("dc:description"),
+        tags: element(xml, "dc:subject")
+            .map(list_items)
+            .unwrap_or_default(),
+        rating,
+        ra: coordinates.first().copied(),
+        dec: coordinates.get(1).copied(),
+    }
+    .sanitized()
+}
+
+/// `xml` with Astra's properties set to `data`. Anything else in the packet
+/// is left as it was; a packet with no `rdf:Description` gets a new one.
+pub fn merge_xmp(xml: &str, data: &SidecarData) -> String {
+    let mut xml = xml.to_string();
+    for name in [
+        "dc:title",
+        "dc:description",
+        "dc:subject",
+        "xmp:Rating",
+        "avm:Spatial.ReferenceValue",
+    ] {
+        let name = regex::escape(name);
+        xml = regex(&format!(r"(?s)\s*<{}(?:\s[^>]*)?>.*?</{}>", name, name))
+            .replace_all(&xml, "")
+            .into_owned();
+    }
+    xml = regex(r#"\s+xmp:Rating\s*=\s*["'][^"']*["']"#)
+        .replace_all(&xml, "")
+        .into_owned();
+
+    let alt = |text: &str| {
+        format!(
+            "<rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>",
+            escape_xml(text)
+        )
+    };
+    let mut properties = String::new();
+    if let Some(title) = &data.title {
+        properties.push_str(&format!(
+            "\n   <dc:title xmlns:dc=\"{}\">{}</dc:title>",
+            NS_DC,
+            alt(title)
+        ));
+    }
+    if let Some(description) = &data.description {
+        properties.push_str(&format!(
+            "\n   <dc:description xmlns:dc=\"{}\">{}</dc:description>",
+            NS_DC,
+            alt(description)
+        ));
+    }
+    if !data.tags.is_empty() {
+        let items: String = data
+            .tags
+            .iter()
+            .map(|tag| format!("<rdf:li>{}</rdf:li>", escape_xml(tag)))
+            .collect();
+        properties.push_str(&format!(
+            "\n   <dc:subject xmlns:dc=\"{}\"><rdf:Bag>{}</rdf:Bag></dc:subject>",
+            NS_DC, items
+        ));
+    }
+    if let Some(rating) = data.rating {
+        properties.push_str(&format!(
+            "\n   <xmp:Rating xmlns:xmp=\"{}\">{}</xmp:Rating>",
+            NS_XMP, rating
+        ));
+    }
+    if let (Some(ra), Some(dec)) = (data.ra, data.dec) {
+        properties.push_str(&format!(
+            "\n   <avm:Spatial.ReferenceValue xmlns:avm=\"{}\"><rdf:Seq><rdf:li>{}</rdf:li><rdf:li>{}</rdf:li></rdf:Seq></avm:Spatial.ReferenceValue>",
+            NS_AVM, ra, dec
+        ));
+    }
+
+    let description = regex(r"(?s)<rdf:Description\b[^>]*?(/?)>");
+    if let Some(found) = description.captures(&xml) {
+        let tag = found.get(0).unwrap();
+        let self_closing = !found[1].is_empty();
+        let (start, end) = (tag.start(), tag.end());
+        if self_closing {
+            let open = xml[start..end - 2].trim_end().to_string();
+            xml.replace_range(
+                start..end,
+                &format!("{}>{}\n  </rdf:Description>", open, properties),
+            );
+        } else {
+            xml.insert_str(end, &properties);
+        }
+        xml
+    } else if let Some(rdf_end) = xml.find("</rdf:RDF>") {
+        xml.insert_str(
+            rdf_end,
+            &format!(
+                "  <rdf:Description rdf:about=\"\">{}\n  </rdf:Description>\n ",
+                properties
+            ),
+        );
+        xml
+    } else {
+        merge_xmp(EMPTY_XMP, data)
+    }
+}
+
+/// Sidecar paths for an original, in the order they're looked for:
+/// `name.xmp` (Lightroom), `name.ext.xmp` (digiKam), then `name.json`
+fn sidecar_paths(original: &Path) -> [PathBuf; 3] {
+    let mut with_xmp = original.as_os_str().to_owned();
+    with_xmp.push(".xmp");
+    [
+        original.with_extension("xmp"),
+        PathBuf::from(with_xmp),
+        original.with_extension("json"),
+    ]
+}
+
+/// Read the first sidecar found next to any of `originals`
+pub fn read_sidecar(originals: &[&Path]) -> Option<SidecarData> {
+    for original in originals {
+        for path in sidecar_paths(original) {
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let data = if path.extension().is_some_and(|e| e == "json") {
+                match serde_json::from_str::<SidecarData>(&contents) {
+                    Ok(data) => data.sanitized(),
+                    Err(e) => {
+                        log::warn!("Ignoring sidecar {}: {}", path.display(), e);
+                        continue;
+                    }
+                }
+            } else {
+                parse_xmp(&contents)
+            };
+            if !data.is_empty() {
+                return Some(data);
+            }
+        }
+    }
+    None
+}
+
+/// Local files an image was imported from, one per distinct name
+fn originals(image: &Image) -> Vec<PathBuf> {
+    let mut originals: Vec<PathBuf> = Vec::new();
+    for url in [image.url.as_deref(), image.fits_url.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            continue;
+        }
+        let path = PathBuf::from(url);
+        if !originals
+            .iter()
+            .any(|o| o.with_extension("") == path.with_extension(""))
+        {
+            originals.push(path);
+        }
+    }
+    originals
+}
+
+/// Write `image`'s sidecars in `formats` next to its originals. Returns the
+/// files written.
+pub fn write_sidecars(image: &Image, formats: &[SidecarFormat]) -> Result<Vec<PathBuf>, String> {
+    let data = SidecarData::from_image(image);
+    let mut written = Vec::new();
+    for original in originals(image) {
+        for format in formats {
+            let path = match format {
+                SidecarFormat::Xmp => original.with_extension("xmp"),
+                SidecarFormat::Json => original.with_extension("json"),
+            };
+            let contents = match format {
+                SidecarFormat::Xmp => {
+                    let existing = fs::read_to_string(&path).ok();
+                    merge_xmp(existing.as_deref().unwrap_or(EMPTY_XMP), &data)
+                }
+                SidecarFormat::Json => {
+                    serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?
+                }
+            };
+            fs::write(&path, contents)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+/// Rewrite an edited image's sidecars if that's turned on. Failures are
+/// logged rather than failing the edit.
+pub(crate) fn update_sidecars(app: &AppHandle, image: &Image) {
+    let Ok(settings) = load_settings::<SidecarSettings>(app, &SIDECARS) else {
+        return;
+    };
+    let formats = settings.formats();
+    if formats.is_empty() {
+        return;
+    }
+    if let Err(e) = write_sidecars(image, &formats) {
+        log::warn!("Failed to update sidecars for {}: {}", image.filename, e);
+    }
+}
+
+#[tauri::command]
+pub fn get_sidecar_settings(app: AppHandle) -> Result<SidecarSettings, String> {
+    load_settings(&app, &SIDECARS)
+}
+
+/// Save the sidecar settings; None restores the defaults (no sidecars)
+#[tauri::command]
+pub fn set_sidecar_settings(
+    app: AppHandle,
+    settings: Option<SidecarSettings>,
+) -> Result<SidecarSettings, String> {
+    let settings = settings.unwrap_or_default();
+    save_settings(&app, &SIDECARS, &settings)?;
+    Ok(settings)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarWriteResult {
+    pub files_written: usize,
+    pub errors: Vec<String>,
+}
+
+/// Write sidecars for the given images now, in `formats` or else the ones
+/// turned on in the settings
+#[tauri::command]
+pub fn write_image_sidecars(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    image_ids: Vec<String>,
+    formats: Option<Vec<SidecarFormat>>,
+) -> Result<SidecarWriteResult, String> {
+    let formats = match formats {
+        Some(formats) => formats,
+        None => load_settings::<SidecarSettings>(&app, &SIDECARS)?.formats(),
+    };
+    if formats.is_empty() {
+        return Err("No sidecar format selected".to_string());
+    }
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut result = SidecarWriteResult {
+        files_written: 0,
+        errors: Vec::new(),
+    };
+    for id in &image_ids {
+        let image = match repository::get_image_by_id(&mut conn, id).map_err(|e| e.to_string())? {
+            Some(image) => image,
+            None => {
+                result.errors.push(format!("Image not found: {}", id));
+                continue;
+            }
+        };
+        match write_sidecars(&image, &formats) {
+            Ok(written) => result.files_written += written.len(),
+            Err(e) => result.errors.push(e),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn data() -> SidecarData {
+        SidecarData {
+            title: Some("M 31 & friends".to_string()),
+            description: Some("Andromeda <with> M 110".to_string()),
+            tags: vec!["galaxy".to_string(), "Seestar".to_string()],
+            rating: Some(4),
+            ra: Some(10.6847),
+            dec: Some(41.2687),
+        }
+    }
+
+    #[test]
+    fn xmp_round_trips_and_keeps_other_properties() {
+        let written = merge_xmp(EMPTY_XMP, &data());
+        assert_eq!(parse_xmp(&written), data());
+
+        // Lightroom writes the rating as an attribute on a self-closing
+        // description alongside its own settings
+        let lightroom = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+    xmp:Rating="2"
+    crs:Exposure2012="+0.35"/>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        assert_eq!(parse_xmp(lightroom).rating, Some(2));
+
+        let merged = merge_xmp(lightroom, &data());
+        assert!(merged.contains(r#"crs:Exposure2012="+0.35""#));
+        assert!(!merged.contains(r#"xmp:Rating="2""#));
+        assert_eq!(parse_xmp(&merged), data());
+
+        // Properties cleared in Astra are removed from the sidecar
+        let cleared = merge_xmp(
+            &merged,
+            &SidecarData {
+                rating: None,
+                tags: Vec::new(),
+                ..data()
+            },
+        );
+        let parsed = parse_xmp(&cleared);
+        assert_eq!(parsed.rating, None);
+        assert!(parsed.tags.is_empty());
+        assert_eq!(parsed.title, data().title);
+        assert!(cleared.contains("crs:Exposure2012"));
+    }
+
+    #[test]
+    fn sidecars_are_found_next_to_originals() {
+        let dir = TempDir::new().unwrap();
+        let fits = dir.path().join("Light_M 31.fit");
+        let jpeg = dir.path().join("Light_M 31.jpg");
+        assert!(read_sidecar(&[&fits, &jpeg]).is_none());
+
+        // digiKam names its sidecar after the whole file name
+        let digikam = dir.path().join("Light_M 31.jpg.xmp");
+        fs::write(&digikam, merge_xmp(EMPTY_XMP, &data())).unwrap();
+        assert_eq!(read_sidecar(&[&fits, &jpeg]), Some(data()));
+        fs::remove_file(&digikam).unwrap();
+
+        // Collection file exports write `summary` and drop rejected ratings
+        fs::write(
+            dir.path().join("Light_M 31.json"),
+            r#"{"summary": "M 31", "tags": ["galaxy", " "], "rating": -1, "gain": 80}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_sidecar(&[&fits]),
+            Some(SidecarData {
+                title: Some("M 31".to_string()),
+                tags: vec!["galaxy".to_string()],
+                ..Default::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Siril script export for a selection of images
+//!
+//! Copies the Light, Dark, Flat and Bias frames behind the selected images
+//! into the folder layout Siril's preprocessing scripts use (`lights/`,
+//! `darks/`, `flats/`, `biases/`) and writes a .ssf script that converts,
+//! calibrates, registers and stacks them. Stacked images contribute their
+//! Light frames; raw frames are sorted by the frame rules.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use walkdir::WalkDir;
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::frame_rules::{frame_classifier, FrameClassifier, FrameType};
+use super::scan::{get_sub_directory, parse_fits_metadata};
+use super::subframes::image_path;
+
+const SCRIPT_FILE: &str = "astra_preprocess.ssf";
+/// Oldest Siril with the commands and options the script uses
+const SIRIL_VERSION: &str = "1.2.0";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SirilScriptExport {
+    pub script_path: String,
+    pub lights: usize,
+    pub darks: usize,
+    pub flats: usize,
+    pub biases: usize,
+    /// Frames copied into the target directory (ones already there are reused)
+    pub files_copied: usize,
+    /// Selected images that couldn't be used, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Frame files for each of Siril's input folders
+#[derive(Debug, Default)]
+struct FrameSets {
+    lights: Vec<PathBuf>,
+    darks: Vec<PathBuf>,
+    flats: Vec<PathBuf>,
+    biases: Vec<PathBuf>,
+}
+
+impl FrameSets {
+    /// Add a frame to its folder; false if the type isn't a raw frame
+    fn add(&mut self, frame_type: FrameType, path: PathBuf) -> bool {
+        let set = match frame_type {
+            FrameType::Light => &mut self.lights,
+            FrameType::Dark => &mut self.darks,
+            FrameType::Flat => &mut self.flats,
+            FrameType::Bias => &mut self.biases,
+            FrameType::Stacked | FrameType::Unknown => return false,
+        };
+        if !set.contains(&path) {
+            set.push(path);
+        }
+        true
+    }
+
+    fn folders(&self) -> [(&'static str, &[PathBuf]); 4] {
+        [
+            ("lights", &self.lights),
+            ("darks", &self.darks),
+            ("flats", &self.flats),
+            ("biases", &self.biases),
+        ]
+    }
+}
+
+fn is_fits(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_lowercase().as_str(), "fit" | "fits"))
+        .unwrap_or(false)
+}
+
+/// FITS file of an image, if it's on disk
+pub(super) fn fits_file(image: &Image) -> Option<PathBuf> {
+    image_path(image)
+        .filter(|path| is_fits(path) && path.is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Raw FITS headers saved with an image: scans store them under
+/// "raw_headers", auto-import stores the header map itself
+pub(super) fn stored_headers(metadata: Option<&str>) -> HashMap<String, String> {
+    let Some(value) = metadata.and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+    else {
+        return HashMap::new();
+    };
+    let headers = value.get("raw_headers").unwrap_or(&value);
+    headers
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Light frames in a stack's sub folder, for stacks whose subs weren't imported
+fn lights_on_disk(stacked_path: &Path, classifier: &FrameClassifier) -> Vec<PathBuf> {
+    let Some(sub_dir) = get_sub_directory(stacked_path).filter(|dir| dir.is_dir()) else {
+        return Vec::new();
+    };
+    let mut lights: Vec<PathBuf> = WalkDir::new(sub_dir)
+        .max_depth(2)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|path| path.is_file() && is_fits(path))
+        .filter(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            classifier.classify_name(&stem) == Some(FrameType::Light)
+        })
+        .collect();
+    lights.sort();
+    lights
+}
+
+/// Siril script stacking the frame folders that have files. Calibration
+/// steps are only written for the masters that can be built.
+fn build_siril_script(frames: &FrameSets, debayer: bool) -> String {
+    let has_darks = !frames.darks.is_empty();
+    let has_flats = !frames.flats.is_empty();
+    let has_biases = !frames.biases.is_empty();
+
+    let mut lines = vec![
+        "############################################".to_string(),
+        "# Preprocessing script exported by Astra".to_string(),
+        format!(
+            "# {} lights, {} darks, {} flats, {} biases",
+            frames.lights.len(),
+            frames.darks.len(),
+            frames.flats.len(),
+            frames.biases.len()
+        ),
+        "############################################".to_string(),
+        String::new(),
+        format!("requires {}", SIRIL_VERSION),
+        String::new(),
+    ];
+    let mut section = |comment: &str, commands: &[String]| {
+        lines.push(format!("# {}", comment));
+        lines.extend(commands.iter().cloned());
+        lines.push(String::new());
+    };
+
+    if has_biases {
+        section(
+            "Master bias",
+            &[
+                "cd biases".to_string(),
+                "convert bias -out=../process".to_string(),
+                "cd ../process".to_string(),
+                "stack bias rej 3 3 -nonorm -out=../masters/bias_stacked".to_string(),
+                "cd ..".to_string(),
+            ],
+        );
+    }
+    if has_flats {
+        let mut commands = vec![
+            "cd flats".to_string(),
+            "convert flat -out=../process".to_string(),
+            "cd ../process".to_string(),
+        ];
+        let sequence = if has_biases {
+            commands.push("calibrate flat -bias=../masters/bias_stacked".to_string());
+            "pp_flat"
+        } else {
+            "flat"
+        };
+        commands.push(format!(
+            "stack {} rej 3 3 -norm=mul -out=../masters/flat_stacked",
+            sequence
+        ));
+        commands.push("cd ..".to_string());
+        section("Master flat", &commands);
+    }
+    if has_darks {
+        section(
+            "Master dark",
+            &[
+                "cd darks".to_string(),
+                "convert dark -out=../process".to_string(),
+                "cd ../process".to_string(),
+                "stack dark rej 3 3 -nonorm -out=../masters/dark_stacked".to_string(),
+                "cd ..".to_string(),
+            ],
+        );
+    }
+
+    let mut commands = vec!["cd lights".to_string()];
+    let mut sequence = "light";
+    if has_darks || has_flats || has_biases {
+        commands.push("convert light -out=../process".to_string());
+        commands.push("cd ../process".to_string());
+        let mut calibrate = "calibrate light".to_string();
+        if has_darks {
+            calibrate.push_str(" -dark=../masters/dark_stacked -cc=dark");
+        } else if has_biases {
+            calibrate.push_str(" -bias=../masters/bias_stacked");
+        }
+        if has_flats {
+            calibrate.push_str(" -flat=../masters/flat_stacked");
+        }
+        if debayer {
+            calibrate.push_str(" -cfa");
+            if has_flats {
+                calibrate.push_str(" -equalize_cfa");
+            }
+            calibrate.push_str(" -debayer");
+        }
+        commands.push(calibrate);
+        sequence = "pp_light";
+    } else {
+        let debayer = if debayer { " -debayer" } else { "" };
+        commands.push(format!("convert light{} -out=../process", debayer));
+        commands.push("cd ../process".to_string());
+    }
+    commands.push(format!("register {}", sequence));
+    let rgb_equal = if debayer { " -rgb_equal" } else { "" };
+    commands.push(format!(
+        "stack r_{} rej 3 3 -norm=addscale -output_norm{} -out=../result",
+        sequence, rgb_equal
+    ));
+    commands.push("cd ..".to_string());
+    section("Calibrate, align and stack the lights", &commands);
+
+    lines.push("close".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Copy frames into Siril's input folders under `target_dir`, reusing files
+/// already there. Returns the number copied.
+fn copy_frames(frames: &FrameSets, target_dir: &Path) -> Result<usize, String> {
+    let mut copied = 0;
+    for (folder, files) in frames.folders() {
+        if files.is_empty() {
+            continue;
+        }
+        let dir = target_dir.join(folder);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        for source in files {
+            let target = dir.join(source.file_name().unwrap_or_default());
+            if target.exists() {
+                continue;
+            }
+            fs::copy(source, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            copied += 1;
+        }
+    }
+    for folder in ["process", "masters"] {
+        let dir = target_dir.join(folder);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    Ok(copied)
+}
+
+/// Write a Siril preprocessing script for the selected images into
+/// `target_dir`, with their frames copied alongside it
+#[tauri::command]
+pub async fn export_siril_script(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    image_ids: Vec<String>,
+    target_dir: String,
+) -> Result<SirilScriptExport, String> {
+    let db = state.db.clone();
+    let classifier = frame_classifier(&app);
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        let mut frames = FrameSets::default();
+        let mut result = SirilScriptExport::default();
+
+        for id in &image_ids {
+            let image = repository::get_image_by_id(&mut conn, id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Image not found: {}", id))?;
+            let Some(path) = fits_file(&image) else {
+                result
+                    .skipped
+                    .push(format!("{}: no FITS file on disk", image.filename));
+                continue;
+            };
+            let stem = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let headers = stored_headers(image.metadata.as_deref());
+
+            match classifier.classify(&stem, Some(&headers)) {
+                FrameType::Stacked => {
+                    let subs = repository::get_image_subframes(&mut conn, &image.id)
+                        .map_err(|e| e.to_string())?;
+                    let mut lights: Vec<PathBuf> = subs.iter().filter_map(fits_file).collect();
+                    if lights.is_empty() {
+                        lights = lights_on_disk(&path, &classifier);
+                    }
+                    if lights.is_empty() {
+                        result
+                            .skipped
+                            .push(format!("{}: no Light frames found", image.filename));
+                    }
+                    for light in lights {
+                        frames.add(FrameType::Light, light);
+                    }
+                }
+                frame_type => {
+                    if !frames.add(frame_type, path) {
+                        result.skipped.push(format!(
+                            "{}: not a stacked image or Light, Dark, Flat or Bias frame",
+                            image.filename
+                        ));
+                    }
+                }
+            }
+        }
+        drop(conn);
+
+        if frames.lights.is_empty() {
+            return Err("No Light frames to stack in the selected images".to_string());
+        }
+
+        // One-shot color cameras (Seestar...) write the Bayer pattern
+        let debayer = parse_fits_metadata(&frames.lights[0])
+            .map(|m| m.raw_headers.contains_key("BAYERPAT"))
+            .unwrap_or(false);
+
+        let target_dir = PathBuf::from(&target_dir);
+        result.files_copied = copy_frames(&frames, &target_dir)?;
+
+        let script_path = target_dir.join(SCRIPT_FILE);
+        fs::write(&script_path, build_siril_script(&frames, debayer))
+            .map_err(|e| format!("Failed to write Siril script: {}", e))?;
+
+        result.script_path = script_path.to_string_lossy().to_string();
+        result.lights = frames.lights.len();
+        result.darks = frames.darks.len();
+        result.flats = frames.flats.len();
+        result.biases = frames.biases.len();
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Siril script export failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_sets(lights: usize, darks: usize, flats: usize, biases: usize) -> FrameSets {
+        let files = |kind: &str, n: usize| {
+            (0..n)
+                .map(|i| PathBuf::from(format!("/data/{}_{:04}.fit", kind, i)))
+                .collect()
+        };
+        FrameSets {
+            lights: files("Light", lights),
+            darks: files("Dark", darks),
+            flats: files("Flat", flats),
+            biases: files("Bias", biases),
+        }
+    }
+
+    #[test]
+    fn lights_only_script_debayers_at_conversion() {
+        let script = build_siril_script(&frame_sets(30, 0, 0, 0), true);
+        assert!(script.contains("requires 1.2.0"));
+        assert!(script.contains("convert light -debayer -out=../process"));
+        assert!(script.contains("register light\n"));
+        assert!(script.contains("stack r_light rej 3 3 -norm=addscale -output_norm -rgb_equal"));
+        assert!(!script.contains("calibrate"));
+        assert!(script.ends_with("close\n"));
+    }
+
+    #[test]
+    fn calibration_uses_the_masters_available() {
+        let script = build_siril_script(&frame_sets(30, 10, 10, 10), true);
+        assert!(script.contains("calibrate flat -bias=../masters/bias_stacked"));
+        assert!(script.contains(
+            "calibrate light -dark=../masters/dark_stacked -cc=dark \
+             -flat=../masters/flat_stacked -cfa -equalize_cfa -debayer"
+        ));
+        assert!(script.contains("stack r_pp_light"));
+
+        let script = build_siril_script(&frame_sets(30, 0, 10, 10), false);
+        assert!(script.contains(
+            "calibrate light -bias=../masters/bias_stacked -flat=../masters/flat_stacked\n"
+        ));
+        assert!(!script.contains("Master dark"));
+        assert!(!script.contains("-rgb_equal"));
+    }
+
+    #[test]
+    fn headers_are_read_from_either_metadata_layout() {
+        let scan = r#"{"object_name":"M 42","raw_headers":{"IMAGETYP":"Some(CharacterString(\"Dark Frame\"))"}}"#;
+        let auto_import = r#"{"IMAGETYP":"Some(CharacterString(\"Flat Frame\"))"}"#;
+        assert!(stored_headers(Some(scan))["IMAGETYP"].contains("Dark"));
+        assert!(stored_headers(Some(auto_import))["IMAGETYP"].contains("Flat"));
+        assert!(stored_headers(None).is_empty());
+
+        let mut frames = FrameSets::default();
+        assert!(frames.add(FrameType::Dark, PathBuf::from("/d/Dark_1.fit")));
+        assert!(frames.add(FrameType::Dark, PathBuf::from("/d/Dark_1.fit")));
+        assert!(!frames.add(FrameType::Unknown, PathBuf::from("/d/M42.fit")));
+        assert_eq!(frames.darks.len(), 1);
+    }
+}
@@ -0,0 +1,391 @@
+//! Sky brightness (SQM-style mag/arcsec²) estimated from a frame's background
+//!
+//! A calibrated frame's background level, converted to electrons with the
+//! camera gain and divided by the exposure time and the pixel's area on the
+//! sky (from its plate solution), is the sky's photon rate per square
+//! arcsecond. Compared with the rate a magnitude 0 star gives through the
+//! same aperture, that's a surface brightness in mag/arcsec², the unit SQM
+//! meters read.
+//!
+//! Without a photometric calibration of the camera the result is an
+//! estimate, good to a few tenths of a magnitude for comparing nights taken
+//! with the same equipment; `calibration_offset` lines it up with an SQM
+//! reading taken at the same time. Readings are kept on the image and
+//! summarized on its session collection to chart sky quality across nights.
+
+use chrono::Utc;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{Collection, Image, UpdateCollection, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::frame_quality::background_level;
+use super::plate_solve::read_fits_pixels;
+use super::scan::{extract_float_value, metadata_number_value};
+
+/// Key of the reading in an image's, and the nightly summary in a session
+/// collection's, metadata JSON
+pub const METADATA_KEY: &str = "sky_brightness";
+
+/// Photons per second per cm² from a magnitude 0 star across a visual
+/// passband (about 1000 photons/s/cm²/Å over ~1000 Å)
+const MAG_ZERO_PHOTON_RATE: f64 = 1.0e6;
+/// Share of the light reaching the sensor that's recorded: optics
+/// transmission times quantum efficiency
+const DEFAULT_THROUGHPUT: f64 = 0.5;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SkyBrightnessOptions {
+    /// Camera gain in electrons per ADU (default: the EGAIN header, else 1)
+    pub electrons_per_adu: Option<f64>,
+    /// Telescope aperture (default: the APTDIA header, else the telescope
+    /// linked to the image)
+    pub aperture_mm: Option<f64>,
+    /// Bias level still in the frame, in ADU (default 0 for calibrated frames)
+    pub pedestal: Option<f64>,
+    /// Optics transmission times quantum efficiency (default 0.5)
+    pub throughput: Option<f64>,
+    /// Added to the result to match a reference SQM reading
+    pub calibration_offset: Option<f64>,
+}
+
+/// A sky brightness estimate for one frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkyBrightnessReading {
+    /// Surface brightness in mag/arcsec²; higher is darker
+    pub mag_arcsec2: f64,
+    /// Background level in ADU, pedestal removed
+    pub background_adu: f64,
+    pub electrons_per_adu: f64,
+    /// No gain was known and unity gain was assumed
+    pub gain_assumed: bool,
+    pub exposure: f64,
+    pub pixel_scale: f64,
+    pub aperture_mm: f64,
+    pub measured_at: String,
+}
+
+/// Nightly summary kept on a session collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSkyBrightness {
+    /// Median of the night's readings
+    pub mag_arcsec2: f64,
+    /// Darkest reading of the night
+    pub darkest: f64,
+    pub readings: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkyBrightnessNight {
+    pub collection_id: String,
+    pub name: String,
+    pub session_date: String,
+    pub mag_arcsec2: f64,
+    pub darkest: f64,
+    pub readings: usize,
+}
+
+/// What goes into an estimate, in the units the formula wants
+#[derive(Debug, Clone, Copy)]
+struct SkyInputs {
+    background_adu: f64,
+    electrons_per_adu: f64,
+    exposure: f64,
+    /// arcsec per pixel
+    pixel_scale: f64,
+    aperture_mm: f64,
+    throughput: f64,
+}
+
+/// Surface brightness in mag/arcsec², or None if the sky left no signal
+fn surface_brightness(inputs: &SkyInputs) -> Option<f64> {
+    let electrons_per_second_arcsec2 = inputs.background_adu * inputs.electrons_per_adu
+        / inputs.exposure
+        / (inputs.pixel_scale * inputs.pixel_scale);
+    let aperture_cm2 = std::f64::consts::PI * (inputs.aperture_mm / 20.0).powi(2);
+    let mag_zero_rate = MAG_ZERO_PHOTON_RATE * aperture_cm2 * inputs.throughput;
+    (electrons_per_second_arcsec2 > 0.0 && mag_zero_rate > 0.0)
+        .then(|| -2.5 * (electrons_per_second_arcsec2 / mag_zero_rate).log10())
+}
+
+/// Pixel scale in arcsec/pixel from an image's plate solution
+fn solved_pixel_scale(metadata: Option<&str>) -> Option<f64> {
+    let meta: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    meta.get("plate_solve")?
+        .get("pixel_scale")?
+        .as_f64()
+        .filter(|scale| *scale > 0.0)
+}
+
+fn header_number(metadata: &str, field: &str, headers: &[&str]) -> Option<f64> {
+    metadata_number_value(metadata, field, headers)
+        .and_then(|v| extract_float_value(&v))
+        .filter(|v| *v > 0.0)
+}
+
+/// Aperture from the APTDIA header, else the telescope linked to the image
+fn image_aperture(conn: &mut SqliteConnection, image: &Image) -> Option<f64> {
+    let metadata = image.metadata.as_deref().unwrap_or("{}");
+    header_number(metadata, "aperture", &["APTDIA"]).or_else(|| {
+        repository::get_equipment_for_image(conn, &image.id)
+            .ok()?
+            .into_iter()
+            .filter(|item| item.kind == "telescope")
+            .find_map(|item| item.aperture_mm)
+    })
+}
+
+/// A reading stored in image metadata
+fn stored_reading(metadata: Option<&str>) -> Option<SkyBrightnessReading> {
+    let value: serde_json::Value = serde_json::from_str(metadata?).ok()?;
+    serde_json::from_value(value.get(METADATA_KEY)?.clone()).ok()
+}
+
+/// Set the sky brightness in a metadata JSON object
+fn with_metadata_value(metadata: Option<&str>, value: serde_json::Value) -> String {
+    let mut meta = metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    meta[METADATA_KEY] = value;
+    meta.to_string()
+}
+
+/// Summary of a night's readings
+fn summarize(readings: &[f64]) -> Option<SessionSkyBrightness> {
+    let mut sorted = readings.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    let median = match sorted.len() {
+        0 => return None,
+        n if n.is_multiple_of(2) => (sorted[mid - 1] + sorted[mid]) / 2.0,
+        _ => sorted[mid],
+    };
+    Some(SessionSkyBrightness {
+        mag_arcsec2: median,
+        darkest: sorted[sorted.len() - 1],
+        readings: sorted.len(),
+    })
+}
+
+/// The night of a session collection, from its `session_date` metadata
+fn session_date(collection: &Collection) -> Option<String> {
+    let meta: serde_json::Value = serde_json::from_str(collection.metadata.as_deref()?).ok()?;
+    meta.get("session_date")?.as_str().map(String::from)
+}
+
+/// Refresh the nightly summary of the session collections `image` (or the
+/// stack it went into) belongs to
+fn update_sessions(conn: &mut SqliteConnection, image: &Image) -> Result<(), String> {
+    let mut collections =
+        repository::get_collections_for_image(conn, &image.id).map_err(|e| e.to_string())?;
+    if let Some(parent) = &image.parent_image_id {
+        collections.extend(
+            repository::get_collections_for_image(conn, parent).map_err(|e| e.to_string())?,
+        );
+    }
+
+    for collection in collections.iter().filter(|c| session_date(c).is_some()) {
+        let images = repository::get_images_in_collection(conn, &collection.id)
+            .map_err(|e| e.to_string())?;
+        let mut readings = Vec::new();
+        for image in &images {
+            readings.extend(stored_reading(image.metadata.as_deref()).map(|r| r.mag_arcsec2));
+            let subs =
+                repository::get_image_subframes(conn, &image.id).map_err(|e| e.to_string())?;
+            readings.extend(
+                subs.iter()
+                    .filter_map(|sub| stored_reading(sub.metadata.as_deref()))
+                    .map(|r| r.mag_arcsec2),
+            );
+        }
+        let Some(summary) = summarize(&readings) else {
+            continue;
+        };
+        let update = UpdateCollection {
+            metadata: Some(with_metadata_value(
+                collection.metadata.as_deref(),
+                serde_json::json!(summary),
+            )),
+            ..Default::default()
+        };
+        repository::update_collection(conn, &collection.id, &update).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Estimate the sky brightness from a plate-solved, calibrated FITS frame.
+/// The reading is kept in the image's metadata and its session collection's
+/// nightly summary is updated.
+#[tauri::command]
+pub async fn estimate_sky_brightness(
+    state: State<'_, AppState>,
+    image_id: String,
+    options: Option<SkyBrightnessOptions>,
+) -> Result<SkyBrightnessReading, String> {
+    let options = options.unwrap_or_default();
+    let db = state.db.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        let image = repository::get_image_by_id(&mut conn, &image_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Image not found: {}", image_id))?;
+        let metadata = image.metadata.as_deref().unwrap_or("{}");
+
+        let pixel_scale = solved_pixel_scale(image.metadata.as_deref())
+            .ok_or("Plate solve the image first; its pixel scale is needed")?;
+        let exposure = image
+            .exposure
+            .or_else(|| header_number(metadata, "exposure", &["EXPTIME", "EXPOSURE"]))
+            .filter(|e| *e > 0.0)
+            .ok_or("The image's exposure time isn't known")?;
+        let aperture_mm = options
+            .aperture_mm
+            .or_else(|| image_aperture(&mut conn, &image))
+            .ok_or(
+                "The telescope aperture isn't known; add it to the telescope's equipment entry",
+            )?;
+        let gain = options
+            .electrons_per_adu
+            .or_else(|| header_number(metadata, "egain", &["EGAIN"]));
+
+        let path = [image.fits_url.as_deref(), image.url.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|path| {
+                let lower = path.to_lowercase();
+                (lower.ends_with(".fit") || lower.ends_with(".fits"))
+                    && std::path::Path::new(path).exists()
+            })
+            .ok_or("Sky brightness needs the image's FITS file")?;
+        let (pixels, _, _) = read_fits_pixels(path)?;
+        let background_adu = background_level(&pixels) - options.pedestal.unwrap_or(0.0);
+
+        let inputs = SkyInputs {
+            background_adu,
+            electrons_per_adu: gain.unwrap_or(1.0),
+            exposure,
+            pixel_scale,
+            aperture_mm,
+            throughput: options.throughput.unwrap_or(DEFAULT_THROUGHPUT),
+        };
+        let mag = surface_brightness(&inputs).ok_or_else(|| {
+            format!(
+                "No sky background left to measure ({:.1} ADU after the pedestal)",
+                background_adu
+            )
+        })?;
+        let reading = SkyBrightnessReading {
+            mag_arcsec2: mag + options.calibration_offset.unwrap_or(0.0),
+            background_adu,
+            electrons_per_adu: inputs.electrons_per_adu,
+            gain_assumed: gain.is_none(),
+            exposure,
+            pixel_scale,
+            aperture_mm,
+            measured_at: Utc::now().to_rfc3339(),
+        };
+
+        let update = UpdateImage {
+            metadata: Some(with_metadata_value(
+                image.metadata.as_deref(),
+                serde_json::json!(reading),
+            )),
+            ..Default::default()
+        };
+        let image =
+            repository::update_image(&mut conn, &image.id, &update).map_err(|e| e.to_string())?;
+        update_sessions(&mut conn, &image)?;
+        log::info!(
+            "Sky brightness of {}: {:.2} mag/arcsec²",
+            image.filename,
+            reading.mag_arcsec2
+        );
+        Ok(reading)
+    })
+    .await
+    .map_err(|e| format!("Sky brightness estimate failed: {}", e))?
+}
+
+/// Nightly sky brightness of every session with readings, oldest first
+#[tauri::command]
+pub fn get_sky_brightness_trend(
+    state: State<'_, AppState>,
+) -> Result<Vec<SkyBrightnessNight>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let collections =
+        repository::get_collections(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    let mut nights: Vec<SkyBrightnessNight> = collections
+        .into_iter()
+        .filter_map(|collection| {
+            let session_date = session_date(&collection)?;
+            let meta: serde_json::Value =
+                serde_json::from_str(collection.metadata.as_deref()?).ok()?;
+            let summary: SessionSkyBrightness =
+                serde_json::from_value(meta.get(METADATA_KEY)?.clone()).ok()?;
+            Some(SkyBrightnessNight {
+                collection_id: collection.id,
+                name: collection.name,
+                session_date,
+                mag_arcsec2: summary.mag_arcsec2,
+                darkest: summary.darkest,
+                readings: summary.readings,
+            })
+        })
+        .collect();
+    nights.sort_by(|a, b| a.session_date.cmp(&b.session_date));
+    Ok(nights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(background_adu: f64) -> SkyInputs {
+        SkyInputs {
+            background_adu,
+            electrons_per_adu: 0.25,
+            exposure: 300.0,
+            pixel_scale: 2.0,
+            aperture_mm: 80.0,
+            throughput: 0.5,
+        }
+    }
+
+    #[test]
+    fn background_converts_to_surface_brightness() {
+        // 120 e⁻ per 2"×2" pixel in 300 s through 80 mm is 0.1 e⁻/s/arcsec²,
+        // about a rural sky
+        let dark = surface_brightness(&inputs(480.0)).unwrap();
+        assert!((dark - 21.0).abs() < 0.01, "{}", dark);
+
+        // Ten times the sky glow is 2.5 magnitudes brighter
+        let bright = surface_brightness(&inputs(4800.0)).unwrap();
+        assert!((dark - bright - 2.5).abs() < 1e-9);
+
+        // A frame with its pedestal over-subtracted has nothing to measure
+        assert_eq!(surface_brightness(&inputs(-3.0)), None);
+    }
+
+    #[test]
+    fn nights_are_summarized_from_their_readings() {
+        assert_eq!(summarize(&[]), None);
+        let night = summarize(&[20.1, 20.9, 20.5, 19.2]).unwrap();
+        assert!((night.mag_arcsec2 - 20.3).abs() < 1e-9);
+        assert_eq!(night.darkest, 20.9);
+        assert_eq!(night.readings, 4);
+
+        let solved = r#"{"plate_solve": {"pixel_scale": 2.4, "center_ra": 10.0}}"#;
+        assert_eq!(solved_pixel_scale(Some(solved)), Some(2.4));
+        assert_eq!(solved_pixel_scale(Some(r#"{"EXPTIME": 10}"#)), None);
+        assert_eq!(
+            header_number(r#"{"raw_headers": {"EGAIN": "0.8"}}"#, "egain", &["EGAIN"]),
+            Some(0.8)
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Survey cutouts of a patch of sky, for previewing a target before imaging
+//!
+//! Images come from the CDS hips2fits service, which reprojects a HiPS
+//! survey (DSS2 color by default) onto a tangent plane centered on the
+//! requested coordinates. Each cutout is kept in the app cache dir, keyed by
+//! its request, so the planner can show it again without a network round
+//! trip, including in offline mode.
+
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+
+use crate::network::{Network, Provider};
+use crate::state::AppState;
+
+const HIPS2FITS_URL: &str = "https://alasky.cds.unistra.fr/hips-image-services/hips2fits";
+/// Cached cutouts, under the app cache dir
+const CACHE_DIR: &str = "sky-cutouts";
+
+const DEFAULT_SURVEY: &str = "CDS/P/DSS2/color";
+const DEFAULT_SIZE: u32 = 800;
+/// Largest cutout side hips2fits is asked for
+const MAX_SIZE: u32 = 4000;
+const MAX_FOV_DEG: f64 = 180.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CutoutOptions {
+    /// Width in pixels (default 800)
+    pub width: Option<u32>,
+    /// Height in pixels (default: square)
+    pub height: Option<u32>,
+    /// HiPS survey ID, e.g. "CDS/P/DSS2/red" (default DSS2 color)
+    pub survey: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkyCutout {
+    /// JPEG in the cutout cache
+    pub path: String,
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub survey: String,
+    pub ra: f64,
+    pub dec: f64,
+    /// Field of view across the width, in degrees
+    pub fov: f64,
+    /// Served from the cache rather than downloaded
+    pub cached: bool,
+}
+
+/// A validated cutout request
+#[derive(Debug, Clone, PartialEq)]
+struct CutoutRequest {
+    ra: f64,
+    dec: f64,
+    fov: f64,
+    width: u32,
+    height: u32,
+    survey: String,
+}
+
+impl CutoutRequest {
+    fn new(ra: f64, dec: f64, fov: f64, options: &CutoutOptions) -> Result<Self, String> {
+        if !ra.is_finite() || !dec.is_finite() || !(-90.0..=90.0).contains(&dec) {
+            return Err(format!("Invalid coordinates: RA {}, Dec {}", ra, dec));
+        }
+        if !fov.is_finite() || fov <= 0.0 || fov > MAX_FOV_DEG {
+            return Err(format!(
+                "Field of view must be between 0 and {} degrees",
+                MAX_FOV_DEG
+            ));
+        }
+        let width = options.width.unwrap_or(DEFAULT_SIZE);
+        let height = options.height.unwrap_or(width);
+        if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+            return Err(format!(
+                "Cutout size must be between 1 and {} pixels",
+                MAX_SIZE
+            ));
+        }
+        let survey = options
+            .survey
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .unwrap_or(DEFAULT_SURVEY)
+            .to_string();
+        // HiPS IDs are authority/path segments; anything else would need
+        // escaping and isn't a survey hips2fits knows
+        if !survey
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/-_.+".contains(c))
+        {
+            return Err(format!("Invalid survey ID: {}", survey));
+        }
+        Ok(CutoutRequest {
+            ra: ra.rem_euclid(360.0),
+            dec,
+            fov,
+            width,
+            height,
+            survey,
+        })
+    }
+
+    /// hips2fits URL. Coordinates are rounded to well under a DSS pixel so
+    /// repeated requests for the same target share a cache entry.
+    fn url(&self) -> String {
+        format!(
+            "{}?hips={}&width={}&height={}&fov={:.5}&projection=TAN&coordsys=icrs&ra={:.5}&dec={:.5}&format=jpg",
+            HIPS2FITS_URL, self.survey, self.width, self.height, self.fov, self.ra, self.dec
+        )
+    }
+
+    fn cache_path(&self, dir: &Path) -> PathBuf {
+        let hash = hex::encode(Sha256::digest(self.url().as_bytes()));
+        dir.join(format!("{}.jpg", &hash[..16]))
+    }
+}
+
+fn jpeg_data_url(data: &[u8]) -> String {
+    format!("data:image/jpeg;base64,{}", BASE64.encode(data))
+}
+
+/// A cutout and its JPEG data, from the cache or hips2fits
+pub(crate) async fn fetch_cutout(
+    app: &AppHandle,
+    network: &Network,
+    ra: f64,
+    dec: f64,
+    fov: f64,
+    options: &CutoutOptions,
+) -> Result<(SkyCutout, Vec<u8>), String> {
+    let request = CutoutRequest::new(ra, dec, fov, options)?;
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map(|d| d.join(CACHE_DIR))
+        .map_err(|e| format!("Failed to get app cache dir: {}", e))?;
+    let path = request.cache_path(&dir);
+
+    let (data, cached) = match tokio::fs::read(&path).await {
+        Ok(data) => (data, true),
+        Err(_) => {
+            let url = request.url();
+            let response = network
+                .send(Provider::Hips, |client| client.get(&url))
+                .await?;
+            let data = response
+                .bytes()
+                .await
+                .map_err(|e| format!("{} request failed: {}", Provider::Hips.name(), e))?
+                .to_vec();
+            (data, false)
+        }
+    };
+
+    // hips2fits answers some bad requests with an HTML page and HTTP 200
+    let (width, height) = image::load_from_memory(&data)
+        .map(|img| (img.width(), img.height()))
+        .map_err(|e| format!("Invalid cutout image: {}", e))?;
+
+    if !cached {
+        let write = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            tokio::fs::write(&path, &data).await
+        };
+        if let Err(e) = write.await {
+            log::warn!("Failed to cache sky cutout: {}", e);
+        }
+    }
+
+    let cutout = SkyCutout {
+        path: path.to_string_lossy().to_string(),
+        data_url: jpeg_data_url(&data),
+        width,
+        height,
+        survey: request.survey,
+        ra: request.ra,
+        dec: request.dec,
+        fov: request.fov,
+        cached,
+    };
+    Ok((cutout, data))
+}
+
+/// Fetch a survey cutout centered on `ra`/`dec` (degrees) spanning `fov`
+/// degrees across its width. Cached cutouts are returned without a request.
+#[tauri::command]
+pub async fn get_sky_cutout(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    ra: f64,
+    dec: f64,
+    fov: f64,
+    options: Option<CutoutOptions>,
+) -> Result<SkyCutout, String> {
+    let options = options.unwrap_or_default();
+    fetch_cutout(&app, &state.network, ra, dec, fov, &options)
+        .await
+        .map(|(cutout, _)| cutout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_defaults_and_validation() {
+        let request = CutoutRequest::new(-10.0, 41.27, 1.5, &CutoutOptions::default()).unwrap();
+        assert_eq!(request.ra, 350.0);
+        assert_eq!((request.width, request.height), (800, 800));
+        assert_eq!(request.survey, DEFAULT_SURVEY);
+
+        let wide = CutoutOptions {
+            width: Some(1200),
+            survey: Some(" CDS/P/DSS2/red ".to_string()),
+            ..Default::default()
+        };
+        let request = CutoutRequest::new(10.68, 41.27, 3.0, &wide).unwrap();
+        assert_eq!((request.width, request.height), (1200, 1200));
+        assert_eq!(request.survey, "CDS/P/DSS2/red");
+
+        let options = CutoutOptions::default();
+        assert!(CutoutRequest::new(10.0, 95.0, 1.0, &options).is_err());
+        assert!(CutoutRequest::new(10.0, 40.0, 0.0, &options).is_err());
+        assert!(CutoutRequest::new(f64::NAN, 40.0, 1.0, &options).is_err());
+        let bad_survey = CutoutOptions {
+            survey: Some("DSS2&fov=9".to_string()),
+            ..Default::default()
+        };
+        assert!(CutoutRequest::new(10.0, 40.0, 1.0, &bad_survey).is_err());
+        let too_big = CutoutOptions {
+            width: Some(MAX_SIZE + 1),
+            ..Default::default()
+        };
+        assert!(CutoutRequest::new(10.0, 40.0, 1.0, &too_big).is_err());
+    }
+
+    #[test]
+    fn url_and_cache_key() {
+        let options = CutoutOptions {
+            width: Some(600),
+            height: Some(400),
+            ..Default::default()
+        };
+        let request = CutoutRequest::new(83.822083, -5.391111, 1.2, &options).unwrap();
+        assert_eq!(
+            request.url(),
+            "https://alasky.cds.unistra.fr/hips-image-services/hips2fits?hips=CDS/P/DSS2/color\
+             &width=600&height=400&fov=1.20000&projection=TAN&coordsys=icrs\
+             &ra=83.82208&dec=-5.39111&format=jpg"
+        );
+
+        let dir = Path::new("/cache");
+        // Differences below the URL's precision share a cache entry
+        let nudged = CutoutRequest::new(83.822081, -5.391112, 1.2, &options).unwrap();
+        assert_eq!(request.cache_path(dir), nudged.cache_path(dir));
+        let other = CutoutRequest::new(83.9, -5.391111, 1.2, &options).unwrap();
+        assert_ne!(request.cache_path(dir), other.cache_path(dir));
+        assert!(request.cache_path(dir).starts_with(dir));
+    }
+}
@@ -1,8 +1,13 @@
 //! Skymap generation commands
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
+use tauri::State;
 
-use crate::python::skymap;
+use crate::db::repository::{self, AcquisitionQuery};
+use crate::skymap::{self, ChartData, FieldMap, SkymapFormat};
+use crate::state::AppState;
 
 /// Input for generating a skymap
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +25,8 @@ pub struct SkymapInput {
     pub image_width: Option<f64>,
     /// Image FOV height in degrees (for rectangle overlay)
     pub image_height: Option<f64>,
+    /// Output format: "png" (default) or "svg"
+    pub format: Option<SkymapFormat>,
 }
 
 /// Result from skymap generation
@@ -27,39 +34,150 @@ pub struct SkymapInput {
 #[serde(rename_all = "camelCase")]
 pub struct SkymapResponse {
     pub success: bool,
+    /// Map as a data URL
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+impl SkymapResponse {
+    fn from_render(rendered: Result<Vec<u8>, String>, format: SkymapFormat) -> Self {
+        match rendered {
+            Ok(bytes) => Self {
+                success: true,
+                image: Some(skymap::data_url(&bytes, format)),
+                error: None,
+            },
+            Err(e) => Self {
+                success: false,
+                image: None,
+                error: Some(e),
+            },
+        }
+    }
+}
+
+impl SkymapInput {
+    fn field_map(&self) -> FieldMap {
+        FieldMap {
+            fov_width: self.fov_width,
+            fov_height: self.fov_height,
+            image_width: self.image_width.unwrap_or(0.0),
+            image_height: self.image_height.unwrap_or(0.0),
+            ..FieldMap::new(self.center_ra, self.center_dec)
+        }
+    }
+}
+
 /// Generate a skymap showing the location of an image on the sky
 #[tauri::command]
 pub fn generate_skymap(input: SkymapInput) -> Result<SkymapResponse, String> {
-    let result = skymap::generate_skymap(
-        input.center_ra,
-        input.center_dec,
-        input.fov_width,
-        input.fov_height,
-        input.image_width,
-        input.image_height,
-    )?;
-
-    Ok(SkymapResponse {
-        success: result.success,
-        image: result.image,
-        error: result.error,
-    })
+    let format = input.format.unwrap_or_default();
+    Ok(SkymapResponse::from_render(
+        skymap::render_field(&input.field_map(), format),
+        format,
+    ))
+}
+
+/// Get the contents of a skymap as positioned stars, objects, lines and the
+/// image field, for an interactive chart. `format` is ignored.
+#[tauri::command]
+pub fn get_skymap_data(input: SkymapInput) -> Result<ChartData, String> {
+    Ok(skymap::field_chart(&input.field_map()))
 }
 
 /// Generate a wide-field skymap showing position on the entire sky
 #[tauri::command]
-pub fn generate_wide_skymap(center_ra: f64, center_dec: f64) -> Result<SkymapResponse, String> {
-    let result = skymap::generate_wide_skymap(center_ra, center_dec)?;
+pub fn generate_wide_skymap(
+    center_ra: f64,
+    center_dec: f64,
+    format: Option<SkymapFormat>,
+) -> Result<SkymapResponse, String> {
+    let format = format.unwrap_or_default();
+    Ok(SkymapResponse::from_render(
+        skymap::render_wide(center_ra, center_dec, format),
+        format,
+    ))
+}
+
+/// Which images to include in a coverage map; unset fields include all
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageMapInput {
+    /// Only images matching these acquisition criteria
+    pub query: Option<AcquisitionQuery>,
+    /// Only images in this collection
+    pub collection_id: Option<String>,
+    /// Output format: "png" (default) or "svg"
+    pub format: Option<SkymapFormat>,
+}
+
+/// Coverage map with a summary of what it shows
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageMapResponse {
+    #[serde(flatten)]
+    pub map: SkymapResponse,
+    /// Images drawn with their plate-solved field
+    pub solved: usize,
+    /// Images drawn at their header coordinates only
+    pub positioned: usize,
+    /// Images with no known sky position
+    pub unplaced: usize,
+    /// Percentage of the sky inside at least one solved field
+    pub coverage_percent: f64,
+}
+
+/// Generate an all-sky map of every imaged field, showing the archive's sky
+/// coverage and its gaps
+#[tauri::command]
+pub async fn generate_coverage_map(
+    state: State<'_, AppState>,
+    input: Option<CoverageMapInput>,
+) -> Result<CoverageMapResponse, String> {
+    let input = input.unwrap_or_default();
+    let format = input.format.unwrap_or_default();
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let mut images = repository::search_images_by_acquisition(
+        &mut conn,
+        &state.user_id,
+        &input.query.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())?;
+    if let Some(collection_id) = &input.collection_id {
+        let in_collection: HashSet<String> =
+            repository::get_images_in_collection(&mut conn, collection_id)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|image| image.id)
+                .collect();
+        images.retain(|image| in_collection.contains(&image.id));
+    }
+    drop(conn);
+
+    let footprints: Vec<_> = images
+        .iter()
+        .filter_map(super::plate_solve::image_footprint)
+        .collect();
+    let solved = footprints.iter().filter(|f| f.width > 0.0).count();
+    let positioned = footprints.len() - solved;
+    let unplaced = images.len() - footprints.len();
+
+    let (rendered, coverage) = tokio::task::spawn_blocking(move || {
+        (
+            skymap::render_coverage(&footprints, format),
+            skymap::sky_coverage(&footprints),
+        )
+    })
+    .await
+    .map_err(|e| format!("Coverage map failed: {}", e))?;
 
-    Ok(SkymapResponse {
-        success: result.success,
-        image: result.image,
-        error: result.error,
+    Ok(CoverageMapResponse {
+        map: SkymapResponse::from_render(rendered, format),
+        solved,
+        positioned,
+        unplaced,
+        coverage_percent: coverage * 100.0,
     })
 }
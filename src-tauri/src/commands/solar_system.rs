@@ -0,0 +1,618 @@
+//! Planetary, lunar and solar images
+//!
+//! Solar system targets don't fit the deep sky flow: they move against the
+//! stars, so there's nothing to plate solve, and what matters is how big and
+//! how fully lit the body was when it was captured. Images are recognized by
+//! their OBJECT header (or file name, which planetary capture software
+//! usually fills in), and the body's apparent diameter, phase and position
+//! at capture time are stored in the image metadata under `solar_system`.
+//!
+//! Positions come from JPL's approximate Keplerian elements (valid
+//! 1800-2050) for the planets and a truncated lunar theory for the Moon,
+//! good to well under a degree, which is plenty for display.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::UpdateImage;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::scan::{metadata_header_value, parse_utc_timestamp};
+
+/// Key of the solar system details in an image's metadata JSON
+pub const METADATA_KEY: &str = "solar_system";
+
+const J2000: f64 = 2_451_545.0;
+const OBLIQUITY_J2000: f64 = 23.439_28;
+const AU_KM: f64 = 149_597_870.7;
+const MOON_RADIUS_KM: f64 = 1_737.4;
+/// General precession in ecliptic longitude, degrees per Julian century
+const PRECESSION_PER_CENTURY: f64 = 1.3972;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Body {
+    Sun,
+    Moon,
+    Mercury,
+    Venus,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+}
+
+/// Names an image of each body goes by, lowercase
+const BODY_NAMES: [(Body, &[&str]); 9] = [
+    (Body::Sun, &["sun", "solar"]),
+    (Body::Moon, &["moon", "lunar", "luna"]),
+    (Body::Mercury, &["mercury"]),
+    (Body::Venus, &["venus"]),
+    (Body::Mars, &["mars"]),
+    (Body::Jupiter, &["jupiter"]),
+    (Body::Saturn, &["saturn"]),
+    (Body::Uranus, &["uranus"]),
+    (Body::Neptune, &["neptune"]),
+];
+
+/// Words that mean a deep sky object named after a planet (Saturn Nebula)
+const DEEP_SKY_WORDS: [&str; 3] = ["nebula", "galaxy", "cluster"];
+
+/// JPL approximate elements at J2000 and their rates per Julian century:
+/// a (au), e, I, L, longitude of perihelion, longitude of the ascending node
+/// (degrees). The Earth entry is the Earth-Moon barycenter.
+type Elements = [[f64; 6]; 2];
+
+const EARTH: Elements = [
+    [
+        1.000_002_61,
+        0.016_711_23,
+        -0.000_015_31,
+        100.464_571_66,
+        102.937_681_93,
+        0.0,
+    ],
+    [
+        0.000_005_62,
+        -0.000_043_92,
+        -0.012_946_68,
+        35_999.372_449_81,
+        0.323_273_64,
+        0.0,
+    ],
+];
+
+fn elements(body: Body) -> Option<Elements> {
+    Some(match body {
+        Body::Mercury => [
+            [
+                0.387_099_27,
+                0.205_635_93,
+                7.004_979_02,
+                252.250_323_50,
+                77.457_796_28,
+                48.330_765_93,
+            ],
+            [
+                0.000_000_37,
+                0.000_019_06,
+                -0.005_947_49,
+                149_472.674_111_75,
+                0.160_476_89,
+                -0.125_340_81,
+            ],
+        ],
+        Body::Venus => [
+            [
+                0.723_335_66,
+                0.006_776_72,
+                3.394_676_05,
+                181.979_099_50,
+                131.602_467_18,
+                76.679_842_55,
+            ],
+            [
+                0.000_003_90,
+                -0.000_041_07,
+                -0.000_788_90,
+                58_517.815_387_29,
+                0.002_683_29,
+                -0.277_694_18,
+            ],
+        ],
+        Body::Mars => [
+            [
+                1.523_710_34,
+                0.093_394_10,
+                1.849_691_42,
+                -4.553_432_05,
+                -23.943_629_59,
+                49.559_538_91,
+            ],
+            [
+                0.000_018_47,
+                0.000_078_82,
+                -0.008_131_31,
+                19_140.302_684_99,
+                0.444_410_88,
+                -0.292_573_43,
+            ],
+        ],
+        Body::Jupiter => [
+            [
+                5.202_887_00,
+                0.048_386_24,
+                1.304_396_95,
+                34.396_440_51,
+                14.728_479_83,
+                100.473_909_09,
+            ],
+            [
+                -0.000_116_07,
+                -0.000_132_53,
+                -0.001_837_14,
+                3_034.746_127_75,
+                0.212_526_68,
+                0.204_691_06,
+            ],
+        ],
+        Body::Saturn => [
+            [
+                9.536_675_94,
+                0.053_861_79,
+                2.485_991_87,
+                49.954_244_23,
+                92.598_878_31,
+                113.662_424_48,
+            ],
+            [
+                -0.001_250_60,
+                -0.000_509_91,
+                0.001_936_09,
+                1_222.493_622_01,
+                -0.418_972_16,
+                -0.288_677_94,
+            ],
+        ],
+        Body::Uranus => [
+            [
+                19.189_164_64,
+                0.047_257_44,
+                0.772_637_83,
+                313.238_104_51,
+                170.954_276_30,
+                74.016_925_03,
+            ],
+            [
+                -0.001_961_76,
+                -0.000_043_97,
+                -0.002_429_39,
+                428.482_027_85,
+                0.408_052_81,
+                0.042_405_89,
+            ],
+        ],
+        Body::Neptune => [
+            [
+                30.069_922_76,
+                0.008_590_48,
+                1.770_043_47,
+                -55.120_029_69,
+                44.964_762_27,
+                131.784_225_74,
+            ],
+            [
+                0.000_262_91,
+                0.000_051_05,
+                0.000_353_72,
+                218.459_453_25,
+                -0.322_414_64,
+                -0.005_086_64,
+            ],
+        ],
+        Body::Sun | Body::Moon => return None,
+    })
+}
+
+/// Apparent equatorial diameter at 1 au, in arcseconds
+fn diameter_at_1_au(body: Body) -> f64 {
+    match body {
+        Body::Sun => 1_919.26,
+        Body::Mercury => 6.74,
+        Body::Venus => 16.92,
+        Body::Mars => 9.36,
+        Body::Jupiter => 196.74,
+        Body::Saturn => 165.6,
+        Body::Uranus => 70.48,
+        Body::Neptune => 68.3,
+        Body::Moon => 2.0 * MOON_RADIUS_KM / AU_KM * 206_264.806,
+    }
+}
+
+impl Body {
+    pub fn name(self) -> &'static str {
+        match self {
+            Body::Sun => "Sun",
+            Body::Moon => "Moon",
+            Body::Mercury => "Mercury",
+            Body::Venus => "Venus",
+            Body::Mars => "Mars",
+            Body::Jupiter => "Jupiter",
+            Body::Saturn => "Saturn",
+            Body::Uranus => "Uranus",
+            Body::Neptune => "Neptune",
+        }
+    }
+}
+
+/// The body a target name refers to, e.g. "Jupiter" or a capture file name
+/// like "2024-10-05-2200_1-Jupiter"
+pub fn body_from_name(name: &str) -> Option<Body> {
+    let lower = name.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.iter().any(|w| DEEP_SKY_WORDS.contains(w)) {
+        return None;
+    }
+    words.iter().find_map(|word| {
+        BODY_NAMES
+            .iter()
+            .find(|(_, names)| names.contains(word))
+            .map(|(body, _)| *body)
+    })
+}
+
+/// The body an image shows. A target name (OBJECT header or the image's
+/// target) decides on its own; only images without one go by file name.
+pub fn identify_body(target: Option<&str>, file_name: &str) -> Option<Body> {
+    match target.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(target) => body_from_name(target),
+        None => body_from_name(file_name),
+    }
+}
+
+/// Where a body was and how it looked at one instant, seen from Earth's
+/// center
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ephemeris {
+    /// J2000 position in degrees
+    pub ra: f64,
+    pub dec: f64,
+    pub distance_au: f64,
+    /// Equatorial diameter in arcseconds
+    pub apparent_diameter_arcsec: f64,
+    /// Sun-body-Earth angle in degrees (none for the Sun)
+    pub phase_angle: Option<f64>,
+    /// Lit fraction of the disk, 0-1 (none for the Sun)
+    pub illuminated_fraction: Option<f64>,
+}
+
+fn julian_date(time: NaiveDateTime) -> f64 {
+    time.and_utc().timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5
+}
+
+/// Heliocentric ecliptic J2000 position in au
+fn heliocentric(elements: &Elements, t: f64) -> [f64; 3] {
+    let at = |k: usize| elements[0][k] + elements[1][k] * t;
+    let (a, e) = (at(0), at(1));
+    let (incl, longitude, perihelion, node) =
+        (at(2).to_radians(), at(3), at(4), at(5).to_radians());
+    let mean_anomaly = ((longitude - perihelion + 180.0).rem_euclid(360.0) - 180.0).to_radians();
+    let argument = (perihelion.to_radians()) - node;
+
+    let mut eccentric = mean_anomaly + e * mean_anomaly.sin();
+    for _ in 0..10 {
+        eccentric -= (eccentric - e * eccentric.sin() - mean_anomaly) / (1.0 - e * eccentric.cos());
+    }
+    let x = a * (eccentric.cos() - e);
+    let y = a * (1.0 - e * e).sqrt() * eccentric.sin();
+
+    let (sw, cw) = argument.sin_cos();
+    let (sn, cn) = node.sin_cos();
+    let (si, ci) = incl.sin_cos();
+    [
+        (cw * cn - sw * sn * ci) * x + (-sw * cn - cw * sn * ci) * y,
+        (cw * sn + sw * cn * ci) * x + (-sw * sn + cw * cn * ci) * y,
+        (sw * si) * x + (cw * si) * y,
+    ]
+}
+
+/// Geocentric ecliptic J2000 position of the Moon in au
+fn moon_geocentric(jd: f64) -> [f64; 3] {
+    let d = jd - J2000;
+    let t = d / 36_525.0;
+    let sin = |deg: f64| deg.to_radians().sin();
+    let cos = |deg: f64| deg.to_radians().cos();
+
+    let mean_longitude = 218.316 + 13.176_396 * d;
+    let anomaly = 134.963 + 13.064_993 * d;
+    let latitude_argument = 93.272 + 13.229_350 * d;
+    let elongation = 297.850 + 12.190_749 * d;
+    let sun_anomaly = 357.529 + 0.985_600_28 * d;
+
+    let longitude = mean_longitude
+        + 6.289 * sin(anomaly)
+        + 1.274 * sin(2.0 * elongation - anomaly)
+        + 0.658 * sin(2.0 * elongation)
+        + 0.214 * sin(2.0 * anomaly)
+        - 0.186 * sin(sun_anomaly)
+        - 0.114 * sin(2.0 * latitude_argument)
+        - PRECESSION_PER_CENTURY * t;
+    let latitude = 5.128 * sin(latitude_argument)
+        + 0.280 * sin(anomaly + latitude_argument)
+        + 0.277 * sin(anomaly - latitude_argument)
+        + 0.173 * sin(2.0 * elongation - latitude_argument);
+    let distance_km = 385_001.0
+        - 20_905.0 * cos(anomaly)
+        - 3_699.0 * cos(2.0 * elongation - anomaly)
+        - 2_956.0 * cos(2.0 * elongation)
+        - 570.0 * cos(2.0 * anomaly);
+
+    let r = distance_km / AU_KM;
+    [
+        r * cos(latitude) * cos(longitude),
+        r * cos(latitude) * sin(longitude),
+        r * sin(latitude),
+    ]
+}
+
+fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/// Position and appearance of `body` at `time` (UTC)
+pub fn ephemeris(body: Body, time: NaiveDateTime) -> Ephemeris {
+    let jd = julian_date(time);
+    let t = (jd - J2000) / 36_525.0;
+    let earth = heliocentric(&EARTH, t);
+
+    // Geocentric and heliocentric vectors of the body
+    let (geocentric, helio) = match body {
+        Body::Sun => ([-earth[0], -earth[1], -earth[2]], None),
+        Body::Moon => {
+            let moon = moon_geocentric(jd);
+            (
+                moon,
+                Some([earth[0] + moon[0], earth[1] + moon[1], earth[2] + moon[2]]),
+            )
+        }
+        planet => {
+            let helio = heliocentric(&elements(planet).expect("planet elements"), t);
+            (
+                [
+                    helio[0] - earth[0],
+                    helio[1] - earth[1],
+                    helio[2] - earth[2],
+                ],
+                Some(helio),
+            )
+        }
+    };
+    let distance = norm(geocentric);
+
+    let (se, ce) = OBLIQUITY_J2000.to_radians().sin_cos();
+    let [x, y, z] = geocentric;
+    let (y, z) = (y * ce - z * se, y * se + z * ce);
+    let ra = y.atan2(x).to_degrees().rem_euclid(360.0);
+    let dec = (z / distance).clamp(-1.0, 1.0).asin().to_degrees();
+
+    // The angle at the body between the Sun and Earth
+    let phase_angle = helio.map(|h| {
+        let dot = h[0] * geocentric[0] + h[1] * geocentric[1] + h[2] * geocentric[2];
+        (dot / (norm(h) * distance))
+            .clamp(-1.0, 1.0)
+            .acos()
+            .to_degrees()
+    });
+
+    let apparent_diameter_arcsec = match body {
+        Body::Moon => 2.0 * (MOON_RADIUS_KM / (distance * AU_KM)).asin().to_degrees() * 3600.0,
+        _ => diameter_at_1_au(body) / distance,
+    };
+
+    Ephemeris {
+        ra,
+        dec,
+        distance_au: distance,
+        apparent_diameter_arcsec,
+        phase_angle,
+        illuminated_fraction: phase_angle.map(|i| (1.0 + i.to_radians().cos()) / 2.0),
+    }
+}
+
+/// What's stored in the image metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarSystemInfo {
+    pub body: Body,
+    /// Capture time the ephemeris is for (UTC)
+    pub observed_at: Option<String>,
+    /// Missing when the capture time isn't known
+    pub ephemeris: Option<Ephemeris>,
+}
+
+/// Solar system details for an image, if it shows a solar system body
+pub fn solar_system_info(
+    target: Option<&str>,
+    file_name: &str,
+    date_obs: Option<&str>,
+) -> Option<SolarSystemInfo> {
+    let body = identify_body(target, file_name)?;
+    let time = date_obs.and_then(parse_utc_timestamp);
+    Some(SolarSystemInfo {
+        body,
+        observed_at: time.map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        ephemeris: time.map(|t| ephemeris(body, t)),
+    })
+}
+
+/// Record solar system details in an image's metadata JSON
+pub fn with_solar_system(metadata: Option<String>, info: &SolarSystemInfo) -> Option<String> {
+    let mut value = metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    value[METADATA_KEY] = serde_json::json!(info);
+    Some(value.to_string())
+}
+
+/// Whether an image's metadata marks it as a solar system image, which
+/// can't be plate solved
+pub(crate) fn is_solar_system(metadata: Option<&str>) -> bool {
+    metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .is_some_and(|m| m.get(METADATA_KEY).is_some_and(|v| v.is_object()))
+}
+
+/// Recognize solar system images already in the library (all of them when
+/// `image_ids` is omitted) and record their ephemeris. Images without a
+/// target are given the body's name. Returns the number of images updated.
+#[tauri::command]
+pub fn identify_solar_system_images(
+    state: State<'_, AppState>,
+    image_ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = match image_ids {
+        Some(ids) => {
+            let mut images = Vec::new();
+            for id in ids {
+                if let Some(image) =
+                    repository::get_image_by_id(&mut conn, &id).map_err(|e| e.to_string())?
+                {
+                    images.push(image);
+                }
+            }
+            images
+        }
+        None => {
+            repository::get_images_by_user(&mut conn, &state.user_id).map_err(|e| e.to_string())?
+        }
+    };
+
+    let mut updated = 0;
+    for image in images {
+        let meta = image.metadata.as_deref().unwrap_or("{}");
+        let target = image
+            .summary
+            .clone()
+            .filter(|s| !s.trim().is_empty())
+            .or_else(|| metadata_header_value(meta, "object_name", "OBJECT"));
+        let Some(info) = solar_system_info(
+            target.as_deref(),
+            &image.filename,
+            image.date_obs.as_deref(),
+        ) else {
+            continue;
+        };
+        let update = UpdateImage {
+            metadata: with_solar_system(image.metadata.clone(), &info),
+            summary: target.is_none().then(|| info.body.name().to_string()),
+            ..Default::default()
+        };
+        repository::update_image(&mut conn, &image.id, &update).map_err(|e| e.to_string())?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveDateTime {
+        parse_utc_timestamp(time).unwrap()
+    }
+
+    #[test]
+    fn bodies_are_recognized_by_target_or_file_name() {
+        assert_eq!(identify_body(Some("Jupiter"), "x"), Some(Body::Jupiter));
+        assert_eq!(
+            identify_body(None, "2024-10-05-2200_1-Jupiter"),
+            Some(Body::Jupiter)
+        );
+        assert_eq!(
+            identify_body(Some(" "), "Lunar_mosaic_01"),
+            Some(Body::Moon)
+        );
+        assert_eq!(identify_body(None, "Stacked_Sun_Ha"), Some(Body::Sun));
+        // Deep sky targets named after planets, and targets that aren't
+        // planets whatever the file is called
+        assert_eq!(identify_body(Some("Saturn Nebula"), "x"), None);
+        assert_eq!(identify_body(Some("M 31"), "Moon_and_M31"), None);
+        assert_eq!(identify_body(None, "Light_NGC 7000_10.0s"), None);
+    }
+
+    #[test]
+    fn planets_at_opposition_are_fully_lit_and_large() {
+        // Jupiter's 2023 opposition, 596 million km away
+        let jupiter = ephemeris(Body::Jupiter, at("2023-11-03T05:00:00"));
+        assert!((jupiter.distance_au - 3.98).abs() < 0.02, "{:?}", jupiter);
+        assert!((jupiter.apparent_diameter_arcsec - 49.5).abs() < 0.5);
+        assert!(jupiter.phase_angle.unwrap() < 1.0);
+
+        // Mars's close 2020 opposition
+        let mars = ephemeris(Body::Mars, at("2020-10-13T23:00:00"));
+        assert!(
+            (mars.apparent_diameter_arcsec - 22.3).abs() < 0.4,
+            "{:?}",
+            mars
+        );
+        assert!(mars.illuminated_fraction.unwrap() > 0.99);
+
+        // Venus at inferior conjunction is a thin crescent
+        let venus = ephemeris(Body::Venus, at("2020-06-03T18:00:00"));
+        assert!(venus.illuminated_fraction.unwrap() < 0.01, "{:?}", venus);
+        assert!(venus.apparent_diameter_arcsec > 57.0);
+    }
+
+    #[test]
+    fn moon_phases_and_sun_size_follow_the_calendar() {
+        let full = ephemeris(Body::Moon, at("2024-01-25T17:54:00"));
+        assert!(full.illuminated_fraction.unwrap() > 0.99, "{:?}", full);
+        let new = ephemeris(Body::Moon, at("2024-01-11T11:57:00"));
+        assert!(new.illuminated_fraction.unwrap() < 0.01, "{:?}", new);
+        let quarter = ephemeris(Body::Moon, at("2024-01-18T03:53:00"));
+        assert!((quarter.illuminated_fraction.unwrap() - 0.5).abs() < 0.05);
+        assert!((1_760.0..2_010.0).contains(&full.apparent_diameter_arcsec));
+
+        // The Sun looks biggest at perihelion in early January
+        let sun = ephemeris(Body::Sun, at("2024-01-03T00:00:00"));
+        assert!(
+            (sun.apparent_diameter_arcsec - 1_951.0).abs() < 5.0,
+            "{:?}",
+            sun
+        );
+        assert!(sun.phase_angle.is_none());
+        // ...and sits near RA 18h 50m, Dec -23°
+        assert!((sun.ra - 283.5).abs() < 1.0 && (sun.dec + 22.9).abs() < 0.5);
+    }
+
+    #[test]
+    fn details_are_kept_in_the_metadata() {
+        let info = solar_system_info(None, "Saturn_0001", Some("2024-09-08T04:00:00Z")).unwrap();
+        assert_eq!(info.body, Body::Saturn);
+        assert_eq!(info.observed_at.as_deref(), Some("2024-09-08T04:00:00"));
+        let saturn = info.ephemeris.as_ref().unwrap();
+        assert!(
+            (saturn.apparent_diameter_arcsec - 19.5).abs() < 0.5,
+            "{:?}",
+            saturn
+        );
+
+        let metadata = with_solar_system(Some(r#"{"exposure": 0.01}"#.to_string()), &info);
+        assert!(is_solar_system(metadata.as_deref()));
+        assert!(!is_solar_system(Some(r#"{"exposure": 0.01}"#)));
+        let value: serde_json::Value = serde_json::from_str(&metadata.unwrap()).unwrap();
+        assert_eq!(value["exposure"], 0.01);
+        assert_eq!(value["solar_system"]["body"], "Saturn");
+
+        // Without a capture time there's no ephemeris, but it's still marked
+        let undated = solar_system_info(Some("Moon"), "x", None).unwrap();
+        assert!(undated.ephemeris.is_none());
+    }
+}
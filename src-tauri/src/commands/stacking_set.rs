@@ -0,0 +1,584 @@
+//! Stacking sets: a target's Light frames across nights, with calibration
+//!
+//! Combining several nights of data means finding every usable sub of a
+//! target wherever it was imported, keeping the ones that match (filter,
+//! exposure, camera) and checking there are darks, flats and biases to
+//! calibrate them. A stacking set is that query written down: a manifest of
+//! the frames per acquisition group, recorded in the app data folder or
+//! exported next to the data. Its image ids can be handed straight to
+//! `export_siril_script`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::filters::normalize_filter_name;
+use super::frame_quality;
+use super::frame_rules::{frame_classifier, FrameClassifier, FrameType};
+use super::object_names::ObjectNames;
+use super::scan::{image_session_date, metadata_header_value};
+use super::siril_script::{fits_file, stored_headers};
+use super::subframes::sub_acquisition;
+
+/// Folder under the app data dir holding recorded stacking sets
+const SETS_DIR: &str = "stacking-sets";
+
+/// Which of a target's Light frames go into the set; empty fields match all
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StackingCriteria {
+    /// Filter names, matched after normalizing ("H-alpha" is "Ha")
+    pub filters: Vec<String>,
+    /// Sub exposure in seconds
+    pub exposure: Option<f64>,
+    /// Camera (INSTRUME header), matched case-insensitively
+    pub camera: Option<String>,
+    /// Keep frames the session quality check flagged as clouded
+    pub include_rejected: bool,
+}
+
+/// One frame file in a stacking set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackingFrame {
+    pub image_id: String,
+    pub path: String,
+    /// Canonical filter name
+    pub filter: Option<String>,
+    /// Seconds
+    pub exposure: Option<f64>,
+    pub camera: Option<String>,
+    pub gain: Option<i32>,
+    /// Observing night the frame was taken on
+    pub night: Option<NaiveDate>,
+}
+
+/// Lights taken with one filter, exposure, camera and gain, and the calibration
+/// frames that fit them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackingGroup {
+    pub filter: Option<String>,
+    pub exposure: Option<f64>,
+    pub camera: Option<String>,
+    pub gain: Option<i32>,
+    pub lights: Vec<StackingFrame>,
+    pub total_seconds: f64,
+    /// Nights the lights were taken on, oldest first
+    pub nights: Vec<NaiveDate>,
+    /// Same camera, exposure and (when known) gain
+    pub darks: Vec<StackingFrame>,
+    /// Same camera and filter
+    pub flats: Vec<StackingFrame>,
+    /// Same camera and (when known) gain
+    pub biases: Vec<StackingFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackingSet {
+    /// File stem of a recorded set; empty until it's saved
+    pub id: String,
+    /// Canonical target name
+    pub target: String,
+    pub created_at: String,
+    pub criteria: StackingCriteria,
+    /// Longest total integration first
+    pub groups: Vec<StackingGroup>,
+    pub light_count: usize,
+    pub total_seconds: f64,
+    /// Light frames of the target that didn't match the criteria
+    pub not_matching: usize,
+    /// Lights left out, with the reason
+    pub skipped: Vec<String>,
+    /// Missing calibration and other problems to fix before stacking
+    pub warnings: Vec<String>,
+}
+
+fn stacking_frame(image: &Image, path: &Path) -> StackingFrame {
+    let (filter, exposure) = sub_acquisition(image);
+    let camera = image
+        .metadata
+        .as_deref()
+        .and_then(|meta| metadata_header_value(meta, "instrument", "INSTRUME"));
+    StackingFrame {
+        image_id: image.id.clone(),
+        path: path.to_string_lossy().to_string(),
+        filter,
+        exposure,
+        camera,
+        gain: image.gain,
+        night: image_session_date(image, None),
+    }
+}
+
+/// Exposure in milliseconds, so 10.0 and 10.000001 count as the same
+fn exposure_key(exposure: Option<f64>) -> Option<i64> {
+    exposure
+        .filter(|e| *e > 0.0)
+        .map(|e| (e * 1000.0).round() as i64)
+}
+
+/// Cameras match when either is unknown, so header-less frames aren't lost
+fn same_camera(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+        _ => true,
+    }
+}
+
+fn same_gain(a: Option<i32>, b: Option<i32>) -> bool {
+    a.zip(b).is_none_or(|(a, b)| a == b)
+}
+
+impl StackingCriteria {
+    fn matches(&self, frame: &StackingFrame) -> bool {
+        let filters: Vec<Option<String>> = self
+            .filters
+            .iter()
+            .map(|f| normalize_filter_name(f))
+            .collect();
+        (filters.is_empty() || filters.contains(&frame.filter))
+            && self
+                .exposure
+                .is_none_or(|e| exposure_key(Some(e)) == exposure_key(frame.exposure))
+            && self.camera.as_deref().is_none_or(|camera| {
+                frame
+                    .camera
+                    .as_deref()
+                    .is_some_and(|c| same_camera(Some(camera), Some(c)))
+            })
+    }
+}
+
+/// Why the session quality check rejected a frame, if it did
+fn quality_rejection(image: &Image) -> Option<String> {
+    let meta: serde_json::Value = serde_json::from_str(image.metadata.as_deref()?).ok()?;
+    meta.get(frame_quality::METADATA_KEY)?
+        .get("rejected")?
+        .as_str()
+        .map(String::from)
+}
+
+fn group_label(group: &StackingGroup) -> String {
+    let mut label = group
+        .filter
+        .clone()
+        .unwrap_or_else(|| "no filter".to_string());
+    if let Some(exposure) = group.exposure {
+        label.push_str(&format!(" {}s", exposure));
+    }
+    if let Some(camera) = &group.camera {
+        label.push_str(&format!(" ({})", camera));
+    }
+    label
+}
+
+/// Group matching lights by filter, exposure, camera and gain and pick the
+/// calibration frames for each group. Returns the groups, longest total
+/// integration first, and warnings about missing calibration.
+fn build_groups(
+    lights: Vec<StackingFrame>,
+    calibration: &[(FrameType, StackingFrame)],
+) -> (Vec<StackingGroup>, Vec<String>) {
+    type GroupKey = (Option<String>, Option<i64>, Option<String>, Option<i32>);
+    let mut by_key: BTreeMap<GroupKey, Vec<StackingFrame>> = BTreeMap::new();
+    for light in lights {
+        let camera = light.camera.as_deref().map(|c| c.trim().to_lowercase());
+        let key = (
+            light.filter.clone(),
+            exposure_key(light.exposure),
+            camera,
+            light.gain,
+        );
+        by_key.entry(key).or_default().push(light);
+    }
+
+    let mut groups: Vec<StackingGroup> = by_key
+        .into_values()
+        .map(|mut lights| {
+            lights.sort_by(|a, b| a.night.cmp(&b.night).then_with(|| a.path.cmp(&b.path)));
+            let first = &lights[0];
+            let (filter, camera, gain) = (first.filter.clone(), first.camera.clone(), first.gain);
+            let exposure = exposure_key(first.exposure).map(|ms| ms as f64 / 1000.0);
+            let mut nights: Vec<NaiveDate> = lights.iter().filter_map(|l| l.night).collect();
+            nights.dedup();
+
+            let frames_of = |frame_type: FrameType, fits: &dyn Fn(&StackingFrame) -> bool| {
+                calibration
+                    .iter()
+                    .filter(|(t, frame)| {
+                        *t == frame_type
+                            && same_camera(camera.as_deref(), frame.camera.as_deref())
+                            && fits(frame)
+                    })
+                    .map(|(_, frame)| frame.clone())
+                    .collect::<Vec<_>>()
+            };
+            let darks = frames_of(FrameType::Dark, &|frame| {
+                exposure.is_some()
+                    && exposure_key(frame.exposure) == exposure_key(exposure)
+                    && same_gain(gain, frame.gain)
+            });
+            let flats = frames_of(FrameType::Flat, &|frame| frame.filter == filter);
+            let biases = frames_of(FrameType::Bias, &|frame| same_gain(gain, frame.gain));
+
+            StackingGroup {
+                total_seconds: exposure.unwrap_or(0.0) * lights.len() as f64,
+                filter,
+                exposure,
+                camera,
+                gain,
+                lights,
+                nights,
+                darks,
+                flats,
+                biases,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.total_seconds.total_cmp(&a.total_seconds));
+
+    let mut warnings = Vec::new();
+    for group in &groups {
+        let label = group_label(group);
+        if group.exposure.is_none() {
+            warnings.push(format!(
+                "{}: {} lights have no exposure time, so darks can't be matched",
+                label,
+                group.lights.len()
+            ));
+        } else if group.darks.is_empty() {
+            warnings.push(format!("{}: no matching darks", label));
+        }
+        if group.flats.is_empty() {
+            warnings.push(format!("{}: no matching flats", label));
+        }
+        if group.biases.is_empty() && group.darks.is_empty() {
+            warnings.push(format!("{}: no biases to calibrate the flats", label));
+        }
+    }
+    (groups, warnings)
+}
+
+/// Gather a target's Light frames (raw ones and the subs linked under its
+/// stacks) and the library's calibration frames into a stacking set
+fn gather_stacking_set(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    classifier: &FrameClassifier,
+    target: &str,
+    criteria: StackingCriteria,
+) -> Result<StackingSet, String> {
+    let frame_type = |image: &Image| {
+        let stem = Path::new(&image.filename)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let headers = stored_headers(image.metadata.as_deref());
+        classifier.classify(&stem, Some(&headers))
+    };
+
+    let names = ObjectNames::load(conn).map_err(|e| e.to_string())?;
+    let aliases = names.aliases(target);
+    let images = repository::get_images_by_target_names(conn, user_id, &aliases)
+        .map_err(|e| e.to_string())?;
+
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for image in images.into_iter().filter(|image| image.triage.is_none()) {
+        match frame_type(&image) {
+            FrameType::Light => candidates.push(image),
+            FrameType::Stacked => {
+                let subs =
+                    repository::get_image_subframes(conn, &image.id).map_err(|e| e.to_string())?;
+                candidates.extend(subs.into_iter().filter(|sub| sub.triage.is_none()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut lights = Vec::new();
+    let mut not_matching = 0;
+    let mut skipped = Vec::new();
+    for image in candidates {
+        if !seen.insert(image.id.clone()) {
+            continue;
+        }
+        if !criteria.include_rejected {
+            if let Some(reason) = quality_rejection(&image) {
+                skipped.push(format!("{}: {}", image.filename, reason));
+                continue;
+            }
+        }
+        let Some(path) = fits_file(&image) else {
+            skipped.push(format!("{}: no FITS file on disk", image.filename));
+            continue;
+        };
+        let frame = stacking_frame(&image, &path);
+        if criteria.matches(&frame) {
+            lights.push(frame);
+        } else {
+            not_matching += 1;
+        }
+    }
+    if lights.is_empty() {
+        return Err(format!(
+            "No Light frames of {} match",
+            names.canonical(target)
+        ));
+    }
+
+    let calibration: Vec<(FrameType, StackingFrame)> =
+        repository::get_images_by_user(conn, user_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|image| image.triage.is_none())
+            .filter_map(|image| {
+                let kind = frame_type(&image);
+                if !matches!(kind, FrameType::Dark | FrameType::Flat | FrameType::Bias) {
+                    return None;
+                }
+                let path = fits_file(&image)?;
+                Some((kind, stacking_frame(&image, &path)))
+            })
+            .collect();
+
+    let (groups, warnings) = build_groups(lights, &calibration);
+    Ok(StackingSet {
+        id: String::new(),
+        target: names.canonical(target),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        criteria,
+        light_count: groups.iter().map(|g| g.lights.len()).sum(),
+        total_seconds: groups.iter().map(|g| g.total_seconds).sum(),
+        groups,
+        not_matching,
+        skipped,
+        warnings,
+    })
+}
+
+fn sets_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|d| d.join(SETS_DIR))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// File stem for a recorded set: the target name and when it was built
+fn set_id(target: &str, created_at: &chrono::DateTime<chrono::Utc>) -> String {
+    let slug: String = target
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!(
+        "{}-{}",
+        slug.trim_matches('-'),
+        created_at.format("%Y%m%d-%H%M%S")
+    )
+}
+
+fn write_set(path: &Path, set: &StackingSet) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(set)
+        .map_err(|e| format!("Failed to serialize stacking set: {}", e))?;
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Build a target's stacking set without saving it
+#[tauri::command]
+pub async fn build_stacking_set(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    target: String,
+    criteria: Option<StackingCriteria>,
+) -> Result<StackingSet, String> {
+    let db = state.db.clone();
+    let user_id = state.user_id.clone();
+    let classifier = frame_classifier(&app);
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get().map_err(|e| e.to_string())?;
+        gather_stacking_set(
+            &mut conn,
+            &user_id,
+            &classifier,
+            &target,
+            criteria.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Building stacking set failed: {}", e))?
+}
+
+/// Build a target's stacking set and record it with the app's data, or
+/// export the manifest to `path` when one is given
+#[tauri::command]
+pub async fn save_stacking_set(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    target: String,
+    criteria: Option<StackingCriteria>,
+    path: Option<String>,
+) -> Result<StackingSet, String> {
+    let mut set = build_stacking_set(app.clone(), state, target, criteria).await?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&set.created_at)
+        .map(|d| d.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now());
+    set.id = set_id(&set.target, &created_at);
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = sets_dir(&app)?;
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            dir.join(format!("{}.json", set.id))
+        }
+    };
+    write_set(&path, &set)?;
+    log::info!(
+        "Stacking set for {}: {} lights in {} groups, written to {}",
+        set.target,
+        set.light_count,
+        set.groups.len(),
+        path.display()
+    );
+    Ok(set)
+}
+
+/// Recorded stacking sets, newest first, optionally for one target
+#[tauri::command]
+pub fn get_stacking_sets(
+    app: AppHandle,
+    target: Option<String>,
+) -> Result<Vec<StackingSet>, String> {
+    let Ok(entries) = fs::read_dir(sets_dir(&app)?) else {
+        return Ok(Vec::new());
+    };
+    let mut sets: Vec<StackingSet> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| serde_json::from_str(&fs::read_to_string(path).ok()?).ok())
+        .filter(|set: &StackingSet| target.as_ref().is_none_or(|t| &set.target == t))
+        .collect();
+    sets.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(sets)
+}
+
+/// Remove a recorded stacking set
+#[tauri::command]
+pub fn delete_stacking_set(app: AppHandle, id: String) -> Result<(), String> {
+    if id.is_empty() || id.contains(['/', '\\', '.']) {
+        return Err(format!("Invalid stacking set id: {}", id));
+    }
+    let path = sets_dir(&app)?.join(format!("{}.json", id));
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete stacking set {}: {}", id, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: &str, filter: Option<&str>, exposure: f64, night: &str) -> StackingFrame {
+        StackingFrame {
+            image_id: id.to_string(),
+            path: format!("/data/{}.fit", id),
+            filter: filter.map(String::from),
+            exposure: Some(exposure),
+            camera: Some("ZWO ASI2600MM Pro".to_string()),
+            gain: Some(100),
+            night: NaiveDate::parse_from_str(night, "%Y-%m-%d").ok(),
+        }
+    }
+
+    #[test]
+    fn criteria_match_normalized_filters_exposure_and_camera() {
+        let light = frame("l1", Some("Ha"), 300.0, "2025-01-10");
+        assert!(StackingCriteria::default().matches(&light));
+
+        let criteria = StackingCriteria {
+            filters: vec!["H-alpha".to_string()],
+            exposure: Some(300.000_001),
+            camera: Some("zwo asi2600mm pro".to_string()),
+            include_rejected: false,
+        };
+        assert!(criteria.matches(&light));
+        assert!(!criteria.matches(&frame("l2", Some("OIII"), 300.0, "2025-01-10")));
+        assert!(!criteria.matches(&frame("l3", Some("Ha"), 180.0, "2025-01-10")));
+
+        let mut unknown_camera = light.clone();
+        unknown_camera.camera = None;
+        assert!(!criteria.matches(&unknown_camera));
+    }
+
+    #[test]
+    fn nights_group_with_matching_calibration() {
+        let lights = vec![
+            frame("ha-2", Some("Ha"), 300.0, "2025-01-12"),
+            frame("ha-1", Some("Ha"), 300.0, "2025-01-10"),
+            frame("ha-3", Some("Ha"), 300.0, "2025-01-12"),
+            frame("oiii-1", Some("OIII"), 300.0, "2025-01-11"),
+        ];
+        let mut other_gain = frame("dark-g0", None, 300.0, "2025-01-05");
+        other_gain.gain = Some(0);
+        let calibration = vec![
+            (
+                FrameType::Dark,
+                frame("dark-300", None, 300.0, "2025-01-05"),
+            ),
+            (
+                FrameType::Dark,
+                frame("dark-180", None, 180.0, "2025-01-05"),
+            ),
+            (FrameType::Dark, other_gain),
+            (
+                FrameType::Flat,
+                frame("flat-ha", Some("Ha"), 2.0, "2025-01-10"),
+            ),
+            (FrameType::Bias, frame("bias", None, 0.001, "2025-01-05")),
+        ];
+
+        let (groups, warnings) = build_groups(lights, &calibration);
+        assert_eq!(groups.len(), 2);
+        let ha = &groups[0];
+        assert_eq!(ha.filter.as_deref(), Some("Ha"));
+        assert_eq!(ha.total_seconds, 900.0);
+        let ids: Vec<&str> = ha.lights.iter().map(|l| l.image_id.as_str()).collect();
+        assert_eq!(ids, ["ha-1", "ha-2", "ha-3"]);
+        assert_eq!(ha.nights.len(), 2);
+        assert_eq!(ha.darks.len(), 1);
+        assert_eq!(ha.darks[0].image_id, "dark-300");
+        assert_eq!(ha.flats.len(), 1);
+        assert_eq!(ha.biases.len(), 1);
+
+        let oiii = &groups[1];
+        assert_eq!(oiii.darks.len(), 1);
+        assert!(oiii.flats.is_empty());
+        assert_eq!(
+            warnings,
+            ["OIII 300s (ZWO ASI2600MM Pro): no matching flats"]
+        );
+    }
+
+    #[test]
+    fn recorded_sets_are_named_after_the_target() {
+        let created = chrono::DateTime::parse_from_rfc3339("2025-01-12T21:30:05Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(set_id("NGC 7000", &created), "ngc-7000-20250112-213005");
+        assert_eq!(set_id("M 42 ", &created), "m-42-20250112-213005");
+    }
+}
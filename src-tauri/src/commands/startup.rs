@@ -0,0 +1,181 @@
+//! Background startup
+//!
+//! Opening (and migrating) the database and initializing Python can take
+//! seconds, so they run after the window opens instead of blocking setup.
+//! Progress is emitted as "startup-status"; commands needing the database
+//! fail with "Database is unavailable" until it's ready. A failed step is
+//! reported rather than aborting the app, and can be retried.
+
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db;
+use crate::python;
+use crate::state::{AppState, StartupStatus, StartupStep, StartupStepState};
+
+use super::python_env::saved_python_config;
+
+/// Automatic database backups kept, one per launch
+const AUTO_BACKUPS_KEPT: usize = 5;
+
+/// Change the startup status and tell the frontend
+fn update_status(app: &AppHandle, change: impl FnOnce(&mut StartupStatus)) {
+    let state = app.state::<AppState>();
+    let status = {
+        let mut status = state.startup.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut status);
+        status.clone()
+    };
+    let _ = app.emit("startup-status", &status);
+}
+
+/// Run a step, recording its progress in the status
+fn run_step(
+    app: &AppHandle,
+    step: fn(&mut StartupStatus) -> &mut StartupStep,
+    run: impl FnOnce() -> Result<(), String>,
+) {
+    update_status(app, |status| {
+        *step(status) = StartupStep {
+            state: StartupStepState::Running,
+            error: None,
+        }
+    });
+    let result = run();
+    if let Err(e) = &result {
+        log::error!("Startup step failed: {}", e);
+    }
+    update_status(app, |status| {
+        *step(status) = match &result {
+            Ok(()) => StartupStep {
+                state: StartupStepState::Ready,
+                error: None,
+            },
+            Err(e) => StartupStep {
+                state: StartupStepState::Failed,
+                error: Some(e.clone()),
+            },
+        }
+    });
+}
+
+/// Copy the database into backups/ (keeping the last few) before anything
+/// else touches it
+fn auto_backup(app: &AppHandle, db_path: &Path) {
+    let Ok(backup_dir) = app.path().app_data_dir().map(|d| d.join("backups")) else {
+        return;
+    };
+    if std::fs::create_dir_all(&backup_dir).is_err() || !db_path.exists() {
+        return;
+    }
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let dest = backup_dir.join(format!("astra_auto_{}.db", ts));
+    if let Err(e) = std::fs::copy(db_path, &dest) {
+        log::warn!("Auto-backup failed: {}", e);
+        return;
+    }
+    log::info!("Auto-backup created: {}", dest.display());
+
+    // Prune: keep only the most recent auto-backups
+    if let Ok(entries) = std::fs::read_dir(&backup_dir) {
+        let mut auto_backups: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("astra_auto_") && n.ends_with(".db"))
+            })
+            .collect();
+        auto_backups.sort();
+        auto_backups.reverse();
+        for old in auto_backups.into_iter().skip(AUTO_BACKUPS_KEPT) {
+            let _ = std::fs::remove_file(&old);
+        }
+    }
+}
+
+/// Back up, open and migrate the database, then start what needs it
+fn open_database(app: &AppHandle) -> Result<(), String> {
+    let db_path = db::get_database_path(app)?;
+    auto_backup(app, &db_path);
+    let pool =
+        db::init_database(&db_path).map_err(|e| format!("Failed to initialize database: {}", e))?;
+    app.state::<AppState>().db.install(pool);
+
+    // Commit import batches an earlier session processed but didn't save
+    super::import_journal::replay_pending(app);
+
+    // Start the local HTTP API if the user enabled it
+    super::api_server::start_saved_server(app);
+
+    // Pick up astrometry.net solves submitted in an earlier session
+    super::nova_jobs::resume_pending_solves(app);
+
+    // Evaluate tonight's go/no-go digest each afternoon
+    super::night_digest::start_scheduler(app);
+
+    // Remind about equipment maintenance coming due
+    super::maintenance::start_reminders(app);
+    Ok(())
+}
+
+/// Initialize Python with the path to the astra_astro module
+fn init_python(app: &AppHandle) -> Result<(), String> {
+    // In development, the module is in ../python relative to src-tauri;
+    // in production, it's bundled with the app's resources
+    let python_path = if cfg!(debug_assertions) {
+        Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../python"))
+    } else {
+        app.path().resource_dir().ok().map(|p| p.join("python"))
+    };
+
+    let python_config = saved_python_config(app);
+    match python::init_python(python_path, &python_config).error {
+        Some(e) => Err(format!("Python is not fully available: {}", e)),
+        None => Ok(()),
+    }
+}
+
+/// Run the startup steps that haven't succeeded yet
+fn run_startup(app: &AppHandle) {
+    let status = app.state::<AppState>().startup.lock().unwrap().clone();
+    if status.database.state != StartupStepState::Ready {
+        run_step(app, |s| &mut s.database, || open_database(app));
+    }
+    // Python features don't depend on the database
+    if status.python.state != StartupStepState::Ready {
+        run_step(app, |s| &mut s.python, || init_python(app));
+    }
+}
+
+/// Start initializing in the background; called once the app state is managed
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || run_startup(&app));
+}
+
+/// Where startup has got to
+#[tauri::command]
+pub fn get_startup_status(state: State<'_, AppState>) -> StartupStatus {
+    state.startup.lock().unwrap().clone()
+}
+
+/// Run failed startup steps again, e.g. after fixing the Python environment
+/// or freeing up disk space
+#[tauri::command]
+pub fn retry_startup(app: AppHandle, state: State<'_, AppState>) -> Result<StartupStatus, String> {
+    let status = state.startup.lock().unwrap().clone();
+    let unfinished = |step: &StartupStep| {
+        matches!(
+            step.state,
+            StartupStepState::Pending | StartupStepState::Running
+        )
+    };
+    if unfinished(&status.database) || unfinished(&status.python) {
+        return Err("Startup is still running".to_string());
+    }
+    start(&app);
+    Ok(status)
+}
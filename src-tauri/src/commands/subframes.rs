@@ -0,0 +1,359 @@
+//! Light frames (subs) imported alongside the stacked image they went into
+//!
+//! Seestar keeps the subs for `<target>/Stacked_*.fit` in `<target>_sub/`
+//! (see `scan::get_sub_directory`), which is how subs are matched to a stack.
+//! Linked subs have `parent_image_id` set and are left out of listings.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{Image, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::filters::normalize_filter_name;
+use super::scan::{
+    extract_float_value, get_sub_directory, metadata_header_value, metadata_number_value,
+};
+
+/// Whether a file is a Light frame (`Light_*.fit`)
+fn is_light_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_lowercase().starts_with("light"))
+}
+
+/// Whether a file is a stacked result (`Stacked_*.fit`)
+fn is_stacked_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.to_lowercase().starts_with("stacked"))
+}
+
+/// The file an image was imported from, preferring the FITS file
+pub(super) fn image_path(image: &Image) -> Option<&Path> {
+    image
+        .fits_url
+        .as_deref()
+        .or(image.url.as_deref())
+        .map(Path::new)
+}
+
+/// Light frames among `images` in `sub_dir` or a folder below it
+pub fn find_subframes<'a>(images: &'a [Image], sub_dir: &Path) -> Vec<&'a Image> {
+    images
+        .iter()
+        .filter(|image| {
+            image_path(image).is_some_and(|path| path.starts_with(sub_dir) && is_light_path(path))
+        })
+        .collect()
+}
+
+/// Link Light frames that have no parent yet to the stack whose `_sub`
+/// folder they sit in. A folder with several stacks (one per session) gets
+/// its subs linked to the latest, which covers the most integration.
+/// Returns the number of subs linked.
+pub fn link_subframes(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<usize> {
+    let images = repository::get_images_by_user(conn, user_id)?;
+
+    let mut stacks: HashMap<PathBuf, &Image> = HashMap::new();
+    for image in &images {
+        let Some(sub_dir) = image_path(image)
+            .filter(|path| is_stacked_path(path))
+            .and_then(get_sub_directory)
+        else {
+            continue;
+        };
+        let latest = stacks.entry(sub_dir).or_insert(image);
+        if (&image.date_obs, &image.filename) > (&latest.date_obs, &latest.filename) {
+            *latest = image;
+        }
+    }
+
+    let mut links: HashMap<&str, Vec<String>> = HashMap::new();
+    for image in images.iter().filter(|i| i.parent_image_id.is_none()) {
+        let Some(path) = image_path(image).filter(|path| is_light_path(path)) else {
+            continue;
+        };
+        if let Some(stack) = path.ancestors().skip(1).find_map(|dir| stacks.get(dir)) {
+            links.entry(&stack.id).or_default().push(image.id.clone());
+        }
+    }
+
+    conn.transaction(|conn| {
+        let mut linked = 0;
+        for (parent_id, sub_ids) in &links {
+            linked += repository::set_image_parent(conn, sub_ids, Some(parent_id))?;
+        }
+        Ok(linked)
+    })
+}
+
+/// Canonical filter and exposure time of a sub, from its columns or headers
+pub(super) fn sub_acquisition(image: &Image) -> (Option<String>, Option<f64>) {
+    let meta = image.metadata.as_deref().unwrap_or("{}");
+    let filter = image
+        .filter
+        .clone()
+        .or_else(|| metadata_header_value(meta, "filter", "FILTER"))
+        .and_then(|f| normalize_filter_name(&f));
+    let exposure = image.exposure.or_else(|| {
+        metadata_number_value(meta, "exposure", &["EXPTIME", "EXPOSURE"])
+            .and_then(|v| extract_float_value(&v))
+    });
+    (filter, exposure)
+}
+
+/// Subs taken through one filter at one exposure time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubExposureGroup {
+    /// Canonical filter name; None when the subs had no filter
+    pub filter: Option<String>,
+    /// Seconds per sub
+    pub exposure: f64,
+    pub count: usize,
+    pub total_seconds: f64,
+}
+
+/// Acquisition summary of the subs behind a stacked image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcquisitionBreakdown {
+    /// Longest total integration first
+    pub groups: Vec<SubExposureGroup>,
+    pub sub_count: usize,
+    /// Subs without a known exposure time, left out of the totals
+    pub unknown_exposure: usize,
+    pub total_integration_seconds: f64,
+}
+
+/// Group subs, given as (filter, exposure seconds), by filter and exposure
+pub fn acquisition_breakdown(subs: &[(Option<String>, Option<f64>)]) -> AcquisitionBreakdown {
+    // Exposures are keyed in milliseconds so 10.0 and 10.000001 group together
+    let mut groups: BTreeMap<(Option<String>, i64), usize> = BTreeMap::new();
+    let mut unknown_exposure = 0;
+    for (filter, exposure) in subs {
+        match exposure.filter(|e| *e > 0.0) {
+            Some(exposure) => {
+                let key = (filter.clone(), (exposure * 1000.0).round() as i64);
+                *groups.entry(key).or_insert(0) += 1;
+            }
+            None => unknown_exposure += 1,
+        }
+    }
+
+    let mut groups: Vec<SubExposureGroup> = groups
+        .into_iter()
+        .map(|((filter, millis), count)| {
+            let exposure = millis as f64 / 1000.0;
+            SubExposureGroup {
+                filter,
+                exposure,
+                count,
+                total_seconds: exposure * count as f64,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.total_seconds.total_cmp(&a.total_seconds));
+
+    AcquisitionBreakdown {
+        total_integration_seconds: groups.iter().map(|g| g.total_seconds).sum(),
+        groups,
+        sub_count: subs.len(),
+        unknown_exposure,
+    }
+}
+
+/// Summarize the imported Light frames behind a stacked image (count ×
+/// exposure per filter, total integration) and store it in the image's
+/// metadata as `acquisition_breakdown`
+#[tauri::command]
+pub fn compute_acquisition_breakdown(
+    state: State<'_, AppState>,
+    image_id: String,
+) -> Result<AcquisitionBreakdown, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let image = repository::get_image_by_id(&mut conn, &image_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Image not found".to_string())?;
+    let sub_dir = image_path(&image)
+        .and_then(get_sub_directory)
+        .ok_or_else(|| "Image has no source file".to_string())?;
+
+    let images =
+        repository::get_images_by_user(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    let subs: Vec<_> = find_subframes(&images, &sub_dir)
+        .into_iter()
+        .filter(|sub| sub.id != image.id)
+        .map(sub_acquisition)
+        .collect();
+    if subs.is_empty() {
+        return Err(format!(
+            "No imported Light frames found in {}",
+            sub_dir.display()
+        ));
+    }
+    let breakdown = acquisition_breakdown(&subs);
+
+    let mut metadata = image
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|m| m.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    metadata["acquisition_breakdown"] =
+        serde_json::to_value(&breakdown).map_err(|e| e.to_string())?;
+    let update = UpdateImage {
+        metadata: Some(metadata.to_string()),
+        ..Default::default()
+    };
+    repository::update_image(&mut conn, &image_id, &update).map_err(|e| e.to_string())?;
+
+    log::info!(
+        "Acquisition breakdown for {}: {} subs, {:.0}s integration",
+        image.filename,
+        breakdown.sub_count,
+        breakdown.total_integration_seconds
+    );
+    Ok(breakdown)
+}
+
+/// Light frames linked under a stacked image, in capture order
+#[tauri::command]
+pub fn get_image_subframes(state: State<'_, AppState>, id: String) -> Result<Vec<Image>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_image_subframes(&mut conn, &id).map_err(|e| e.to_string())
+}
+
+/// Link the Light frames already in the library to their stacked images.
+/// Bulk scans do this as they import; returns the number of subs linked.
+#[tauri::command]
+pub fn link_image_subframes(state: State<'_, AppState>) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let linked = link_subframes(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    log::info!("Linked {} subframes to their stacked images", linked);
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::NewImage;
+
+    const USER: &str = "local-user";
+
+    fn add_image(conn: &mut SqliteConnection, id: &str, fits_path: &str, date_obs: &str) {
+        repository::create_image(
+            conn,
+            &NewImage {
+                user_id: USER.to_string(),
+                filename: id.to_string(),
+                summary: Some("M 42".to_string()),
+                fits_url: Some(fits_path.to_string()),
+                exposure: Some(10.0),
+                date_obs: Some(date_obs.to_string()),
+                ..NewImage::test(id)
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn light_frames_are_recognized_by_name() {
+        assert!(is_light_path(Path::new(
+            "/data/M 42_sub/Light_M 42_10.0s_IRCUT_20250115-203012.fit"
+        )));
+        assert!(!is_light_path(Path::new("/data/M 42/Stacked_210_M 42.fit")));
+    }
+
+    #[test]
+    fn breakdown_groups_by_filter_and_exposure() {
+        let sub =
+            |filter: Option<&str>, exposure: Option<f64>| (filter.map(String::from), exposure);
+        let mut subs = vec![sub(Some("Ha"), Some(300.0)); 4];
+        subs.extend(vec![sub(None, Some(10.0)); 30]);
+        subs.push(sub(None, Some(10.000_000_1)));
+        subs.push(sub(Some("Ha"), None));
+
+        let breakdown = acquisition_breakdown(&subs);
+        assert_eq!(breakdown.sub_count, 36);
+        assert_eq!(breakdown.unknown_exposure, 1);
+        assert_eq!(breakdown.total_integration_seconds, 1510.0);
+        assert_eq!(
+            breakdown.groups,
+            vec![
+                SubExposureGroup {
+                    filter: Some("Ha".to_string()),
+                    exposure: 300.0,
+                    count: 4,
+                    total_seconds: 1200.0,
+                },
+                SubExposureGroup {
+                    filter: None,
+                    exposure: 10.0,
+                    count: 31,
+                    total_seconds: 310.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn subs_link_to_the_latest_stack_in_their_folder() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        add_image(
+            &mut conn,
+            "old",
+            "/data/M 42/Stacked_30_M 42.fit",
+            "2025-01-10T21:00:00",
+        );
+        add_image(
+            &mut conn,
+            "new",
+            "/data/M 42/Stacked_90_M 42.fit",
+            "2025-01-15T21:00:00",
+        );
+        add_image(
+            &mut conn,
+            "l1",
+            "/data/M 42_sub/Light_M 42_1.fit",
+            "2025-01-15T20:00:00",
+        );
+        add_image(
+            &mut conn,
+            "l2",
+            "/data/M 42_sub/Light_M 42_2.fit",
+            "2025-01-15T20:01:00",
+        );
+        add_image(
+            &mut conn,
+            "m31",
+            "/data/M 31_sub/Light_M 31_1.fit",
+            "2025-01-15T22:00:00",
+        );
+
+        assert_eq!(link_subframes(&mut conn, USER).unwrap(), 2);
+        assert_eq!(link_subframes(&mut conn, USER).unwrap(), 0);
+        let sub_ids = |conn: &mut SqliteConnection, id: &str| -> Vec<String> {
+            repository::get_image_subframes(conn, id)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.id)
+                .collect()
+        };
+        assert_eq!(sub_ids(&mut conn, "new"), ["l1", "l2"]);
+        assert!(sub_ids(&mut conn, "old").is_empty());
+
+        // Deleting the stack lists its subs on their own again
+        repository::delete_image(&mut conn, "new").unwrap();
+        let sub = repository::get_image_by_id(&mut conn, "l1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sub.parent_image_id, None);
+    }
+}
@@ -0,0 +1,613 @@
+//! Sync between machines through a shared folder (e.g. a synced drive or NAS)
+//!
+//! Every insert, update, and delete is recorded in `sync_state` by triggers.
+//! Exporting writes the local changes since the last export to a JSON change
+//! bundle in the shared folder; importing merges bundles written by other
+//! devices, resolving conflicts per row with last-writer-wins.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::models::{NewSyncImport, SyncImport, SyncState};
+use crate::db::repository;
+use crate::state::AppState;
+
+/// Synced tables and their primary keys, parents before children
+const SYNC_TABLES: &[(&str, &str)] = &[
+    ("users", "id"),
+    ("astro_objects", "id"),
+    ("collections", "id"),
+    ("equipment", "id"),
+    ("equipment_profiles", "id"),
+    ("processing_presets", "id"),
+    ("saved_searches", "id"),
+    ("pinned_targets", "id"),
+    ("locations", "id"),
+    ("filters", "id"),
+    ("images", "id"),
+    ("collection_images", "id"),
+    ("image_equipment", "id"),
+    ("image_filters", "image_id"),
+    ("astronomy_todos", "id"),
+    ("observation_schedules", "id"),
+    ("notes", "id"),
+    ("maintenance_entries", "id"),
+    ("packing_items", "id"),
+    ("ui_preferences", "user_id"),
+];
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const BUNDLE_PREFIX: &str = "astra-sync-";
+/// Device id lives in the app data dir, not the database, so a database
+/// copied or restored onto another machine does not share its identity
+const DEVICE_ID_FILE: &str = "sync-device-id";
+const LAST_EXPORT_KEY: &str = "last_export_version";
+
+/// One row's change vector and, unless deleted, its contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChange {
+    pub table: String,
+    pub row_id: String,
+    pub updated_at: String,
+    pub deleted: bool,
+    pub row: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncBundle {
+    pub format_version: u32,
+    pub bundle_id: String,
+    pub device_id: String,
+    pub created_at: String,
+    pub changes: Vec<SyncChange>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncApplySummary {
+    pub applied: usize,
+    /// Changes older than the local copy of the row
+    pub skipped: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncExportResult {
+    /// None when there was nothing to export
+    pub bundle_path: Option<String>,
+    pub changes: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncImportResult {
+    pub bundles_imported: usize,
+    pub applied: usize,
+    pub skipped: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub device_id: String,
+    /// Local changes not yet exported
+    pub pending_changes: usize,
+    pub imported_bundles: Vec<SyncImport>,
+}
+
+#[derive(QueryableByName)]
+struct ColumnInfo {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct JsonRow {
+    #[diesel(sql_type = Text)]
+    row: String,
+}
+
+fn primary_key(table: &str) -> Option<&'static str> {
+    SYNC_TABLES
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, pk)| *pk)
+}
+
+fn table_columns(conn: &mut SqliteConnection, table: &str) -> QueryResult<Vec<String>> {
+    let columns: Vec<ColumnInfo> =
+        diesel::sql_query(format!("PRAGMA table_info(\"{}\")", table)).load(conn)?;
+    Ok(columns.into_iter().map(|c| c.name).collect())
+}
+
+/// Read a row as a JSON object of column values
+fn read_row(
+    conn: &mut SqliteConnection,
+    table: &str,
+    pk: &str,
+    id: &str,
+) -> QueryResult<Option<Map<String, Value>>> {
+    let fields: Vec<String> = table_columns(conn, table)?
+        .iter()
+        .map(|c| format!("'{0}', \"{0}\"", c))
+        .collect();
+    let query = format!(
+        "SELECT json_object({}) AS row FROM \"{}\" WHERE \"{}\" = ?",
+        fields.join(", "),
+        table,
+        pk
+    );
+    let row: Option<JsonRow> = diesel::sql_query(query)
+        .bind::<Text, _>(id)
+        .get_result(conn)
+        .optional()?;
+
+    Ok(row
+        .and_then(|r| serde_json::from_str::<Value>(&r.row).ok())
+        .and_then(|v| match v {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }))
+}
+
+/// Insert or update a row from its JSON object. Only columns present both
+/// locally and in the row are written, so bundles from a device on an older
+/// schema leave newer columns untouched.
+fn write_row(
+    conn: &mut SqliteConnection,
+    table: &str,
+    pk: &str,
+    row: &Map<String, Value>,
+) -> QueryResult<()> {
+    let columns: Vec<String> = table_columns(conn, table)?
+        .into_iter()
+        .filter(|c| row.contains_key(c))
+        .collect();
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| format!("json_extract(?1, '$.\"{}\"')", c))
+        .collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|c| c.as_str() != pk)
+        .map(|c| format!("\"{0}\" = excluded.\"{0}\"", c))
+        .collect();
+    let on_conflict = if updates.is_empty() {
+        "DO NOTHING".to_string()
+    } else {
+        format!("DO UPDATE SET {}", updates.join(", "))
+    };
+    let query = format!(
+        "INSERT INTO \"{}\" ({}) VALUES ({}) ON CONFLICT(\"{}\") {}",
+        table,
+        columns
+            .iter()
+            .map(|c| format!("\"{}\"", c))
+            .collect::<Vec<_>>()
+            .join(", "),
+        values.join(", "),
+        pk,
+        on_conflict
+    );
+
+    let json = Value::Object(row.clone()).to_string();
+    diesel::sql_query(query)
+        .bind::<Text, _>(json)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Collect local changes recorded after `after_version`
+pub fn collect_changes(
+    conn: &mut SqliteConnection,
+    after_version: i64,
+) -> QueryResult<Vec<SyncChange>> {
+    let mut changes = Vec::new();
+    for state in repository::get_local_sync_changes(conn, after_version)? {
+        let Some(pk) = primary_key(&state.table_name) else {
+            continue;
+        };
+        let row = if state.deleted {
+            None
+        } else {
+            read_row(conn, &state.table_name, pk, &state.row_id)?
+        };
+        changes.push(SyncChange {
+            deleted: row.is_none(),
+            table: state.table_name,
+            row_id: state.row_id,
+            updated_at: state.updated_at,
+            row,
+        });
+    }
+    Ok(changes)
+}
+
+/// Last-writer-wins: the later change wins; ties go to the higher device id
+/// so both machines settle on the same row.
+fn incoming_wins(
+    change: &SyncChange,
+    remote_device: &str,
+    local: Option<&SyncState>,
+    local_device: &str,
+) -> bool {
+    let Some(local) = local else {
+        return true;
+    };
+    match change.updated_at.cmp(&local.updated_at) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => remote_device > local.origin.as_deref().unwrap_or(local_device),
+    }
+}
+
+/// Merge changes from `remote_device` into the database
+pub fn apply_changes(
+    conn: &mut SqliteConnection,
+    local_device: &str,
+    remote_device: &str,
+    changes: &[SyncChange],
+) -> QueryResult<SyncApplySummary> {
+    let table_order = |table: &str| SYNC_TABLES.iter().position(|(name, _)| *name == table);
+
+    // Upserts parents first, deletes children first
+    let mut ordered: Vec<&SyncChange> = changes
+        .iter()
+        .filter(|c| table_order(&c.table).is_some())
+        .collect();
+    ordered.sort_by_key(|c| {
+        let order = table_order(&c.table).unwrap_or_default() as i64;
+        (c.deleted, if c.deleted { -order } else { order })
+    });
+
+    let mut summary = SyncApplySummary::default();
+    for change in ordered {
+        let Some(pk) = primary_key(&change.table) else {
+            continue;
+        };
+        let local = repository::get_sync_state(conn, &change.table, &change.row_id)?;
+        if !incoming_wins(change, remote_device, local.as_ref(), local_device) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        match (&change.row, change.deleted) {
+            (Some(row), false) => write_row(conn, &change.table, pk, row)?,
+            _ => {
+                diesel::sql_query(format!(
+                    "DELETE FROM \"{}\" WHERE \"{}\" = ?",
+                    change.table, pk
+                ))
+                .bind::<Text, _>(&change.row_id)
+                .execute(conn)?;
+            }
+        }
+        repository::set_remote_sync_state(
+            conn,
+            &change.table,
+            &change.row_id,
+            &change.updated_at,
+            change.deleted,
+            remote_device,
+        )?;
+        summary.applied += 1;
+    }
+    Ok(summary)
+}
+
+fn last_export_version(conn: &mut SqliteConnection) -> QueryResult<i64> {
+    // Rows seeded by the sync migration have version 0, so a first export
+    // includes everything
+    Ok(repository::get_sync_meta(conn, LAST_EXPORT_KEY)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(-1))
+}
+
+/// Build a bundle of the changes since the last export and mark them exported
+pub fn export_changes(
+    conn: &mut SqliteConnection,
+    device_id: &str,
+) -> QueryResult<Option<SyncBundle>> {
+    conn.transaction(|conn| {
+        let after = last_export_version(conn)?;
+        let max_version = repository::get_max_sync_version(conn)?;
+        let changes = collect_changes(conn, after)?;
+        repository::set_sync_meta(conn, LAST_EXPORT_KEY, &max_version.to_string())?;
+
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(SyncBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            bundle_id: uuid::Uuid::new_v4().to_string(),
+            device_id: device_id.to_string(),
+            created_at: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            changes,
+        }))
+    })
+}
+
+/// Import bundles from `folder` written by other devices and not yet merged
+pub fn import_folder(
+    conn: &mut SqliteConnection,
+    device_id: &str,
+    folder: &Path,
+) -> Result<SyncImportResult, String> {
+    let entries = fs::read_dir(folder).map_err(|e| format!("Failed to read sync folder: {}", e))?;
+
+    let mut bundles = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(BUNDLE_PREFIX) || !name.ends_with(".json") {
+            continue;
+        }
+        let bundle: SyncBundle = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+        {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                log::warn!("Skipping unreadable sync bundle {:?}: {}", path, e);
+                continue;
+            }
+        };
+        if bundle.device_id == device_id {
+            continue;
+        }
+        if bundle.format_version > BUNDLE_FORMAT_VERSION {
+            log::warn!(
+                "Skipping sync bundle {:?} from a newer version of Astra",
+                path
+            );
+            continue;
+        }
+        bundles.push(bundle);
+    }
+    bundles.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    let mut result = SyncImportResult::default();
+    for bundle in bundles {
+        let summary = conn
+            .transaction(|conn| {
+                if repository::is_sync_bundle_imported(conn, &bundle.bundle_id)? {
+                    return Ok(None);
+                }
+                let summary = apply_changes(conn, device_id, &bundle.device_id, &bundle.changes)?;
+                repository::record_sync_import(
+                    conn,
+                    &NewSyncImport {
+                        bundle_id: bundle.bundle_id.clone(),
+                        device_id: bundle.device_id.clone(),
+                        change_count: bundle.changes.len() as i32,
+                    },
+                )?;
+                QueryResult::Ok(Some(summary))
+            })
+            .map_err(|e| format!("Failed to import sync bundle {}: {}", bundle.bundle_id, e))?;
+
+        if let Some(summary) = summary {
+            result.bundles_imported += 1;
+            result.applied += summary.applied;
+            result.skipped += summary.skipped;
+        }
+    }
+
+    result.message = format!(
+        "Imported {} bundles: {} changes applied, {} older changes skipped",
+        result.bundles_imported, result.applied, result.skipped
+    );
+    Ok(result)
+}
+
+fn get_device_id(app: &AppHandle) -> Result<String, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let path = data_dir.join(DEVICE_ID_FILE);
+
+    if let Ok(id) = fs::read_to_string(&path) {
+        let id = id.trim().to_string();
+        if !id.is_empty() {
+            return Ok(id);
+        }
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    fs::write(&path, &id).map_err(|e| format!("Failed to save sync device id: {}", e))?;
+    Ok(id)
+}
+
+fn export_to_folder(
+    conn: &mut SqliteConnection,
+    device_id: &str,
+    folder: &Path,
+) -> Result<SyncExportResult, String> {
+    fs::create_dir_all(folder).map_err(|e| format!("Failed to create sync folder: {}", e))?;
+
+    let Some(bundle) =
+        export_changes(conn, device_id).map_err(|e| format!("Failed to collect changes: {}", e))?
+    else {
+        return Ok(SyncExportResult {
+            bundle_path: None,
+            changes: 0,
+            message: "No changes to export".to_string(),
+        });
+    };
+
+    let path: PathBuf = folder.join(format!(
+        "{}{}-{}.json",
+        BUNDLE_PREFIX,
+        device_id,
+        Utc::now().format("%Y%m%d_%H%M%S")
+    ));
+    let data = serde_json::to_string(&bundle)
+        .map_err(|e| format!("Failed to serialize sync bundle: {}", e))?;
+    fs::write(&path, data).map_err(|e| format!("Failed to write sync bundle: {}", e))?;
+
+    Ok(SyncExportResult {
+        bundle_path: Some(path.to_string_lossy().to_string()),
+        changes: bundle.changes.len(),
+        message: format!("Exported {} changes", bundle.changes.len()),
+    })
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Get this device's sync id, pending local changes, and imported bundles
+#[tauri::command]
+pub fn get_sync_status(app: AppHandle, state: State<'_, AppState>) -> Result<SyncStatus, String> {
+    let device_id = get_device_id(&app)?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let after = last_export_version(&mut conn).map_err(|e| e.to_string())?;
+    let pending_changes = repository::get_local_sync_changes(&mut conn, after)
+        .map_err(|e| e.to_string())?
+        .len();
+    let imported_bundles = repository::get_sync_imports(&mut conn).map_err(|e| e.to_string())?;
+
+    Ok(SyncStatus {
+        device_id,
+        pending_changes,
+        imported_bundles,
+    })
+}
+
+/// Write local changes since the last export to a bundle in `folder`
+#[tauri::command]
+pub fn export_sync_changes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    folder: String,
+) -> Result<SyncExportResult, String> {
+    let device_id = get_device_id(&app)?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    export_to_folder(&mut conn, &device_id, Path::new(&folder))
+}
+
+/// Merge change bundles from other devices found in `folder`
+#[tauri::command]
+pub fn import_sync_changes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    folder: String,
+) -> Result<SyncImportResult, String> {
+    let device_id = get_device_id(&app)?;
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    import_folder(&mut conn, &device_id, Path::new(&folder))
+}
+
+/// Import bundles from other devices, then export local changes
+#[tauri::command]
+pub fn sync_folder(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    folder: String,
+) -> Result<SyncImportResult, String> {
+    let device_id = get_device_id(&app)?;
+    let folder = PathBuf::from(folder);
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let mut result = import_folder(&mut conn, &device_id, &folder)?;
+    let export = export_to_folder(&mut conn, &device_id, &folder)?;
+    result.message = format!("{}; {}", result.message, export.message);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_db() -> SqliteConnection {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    fn insert_collection(conn: &mut SqliteConnection, id: &str, name: &str) {
+        diesel::sql_query(format!(
+            "INSERT INTO collections (id, user_id, name) VALUES ('{}', 'local-user', '{}')",
+            id, name
+        ))
+        .execute(conn)
+        .unwrap();
+    }
+
+    fn collection_name(conn: &mut SqliteConnection, id: &str) -> Option<String> {
+        repository::get_collection_by_id(conn, id)
+            .unwrap()
+            .map(|c| c.name)
+    }
+
+    /// Export from `src` and import into `dst`
+    fn sync(src: &mut SqliteConnection, src_id: &str, dst: &mut SqliteConnection, dst_id: &str) {
+        if let Some(bundle) = export_changes(src, src_id).unwrap() {
+            apply_changes(dst, dst_id, src_id, &bundle.changes).unwrap();
+        }
+    }
+
+    #[test]
+    fn changes_and_tombstones_propagate() {
+        let (mut desktop, mut laptop) = (open_db(), open_db());
+        insert_collection(&mut desktop, "c1", "M31");
+        sync(&mut desktop, "desktop", &mut laptop, "laptop");
+        assert_eq!(collection_name(&mut laptop, "c1").as_deref(), Some("M31"));
+
+        // Applied remote changes are not echoed back
+        let echo = export_changes(&mut laptop, "laptop").unwrap().unwrap();
+        assert!(echo.changes.iter().all(|c| c.row_id != "c1"));
+
+        diesel::sql_query("DELETE FROM collections WHERE id = 'c1'")
+            .execute(&mut desktop)
+            .unwrap();
+        sync(&mut desktop, "desktop", &mut laptop, "laptop");
+        assert_eq!(collection_name(&mut laptop, "c1"), None);
+        assert!(export_changes(&mut desktop, "desktop").unwrap().is_none());
+    }
+
+    #[test]
+    fn last_writer_wins() {
+        let (mut desktop, mut laptop) = (open_db(), open_db());
+        insert_collection(&mut desktop, "c1", "M31");
+        sync(&mut desktop, "desktop", &mut laptop, "laptop");
+
+        diesel::sql_query("UPDATE collections SET name = 'Desktop' WHERE id = 'c1'")
+            .execute(&mut desktop)
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        diesel::sql_query("UPDATE collections SET name = 'Laptop' WHERE id = 'c1'")
+            .execute(&mut laptop)
+            .unwrap();
+
+        let from_desktop = export_changes(&mut desktop, "desktop").unwrap().unwrap();
+        let from_laptop = export_changes(&mut laptop, "laptop").unwrap().unwrap();
+        let skipped =
+            apply_changes(&mut laptop, "laptop", "desktop", &from_desktop.changes).unwrap();
+        apply_changes(&mut desktop, "desktop", "laptop", &from_laptop.changes).unwrap();
+
+        assert!(skipped.skipped >= 1);
+        assert_eq!(
+            collection_name(&mut desktop, "c1").as_deref(),
+            Some("Laptop")
+        );
+        assert_eq!(
+            collection_name(&mut laptop, "c1").as_deref(),
+            Some("Laptop")
+        );
+    }
+}
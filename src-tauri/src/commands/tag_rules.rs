@@ -0,0 +1,430 @@
+//! Auto-tagging rules: tags assigned from image metadata
+//!
+//! Each rule tests one metadata value (exposure ≥ 300s, FILTER equals "Ha",
+//! telescope contains "Seestar"...) and adds its tag when it matches. Rules
+//! run on every import and can be applied to images already in the library;
+//! they only ever add tags, so tags typed by hand are left alone.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::models::{Image, UpdateImage};
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::frame_rules::{frame_classifier, FrameClassifier, FrameType};
+use super::scan::{extract_int_value, metadata_header_value, metadata_number_value, FitsMetadata};
+use super::settings::{load_settings, load_settings_in, save_settings, TAG_RULES};
+use super::siril_script::stored_headers;
+
+/// Metadata value a rule tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TagField {
+    /// OBJECT (the canonical target name)
+    Object,
+    /// Exposure in seconds
+    Exposure,
+    Gain,
+    Filter,
+    Telescope,
+    Instrument,
+    /// Number of frames in a stack
+    StackedFrames,
+    /// "stacked", "light", "dark", "flat", "bias" or "unknown"
+    FrameType,
+}
+
+impl TagField {
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            TagField::Exposure | TagField::Gain | TagField::StackedFrames
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TagMatch {
+    /// Same text (ignoring case) or the same number
+    Equals,
+    /// Text contains the value, ignoring case
+    Contains,
+    /// Number is at least the value
+    AtLeast,
+    /// Number is at most the value
+    AtMost,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRule {
+    pub tag: String,
+    pub field: TagField,
+    #[serde(rename = "match")]
+    pub matches: TagMatch,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TagRules {
+    /// Tag images at import; applying rules on demand works either way
+    pub enabled: bool,
+    pub rules: Vec<TagRule>,
+}
+
+impl Default for TagRules {
+    fn default() -> Self {
+        TagRules {
+            enabled: true,
+            rules: vec![
+                TagRule {
+                    tag: "stacked".to_string(),
+                    field: TagField::FrameType,
+                    matches: TagMatch::Equals,
+                    value: "stacked".to_string(),
+                },
+                TagRule {
+                    tag: "seestar".to_string(),
+                    field: TagField::Telescope,
+                    matches: TagMatch::Contains,
+                    value: "seestar".to_string(),
+                },
+            ],
+        }
+    }
+}
+
+/// The values rules are tested against
+#[derive(Debug, Clone)]
+pub struct TagFacts {
+    pub object: Option<String>,
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    pub instrument: Option<String>,
+    pub stacked_frames: Option<i32>,
+    pub frame_type: FrameType,
+}
+
+impl TagFacts {
+    /// Facts for a frame being imported
+    pub fn from_metadata(metadata: &FitsMetadata, frame_type: FrameType) -> Self {
+        TagFacts {
+            object: metadata.object_name.clone(),
+            exposure: metadata.exposure,
+            gain: metadata.gain,
+            filter: metadata.filter.clone(),
+            telescope: metadata.telescope.clone(),
+            instrument: metadata.instrument.clone(),
+            stacked_frames: metadata.stacked_frames,
+            frame_type,
+        }
+    }
+
+    /// Facts for an image already in the library, from its columns and
+    /// stored headers
+    pub fn from_image(image: &Image, classifier: &FrameClassifier) -> Self {
+        let metadata = image.metadata.as_deref().unwrap_or("{}");
+        TagFacts {
+            object: image.summary.clone(),
+            exposure: image.exposure,
+            gain: image.gain,
+            filter: image.filter.clone(),
+            telescope: image.telescope.clone(),
+            instrument: metadata_header_value(metadata, "instrument", "INSTRUME"),
+            stacked_frames: metadata_number_value(
+                metadata,
+                "stacked_frames",
+                &["STACKCNT", "NCOMBINE"],
+            )
+            .and_then(|v| extract_int_value(&v)),
+            frame_type: classifier.classify(
+                file_stem(&image.filename),
+                Some(&stored_headers(image.metadata.as_deref())),
+            ),
+        }
+    }
+
+    fn text(&self, field: TagField) -> Option<String> {
+        let text = match field {
+            TagField::Object => self.object.clone(),
+            TagField::Filter => self.filter.clone(),
+            TagField::Telescope => self.telescope.clone(),
+            TagField::Instrument => self.instrument.clone(),
+            TagField::FrameType => Some(frame_type_name(self.frame_type).to_string()),
+            TagField::Exposure | TagField::Gain | TagField::StackedFrames => None,
+        };
+        text.map(|t| t.trim().to_lowercase())
+    }
+
+    fn number(&self, field: TagField) -> Option<f64> {
+        match field {
+            TagField::Exposure => self.exposure,
+            TagField::Gain => self.gain.map(f64::from),
+            TagField::StackedFrames => self.stacked_frames.map(f64::from),
+            _ => None,
+        }
+    }
+}
+
+fn frame_type_name(frame_type: FrameType) -> &'static str {
+    match frame_type {
+        FrameType::Stacked => "stacked",
+        FrameType::Light => "light",
+        FrameType::Dark => "dark",
+        FrameType::Flat => "flat",
+        FrameType::Bias => "bias",
+        FrameType::Unknown => "unknown",
+    }
+}
+
+/// Image file name without an image extension (scans store names without one)
+fn file_stem(filename: &str) -> &str {
+    match filename.rsplit_once('.') {
+        Some((stem, ext))
+            if ["fit", "fits", "fts", "jpg", "jpeg", "png", "tif", "tiff"]
+                .contains(&ext.to_lowercase().as_str()) =>
+        {
+            stem
+        }
+        _ => filename,
+    }
+}
+
+impl TagRule {
+    fn matches_facts(&self, facts: &TagFacts) -> bool {
+        if self.field.is_numeric() {
+            let (Some(actual), Ok(wanted)) =
+                (facts.number(self.field), self.value.trim().parse::<f64>())
+            else {
+                return false;
+            };
+            return match self.matches {
+                TagMatch::Equals => actual == wanted,
+                TagMatch::AtLeast => actual >= wanted,
+                TagMatch::AtMost => actual <= wanted,
+                TagMatch::Contains => false,
+            };
+        }
+
+        let Some(actual) = facts.text(self.field) else {
+            return false;
+        };
+        let wanted = self.value.trim().to_lowercase();
+        match self.matches {
+            TagMatch::Equals => actual == wanted,
+            TagMatch::Contains => !wanted.is_empty() && actual.contains(&wanted),
+            TagMatch::AtLeast | TagMatch::AtMost => false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let tag = self.tag.trim();
+        if tag.is_empty() || tag.contains(',') {
+            return Err(format!("Invalid tag '{}'", self.tag));
+        }
+        if self.field.is_numeric() {
+            if self.matches == TagMatch::Contains {
+                return Err(format!("Rule for '{}' compares a number", tag));
+            }
+            if self.value.trim().parse::<f64>().is_err() {
+                return Err(format!(
+                    "Rule for '{}' needs a number, not '{}'",
+                    tag, self.value
+                ));
+            }
+        } else if matches!(self.matches, TagMatch::AtLeast | TagMatch::AtMost) {
+            return Err(format!("Rule for '{}' compares text", tag));
+        }
+        Ok(())
+    }
+}
+
+/// Tags of every rule matching `facts`, in rule order
+pub fn rule_tags(rules: &TagRules, facts: &TagFacts) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for rule in rules.rules.iter().filter(|r| r.matches_facts(facts)) {
+        let tag = rule.tag.trim();
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Add tags to a comma-separated tag list, skipping ones already there
+pub fn merge_tags(existing: Option<&str>, tags: &[String]) -> Option<String> {
+    let mut merged: Vec<String> = existing
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    for tag in tags {
+        if !merged.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            merged.push(tag.clone());
+        }
+    }
+    (!merged.is_empty()).then(|| merged.join(", "))
+}
+
+/// Saved tag rules, or the defaults
+pub fn tag_rules(app: &AppHandle) -> TagRules {
+    load_settings(app, &TAG_RULES).unwrap_or_default()
+}
+
+/// Tag rules saved in `data_dir`, for running without the app
+pub(crate) fn tag_rules_in(data_dir: &Path) -> TagRules {
+    load_settings_in(data_dir, &TAG_RULES)
+}
+
+#[tauri::command]
+pub fn get_tag_rules(app: AppHandle) -> Result<TagRules, String> {
+    load_settings(&app, &TAG_RULES)
+}
+
+/// Save tag rules; None restores the defaults
+#[tauri::command]
+pub fn set_tag_rules(app: AppHandle, rules: Option<TagRules>) -> Result<TagRules, String> {
+    let rules = rules.unwrap_or_default();
+    for rule in &rules.rules {
+        rule.validate()?;
+    }
+
+    save_settings(&app, &TAG_RULES, &rules)?;
+    Ok(rules)
+}
+
+/// Apply the saved rules to images already in the library (all of them when
+/// `ids` is None). Returns how many images gained tags.
+#[tauri::command]
+pub fn apply_tag_rules(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let rules = tag_rules(&app);
+    let classifier = frame_classifier(&app);
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let images = match ids {
+        Some(ids) => {
+            let mut images = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(image) =
+                    repository::get_image_by_id(&mut conn, &id).map_err(|e| e.to_string())?
+                {
+                    images.push(image);
+                }
+            }
+            images
+        }
+        None => {
+            repository::get_images_by_user(&mut conn, &state.user_id).map_err(|e| e.to_string())?
+        }
+    };
+
+    let mut tagged = 0;
+    for image in images.iter().filter(|i| i.user_id == state.user_id) {
+        let tags = rule_tags(&rules, &TagFacts::from_image(image, &classifier));
+        let merged = merge_tags(image.tags.as_deref(), &tags);
+        if merged == image.tags {
+            continue;
+        }
+        let update = UpdateImage {
+            tags: merged,
+            ..Default::default()
+        };
+        repository::update_image(&mut conn, &image.id, &update).map_err(|e| e.to_string())?;
+        tagged += 1;
+    }
+    Ok(tagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts(frame_type: FrameType) -> TagFacts {
+        TagFacts::from_metadata(
+            &FitsMetadata {
+                object_name: Some("M 42".to_string()),
+                exposure: Some(300.0),
+                filter: Some("Ha".to_string()),
+                telescope: Some("Seestar S50".to_string()),
+                ..Default::default()
+            },
+            frame_type,
+        )
+    }
+
+    fn rule(tag: &str, field: TagField, matches: TagMatch, value: &str) -> TagRule {
+        TagRule {
+            tag: tag.to_string(),
+            field,
+            matches,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn default_rules_tag_stacks_and_seestar() {
+        let rules = TagRules::default();
+        assert_eq!(
+            rule_tags(&rules, &facts(FrameType::Stacked)),
+            vec!["stacked", "seestar"]
+        );
+        assert_eq!(rule_tags(&rules, &facts(FrameType::Light)), vec!["seestar"]);
+    }
+
+    #[test]
+    fn rules_compare_numbers_and_text() {
+        let rules = TagRules {
+            enabled: true,
+            rules: vec![
+                rule(
+                    "long-exposure",
+                    TagField::Exposure,
+                    TagMatch::AtLeast,
+                    "300",
+                ),
+                rule("short", TagField::Exposure, TagMatch::AtMost, "30"),
+                rule("narrowband", TagField::Filter, TagMatch::Equals, "HA"),
+                rule("narrowband", TagField::Filter, TagMatch::Equals, "OIII"),
+                rule("orion", TagField::Object, TagMatch::Contains, "m 42"),
+                rule("high-gain", TagField::Gain, TagMatch::AtLeast, "200"),
+            ],
+        };
+        assert_eq!(
+            rule_tags(&rules, &facts(FrameType::Stacked)),
+            vec!["long-exposure", "narrowband", "orion"]
+        );
+    }
+
+    #[test]
+    fn rules_are_validated() {
+        let valid =
+            |tag, field, matches, value| rule(tag, field, matches, value).validate().is_ok();
+        assert!(valid("x", TagField::Exposure, TagMatch::AtLeast, "300"));
+        assert!(!valid("x", TagField::Exposure, TagMatch::AtLeast, "long"));
+        assert!(!valid("x", TagField::Gain, TagMatch::Contains, "1"));
+        assert!(!valid("x", TagField::Filter, TagMatch::AtMost, "Ha"));
+        assert!(!valid("a,b", TagField::Filter, TagMatch::Equals, "Ha"));
+    }
+
+    #[test]
+    fn merged_tags_keep_existing_ones() {
+        let tags = vec!["stacked".to_string(), "Orion".to_string()];
+        assert_eq!(
+            merge_tags(Some("orion, favourite"), &tags),
+            Some("orion, favourite, stacked".to_string())
+        );
+        assert_eq!(merge_tags(None, &[]), None);
+    }
+}
@@ -0,0 +1,270 @@
+//! Target profile: everything known about one object in a single call
+//!
+//! Drives the target page. The object's names are resolved first (see
+//! `object_names`), so images, observing list entries and sessions recorded
+//! under any alias are gathered together.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::models::{AstronomyTodo, Image};
+use crate::db::repository::{self, FilterExposureSummary};
+use crate::python::{altitude, simbad};
+use crate::skymap::{self, DeepSkyObject};
+use crate::state::AppState;
+
+use super::astronomy::{lookup_object, LocationInput};
+use super::images::listed_images;
+use super::meridian::{get_meridian_transit, MeridianTransit};
+use super::object_names::ObjectNames;
+use super::scan::get_session_date_utc;
+
+/// One night the target was imaged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetSession {
+    pub date: NaiveDate,
+    pub image_count: usize,
+    /// Sum of the images' exposure times, in seconds
+    pub exposure_seconds: f64,
+    pub collection_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetVisibility {
+    pub transit: MeridianTransit,
+    /// Tonight's altitude chart; missing when the Python sidecar couldn't
+    /// compute it
+    pub night: Option<altitude::NightChart>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetProfile {
+    /// Canonical name
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// Missing when SIMBAD doesn't know the name and can't be reached
+    pub simbad: Option<simbad::SimbadObject>,
+    /// Entry in the embedded deep sky catalogs
+    pub catalog: Option<DeepSkyObject>,
+    /// J2000 coordinates in degrees, from SIMBAD or the catalogs
+    pub ra: Option<f64>,
+    pub dec: Option<f64>,
+    /// Library images of the target, newest first
+    pub images: Vec<Image>,
+    /// Integration per filter, most first
+    pub filters: Vec<FilterExposureSummary>,
+    pub total_exposure_seconds: f64,
+    pub todos: Vec<AstronomyTodo>,
+    /// Nights the target was imaged, most recent first
+    pub sessions: Vec<TargetSession>,
+    /// Best rated processed or stacked image
+    pub best_image: Option<Image>,
+    /// Only with a location and known coordinates
+    pub visibility: Option<TargetVisibility>,
+}
+
+/// Catalog entry for an object, by canonical name
+fn catalog_object(names: &ObjectNames, canonical: &str) -> Option<DeepSkyObject> {
+    skymap::deep_sky_objects()
+        .iter()
+        .find(|object| {
+            std::iter::once(&object.name)
+                .chain(&object.common_names)
+                .any(|name| names.canonical(name) == canonical)
+        })
+        .cloned()
+}
+
+/// Group images by observing night
+fn target_sessions(images: &[Image], longitude: Option<f64>) -> Vec<TargetSession> {
+    let mut nights: BTreeMap<NaiveDate, TargetSession> = BTreeMap::new();
+    for image in images {
+        let Some(date) = image
+            .date_obs
+            .as_deref()
+            .and_then(|d| get_session_date_utc(d, longitude))
+        else {
+            continue;
+        };
+        let session = nights.entry(date).or_insert_with(|| TargetSession {
+            date,
+            image_count: 0,
+            exposure_seconds: 0.0,
+            collection_ids: Vec::new(),
+        });
+        session.image_count += 1;
+        session.exposure_seconds += image.exposure.unwrap_or(0.0);
+        if let Some(id) = &image.collection_id {
+            if !session.collection_ids.contains(id) {
+                session.collection_ids.push(id.clone());
+            }
+        }
+    }
+    nights.into_values().rev().collect()
+}
+
+/// Processed results and stacks, rather than single frames
+fn is_finished_image(image: &Image) -> bool {
+    let processed = image
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .any(|tag| tag.trim().eq_ignore_ascii_case("processed"));
+    processed || image.filename.to_lowercase().starts_with("stacked")
+}
+
+/// Highest rated finished image, favorites and then the newest breaking ties
+fn best_image(images: &[Image]) -> Option<Image> {
+    images
+        .iter()
+        .filter(|image| is_finished_image(image))
+        .max_by_key(|image| (image.rating.unwrap_or(0), image.favorite, image.created_at))
+        .cloned()
+}
+
+/// Everything known about a target: catalog and SIMBAD data, its images and
+/// integration per filter, observing list entries, past sessions, the best
+/// processed image and, given a location, when it's visible next
+#[tauri::command]
+pub async fn get_target_profile(
+    state: State<'_, AppState>,
+    name: String,
+    location: Option<LocationInput>,
+) -> Result<TargetProfile, String> {
+    let db = state.db.clone();
+    let network = state.network.clone();
+    let user_id = state.user_id.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut conn = db.get()?;
+        let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+        let canonical = names.canonical(&name);
+        let aliases = names.aliases(&name);
+
+        let images = repository::get_images_by_target_names(&mut conn, &user_id, &aliases)
+            .map_err(|e| e.to_string())?;
+        let images = listed_images(images, None);
+        let filters = repository::get_filter_summary_for_images(
+            &mut conn,
+            images.iter().map(|image| image.id.clone()).collect(),
+        )
+        .map_err(|e| e.to_string())?;
+        let todos: Vec<AstronomyTodo> = repository::get_todos(&mut conn, &user_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|todo| names.canonical(&todo.name) == canonical)
+            .collect();
+
+        let simbad = lookup_object(&mut conn, &network, &canonical).unwrap_or_else(|e| {
+            log::info!("No SIMBAD data for {}: {}", canonical, e);
+            None
+        });
+        drop(conn);
+
+        let catalog = catalog_object(&names, &canonical);
+        let (ra, dec) = simbad
+            .as_ref()
+            .and_then(|s| s.ra_deg.zip(s.dec_deg))
+            .or_else(|| catalog.as_ref().map(|c| (c.ra, c.dec)))
+            .unzip();
+
+        let longitude = location.as_ref().map(|l| l.longitude);
+        let visibility = match (location, ra.zip(dec)) {
+            (Some(location), Some((ra, dec))) => {
+                let observer = altitude::ObserverLocation {
+                    latitude: location.latitude,
+                    longitude: location.longitude,
+                    elevation: location.elevation,
+                    name: location.name.clone(),
+                };
+                let night = altitude::get_night_chart(ra, dec, &observer, None, None, None)
+                    .map_err(|e| log::warn!("Night chart for {} failed: {}", canonical, e))
+                    .ok();
+                Some(TargetVisibility {
+                    transit: get_meridian_transit(ra, dec, location, None)?,
+                    night,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(TargetProfile {
+            aliases,
+            simbad,
+            catalog,
+            ra,
+            dec,
+            total_exposure_seconds: filters.iter().map(|f| f.total_exposure_seconds).sum(),
+            filters,
+            todos,
+            sessions: target_sessions(&images, longitude),
+            best_image: best_image(&images),
+            images,
+            visibility,
+            name: canonical,
+        })
+    })
+    .await
+    .map_err(|e| format!("Building target profile failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_image(id: &str, date_obs: &str) -> Image {
+        Image {
+            collection_id: Some(format!("session-{}", &date_obs[..10])),
+            summary: Some("M 42".to_string()),
+            exposure: Some(300.0),
+            date_obs: Some(date_obs.to_string()),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn sessions_group_by_night() {
+        let images = [
+            make_image("a", "2025-01-10T22:00:00"),
+            make_image("b", "2025-01-11T03:00:00"),
+            make_image("c", "2025-02-01T21:00:00"),
+        ];
+        let sessions = target_sessions(&images, Some(0.0));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            sessions[0].date,
+            NaiveDate::from_ymd_opt(2025, 2, 1).unwrap()
+        );
+        assert_eq!(sessions[1].image_count, 2);
+        assert_eq!(sessions[1].exposure_seconds, 600.0);
+        assert_eq!(sessions[1].collection_ids.len(), 2);
+    }
+
+    #[test]
+    fn best_image_prefers_rated_results() {
+        let frame = Image {
+            rating: Some(5),
+            ..make_image("frame", "2025-01-10T22:00:00")
+        };
+        let stack = Image {
+            filename: "Stacked_120_M42.fit".to_string(),
+            rating: Some(3),
+            ..make_image("stack", "2025-01-10T22:00:00")
+        };
+        let processed = Image {
+            tags: Some("auto, processed".to_string()),
+            rating: Some(4),
+            ..make_image("processed", "2025-01-10T22:00:00")
+        };
+        assert!(best_image(std::slice::from_ref(&frame)).is_none());
+        let best = best_image(&[frame, stack, processed]).unwrap();
+        assert_eq!(best.id, "processed");
+    }
+}
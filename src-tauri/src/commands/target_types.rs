@@ -0,0 +1,287 @@
+//! Target type classification without the round trip to SIMBAD
+//!
+//! Processing defaults depend on what kind of object an image shows. Names
+//! are classified locally first: a table of well-known objects whose catalog
+//! type is misleading for imaging (M 16 is an open cluster to SIMBAD, a
+//! nebula to anyone photographing it), then the type listed in the embedded
+//! deep sky catalogs, then what the catalog prefix implies (Sh2 objects are
+//! HII regions, vdB objects reflection nebulae...). Only names the local pass
+//! isn't sure about go to Python and SIMBAD. Either way the result is kept in
+//! the object's `astro_objects` metadata, so each object is classified once.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+
+use crate::db::models::NewAstroObject;
+use crate::db::repository;
+use crate::python::image_process::TargetInfo;
+use crate::skymap::{self, DeepSkyObject};
+
+use super::object_names::{normalize_designation, ObjectNames};
+
+/// Local classifications at least this confident skip SIMBAD
+pub const CONFIDENT: f64 = 0.8;
+
+/// Key of the cached classification in an object's metadata JSON
+const METADATA_KEY: &str = "target_classification";
+
+const EMISSION_NEBULA: &str = "emission_nebula";
+const REFLECTION_NEBULA: &str = "reflection_nebula";
+const PLANETARY_NEBULA: &str = "planetary_nebula";
+const GALAXY: &str = "galaxy";
+const GLOBULAR_CLUSTER: &str = "globular_cluster";
+const OPEN_CLUSTER: &str = "open_cluster";
+const STAR_FIELD: &str = "star_field";
+
+/// Objects imaged for something other than their catalog type, or famous
+/// enough to settle outright
+const KNOWN_OBJECTS: [(&str, &str); 17] = [
+    ("M 1", EMISSION_NEBULA),
+    ("M 8", EMISSION_NEBULA),
+    ("M 16", EMISSION_NEBULA),
+    ("M 17", EMISSION_NEBULA),
+    ("M 20", EMISSION_NEBULA),
+    ("M 42", EMISSION_NEBULA),
+    ("M 43", EMISSION_NEBULA),
+    ("M 78", REFLECTION_NEBULA),
+    ("NGC 7000", EMISSION_NEBULA),
+    ("NGC 6960", EMISSION_NEBULA),
+    ("NGC 6992", EMISSION_NEBULA),
+    ("NGC 2237", EMISSION_NEBULA),
+    ("NGC 2244", EMISSION_NEBULA),
+    ("IC 434", EMISSION_NEBULA),
+    ("IC 1805", EMISSION_NEBULA),
+    ("IC 1848", EMISSION_NEBULA),
+    ("IC 5070", EMISSION_NEBULA),
+];
+
+/// What a catalog prefix says about objects missing from the catalogs or
+/// listed there without a type, with the confidence it deserves
+const PREFIXES: [(&str, &str, f64); 8] = [
+    ("Sh2-", EMISSION_NEBULA, 0.9),
+    ("vdB ", REFLECTION_NEBULA, 0.9),
+    ("LBN ", EMISSION_NEBULA, 0.8),
+    ("LDN ", STAR_FIELD, 0.8),
+    ("B ", STAR_FIELD, 0.8),
+    ("Abell ", PLANETARY_NEBULA, 0.6),
+    // Most NGC and IC objects are galaxies, but far from all
+    ("NGC ", GALAXY, 0.4),
+    ("IC ", GALAXY, 0.4),
+];
+
+/// Target type for a catalog's object type ("HII (ionized) region",
+/// "Seyfert 2 Galaxy"...)
+fn catalog_target_type(object_type: &str) -> Option<(&'static str, f64)> {
+    let object_type = object_type.to_lowercase();
+    let has = |word: &str| object_type.contains(word);
+    let classified = if has("planetary nebula") {
+        (PLANETARY_NEBULA, 0.95)
+    } else if has("galax") {
+        (GALAXY, 0.95)
+    } else if has("globular") {
+        (GLOBULAR_CLUSTER, 0.95)
+    } else if has("reflection") {
+        (REFLECTION_NEBULA, 0.9)
+    } else if has("hii") || has("emission") || has("supernova") {
+        (EMISSION_NEBULA, 0.9)
+    } else if has("nebula") {
+        // "Star cluster + Nebula" and plain "Nebula"
+        (EMISSION_NEBULA, 0.7)
+    } else if has("cluster") || has("association") {
+        (OPEN_CLUSTER, 0.85)
+    } else if has("star") {
+        (STAR_FIELD, 0.6)
+    } else {
+        // "Duplicated object", "Other", "Composite object"
+        return None;
+    };
+    Some(classified)
+}
+
+/// Embedded catalog objects by normalized name, Messier first
+fn catalog_objects() -> &'static HashMap<String, &'static DeepSkyObject> {
+    static OBJECTS: OnceLock<HashMap<String, &'static DeepSkyObject>> = OnceLock::new();
+    OBJECTS.get_or_init(|| {
+        let mut objects = HashMap::new();
+        for object in skymap::deep_sky_objects() {
+            objects
+                .entry(normalize_designation(&object.name))
+                .or_insert(object);
+        }
+        objects
+    })
+}
+
+fn target_info(object_name: &str, target_type: &str, confidence: f64) -> TargetInfo {
+    TargetInfo {
+        target_type: target_type.to_string(),
+        object_name: object_name.to_string(),
+        confidence,
+        simbad_type: None,
+    }
+}
+
+/// Classify `canonical` (a name from [`ObjectNames::canonical`]) from the
+/// embedded data alone; None when nothing is known about it
+pub fn local_target_type(object_name: &str, canonical: &str) -> Option<TargetInfo> {
+    if let Some((_, target_type)) = KNOWN_OBJECTS.iter().find(|(name, _)| *name == canonical) {
+        return Some(target_info(object_name, target_type, 1.0));
+    }
+
+    let from_catalog = catalog_objects()
+        .get(canonical)
+        .and_then(|object| object.object_type.as_deref())
+        .and_then(catalog_target_type);
+    let from_prefix = PREFIXES
+        .iter()
+        .find(|(prefix, _, _)| canonical.starts_with(prefix))
+        .map(|(_, target_type, confidence)| (*target_type, *confidence));
+
+    [from_catalog, from_prefix]
+        .into_iter()
+        .flatten()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(target_type, confidence)| target_info(object_name, target_type, confidence))
+}
+
+/// Classification cached on the object, if it has been classified before
+pub fn cached_target_type(
+    conn: &mut SqliteConnection,
+    object_name: &str,
+    canonical: &str,
+) -> QueryResult<Option<TargetInfo>> {
+    let Some(object) = repository::get_astro_object_by_name(conn, canonical)? else {
+        return Ok(None);
+    };
+    Ok(object
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .and_then(|mut metadata| metadata.get_mut(METADATA_KEY).map(serde_json::Value::take))
+        .and_then(|cached| serde_json::from_value::<TargetInfo>(cached).ok())
+        .map(|info| TargetInfo {
+            object_name: object_name.to_string(),
+            ..info
+        }))
+}
+
+/// Remember a classification on the object, creating its record if needed
+pub fn cache_target_type(
+    conn: &mut SqliteConnection,
+    canonical: &str,
+    info: &TargetInfo,
+) -> QueryResult<()> {
+    let existing = repository::get_astro_object_by_name(conn, canonical)?;
+    let mut metadata = existing
+        .as_ref()
+        .and_then(|o| o.metadata.as_deref())
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|m| m.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    metadata[METADATA_KEY] = serde_json::json!({
+        "targetType": info.target_type,
+        "objectName": canonical,
+        "confidence": info.confidence,
+        "simbadType": info.simbad_type,
+        "classifiedAt": chrono::Utc::now().to_rfc3339(),
+    });
+    let metadata = metadata.to_string();
+
+    if existing.is_some() {
+        repository::set_astro_object_metadata(conn, canonical, &metadata)?;
+        return Ok(());
+    }
+    repository::save_astro_object(
+        conn,
+        &NewAstroObject {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: canonical.to_string(),
+            display_name: canonical.to_string(),
+            object_type: None,
+            seq: None,
+            aliases: None,
+            notes: None,
+            metadata: Some(metadata),
+        },
+    )
+}
+
+/// Canonical name of `object_name` for caching
+pub fn canonical_name(conn: &mut SqliteConnection, object_name: &str) -> QueryResult<String> {
+    Ok(ObjectNames::load(conn)?.canonical(object_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(name: &str) -> Option<(String, f64)> {
+        let canonical = ObjectNames::default().canonical(name);
+        local_target_type(name, &canonical).map(|info| (info.target_type, info.confidence))
+    }
+
+    #[test]
+    fn well_known_objects_are_settled_locally() {
+        // SIMBAD calls M 16 an open cluster
+        assert_eq!(classify("M16"), Some((EMISSION_NEBULA.to_string(), 1.0)));
+        assert_eq!(
+            classify("Orion Nebula"),
+            Some((EMISSION_NEBULA.to_string(), 1.0))
+        );
+    }
+
+    #[test]
+    fn catalog_types_are_mapped() {
+        let (target_type, confidence) = classify("M 31").unwrap();
+        assert_eq!(target_type, GALAXY);
+        assert!(confidence >= CONFIDENT);
+        assert_eq!(classify("M13").unwrap().0, GLOBULAR_CLUSTER);
+        assert_eq!(classify("M 57").unwrap().0, PLANETARY_NEBULA);
+        assert_eq!(classify("NGC 869").unwrap().0, OPEN_CLUSTER);
+        assert_eq!(classify("sh2 155").unwrap().0, EMISSION_NEBULA);
+    }
+
+    #[test]
+    fn prefixes_fill_in_for_untyped_entries() {
+        assert_eq!(classify("B 33"), Some((STAR_FIELD.to_string(), 0.8)));
+        assert_eq!(classify("vdB 152").unwrap().0, REFLECTION_NEBULA);
+        // Not confident enough to skip SIMBAD
+        assert!(classify("Abell 31").unwrap().1 < CONFIDENT);
+        assert_eq!(classify("Comet C/2023 A3"), None);
+    }
+
+    #[test]
+    fn classifications_are_cached_on_the_object() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        crate::db::run_migrations(&mut conn).unwrap();
+
+        let canonical = canonical_name(&mut conn, "Abell31").unwrap();
+        assert_eq!(canonical, "Abell31");
+        assert!(cached_target_type(&mut conn, "Abell31", &canonical)
+            .unwrap()
+            .is_none());
+
+        let info = TargetInfo {
+            simbad_type: Some("PN".to_string()),
+            ..target_info("Abell31", PLANETARY_NEBULA, 0.85)
+        };
+        cache_target_type(&mut conn, &canonical, &info).unwrap();
+        let cached = cached_target_type(&mut conn, "abell31", &canonical)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.target_type, PLANETARY_NEBULA);
+        assert_eq!(cached.object_name, "abell31");
+        assert_eq!(cached.simbad_type.as_deref(), Some("PN"));
+
+        // Re-classifying replaces the cached entry on the same record
+        cache_target_type(&mut conn, &canonical, &target_info("Abell31", GALAXY, 0.9)).unwrap();
+        let cached = cached_target_type(&mut conn, "Abell31", &canonical)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.target_type, GALAXY);
+        assert_eq!(repository::get_astro_objects(&mut conn).unwrap().len(), 1);
+    }
+}
@@ -6,11 +6,15 @@ use crate::db::models::Image;
 use crate::db::repository::{self, TargetWithCount};
 use crate::state::AppState;
 
-/// Get all unique targets with their image counts
+use super::object_names::ObjectNames;
+
+/// Get all unique targets with their image counts, with aliases of an
+/// object counted under its canonical name
 #[tauri::command]
 pub fn get_targets(state: State<'_, AppState>) -> Result<Vec<TargetWithCount>, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    repository::get_targets_with_counts(&mut conn, &state.user_id)
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    repository::get_targets_with_counts_by(&mut conn, &state.user_id, |name| names.canonical(name))
         .map_err(|e| e.to_string())
 }
 
@@ -25,13 +29,16 @@ pub fn search_images_by_target(
         .map_err(|e| e.to_string())
 }
 
-/// Get all images for a specific target (exact match)
+/// Get all images for a specific target under any of its names
 #[tauri::command]
 pub fn get_images_by_target(
     state: State<'_, AppState>,
     target_name: String,
 ) -> Result<Vec<Image>, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
-    repository::get_images_by_target(&mut conn, &state.user_id, &target_name)
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
+    let mut target_names = names.aliases(&target_name);
+    target_names.push(target_name);
+    repository::get_images_by_target_names(&mut conn, &state.user_id, &target_names)
         .map_err(|e| e.to_string())
 }
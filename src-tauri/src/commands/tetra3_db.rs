@@ -1,9 +1,12 @@
 //! Tetra3 database downloads from astra.gallery.
 
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::AsyncWriteExt;
 
+use crate::network::Provider;
+use crate::state::AppState;
+
 const TETRA3_BASE_URL: &str = "https://astra.gallery/downloads/tetra3";
 const EMIT_BYTES_THRESHOLD: u64 = 4 * 1024 * 1024;
 
@@ -28,6 +31,7 @@ pub struct DownloadResult {
 #[tauri::command]
 pub async fn download_tetra3_db(
     app: AppHandle,
+    state: State<'_, AppState>,
     filename: String,
 ) -> Result<DownloadResult, String> {
     let app_data = app
@@ -41,15 +45,10 @@ pub async fn download_tetra3_db(
     let dest_path = tetra3_dir.join(&filename);
 
     let url = format!("{TETRA3_BASE_URL}/{filename}");
-    let client = reqwest::Client::new();
-    let mut response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("request: {e}"))?;
-    if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()));
-    }
+    let mut response = state
+        .network
+        .send(Provider::Downloads, |client| client.get(&url))
+        .await?;
     let total = response.content_length().unwrap_or(0);
 
     let mut file = tokio::fs::File::create(&dest_path)
@@ -0,0 +1,522 @@
+//! Time-lapse movies from a night's frames
+//!
+//! Frames are stretched one at a time (FITS files through the native
+//! stretch pipeline, anything else from its display image), scaled to a
+//! common size and handed to an encoder as they're rendered, so memory use
+//! doesn't grow with the number of frames. GIFs are encoded in-process;
+//! MP4s go through ffmpeg when it's installed.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::FilterType;
+use image::{Delay, DynamicImage, Frame, RgbImage};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::solvers::find_executable;
+use crate::state::AppState;
+use crate::stretch::{stretch_fits, StretchParams};
+
+use super::gallery_export::load_source_image;
+use super::scan::{parse_utc_timestamp, CollectProgress};
+
+/// Global cancellation flag for time-lapse rendering
+static TIMELAPSE_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+const PROGRESS_EVENT: &str = "timelapse-progress";
+
+/// ffmpeg executables tried, in order
+const FFMPEG_CANDIDATES: &[&str] = &[
+    "ffmpeg",
+    "/opt/homebrew/bin/ffmpeg",
+    "/usr/local/bin/ffmpeg",
+    "C:\\Program Files\\ffmpeg\\bin\\ffmpeg.exe",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelapseFormat {
+    #[default]
+    Gif,
+    /// H.264, needs ffmpeg
+    Mp4,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TimelapseOptions {
+    pub format: TimelapseFormat,
+    /// Frames per second
+    pub frame_rate: f64,
+    /// Longest side in pixels (default 720 for GIFs, 1920 for MP4s). Frames
+    /// are never scaled up.
+    pub max_size: Option<u32>,
+}
+
+impl Default for TimelapseOptions {
+    fn default() -> Self {
+        Self {
+            format: TimelapseFormat::Gif,
+            frame_rate: 10.0,
+            max_size: None,
+        }
+    }
+}
+
+impl TimelapseOptions {
+    fn max_size(&self) -> u32 {
+        self.max_size.unwrap_or(match self.format {
+            TimelapseFormat::Gif => 720,
+            TimelapseFormat::Mp4 => 1920,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelapseResult {
+    pub path: String,
+    pub frames: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Images that couldn't be loaded, left out of the movie
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Frames in capture order; undated images go last, by file name
+fn frame_order(mut images: Vec<Image>) -> Vec<Image> {
+    images.sort_by_cached_key(|image| {
+        let time = image.date_obs.as_deref().and_then(parse_utc_timestamp);
+        (time.is_none(), time, image.filename.clone())
+    });
+    images
+}
+
+/// Movie size for frames of `width`×`height`: fit within `max_size` and
+/// round down to even dimensions, which H.264 needs
+fn frame_size(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    let scale = (max_size as f64 / width.max(height) as f64).min(1.0);
+    let even = |v: u32| ((v as f64 * scale) as u32 & !1).max(2);
+    (even(width), even(height))
+}
+
+fn is_fits(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    ext == "fit" || ext == "fits" || ext == "fts"
+}
+
+/// Stretched frame for an image, preferring its FITS data
+fn load_frame(image: &Image, previews_dir: &Path) -> Result<RgbImage, String> {
+    let fits = [image.url.as_deref(), image.fits_url.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(Path::new)
+        .find(|path| is_fits(path) && path.exists());
+    if let Some(path) = fits {
+        // Keep every frame's full field so they line up; gradient removal
+        // would hide the clouds a time-lapse is often meant to show
+        let params = StretchParams {
+            autocrop: false,
+            gradient_removal: false,
+            ..Default::default()
+        };
+        return stretch_fits(path, &params)?.to_rgb_image();
+    }
+    load_source_image(image, previews_dir)
+        .map(|source| source.to_rgb8())
+        .ok_or_else(|| format!("{}: no displayable file", image.filename))
+}
+
+/// Where encoded frames go
+enum Encoder {
+    Gif {
+        encoder: GifEncoder<BufWriter<File>>,
+        delay: Delay,
+    },
+    Ffmpeg {
+        child: Child,
+        stdin: BufWriter<ChildStdin>,
+    },
+}
+
+impl Encoder {
+    fn create(
+        options: &TimelapseOptions,
+        (width, height): (u32, u32),
+        path: &Path,
+    ) -> Result<Self, String> {
+        match options.format {
+            TimelapseFormat::Gif => {
+                let file = File::create(path)
+                    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(|e| format!("Failed to write GIF: {}", e))?;
+                Ok(Encoder::Gif {
+                    encoder,
+                    delay: Delay::from_saturating_duration(Duration::from_secs_f64(
+                        1.0 / options.frame_rate,
+                    )),
+                })
+            }
+            TimelapseFormat::Mp4 => {
+                let ffmpeg = find_executable(None, FFMPEG_CANDIDATES).ok_or(
+                    "MP4 time-lapses need ffmpeg, which wasn't found. Install it or export a GIF.",
+                )?;
+                let mut child = Command::new(&ffmpeg)
+                    .args(ffmpeg_args(options.frame_rate, (width, height), path))
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| format!("Failed to start {}: {}", ffmpeg.display(), e))?;
+                let stdin = child.stdin.take().ok_or("Failed to open ffmpeg input")?;
+                Ok(Encoder::Ffmpeg {
+                    child,
+                    stdin: BufWriter::new(stdin),
+                })
+            }
+        }
+    }
+
+    fn add(&mut self, frame: RgbImage) -> Result<(), String> {
+        match self {
+            Encoder::Gif { encoder, delay } => {
+                let rgba = DynamicImage::ImageRgb8(frame).to_rgba8();
+                encoder
+                    .encode_frame(Frame::from_parts(rgba, 0, 0, *delay))
+                    .map_err(|e| format!("Failed to write GIF frame: {}", e))
+            }
+            Encoder::Ffmpeg { stdin, .. } => stdin
+                .write_all(frame.as_raw())
+                .map_err(|e| format!("Failed to send frame to ffmpeg: {}", e)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            Encoder::Gif { encoder, .. } => {
+                // The GIF trailer is written when the encoder is dropped
+                drop(encoder);
+                Ok(())
+            }
+            Encoder::Ffmpeg { child, mut stdin } => {
+                stdin
+                    .flush()
+                    .map_err(|e| format!("Failed to send frame to ffmpeg: {}", e))?;
+                // Closing its input lets ffmpeg finish the file
+                drop(stdin);
+                let output = child
+                    .wait_with_output()
+                    .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "ffmpeg failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ))
+                }
+            }
+        }
+    }
+
+    fn abort(self) {
+        if let Encoder::Ffmpeg { mut child, stdin } = self {
+            drop(stdin);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// ffmpeg arguments to encode raw RGB frames from stdin as H.264 at `path`
+fn ffmpeg_args(frame_rate: f64, (width, height): (u32, u32), path: &Path) -> Vec<String> {
+    [
+        "-y",
+        "-loglevel",
+        "error",
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        "rgb24",
+        "-s",
+        &format!("{}x{}", width, height),
+        "-framerate",
+        &format!("{}", frame_rate),
+        "-i",
+        "-",
+        "-c:v",
+        "libx264",
+        "-pix_fmt",
+        "yuv420p",
+        "-movflags",
+        "+faststart",
+        &path.to_string_lossy(),
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+/// Render `images` as a time-lapse at `path`. `progress` is called with
+/// (frames done, total, current file) before each frame and once at the
+/// end.
+fn render_timelapse(
+    images: &[Image],
+    previews_dir: &Path,
+    path: &Path,
+    options: &TimelapseOptions,
+    cancelled: &AtomicBool,
+    mut progress: impl FnMut(usize, usize, &str),
+) -> Result<TimelapseResult, String> {
+    if !(options.frame_rate > 0.0 && options.frame_rate <= 120.0) {
+        return Err("Frame rate must be between 0 and 120 fps".to_string());
+    }
+    let mut result = TimelapseResult {
+        path: path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    let total = images.len();
+    let mut encoder: Option<Encoder> = None;
+
+    for (i, image) in images.iter().enumerate() {
+        if cancelled.load(Ordering::SeqCst) {
+            result.cancelled = true;
+            break;
+        }
+        progress(i, total, &image.filename);
+
+        let frame = match load_frame(image, previews_dir) {
+            Ok(frame) => frame,
+            Err(e) => {
+                log::warn!("Time-lapse: skipping {}: {}", image.filename, e);
+                result.errors.push(e);
+                continue;
+            }
+        };
+        // The first frame that loads sets the movie size
+        if encoder.is_none() {
+            let (width, height) = frame_size(frame.width(), frame.height(), options.max_size());
+            result.width = width;
+            result.height = height;
+            encoder = Some(Encoder::create(options, (width, height), path)?);
+        }
+        let frame = if (frame.width(), frame.height()) == (result.width, result.height) {
+            frame
+        } else {
+            image::imageops::resize(&frame, result.width, result.height, FilterType::Triangle)
+        };
+        let added = encoder.as_mut().map(|encoder| encoder.add(frame));
+        if let Some(Err(e)) = added {
+            if let Some(encoder) = encoder.take() {
+                encoder.abort();
+            }
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+        result.frames += 1;
+    }
+
+    if result.cancelled {
+        if let Some(encoder) = encoder {
+            encoder.abort();
+            let _ = std::fs::remove_file(path);
+        }
+        return Ok(result);
+    }
+    let Some(encoder) = encoder else {
+        return Err("None of the images could be loaded".to_string());
+    };
+    progress(total, total, "");
+    encoder.finish()?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn cancel_timelapse() -> Result<(), String> {
+    TIMELAPSE_CANCELLED.store(true, Ordering::SeqCst);
+    log::info!("Time-lapse cancellation requested");
+    Ok(())
+}
+
+/// Assemble a time-lapse at `path` from `image_ids`, or else every image in
+/// `collection_id`, in capture order. Progress is emitted as
+/// `timelapse-progress` events.
+#[tauri::command]
+pub async fn create_timelapse(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    image_ids: Option<Vec<String>>,
+    collection_id: Option<String>,
+    path: String,
+    options: Option<TimelapseOptions>,
+) -> Result<TimelapseResult, String> {
+    // Reset cancellation flag at start
+    TIMELAPSE_CANCELLED.store(false, Ordering::SeqCst);
+
+    let previews_dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map(|d| d.join("previews"))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let images = match (image_ids, collection_id) {
+        (Some(ids), _) => {
+            let mut images = Vec::new();
+            for id in ids {
+                let image = repository::get_image_by_id(&mut conn, &id)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("Image not found: {}", id))?;
+                images.push(image);
+            }
+            images
+        }
+        (None, Some(collection_id)) => {
+            repository::get_images_in_collection(&mut conn, &collection_id)
+                .map_err(|e| e.to_string())?
+        }
+        (None, None) => return Err("Choose images or a collection".to_string()),
+    };
+    drop(conn);
+    if images.is_empty() {
+        return Err("No images to make a time-lapse from".to_string());
+    }
+
+    let options = options.unwrap_or_default();
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || {
+        let images = frame_order(images);
+        render_timelapse(
+            &images,
+            &previews_dir,
+            &path,
+            &options,
+            &TIMELAPSE_CANCELLED,
+            |current, total, file| {
+                let _ = window.emit(
+                    PROGRESS_EVENT,
+                    &CollectProgress {
+                        current,
+                        total,
+                        current_file: file.to_string(),
+                        percent: (current * 100 / total.max(1)) as u8,
+                        cancelled: TIMELAPSE_CANCELLED.load(Ordering::SeqCst),
+                        phase: if current < total {
+                            "rendering"
+                        } else {
+                            "encoding"
+                        }
+                        .to_string(),
+                    },
+                );
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Time-lapse failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    fn frame_image(id: &str, date_obs: Option<&str>, path: &Path) -> Image {
+        Image {
+            filename: format!("{}.jpg", id),
+            url: Some(path.to_string_lossy().to_string()),
+            date_obs: date_obs.map(str::to_string),
+            ..Image::test(id)
+        }
+    }
+
+    #[test]
+    fn frames_are_ordered_and_sized_for_encoding() {
+        let dir = Path::new("/nowhere");
+        let images = vec![
+            frame_image("undated", None, dir),
+            frame_image("late", Some("2024-03-10T03:00:00"), dir),
+            frame_image("early", Some("2024-03-09T22:15:00Z"), dir),
+        ];
+        let ids: Vec<_> = frame_order(images).into_iter().map(|i| i.id).collect();
+        assert_eq!(ids, ["early", "late", "undated"]);
+
+        assert_eq!(frame_size(4144, 2822, 1920), (1920, 1306));
+        assert_eq!(frame_size(641, 481, 1920), (640, 480));
+        assert_eq!(frame_size(3000, 3000, 720), (720, 720));
+    }
+
+    #[test]
+    fn gif_has_a_frame_per_loadable_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut images = Vec::new();
+        for (i, shade) in [40u8, 120, 200].iter().enumerate() {
+            let path = dir.path().join(format!("frame{}.png", i));
+            // Odd sizes and a different size midway get scaled to the first
+            let size = if i == 1 { (201, 151) } else { (101, 75) };
+            RgbImage::from_pixel(size.0, size.1, image::Rgb([*shade, *shade, *shade]))
+                .save(&path)
+                .unwrap();
+            images.push(frame_image(&format!("f{}", i), None, &path));
+        }
+        images.push(frame_image("gone", None, &dir.path().join("gone.png")));
+
+        let out = dir.path().join("night.gif");
+        let mut calls = Vec::new();
+        let options = TimelapseOptions {
+            frame_rate: 5.0,
+            ..Default::default()
+        };
+        let result = render_timelapse(
+            &images,
+            dir.path(),
+            &out,
+            &options,
+            &AtomicBool::new(false),
+            |current, total, _| calls.push((current, total)),
+        )
+        .unwrap();
+
+        assert_eq!((result.frames, result.width, result.height), (3, 100, 74));
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(calls.last(), Some(&(4, 4)));
+
+        let decoder = GifDecoder::new(std::io::BufReader::new(File::open(&out).unwrap())).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].delay().numer_denom_ms(), (200, 1));
+        assert!(frames[2].buffer().get_pixel(50, 37)[0] > 180);
+
+        // A cancelled render leaves no partial file behind
+        let cancelled_path = dir.path().join("cancelled.gif");
+        let cancelled = render_timelapse(
+            &images,
+            dir.path(),
+            &cancelled_path,
+            &options,
+            &AtomicBool::new(true),
+            |_, _, _| {},
+        )
+        .unwrap();
+        assert!(cancelled.cancelled);
+        assert!(!cancelled_path.exists());
+    }
+}
@@ -7,6 +7,8 @@ use crate::db::models::{AstronomyTodo, NewAstronomyTodo, UpdateAstronomyTodo};
 use crate::db::repository;
 use crate::state::AppState;
 
+use super::object_names::ObjectNames;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTodoInput {
     pub name: String,
@@ -57,11 +59,12 @@ pub fn create_todo(
     input: CreateTodoInput,
 ) -> Result<AstronomyTodo, String> {
     let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let names = ObjectNames::load(&mut conn).map_err(|e| e.to_string())?;
 
     let new_todo = NewAstronomyTodo {
         id: uuid::Uuid::new_v4().to_string(),
         user_id: state.user_id.clone(),
-        name: input.name,
+        name: names.canonical(&input.name),
         ra: input.ra,
         dec: input.dec,
         magnitude: input.magnitude,
@@ -102,6 +105,8 @@ pub fn update_todo(
         flagged: input.flagged,
         last_updated: Some(chrono::Utc::now().to_rfc3339()),
         tags: input.tags.map(|t| serde_json::to_string(&t).unwrap_or_default()),
+        exposure_plan: None,
+        framing: None,
     };
 
     repository::update_todo(&mut conn, &input.id, &update)
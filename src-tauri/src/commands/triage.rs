@@ -0,0 +1,206 @@
+//! Import triage: frames that look like failed captures or test shots
+//!
+//! Capture software leaves behind frames that failed partway and very short
+//! test exposures. Imports flag frames tripping one of these rules as pending
+//! triage instead of adding them to the library as normal images; they stay
+//! out of listings until they're reviewed and either kept or discarded.
+//! Discarded frames keep their record so rescans don't import them again.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::models::Image;
+use crate::db::repository;
+use crate::state::AppState;
+
+use super::scan::FitsMetadata;
+use super::settings::{load_settings, load_settings_in, save_settings, TRIAGE_RULES};
+
+/// Triage status of an image waiting for review
+pub const TRIAGE_PENDING: &str = "pending";
+/// Triage status of an image the user threw out
+pub const TRIAGE_DISCARDED: &str = "discarded";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TriageRules {
+    /// Flag frames at import; when off everything is imported as before
+    pub enabled: bool,
+    /// Frames with an EXPTIME below this many seconds are test shots
+    pub min_exposure: f64,
+    /// Flag frames without an OBJECT header
+    pub require_object: bool,
+    /// Markers of a failed frame, matched case-insensitively against the
+    /// file name and OBJECT header
+    pub error_markers: Vec<String>,
+}
+
+impl Default for TriageRules {
+    fn default() -> Self {
+        TriageRules {
+            enabled: true,
+            min_exposure: 1.0,
+            require_object: true,
+            error_markers: vec!["failed".to_string(), "error".to_string()],
+        }
+    }
+}
+
+/// Why a frame should be reviewed before it's imported; empty if it looks fine
+pub fn triage_reasons(rules: &TriageRules, stem: &str, metadata: &FitsMetadata) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if !rules.enabled {
+        return reasons;
+    }
+
+    if let Some(exposure) = metadata.exposure {
+        if exposure < rules.min_exposure {
+            reasons.push(format!(
+                "Exposure {}s is below {}s",
+                exposure, rules.min_exposure
+            ));
+        }
+    }
+
+    let object = metadata
+        .object_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|o| !o.is_empty());
+    if rules.require_object && object.is_none() {
+        reasons.push("No OBJECT header".to_string());
+    }
+
+    let name = stem.to_lowercase();
+    let object = object.map(str::to_lowercase).unwrap_or_default();
+    for marker in &rules.error_markers {
+        let marker = marker.trim().to_lowercase();
+        if !marker.is_empty() && (name.contains(&marker) || object.contains(&marker)) {
+            reasons.push(format!("Marked as failed ('{}')", marker));
+        }
+    }
+
+    reasons
+}
+
+/// Record triage reasons in an image's metadata JSON under "triage_reasons"
+pub fn with_triage_reasons(metadata: Option<String>, reasons: &[String]) -> Option<String> {
+    let mut value = metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    value["triage_reasons"] = serde_json::json!(reasons);
+    Some(value.to_string())
+}
+
+/// Saved triage rules, or the defaults
+pub fn triage_rules(app: &AppHandle) -> TriageRules {
+    load_settings(app, &TRIAGE_RULES).unwrap_or_default()
+}
+
+/// Triage rules saved in `data_dir`, for running without the app
+pub(crate) fn triage_rules_in(data_dir: &Path) -> TriageRules {
+    load_settings_in(data_dir, &TRIAGE_RULES)
+}
+
+#[tauri::command]
+pub fn get_triage_rules(app: AppHandle) -> Result<TriageRules, String> {
+    load_settings(&app, &TRIAGE_RULES)
+}
+
+/// Save triage rules; None restores the defaults
+#[tauri::command]
+pub fn set_triage_rules(app: AppHandle, rules: Option<TriageRules>) -> Result<TriageRules, String> {
+    let rules = rules.unwrap_or_default();
+    if !rules.min_exposure.is_finite() || rules.min_exposure < 0.0 {
+        return Err("Minimum exposure must be zero or more seconds".to_string());
+    }
+
+    save_settings(&app, &TRIAGE_RULES, &rules)?;
+    Ok(rules)
+}
+
+/// Frames waiting for review, newest first
+#[tauri::command]
+pub fn get_triage_images(state: State<'_, AppState>) -> Result<Vec<Image>, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    repository::get_images_by_triage(&mut conn, &state.user_id, TRIAGE_PENDING)
+        .map_err(|e| e.to_string())
+}
+
+/// Keep frames (they join the library) or discard them. Returns how many
+/// images were updated.
+#[tauri::command]
+pub fn review_triage_images(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    keep: bool,
+) -> Result<usize, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let status = if keep { None } else { Some(TRIAGE_DISCARDED) };
+    repository::set_image_triage(&mut conn, &state.user_id, &ids, status).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(object: Option<&str>, exposure: Option<f64>) -> FitsMetadata {
+        FitsMetadata {
+            object_name: object.map(str::to_string),
+            exposure,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn good_frames_pass() {
+        let rules = TriageRules::default();
+        let frame = metadata(Some("M 42"), Some(10.0));
+        assert!(
+            triage_reasons(&rules, "Light_M 42_10.0s_IRCUT_20250115-203012", &frame).is_empty()
+        );
+    }
+
+    #[test]
+    fn junk_frames_are_flagged() {
+        let rules = TriageRules::default();
+        assert_eq!(
+            triage_reasons(
+                &rules,
+                "Light_M 42_0.1s",
+                &metadata(Some("M 42"), Some(0.1))
+            ),
+            vec!["Exposure 0.1s is below 1s"]
+        );
+        assert_eq!(
+            triage_reasons(&rules, "capture_0001", &metadata(Some("  "), Some(10.0))),
+            vec!["No OBJECT header"]
+        );
+        assert_eq!(
+            triage_reasons(&rules, "Stacked_M 42_FAILED", &metadata(Some("M 42"), None)),
+            vec!["Marked as failed ('failed')"]
+        );
+
+        let disabled = TriageRules {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(triage_reasons(&disabled, "test", &metadata(None, Some(0.0))).is_empty());
+    }
+
+    #[test]
+    fn reasons_are_added_to_metadata() {
+        let reasons = vec!["No OBJECT header".to_string()];
+        let json = with_triage_reasons(Some(r#"{"EXPTIME":"10"}"#.to_string()), &reasons).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["EXPTIME"], "10");
+        assert_eq!(value["triage_reasons"][0], "No OBJECT header");
+
+        let json = with_triage_reasons(None, &reasons).unwrap();
+        assert!(json.contains("triage_reasons"));
+    }
+}
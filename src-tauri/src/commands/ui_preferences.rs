@@ -0,0 +1,128 @@
+//! UI preferences: theme, layout, default views and column choices
+//!
+//! Stored as one JSON object per user in the database, so they sync along
+//! with the library instead of living in a single webview's localStorage.
+//! The backend doesn't interpret the keys; each part of the UI owns its own.
+//! Changes are announced as "ui-preferences-changed" so other windows can
+//! follow along.
+
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::db::models::NewUiPreferences;
+use crate::db::repository;
+use crate::events;
+use crate::state::AppState;
+
+/// Parse stored preferences, treating anything but an object as empty
+fn parse_preferences(json: &str) -> Map<String, Value> {
+    match serde_json::from_str(json) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Apply top-level keys from `changes` over `current`; a null value removes
+/// the key
+fn merge_preferences(
+    mut current: Map<String, Value>,
+    changes: Map<String, Value>,
+) -> Map<String, Value> {
+    for (key, value) in changes {
+        if value.is_null() {
+            current.remove(&key);
+        } else {
+            current.insert(key, value);
+        }
+    }
+    current
+}
+
+/// The current user's UI preferences (an empty object before the first save)
+#[tauri::command]
+pub fn get_ui_preferences(state: State<'_, AppState>) -> Result<Value, String> {
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+    let stored =
+        repository::get_ui_preferences(&mut conn, &state.user_id).map_err(|e| e.to_string())?;
+    Ok(Value::Object(
+        stored
+            .map(|p| parse_preferences(&p.preferences))
+            .unwrap_or_default(),
+    ))
+}
+
+/// Update the current user's UI preferences. Top-level keys are merged into
+/// what's stored (null removes one) unless `replace` is set. Returns the
+/// preferences as saved.
+#[tauri::command]
+pub fn set_ui_preferences(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    preferences: Value,
+    replace: Option<bool>,
+) -> Result<Value, String> {
+    let Value::Object(changes) = preferences else {
+        return Err("UI preferences must be a JSON object".to_string());
+    };
+    let mut conn = state.db.get().map_err(|e| e.to_string())?;
+
+    let merged = if replace.unwrap_or(false) {
+        merge_preferences(Map::new(), changes)
+    } else {
+        let current = repository::get_ui_preferences(&mut conn, &state.user_id)
+            .map_err(|e| e.to_string())?
+            .map(|p| parse_preferences(&p.preferences))
+            .unwrap_or_default();
+        merge_preferences(current, changes)
+    };
+
+    let merged = Value::Object(merged);
+    repository::save_ui_preferences(
+        &mut conn,
+        &NewUiPreferences {
+            user_id: state.user_id.clone(),
+            preferences: merged.to_string(),
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    let _ = app.emit(events::UI_PREFERENCES_CHANGED, &merged);
+    events::publish(events::UI_PREFERENCES_CHANGED, &merged);
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("not an object"),
+        }
+    }
+
+    #[test]
+    fn merge_replaces_top_level_keys_and_drops_nulls() {
+        let current = object(json!({
+            "theme": "dark",
+            "gallery": { "view": "grid", "columns": ["filename", "date"] },
+            "sidebar": "collapsed",
+        }));
+        let changes = object(json!({
+            "gallery": { "view": "list" },
+            "sidebar": null,
+            "nightMode": true,
+        }));
+        let merged = merge_preferences(current, changes);
+        assert_eq!(
+            Value::Object(merged),
+            json!({ "theme": "dark", "gallery": { "view": "list" }, "nightMode": true })
+        );
+
+        assert!(parse_preferences("[1, 2]").is_empty());
+        assert!(parse_preferences("not json").is_empty());
+        assert_eq!(parse_preferences(r#"{"theme":"red"}"#)["theme"], "red");
+    }
+}
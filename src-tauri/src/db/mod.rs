@@ -10,7 +10,9 @@ use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tauri::Manager;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
@@ -18,17 +20,52 @@ pub type DbConnection = r2d2::PooledConnection<ConnectionManager<SqliteConnectio
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
+/// How long a hot swap waits for in-flight queries to finish
+const QUIESCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Database pool handle that can be replaced while the app is running
+/// (e.g. after restoring a backup). Clones share the same slot, so
+/// background tasks pick up the new pool as well.
+#[derive(Clone)]
+pub struct SharedDbPool(Arc<RwLock<Option<DbPool>>>);
+
+impl SharedDbPool {
+    pub fn new(pool: DbPool) -> Self {
+        Self(Arc::new(RwLock::new(Some(pool))))
+    }
+
+    /// No database yet; checkouts fail until one is installed
+    pub fn empty() -> Self {
+        Self(Arc::new(RwLock::new(None)))
+    }
+
+    /// Start using `pool` (e.g. once startup has opened the database)
+    pub fn install(&self, pool: DbPool) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Some(pool);
+    }
+
+    /// Check out a connection from the current pool
+    pub fn get(&self) -> Result<DbConnection, String> {
+        let slot = self.0.read().unwrap_or_else(|e| e.into_inner());
+        slot.as_ref()
+            .ok_or_else(|| "Database is unavailable".to_string())?
+            .get()
+            .map_err(|e| e.to_string())
+    }
+}
+
 /// Get the database path in the app data directory
-pub fn get_database_path(app_handle: &tauri::AppHandle) -> PathBuf {
+pub fn get_database_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
-        .expect("Failed to get app data directory");
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
 
     // Create directory if it doesn't exist
-    std::fs::create_dir_all(&app_data_dir).expect("Failed to create app data directory");
+    std::fs::create_dir_all(&app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
 
-    app_data_dir.join("astra.db")
+    Ok(app_data_dir.join("astra.db"))
 }
 
 /// Establish a connection pool to the SQLite database
@@ -57,3 +94,122 @@ pub fn init_database(database_path: &PathBuf) -> Result<DbPool, Box<dyn std::err
 
     Ok(pool)
 }
+
+/// Remove SQLite side files that belong to the database being replaced
+fn remove_sqlite_side_files(database_path: &Path) {
+    for suffix in ["-journal", "-wal", "-shm"] {
+        let mut side = database_path.as_os_str().to_owned();
+        side.push(suffix);
+        let _ = std::fs::remove_file(PathBuf::from(side));
+    }
+}
+
+/// Replace the live database with `staged_path` without restarting.
+///
+/// Blocks new checkouts, waits for every pooled connection to be returned,
+/// closes the pool, atomically renames the staged file over the database,
+/// re-runs migrations, and installs a fresh pool. If the new database cannot
+/// be opened, the previous file is put back and reopened.
+pub fn hot_swap_database(
+    shared: &SharedDbPool,
+    database_path: &Path,
+    staged_path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut slot = shared.0.write().unwrap_or_else(|e| e.into_inner());
+
+    // Quiesce: holding every connection guarantees nothing is mid-query
+    if let Some(pool) = slot.as_ref() {
+        let mut held = Vec::with_capacity(pool.max_size() as usize);
+        for _ in 0..pool.max_size() {
+            held.push(pool.get_timeout(QUIESCE_TIMEOUT).map_err(|_| {
+                "Database is busy (a scan or import may be running); try again when it finishes"
+            })?);
+        }
+    }
+    // Dropping the last pool handle closes its connections
+    slot.take();
+
+    let previous = database_path.with_extension("db.swap-old");
+    if database_path.exists() {
+        std::fs::copy(database_path, &previous)?;
+    }
+    remove_sqlite_side_files(database_path);
+
+    let swapped = std::fs::rename(staged_path, database_path)
+        .map_err(|e| e.into())
+        .and_then(|_| init_database(&database_path.to_path_buf()));
+
+    match swapped {
+        Ok(pool) => {
+            *slot = Some(pool);
+            let _ = std::fs::remove_file(&previous);
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Database swap failed, reopening previous database: {}", e);
+            if previous.exists() {
+                remove_sqlite_side_files(database_path);
+                let _ = std::fs::rename(&previous, database_path);
+            }
+            *slot = init_database(&database_path.to_path_buf()).ok();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn collection_names(shared: &SharedDbPool) -> Vec<String> {
+        let mut conn = shared.get().unwrap();
+        schema::collections::table
+            .select(schema::collections::name)
+            .load(&mut conn)
+            .unwrap()
+    }
+
+    fn seed_database(path: &Path, collection: &str) {
+        let pool = init_database(&path.to_path_buf()).unwrap();
+        let mut conn = pool.get().unwrap();
+        diesel::sql_query(format!(
+            "INSERT INTO collections (id, user_id, name) VALUES ('{0}', 'local-user', '{0}')",
+            collection
+        ))
+        .execute(&mut conn)
+        .unwrap();
+    }
+
+    #[test]
+    fn hot_swap_replaces_live_database() {
+        let dir = TempDir::new().unwrap();
+        let live = dir.path().join("astra.db");
+        let staged = dir.path().join("astra.db.restoring");
+        seed_database(&live, "old");
+        seed_database(&staged, "restored");
+
+        let shared = SharedDbPool::new(init_database(&live).unwrap());
+        let background = shared.clone();
+        assert_eq!(collection_names(&shared), vec!["old"]);
+
+        hot_swap_database(&shared, &live, &staged).unwrap();
+
+        assert_eq!(collection_names(&shared), vec!["restored"]);
+        // Clones held by background tasks see the new database too
+        assert_eq!(collection_names(&background), vec!["restored"]);
+        assert!(!staged.exists());
+        assert!(!live.with_extension("db.swap-old").exists());
+    }
+
+    #[test]
+    fn hot_swap_keeps_previous_database_when_staged_file_is_missing() {
+        let dir = TempDir::new().unwrap();
+        let live = dir.path().join("astra.db");
+        seed_database(&live, "old");
+
+        let shared = SharedDbPool::new(init_database(&live).unwrap());
+        assert!(hot_swap_database(&shared, &live, &dir.path().join("missing.db")).is_err());
+        assert_eq!(collection_names(&shared), vec!["old"]);
+    }
+}
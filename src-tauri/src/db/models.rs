@@ -78,6 +78,11 @@ pub struct Collection {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub archived: bool,
+    /// Image shown as the collection's cover
+    pub cover_image_id: Option<String>,
+    /// Default image order (see `repository::ImageSort`); newest added
+    /// first when None
+    pub image_sort: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -95,6 +100,27 @@ pub struct NewCollection {
     pub archived: bool,
 }
 
+#[cfg(test)]
+impl NewCollection {
+    /// A bare private collection named after its id, belonging to
+    /// "user-1", for tests to fill in with
+    /// `NewCollection { .., ..NewCollection::test(id) }`
+    pub fn test(id: &str) -> Self {
+        NewCollection {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            name: id.to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
 #[diesel(table_name = collections)]
 pub struct UpdateCollection {
@@ -135,6 +161,70 @@ pub struct Image {
     pub thumbnail: Option<String>,
     pub fits_url: Option<String>,
     pub blob_id: Option<String>,
+    /// Sub-frame exposure in seconds
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    /// FILTER header as written by the capture software
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    /// DATE-OBS header (UTC start of the observation)
+    pub date_obs: Option<String>,
+    /// Plate solution as JSON (see `solvers::wcs::Wcs`)
+    pub wcs: Option<String>,
+    /// Stacked image this Light frame went into
+    pub parent_image_id: Option<String>,
+    /// Import triage status: "pending" review or "discarded"; None for
+    /// normal images
+    pub triage: Option<String>,
+    /// Star rating, 1-5; None when unrated
+    pub rating: Option<i32>,
+    /// Saved location the image was taken at, when one matched
+    pub capture_site_id: Option<String>,
+    /// Coordinates of the capture site, kept if the location is deleted
+    pub capture_latitude: Option<f64>,
+    pub capture_longitude: Option<f64>,
+}
+
+#[cfg(test)]
+impl Image {
+    /// A bare image "<id>.fit" belonging to "user-1", for tests to fill in
+    /// with `Image { .., ..Image::test(id) }`
+    pub fn test(id: &str) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        Image {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            collection_id: None,
+            filename: format!("{}.fit", id),
+            url: None,
+            summary: None,
+            description: None,
+            content_type: None,
+            favorite: false,
+            tags: None,
+            visibility: None,
+            location: None,
+            annotations: None,
+            metadata: None,
+            created_at: now,
+            updated_at: now,
+            thumbnail: None,
+            fits_url: None,
+            blob_id: None,
+            exposure: None,
+            gain: None,
+            filter: None,
+            telescope: None,
+            date_obs: None,
+            wcs: None,
+            parent_image_id: None,
+            triage: None,
+            rating: None,
+            capture_site_id: None,
+            capture_latitude: None,
+            capture_longitude: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -157,6 +247,49 @@ pub struct NewImage {
     pub thumbnail: Option<String>,
     pub fits_url: Option<String>,
     pub blob_id: Option<String>,
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    pub date_obs: Option<String>,
+    pub wcs: Option<String>,
+    pub parent_image_id: Option<String>,
+    pub triage: Option<String>,
+}
+
+#[cfg(test)]
+impl NewImage {
+    /// A bare image "<id>.fit" belonging to "user-1", for tests to fill in
+    /// with `NewImage { .., ..NewImage::test(id) }`
+    pub fn test(id: &str) -> Self {
+        NewImage {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            collection_id: None,
+            filename: format!("{}.fit", id),
+            url: None,
+            summary: None,
+            description: None,
+            content_type: None,
+            favorite: false,
+            tags: None,
+            visibility: None,
+            location: None,
+            annotations: None,
+            metadata: None,
+            thumbnail: None,
+            fits_url: None,
+            blob_id: None,
+            exposure: None,
+            gain: None,
+            filter: None,
+            telescope: None,
+            date_obs: None,
+            wcs: None,
+            parent_image_id: None,
+            triage: None,
+        }
+    }
 }
 
 // Note: For Insertable, field order doesn't strictly matter as Diesel uses field names,
@@ -180,6 +313,12 @@ pub struct UpdateImage {
     pub thumbnail: Option<String>,
     pub fits_url: Option<String>,
     pub blob_id: Option<String>,
+    pub exposure: Option<f64>,
+    pub gain: Option<i32>,
+    pub filter: Option<String>,
+    pub telescope: Option<String>,
+    pub date_obs: Option<String>,
+    pub wcs: Option<String>,
 }
 
 // ============================================================================
@@ -194,6 +333,9 @@ pub struct CollectionImage {
     pub collection_id: String,
     pub image_id: String,
     pub created_at: NaiveDateTime,
+    /// Manual position in the collection; None until the collection is
+    /// reordered (or for images added since)
+    pub sort_order: Option<i32>,
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -230,6 +372,10 @@ pub struct AstronomyTodo {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub tags: Option<String>,
+    /// Exposure plan as JSON (see `commands::exposure_plan::ExposurePlan`)
+    pub exposure_plan: Option<String>,
+    /// Chosen framing as JSON (see [`Framing`])
+    pub framing: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -269,6 +415,8 @@ pub struct UpdateAstronomyTodo {
     pub flagged: Option<bool>,
     pub last_updated: Option<String>,
     pub tags: Option<String>,
+    pub exposure_plan: Option<String>,
+    pub framing: Option<String>,
 }
 
 // ============================================================================
@@ -290,6 +438,7 @@ pub struct ObservationSchedule {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub equipment_id: Option<String>,
+    pub archived: bool,
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -304,6 +453,7 @@ pub struct NewObservationSchedule {
     pub items: String,
     pub is_active: bool,
     pub equipment_id: Option<String>,
+    pub archived: bool,
 }
 
 #[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
@@ -316,6 +466,7 @@ pub struct UpdateObservationSchedule {
     pub items: Option<String>,
     pub is_active: Option<bool>,
     pub equipment_id: Option<String>,
+    pub archived: Option<bool>,
 }
 
 /// Schedule item stored as JSON in the items field
@@ -329,6 +480,35 @@ pub struct ScheduleItem {
     pub priority: i32,
     pub notes: Option<String>,
     pub completed: bool,
+    /// Framing chosen for this slot, when it differs from the todo's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framing: Option<Framing>,
+    /// Planned length; when set the end time follows from the start instead
+    /// of being fixed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<i64>,
+    /// Items that must finish first; the item starts when the last of them
+    /// ends rather than at a fixed time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+/// Where to point and how to turn the camera for a target, from the framing
+/// assistant (`commands::framing`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Framing {
+    /// Field center in degrees
+    pub center_ra: f64,
+    pub center_dec: f64,
+    /// Camera angle in degrees east of north
+    pub rotation: f64,
+    /// Camera field in degrees
+    pub field_width: f64,
+    pub field_height: f64,
+    /// Equipment profile the field was computed for
+    pub profile_id: Option<String>,
+    pub planned_at: String,
 }
 
 // ============================================================================
@@ -401,8 +581,14 @@ pub struct ScannedDirectory {
     pub fs_modified_at: i64,
     /// When we last scanned this directory (ISO 8601 string)
     pub last_scanned_at: String,
-    /// Number of images found in this directory
+    /// Number of images found in this directory by the last scan
     pub image_count: i32,
+    /// Images the last scan imported from this directory
+    pub last_imported: i32,
+    /// Images the last scan failed to import
+    pub last_failed: i32,
+    /// Fingerprint of the image files found by the last scan
+    pub listing_hash: Option<String>,
 }
 
 #[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
@@ -414,6 +600,9 @@ pub struct NewScannedDirectory {
     pub fs_modified_at: i64,
     pub last_scanned_at: String,
     pub image_count: i32,
+    pub last_imported: i32,
+    pub last_failed: i32,
+    pub listing_hash: Option<String>,
 }
 
 #[derive(Debug, Clone, AsChangeset, Serialize, Deserialize)]
@@ -422,4 +611,618 @@ pub struct UpdateScannedDirectory {
     pub fs_modified_at: Option<i64>,
     pub last_scanned_at: Option<String>,
     pub image_count: Option<i32>,
+    pub last_imported: Option<i32>,
+    pub last_failed: Option<i32>,
+    pub listing_hash: Option<Option<String>>,
+}
+
+// ============================================================================
+// Equipment - Telescopes, cameras, filters, mounts, reducers
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = equipment)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Equipment {
+    pub id: String,
+    pub user_id: String,
+    /// One of: telescope, camera, filter, mount, reducer
+    pub kind: String,
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    /// Comma-separated FITS header values (TELESCOP/INSTRUME) this item matches
+    pub match_patterns: Option<String>,
+    pub focal_length_mm: Option<f64>,
+    pub aperture_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<i32>,
+    pub sensor_height_px: Option<i32>,
+    pub reduction_factor: Option<f64>,
+    pub notes: Option<String>,
+    pub metadata: Option<String>,
+    pub retired: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[cfg(test)]
+impl Equipment {
+    /// A bare, active item belonging to "user-1", for tests to fill in with
+    /// `Equipment { .., ..Equipment::test(id, kind, name) }`
+    pub fn test(id: &str, kind: &str, name: &str) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        Equipment {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            kind: kind.to_string(),
+            name: name.to_string(),
+            manufacturer: None,
+            model: None,
+            match_patterns: None,
+            focal_length_mm: None,
+            aperture_mm: None,
+            pixel_size_um: None,
+            sensor_width_px: None,
+            sensor_height_px: None,
+            reduction_factor: None,
+            notes: None,
+            metadata: None,
+            retired: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = equipment)]
+pub struct NewEquipment {
+    pub id: String,
+    pub user_id: String,
+    pub kind: String,
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub match_patterns: Option<String>,
+    pub focal_length_mm: Option<f64>,
+    pub aperture_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<i32>,
+    pub sensor_height_px: Option<i32>,
+    pub reduction_factor: Option<f64>,
+    pub notes: Option<String>,
+    pub metadata: Option<String>,
+    pub retired: bool,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = equipment)]
+pub struct UpdateEquipment {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub match_patterns: Option<String>,
+    pub focal_length_mm: Option<f64>,
+    pub aperture_mm: Option<f64>,
+    pub pixel_size_um: Option<f64>,
+    pub sensor_width_px: Option<i32>,
+    pub sensor_height_px: Option<i32>,
+    pub reduction_factor: Option<f64>,
+    pub notes: Option<String>,
+    pub metadata: Option<String>,
+    pub retired: Option<bool>,
+}
+
+// ============================================================================
+// EquipmentProfile - A rig with plate solving and processing presets
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = equipment_profiles)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct EquipmentProfile {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub telescope_id: Option<String>,
+    pub camera_id: Option<String>,
+    pub reducer_id: Option<String>,
+    /// Expected image scale bounds (arcsec/pixel); derived from optics when None
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    /// JSON object of ProcessingParams overrides (camelCase keys)
+    pub processing_params: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = equipment_profiles)]
+pub struct NewEquipmentProfile {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub telescope_id: Option<String>,
+    pub camera_id: Option<String>,
+    pub reducer_id: Option<String>,
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    pub processing_params: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = equipment_profiles)]
+pub struct UpdateEquipmentProfile {
+    pub name: Option<String>,
+    pub telescope_id: Option<String>,
+    pub camera_id: Option<String>,
+    pub reducer_id: Option<String>,
+    pub scale_lower: Option<f64>,
+    pub scale_upper: Option<f64>,
+    pub processing_params: Option<String>,
+    pub notes: Option<String>,
+}
+
+// ============================================================================
+// ProcessingPreset - Named, reusable processing parameters
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = processing_presets)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ProcessingPreset {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Target type the preset is meant for; None applies to any target
+    pub target_type: Option<String>,
+    /// JSON object of ProcessingParams (camelCase keys)
+    pub params: String,
+    /// Default preset for its target type
+    pub is_default: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = processing_presets)]
+pub struct NewProcessingPreset {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub target_type: Option<String>,
+    pub params: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = processing_presets)]
+pub struct UpdateProcessingPreset {
+    pub name: Option<String>,
+    pub target_type: Option<String>,
+    pub params: Option<String>,
+}
+
+// ============================================================================
+// SavedSearch - Named search criteria over images or todos
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = saved_searches)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SavedSearch {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// What the search runs over: "images" or "todos"
+    pub entity: String,
+    /// JSON object of SearchCriteria (camelCase keys)
+    pub criteria: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Shown on the Home screen
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = saved_searches)]
+pub struct NewSavedSearch {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub entity: String,
+    pub criteria: String,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = saved_searches)]
+pub struct UpdateSavedSearch {
+    pub name: Option<String>,
+    pub entity: Option<String>,
+    pub criteria: Option<String>,
+    pub pinned: Option<bool>,
+}
+
+// ============================================================================
+// MaintenanceEntry - Equipment maintenance log with due intervals
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = maintenance_entries)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MaintenanceEntry {
+    pub id: String,
+    pub user_id: String,
+    pub equipment_id: String,
+    /// e.g. "Collimation", "Desiccant regeneration", "Firmware update"
+    pub task: String,
+    /// YYYY-MM-DD
+    pub performed_on: String,
+    /// Days until the task is due again; None for one-off work
+    pub interval_days: Option<i32>,
+    pub notes: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = maintenance_entries)]
+pub struct NewMaintenanceEntry {
+    pub id: String,
+    pub user_id: String,
+    pub equipment_id: String,
+    pub task: String,
+    pub performed_on: String,
+    pub interval_days: Option<i32>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = maintenance_entries)]
+pub struct UpdateMaintenanceEntry {
+    pub task: Option<String>,
+    pub performed_on: Option<String>,
+    /// `Some(None)` makes the task one-off
+    pub interval_days: Option<Option<i32>>,
+    /// `Some(None)` clears the notes
+    pub notes: Option<Option<String>>,
+}
+
+// ============================================================================
+// Note - Markdown notes on images, collections, sessions, targets, equipment
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = notes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Note {
+    pub id: String,
+    pub user_id: String,
+    /// One of: image, collection, session, target, equipment
+    pub entity_type: String,
+    /// Record id; the night (YYYY-MM-DD) for sessions, the canonical object
+    /// name for targets
+    pub entity_id: String,
+    /// Markdown
+    pub body: String,
+    /// JSON array of attachments (see `commands::notes::NoteAttachment`)
+    pub attachments: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = notes)]
+pub struct NewNote {
+    pub id: String,
+    pub user_id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub body: String,
+    pub attachments: Option<String>,
+}
+
+#[derive(Debug, Clone, AsChangeset, Serialize, Deserialize, Default)]
+#[diesel(table_name = notes)]
+pub struct UpdateNote {
+    pub body: Option<String>,
+    /// `Some(None)` clears the attachments
+    pub attachments: Option<Option<String>>,
+}
+
+// ============================================================================
+// PackingItem - Field-kit checklist for a schedule
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = packing_items)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PackingItem {
+    pub id: String,
+    pub user_id: String,
+    pub schedule_id: String,
+    pub name: String,
+    /// One of: optics, camera, mount, power, dew, cables, consumables, other
+    pub category: String,
+    /// Equipment item the entry was generated from, if any
+    pub equipment_id: Option<String>,
+    pub packed: bool,
+    pub sort_order: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = packing_items)]
+pub struct NewPackingItem {
+    pub id: String,
+    pub user_id: String,
+    pub schedule_id: String,
+    pub name: String,
+    pub category: String,
+    pub equipment_id: Option<String>,
+    pub packed: bool,
+    pub sort_order: i32,
+}
+
+// ============================================================================
+// PinnedTarget - Targets shown on the Home screen
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = pinned_targets)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PinnedTarget {
+    pub id: String,
+    pub user_id: String,
+    /// Canonical object name
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = pinned_targets)]
+pub struct NewPinnedTarget {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+}
+
+// ============================================================================
+// SessionDigest - Nightly go/no-go verdict from forecast, moon and schedules
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = session_digests)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SessionDigest {
+    pub id: String,
+    pub user_id: String,
+    /// Local date the evening starts on (YYYY-MM-DD)
+    pub night: String,
+    /// One of: go, marginal, no-go
+    pub verdict: String,
+    pub summary: String,
+    /// JSON `commands::night_digest::NightDigest`
+    pub details: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = session_digests)]
+pub struct NewSessionDigest {
+    pub id: String,
+    pub user_id: String,
+    pub night: String,
+    pub verdict: String,
+    pub summary: String,
+    pub details: String,
+}
+
+// ============================================================================
+// JobMetric - Timing of one phase of a scan or auto-import job
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = job_metrics)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JobMetric {
+    pub id: String,
+    pub user_id: String,
+    pub job_id: String,
+    /// One of: scan, fits_parse, thumbnail, db_insert, total
+    pub phase: String,
+    pub count: i32,
+    /// Summed over parallel workers
+    pub total_ms: i64,
+    pub max_ms: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = job_metrics)]
+pub struct NewJobMetric {
+    pub id: String,
+    pub user_id: String,
+    pub job_id: String,
+    pub phase: String,
+    pub count: i32,
+    pub total_ms: i64,
+    pub max_ms: i64,
+}
+
+// ============================================================================
+// UiPreferences - Theme, layout and view choices, synced with the library
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = ui_preferences)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct UiPreferences {
+    pub user_id: String,
+    /// JSON object, keyed by whatever part of the UI owns the setting
+    pub preferences: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = ui_preferences)]
+pub struct NewUiPreferences {
+    pub user_id: String,
+    pub preferences: String,
+}
+
+// ============================================================================
+// Location - Observing sites
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = locations)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Location {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Metres above sea level
+    pub elevation: Option<f64>,
+    /// Bortle class 1-9
+    pub bortle: Option<i32>,
+    /// How the location was added: "manual", "gps" or "candidate" (a dark
+    /// site search suggestion)
+    pub source: String,
+    pub is_default: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = locations)]
+pub struct NewLocation {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+    pub bortle: Option<i32>,
+    pub source: String,
+    pub is_default: bool,
+}
+
+// ============================================================================
+// ImageEquipment (Join Table)
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = image_equipment)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImageEquipment {
+    pub id: String,
+    pub image_id: String,
+    pub equipment_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = image_equipment)]
+pub struct NewImageEquipment {
+    pub id: String,
+    pub image_id: String,
+    pub equipment_id: String,
+}
+
+// ============================================================================
+// Filter - Normalized filters parsed from FITS FILTER headers
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = filters)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Filter {
+    pub id: String,
+    pub user_id: String,
+    /// Canonical filter name, e.g. "Ha", "OIII", "L"
+    pub name: String,
+    /// One of: narrowband, dualband, broadband, other
+    pub band: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = filters)]
+pub struct NewFilter {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub band: String,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = image_filters)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImageFilter {
+    pub image_id: String,
+    pub filter_id: String,
+    /// FILTER header value as written by the capture software
+    pub raw_value: Option<String>,
+    pub frame_count: i32,
+    /// Total integration time in seconds (sub exposure * frame_count)
+    pub exposure_seconds: f64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = image_filters)]
+pub struct NewImageFilter {
+    pub image_id: String,
+    pub filter_id: String,
+    pub raw_value: Option<String>,
+    pub frame_count: i32,
+    pub exposure_seconds: f64,
+}
+
+// ============================================================================
+// Sync - Per-row change vectors and imported change bundles
+// ============================================================================
+
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = sync_state)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SyncState {
+    pub table_name: String,
+    pub row_id: String,
+    /// UTC time of the last change (ISO 8601 with milliseconds)
+    pub updated_at: String,
+    /// Tombstone: the row was deleted
+    pub deleted: bool,
+    /// Device the last change came from; None for local changes
+    pub origin: Option<String>,
+    /// Local change number, increasing with every change
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = sync_imports)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SyncImport {
+    pub bundle_id: String,
+    pub device_id: String,
+    pub change_count: i32,
+    pub imported_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = sync_imports)]
+pub struct NewSyncImport {
+    pub bundle_id: String,
+    pub device_id: String,
+    pub change_count: i32,
 }
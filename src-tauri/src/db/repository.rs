@@ -39,13 +39,37 @@ pub fn update_user(
 // Collection Repository
 // ============================================================================
 
+/// Get the user's collections, excluding archived ones
 pub fn get_collections(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Collection>> {
     collections::table
         .filter(collections::user_id.eq(user_id))
+        .filter(collections::archived.eq(false))
         .order(collections::created_at.desc())
         .load(conn)
 }
 
+/// Get all of the user's collections, including archived ones
+pub fn get_all_collections(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<Collection>> {
+    collections::table
+        .filter(collections::user_id.eq(user_id))
+        .order(collections::created_at.desc())
+        .load(conn)
+}
+
+pub fn get_archived_collections(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<Collection>> {
+    collections::table
+        .filter(collections::user_id.eq(user_id))
+        .filter(collections::archived.eq(true))
+        .order(collections::updated_at.desc())
+        .load(conn)
+}
+
 pub fn get_collection_by_id(
     conn: &mut SqliteConnection,
     collection_id: &str,
@@ -81,6 +105,43 @@ pub fn create_collection(
         .first(conn)
 }
 
+/// Create an auto-imported collection, or get the auto-imported collection
+/// of the same name if one exists.
+///
+/// Auto-imported collection names are unique per user, so imports racing to
+/// create the same session collection all end up with whichever was inserted
+/// first. Returns the collection and whether it was created.
+pub fn get_or_create_auto_collection(
+    conn: &mut SqliteConnection,
+    new_collection: &NewCollection,
+) -> QueryResult<(Collection, bool)> {
+    let inserted = diesel::insert_or_ignore_into(collections::table)
+        .values(new_collection)
+        .execute(conn)?;
+    if inserted > 0 {
+        let collection = collections::table
+            .filter(collections::id.eq(&new_collection.id))
+            .first(conn)?;
+        return Ok((collection, true));
+    }
+
+    let auto_imported = |c: &Collection| {
+        c.metadata
+            .as_deref()
+            .and_then(|m| serde_json::from_str::<serde_json::Value>(m).ok())
+            .and_then(|m| m.get("auto_imported")?.as_bool())
+            .unwrap_or(false)
+    };
+    collections::table
+        .filter(collections::user_id.eq(&new_collection.user_id))
+        .filter(collections::name.eq(&new_collection.name))
+        .load::<Collection>(conn)?
+        .into_iter()
+        .find(auto_imported)
+        .map(|c| (c, false))
+        .ok_or(diesel::result::Error::NotFound)
+}
+
 pub fn update_collection(
     conn: &mut SqliteConnection,
     collection_id: &str,
@@ -102,6 +163,77 @@ pub fn delete_collection(conn: &mut SqliteConnection, collection_id: &str) -> Qu
     diesel::delete(collections::table.filter(collections::id.eq(collection_id))).execute(conn)
 }
 
+pub fn set_collection_archived(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    archived: bool,
+) -> QueryResult<Collection> {
+    diesel::update(collections::table.filter(collections::id.eq(collection_id)))
+        .set((
+            collections::archived.eq(archived),
+            collections::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    collections::table
+        .filter(collections::id.eq(collection_id))
+        .first(conn)
+}
+
+/// Set or clear the image shown as a collection's cover
+pub fn set_collection_cover(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    image_id: Option<&str>,
+) -> QueryResult<Collection> {
+    diesel::update(collections::table.filter(collections::id.eq(collection_id)))
+        .set((
+            collections::cover_image_id.eq(image_id),
+            collections::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    collections::table
+        .filter(collections::id.eq(collection_id))
+        .first(conn)
+}
+
+/// Set the collection's default image order, or clear it with None
+pub fn set_collection_image_sort(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    sort: Option<ImageSort>,
+) -> QueryResult<Collection> {
+    diesel::update(collections::table.filter(collections::id.eq(collection_id)))
+        .set((
+            collections::image_sort.eq(sort.map(ImageSort::as_str)),
+            collections::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    collections::table
+        .filter(collections::id.eq(collection_id))
+        .first(conn)
+}
+
+/// Clear covers pointing at any of `image_ids`, limited to one collection if
+/// `collection_id` is given
+fn clear_collection_covers(
+    conn: &mut SqliteConnection,
+    collection_id: Option<&str>,
+    image_ids: &[String],
+) -> QueryResult<usize> {
+    let mut query = diesel::update(collections::table)
+        .filter(collections::cover_image_id.eq_any(image_ids))
+        .into_boxed();
+    if let Some(id) = collection_id {
+        query = query.filter(collections::id.eq(id));
+    }
+    query
+        .set(collections::cover_image_id.eq(None::<String>))
+        .execute(conn)
+}
+
 // ============================================================================
 // Image Repository
 // ============================================================================
@@ -161,6 +293,62 @@ pub fn get_image_by_url(
         .optional()
 }
 
+/// Find an image by its content-addressed blob ID
+pub fn get_image_by_blob_id(
+    conn: &mut SqliteConnection,
+    blob_id: &str,
+) -> QueryResult<Option<Image>> {
+    images::table
+        .filter(images::blob_id.eq(blob_id))
+        .first(conn)
+        .optional()
+}
+
+/// Processed images derived from a source image, newest first. Processing
+/// records the source as `source_image_id` in the processed image's metadata.
+pub fn get_processed_versions(
+    conn: &mut SqliteConnection,
+    source_image_id: &str,
+) -> QueryResult<Vec<Image>> {
+    let pattern = format!("%\"source_image_id\":\"{}\"%", source_image_id);
+    images::table
+        .filter(images::metadata.like(pattern))
+        .order(images::created_at.desc())
+        .load(conn)
+}
+
+/// Ids of the user's images that have at least one processed version
+pub fn get_processed_source_ids(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<std::collections::HashSet<String>> {
+    let metadata: Vec<Option<String>> = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::metadata.like("%\"source_image_id\"%"))
+        .select(images::metadata)
+        .load(conn)?;
+
+    Ok(metadata
+        .into_iter()
+        .flatten()
+        .filter_map(|meta| serde_json::from_str::<serde_json::Value>(&meta).ok())
+        .filter_map(|meta| meta.get("source_image_id")?.as_str().map(String::from))
+        .collect())
+}
+
+/// The user's images with a nova.astrometry.net solve in progress, recorded
+/// as `nova_job` in their metadata
+pub fn get_images_with_nova_jobs(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<Image>> {
+    images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::metadata.like("%\"nova_job\"%"))
+        .order(images::created_at.asc())
+        .load(conn)
+}
+
 /// Get all image URLs for a user (for efficient duplicate checking during bulk import)
 pub fn get_all_image_urls(
     conn: &mut SqliteConnection,
@@ -250,13 +438,112 @@ pub fn update_image(
     images::table.filter(images::id.eq(image_id)).first(conn)
 }
 
+/// Set an image's star rating, or clear it with None
+pub fn set_image_rating(
+    conn: &mut SqliteConnection,
+    image_id: &str,
+    rating: Option<i32>,
+) -> QueryResult<Image> {
+    diesel::update(images::table.filter(images::id.eq(image_id)))
+        .set((
+            images::rating.eq(rating),
+            images::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    images::table.filter(images::id.eq(image_id)).first(conn)
+}
+
 pub fn delete_image(conn: &mut SqliteConnection, image_id: &str) -> QueryResult<usize> {
     // Also delete from collection_images join table
     diesel::delete(collection_images::table.filter(collection_images::image_id.eq(image_id)))
         .execute(conn)?;
+    clear_collection_covers(conn, None, &[image_id.to_string()])?;
+    // Subs of a deleted stack go back to being listed on their own
+    diesel::update(images::table.filter(images::parent_image_id.eq(image_id)))
+        .set(images::parent_image_id.eq(None::<String>))
+        .execute(conn)?;
     diesel::delete(images::table.filter(images::id.eq(image_id))).execute(conn)
 }
 
+/// Light frames linked to a stacked image, in capture order
+pub fn get_image_subframes(
+    conn: &mut SqliteConnection,
+    parent_id: &str,
+) -> QueryResult<Vec<Image>> {
+    images::table
+        .filter(images::parent_image_id.eq(parent_id))
+        .order((images::date_obs.asc(), images::filename.asc()))
+        .load(conn)
+}
+
+/// Link images to the stack they went into, or unlink them with None
+pub fn set_image_parent(
+    conn: &mut SqliteConnection,
+    image_ids: &[String],
+    parent_id: Option<&str>,
+) -> QueryResult<usize> {
+    diesel::update(images::table.filter(images::id.eq_any(image_ids)))
+        .set(images::parent_image_id.eq(parent_id))
+        .execute(conn)
+}
+
+/// Record where images were taken: a saved location and/or its coordinates.
+/// None for all three clears the capture site.
+pub fn set_image_capture_site(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_ids: &[String],
+    site_id: Option<&str>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> QueryResult<usize> {
+    diesel::update(
+        images::table
+            .filter(images::user_id.eq(user_id))
+            .filter(images::id.eq_any(image_ids)),
+    )
+    .set((
+        images::capture_site_id.eq(site_id),
+        images::capture_latitude.eq(latitude),
+        images::capture_longitude.eq(longitude),
+        images::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(conn)
+}
+
+/// Images with the given import triage status, newest first
+pub fn get_images_by_triage(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    status: &str,
+) -> QueryResult<Vec<Image>> {
+    images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::triage.eq(status))
+        .order(images::created_at.desc())
+        .load(conn)
+}
+
+/// Set the import triage status of images; None returns them to the library
+pub fn set_image_triage(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_ids: &[String],
+    status: Option<&str>,
+) -> QueryResult<usize> {
+    diesel::update(
+        images::table
+            .filter(images::user_id.eq(user_id))
+            .filter(images::id.eq_any(image_ids)),
+    )
+    .set((
+        images::triage.eq(status),
+        images::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(conn)
+}
+
 // ============================================================================
 // CollectionImage Repository (Many-to-Many)
 // ============================================================================
@@ -275,12 +562,13 @@ pub fn add_image_to_collection(
         .first(conn)
 }
 
-/// Remove an image from a collection
+/// Remove an image from a collection, clearing the cover if it was the image
 pub fn remove_image_from_collection(
     conn: &mut SqliteConnection,
     collection_id: &str,
     image_id: &str,
 ) -> QueryResult<usize> {
+    clear_collection_covers(conn, Some(collection_id), &[image_id.to_string()])?;
     diesel::delete(
         collection_images::table
             .filter(collection_images::collection_id.eq(collection_id))
@@ -344,6 +632,135 @@ pub fn get_images_in_collection(
     result
 }
 
+/// Order of a collection's images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSort {
+    /// By DATE-OBS, falling back to when the image was added
+    CaptureTime,
+    /// By file name
+    Name,
+    /// Highest rated first, unrated last
+    Rating,
+    /// As arranged with `set_collection_image_order`
+    Manual,
+}
+
+impl ImageSort {
+    /// Value stored in `collections.image_sort`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageSort::CaptureTime => "capture_time",
+            ImageSort::Name => "name",
+            ImageSort::Rating => "rating",
+            ImageSort::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "capture_time" => Some(ImageSort::CaptureTime),
+            "name" => Some(ImageSort::Name),
+            "rating" => Some(ImageSort::Rating),
+            "manual" => Some(ImageSort::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Sort images in place. `positions` holds each image's manual position
+/// and when it was added to the collection, for `ImageSort::Manual`.
+pub fn sort_images(
+    images: &mut [Image],
+    sort: ImageSort,
+    positions: &std::collections::HashMap<String, (Option<i32>, chrono::NaiveDateTime)>,
+) {
+    match sort {
+        ImageSort::CaptureTime => images.sort_by_cached_key(|image| {
+            image
+                .date_obs
+                .clone()
+                .unwrap_or_else(|| image.created_at.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+        }),
+        ImageSort::Name => images.sort_by_cached_key(|image| image.filename.to_lowercase()),
+        ImageSort::Rating => {
+            images.sort_by_key(|image| std::cmp::Reverse(image.rating.unwrap_or(0)))
+        }
+        // Placed images first, then the rest in the order they were added
+        ImageSort::Manual => images.sort_by_key(|image| {
+            positions
+                .get(&image.id)
+                .map(|(position, added)| (position.is_none(), *position, *added))
+        }),
+    }
+}
+
+/// Images in a collection, in the collection's chosen order (newest added
+/// first when it has none)
+pub fn get_sorted_collection_images(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+) -> QueryResult<Vec<Image>> {
+    let mut images = get_images_in_collection(conn, collection_id)?;
+    let sort: Option<String> = collections::table
+        .filter(collections::id.eq(collection_id))
+        .select(collections::image_sort)
+        .first::<Option<String>>(conn)
+        .optional()?
+        .flatten();
+    let Some(sort) = sort.as_deref().and_then(ImageSort::parse) else {
+        return Ok(images);
+    };
+
+    let positions = if sort == ImageSort::Manual {
+        collection_images::table
+            .filter(collection_images::collection_id.eq(collection_id))
+            .select((
+                collection_images::image_id,
+                collection_images::sort_order,
+                collection_images::created_at,
+            ))
+            .load::<(String, Option<i32>, chrono::NaiveDateTime)>(conn)?
+            .into_iter()
+            .map(|(image_id, position, added)| (image_id, (position, added)))
+            .collect()
+    } else {
+        std::collections::HashMap::new()
+    };
+    sort_images(&mut images, sort, &positions);
+    Ok(images)
+}
+
+/// Arrange a collection's images manually: `image_ids` take positions in
+/// the order given, and any left out follow them. Switches the collection
+/// to manual sorting.
+pub fn set_collection_image_order(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    image_ids: &[String],
+) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        diesel::update(
+            collection_images::table
+                .filter(collection_images::collection_id.eq(collection_id))
+                .filter(collection_images::image_id.ne_all(image_ids)),
+        )
+        .set(collection_images::sort_order.eq(None::<i32>))
+        .execute(conn)?;
+        for (position, image_id) in image_ids.iter().enumerate() {
+            diesel::update(
+                collection_images::table
+                    .filter(collection_images::collection_id.eq(collection_id))
+                    .filter(collection_images::image_id.eq(image_id)),
+            )
+            .set(collection_images::sort_order.eq(position as i32))
+            .execute(conn)?;
+        }
+        set_collection_image_sort(conn, collection_id, Some(ImageSort::Manual))?;
+        Ok(())
+    })
+}
+
 /// Get collections for an image with full collection data
 pub fn get_collections_for_image(
     conn: &mut SqliteConnection,
@@ -386,6 +803,114 @@ pub fn is_image_in_collection(
     Ok(count > 0)
 }
 
+/// Add images to a collection, skipping those already in it. Returns how
+/// many were added.
+fn add_images_to_collection(
+    conn: &mut SqliteConnection,
+    collection_id: &str,
+    image_ids: &[String],
+) -> QueryResult<usize> {
+    let mut seen: std::collections::HashSet<String> =
+        get_collection_image_ids(conn, collection_id)?
+            .into_iter()
+            .collect();
+    let entries: Vec<NewCollectionImage> = image_ids
+        .iter()
+        .filter(|id| seen.insert(id.to_string()))
+        .map(|image_id| NewCollectionImage {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_id: collection_id.to_string(),
+            image_id: image_id.clone(),
+        })
+        .collect();
+    diesel::insert_into(collection_images::table)
+        .values(&entries)
+        .execute(conn)
+}
+
+/// Move an image's legacy primary collection along with its membership
+fn reassign_primary_collection(
+    conn: &mut SqliteConnection,
+    from_id: &str,
+    to_id: &str,
+    image_ids: Option<&[String]>,
+) -> QueryResult<usize> {
+    let mut query = diesel::update(images::table)
+        .filter(images::collection_id.eq(from_id))
+        .into_boxed();
+    if let Some(ids) = image_ids {
+        query = query.filter(images::id.eq_any(ids));
+    }
+    query.set(images::collection_id.eq(to_id)).execute(conn)
+}
+
+/// Merge `source_id` into `target_id`: its images join the target (once,
+/// if they were in both) and the source collection is deleted. Returns how
+/// many images were new to the target.
+pub fn merge_collections(
+    conn: &mut SqliteConnection,
+    target_id: &str,
+    source_id: &str,
+) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let image_ids = get_collection_image_ids(conn, source_id)?;
+        let added = add_images_to_collection(conn, target_id, &image_ids)?;
+        reassign_primary_collection(conn, source_id, target_id, None)?;
+        // Notes on the source carry over to the target
+        diesel::update(notes::table)
+            .filter(notes::entity_type.eq("collection"))
+            .filter(notes::entity_id.eq(source_id))
+            .set(notes::entity_id.eq(target_id))
+            .execute(conn)?;
+        delete_collection(conn, source_id)?;
+        Ok(added)
+    })
+}
+
+/// Create `new_collection` as a copy of `source_id`'s settings, with its
+/// images too if `include_images` is set
+pub fn duplicate_collection(
+    conn: &mut SqliteConnection,
+    source_id: &str,
+    new_collection: &NewCollection,
+    include_images: bool,
+) -> QueryResult<Collection> {
+    conn.transaction(|conn| {
+        let collection = create_collection(conn, new_collection)?;
+        if include_images {
+            let image_ids = get_collection_image_ids(conn, source_id)?;
+            add_images_to_collection(conn, &collection.id, &image_ids)?;
+        }
+        Ok(collection)
+    })
+}
+
+/// Split images out of `source_id` into new collections, each created with
+/// the images listed for it. Images not listed stay in the source.
+pub fn split_collection(
+    conn: &mut SqliteConnection,
+    source_id: &str,
+    parts: &[(NewCollection, Vec<String>)],
+) -> QueryResult<Vec<Collection>> {
+    conn.transaction(|conn| {
+        let mut created = Vec::with_capacity(parts.len());
+        for (new_collection, image_ids) in parts {
+            let collection = create_collection(conn, new_collection)?;
+            add_images_to_collection(conn, &collection.id, image_ids)?;
+            diesel::delete(
+                collection_images::table
+                    .filter(collection_images::collection_id.eq(source_id))
+                    .filter(collection_images::image_id.eq_any(image_ids)),
+            )
+            .execute(conn)?;
+            clear_collection_covers(conn, Some(source_id), image_ids)?;
+            reassign_primary_collection(conn, source_id, &collection.id, Some(image_ids))?;
+            created.push(collection);
+        }
+        Ok(created)
+    })
+}
+
 // ============================================================================
 // AstronomyTodo Repository
 // ============================================================================
@@ -471,10 +996,26 @@ pub fn get_schedules(
 ) -> QueryResult<Vec<ObservationSchedule>> {
     observation_schedules::table
         .filter(observation_schedules::user_id.eq(user_id))
+        .filter(observation_schedules::archived.eq(false))
         .order(observation_schedules::created_at.desc())
         .load(conn)
 }
 
+/// Get archived schedules, most recent night first
+pub fn get_archived_schedules(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<ObservationSchedule>> {
+    observation_schedules::table
+        .filter(observation_schedules::user_id.eq(user_id))
+        .filter(observation_schedules::archived.eq(true))
+        .order((
+            observation_schedules::scheduled_date.desc(),
+            observation_schedules::created_at.desc(),
+        ))
+        .load(conn)
+}
+
 pub fn get_active_schedule(
     conn: &mut SqliteConnection,
     user_id: &str,
@@ -482,11 +1023,12 @@ pub fn get_active_schedule(
     observation_schedules::table
         .filter(observation_schedules::user_id.eq(user_id))
         .filter(observation_schedules::is_active.eq(true))
+        .order(observation_schedules::created_at.desc())
         .first(conn)
         .optional()
 }
 
-/// Get all active schedules for a user (one per equipment set)
+/// Get all active schedules for a user (one per equipment set and night)
 pub fn get_active_schedules(
     conn: &mut SqliteConnection,
     user_id: &str,
@@ -494,6 +1036,23 @@ pub fn get_active_schedules(
     observation_schedules::table
         .filter(observation_schedules::user_id.eq(user_id))
         .filter(observation_schedules::is_active.eq(true))
+        .order((
+            observation_schedules::scheduled_date.asc(),
+            observation_schedules::created_at.desc(),
+        ))
+        .load(conn)
+}
+
+/// Get the active schedules for the night of `date` (YYYY-MM-DD)
+pub fn get_active_schedules_for_night(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    date: &str,
+) -> QueryResult<Vec<ObservationSchedule>> {
+    observation_schedules::table
+        .filter(observation_schedules::user_id.eq(user_id))
+        .filter(observation_schedules::is_active.eq(true))
+        .filter(observation_schedules::scheduled_date.like(format!("{}%", schedule_night(date))))
         .order(observation_schedules::created_at.desc())
         .load(conn)
 }
@@ -508,42 +1067,65 @@ pub fn get_schedule_by_id(
         .optional()
 }
 
+/// Night a schedule is for: the date part of its scheduled date
+fn schedule_night(scheduled_date: &str) -> &str {
+    scheduled_date.get(..10).unwrap_or(scheduled_date)
+}
+
+/// Deactivate the user's other active schedules for the same equipment set
+/// and night (both matched as null when unset), so at most one is active
+/// for each
+fn deactivate_competing_schedules(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    schedule_id: &str,
+    equipment_id: Option<&str>,
+    scheduled_date: Option<&str>,
+) -> QueryResult<usize> {
+    let mut query = observation_schedules::table
+        .select(observation_schedules::id)
+        .filter(observation_schedules::user_id.eq(user_id))
+        .filter(observation_schedules::is_active.eq(true))
+        .filter(observation_schedules::id.ne(schedule_id))
+        .into_boxed();
+    query = match equipment_id {
+        Some(eq_id) => query.filter(observation_schedules::equipment_id.eq(eq_id)),
+        None => query.filter(observation_schedules::equipment_id.is_null()),
+    };
+    query = match scheduled_date {
+        Some(date) => query.filter(
+            observation_schedules::scheduled_date.like(format!("{}%", schedule_night(date))),
+        ),
+        None => query.filter(observation_schedules::scheduled_date.is_null()),
+    };
+    let competing: Vec<String> = query.load(conn)?;
+
+    diesel::update(
+        observation_schedules::table.filter(observation_schedules::id.eq_any(&competing)),
+    )
+    .set(observation_schedules::is_active.eq(false))
+    .execute(conn)
+}
+
 pub fn create_schedule(
     conn: &mut SqliteConnection,
     new_schedule: &NewObservationSchedule,
 ) -> QueryResult<ObservationSchedule> {
-    // If this schedule is active, deactivate other schedules with the same equipment_id
+    // Archived schedules are never active
+    let mut new_schedule = new_schedule.clone();
+    new_schedule.is_active &= !new_schedule.archived;
     if new_schedule.is_active {
-        // Only deactivate schedules with matching equipment_id (or both null)
-        if let Some(ref eq_id) = new_schedule.equipment_id {
-            // Deactivate schedules with the same equipment_id
-            diesel::update(
-                observation_schedules::table.filter(
-                    observation_schedules::user_id
-                        .eq(&new_schedule.user_id)
-                        .and(observation_schedules::is_active.eq(true))
-                        .and(observation_schedules::equipment_id.eq(eq_id)),
-                ),
-            )
-            .set(observation_schedules::is_active.eq(false))
-            .execute(conn)?;
-        } else {
-            // Deactivate schedules with null equipment_id
-            diesel::update(
-                observation_schedules::table.filter(
-                    observation_schedules::user_id
-                        .eq(&new_schedule.user_id)
-                        .and(observation_schedules::is_active.eq(true))
-                        .and(observation_schedules::equipment_id.is_null()),
-                ),
-            )
-            .set(observation_schedules::is_active.eq(false))
-            .execute(conn)?;
-        }
+        deactivate_competing_schedules(
+            conn,
+            &new_schedule.user_id,
+            &new_schedule.id,
+            new_schedule.equipment_id.as_deref(),
+            new_schedule.scheduled_date.as_deref(),
+        )?;
     }
 
     diesel::insert_into(observation_schedules::table)
-        .values(new_schedule)
+        .values(&new_schedule)
         .execute(conn)?;
 
     observation_schedules::table
@@ -556,45 +1138,41 @@ pub fn update_schedule(
     schedule_id: &str,
     update: &UpdateObservationSchedule,
 ) -> QueryResult<ObservationSchedule> {
-    // If activating this schedule, deactivate other schedules with the same equipment_id
-    if update.is_active == Some(true) {
-        if let Some(schedule) = get_schedule_by_id(conn, schedule_id)? {
-            // Determine which equipment_id to match:
-            // If update has equipment_id set, use that; otherwise use the existing schedule's equipment_id
-            let effective_equipment_id = update.equipment_id.as_ref().or(schedule.equipment_id.as_ref());
-
-            if let Some(eq_id) = effective_equipment_id {
-                // Deactivate schedules with the same equipment_id (excluding this one)
-                diesel::update(
-                    observation_schedules::table.filter(
-                        observation_schedules::user_id
-                            .eq(&schedule.user_id)
-                            .and(observation_schedules::is_active.eq(true))
-                            .and(observation_schedules::id.ne(schedule_id))
-                            .and(observation_schedules::equipment_id.eq(eq_id)),
-                    ),
-                )
-                .set(observation_schedules::is_active.eq(false))
-                .execute(conn)?;
-            } else {
-                // Deactivate schedules with null equipment_id (excluding this one)
-                diesel::update(
-                    observation_schedules::table.filter(
-                        observation_schedules::user_id
-                            .eq(&schedule.user_id)
-                            .and(observation_schedules::is_active.eq(true))
-                            .and(observation_schedules::id.ne(schedule_id))
-                            .and(observation_schedules::equipment_id.is_null()),
-                    ),
-                )
-                .set(observation_schedules::is_active.eq(false))
-                .execute(conn)?;
-            }
-        }
+    // Archiving deactivates a schedule, and activating it restores it
+    let mut update = update.clone();
+    if update.archived == Some(true) {
+        update.is_active = Some(false);
+    } else if update.is_active == Some(true) {
+        update.archived = Some(false);
+    }
+
+    // Activating, or moving an active schedule to other equipment or
+    // another night, takes over from the active schedule there
+    let moved = update.equipment_id.is_some() || update.scheduled_date.is_some();
+    if update.is_active == Some(true) || (moved && update.is_active.is_none()) {
+        if let Some(schedule) = get_schedule_by_id(conn, schedule_id)?
+            .filter(|schedule| update.is_active.unwrap_or(schedule.is_active))
+        {
+            let equipment_id = update
+                .equipment_id
+                .as_deref()
+                .or(schedule.equipment_id.as_deref());
+            let scheduled_date = update
+                .scheduled_date
+                .as_deref()
+                .or(schedule.scheduled_date.as_deref());
+            deactivate_competing_schedules(
+                conn,
+                &schedule.user_id,
+                schedule_id,
+                equipment_id,
+                scheduled_date,
+            )?;
+        }
     }
 
     diesel::update(observation_schedules::table.filter(observation_schedules::id.eq(schedule_id)))
-        .set(update)
+        .set(&update)
         .execute(conn)?;
 
     observation_schedules::table
@@ -602,11 +1180,182 @@ pub fn update_schedule(
         .first(conn)
 }
 
+/// Archive (or restore) a schedule. Archived schedules are deactivated;
+/// restored ones stay inactive until activated again.
+pub fn set_schedule_archived(
+    conn: &mut SqliteConnection,
+    schedule_id: &str,
+    archived: bool,
+) -> QueryResult<ObservationSchedule> {
+    update_schedule(
+        conn,
+        schedule_id,
+        &UpdateObservationSchedule {
+            archived: Some(archived),
+            ..Default::default()
+        },
+    )
+}
+
+/// Archive the user's schedules for nights before `date` (YYYY-MM-DD).
+/// Returns how many were archived.
+pub fn archive_schedules_before(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    date: &str,
+) -> QueryResult<usize> {
+    diesel::update(
+        observation_schedules::table
+            .filter(observation_schedules::user_id.eq(user_id))
+            .filter(observation_schedules::archived.eq(false))
+            .filter(observation_schedules::scheduled_date.lt(schedule_night(date))),
+    )
+    .set((
+        observation_schedules::archived.eq(true),
+        observation_schedules::is_active.eq(false),
+    ))
+    .execute(conn)
+}
+
 pub fn delete_schedule(conn: &mut SqliteConnection, schedule_id: &str) -> QueryResult<usize> {
+    delete_packing_items_for_schedule(conn, schedule_id)?;
     diesel::delete(observation_schedules::table.filter(observation_schedules::id.eq(schedule_id)))
         .execute(conn)
 }
 
+// ============================================================================
+// PackingItem Repository
+// ============================================================================
+
+/// A schedule's packing list in checklist order
+pub fn get_packing_items(
+    conn: &mut SqliteConnection,
+    schedule_id: &str,
+) -> QueryResult<Vec<PackingItem>> {
+    packing_items::table
+        .filter(packing_items::schedule_id.eq(schedule_id))
+        .order((packing_items::sort_order.asc(), packing_items::created_at.asc()))
+        .load(conn)
+}
+
+pub fn get_packing_item_by_id(
+    conn: &mut SqliteConnection,
+    item_id: &str,
+) -> QueryResult<Option<PackingItem>> {
+    packing_items::table
+        .filter(packing_items::id.eq(item_id))
+        .first(conn)
+        .optional()
+}
+
+/// Replace a schedule's packing list
+pub fn replace_packing_items(
+    conn: &mut SqliteConnection,
+    schedule_id: &str,
+    items: &[NewPackingItem],
+) -> QueryResult<Vec<PackingItem>> {
+    conn.transaction(|conn| {
+        delete_packing_items_for_schedule(conn, schedule_id)?;
+        diesel::insert_into(packing_items::table)
+            .values(items)
+            .execute(conn)?;
+        get_packing_items(conn, schedule_id)
+    })
+}
+
+pub fn create_packing_item(
+    conn: &mut SqliteConnection,
+    new_item: &NewPackingItem,
+) -> QueryResult<PackingItem> {
+    diesel::insert_into(packing_items::table)
+        .values(new_item)
+        .execute(conn)?;
+
+    packing_items::table
+        .filter(packing_items::id.eq(&new_item.id))
+        .first(conn)
+}
+
+pub fn set_packing_item_packed(
+    conn: &mut SqliteConnection,
+    item_id: &str,
+    packed: bool,
+) -> QueryResult<PackingItem> {
+    diesel::update(packing_items::table.filter(packing_items::id.eq(item_id)))
+        .set((
+            packing_items::packed.eq(packed),
+            packing_items::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    packing_items::table
+        .filter(packing_items::id.eq(item_id))
+        .first(conn)
+}
+
+pub fn delete_packing_item(conn: &mut SqliteConnection, item_id: &str) -> QueryResult<usize> {
+    diesel::delete(packing_items::table.filter(packing_items::id.eq(item_id))).execute(conn)
+}
+
+pub fn delete_packing_items_for_schedule(
+    conn: &mut SqliteConnection,
+    schedule_id: &str,
+) -> QueryResult<usize> {
+    diesel::delete(packing_items::table.filter(packing_items::schedule_id.eq(schedule_id)))
+        .execute(conn)
+}
+
+// ============================================================================
+// AstroObject Repository - Object names and their aliases
+// ============================================================================
+
+pub fn get_astro_objects(conn: &mut SqliteConnection) -> QueryResult<Vec<AstroObject>> {
+    astro_objects::table.order(astro_objects::name.asc()).load(conn)
+}
+
+pub fn get_astro_object_by_name(
+    conn: &mut SqliteConnection,
+    name: &str,
+) -> QueryResult<Option<AstroObject>> {
+    astro_objects::table
+        .filter(astro_objects::name.eq(name))
+        .first(conn)
+        .optional()
+}
+
+/// Create an object, or replace the display name and aliases of the one
+/// with the same name
+pub fn save_astro_object(conn: &mut SqliteConnection, object: &NewAstroObject) -> QueryResult<()> {
+    diesel::insert_into(astro_objects::table)
+        .values(object)
+        .on_conflict(astro_objects::name)
+        .do_update()
+        .set((
+            astro_objects::display_name.eq(&object.display_name),
+            astro_objects::aliases.eq(&object.aliases),
+            astro_objects::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn set_astro_object_metadata(
+    conn: &mut SqliteConnection,
+    name: &str,
+    metadata: &str,
+) -> QueryResult<usize> {
+    diesel::update(astro_objects::table.filter(astro_objects::name.eq(name)))
+        .set((
+            astro_objects::metadata.eq(metadata),
+            astro_objects::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)
+}
+
+pub fn delete_astro_object(conn: &mut SqliteConnection, name: &str) -> QueryResult<usize> {
+    diesel::delete(astro_objects::table.filter(astro_objects::name.eq(name))).execute(conn)
+}
+
 // ============================================================================
 // SimbadCache Repository
 // ============================================================================
@@ -664,6 +1413,16 @@ fn extract_annotation_names(annotations: &str) -> Vec<String> {
 pub fn get_targets_with_counts(
     conn: &mut SqliteConnection,
     user_id: &str,
+) -> QueryResult<Vec<TargetWithCount>> {
+    get_targets_with_counts_by(conn, user_id, str::to_string)
+}
+
+/// Unique targets with image counts, with each name mapped through
+/// `canonical` first so aliases of one object are counted together
+pub fn get_targets_with_counts_by(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    canonical: impl Fn(&str) -> String,
 ) -> QueryResult<Vec<TargetWithCount>> {
     // Get all images for this user
     let images = images::table
@@ -693,6 +1452,12 @@ pub fn get_targets_with_counts(
             target_names.extend(extract_annotation_names(annotations));
         }
 
+        // Count the image once per object, however many names it has
+        let mut target_names: Vec<String> =
+            target_names.iter().map(|name| canonical(name)).collect();
+        target_names.sort();
+        target_names.dedup();
+
         // Update counts for each target name
         for name in target_names {
             let entry = target_map.entry(name).or_insert((0, None, None));
@@ -773,6 +1538,180 @@ pub fn get_images_by_target(
     Ok(results)
 }
 
+/// Images matching any of `target_names` (see `get_images_by_target`),
+/// newest first
+pub fn get_images_by_target_names(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    target_names: &[String],
+) -> QueryResult<Vec<Image>> {
+    let mut seen = std::collections::HashSet::new();
+    let mut results = Vec::new();
+    for name in target_names {
+        for image in get_images_by_target(conn, user_id, name)? {
+            if seen.insert(image.id.clone()) {
+                results.push(image);
+            }
+        }
+    }
+    results.sort_by_key(|image| std::cmp::Reverse(image.created_at));
+    Ok(results)
+}
+
+/// Distinct target names in image summaries
+pub fn get_image_summaries(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<String>> {
+    images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::summary.is_not_null())
+        .select(images::summary.assume_not_null())
+        .distinct()
+        .load(conn)
+}
+
+/// Rename a target in image summaries. Returns how many images changed.
+pub fn rename_image_target(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    from: &str,
+    to: &str,
+) -> QueryResult<usize> {
+    diesel::update(
+        images::table
+            .filter(images::user_id.eq(user_id))
+            .filter(images::summary.eq(from)),
+    )
+    .set((images::summary.eq(to), images::updated_at.eq(diesel::dsl::now)))
+    .execute(conn)
+}
+
+/// Rename observing list entries for a target. Returns how many changed.
+pub fn rename_todo_target(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    from: &str,
+    to: &str,
+) -> QueryResult<usize> {
+    diesel::update(
+        astronomy_todos::table
+            .filter(astronomy_todos::user_id.eq(user_id))
+            .filter(astronomy_todos::name.eq(from)),
+    )
+    .set((
+        astronomy_todos::name.eq(to),
+        astronomy_todos::last_updated.eq(chrono::Utc::now().to_rfc3339()),
+        astronomy_todos::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(conn)
+}
+
+// ============================================================================
+// Acquisition Repository - Query images by their acquisition columns
+// ============================================================================
+
+/// Criteria for `search_images_by_acquisition`; unset fields match anything
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcquisitionQuery {
+    pub telescope: Option<String>,
+    pub filter: Option<String>,
+    pub gain: Option<i32>,
+    pub min_exposure: Option<f64>,
+    pub max_exposure: Option<f64>,
+    /// Inclusive `YYYY-MM-DD` bounds on DATE-OBS (UTC)
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    /// Saved location the images were taken at
+    pub capture_site_id: Option<String>,
+}
+
+/// Distinct acquisition values in the library, for filter dropdowns
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcquisitionOptions {
+    pub telescopes: Vec<String>,
+    pub filters: Vec<String>,
+    pub gains: Vec<i32>,
+}
+
+/// Find images matching acquisition criteria, newest observation first
+pub fn search_images_by_acquisition(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    query: &AcquisitionQuery,
+) -> QueryResult<Vec<Image>> {
+    let mut q = images::table
+        .filter(images::user_id.eq(user_id))
+        .into_boxed();
+
+    if let Some(telescope) = &query.telescope {
+        q = q.filter(images::telescope.eq(telescope));
+    }
+    if let Some(filter) = &query.filter {
+        q = q.filter(images::filter.eq(filter));
+    }
+    if let Some(gain) = query.gain {
+        q = q.filter(images::gain.eq(gain));
+    }
+    if let Some(min) = query.min_exposure {
+        q = q.filter(images::exposure.ge(min));
+    }
+    if let Some(max) = query.max_exposure {
+        q = q.filter(images::exposure.le(max));
+    }
+    if let Some(from) = &query.date_from {
+        q = q.filter(images::date_obs.ge(from));
+    }
+    if let Some(to) = &query.date_to {
+        // DATE-OBS carries a time, so compare against the start of the next day
+        let end = chrono::NaiveDate::parse_from_str(to, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.succ_opt())
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| to.clone());
+        q = q.filter(images::date_obs.lt(end));
+    }
+    if let Some(site) = &query.capture_site_id {
+        q = q.filter(images::capture_site_id.eq(site));
+    }
+
+    q.order((images::date_obs.desc(), images::created_at.desc()))
+        .load(conn)
+}
+
+/// Get the distinct telescopes, filters and gains used across a user's images
+pub fn get_acquisition_options(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<AcquisitionOptions> {
+    let telescopes = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::telescope.is_not_null())
+        .select(images::telescope)
+        .distinct()
+        .order(images::telescope.asc())
+        .load::<Option<String>>(conn)?;
+    let filters = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::filter.is_not_null())
+        .select(images::filter)
+        .distinct()
+        .order(images::filter.asc())
+        .load::<Option<String>>(conn)?;
+    let gains = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::gain.is_not_null())
+        .select(images::gain)
+        .distinct()
+        .order(images::gain.asc())
+        .load::<Option<i32>>(conn)?;
+
+    Ok(AcquisitionOptions {
+        telescopes: telescopes.into_iter().flatten().collect(),
+        filters: filters.into_iter().flatten().collect(),
+        gains: gains.into_iter().flatten().collect(),
+    })
+}
+
 // ============================================================================
 // ScannedDirectory Repository - Directory scan caching
 // ============================================================================
@@ -790,6 +1729,17 @@ pub fn get_scanned_directory(
         .optional()
 }
 
+/// Get all scanned directories for a user, by path
+pub fn get_scanned_directories(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<ScannedDirectory>> {
+    scanned_directories::table
+        .filter(scanned_directories::user_id.eq(user_id))
+        .order(scanned_directories::path.asc())
+        .load(conn)
+}
+
 /// Get all scanned directories for a user that are subdirectories of a given path
 pub fn get_scanned_subdirectories(
     conn: &mut SqliteConnection,
@@ -817,6 +1767,9 @@ pub fn upsert_scanned_directory(
             scanned_directories::fs_modified_at.eq(&entry.fs_modified_at),
             scanned_directories::last_scanned_at.eq(&entry.last_scanned_at),
             scanned_directories::image_count.eq(&entry.image_count),
+            scanned_directories::last_imported.eq(&entry.last_imported),
+            scanned_directories::last_failed.eq(&entry.last_failed),
+            scanned_directories::listing_hash.eq(&entry.listing_hash),
         ))
         .execute(conn)?;
     Ok(())
@@ -836,519 +1789,3048 @@ pub fn delete_scanned_directory(
     .execute(conn)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::models::*;
-    use crate::db::DbPool;
-    use diesel::r2d2::{self, ConnectionManager};
-    use diesel::sqlite::SqliteConnection;
-    use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+// ============================================================================
+// Equipment Repository
+// ============================================================================
 
-    const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+pub fn get_equipment(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Equipment>> {
+    equipment::table
+        .filter(equipment::user_id.eq(user_id))
+        .order((equipment::kind.asc(), equipment::name.asc()))
+        .load(conn)
+}
 
-    fn setup_test_db() -> DbPool {
-        let manager = ConnectionManager::<SqliteConnection>::new(":memory:");
-        let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
-        let mut conn = pool.get().unwrap();
-        conn.run_pending_migrations(MIGRATIONS).unwrap();
-        pool
-    }
+pub fn get_equipment_by_id(
+    conn: &mut SqliteConnection,
+    equipment_id: &str,
+) -> QueryResult<Option<Equipment>> {
+    equipment::table
+        .filter(equipment::id.eq(equipment_id))
+        .first(conn)
+        .optional()
+}
 
-    /// Insert a test user so foreign key constraints are satisfied.
-    fn insert_test_user(conn: &mut SqliteConnection, user_id: &str) {
-        diesel::insert_into(users::table)
-            .values(&NewUser {
-                id: user_id.to_string(),
-                email: None,
-                name: Some("Test User".to_string()),
-                image: None,
-                username: None,
-                first_name: None,
-                last_name: None,
-            })
-            .execute(conn)
-            .unwrap();
-    }
+pub fn create_equipment(
+    conn: &mut SqliteConnection,
+    new_equipment: &NewEquipment,
+) -> QueryResult<Equipment> {
+    diesel::insert_into(equipment::table)
+        .values(new_equipment)
+        .execute(conn)?;
 
-    // ========================================================================
-    // Collection CRUD
-    // ========================================================================
+    equipment::table
+        .filter(equipment::id.eq(&new_equipment.id))
+        .first(conn)
+}
 
-    #[test]
-    fn collection_create_and_get() {
-        let pool = setup_test_db();
-        let mut conn = pool.get().unwrap();
-        insert_test_user(&mut conn, "user-1");
+pub fn update_equipment(
+    conn: &mut SqliteConnection,
+    equipment_id: &str,
+    update: &UpdateEquipment,
+) -> QueryResult<Equipment> {
+    diesel::update(equipment::table.filter(equipment::id.eq(equipment_id)))
+        .set(update)
+        .execute(conn)?;
 
-        let new = NewCollection {
-            id: "coll-1".to_string(),
-            user_id: "user-1".to_string(),
-            name: "My Collection".to_string(),
-            description: Some("Test description".to_string()),
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
-            archived: false,
-        };
-        let created = create_collection(&mut conn, &new).unwrap();
-        assert_eq!(created.name, "My Collection");
-        assert_eq!(created.description, Some("Test description".to_string()));
+    equipment::table
+        .filter(equipment::id.eq(equipment_id))
+        .first(conn)
+}
 
-        let fetched = get_collection_by_id(&mut conn, "coll-1").unwrap();
-        assert!(fetched.is_some());
-        assert_eq!(fetched.unwrap().name, "My Collection");
-    }
+pub fn delete_equipment(conn: &mut SqliteConnection, equipment_id: &str) -> QueryResult<usize> {
+    diesel::delete(image_equipment::table.filter(image_equipment::equipment_id.eq(equipment_id)))
+        .execute(conn)?;
+    // Detach from any profiles that reference it
+    diesel::update(equipment_profiles::table.filter(equipment_profiles::telescope_id.eq(equipment_id)))
+        .set(equipment_profiles::telescope_id.eq(None::<String>))
+        .execute(conn)?;
+    diesel::update(equipment_profiles::table.filter(equipment_profiles::camera_id.eq(equipment_id)))
+        .set(equipment_profiles::camera_id.eq(None::<String>))
+        .execute(conn)?;
+    diesel::update(equipment_profiles::table.filter(equipment_profiles::reducer_id.eq(equipment_id)))
+        .set(equipment_profiles::reducer_id.eq(None::<String>))
+        .execute(conn)?;
+    diesel::delete(maintenance_entries::table.filter(maintenance_entries::equipment_id.eq(equipment_id)))
+        .execute(conn)?;
+    diesel::update(packing_items::table.filter(packing_items::equipment_id.eq(equipment_id)))
+        .set(packing_items::equipment_id.eq(None::<String>))
+        .execute(conn)?;
+    diesel::delete(equipment::table.filter(equipment::id.eq(equipment_id))).execute(conn)
+}
 
-    #[test]
-    fn collection_get_by_name() {
-        let pool = setup_test_db();
-        let mut conn = pool.get().unwrap();
-        insert_test_user(&mut conn, "user-1");
+/// Link an image to a piece of equipment (no-op if already linked)
+pub fn link_image_equipment(
+    conn: &mut SqliteConnection,
+    new_entry: &NewImageEquipment,
+) -> QueryResult<usize> {
+    diesel::insert_or_ignore_into(image_equipment::table)
+        .values(new_entry)
+        .execute(conn)
+}
 
-        let new = NewCollection {
-            id: "coll-1".to_string(),
+/// Remove the link between an image and a piece of equipment
+pub fn unlink_image_equipment(
+    conn: &mut SqliteConnection,
+    image_id: &str,
+    equipment_id: &str,
+) -> QueryResult<usize> {
+    diesel::delete(
+        image_equipment::table
+            .filter(image_equipment::image_id.eq(image_id))
+            .filter(image_equipment::equipment_id.eq(equipment_id)),
+    )
+    .execute(conn)
+}
+
+/// Get all equipment linked to an image
+pub fn get_equipment_for_image(
+    conn: &mut SqliteConnection,
+    image_id: &str,
+) -> QueryResult<Vec<Equipment>> {
+    let equipment_ids: Vec<String> = image_equipment::table
+        .filter(image_equipment::image_id.eq(image_id))
+        .select(image_equipment::equipment_id)
+        .load(conn)?;
+
+    if equipment_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    equipment::table
+        .filter(equipment::id.eq_any(equipment_ids))
+        .order((equipment::kind.asc(), equipment::name.asc()))
+        .load(conn)
+}
+
+/// Get ids of images linked to *all* of the given equipment (i.e. shot with that rig)
+pub fn get_image_ids_for_rig(
+    conn: &mut SqliteConnection,
+    equipment_ids: &[String],
+) -> QueryResult<Vec<String>> {
+    if equipment_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let links: Vec<(String, String)> = image_equipment::table
+        .filter(image_equipment::equipment_id.eq_any(equipment_ids))
+        .select((image_equipment::image_id, image_equipment::equipment_id))
+        .load(conn)?;
+
+    let mut matched: std::collections::HashMap<String, std::collections::HashSet<String>> =
+        std::collections::HashMap::new();
+    for (image_id, equipment_id) in links {
+        matched.entry(image_id).or_default().insert(equipment_id);
+    }
+
+    let wanted: std::collections::HashSet<&String> = equipment_ids.iter().collect();
+    Ok(matched
+        .into_iter()
+        .filter(|(_, ids)| ids.len() == wanted.len())
+        .map(|(image_id, _)| image_id)
+        .collect())
+}
+
+/// Get images shot with a rig (all of the given equipment)
+pub fn get_images_by_rig(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    equipment_ids: &[String],
+) -> QueryResult<Vec<Image>> {
+    let image_ids = get_image_ids_for_rig(conn, equipment_ids)?;
+
+    if image_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::id.eq_any(image_ids))
+        .order(images::created_at.desc())
+        .load(conn)
+}
+
+/// Count images (total, stacked) shot with a rig
+pub fn count_images_by_rig(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    equipment_ids: &[String],
+) -> QueryResult<(i64, i64)> {
+    let image_ids = get_image_ids_for_rig(conn, equipment_ids)?;
+
+    if image_ids.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let total: i64 = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::id.eq_any(&image_ids))
+        .count()
+        .get_result(conn)?;
+    let stacked: i64 = images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::id.eq_any(&image_ids))
+        .filter(images::tags.like("%stacked%"))
+        .count()
+        .get_result(conn)?;
+    Ok((total, stacked))
+}
+
+/// Check whether a FITS header value (TELESCOP/INSTRUME) refers to this equipment.
+///
+/// Matches case-insensitively on the equipment name or any of its
+/// comma-separated match patterns appearing in the header value.
+pub fn equipment_matches_header(item: &Equipment, header_value: &str) -> bool {
+    let value = header_value.trim().to_lowercase();
+    if value.is_empty() {
+        return false;
+    }
+
+    if item.name.trim().to_lowercase() == value {
+        return true;
+    }
+
+    item.match_patterns
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|p| p.trim().to_lowercase())
+        .any(|p| !p.is_empty() && value.contains(&p))
+}
+
+/// Find equipment of a given kind matching a FITS header value
+pub fn find_matching_equipment(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    kind: &str,
+    header_value: &str,
+) -> QueryResult<Vec<Equipment>> {
+    let candidates: Vec<Equipment> = equipment::table
+        .filter(equipment::user_id.eq(user_id))
+        .filter(equipment::kind.eq(kind))
+        .load(conn)?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|item| equipment_matches_header(item, header_value))
+        .collect())
+}
+
+/// Link an image to equipment matched from its TELESCOP/INSTRUME headers.
+/// Returns the number of new links created.
+pub fn auto_link_image_equipment(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_id: &str,
+    telescope: Option<&str>,
+    instrument: Option<&str>,
+) -> QueryResult<usize> {
+    let mut matched = Vec::new();
+    if let Some(telescope) = telescope {
+        matched.extend(find_matching_equipment(conn, user_id, "telescope", telescope)?);
+    }
+    if let Some(instrument) = instrument {
+        matched.extend(find_matching_equipment(conn, user_id, "camera", instrument)?);
+    }
+
+    let mut linked = 0;
+    for item in matched {
+        linked += link_image_equipment(
+            conn,
+            &NewImageEquipment {
+                id: uuid::Uuid::new_v4().to_string(),
+                image_id: image_id.to_string(),
+                equipment_id: item.id,
+            },
+        )?;
+    }
+    Ok(linked)
+}
+
+// ============================================================================
+// MaintenanceEntry Repository
+// ============================================================================
+
+/// The whole maintenance log, most recent work first
+pub fn get_maintenance_entries(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<MaintenanceEntry>> {
+    maintenance_entries::table
+        .filter(maintenance_entries::user_id.eq(user_id))
+        .order((maintenance_entries::performed_on.desc(), maintenance_entries::created_at.desc()))
+        .load(conn)
+}
+
+/// One item's maintenance log, most recent work first
+pub fn get_maintenance_for_equipment(
+    conn: &mut SqliteConnection,
+    equipment_id: &str,
+) -> QueryResult<Vec<MaintenanceEntry>> {
+    maintenance_entries::table
+        .filter(maintenance_entries::equipment_id.eq(equipment_id))
+        .order((maintenance_entries::performed_on.desc(), maintenance_entries::created_at.desc()))
+        .load(conn)
+}
+
+pub fn get_maintenance_entry_by_id(
+    conn: &mut SqliteConnection,
+    entry_id: &str,
+) -> QueryResult<Option<MaintenanceEntry>> {
+    maintenance_entries::table
+        .filter(maintenance_entries::id.eq(entry_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn create_maintenance_entry(
+    conn: &mut SqliteConnection,
+    new_entry: &NewMaintenanceEntry,
+) -> QueryResult<MaintenanceEntry> {
+    diesel::insert_into(maintenance_entries::table)
+        .values(new_entry)
+        .execute(conn)?;
+
+    maintenance_entries::table
+        .filter(maintenance_entries::id.eq(&new_entry.id))
+        .first(conn)
+}
+
+pub fn update_maintenance_entry(
+    conn: &mut SqliteConnection,
+    entry_id: &str,
+    update: &UpdateMaintenanceEntry,
+) -> QueryResult<MaintenanceEntry> {
+    diesel::update(maintenance_entries::table.filter(maintenance_entries::id.eq(entry_id)))
+        .set((update, maintenance_entries::updated_at.eq(diesel::dsl::now)))
+        .execute(conn)?;
+
+    maintenance_entries::table
+        .filter(maintenance_entries::id.eq(entry_id))
+        .first(conn)
+}
+
+pub fn delete_maintenance_entry(conn: &mut SqliteConnection, entry_id: &str) -> QueryResult<usize> {
+    diesel::delete(maintenance_entries::table.filter(maintenance_entries::id.eq(entry_id))).execute(conn)
+}
+
+// ============================================================================
+// EquipmentProfile Repository
+// ============================================================================
+
+pub fn get_equipment_profiles(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<EquipmentProfile>> {
+    equipment_profiles::table
+        .filter(equipment_profiles::user_id.eq(user_id))
+        .order(equipment_profiles::name.asc())
+        .load(conn)
+}
+
+pub fn get_equipment_profile_by_id(
+    conn: &mut SqliteConnection,
+    profile_id: &str,
+) -> QueryResult<Option<EquipmentProfile>> {
+    equipment_profiles::table
+        .filter(equipment_profiles::id.eq(profile_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn create_equipment_profile(
+    conn: &mut SqliteConnection,
+    new_profile: &NewEquipmentProfile,
+) -> QueryResult<EquipmentProfile> {
+    diesel::insert_into(equipment_profiles::table)
+        .values(new_profile)
+        .execute(conn)?;
+
+    equipment_profiles::table
+        .filter(equipment_profiles::id.eq(&new_profile.id))
+        .first(conn)
+}
+
+pub fn update_equipment_profile(
+    conn: &mut SqliteConnection,
+    profile_id: &str,
+    update: &UpdateEquipmentProfile,
+) -> QueryResult<EquipmentProfile> {
+    diesel::update(equipment_profiles::table.filter(equipment_profiles::id.eq(profile_id)))
+        .set(update)
+        .execute(conn)?;
+
+    equipment_profiles::table
+        .filter(equipment_profiles::id.eq(profile_id))
+        .first(conn)
+}
+
+pub fn delete_equipment_profile(conn: &mut SqliteConnection, profile_id: &str) -> QueryResult<usize> {
+    diesel::delete(equipment_profiles::table.filter(equipment_profiles::id.eq(profile_id)))
+        .execute(conn)
+}
+
+/// Find the profile whose gear best matches the equipment linked to an image.
+///
+/// Every piece of gear set on a profile must be linked to the image; among
+/// matching profiles the most specific (most gear set) wins.
+pub fn find_profile_for_image(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    image_id: &str,
+) -> QueryResult<Option<EquipmentProfile>> {
+    let linked: std::collections::HashSet<String> = image_equipment::table
+        .filter(image_equipment::image_id.eq(image_id))
+        .select(image_equipment::equipment_id)
+        .load::<String>(conn)?
+        .into_iter()
+        .collect();
+
+    if linked.is_empty() {
+        return Ok(None);
+    }
+
+    let profiles = get_equipment_profiles(conn, user_id)?;
+    Ok(profiles
+        .into_iter()
+        .filter_map(|profile| {
+            let gear = [&profile.telescope_id, &profile.camera_id, &profile.reducer_id];
+            let set: Vec<&String> = gear.iter().filter_map(|id| id.as_ref()).collect();
+            if set.is_empty() || !set.iter().all(|id| linked.contains(*id)) {
+                return None;
+            }
+            Some((set.len(), profile))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, profile)| profile))
+}
+
+// ============================================================================
+// ProcessingPreset Repository
+// ============================================================================
+
+pub fn get_processing_presets(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<ProcessingPreset>> {
+    processing_presets::table
+        .filter(processing_presets::user_id.eq(user_id))
+        .order((processing_presets::target_type.asc(), processing_presets::name.asc()))
+        .load(conn)
+}
+
+pub fn get_processing_preset_by_id(
+    conn: &mut SqliteConnection,
+    preset_id: &str,
+) -> QueryResult<Option<ProcessingPreset>> {
+    processing_presets::table
+        .filter(processing_presets::id.eq(preset_id))
+        .first(conn)
+        .optional()
+}
+
+/// Unset the default flag on a user's other presets for the same target type
+fn clear_default_presets(
+    conn: &mut SqliteConnection,
+    preset: &ProcessingPreset,
+) -> QueryResult<usize> {
+    let others = processing_presets::table
+        .filter(processing_presets::user_id.eq(&preset.user_id))
+        .filter(processing_presets::id.ne(&preset.id))
+        .filter(processing_presets::is_default.eq(true))
+        .into_boxed();
+    let others = match &preset.target_type {
+        Some(target_type) => others.filter(processing_presets::target_type.eq(target_type)),
+        None => others.filter(processing_presets::target_type.is_null()),
+    };
+    let ids: Vec<String> = others.select(processing_presets::id).load(conn)?;
+
+    diesel::update(processing_presets::table.filter(processing_presets::id.eq_any(ids)))
+        .set(processing_presets::is_default.eq(false))
+        .execute(conn)
+}
+
+pub fn create_processing_preset(
+    conn: &mut SqliteConnection,
+    new_preset: &NewProcessingPreset,
+) -> QueryResult<ProcessingPreset> {
+    conn.transaction(|conn| {
+        diesel::insert_into(processing_presets::table)
+            .values(new_preset)
+            .execute(conn)?;
+
+        let preset: ProcessingPreset = processing_presets::table
+            .filter(processing_presets::id.eq(&new_preset.id))
+            .first(conn)?;
+        if preset.is_default {
+            clear_default_presets(conn, &preset)?;
+        }
+        Ok(preset)
+    })
+}
+
+pub fn update_processing_preset(
+    conn: &mut SqliteConnection,
+    preset_id: &str,
+    update: &UpdateProcessingPreset,
+) -> QueryResult<ProcessingPreset> {
+    conn.transaction(|conn| {
+        diesel::update(processing_presets::table.filter(processing_presets::id.eq(preset_id)))
+            .set(update)
+            .execute(conn)?;
+
+        let preset: ProcessingPreset = processing_presets::table
+            .filter(processing_presets::id.eq(preset_id))
+            .first(conn)?;
+        // Moving a default preset to another target type takes over that type's default
+        if preset.is_default {
+            clear_default_presets(conn, &preset)?;
+        }
+        Ok(preset)
+    })
+}
+
+pub fn delete_processing_preset(conn: &mut SqliteConnection, preset_id: &str) -> QueryResult<usize> {
+    diesel::delete(processing_presets::table.filter(processing_presets::id.eq(preset_id)))
+        .execute(conn)
+}
+
+/// Make a preset the default for its target type (replacing any previous
+/// default), or clear its default flag
+pub fn set_default_processing_preset(
+    conn: &mut SqliteConnection,
+    preset_id: &str,
+    is_default: bool,
+) -> QueryResult<ProcessingPreset> {
+    conn.transaction(|conn| {
+        diesel::update(processing_presets::table.filter(processing_presets::id.eq(preset_id)))
+            .set(processing_presets::is_default.eq(is_default))
+            .execute(conn)?;
+
+        let preset: ProcessingPreset = processing_presets::table
+            .filter(processing_presets::id.eq(preset_id))
+            .first(conn)?;
+        if is_default {
+            clear_default_presets(conn, &preset)?;
+        }
+        Ok(preset)
+    })
+}
+
+/// Default preset for a target type, falling back to the default preset
+/// for any target
+pub fn get_default_processing_preset(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    target_type: Option<&str>,
+) -> QueryResult<Option<ProcessingPreset>> {
+    let defaults: Vec<ProcessingPreset> = processing_presets::table
+        .filter(processing_presets::user_id.eq(user_id))
+        .filter(processing_presets::is_default.eq(true))
+        .load(conn)?;
+
+    let specific = target_type.and_then(|target_type| {
+        defaults
+            .iter()
+            .find(|p| p.target_type.as_deref() == Some(target_type))
+    });
+    Ok(specific
+        .or_else(|| defaults.iter().find(|p| p.target_type.is_none()))
+        .cloned())
+}
+
+// ============================================================================
+// SavedSearch Repository
+// ============================================================================
+
+pub fn get_saved_searches(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<SavedSearch>> {
+    saved_searches::table
+        .filter(saved_searches::user_id.eq(user_id))
+        .order((saved_searches::entity.asc(), saved_searches::name.asc()))
+        .load(conn)
+}
+
+pub fn get_saved_search_by_id(
+    conn: &mut SqliteConnection,
+    search_id: &str,
+) -> QueryResult<Option<SavedSearch>> {
+    saved_searches::table
+        .filter(saved_searches::id.eq(search_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn create_saved_search(
+    conn: &mut SqliteConnection,
+    new_search: &NewSavedSearch,
+) -> QueryResult<SavedSearch> {
+    diesel::insert_into(saved_searches::table)
+        .values(new_search)
+        .execute(conn)?;
+
+    saved_searches::table
+        .filter(saved_searches::id.eq(&new_search.id))
+        .first(conn)
+}
+
+pub fn update_saved_search(
+    conn: &mut SqliteConnection,
+    search_id: &str,
+    update: &UpdateSavedSearch,
+) -> QueryResult<SavedSearch> {
+    diesel::update(saved_searches::table.filter(saved_searches::id.eq(search_id)))
+        .set(update)
+        .execute(conn)?;
+
+    saved_searches::table
+        .filter(saved_searches::id.eq(search_id))
+        .first(conn)
+}
+
+pub fn delete_saved_search(conn: &mut SqliteConnection, search_id: &str) -> QueryResult<usize> {
+    diesel::delete(saved_searches::table.filter(saved_searches::id.eq(search_id))).execute(conn)
+}
+
+// ============================================================================
+// Favorites Repository
+// ============================================================================
+
+/// Favorite images, most recently changed first
+pub fn get_favorite_images(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Image>> {
+    images::table
+        .filter(images::user_id.eq(user_id))
+        .filter(images::favorite.eq(true))
+        .order(images::updated_at.desc())
+        .load(conn)
+}
+
+/// Favorite collections that aren't archived, most recently changed first
+pub fn get_favorite_collections(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<Collection>> {
+    collections::table
+        .filter(collections::user_id.eq(user_id))
+        .filter(collections::favorite.eq(true))
+        .filter(collections::archived.eq(false))
+        .order(collections::updated_at.desc())
+        .load(conn)
+}
+
+/// Flagged todos that are still open, newest first
+pub fn get_flagged_todos(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<AstronomyTodo>> {
+    astronomy_todos::table
+        .filter(astronomy_todos::user_id.eq(user_id))
+        .filter(astronomy_todos::flagged.eq(true))
+        .filter(astronomy_todos::completed.eq(false))
+        .order(astronomy_todos::created_at.desc())
+        .load(conn)
+}
+
+pub fn get_pinned_saved_searches(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<SavedSearch>> {
+    saved_searches::table
+        .filter(saved_searches::user_id.eq(user_id))
+        .filter(saved_searches::pinned.eq(true))
+        .order(saved_searches::name.asc())
+        .load(conn)
+}
+
+/// Pinned targets in the order they were pinned. Pins made on different
+/// devices can name the same target twice, so only the first is kept.
+pub fn get_pinned_targets(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<PinnedTarget>> {
+    let mut pins: Vec<PinnedTarget> = pinned_targets::table
+        .filter(pinned_targets::user_id.eq(user_id))
+        .order((pinned_targets::created_at.asc(), pinned_targets::name.asc()))
+        .load(conn)?;
+    let mut seen = std::collections::HashSet::new();
+    pins.retain(|pin| seen.insert(pin.name.clone()));
+    Ok(pins)
+}
+
+/// Pin a target, returning false if it already was
+pub fn pin_target(conn: &mut SqliteConnection, new_pin: &NewPinnedTarget) -> QueryResult<bool> {
+    conn.transaction(|conn| {
+        let pinned = pinned_targets::table
+            .filter(pinned_targets::user_id.eq(&new_pin.user_id))
+            .filter(pinned_targets::name.eq(&new_pin.name))
+            .count()
+            .get_result::<i64>(conn)?;
+        if pinned > 0 {
+            return Ok(false);
+        }
+        diesel::insert_into(pinned_targets::table)
+            .values(new_pin)
+            .execute(conn)?;
+        Ok(true)
+    })
+}
+
+pub fn unpin_target(conn: &mut SqliteConnection, user_id: &str, name: &str) -> QueryResult<usize> {
+    diesel::delete(
+        pinned_targets::table
+            .filter(pinned_targets::user_id.eq(user_id))
+            .filter(pinned_targets::name.eq(name)),
+    )
+    .execute(conn)
+}
+
+// ============================================================================
+// UiPreferences Repository
+// ============================================================================
+
+pub fn get_ui_preferences(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Option<UiPreferences>> {
+    ui_preferences::table
+        .filter(ui_preferences::user_id.eq(user_id))
+        .first(conn)
+        .optional()
+}
+
+/// Store a user's preferences JSON, creating the row on first save
+pub fn save_ui_preferences(
+    conn: &mut SqliteConnection,
+    new: &NewUiPreferences,
+) -> QueryResult<UiPreferences> {
+    diesel::insert_into(ui_preferences::table)
+        .values(new)
+        .on_conflict(ui_preferences::user_id)
+        .do_update()
+        .set((
+            ui_preferences::preferences.eq(&new.preferences),
+            ui_preferences::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    ui_preferences::table
+        .filter(ui_preferences::user_id.eq(&new.user_id))
+        .first(conn)
+}
+
+// ============================================================================
+// Note Repository
+// ============================================================================
+
+/// Notes on one entity, newest first
+pub fn get_notes_for_entity(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    entity_type: &str,
+    entity_id: &str,
+) -> QueryResult<Vec<Note>> {
+    notes::table
+        .filter(notes::user_id.eq(user_id))
+        .filter(notes::entity_type.eq(entity_type))
+        .filter(notes::entity_id.eq(entity_id))
+        .order(notes::created_at.desc())
+        .load(conn)
+}
+
+/// All notes on one type of entity, newest first
+pub fn get_notes_by_type(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    entity_type: &str,
+) -> QueryResult<Vec<Note>> {
+    notes::table
+        .filter(notes::user_id.eq(user_id))
+        .filter(notes::entity_type.eq(entity_type))
+        .order(notes::created_at.desc())
+        .load(conn)
+}
+
+pub fn get_notes(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Note>> {
+    notes::table
+        .filter(notes::user_id.eq(user_id))
+        .order(notes::updated_at.desc())
+        .load(conn)
+}
+
+pub fn get_note_by_id(conn: &mut SqliteConnection, note_id: &str) -> QueryResult<Option<Note>> {
+    notes::table
+        .filter(notes::id.eq(note_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn create_note(conn: &mut SqliteConnection, new_note: &NewNote) -> QueryResult<Note> {
+    diesel::insert_into(notes::table)
+        .values(new_note)
+        .execute(conn)?;
+
+    notes::table.filter(notes::id.eq(&new_note.id)).first(conn)
+}
+
+pub fn update_note(
+    conn: &mut SqliteConnection,
+    note_id: &str,
+    update: &UpdateNote,
+) -> QueryResult<Note> {
+    diesel::update(notes::table.filter(notes::id.eq(note_id)))
+        .set((update, notes::updated_at.eq(diesel::dsl::now)))
+        .execute(conn)?;
+
+    notes::table.filter(notes::id.eq(note_id)).first(conn)
+}
+
+pub fn delete_note(conn: &mut SqliteConnection, note_id: &str) -> QueryResult<usize> {
+    diesel::delete(notes::table.filter(notes::id.eq(note_id))).execute(conn)
+}
+
+/// Remove the notes on an entity that is being deleted, returning their ids
+pub fn delete_notes_for_entity(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    entity_type: &str,
+    entity_id: &str,
+) -> QueryResult<Vec<String>> {
+    conn.transaction(|conn| {
+        let filter = notes::table
+            .filter(notes::user_id.eq(user_id))
+            .filter(notes::entity_type.eq(entity_type))
+            .filter(notes::entity_id.eq(entity_id));
+        let ids: Vec<String> = filter.select(notes::id).load(conn)?;
+        diesel::delete(filter).execute(conn)?;
+        Ok(ids)
+    })
+}
+
+/// Notes whose body contains `query`, optionally limited to one entity
+/// type, most recently changed first
+pub fn search_notes(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    query: &str,
+    entity_type: Option<&str>,
+) -> QueryResult<Vec<Note>> {
+    let pattern = format!("%{}%", query);
+    let mut q = notes::table
+        .filter(notes::user_id.eq(user_id))
+        .filter(notes::body.like(&pattern))
+        .into_boxed();
+    if let Some(entity_type) = entity_type {
+        q = q.filter(notes::entity_type.eq(entity_type));
+    }
+    q.order(notes::updated_at.desc()).load(conn)
+}
+
+// ============================================================================
+// SessionDigest Repository
+// ============================================================================
+
+pub fn get_session_digest(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    night: &str,
+) -> QueryResult<Option<SessionDigest>> {
+    session_digests::table
+        .filter(session_digests::user_id.eq(user_id))
+        .filter(session_digests::night.eq(night))
+        .first(conn)
+        .optional()
+}
+
+/// The latest `limit` digests, newest night first
+pub fn get_session_digests(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    limit: i64,
+) -> QueryResult<Vec<SessionDigest>> {
+    session_digests::table
+        .filter(session_digests::user_id.eq(user_id))
+        .order(session_digests::night.desc())
+        .limit(limit)
+        .load(conn)
+}
+
+/// Store a night's digest, replacing any earlier one for the same night
+pub fn save_session_digest(
+    conn: &mut SqliteConnection,
+    digest: &NewSessionDigest,
+) -> QueryResult<SessionDigest> {
+    diesel::insert_into(session_digests::table)
+        .values(digest)
+        .on_conflict((session_digests::user_id, session_digests::night))
+        .do_update()
+        .set((
+            session_digests::verdict.eq(&digest.verdict),
+            session_digests::summary.eq(&digest.summary),
+            session_digests::details.eq(&digest.details),
+            session_digests::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(conn)?;
+
+    get_session_digest(conn, &digest.user_id, &digest.night)?.ok_or(diesel::NotFound)
+}
+
+// ============================================================================
+// JobMetric Repository
+// ============================================================================
+
+pub fn get_job_metrics(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    job_id: &str,
+) -> QueryResult<Vec<JobMetric>> {
+    job_metrics::table
+        .filter(job_metrics::user_id.eq(user_id))
+        .filter(job_metrics::job_id.eq(job_id))
+        .load(conn)
+}
+
+/// Record a job's phase timings, then drop all but the latest `keep_jobs`
+/// jobs' metrics
+pub fn save_job_metrics(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    metrics: &[NewJobMetric],
+    keep_jobs: usize,
+) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let saved = diesel::insert_into(job_metrics::table)
+            .values(metrics)
+            .execute(conn)?;
+
+        let recent: Vec<String> = job_metrics::table
+            .filter(job_metrics::user_id.eq(user_id))
+            .order(job_metrics::created_at.desc())
+            .select(job_metrics::job_id)
+            .load(conn)?;
+        let mut seen = std::collections::HashSet::new();
+        let stale: Vec<String> = recent
+            .into_iter()
+            .filter(|job_id| seen.insert(job_id.clone()))
+            .skip(keep_jobs)
+            .collect();
+        if !stale.is_empty() {
+            diesel::delete(
+                job_metrics::table
+                    .filter(job_metrics::user_id.eq(user_id))
+                    .filter(job_metrics::job_id.eq_any(&stale)),
+            )
+            .execute(conn)?;
+        }
+        Ok(saved)
+    })
+}
+
+// ============================================================================
+// Location Repository
+// ============================================================================
+
+pub fn get_locations(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Location>> {
+    locations::table
+        .filter(locations::user_id.eq(user_id))
+        .order((locations::is_default.desc(), locations::name.asc()))
+        .load(conn)
+}
+
+pub fn get_location_by_id(
+    conn: &mut SqliteConnection,
+    location_id: &str,
+) -> QueryResult<Option<Location>> {
+    locations::table
+        .filter(locations::id.eq(location_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn get_default_location(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Option<Location>> {
+    locations::table
+        .filter(locations::user_id.eq(user_id))
+        .filter(locations::is_default.eq(true))
+        .first(conn)
+        .optional()
+}
+
+/// Unset the default flag on a user's other locations
+fn clear_default_locations(conn: &mut SqliteConnection, location: &Location) -> QueryResult<usize> {
+    let ids: Vec<String> = locations::table
+        .filter(locations::user_id.eq(&location.user_id))
+        .filter(locations::id.ne(&location.id))
+        .filter(locations::is_default.eq(true))
+        .select(locations::id)
+        .load(conn)?;
+
+    diesel::update(locations::table.filter(locations::id.eq_any(ids)))
+        .set(locations::is_default.eq(false))
+        .execute(conn)
+}
+
+pub fn create_location(
+    conn: &mut SqliteConnection,
+    new_location: &NewLocation,
+) -> QueryResult<Location> {
+    conn.transaction(|conn| {
+        diesel::insert_into(locations::table)
+            .values(new_location)
+            .execute(conn)?;
+
+        let location: Location = locations::table
+            .filter(locations::id.eq(&new_location.id))
+            .first(conn)?;
+        if location.is_default {
+            clear_default_locations(conn, &location)?;
+        }
+        Ok(location)
+    })
+}
+
+/// Make a location the default (replacing any previous default), or clear
+/// its default flag
+pub fn set_default_location(
+    conn: &mut SqliteConnection,
+    location_id: &str,
+    is_default: bool,
+) -> QueryResult<Location> {
+    conn.transaction(|conn| {
+        diesel::update(locations::table.filter(locations::id.eq(location_id)))
+            .set((
+                locations::is_default.eq(is_default),
+                locations::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        let location: Location = locations::table
+            .filter(locations::id.eq(location_id))
+            .first(conn)?;
+        if is_default {
+            clear_default_locations(conn, &location)?;
+        }
+        Ok(location)
+    })
+}
+
+pub fn delete_location(conn: &mut SqliteConnection, location_id: &str) -> QueryResult<usize> {
+    // Images taken there keep the site's coordinates
+    diesel::update(images::table.filter(images::capture_site_id.eq(location_id)))
+        .set(images::capture_site_id.eq(None::<String>))
+        .execute(conn)?;
+    diesel::delete(locations::table.filter(locations::id.eq(location_id))).execute(conn)
+}
+
+/// Remove the suggestions left by a previous dark site search, keeping any
+/// that have since been made the default
+pub fn delete_candidate_locations(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<usize> {
+    diesel::delete(
+        locations::table
+            .filter(locations::user_id.eq(user_id))
+            .filter(locations::source.eq("candidate"))
+            .filter(locations::is_default.eq(false)),
+    )
+    .execute(conn)
+}
+
+// ============================================================================
+// Filter Repository - Normalized filters and per-image filter usage
+// ============================================================================
+
+/// Exposure accumulated through one filter
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterExposureSummary {
+    pub filter: String,
+    pub band: String,
+    pub image_count: i64,
+    pub frame_count: i64,
+    pub total_exposure_seconds: f64,
+}
+
+/// Exposure accumulated through one filter on one target
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetFilterExposure {
+    pub target: String,
+    pub filter: String,
+    pub band: String,
+    pub image_count: i64,
+    pub frame_count: i64,
+    pub total_exposure_seconds: f64,
+}
+
+pub fn get_filters(conn: &mut SqliteConnection, user_id: &str) -> QueryResult<Vec<Filter>> {
+    filters::table
+        .filter(filters::user_id.eq(user_id))
+        .order(filters::name.asc())
+        .load(conn)
+}
+
+/// Get a filter by canonical name, creating it if this is the first time it's seen
+pub fn get_or_create_filter(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    name: &str,
+    band: &str,
+) -> QueryResult<Filter> {
+    diesel::insert_or_ignore_into(filters::table)
+        .values(&NewFilter {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            band: band.to_string(),
+        })
+        .execute(conn)?;
+
+    filters::table
+        .filter(filters::user_id.eq(user_id))
+        .filter(filters::name.eq(name))
+        .first(conn)
+}
+
+/// Record (or replace) the filter and exposure used for an image
+pub fn set_image_filter(conn: &mut SqliteConnection, entry: &NewImageFilter) -> QueryResult<()> {
+    diesel::insert_into(image_filters::table)
+        .values(entry)
+        .on_conflict(image_filters::image_id)
+        .do_update()
+        .set((
+            image_filters::filter_id.eq(&entry.filter_id),
+            image_filters::raw_value.eq(&entry.raw_value),
+            image_filters::frame_count.eq(&entry.frame_count),
+            image_filters::exposure_seconds.eq(&entry.exposure_seconds),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// (image_id, filter name, band, frames, exposure seconds)
+type ImageFilterRow = (String, String, String, i32, f64);
+
+fn load_image_filter_rows(
+    conn: &mut SqliteConnection,
+    image_ids: Vec<String>,
+) -> QueryResult<Vec<ImageFilterRow>> {
+    image_filters::table
+        .inner_join(filters::table)
+        .filter(image_filters::image_id.eq_any(image_ids))
+        .select((
+            image_filters::image_id,
+            filters::name,
+            filters::band,
+            image_filters::frame_count,
+            image_filters::exposure_seconds,
+        ))
+        .load(conn)
+}
+
+/// Summarize exposure per filter for a target (matched like `get_images_by_target`)
+pub fn get_filter_summary_for_target(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    target_name: &str,
+) -> QueryResult<Vec<FilterExposureSummary>> {
+    let image_ids: Vec<String> = get_images_by_target(conn, user_id, target_name)?
+        .into_iter()
+        .map(|img| img.id)
+        .collect();
+    get_filter_summary_for_images(conn, image_ids)
+}
+
+/// Summarize exposure per filter across the given images
+pub fn get_filter_summary_for_images(
+    conn: &mut SqliteConnection,
+    image_ids: Vec<String>,
+) -> QueryResult<Vec<FilterExposureSummary>> {
+    if image_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut by_filter: std::collections::HashMap<String, FilterExposureSummary> =
+        std::collections::HashMap::new();
+    for (_, name, band, frames, exposure) in load_image_filter_rows(conn, image_ids)? {
+        let entry = by_filter.entry(name.clone()).or_insert(FilterExposureSummary {
+            filter: name,
+            band,
+            image_count: 0,
+            frame_count: 0,
+            total_exposure_seconds: 0.0,
+        });
+        entry.image_count += 1;
+        entry.frame_count += frames as i64;
+        entry.total_exposure_seconds += exposure;
+    }
+
+    let mut summaries: Vec<FilterExposureSummary> = by_filter.into_values().collect();
+    summaries.sort_by(|a, b| b.total_exposure_seconds.total_cmp(&a.total_exposure_seconds));
+    Ok(summaries)
+}
+
+/// Summarize exposure per filter for every target (grouped by image summary)
+pub fn get_filter_exposure_by_target(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> QueryResult<Vec<TargetFilterExposure>> {
+    let targets: Vec<(String, Option<String>)> = images::table
+        .filter(images::user_id.eq(user_id))
+        .select((images::id, images::summary))
+        .load(conn)?;
+
+    let target_by_image: std::collections::HashMap<String, String> = targets
+        .into_iter()
+        .filter_map(|(id, summary)| {
+            let summary = summary?.trim().to_string();
+            (!summary.is_empty()).then_some((id, summary))
+        })
+        .collect();
+
+    if target_by_image.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let image_ids: Vec<String> = target_by_image.keys().cloned().collect();
+    let mut grouped: std::collections::HashMap<(String, String), TargetFilterExposure> =
+        std::collections::HashMap::new();
+    for (image_id, name, band, frames, exposure) in load_image_filter_rows(conn, image_ids)? {
+        let Some(target) = target_by_image.get(&image_id) else {
+            continue;
+        };
+        let entry = grouped
+            .entry((target.clone(), name.clone()))
+            .or_insert(TargetFilterExposure {
+                target: target.clone(),
+                filter: name,
+                band,
+                image_count: 0,
+                frame_count: 0,
+                total_exposure_seconds: 0.0,
+            });
+        entry.image_count += 1;
+        entry.frame_count += frames as i64;
+        entry.total_exposure_seconds += exposure;
+    }
+
+    let mut rows: Vec<TargetFilterExposure> = grouped.into_values().collect();
+    rows.sort_by(|a, b| {
+        a.target
+            .cmp(&b.target)
+            .then_with(|| b.total_exposure_seconds.total_cmp(&a.total_exposure_seconds))
+    });
+    Ok(rows)
+}
+
+// ============================================================================
+// Sync Repository - Change vectors, bookkeeping, and imported bundles
+// ============================================================================
+
+pub fn get_sync_meta(conn: &mut SqliteConnection, meta_key: &str) -> QueryResult<Option<String>> {
+    sync_meta::table
+        .filter(sync_meta::key.eq(meta_key))
+        .select(sync_meta::value)
+        .first(conn)
+        .optional()
+}
+
+pub fn set_sync_meta(conn: &mut SqliteConnection, meta_key: &str, meta_value: &str) -> QueryResult<()> {
+    diesel::replace_into(sync_meta::table)
+        .values((sync_meta::key.eq(meta_key), sync_meta::value.eq(meta_value)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Local changes (not received from another device) newer than `after_version`
+pub fn get_local_sync_changes(
+    conn: &mut SqliteConnection,
+    after_version: i64,
+) -> QueryResult<Vec<SyncState>> {
+    sync_state::table
+        .filter(sync_state::version.gt(after_version))
+        .filter(sync_state::origin.is_null())
+        .order(sync_state::version.asc())
+        .select(SyncState::as_select())
+        .load(conn)
+}
+
+pub fn get_sync_state(
+    conn: &mut SqliteConnection,
+    table: &str,
+    id: &str,
+) -> QueryResult<Option<SyncState>> {
+    sync_state::table
+        .filter(sync_state::table_name.eq(table))
+        .filter(sync_state::row_id.eq(id))
+        .select(SyncState::as_select())
+        .first(conn)
+        .optional()
+}
+
+pub fn get_max_sync_version(conn: &mut SqliteConnection) -> QueryResult<i64> {
+    sync_state::table
+        .select(diesel::dsl::max(sync_state::version))
+        .first::<Option<i64>>(conn)
+        .map(|v| v.unwrap_or(0))
+}
+
+/// Record a change vector received from another device. Overwrites the
+/// entry the triggers wrote when the change was applied locally.
+pub fn set_remote_sync_state(
+    conn: &mut SqliteConnection,
+    table: &str,
+    id: &str,
+    changed_at: &str,
+    is_deleted: bool,
+    device_id: &str,
+) -> QueryResult<()> {
+    let next_version = get_max_sync_version(conn)? + 1;
+    diesel::replace_into(sync_state::table)
+        .values(&SyncState {
+            table_name: table.to_string(),
+            row_id: id.to_string(),
+            updated_at: changed_at.to_string(),
+            deleted: is_deleted,
+            origin: Some(device_id.to_string()),
+            version: next_version,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn is_sync_bundle_imported(conn: &mut SqliteConnection, id: &str) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        sync_imports::table.filter(sync_imports::bundle_id.eq(id)),
+    ))
+    .get_result(conn)
+}
+
+pub fn record_sync_import(conn: &mut SqliteConnection, import: &NewSyncImport) -> QueryResult<()> {
+    diesel::insert_into(sync_imports::table)
+        .values(import)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_sync_imports(conn: &mut SqliteConnection) -> QueryResult<Vec<SyncImport>> {
+    sync_imports::table
+        .order(sync_imports::imported_at.desc())
+        .select(SyncImport::as_select())
+        .load(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::*;
+    use crate::db::DbPool;
+    use diesel::r2d2::{self, ConnectionManager};
+    use diesel::sqlite::SqliteConnection;
+    use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+    const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+    fn setup_test_db() -> DbPool {
+        let manager = ConnectionManager::<SqliteConnection>::new(":memory:");
+        let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
+        let mut conn = pool.get().unwrap();
+        conn.run_pending_migrations(MIGRATIONS).unwrap();
+        pool
+    }
+
+    /// Insert a test user so foreign key constraints are satisfied.
+    fn insert_test_user(conn: &mut SqliteConnection, user_id: &str) {
+        diesel::insert_into(users::table)
+            .values(&NewUser {
+                id: user_id.to_string(),
+                email: None,
+                name: Some("Test User".to_string()),
+                image: None,
+                username: None,
+                first_name: None,
+                last_name: None,
+            })
+            .execute(conn)
+            .unwrap();
+    }
+
+    // ========================================================================
+    // Collection CRUD
+    // ========================================================================
+
+    #[test]
+    fn collection_create_and_get() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = NewCollection {
+            id: "coll-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "My Collection".to_string(),
+            description: Some("Test description".to_string()),
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        let created = create_collection(&mut conn, &new).unwrap();
+        assert_eq!(created.name, "My Collection");
+        assert_eq!(created.description, Some("Test description".to_string()));
+
+        let fetched = get_collection_by_id(&mut conn, "coll-1").unwrap();
+        assert!(fetched.is_some());
+        assert_eq!(fetched.unwrap().name, "My Collection");
+    }
+
+    #[test]
+    fn collection_get_by_name() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = NewCollection {
+            id: "coll-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "2026-01-15".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &new).unwrap();
+
+        let found = get_collection_by_name(&mut conn, "user-1", "2026-01-15").unwrap();
+        assert!(found.is_some());
+
+        let not_found = get_collection_by_name(&mut conn, "user-1", "nonexistent").unwrap();
+        assert!(not_found.is_none());
+    }
+
+    #[test]
+    fn collection_update() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = NewCollection {
+            id: "coll-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Original".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &new).unwrap();
+
+        let update = UpdateCollection {
+            name: Some("Updated".to_string()),
+            favorite: Some(true),
+            ..Default::default()
+        };
+        let updated = update_collection(&mut conn, "coll-1", &update).unwrap();
+        assert_eq!(updated.name, "Updated");
+        assert!(updated.favorite);
+    }
+
+    #[test]
+    fn collection_delete() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = NewCollection {
+            id: "coll-del".to_string(),
+            user_id: "user-1".to_string(),
+            name: "To Delete".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &new).unwrap();
+
+        let deleted = delete_collection(&mut conn, "coll-del").unwrap();
+        assert_eq!(deleted, 1);
+
+        let fetched = get_collection_by_id(&mut conn, "coll-del").unwrap();
+        assert!(fetched.is_none());
+    }
+
+    #[test]
+    fn collection_list_by_user() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        insert_test_user(&mut conn, "user-2");
+
+        for i in 0..3 {
+            let new = NewCollection {
+                id: format!("coll-{}", i),
+                user_id: "user-1".to_string(),
+                name: format!("Collection {}", i),
+                description: None,
+                visibility: "private".to_string(),
+                template: None,
+                favorite: false,
+                tags: None,
+                metadata: None,
+                archived: false,
+            };
+            create_collection(&mut conn, &new).unwrap();
+        }
+
+        let colls = get_collections(&mut conn, "user-1").unwrap();
+        assert_eq!(colls.len(), 3);
+
+        let colls2 = get_collections(&mut conn, "user-2").unwrap();
+        assert!(colls2.is_empty());
+    }
+
+    #[test]
+    fn archived_collections_are_listed_separately() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        create_collection(&mut conn, &make_new_collection("active", "Active")).unwrap();
+        create_collection(&mut conn, &make_new_collection("old", "Old")).unwrap();
+
+        let old = set_collection_archived(&mut conn, "old", true).unwrap();
+        assert!(old.archived);
+
+        let active = get_collections(&mut conn, "user-1").unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, "active");
+        let archived = get_archived_collections(&mut conn, "user-1").unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, "old");
+        assert_eq!(get_all_collections(&mut conn, "user-1").unwrap().len(), 2);
+
+        set_collection_archived(&mut conn, "old", false).unwrap();
+        assert_eq!(get_collections(&mut conn, "user-1").unwrap().len(), 2);
+        let archived = get_archived_collections(&mut conn, "user-1").unwrap();
+        assert!(archived.is_empty());
+    }
+
+    fn make_auto_collection(id: &str, name: &str) -> NewCollection {
+        NewCollection {
+            metadata: Some(r#"{"auto_imported":true,"session_date":"2025-03-01"}"#.to_string()),
+            ..make_new_collection(id, name)
+        }
+    }
+
+    #[test]
+    fn auto_collections_are_created_once_per_name() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let (first, created) =
+            get_or_create_auto_collection(&mut conn, &make_auto_collection("a", "2025-03-01"))
+                .unwrap();
+        assert!(created);
+        // A second import racing to create the same session gets the first
+        let (second, created) =
+            get_or_create_auto_collection(&mut conn, &make_auto_collection("b", "2025-03-01"))
+                .unwrap();
+        assert!(!created);
+        assert_eq!(second.id, first.id);
+        assert!(create_collection(&mut conn, &make_auto_collection("c", "2025-03-01")).is_err());
+
+        // Collections the user made may share the name
+        create_collection(&mut conn, &make_new_collection("mine", "2025-03-01")).unwrap();
+        assert_eq!(get_collections(&mut conn, "user-1").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn migration_merges_duplicate_auto_collections() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        // Stop before the unique index, when duplicates could still be made
+        while !conn
+            .applied_migrations()
+            .unwrap()
+            .iter()
+            .any(|v| v.to_string() == "20250123000000")
+        {
+            conn.run_next_migration(MIGRATIONS).unwrap();
+        }
+        insert_test_user(&mut conn, "user-1");
+        create_collection(&mut conn, &make_auto_collection("old", "2025-03-01")).unwrap();
+        create_collection(&mut conn, &make_auto_collection("dup", "2025-03-01")).unwrap();
+        create_collection(&mut conn, &make_new_collection("mine", "2025-03-01")).unwrap();
+        for (image, collection) in [("a", "old"), ("b", "dup"), ("c", "mine")] {
+            // Inserted directly: reading the row back needs columns added by
+            // later migrations
+            diesel::insert_into(images::table)
+                .values(&make_new_image(image, "user-1"))
+                .execute(&mut conn)
+                .unwrap();
+            add_image_to_collection(
+                &mut conn,
+                &NewCollectionImage {
+                    id: format!("ci-{}", image),
+                    collection_id: collection.to_string(),
+                    image_id: image.to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        conn.run_pending_migrations(MIGRATIONS).unwrap();
+        let ids: Vec<_> = get_collections(&mut conn, "user-1")
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"old".to_string()) && ids.contains(&"mine".to_string()));
+        let merged: Vec<_> = get_images_in_collection(&mut conn, "old")
+            .unwrap()
+            .into_iter()
+            .map(|i| i.id)
+            .collect();
+        assert_eq!(merged.len(), 2);
+        assert!(merged.contains(&"b".to_string()));
+    }
+
+    // ========================================================================
+    // Image CRUD
+    // ========================================================================
+
+    fn make_new_image(id: &str, user_id: &str) -> NewImage {
+        NewImage {
+            user_id: user_id.to_string(),
+            filename: format!("{}.jpg", id),
+            url: Some(format!("/images/{}.jpg", id)),
+            summary: Some("M42".to_string()),
+            content_type: Some("image/jpeg".to_string()),
+            visibility: Some("private".to_string()),
+            ..NewImage::test(id)
+        }
+    }
+
+    #[test]
+    fn image_create_and_get_by_id() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = make_new_image("img-1", "user-1");
+        let created = create_image(&mut conn, &new).unwrap();
+        assert_eq!(created.filename, "img-1.jpg");
+
+        let fetched = get_image_by_id(&mut conn, "img-1").unwrap();
+        assert!(fetched.is_some());
+        assert_eq!(fetched.unwrap().summary, Some("M42".to_string()));
+    }
+
+    #[test]
+    fn search_images_by_acquisition_columns() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        for (id, filter, exposure, date_obs) in [
+            ("ha-1", "Ha", 300.0, "2025-03-01T22:10:00"),
+            ("ha-2", "Ha", 120.0, "2025-03-02T23:59:00"),
+            ("oiii", "OIII", 300.0, "2025-03-03T01:00:00"),
+        ] {
+            create_image(
+                &mut conn,
+                &NewImage {
+                    telescope: Some("RedCat 51".to_string()),
+                    filter: Some(filter.to_string()),
+                    exposure: Some(exposure),
+                    date_obs: Some(date_obs.to_string()),
+                    wcs: None,
+                    parent_image_id: None,
+                    triage: None,
+                    ..make_new_image(id, "user-1")
+                },
+            )
+            .unwrap();
+        }
+        create_image(&mut conn, &make_new_image("no-meta", "user-1")).unwrap();
+
+        let ids = |query: AcquisitionQuery, conn: &mut SqliteConnection| -> Vec<String> {
+            search_images_by_acquisition(conn, "user-1", &query)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.id)
+                .collect()
+        };
+
+        let ha = AcquisitionQuery {
+            filter: Some("Ha".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(ha, &mut conn), vec!["ha-2", "ha-1"]);
+
+        let long_subs = AcquisitionQuery {
+            min_exposure: Some(200.0),
+            ..Default::default()
+        };
+        assert_eq!(ids(long_subs, &mut conn), vec!["oiii", "ha-1"]);
+
+        let one_night = AcquisitionQuery {
+            date_from: Some("2025-03-02".to_string()),
+            date_to: Some("2025-03-02".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(one_night, &mut conn), vec!["ha-2"]);
+
+        let options = get_acquisition_options(&mut conn, "user-1").unwrap();
+        assert_eq!(options.telescopes, vec!["RedCat 51"]);
+        assert_eq!(options.filters, vec!["Ha", "OIII"]);
+        assert!(options.gains.is_empty());
+    }
+
+    #[test]
+    fn image_get_by_url() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = make_new_image("img-url", "user-1");
+        create_image(&mut conn, &new).unwrap();
+
+        let found = get_image_by_url(&mut conn, "/images/img-url.jpg").unwrap();
+        assert!(found.is_some());
+        assert_eq!(found.unwrap().id, "img-url");
+
+        let not_found = get_image_by_url(&mut conn, "/nonexistent.jpg").unwrap();
+        assert!(not_found.is_none());
+    }
+
+    #[test]
+    fn image_delete() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let new = make_new_image("img-del", "user-1");
+        create_image(&mut conn, &new).unwrap();
+
+        let deleted = delete_image(&mut conn, "img-del").unwrap();
+        assert_eq!(deleted, 1);
+
+        let fetched = get_image_by_id(&mut conn, "img-del").unwrap();
+        assert!(fetched.is_none());
+    }
+
+    // ========================================================================
+    // Collection-Image relationships
+    // ========================================================================
+
+    #[test]
+    fn add_and_check_image_in_collection() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let coll = NewCollection {
+            id: "coll-1".to_string(),
             user_id: "user-1".to_string(),
-            name: "2026-01-15".to_string(),
+            name: "Session".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &coll).unwrap();
+
+        let img = make_new_image("img-1", "user-1");
+        create_image(&mut conn, &img).unwrap();
+
+        // Not in collection yet
+        assert!(!is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+
+        // Add to collection
+        let entry = NewCollectionImage {
+            id: "ci-1".to_string(),
+            collection_id: "coll-1".to_string(),
+            image_id: "img-1".to_string(),
+        };
+        add_image_to_collection(&mut conn, &entry).unwrap();
+
+        // Now in collection
+        assert!(is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+
+        // Check count
+        let count = get_collection_image_count(&mut conn, "coll-1").unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn remove_image_from_collection_works() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let coll = NewCollection {
+            id: "coll-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Session".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &coll).unwrap();
+
+        let img = make_new_image("img-1", "user-1");
+        create_image(&mut conn, &img).unwrap();
+
+        let entry = NewCollectionImage {
+            id: "ci-1".to_string(),
+            collection_id: "coll-1".to_string(),
+            image_id: "img-1".to_string(),
+        };
+        add_image_to_collection(&mut conn, &entry).unwrap();
+        assert!(is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+
+        remove_image_from_collection(&mut conn, "coll-1", "img-1").unwrap();
+        assert!(!is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+    }
+
+    #[test]
+    fn get_images_in_collection_returns_images() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let coll = NewCollection {
+            id: "coll-1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Session".to_string(),
+            description: None,
+            visibility: "private".to_string(),
+            template: None,
+            favorite: false,
+            tags: None,
+            metadata: None,
+            archived: false,
+        };
+        create_collection(&mut conn, &coll).unwrap();
+
+        for i in 0..3 {
+            let img = make_new_image(&format!("img-{}", i), "user-1");
+            create_image(&mut conn, &img).unwrap();
+            let entry = NewCollectionImage {
+                id: format!("ci-{}", i),
+                collection_id: "coll-1".to_string(),
+                image_id: format!("img-{}", i),
+            };
+            add_image_to_collection(&mut conn, &entry).unwrap();
+        }
+
+        let images = get_images_in_collection(&mut conn, "coll-1").unwrap();
+        assert_eq!(images.len(), 3);
+    }
+
+    #[test]
+    fn collection_images_follow_sort_preference() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        create_collection(&mut conn, &make_new_collection("coll-1", "Session")).unwrap();
+
+        for (id, filename) in [
+            ("img-0", "c.fits"),
+            ("img-1", "A.fits"),
+            ("img-2", "b.fits"),
+        ] {
+            let img = NewImage {
+                filename: filename.to_string(),
+                ..make_new_image(id, "user-1")
+            };
+            create_image(&mut conn, &img).unwrap();
+        }
+        add_images_to_collection(
+            &mut conn,
+            "coll-1",
+            &[
+                "img-0".to_string(),
+                "img-1".to_string(),
+                "img-2".to_string(),
+            ],
+        )
+        .unwrap();
+        let ids = |conn: &mut SqliteConnection| -> Vec<String> {
+            get_sorted_collection_images(conn, "coll-1")
+                .unwrap()
+                .into_iter()
+                .map(|image| image.id)
+                .collect()
+        };
+
+        set_collection_image_sort(&mut conn, "coll-1", Some(ImageSort::Name)).unwrap();
+        assert_eq!(ids(&mut conn), ["img-1", "img-2", "img-0"]);
+
+        set_image_rating(&mut conn, "img-2", Some(5)).unwrap();
+        set_image_rating(&mut conn, "img-0", Some(3)).unwrap();
+        set_collection_image_sort(&mut conn, "coll-1", Some(ImageSort::Rating)).unwrap();
+        assert_eq!(ids(&mut conn), ["img-2", "img-0", "img-1"]);
+
+        // Images left out of a reorder follow the placed ones
+        set_collection_image_order(
+            &mut conn,
+            "coll-1",
+            &["img-2".to_string(), "img-1".to_string()],
+        )
+        .unwrap();
+        let collection = get_collection_by_id(&mut conn, "coll-1").unwrap().unwrap();
+        assert_eq!(collection.image_sort.as_deref(), Some("manual"));
+        assert_eq!(ids(&mut conn), ["img-2", "img-1", "img-0"]);
+
+        set_collection_image_order(&mut conn, "coll-1", &["img-0".to_string()]).unwrap();
+        assert_eq!(ids(&mut conn)[0], "img-0");
+    }
+
+    fn make_new_collection(id: &str, name: &str) -> NewCollection {
+        NewCollection {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            name: name.to_string(),
             description: None,
             visibility: "private".to_string(),
             template: None,
             favorite: false,
             tags: None,
-            metadata: None,
-            archived: false,
-        };
-        create_collection(&mut conn, &new).unwrap();
+            metadata: None,
+            archived: false,
+        }
+    }
+
+    /// Create a collection holding new images with the given ids
+    fn collection_with_images(conn: &mut SqliteConnection, id: &str, image_ids: &[&str]) {
+        create_collection(conn, &make_new_collection(id, id)).unwrap();
+        for image_id in image_ids {
+            if get_image_by_id(conn, image_id).unwrap().is_none() {
+                let image = NewImage {
+                    collection_id: Some(id.to_string()),
+                    ..make_new_image(image_id, "user-1")
+                };
+                create_image(conn, &image).unwrap();
+            }
+            add_images_to_collection(conn, id, &[image_id.to_string()]).unwrap();
+        }
+    }
+
+    fn sorted_image_ids(conn: &mut SqliteConnection, collection_id: &str) -> Vec<String> {
+        let mut ids = get_collection_image_ids(conn, collection_id).unwrap();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn merge_collections_dedupes_and_removes_source() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        collection_with_images(&mut conn, "target", &["a", "b"]);
+        collection_with_images(&mut conn, "source", &["b", "c"]);
+        let note = NewNote {
+            id: "n1".to_string(),
+            user_id: "user-1".to_string(),
+            entity_type: "collection".to_string(),
+            entity_id: "source".to_string(),
+            body: "Flats taken at dusk".to_string(),
+            attachments: None,
+        };
+        create_note(&mut conn, &note).unwrap();
+
+        assert_eq!(merge_collections(&mut conn, "target", "source").unwrap(), 1);
+        assert_eq!(sorted_image_ids(&mut conn, "target"), vec!["a", "b", "c"]);
+        assert!(get_collection_by_id(&mut conn, "source").unwrap().is_none());
+        let c = get_image_by_id(&mut conn, "c").unwrap().unwrap();
+        assert_eq!(c.collection_id.as_deref(), Some("target"));
+        let notes = get_notes_for_entity(&mut conn, "user-1", "collection", "target").unwrap();
+        assert_eq!(notes.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_collection_optionally_copies_images() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        collection_with_images(&mut conn, "source", &["a", "b"]);
+
+        let template = make_new_collection("template", "Template");
+        duplicate_collection(&mut conn, "source", &template, false).unwrap();
+        assert!(sorted_image_ids(&mut conn, "template").is_empty());
+
+        let copy = make_new_collection("copy", "Copy");
+        duplicate_collection(&mut conn, "source", &copy, true).unwrap();
+        assert_eq!(sorted_image_ids(&mut conn, "copy"), vec!["a", "b"]);
+        assert_eq!(sorted_image_ids(&mut conn, "source"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn split_collection_moves_listed_images() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        collection_with_images(&mut conn, "source", &["a", "b", "c", "d"]);
+
+        let parts = vec![
+            (
+                make_new_collection("m31", "M31"),
+                vec!["a".to_string(), "b".to_string()],
+            ),
+            (make_new_collection("m42", "M42"), vec!["c".to_string()]),
+        ];
+        let created = split_collection(&mut conn, "source", &parts).unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(sorted_image_ids(&mut conn, "m31"), vec!["a", "b"]);
+        assert_eq!(sorted_image_ids(&mut conn, "m42"), vec!["c"]);
+        assert_eq!(sorted_image_ids(&mut conn, "source"), vec!["d"]);
+        let a = get_image_by_id(&mut conn, "a").unwrap().unwrap();
+        assert_eq!(a.collection_id.as_deref(), Some("m31"));
+    }
+
+    #[test]
+    fn cover_is_cleared_when_image_leaves_collection() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        collection_with_images(&mut conn, "one", &["a", "b"]);
+        collection_with_images(&mut conn, "two", &["a"]);
+
+        set_collection_cover(&mut conn, "one", Some("a")).unwrap();
+        let two = set_collection_cover(&mut conn, "two", Some("a")).unwrap();
+        assert_eq!(two.cover_image_id.as_deref(), Some("a"));
+
+        remove_image_from_collection(&mut conn, "one", "a").unwrap();
+        let one = get_collection_by_id(&mut conn, "one").unwrap().unwrap();
+        assert_eq!(one.cover_image_id, None);
+        let two = get_collection_by_id(&mut conn, "two").unwrap().unwrap();
+        assert_eq!(two.cover_image_id.as_deref(), Some("a"));
+
+        delete_image(&mut conn, "a").unwrap();
+        let two = get_collection_by_id(&mut conn, "two").unwrap().unwrap();
+        assert_eq!(two.cover_image_id, None);
+    }
+
+    // ========================================================================
+    // Todo CRUD
+    // ========================================================================
+
+    fn make_new_todo(id: &str, user_id: &str, name: &str) -> NewAstronomyTodo {
+        NewAstronomyTodo {
+            id: id.to_string(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            ra: "05h35m17s".to_string(),
+            dec: "-05d23m28s".to_string(),
+            magnitude: "4.0".to_string(),
+            size: "85'".to_string(),
+            object_type: Some("Nebula".to_string()),
+            added_at: "2026-01-15T00:00:00Z".to_string(),
+            completed: false,
+            completed_at: None,
+            goal_time: None,
+            notes: None,
+            flagged: false,
+            last_updated: None,
+            tags: None,
+        }
+    }
 
-        let found = get_collection_by_name(&mut conn, "user-1", "2026-01-15").unwrap();
-        assert!(found.is_some());
+    #[test]
+    fn todo_create_and_get() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
 
-        let not_found = get_collection_by_name(&mut conn, "user-1", "nonexistent").unwrap();
-        assert!(not_found.is_none());
+        let new = make_new_todo("todo-1", "user-1", "M42");
+        let created = create_todo(&mut conn, &new).unwrap();
+        assert_eq!(created.name, "M42");
+        assert!(!created.completed);
+
+        let fetched = get_todo_by_id(&mut conn, "todo-1").unwrap();
+        assert!(fetched.is_some());
     }
 
     #[test]
-    fn collection_update() {
+    fn todo_update() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = NewCollection {
-            id: "coll-1".to_string(),
-            user_id: "user-1".to_string(),
-            name: "Original".to_string(),
-            description: None,
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
-            archived: false,
-        };
-        create_collection(&mut conn, &new).unwrap();
+        let new = make_new_todo("todo-1", "user-1", "M42");
+        create_todo(&mut conn, &new).unwrap();
 
-        let update = UpdateCollection {
-            name: Some("Updated".to_string()),
-            favorite: Some(true),
+        let update = UpdateAstronomyTodo {
+            completed: Some(true),
+            notes: Some("Great view tonight!".to_string()),
             ..Default::default()
         };
-        let updated = update_collection(&mut conn, "coll-1", &update).unwrap();
-        assert_eq!(updated.name, "Updated");
-        assert!(updated.favorite);
+        let updated = update_todo(&mut conn, "todo-1", &update).unwrap();
+        assert!(updated.completed);
+        assert_eq!(updated.notes, Some("Great view tonight!".to_string()));
     }
 
     #[test]
-    fn collection_delete() {
+    fn todo_delete() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = NewCollection {
-            id: "coll-del".to_string(),
-            user_id: "user-1".to_string(),
-            name: "To Delete".to_string(),
-            description: None,
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
-            archived: false,
-        };
-        create_collection(&mut conn, &new).unwrap();
+        let new = make_new_todo("todo-del", "user-1", "M31");
+        create_todo(&mut conn, &new).unwrap();
 
-        let deleted = delete_collection(&mut conn, "coll-del").unwrap();
+        let deleted = delete_todo(&mut conn, "todo-del").unwrap();
         assert_eq!(deleted, 1);
 
-        let fetched = get_collection_by_id(&mut conn, "coll-del").unwrap();
+        let fetched = get_todo_by_id(&mut conn, "todo-del").unwrap();
         assert!(fetched.is_none());
     }
 
     #[test]
-    fn collection_list_by_user() {
+    fn todo_list_by_user() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
-        insert_test_user(&mut conn, "user-2");
 
         for i in 0..3 {
-            let new = NewCollection {
-                id: format!("coll-{}", i),
-                user_id: "user-1".to_string(),
-                name: format!("Collection {}", i),
-                description: None,
-                visibility: "private".to_string(),
-                template: None,
-                favorite: false,
-                tags: None,
-                metadata: None,
-                archived: false,
-            };
-            create_collection(&mut conn, &new).unwrap();
+            let new = make_new_todo(&format!("todo-{}", i), "user-1", &format!("M{}", i + 1));
+            create_todo(&mut conn, &new).unwrap();
         }
 
-        let colls = get_collections(&mut conn, "user-1").unwrap();
-        assert_eq!(colls.len(), 3);
+        let todos = get_todos(&mut conn, "user-1").unwrap();
+        assert_eq!(todos.len(), 3);
+    }
 
-        let colls2 = get_collections(&mut conn, "user-2").unwrap();
-        assert!(colls2.is_empty());
+    // ========================================================================
+    // Query tests
+    // ========================================================================
+
+    #[test]
+    fn get_targets_with_counts_groups_by_summary() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        // Create 2 images with summary "M42" and 1 with "M31"
+        for i in 0..2 {
+            let mut img = make_new_image(&format!("m42-{}", i), "user-1");
+            img.summary = Some("M42".to_string());
+            create_image(&mut conn, &img).unwrap();
+        }
+        let mut img = make_new_image("m31-0", "user-1");
+        img.summary = Some("M31".to_string());
+        create_image(&mut conn, &img).unwrap();
+
+        let targets = get_targets_with_counts(&mut conn, "user-1").unwrap();
+        assert_eq!(targets.len(), 2);
+        // Sorted by count descending, so M42 first
+        assert_eq!(targets[0].name, "M42");
+        assert_eq!(targets[0].image_count, 2);
+        assert_eq!(targets[1].name, "M31");
+        assert_eq!(targets[1].image_count, 1);
+    }
+
+    #[test]
+    fn search_images_by_target_partial_match() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let mut img = make_new_image("img-1", "user-1");
+        img.summary = Some("Orion Nebula M42".to_string());
+        create_image(&mut conn, &img).unwrap();
+
+        let mut img2 = make_new_image("img-2", "user-1");
+        img2.summary = Some("Andromeda M31".to_string());
+        create_image(&mut conn, &img2).unwrap();
+
+        let results = search_images_by_target(&mut conn, "user-1", "M42").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "img-1");
+
+        // Partial match
+        let results = search_images_by_target(&mut conn, "user-1", "Nebula").unwrap();
+        assert_eq!(results.len(), 1);
+
+        // No match
+        let results = search_images_by_target(&mut conn, "user-1", "NGC 7000").unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn processed_versions_match_source_id() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        create_image(&mut conn, &make_new_image("src-1", "user-1")).unwrap();
+        for (id, source) in [("proc-1", "src-1"), ("proc-2", "src-1"), ("proc-3", "src-10")] {
+            let mut img = make_new_image(id, "user-1");
+            img.metadata = Some(serde_json::json!({ "source_image_id": source }).to_string());
+            create_image(&mut conn, &img).unwrap();
+        }
+
+        let mut ids: Vec<String> = get_processed_versions(&mut conn, "src-1")
+            .unwrap()
+            .into_iter()
+            .map(|i| i.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["proc-1", "proc-2"]);
     }
 
     // ========================================================================
-    // Image CRUD
+    // Equipment
     // ========================================================================
 
-    fn make_new_image(id: &str, user_id: &str) -> NewImage {
-        NewImage {
+    fn make_new_equipment(id: &str, user_id: &str, kind: &str, name: &str) -> NewEquipment {
+        NewEquipment {
             id: id.to_string(),
             user_id: user_id.to_string(),
-            collection_id: None,
-            filename: format!("{}.jpg", id),
-            url: Some(format!("/images/{}.jpg", id)),
-            summary: Some("M42".to_string()),
-            description: None,
-            content_type: Some("image/jpeg".to_string()),
-            favorite: false,
-            tags: None,
-            visibility: Some("private".to_string()),
-            location: None,
-            annotations: None,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            manufacturer: None,
+            model: None,
+            match_patterns: None,
+            focal_length_mm: None,
+            aperture_mm: None,
+            pixel_size_um: None,
+            sensor_width_px: None,
+            sensor_height_px: None,
+            reduction_factor: None,
+            notes: None,
             metadata: None,
-            thumbnail: None,
-            fits_url: None,
-            blob_id: None,
+            retired: false,
         }
     }
 
     #[test]
-    fn image_create_and_get_by_id() {
+    fn equipment_create_update_delete() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = make_new_image("img-1", "user-1");
-        let created = create_image(&mut conn, &new).unwrap();
-        assert_eq!(created.filename, "img-1.jpg");
+        let mut new = make_new_equipment("eq-1", "user-1", "telescope", "RedCat 51");
+        new.focal_length_mm = Some(250.0);
+        let created = create_equipment(&mut conn, &new).unwrap();
+        assert_eq!(created.focal_length_mm, Some(250.0));
 
-        let fetched = get_image_by_id(&mut conn, "img-1").unwrap();
-        assert!(fetched.is_some());
-        assert_eq!(fetched.unwrap().summary, Some("M42".to_string()));
+        let update = UpdateEquipment {
+            aperture_mm: Some(51.0),
+            ..Default::default()
+        };
+        let updated = update_equipment(&mut conn, "eq-1", &update).unwrap();
+        assert_eq!(updated.aperture_mm, Some(51.0));
+        assert_eq!(updated.focal_length_mm, Some(250.0));
+
+        assert_eq!(delete_equipment(&mut conn, "eq-1").unwrap(), 1);
+        assert!(get_equipment_by_id(&mut conn, "eq-1").unwrap().is_none());
     }
 
     #[test]
-    fn image_get_by_url() {
+    fn maintenance_log_crud_and_cascade() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
+        create_equipment(&mut conn, &make_new_equipment("eq-1", "user-1", "telescope", "C8")).unwrap();
+
+        for (id, task, performed_on) in [
+            ("m-1", "Collimation", "2025-01-02"),
+            ("m-2", "Collimation", "2025-03-01"),
+            ("m-3", "Firmware update", "2025-02-10"),
+        ] {
+            let entry = NewMaintenanceEntry {
+                id: id.to_string(),
+                user_id: "user-1".to_string(),
+                equipment_id: "eq-1".to_string(),
+                task: task.to_string(),
+                performed_on: performed_on.to_string(),
+                interval_days: Some(60),
+                notes: None,
+            };
+            create_maintenance_entry(&mut conn, &entry).unwrap();
+        }
+        let log = get_maintenance_for_equipment(&mut conn, "eq-1").unwrap();
+        let ids: Vec<&str> = log.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["m-2", "m-3", "m-1"]);
 
-        let new = make_new_image("img-url", "user-1");
-        create_image(&mut conn, &new).unwrap();
+        let update = UpdateMaintenanceEntry {
+            interval_days: Some(None),
+            notes: Some(Some("Star test".to_string())),
+            ..Default::default()
+        };
+        let updated = update_maintenance_entry(&mut conn, "m-3", &update).unwrap();
+        assert_eq!(updated.interval_days, None);
+        assert_eq!(updated.notes.as_deref(), Some("Star test"));
+        assert_eq!(updated.task, "Firmware update");
+
+        assert_eq!(delete_maintenance_entry(&mut conn, "m-1").unwrap(), 1);
+        delete_equipment(&mut conn, "eq-1").unwrap();
+        assert!(get_maintenance_entries(&mut conn, "user-1").unwrap().is_empty());
+    }
 
-        let found = get_image_by_url(&mut conn, "/images/img-url.jpg").unwrap();
-        assert!(found.is_some());
-        assert_eq!(found.unwrap().id, "img-url");
+    #[test]
+    fn auto_link_matches_telescope_and_camera_headers() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
 
-        let not_found = get_image_by_url(&mut conn, "/nonexistent.jpg").unwrap();
-        assert!(not_found.is_none());
+        let mut scope = make_new_equipment("eq-scope", "user-1", "telescope", "Seestar S50");
+        scope.match_patterns = Some("seestar".to_string());
+        create_equipment(&mut conn, &scope).unwrap();
+        create_equipment(&mut conn, &make_new_equipment("eq-cam", "user-1", "camera", "ZWO ASI585MC")).unwrap();
+        create_equipment(&mut conn, &make_new_equipment("eq-other", "user-1", "camera", "ZWO ASI2600MC")).unwrap();
+
+        create_image(&mut conn, &make_new_image("img-1", "user-1")).unwrap();
+
+        let linked = auto_link_image_equipment(
+            &mut conn,
+            "user-1",
+            "img-1",
+            Some("Seestar S50 #1234"),
+            Some("zwo asi585mc"),
+        )
+        .unwrap();
+        assert_eq!(linked, 2);
+
+        // Re-linking is a no-op
+        let relinked = auto_link_image_equipment(
+            &mut conn,
+            "user-1",
+            "img-1",
+            Some("Seestar S50 #1234"),
+            Some("ZWO ASI585MC"),
+        )
+        .unwrap();
+        assert_eq!(relinked, 0);
+
+        let gear = get_equipment_for_image(&mut conn, "img-1").unwrap();
+        assert_eq!(gear.len(), 2);
     }
 
     #[test]
-    fn image_delete() {
+    fn images_by_rig_requires_all_equipment() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = make_new_image("img-del", "user-1");
-        create_image(&mut conn, &new).unwrap();
+        create_equipment(&mut conn, &make_new_equipment("scope", "user-1", "telescope", "Scope")).unwrap();
+        create_equipment(&mut conn, &make_new_equipment("cam", "user-1", "camera", "Cam")).unwrap();
+
+        for id in ["img-1", "img-2"] {
+            create_image(&mut conn, &make_new_image(id, "user-1")).unwrap();
+            link_image_equipment(
+                &mut conn,
+                &NewImageEquipment {
+                    id: format!("{}-scope", id),
+                    image_id: id.to_string(),
+                    equipment_id: "scope".to_string(),
+                },
+            )
+            .unwrap();
+        }
+        link_image_equipment(
+            &mut conn,
+            &NewImageEquipment {
+                id: "img-1-cam".to_string(),
+                image_id: "img-1".to_string(),
+                equipment_id: "cam".to_string(),
+            },
+        )
+        .unwrap();
 
-        let deleted = delete_image(&mut conn, "img-del").unwrap();
-        assert_eq!(deleted, 1);
+        let rig = vec!["scope".to_string(), "cam".to_string()];
+        let images = get_images_by_rig(&mut conn, "user-1", &rig).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, "img-1");
+
+        let (total, stacked) = count_images_by_rig(&mut conn, "user-1", &["scope".to_string()]).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(stacked, 0);
+    }
+
+    // ========================================================================
+    // Filters
+    // ========================================================================
+
+    #[test]
+    fn filter_summary_sums_exposure_per_filter() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        let ha = get_or_create_filter(&mut conn, "user-1", "Ha", "narrowband").unwrap();
+        let oiii = get_or_create_filter(&mut conn, "user-1", "OIII", "narrowband").unwrap();
+        // Second lookup returns the existing row
+        let ha_again = get_or_create_filter(&mut conn, "user-1", "Ha", "narrowband").unwrap();
+        assert_eq!(ha.id, ha_again.id);
+
+        for (id, filter_id, exposure) in [
+            ("img-1", &ha.id, 600.0),
+            ("img-2", &ha.id, 300.0),
+            ("img-3", &oiii.id, 300.0),
+        ] {
+            create_image(&mut conn, &make_new_image(id, "user-1")).unwrap();
+            set_image_filter(
+                &mut conn,
+                &NewImageFilter {
+                    image_id: id.to_string(),
+                    filter_id: filter_id.clone(),
+                    raw_value: None,
+                    frame_count: 10,
+                    exposure_seconds: exposure,
+                },
+            )
+            .unwrap();
+        }
 
-        let fetched = get_image_by_id(&mut conn, "img-del").unwrap();
-        assert!(fetched.is_none());
+        let summary = get_filter_summary_for_target(&mut conn, "user-1", "M42").unwrap();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].filter, "Ha");
+        assert_eq!(summary[0].image_count, 2);
+        assert_eq!(summary[0].frame_count, 20);
+        assert_eq!(summary[0].total_exposure_seconds, 900.0);
+        assert_eq!(summary[1].filter, "OIII");
+
+        let by_target = get_filter_exposure_by_target(&mut conn, "user-1").unwrap();
+        assert_eq!(by_target.len(), 2);
+        assert!(by_target.iter().all(|row| row.target == "M42"));
     }
 
-    // ========================================================================
-    // Collection-Image relationships
-    // ========================================================================
-
     #[test]
-    fn add_and_check_image_in_collection() {
+    fn find_profile_for_image_prefers_most_specific() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let coll = NewCollection {
-            id: "coll-1".to_string(),
+        create_equipment(&mut conn, &make_new_equipment("scope", "user-1", "telescope", "Scope")).unwrap();
+        create_equipment(&mut conn, &make_new_equipment("cam", "user-1", "camera", "Cam")).unwrap();
+        create_image(&mut conn, &make_new_image("img-1", "user-1")).unwrap();
+
+        // No linked gear, no profile
+        assert!(find_profile_for_image(&mut conn, "user-1", "img-1").unwrap().is_none());
+
+        for equipment_id in ["scope", "cam"] {
+            link_image_equipment(
+                &mut conn,
+                &NewImageEquipment {
+                    id: format!("link-{}", equipment_id),
+                    image_id: "img-1".to_string(),
+                    equipment_id: equipment_id.to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        let mut scope_only = NewEquipmentProfile {
+            id: "p-scope".to_string(),
             user_id: "user-1".to_string(),
-            name: "Session".to_string(),
+            name: "Scope only".to_string(),
+            telescope_id: Some("scope".to_string()),
+            camera_id: None,
+            reducer_id: None,
+            scale_lower: None,
+            scale_upper: None,
+            processing_params: None,
+            notes: None,
+        };
+        create_equipment_profile(&mut conn, &scope_only).unwrap();
+        scope_only.id = "p-rig".to_string();
+        scope_only.name = "Full rig".to_string();
+        scope_only.camera_id = Some("cam".to_string());
+        create_equipment_profile(&mut conn, &scope_only).unwrap();
+
+        let found = find_profile_for_image(&mut conn, "user-1", "img-1").unwrap().unwrap();
+        assert_eq!(found.id, "p-rig");
+
+        // Deleting the camera detaches it from the profile
+        delete_equipment(&mut conn, "cam").unwrap();
+        let rig = get_equipment_profile_by_id(&mut conn, "p-rig").unwrap().unwrap();
+        assert!(rig.camera_id.is_none());
+    }
+
+    fn make_new_schedule(id: &str, date: Option<&str>, is_active: bool) -> NewObservationSchedule {
+        NewObservationSchedule {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            name: format!("Schedule {}", id),
             description: None,
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
+            scheduled_date: date.map(str::to_string),
+            location: None,
+            items: "[]".to_string(),
+            is_active,
+            equipment_id: None,
             archived: false,
-        };
-        create_collection(&mut conn, &coll).unwrap();
+        }
+    }
 
-        let img = make_new_image("img-1", "user-1");
-        create_image(&mut conn, &img).unwrap();
+    fn active_ids(conn: &mut SqliteConnection) -> Vec<String> {
+        let mut ids: Vec<String> = get_active_schedules(conn, "user-1")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        ids.sort();
+        ids
+    }
 
-        // Not in collection yet
-        assert!(!is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+    #[test]
+    fn one_active_schedule_per_night_and_equipment() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
 
-        // Add to collection
-        let entry = NewCollectionImage {
-            id: "ci-1".to_string(),
-            collection_id: "coll-1".to_string(),
-            image_id: "img-1".to_string(),
+        create_schedule(&mut conn, &make_new_schedule("a", Some("2026-03-01"), true)).unwrap();
+        create_schedule(&mut conn, &make_new_schedule("b", Some("2026-03-02"), true)).unwrap();
+        create_schedule(
+            &mut conn,
+            &make_new_schedule("c", Some("2026-03-02T20:00:00"), true),
+        )
+        .unwrap();
+        let other_rig = NewObservationSchedule {
+            equipment_id: Some("rig-2".to_string()),
+            ..make_new_schedule("d", Some("2026-03-02"), true)
         };
-        add_image_to_collection(&mut conn, &entry).unwrap();
+        create_schedule(&mut conn, &other_rig).unwrap();
+        // "c" took over the night of March 2nd from "b" on the same rig
+        assert_eq!(active_ids(&mut conn), vec!["a", "c", "d"]);
 
-        // Now in collection
-        assert!(is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+        let night = get_active_schedules_for_night(&mut conn, "user-1", "2026-03-02").unwrap();
+        assert_eq!(night.len(), 2);
 
-        // Check count
-        let count = get_collection_image_count(&mut conn, "coll-1").unwrap();
-        assert_eq!(count, 1);
+        // Moving "a" onto March 2nd replaces "c" there
+        let update = UpdateObservationSchedule {
+            scheduled_date: Some("2026-03-02".to_string()),
+            ..Default::default()
+        };
+        update_schedule(&mut conn, "a", &update).unwrap();
+        assert_eq!(active_ids(&mut conn), vec!["a", "d"]);
     }
 
     #[test]
-    fn remove_image_from_collection_works() {
+    fn packing_list_is_replaced_and_follows_schedule() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
+        create_schedule(&mut conn, &make_new_schedule("s", Some("2026-03-01"), true)).unwrap();
 
-        let coll = NewCollection {
-            id: "coll-1".to_string(),
+        let item = |id: &str, name: &str, sort_order: i32| NewPackingItem {
+            id: id.to_string(),
             user_id: "user-1".to_string(),
-            name: "Session".to_string(),
-            description: None,
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
-            archived: false,
+            schedule_id: "s".to_string(),
+            name: name.to_string(),
+            category: "power".to_string(),
+            equipment_id: None,
+            packed: false,
+            sort_order,
         };
-        create_collection(&mut conn, &coll).unwrap();
+        let first = [item("p-1", "Batteries", 1), item("p-2", "Dew band", 0)];
+        replace_packing_items(&mut conn, "s", &first).unwrap();
+        // Regenerating replaces the whole list
+        let second = [item("p-3", "Power bank", 1), item("p-4", "Dew band", 0)];
+        let list = replace_packing_items(&mut conn, "s", &second).unwrap();
+        let names: Vec<&str> = list.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Dew band", "Power bank"]);
+
+        assert!(set_packing_item_packed(&mut conn, "p-3", true).unwrap().packed);
+        assert_eq!(delete_packing_item(&mut conn, "p-4").unwrap(), 1);
+        assert_eq!(delete_schedule(&mut conn, "s").unwrap(), 1);
+        assert!(get_packing_item_by_id(&mut conn, "p-3").unwrap().is_none());
+    }
 
-        let img = make_new_image("img-1", "user-1");
-        create_image(&mut conn, &img).unwrap();
+    #[test]
+    fn archived_schedules_are_listed_separately_and_inactive() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
 
-        let entry = NewCollectionImage {
-            id: "ci-1".to_string(),
-            collection_id: "coll-1".to_string(),
-            image_id: "img-1".to_string(),
+        create_schedule(
+            &mut conn,
+            &make_new_schedule("old", Some("2026-01-10"), true),
+        )
+        .unwrap();
+        create_schedule(
+            &mut conn,
+            &make_new_schedule("new", Some("2026-02-10"), true),
+        )
+        .unwrap();
+        create_schedule(&mut conn, &make_new_schedule("undated", None, false)).unwrap();
+
+        assert_eq!(
+            archive_schedules_before(&mut conn, "user-1", "2026-02-01").unwrap(),
+            1
+        );
+        let archived = get_archived_schedules(&mut conn, "user-1").unwrap();
+        assert_eq!(archived.len(), 1);
+        assert!(archived[0].archived && !archived[0].is_active);
+        assert_eq!(get_schedules(&mut conn, "user-1").unwrap().len(), 2);
+        assert_eq!(active_ids(&mut conn), vec!["new"]);
+
+        let archived = set_schedule_archived(&mut conn, "new", true).unwrap();
+        assert!(archived.archived && !archived.is_active);
+
+        // Activating an archived schedule restores it
+        let update = UpdateObservationSchedule {
+            is_active: Some(true),
+            ..Default::default()
         };
-        add_image_to_collection(&mut conn, &entry).unwrap();
-        assert!(is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+        let restored = update_schedule(&mut conn, "old", &update).unwrap();
+        assert!(restored.is_active && !restored.archived);
+        assert_eq!(
+            get_archived_schedules(&mut conn, "user-1").unwrap().len(),
+            1
+        );
+    }
 
-        remove_image_from_collection(&mut conn, "coll-1", "img-1").unwrap();
-        assert!(!is_image_in_collection(&mut conn, "coll-1", "img-1").unwrap());
+    fn make_new_preset(id: &str, target_type: Option<&str>, is_default: bool) -> NewProcessingPreset {
+        NewProcessingPreset {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            name: id.to_string(),
+            target_type: target_type.map(String::from),
+            params: r#"{"contrast":1.5}"#.to_string(),
+            is_default,
+        }
     }
 
     #[test]
-    fn get_images_in_collection_returns_images() {
+    fn processing_preset_defaults_per_target_type() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let coll = NewCollection {
-            id: "coll-1".to_string(),
-            user_id: "user-1".to_string(),
-            name: "Session".to_string(),
-            description: None,
-            visibility: "private".to_string(),
-            template: None,
-            favorite: false,
-            tags: None,
-            metadata: None,
-            archived: false,
+        create_processing_preset(&mut conn, &make_new_preset("any", None, true)).unwrap();
+        create_processing_preset(&mut conn, &make_new_preset("galaxy-a", Some("galaxy"), true)).unwrap();
+        create_processing_preset(&mut conn, &make_new_preset("galaxy-b", Some("galaxy"), true)).unwrap();
+
+        // The newest default replaces the previous one for the same target type only
+        let a = get_processing_preset_by_id(&mut conn, "galaxy-a").unwrap().unwrap();
+        assert!(!a.is_default);
+        let default = |conn: &mut SqliteConnection, target_type| {
+            get_default_processing_preset(conn, "user-1", target_type)
+                .unwrap()
+                .map(|p| p.id)
         };
-        create_collection(&mut conn, &coll).unwrap();
+        assert_eq!(default(&mut conn, Some("galaxy")).as_deref(), Some("galaxy-b"));
+        assert_eq!(default(&mut conn, Some("open_cluster")).as_deref(), Some("any"));
+        assert_eq!(default(&mut conn, None).as_deref(), Some("any"));
 
-        for i in 0..3 {
-            let img = make_new_image(&format!("img-{}", i), "user-1");
-            create_image(&mut conn, &img).unwrap();
-            let entry = NewCollectionImage {
-                id: format!("ci-{}", i),
-                collection_id: "coll-1".to_string(),
-                image_id: format!("img-{}", i),
-            };
-            add_image_to_collection(&mut conn, &entry).unwrap();
-        }
+        set_default_processing_preset(&mut conn, "galaxy-a", true).unwrap();
+        assert_eq!(default(&mut conn, Some("galaxy")).as_deref(), Some("galaxy-a"));
 
-        let images = get_images_in_collection(&mut conn, "coll-1").unwrap();
-        assert_eq!(images.len(), 3);
-    }
+        set_default_processing_preset(&mut conn, "galaxy-a", false).unwrap();
+        assert_eq!(default(&mut conn, Some("galaxy")).as_deref(), Some("any"));
 
-    // ========================================================================
-    // Todo CRUD
-    // ========================================================================
+        assert_eq!(get_processing_presets(&mut conn, "user-1").unwrap().len(), 3);
+        assert_eq!(delete_processing_preset(&mut conn, "any").unwrap(), 1);
+        assert!(default(&mut conn, Some("galaxy")).is_none());
+    }
 
-    fn make_new_todo(id: &str, user_id: &str, name: &str) -> NewAstronomyTodo {
-        NewAstronomyTodo {
+    fn make_new_location(id: &str, is_default: bool) -> NewLocation {
+        NewLocation {
             id: id.to_string(),
-            user_id: user_id.to_string(),
-            name: name.to_string(),
-            ra: "05h35m17s".to_string(),
-            dec: "-05d23m28s".to_string(),
-            magnitude: "4.0".to_string(),
-            size: "85'".to_string(),
-            object_type: Some("Nebula".to_string()),
-            added_at: "2026-01-15T00:00:00Z".to_string(),
-            completed: false,
-            completed_at: None,
-            goal_time: None,
-            notes: None,
-            flagged: false,
-            last_updated: None,
-            tags: None,
+            user_id: "user-1".to_string(),
+            name: id.to_string(),
+            latitude: 51.48,
+            longitude: 0.0,
+            elevation: Some(46.0),
+            bortle: None,
+            source: "manual".to_string(),
+            is_default,
         }
     }
 
     #[test]
-    fn todo_create_and_get() {
+    fn location_default_is_unique() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = make_new_todo("todo-1", "user-1", "M42");
-        let created = create_todo(&mut conn, &new).unwrap();
-        assert_eq!(created.name, "M42");
-        assert!(!created.completed);
-
-        let fetched = get_todo_by_id(&mut conn, "todo-1").unwrap();
-        assert!(fetched.is_some());
+        create_location(&mut conn, &make_new_location("home", true)).unwrap();
+        create_location(&mut conn, &make_new_location("club", true)).unwrap();
+        let default = |conn: &mut SqliteConnection| {
+            get_default_location(conn, "user-1").unwrap().map(|l| l.id)
+        };
+        assert_eq!(default(&mut conn).as_deref(), Some("club"));
+
+        set_default_location(&mut conn, "home", true).unwrap();
+        assert_eq!(default(&mut conn).as_deref(), Some("home"));
+        let all = get_locations(&mut conn, "user-1").unwrap();
+        assert_eq!(all.iter().filter(|l| l.is_default).count(), 1);
+        assert_eq!(all[0].id, "home");
+
+        set_default_location(&mut conn, "home", false).unwrap();
+        assert!(default(&mut conn).is_none());
+        assert_eq!(delete_location(&mut conn, "club").unwrap(), 1);
+        assert!(get_location_by_id(&mut conn, "club").unwrap().is_none());
     }
 
     #[test]
-    fn todo_update() {
+    fn capture_site_is_searchable_and_outlives_its_location() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
+        create_location(&mut conn, &make_new_location("home", true)).unwrap();
+        for id in ["a", "b"] {
+            create_image(&mut conn, &make_new_image(id, "user-1")).unwrap();
+        }
 
-        let new = make_new_todo("todo-1", "user-1", "M42");
-        create_todo(&mut conn, &new).unwrap();
-
-        let update = UpdateAstronomyTodo {
-            completed: Some(true),
-            notes: Some("Great view tonight!".to_string()),
+        let tagged = set_image_capture_site(
+            &mut conn,
+            "user-1",
+            &["a".to_string()],
+            Some("home"),
+            Some(51.5),
+            Some(-0.1),
+        )
+        .unwrap();
+        assert_eq!(tagged, 1);
+        let at_home = AcquisitionQuery {
+            capture_site_id: Some("home".to_string()),
             ..Default::default()
         };
-        let updated = update_todo(&mut conn, "todo-1", &update).unwrap();
-        assert!(updated.completed);
-        assert_eq!(updated.notes, Some("Great view tonight!".to_string()));
+        let found = search_images_by_acquisition(&mut conn, "user-1", &at_home).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "a");
+
+        delete_location(&mut conn, "home").unwrap();
+        let image = get_image_by_id(&mut conn, "a").unwrap().unwrap();
+        assert_eq!(image.capture_site_id, None);
+        assert_eq!(image.capture_latitude, Some(51.5));
+        assert!(search_images_by_acquisition(&mut conn, "user-1", &at_home)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    fn todo_delete() {
+    fn saved_search_crud() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let new = make_new_todo("todo-del", "user-1", "M31");
-        create_todo(&mut conn, &new).unwrap();
+        let new = NewSavedSearch {
+            id: "s1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Narrowband".to_string(),
+            entity: "images".to_string(),
+            criteria: r#"{"band":"narrowband"}"#.to_string(),
+        };
+        create_saved_search(&mut conn, &new).unwrap();
 
-        let deleted = delete_todo(&mut conn, "todo-del").unwrap();
-        assert_eq!(deleted, 1);
+        let update = UpdateSavedSearch {
+            name: Some("Unprocessed narrowband".to_string()),
+            criteria: Some(r#"{"band":"narrowband","processed":false}"#.to_string()),
+            ..Default::default()
+        };
+        let updated = update_saved_search(&mut conn, "s1", &update).unwrap();
+        assert_eq!(updated.name, "Unprocessed narrowband");
+        assert_eq!(updated.entity, "images");
 
-        let fetched = get_todo_by_id(&mut conn, "todo-del").unwrap();
-        assert!(fetched.is_none());
+        assert_eq!(get_saved_searches(&mut conn, "user-1").unwrap().len(), 1);
+        assert_eq!(delete_saved_search(&mut conn, "s1").unwrap(), 1);
+        assert!(get_saved_search_by_id(&mut conn, "s1").unwrap().is_none());
     }
 
     #[test]
-    fn todo_list_by_user() {
+    fn job_metrics_keep_only_recent_jobs() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        for i in 0..3 {
-            let new = make_new_todo(&format!("todo-{}", i), "user-1", &format!("M{}", i + 1));
-            create_todo(&mut conn, &new).unwrap();
+        let metric = |job_id: &str, phase: &str| NewJobMetric {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: "user-1".to_string(),
+            job_id: job_id.to_string(),
+            phase: phase.to_string(),
+            count: 3,
+            total_ms: 1200,
+            max_ms: 500,
+        };
+        for (job_id, age) in [("scan-old", 2), ("scan-mid", 1)] {
+            save_job_metrics(&mut conn, "user-1", &[metric(job_id, "total")], 10).unwrap();
+            diesel::update(job_metrics::table.filter(job_metrics::job_id.eq(job_id)))
+                .set(job_metrics::created_at.eq(diesel::dsl::sql(&format!(
+                    "datetime('now', '-{} hours')",
+                    age
+                ))))
+                .execute(&mut conn)
+                .unwrap();
         }
+        let saved = save_job_metrics(
+            &mut conn,
+            "user-1",
+            &[metric("scan-new", "fits_parse"), metric("scan-new", "total")],
+            2,
+        )
+        .unwrap();
+        assert_eq!(saved, 2);
 
-        let todos = get_todos(&mut conn, "user-1").unwrap();
-        assert_eq!(todos.len(), 3);
+        assert_eq!(get_job_metrics(&mut conn, "user-1", "scan-new").unwrap().len(), 2);
+        assert_eq!(get_job_metrics(&mut conn, "user-1", "scan-mid").unwrap().len(), 1);
+        assert!(get_job_metrics(&mut conn, "user-1", "scan-old").unwrap().is_empty());
     }
 
-    // ========================================================================
-    // Query tests
-    // ========================================================================
+    #[test]
+    fn ui_preferences_are_saved_per_user() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+        assert!(get_ui_preferences(&mut conn, "user-1").unwrap().is_none());
+
+        let save = |conn: &mut SqliteConnection, json: &str| {
+            save_ui_preferences(
+                conn,
+                &NewUiPreferences {
+                    user_id: "user-1".to_string(),
+                    preferences: json.to_string(),
+                },
+            )
+            .unwrap()
+        };
+        save(&mut conn, r#"{"theme":"dark"}"#);
+        let saved = save(&mut conn, r#"{"theme":"red"}"#);
+        assert_eq!(saved.preferences, r#"{"theme":"red"}"#);
+        let stored = get_ui_preferences(&mut conn, "user-1").unwrap().unwrap();
+        assert_eq!(stored.preferences, saved.preferences);
+        let rows: i64 = ui_preferences::table.count().get_result(&mut conn).unwrap();
+        assert_eq!(rows, 1);
+    }
 
     #[test]
-    fn get_targets_with_counts_groups_by_summary() {
+    fn favorites_and_pins() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        // Create 2 images with summary "M42" and 1 with "M31"
-        for i in 0..2 {
-            let mut img = make_new_image(&format!("m42-{}", i), "user-1");
-            img.summary = Some("M42".to_string());
-            create_image(&mut conn, &img).unwrap();
+        let mut image = make_new_image("fav", "user-1");
+        image.favorite = true;
+        create_image(&mut conn, &image).unwrap();
+        create_image(&mut conn, &make_new_image("plain", "user-1")).unwrap();
+        let ids = |images: Vec<Image>| images.into_iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(get_favorite_images(&mut conn, "user-1").unwrap()),
+            vec!["fav"]
+        );
+
+        for (id, favorite, archived) in [
+            ("c1", true, false),
+            ("c2", true, true),
+            ("c3", false, false),
+        ] {
+            let mut collection = make_new_collection(id, id);
+            collection.favorite = favorite;
+            collection.archived = archived;
+            create_collection(&mut conn, &collection).unwrap();
         }
-        let mut img = make_new_image("m31-0", "user-1");
-        img.summary = Some("M31".to_string());
-        create_image(&mut conn, &img).unwrap();
+        let favorites = get_favorite_collections(&mut conn, "user-1").unwrap();
+        assert_eq!(
+            favorites.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            vec!["c1"]
+        );
+
+        for (id, flagged, completed) in [
+            ("t1", true, false),
+            ("t2", true, true),
+            ("t3", false, false),
+        ] {
+            let mut todo = make_new_todo(id, "user-1", id);
+            todo.flagged = flagged;
+            todo.completed = completed;
+            create_todo(&mut conn, &todo).unwrap();
+        }
+        let flagged = get_flagged_todos(&mut conn, "user-1").unwrap();
+        assert_eq!(
+            flagged.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(),
+            vec!["t1"]
+        );
+
+        let search = NewSavedSearch {
+            id: "s1".to_string(),
+            user_id: "user-1".to_string(),
+            name: "Narrowband".to_string(),
+            entity: "images".to_string(),
+            criteria: "{}".to_string(),
+        };
+        assert!(!create_saved_search(&mut conn, &search).unwrap().pinned);
+        assert!(get_pinned_saved_searches(&mut conn, "user-1")
+            .unwrap()
+            .is_empty());
+        let pin = UpdateSavedSearch {
+            pinned: Some(true),
+            ..Default::default()
+        };
+        update_saved_search(&mut conn, "s1", &pin).unwrap();
+        assert_eq!(
+            get_pinned_saved_searches(&mut conn, "user-1")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let pin_target_named = |conn: &mut SqliteConnection, id: &str, name: &str| {
+            let new_pin = NewPinnedTarget {
+                id: id.to_string(),
+                user_id: "user-1".to_string(),
+                name: name.to_string(),
+            };
+            pin_target(conn, &new_pin).unwrap()
+        };
+        assert!(pin_target_named(&mut conn, "p1", "M 31"));
+        assert!(!pin_target_named(&mut conn, "p2", "M 31"));
+        assert!(pin_target_named(&mut conn, "p3", "M 42"));
+        // The same target pinned on another device and synced in
+        diesel::insert_into(pinned_targets::table)
+            .values(&NewPinnedTarget {
+                id: "p4".to_string(),
+                user_id: "user-1".to_string(),
+                name: "M 31".to_string(),
+            })
+            .execute(&mut conn)
+            .unwrap();
+        let names = |conn: &mut SqliteConnection| {
+            get_pinned_targets(conn, "user-1")
+                .unwrap()
+                .into_iter()
+                .map(|p| p.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&mut conn), vec!["M 31", "M 42"]);
 
-        let targets = get_targets_with_counts(&mut conn, "user-1").unwrap();
-        assert_eq!(targets.len(), 2);
-        // Sorted by count descending, so M42 first
-        assert_eq!(targets[0].name, "M42");
-        assert_eq!(targets[0].image_count, 2);
-        assert_eq!(targets[1].name, "M31");
-        assert_eq!(targets[1].image_count, 1);
+        assert_eq!(unpin_target(&mut conn, "user-1", "M 31").unwrap(), 2);
+        assert_eq!(names(&mut conn), vec!["M 42"]);
     }
 
     #[test]
-    fn search_images_by_target_partial_match() {
+    fn note_crud_and_search() {
         let pool = setup_test_db();
         let mut conn = pool.get().unwrap();
         insert_test_user(&mut conn, "user-1");
 
-        let mut img = make_new_image("img-1", "user-1");
-        img.summary = Some("Orion Nebula M42".to_string());
-        create_image(&mut conn, &img).unwrap();
+        let new_note = |id: &str, entity_type: &str, entity_id: &str, body: &str| NewNote {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            entity_type: entity_type.to_string(),
+            entity_id: entity_id.to_string(),
+            body: body.to_string(),
+            attachments: None,
+        };
+        for (id, entity_type, entity_id, body) in [
+            ("n1", "image", "img-1", "Seeing was poor"),
+            ("n2", "image", "img-1", "Dew on the **corrector**"),
+            ("n3", "session", "2025-01-10", "Clouds after 2am"),
+            ("n4", "target", "M 42", "Try HDR next time"),
+        ] {
+            create_note(&mut conn, &new_note(id, entity_type, entity_id, body)).unwrap();
+        }
 
-        let mut img2 = make_new_image("img-2", "user-1");
-        img2.summary = Some("Andromeda M31".to_string());
-        create_image(&mut conn, &img2).unwrap();
+        assert_eq!(
+            get_notes_for_entity(&mut conn, "user-1", "image", "img-1")
+                .unwrap()
+                .len(),
+            2
+        );
+        let targets = get_notes_by_type(&mut conn, "user-1", "target").unwrap();
+        assert_eq!(targets.len(), 1);
+
+        let update = UpdateNote {
+            body: Some("Seeing was poor until midnight".to_string()),
+            ..Default::default()
+        };
+        let note = update_note(&mut conn, "n1", &update).unwrap();
+        assert_eq!(note.body, "Seeing was poor until midnight");
+        assert!(note.attachments.is_none());
+
+        let ids = |notes: Vec<Note>| {
+            let mut ids: Vec<_> = notes.into_iter().map(|n| n.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(search_notes(&mut conn, "user-1", "seeing", None).unwrap()),
+            vec!["n1"]
+        );
+        assert_eq!(
+            ids(search_notes(&mut conn, "user-1", "t", Some("image")).unwrap()),
+            vec!["n1", "n2"]
+        );
+
+        let mut removed = delete_notes_for_entity(&mut conn, "user-1", "image", "img-1").unwrap();
+        removed.sort();
+        assert_eq!(removed, vec!["n1", "n2"]);
+        assert!(get_notes_for_entity(&mut conn, "user-1", "image", "img-1")
+            .unwrap()
+            .is_empty());
+        assert_eq!(delete_note(&mut conn, "n3").unwrap(), 1);
+        assert!(get_note_by_id(&mut conn, "n3").unwrap().is_none());
+        assert!(get_note_by_id(&mut conn, "n4").unwrap().is_some());
+    }
 
-        let results = search_images_by_target(&mut conn, "user-1", "M42").unwrap();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].id, "img-1");
+    #[test]
+    fn session_digests_are_replaced_per_night() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
 
-        // Partial match
-        let results = search_images_by_target(&mut conn, "user-1", "Nebula").unwrap();
-        assert_eq!(results.len(), 1);
+        let digest = |id: &str, night: &str, verdict: &str| NewSessionDigest {
+            id: id.to_string(),
+            user_id: "user-1".to_string(),
+            night: night.to_string(),
+            verdict: verdict.to_string(),
+            summary: verdict.to_string(),
+            details: "{}".to_string(),
+        };
+        save_session_digest(&mut conn, &digest("d1", "2025-01-10", "go")).unwrap();
+        save_session_digest(&mut conn, &digest("d2", "2025-01-11", "marginal")).unwrap();
+        // Re-evaluating a night keeps its row
+        let saved = save_session_digest(&mut conn, &digest("d3", "2025-01-10", "no-go")).unwrap();
+        assert_eq!((saved.id.as_str(), saved.verdict.as_str()), ("d1", "no-go"));
+
+        let nights: Vec<_> = get_session_digests(&mut conn, "user-1", 10)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.night)
+            .collect();
+        assert_eq!(nights, vec!["2025-01-11", "2025-01-10"]);
+        assert!(get_session_digest(&mut conn, "user-1", "2025-01-12")
+            .unwrap()
+            .is_none());
+    }
 
-        // No match
-        let results = search_images_by_target(&mut conn, "user-1", "NGC 7000").unwrap();
-        assert!(results.is_empty());
+    #[test]
+    fn processed_source_ids_come_from_metadata() {
+        let pool = setup_test_db();
+        let mut conn = pool.get().unwrap();
+        insert_test_user(&mut conn, "user-1");
+
+        create_image(&mut conn, &make_new_image("raw", "user-1")).unwrap();
+        let mut processed = make_new_image("out", "user-1");
+        processed.metadata = Some(serde_json::json!({ "source_image_id": "raw" }).to_string());
+        create_image(&mut conn, &processed).unwrap();
+
+        let ids = get_processed_source_ids(&mut conn, "user-1").unwrap();
+        assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec!["raw"]);
     }
 }
@@ -35,6 +35,8 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         tags -> Nullable<Text>,
+        exposure_plan -> Nullable<Text>,
+        framing -> Nullable<Text>,
     }
 }
 
@@ -44,6 +46,7 @@ diesel::table! {
         collection_id -> Text,
         image_id -> Text,
         created_at -> Timestamp,
+        sort_order -> Nullable<Integer>,
     }
 }
 
@@ -61,6 +64,78 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         archived -> Bool,
+        cover_image_id -> Nullable<Text>,
+        image_sort -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    equipment (id) {
+        id -> Text,
+        user_id -> Text,
+        kind -> Text,
+        name -> Text,
+        manufacturer -> Nullable<Text>,
+        model -> Nullable<Text>,
+        match_patterns -> Nullable<Text>,
+        focal_length_mm -> Nullable<Double>,
+        aperture_mm -> Nullable<Double>,
+        pixel_size_um -> Nullable<Double>,
+        sensor_width_px -> Nullable<Integer>,
+        sensor_height_px -> Nullable<Integer>,
+        reduction_factor -> Nullable<Double>,
+        notes -> Nullable<Text>,
+        metadata -> Nullable<Text>,
+        retired -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    equipment_profiles (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        telescope_id -> Nullable<Text>,
+        camera_id -> Nullable<Text>,
+        reducer_id -> Nullable<Text>,
+        scale_lower -> Nullable<Double>,
+        scale_upper -> Nullable<Double>,
+        processing_params -> Nullable<Text>,
+        notes -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    filters (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        band -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    image_equipment (id) {
+        id -> Text,
+        image_id -> Text,
+        equipment_id -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    image_filters (image_id) {
+        image_id -> Text,
+        filter_id -> Text,
+        raw_value -> Nullable<Text>,
+        frame_count -> Integer,
+        exposure_seconds -> Double,
+        created_at -> Timestamp,
     }
 }
 
@@ -85,6 +160,74 @@ diesel::table! {
         thumbnail -> Nullable<Text>,
         fits_url -> Nullable<Text>,
         blob_id -> Nullable<Text>,
+        exposure -> Nullable<Double>,
+        gain -> Nullable<Integer>,
+        filter -> Nullable<Text>,
+        telescope -> Nullable<Text>,
+        date_obs -> Nullable<Text>,
+        wcs -> Nullable<Text>,
+        parent_image_id -> Nullable<Text>,
+        triage -> Nullable<Text>,
+        rating -> Nullable<Integer>,
+        capture_site_id -> Nullable<Text>,
+        capture_latitude -> Nullable<Double>,
+        capture_longitude -> Nullable<Double>,
+    }
+}
+
+diesel::table! {
+    job_metrics (id) {
+        id -> Text,
+        user_id -> Text,
+        job_id -> Text,
+        phase -> Text,
+        count -> Integer,
+        total_ms -> BigInt,
+        max_ms -> BigInt,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    locations (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        latitude -> Double,
+        longitude -> Double,
+        elevation -> Nullable<Double>,
+        bortle -> Nullable<Integer>,
+        source -> Text,
+        is_default -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    maintenance_entries (id) {
+        id -> Text,
+        user_id -> Text,
+        equipment_id -> Text,
+        task -> Text,
+        performed_on -> Text,
+        interval_days -> Nullable<Integer>,
+        notes -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    notes (id) {
+        id -> Text,
+        user_id -> Text,
+        entity_type -> Text,
+        entity_id -> Text,
+        body -> Text,
+        attachments -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -101,6 +244,57 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         equipment_id -> Nullable<Text>,
+        archived -> Bool,
+    }
+}
+
+diesel::table! {
+    packing_items (id) {
+        id -> Text,
+        user_id -> Text,
+        schedule_id -> Text,
+        name -> Text,
+        category -> Text,
+        equipment_id -> Nullable<Text>,
+        packed -> Bool,
+        sort_order -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    pinned_targets (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    processing_presets (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        target_type -> Nullable<Text>,
+        params -> Text,
+        is_default -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    saved_searches (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        entity -> Text,
+        criteria -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        pinned -> Bool,
     }
 }
 
@@ -112,6 +306,22 @@ diesel::table! {
         fs_modified_at -> BigInt,
         last_scanned_at -> Text,
         image_count -> Integer,
+        last_imported -> Integer,
+        last_failed -> Integer,
+        listing_hash -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    session_digests (id) {
+        id -> Text,
+        user_id -> Text,
+        night -> Text,
+        verdict -> Text,
+        summary -> Text,
+        details -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -124,6 +334,42 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sync_imports (bundle_id) {
+        bundle_id -> Text,
+        device_id -> Text,
+        change_count -> Integer,
+        imported_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sync_meta (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    sync_state (table_name, row_id) {
+        table_name -> Text,
+        row_id -> Text,
+        updated_at -> Text,
+        deleted -> Bool,
+        origin -> Nullable<Text>,
+        version -> BigInt,
+    }
+}
+
+diesel::table! {
+    ui_preferences (user_id) {
+        user_id -> Text,
+        preferences -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Text,
@@ -146,18 +392,55 @@ diesel::joinable!(astronomy_todos -> users (user_id));
 diesel::joinable!(collection_images -> collections (collection_id));
 diesel::joinable!(collection_images -> images (image_id));
 diesel::joinable!(collections -> users (user_id));
+diesel::joinable!(equipment -> users (user_id));
+diesel::joinable!(equipment_profiles -> users (user_id));
+diesel::joinable!(filters -> users (user_id));
+diesel::joinable!(image_equipment -> equipment (equipment_id));
+diesel::joinable!(image_equipment -> images (image_id));
+diesel::joinable!(image_filters -> filters (filter_id));
+diesel::joinable!(image_filters -> images (image_id));
 diesel::joinable!(images -> collections (collection_id));
 diesel::joinable!(images -> users (user_id));
+diesel::joinable!(job_metrics -> users (user_id));
+diesel::joinable!(locations -> users (user_id));
+diesel::joinable!(maintenance_entries -> equipment (equipment_id));
+diesel::joinable!(maintenance_entries -> users (user_id));
+diesel::joinable!(notes -> users (user_id));
 diesel::joinable!(observation_schedules -> users (user_id));
+diesel::joinable!(packing_items -> observation_schedules (schedule_id));
+diesel::joinable!(packing_items -> users (user_id));
+diesel::joinable!(pinned_targets -> users (user_id));
+diesel::joinable!(processing_presets -> users (user_id));
+diesel::joinable!(saved_searches -> users (user_id));
+diesel::joinable!(session_digests -> users (user_id));
+diesel::joinable!(ui_preferences -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     astro_objects,
     astronomy_todos,
     collection_images,
     collections,
+    equipment,
+    equipment_profiles,
+    filters,
+    image_equipment,
+    image_filters,
     images,
+    job_metrics,
+    locations,
+    maintenance_entries,
+    notes,
     observation_schedules,
+    packing_items,
+    pinned_targets,
+    processing_presets,
+    saved_searches,
     scanned_directories,
+    session_digests,
     simbad_cache,
+    sync_imports,
+    sync_meta,
+    sync_state,
+    ui_preferences,
     users,
 );
@@ -0,0 +1,105 @@
+//! Activity feed for external tools
+//!
+//! Import progress, newly added images, finished jobs, the nightly go/no-go
+//! digest, maintenance reminders and UI preference changes are published
+//! here alongside the window events the UI listens to. The local API server
+//! streams them to websocket clients at `/api/events` when event streaming
+//! is turned on, so overlays and bots can react to what Astra is doing.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber; a client that falls further behind
+/// skips ahead to the newest ones
+const CAPACITY: usize = 256;
+
+/// Progress of a bulk scan (`ScanProgress`)
+pub const SCAN_PROGRESS: &str = "scan-progress";
+/// A bulk scan finished (`BulkScanResult`)
+pub const SCAN_FINISHED: &str = "scan-finished";
+/// Progress of an auto-import cycle
+pub const AUTO_IMPORT_PROGRESS: &str = "auto-import-progress";
+/// An auto-import cycle finished (`AutoImportStatus`)
+pub const AUTO_IMPORT_FINISHED: &str = "auto-import-finished";
+/// An image was added to the library (`ImageAdded`)
+pub const IMAGE_ADDED: &str = "image-added";
+/// An astrometry.net job finished (`NovaJobFinished`)
+pub const NOVA_JOB_FINISHED: &str = "nova-job-finished";
+/// Tonight's go/no-go digest is ready (`NightDigest`)
+pub const NIGHT_DIGEST: &str = "night-digest";
+/// Equipment maintenance is due (`Vec<DueMaintenance>`)
+pub const MAINTENANCE_DUE: &str = "maintenance-due";
+/// The user's UI preferences were saved (the preferences object)
+pub const UI_PREFERENCES_CHANGED: &str = "ui-preferences-changed";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+    /// RFC 3339, UTC
+    pub timestamp: String,
+}
+
+/// Payload of [`IMAGE_ADDED`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageAdded {
+    pub id: String,
+    pub filename: String,
+    pub object_name: Option<String>,
+    /// Whether it is a stacked image rather than a raw subframe
+    pub stacked: bool,
+    pub collection_id: Option<String>,
+    /// Held back for triage review (e.g. a failed frame) rather than listed
+    pub held_for_triage: bool,
+    /// How it arrived: "scan" or "auto-import"
+    pub source: &'static str,
+}
+
+fn sender() -> &'static broadcast::Sender<ActivityEvent> {
+    static EVENTS: OnceLock<broadcast::Sender<ActivityEvent>> = OnceLock::new();
+    EVENTS.get_or_init(|| broadcast::channel(CAPACITY).0)
+}
+
+/// Publish an event to every subscriber. Free when nobody is listening.
+pub fn publish(event: &str, payload: &impl Serialize) {
+    let sender = sender();
+    if sender.receiver_count() == 0 {
+        return;
+    }
+    match serde_json::to_value(payload) {
+        Ok(payload) => {
+            let _ = sender.send(ActivityEvent {
+                event: event.to_string(),
+                payload,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+        Err(e) => log::warn!("Failed to serialize {} event: {}", event, e),
+    }
+}
+
+/// Receive events published from now on
+pub fn subscribe() -> broadcast::Receiver<ActivityEvent> {
+    sender().subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_receive_events_published_after_subscribing() {
+        let mut events = subscribe();
+        publish("test-event", &serde_json::json!({ "imagesImported": 3 }));
+
+        // Other tests may publish to the same feed concurrently
+        let event = std::iter::from_fn(|| events.try_recv().ok())
+            .find(|e| e.event == "test-event")
+            .unwrap();
+        assert_eq!(event.payload["imagesImported"], 3);
+    }
+}
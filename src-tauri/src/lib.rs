@@ -5,11 +5,20 @@
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 
+mod bitmap_font;
+#[cfg(feature = "cli")]
+pub mod cli;
 mod commands;
 mod db;
+mod events;
 mod fits_variant;
+mod logging;
+mod network;
+mod preview_cache;
 mod python;
 mod share;
+mod skymap;
+mod solvers;
 mod state;
 pub mod stretch;
 
@@ -67,6 +76,7 @@ pub fn run_standalone_migration(
     );
     let hoardfs = std::sync::Arc::new(std::sync::Mutex::new(hfs));
 
+    let db_pool = db::SharedDbPool::new(db_pool);
     commands::hoardfs::migrate_library_core(&db_pool, &hoardfs, &handle, "local-user", on_progress)
 }
 
@@ -89,9 +99,8 @@ pub struct AppInfo {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logging
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .init();
+    // Initialize logging; log files start once the app data dir is known
+    logging::init("info");
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -99,48 +108,9 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_geolocation::init())
         .setup(|app| {
-            // Initialize database
-            let db_path = db::get_database_path(app.handle());
-            let db_pool = db::init_database(&db_path)
-                .expect("Failed to initialize database");
-
-            // Auto-backup on startup (keep last 5, one per launch)
-            {
-                let handle = app.handle().clone();
-                let backup_dir = handle
-                    .path()
-                    .app_data_dir()
-                    .map(|d| d.join("backups"));
-
-                if let Ok(backup_dir) = backup_dir {
-                    if std::fs::create_dir_all(&backup_dir).is_ok() && db_path.exists() {
-                        let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
-                        let dest = backup_dir.join(format!("astra_auto_{}.db", ts));
-                        if let Err(e) = std::fs::copy(&db_path, &dest) {
-                            log::warn!("Auto-backup failed: {}", e);
-                        } else {
-                            log::info!("Auto-backup created: {}", dest.display());
-                            // Prune: keep only the 5 most recent auto-backups
-                            if let Ok(entries) = std::fs::read_dir(&backup_dir) {
-                                let mut auto_backups: Vec<std::path::PathBuf> = entries
-                                    .flatten()
-                                    .map(|e| e.path())
-                                    .filter(|p| {
-                                        p.file_name()
-                                            .and_then(|n| n.to_str())
-                                            .map(|n| n.starts_with("astra_auto_") && n.ends_with(".db"))
-                                            .unwrap_or(false)
-                                    })
-                                    .collect();
-                                auto_backups.sort();
-                                auto_backups.reverse();
-                                for old in auto_backups.into_iter().skip(5) {
-                                    let _ = std::fs::remove_file(&old);
-                                }
-                            }
-                        }
-                    }
-                }
+            // Keep the log in app data from here on
+            if let Ok(dir) = app.path().app_data_dir() {
+                logging::set_log_dir(dir.join("logs"));
             }
 
             // Initialize HoardFS content-addressed storage
@@ -176,37 +146,27 @@ pub fn run() {
                 }
             };
 
-            // Create app state
-            let app_state = AppState::new(db_pool, hoardfs);
+            // Create app state. The database and Python are initialized in
+            // the background so the window opens right away.
+            let network = commands::network::load_network(app.handle());
+            let preview_cache = commands::preview_cache::load_preview_cache(app.handle());
+            let app_state =
+                AppState::new(db::SharedDbPool::empty(), hoardfs, network, preview_cache);
             app.manage(app_state);
 
             // FUSE mount state (only with `fuse` feature)
             #[cfg(feature = "fuse")]
             app.manage(commands::hoardfs::FuseMountState::new());
 
-            // Initialize Python with path to astra_astro module
-            // In development, the module is in ../python relative to src-tauri
-            // In production, it should be bundled with the app
-            let python_path = if cfg!(debug_assertions) {
-                // Development mode: use the python directory relative to the project
-                Some(std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../python"))
-            } else {
-                // Production mode: use the bundled resources
-                app.path()
-                    .resource_dir()
-                    .ok()
-                    .map(|p| p.join("python"))
-            };
-
-            if let Err(e) = python::init_python(python_path) {
-                log::warn!("Failed to initialize Python: {}", e);
-                // Don't fail - Python features will be unavailable
-            }
+            commands::startup::start(app.handle());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_app_info,
+            // Startup commands
+            commands::get_startup_status,
+            commands::retry_startup,
             // Todo commands
             commands::get_todos,
             commands::get_todo,
@@ -214,12 +174,53 @@ pub fn run() {
             commands::update_todo,
             commands::delete_todo,
             commands::sync_todos,
+            commands::calculate_exposure_plan,
+            commands::plan_framing,
             // Collection commands
             commands::get_collections,
+            commands::get_archived_collections,
             commands::get_collection,
             commands::create_collection,
             commands::update_collection,
             commands::delete_collection,
+            commands::archive_collection,
+            commands::repair_session_collections,
+            commands::backfill_dates,
+            commands::merge_collections,
+            commands::duplicate_collection,
+            commands::split_collection,
+            commands::set_collection_cover,
+            commands::set_collection_image_sort,
+            commands::reorder_collection_images,
+            commands::get_collection_summary,
+            // Equipment commands
+            commands::get_equipment,
+            commands::get_equipment_item,
+            commands::create_equipment,
+            commands::update_equipment,
+            commands::delete_equipment,
+            commands::link_image_equipment,
+            commands::unlink_image_equipment,
+            commands::get_image_equipment,
+            commands::get_images_by_rig,
+            commands::match_equipment_to_images,
+            commands::get_equipment_profiles,
+            commands::create_equipment_profile,
+            commands::update_equipment_profile,
+            commands::delete_equipment_profile,
+            commands::get_image_equipment_profile,
+            commands::get_equipment_usage,
+            // Maintenance commands
+            commands::get_maintenance_log,
+            commands::add_maintenance_entry,
+            commands::update_maintenance_entry,
+            commands::delete_maintenance_entry,
+            commands::get_due_maintenance,
+            // Filter usage commands
+            commands::get_filters,
+            commands::get_filter_summary,
+            commands::get_filter_exposure_by_target,
+            commands::rebuild_filter_usage,
             // Image commands
             commands::get_images,
             commands::get_collection_images,
@@ -227,6 +228,7 @@ pub fn run() {
             commands::create_image,
             commands::update_image,
             commands::delete_image,
+            commands::set_image_rating,
             // Image-Collection relationship commands
             commands::add_image_to_collection,
             commands::remove_image_from_collection,
@@ -235,6 +237,13 @@ pub fn run() {
             // Image data serving commands
             commands::get_image_data,
             commands::get_image_thumbnail,
+            // Image comparison commands
+            commands::compare_images,
+            // Open-with commands
+            commands::reveal_image_in_file_manager,
+            commands::open_image_in_external_editor,
+            commands::get_external_editors,
+            commands::set_external_editors,
             // FITS URL population commands
             commands::populate_fits_urls,
             commands::ensure_fits_url,
@@ -242,17 +251,58 @@ pub fn run() {
             commands::get_schedules,
             commands::get_active_schedule,
             commands::get_active_schedules,
+            commands::get_active_schedules_for_night,
+            commands::get_archived_schedules,
             commands::get_schedule,
             commands::create_schedule,
             commands::update_schedule,
             commands::delete_schedule,
+            commands::archive_schedule,
+            commands::archive_past_schedules,
             commands::add_schedule_item,
             commands::remove_schedule_item,
+            commands::get_schedule_warnings,
+            commands::shift_schedule,
+            // Packing list commands
+            commands::get_packing_list,
+            commands::regenerate_packing_list,
+            commands::add_packing_item,
+            commands::set_packing_item_packed,
+            commands::delete_packing_item,
+            // Night digest commands
+            commands::get_night_digest,
+            commands::get_night_digests,
+            commands::evaluate_night_digest,
+            commands::get_night_digest_config,
+            commands::set_night_digest_config,
             // Astronomy commands
             commands::lookup_astronomy_object,
+            commands::lookup_objects_batch,
             commands::calculate_object_altitude,
             commands::calculate_altitude_data,
             commands::get_sun_times,
+            commands::get_night_chart,
+            commands::compare_altitudes,
+            commands::get_meridian_transit,
+            commands::get_meridian_flip,
+            commands::get_polar_alignment,
+            // Location commands
+            commands::get_locations,
+            commands::capture_current_location,
+            commands::set_default_location,
+            commands::delete_location,
+            commands::find_dark_sites,
+            // Capture site commands
+            commands::set_image_capture_site,
+            commands::backfill_capture_sites,
+            // Network commands
+            commands::get_network_settings,
+            commands::set_network_settings,
+            commands::set_offline_mode,
+            // Preview cache commands
+            commands::get_preview_cache_status,
+            commands::set_preview_cache_settings,
+            commands::clear_preview_cache,
             // Backup commands
             commands::create_backup,
             commands::list_backups,
@@ -260,41 +310,181 @@ pub fn run() {
             commands::delete_backup,
             commands::export_database,
             commands::import_database,
+            commands::verify_backup,
+            commands::get_merge_source_collections,
+            commands::merge_import_database,
             commands::get_image_path_prefixes,
             commands::remap_image_paths,
+            commands::check_image_paths,
+            commands::get_path_remap_presets,
+            commands::save_path_remap_preset,
+            commands::delete_path_remap_preset,
+            commands::apply_path_remap_preset,
+            // Sync commands
+            commands::get_sync_status,
+            commands::export_sync_changes,
+            commands::import_sync_changes,
+            commands::sync_folder,
+            // Local HTTP API commands
+            commands::get_api_server_status,
+            commands::configure_api_server,
+            commands::regenerate_api_token,
+            // Python environment commands
+            commands::get_python_status,
+            commands::get_python_settings,
+            commands::set_python_venv_path,
+            commands::set_python_backend,
+            commands::reinitialize_python,
+            // Export commands
+            commands::export_astrobin_csv,
+            commands::export_data,
+            commands::get_export_columns,
+            commands::export_gallery,
+            commands::export_collection_files,
+            commands::cancel_collection_files_export,
+            commands::export_presentation_image,
+            commands::create_timelapse,
+            commands::cancel_timelapse,
+            commands::export_image_bundle,
+            commands::import_image_bundle,
+            commands::generate_session_report,
+            commands::export_siril_script,
+            // Stacking set commands
+            commands::build_stacking_set,
+            commands::save_stacking_set,
+            commands::get_stacking_sets,
+            commands::delete_stacking_set,
             // Bulk scan commands
             commands::bulk_scan_directory,
             commands::preview_bulk_scan,
             commands::cancel_scan,
+            commands::get_running_scan_job,
+            commands::pause_scan,
+            commands::resume_scan,
+            commands::get_scanned_directories,
+            commands::forget_scanned_directory,
+            commands::get_frame_rules,
+            commands::set_frame_rules,
+            commands::get_collection_naming,
+            commands::set_collection_naming,
+            commands::preview_collection_name,
+            commands::get_import_memory_settings,
+            commands::set_import_memory_settings,
             // Raw file collection commands
             commands::collect_raw_files,
             commands::cancel_collect,
+            // Subframe commands
+            commands::compute_acquisition_breakdown,
+            commands::get_image_subframes,
+            commands::link_image_subframes,
+            commands::get_session_quality_timeline,
+            commands::cancel_session_quality,
+            // Sky brightness commands
+            commands::estimate_sky_brightness,
+            commands::get_sky_brightness_trend,
+            // Import triage commands
+            commands::get_triage_rules,
+            commands::set_triage_rules,
+            commands::get_triage_images,
+            commands::review_triage_images,
+            // Auto-tagging commands
+            commands::get_tag_rules,
+            commands::set_tag_rules,
+            commands::apply_tag_rules,
+            // Sidecar commands
+            commands::get_sidecar_settings,
+            commands::set_sidecar_settings,
+            commands::write_image_sidecars,
+            // Solar system commands
+            commands::identify_solar_system_images,
+            // Attention queue commands
+            commands::get_attention_queue,
+            // Library health commands
+            commands::get_library_health,
+            // Log commands
+            commands::get_recent_logs,
+            commands::export_logs,
+            // Job metrics commands
+            commands::get_job_metrics,
             // Plate solving commands
             commands::plate_solve_image,
+            commands::plate_solve_collection,
+            commands::pixel_to_sky,
+            commands::sky_to_pixel,
             commands::query_sky_region,
             commands::detect_plate_solvers,
             commands::get_solve_hints,
+            commands::submit_nova_solve,
+            commands::get_nova_jobs,
+            commands::identify_target,
+            commands::get_annotated_preview,
             // Skymap commands
             commands::generate_skymap,
+            commands::get_skymap_data,
             commands::generate_wide_skymap,
+            commands::generate_coverage_map,
+            commands::get_sky_cutout,
             // Image processing commands
             commands::process_fits_image,
+            commands::reprocess_image,
+            commands::preview_processing,
             commands::classify_target_type,
             commands::get_processing_defaults,
+            commands::get_processing_presets,
+            commands::create_processing_preset,
+            commands::update_processing_preset,
+            commands::delete_processing_preset,
+            commands::set_default_processing_preset,
+            commands::get_processing_settings,
+            commands::set_processing_output_root,
+            commands::set_processing_output_formats,
             commands::regenerate_preview,
             commands::bulk_regenerate_previews,
             commands::get_unique_tags,
             commands::get_unique_cameras,
+            commands::search_images_by_acquisition,
+            commands::get_acquisition_options,
             commands::check_source_health,
             commands::migrate_previews_to_local,
             commands::scan_unimported_files,
             commands::cancel_unimported_scan,
             commands::get_image_stats,
             commands::download_tetra3_db,
+            // Saved search commands
+            commands::get_saved_searches,
+            commands::create_saved_search,
+            commands::update_saved_search,
+            commands::delete_saved_search,
+            commands::execute_saved_search,
+            commands::execute_search,
+            // Favorites commands
+            commands::get_favorites,
+            commands::pin_target,
+            commands::unpin_target,
+            commands::pin_saved_search,
+            commands::unpin_saved_search,
+            // UI preference commands
+            commands::get_ui_preferences,
+            commands::set_ui_preferences,
+            // Note commands
+            commands::get_notes,
+            commands::get_note,
+            commands::create_note,
+            commands::update_note,
+            commands::delete_note,
+            commands::add_note_attachment,
+            commands::remove_note_attachment,
+            commands::get_note_attachment_path,
+            commands::search_notes,
             // Target browser commands
             commands::get_targets,
             commands::search_images_by_target,
             commands::get_images_by_target,
+            commands::normalize_object_name,
+            commands::merge_targets,
+            commands::get_target_profile,
+            // Quick search commands
+            commands::quick_search,
             // Share commands
             commands::configure_share_upload,
             commands::get_share_config,
@@ -0,0 +1,376 @@
+//! Application log capture
+//!
+//! Log records go to the console as before (env_logger, filtered by
+//! `RUST_LOG`) and to rotating files under `logs/` in the app data dir, so
+//! what happened during a failed import is still there after the console is
+//! gone. Records logged inside a job (a bulk scan or an auto-import cycle)
+//! carry its id, so one job's lines can be picked out for a support request.
+//!
+//! Lines look like
+//! `2025-01-12T21:04:33.120+00:00 WARN  [astra_lib::commands::scan] (job scan-1a2b3c4d) message`;
+//! the job part is left out outside a job.
+
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Local};
+use log::{Level, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+/// Current log file; rotated ones get `.1` (newest) to `.N` appended
+pub const LOG_FILE: &str = "astra.log";
+/// A log file is rotated once it grows past this
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated files kept besides the current one
+const ROTATED_FILES: usize = 4;
+/// Lines kept in memory until the log directory is known
+const MAX_PENDING_LINES: usize = 1000;
+
+thread_local! {
+    /// Job of blocking code running on this thread
+    static THREAD_JOB: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+tokio::task_local! {
+    /// Job of the current async task
+    static TASK_JOB: String;
+}
+
+/// A parsed log line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// RFC 3339, local time
+    pub timestamp: String,
+    /// "error", "warn", "info", "debug" or "trace"
+    pub level: String,
+    /// Module that logged it
+    pub target: String,
+    pub job_id: Option<String>,
+    pub message: String,
+}
+
+/// Where file output goes: held in memory until the app data dir is known
+enum Sink {
+    Pending(Vec<String>),
+    File(RollingFile),
+}
+
+struct RollingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RollingFile {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let size = file.metadata()?.len();
+        Ok(RollingFile {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > MAX_FILE_BYTES {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `astra.log.N` up by one (dropping the oldest) and start afresh
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |n: usize| self.dir.join(format!("{}.{}", LOG_FILE, n));
+        let _ = fs::remove_file(rotated(ROTATED_FILES));
+        for n in (1..ROTATED_FILES).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        fs::rename(self.dir.join(LOG_FILE), rotated(1))?;
+        *self = RollingFile::open(&self.dir)?;
+        Ok(())
+    }
+}
+
+struct AppLogger {
+    console: env_logger::Logger,
+    sink: Mutex<Sink>,
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.console.matches(record) {
+            return;
+        }
+        self.console.log(record);
+
+        let line = format_line(
+            &Local::now(),
+            record.level(),
+            record.target(),
+            current_job().as_deref(),
+            &record.args().to_string(),
+        );
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        match &mut *sink {
+            Sink::Pending(lines) => {
+                if lines.len() < MAX_PENDING_LINES {
+                    lines.push(line);
+                }
+            }
+            Sink::File(file) => {
+                // Logging about a logging failure would recurse
+                if let Err(e) = file.write_line(&line) {
+                    eprintln!("Failed to write log file: {}", e);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        if let Sink::File(file) = &mut *self.sink.lock().unwrap_or_else(|e| e.into_inner()) {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<&'static AppLogger> = OnceLock::new();
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Install the logger. Console output is filtered by `RUST_LOG`, defaulting
+/// to `default_filter`; file output follows once `set_log_dir` is called.
+pub fn init(default_filter: &str) {
+    let console =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+            .build();
+    let max_level = console.filter();
+    let logger: &'static AppLogger = Box::leak(Box::new(AppLogger {
+        console,
+        sink: Mutex::new(Sink::Pending(Vec::new())),
+    }));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+        let _ = LOGGER.set(logger);
+    }
+}
+
+/// Start writing log files to `dir`, including what was logged before
+pub fn set_log_dir(dir: PathBuf) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let mut file = match RollingFile::open(&dir) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open log file in {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    let mut sink = logger.sink.lock().unwrap_or_else(|e| e.into_inner());
+    if let Sink::Pending(lines) = &*sink {
+        for line in lines {
+            let _ = file.write_line(line);
+        }
+    }
+    *sink = Sink::File(file);
+    let _ = LOG_DIR.set(dir);
+}
+
+/// Directory log files are written to, once known
+pub fn log_dir() -> Option<&'static Path> {
+    LOG_DIR.get().map(PathBuf::as_path)
+}
+
+/// Log files in `dir`, newest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    std::iter::once(dir.join(LOG_FILE))
+        .chain((1..=ROTATED_FILES).map(|n| dir.join(format!("{}.{}", LOG_FILE, n))))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// A fresh job id, e.g. "scan-1a2b3c4d"
+pub fn new_job_id(kind: &str) -> String {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", kind, &id[..8])
+}
+
+/// Job the current task or thread is running in
+pub fn current_job() -> Option<String> {
+    TASK_JOB
+        .try_with(String::clone)
+        .ok()
+        .or_else(|| THREAD_JOB.with(|job| job.borrow().clone()))
+}
+
+/// Run a future as part of a job. Tasks it spawns don't inherit the job;
+/// pass `current_job()` along to them.
+pub async fn in_job<F: Future>(job_id: Option<String>, future: F) -> F::Output {
+    match job_id {
+        Some(job_id) => TASK_JOB.scope(job_id, future).await,
+        None => future.await,
+    }
+}
+
+/// Restores the thread's previous job when dropped
+pub struct JobGuard {
+    previous: Option<String>,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD_JOB.with(|job| *job.borrow_mut() = previous);
+    }
+}
+
+/// Mark blocking code on this thread as part of a job until the guard drops
+pub fn enter_job(job_id: Option<String>) -> JobGuard {
+    JobGuard {
+        previous: THREAD_JOB.with(|job| job.replace(job_id)),
+    }
+}
+
+fn format_line(
+    time: &DateTime<Local>,
+    level: Level,
+    target: &str,
+    job_id: Option<&str>,
+    message: &str,
+) -> String {
+    let job = job_id
+        .map(|id| format!(" (job {})", id))
+        .unwrap_or_default();
+    format!(
+        "{} {:<5} [{}]{} {}\n",
+        time.format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+        level,
+        target,
+        job,
+        message
+    )
+}
+
+/// Parse the start of a log entry; other lines continue the previous one
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let (level, rest) = rest.trim_start().split_once(' ')?;
+    let level = Level::from_str(level).ok()?;
+    let rest = rest.trim_start().strip_prefix('[')?;
+    let (target, rest) = rest.split_once(']')?;
+    let (job_id, message) = match rest.strip_prefix(" (job ") {
+        Some(job) => {
+            let (id, message) = job.split_once(')')?;
+            (Some(id.to_string()), message)
+        }
+        None => (None, rest),
+    };
+    Some(LogEntry {
+        timestamp: timestamp.to_string(),
+        level: level.as_str().to_lowercase(),
+        target: target.to_string(),
+        job_id,
+        message: message.strip_prefix(' ').unwrap_or(message).to_string(),
+    })
+}
+
+/// Entries in a log file's text, oldest first
+pub fn parse_entries(text: &str) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in text.lines() {
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn lines_round_trip_with_and_without_a_job() {
+        let time = Local.with_ymd_and_hms(2025, 1, 12, 21, 4, 33).unwrap();
+        let text = format_line(
+            &time,
+            Level::Warn,
+            "astra_lib::commands::scan",
+            Some("scan-1a2b3c4d"),
+            "Failed to parse a.fit:\nbad header",
+        ) + &format_line(&time, Level::Info, "astra_lib", None, "Started");
+
+        let entries = parse_entries(&text);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, "warn");
+        assert_eq!(entries[0].target, "astra_lib::commands::scan");
+        assert_eq!(entries[0].job_id.as_deref(), Some("scan-1a2b3c4d"));
+        assert_eq!(entries[0].message, "Failed to parse a.fit:\nbad header");
+        assert_eq!(entries[1].job_id, None);
+        assert_eq!(entries[1].message, "Started");
+        assert_eq!(
+            entries[1].timestamp,
+            time.format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string()
+        );
+    }
+
+    #[test]
+    fn files_rotate_past_the_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = RollingFile::open(dir.path()).unwrap();
+        let line = format!("{}\n", "x".repeat(1024 * 1024 - 1));
+        for _ in 0..(5 * (ROTATED_FILES + 2)) {
+            file.write_line(&line).unwrap();
+        }
+        let files = log_files(dir.path());
+        assert_eq!(files.len(), ROTATED_FILES + 1);
+        assert_eq!(files[0], dir.path().join(LOG_FILE));
+        for path in &files {
+            assert!(fs::metadata(path).unwrap().len() <= MAX_FILE_BYTES);
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_follow_tasks_and_threads() {
+        assert_eq!(current_job(), None);
+        let job = in_job(Some("scan-1".to_string()), async { current_job() }).await;
+        assert_eq!(job.as_deref(), Some("scan-1"));
+
+        {
+            let _outer = enter_job(Some("auto-import-1".to_string()));
+            {
+                let _inner = enter_job(Some("auto-import-2".to_string()));
+                assert_eq!(current_job().as_deref(), Some("auto-import-2"));
+            }
+            assert_eq!(current_job().as_deref(), Some("auto-import-1"));
+        }
+        assert_eq!(current_job(), None);
+        assert!(new_job_id("scan").starts_with("scan-"));
+        assert_eq!(new_job_id("scan").len(), "scan-".len() + 8);
+    }
+}
@@ -0,0 +1,447 @@
+//! Throttled access to external services
+//!
+//! SIMBAD, astrometry.net, elevation and routing lookups, weather forecasts,
+//! survey cutouts and downloads all go through one [`Network`]. It spaces out
+//! requests to each provider, retries transient failures with exponential
+//! backoff and refuses requests while offline mode is on. JSON responses are
+//! kept on disk so a lookup can still be answered from the last good response
+//! when offline or a service is down.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Delay before the first retry; doubled for each one after it
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait between retries
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// An external service requests are made to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Provider {
+    Simbad,
+    AstrometryNet,
+    Elevation,
+    Routing,
+    Hips,
+    Weather,
+    Downloads,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 7] = [
+        Provider::Simbad,
+        Provider::AstrometryNet,
+        Provider::Elevation,
+        Provider::Routing,
+        Provider::Hips,
+        Provider::Weather,
+        Provider::Downloads,
+    ];
+
+    /// Name used in errors and logs
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Simbad => "SIMBAD",
+            Provider::AstrometryNet => "astrometry.net",
+            Provider::Elevation => "Elevation lookup",
+            Provider::Routing => "Routing",
+            Provider::Hips => "hips2fits",
+            Provider::Weather => "Weather forecast",
+            Provider::Downloads => "Download",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Provider::Simbad => "simbad",
+            Provider::AstrometryNet => "astrometry-net",
+            Provider::Elevation => "elevation",
+            Provider::Routing => "routing",
+            Provider::Hips => "hips2fits",
+            Provider::Weather => "weather",
+            Provider::Downloads => "downloads",
+        }
+    }
+
+    pub fn default_limit(self) -> ProviderLimit {
+        let (min_interval_ms, max_retries) = match self {
+            // Each SIMBAD lookup is two TAP queries
+            Provider::Simbad => (500, 2),
+            Provider::AstrometryNet => (1000, 3),
+            Provider::Elevation => (1000, 2),
+            // The public OSRM server allows one request per second
+            Provider::Routing => (1000, 2),
+            Provider::Hips => (1000, 2),
+            Provider::Weather => (1000, 2),
+            Provider::Downloads => (0, 1),
+        };
+        ProviderLimit {
+            min_interval_ms,
+            max_retries,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderLimit {
+    /// Minimum spacing between requests to the provider
+    pub min_interval_ms: u64,
+    /// Retries after a transient failure (network error, HTTP 429 or 5xx)
+    pub max_retries: u32,
+}
+
+impl ProviderLimit {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.min_interval_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    /// Refuse all external requests; lookups answer from cached responses
+    pub offline: bool,
+    /// Per-provider limits; providers left out use their defaults
+    pub limits: BTreeMap<Provider, ProviderLimit>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings {
+            offline: false,
+            limits: Provider::ALL
+                .into_iter()
+                .map(|p| (p, p.default_limit()))
+                .collect(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn limit(&self, provider: Provider) -> ProviderLimit {
+        self.limits
+            .get(&provider)
+            .copied()
+            .unwrap_or_else(|| provider.default_limit())
+    }
+}
+
+/// Why a request failed, and whether trying again might help
+#[derive(Debug)]
+pub enum RequestError {
+    /// Transient failure: the request is retried
+    Retry(String),
+    /// Permanent failure (bad request, unparseable answer)
+    Fail(String),
+}
+
+pub struct Network {
+    settings: Mutex<NetworkSettings>,
+    /// Earliest time the next request to each provider may be sent
+    next_slot: Mutex<HashMap<Provider, Instant>>,
+    /// Where the last good JSON response per URL is kept
+    cache_dir: Option<PathBuf>,
+    client: reqwest::Client,
+}
+
+impl Network {
+    pub fn new(settings: NetworkSettings, cache_dir: Option<PathBuf>) -> Self {
+        Network {
+            settings: Mutex::new(settings),
+            next_slot: Mutex::new(HashMap::new()),
+            cache_dir,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn settings(&self) -> NetworkSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: NetworkSettings) {
+        *self.settings.lock().unwrap() = settings;
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.settings.lock().unwrap().offline
+    }
+
+    pub fn limit(&self, provider: Provider) -> ProviderLimit {
+        self.settings.lock().unwrap().limit(provider)
+    }
+
+    /// Error out when offline mode is on
+    pub fn check_online(&self, provider: Provider) -> Result<(), String> {
+        if self.is_offline() {
+            return Err(format!(
+                "Offline mode is on; {} is unavailable",
+                provider.name()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Claim the next request slot for a provider; returns how long to wait
+    /// before sending. Concurrent callers get successive slots.
+    fn reserve(&self, provider: Provider) -> Duration {
+        let interval = self.limit(provider).interval();
+        let now = Instant::now();
+        let mut slots = self.next_slot.lock().unwrap();
+        let slot = slots
+            .get(&provider)
+            .copied()
+            .filter(|slot| *slot > now)
+            .unwrap_or(now);
+        slots.insert(provider, slot + interval);
+        slot - now
+    }
+
+    /// After a failed attempt: how long to back off before retrying, or the
+    /// error to give up with
+    fn retry_after(
+        &self,
+        provider: Provider,
+        attempt: u32,
+        error: RequestError,
+    ) -> Result<Duration, String> {
+        let error = match error {
+            RequestError::Retry(e) if attempt <= self.limit(provider).max_retries => e,
+            RequestError::Retry(e) | RequestError::Fail(e) => return Err(e),
+        };
+        let delay = RETRY_BACKOFF
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_RETRY_BACKOFF);
+        log::warn!(
+            "{} request failed (attempt {}), retrying in {:?}: {}",
+            provider.name(),
+            attempt,
+            delay,
+            error
+        );
+        Ok(delay)
+    }
+
+    /// Send a request to a provider, rate limited and retried
+    pub async fn request<T, F, Fut>(&self, provider: Provider, mut send: F) -> Result<T, String>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, RequestError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.check_online(provider)?;
+            tokio::time::sleep(self.reserve(provider)).await;
+            match send().await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_after(provider, attempt, e)?).await;
+                }
+            }
+        }
+    }
+
+    /// `request` for blocking calls (e.g. SIMBAD through Python)
+    pub fn request_blocking<T>(
+        &self,
+        provider: Provider,
+        mut send: impl FnMut() -> Result<T, RequestError>,
+    ) -> Result<T, String> {
+        let mut attempt = 0;
+        loop {
+            self.check_online(provider)?;
+            std::thread::sleep(self.reserve(provider));
+            match send() {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    attempt += 1;
+                    std::thread::sleep(self.retry_after(provider, attempt, e)?);
+                }
+            }
+        }
+    }
+
+    /// Send an HTTP request, rate limited and retried. Network errors and
+    /// HTTP 429 or 5xx answers are retried; other failed statuses are not.
+    pub async fn send(
+        &self,
+        provider: Provider,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, String> {
+        self.request(provider, || {
+            let request = build(&self.client);
+            async move {
+                let response = request.send().await.map_err(|e| {
+                    RequestError::Retry(format!("{} request failed: {}", provider.name(), e))
+                })?;
+                let status = response.status();
+                if status.is_success() {
+                    return Ok(response);
+                }
+                let error = format!("{} returned HTTP {}", provider.name(), status);
+                if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    Err(RequestError::Retry(error))
+                } else {
+                    Err(RequestError::Fail(error))
+                }
+            }
+        })
+        .await
+    }
+
+    /// GET a JSON document. Good responses are cached; when offline or the
+    /// request fails, the last cached response for the URL is used instead.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        provider: Provider,
+        url: &str,
+    ) -> Result<T, String> {
+        let fetched = async {
+            let response = self.send(provider, |client| client.get(url)).await?;
+            let body = response
+                .text()
+                .await
+                .map_err(|e| format!("{} request failed: {}", provider.name(), e))?;
+            let value: T = serde_json::from_str(&body)
+                .map_err(|e| format!("Invalid {} response: {}", provider.name(), e))?;
+            Ok::<_, String>((value, body))
+        }
+        .await;
+
+        match fetched {
+            Ok((value, body)) => {
+                self.store_response(provider, url, &body);
+                Ok(value)
+            }
+            Err(e) => match self.cached_response(provider, url) {
+                Some(value) => {
+                    log::info!("Using cached {} response: {}", provider.name(), e);
+                    Ok(value)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn cache_path(&self, provider: Provider, url: &str) -> Option<PathBuf> {
+        let hash = hex::encode(Sha256::digest(url.as_bytes()));
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}-{}.json", provider.key(), &hash[..16])))
+    }
+
+    fn cached_response<T: DeserializeOwned>(&self, provider: Provider, url: &str) -> Option<T> {
+        let data = fs::read_to_string(self.cache_path(provider, url)?).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    fn store_response(&self, provider: Provider, url: &str, body: &str) {
+        let Some(path) = self.cache_path(provider, url) else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, body));
+        if let Err(e) = result {
+            log::warn!("Failed to cache {} response: {}", provider.name(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn network(min_interval_ms: u64, max_retries: u32) -> Network {
+        let mut settings = NetworkSettings::default();
+        settings.limits.insert(
+            Provider::Simbad,
+            ProviderLimit {
+                min_interval_ms,
+                max_retries,
+            },
+        );
+        Network::new(settings, None)
+    }
+
+    #[test]
+    fn requests_are_spaced_per_provider() {
+        let network = network(1000, 0);
+        assert_eq!(network.reserve(Provider::Simbad), Duration::ZERO);
+        let wait = network.reserve(Provider::Simbad);
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+        let wait = network.reserve(Provider::Simbad);
+        assert!(wait > Duration::from_millis(1900));
+        // Other providers have their own slots
+        assert_eq!(network.reserve(Provider::Downloads), Duration::ZERO);
+    }
+
+    #[test]
+    fn transient_failures_are_retried() {
+        let network = network(0, 1);
+        let attempts = Cell::new(0);
+        let result = network.request_blocking(Provider::Simbad, || {
+            attempts.set(attempts.get() + 1);
+            match attempts.get() {
+                1 => Err(RequestError::Retry("timed out".to_string())),
+                _ => Ok("M 42"),
+            }
+        });
+        assert_eq!(result, Ok("M 42"));
+        assert_eq!(attempts.get(), 2);
+
+        attempts.set(0);
+        let result: Result<(), String> = network.request_blocking(Provider::Simbad, || {
+            attempts.set(attempts.get() + 1);
+            Err(RequestError::Fail("HTTP 400".to_string()))
+        });
+        assert_eq!(result, Err("HTTP 400".to_string()));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn offline_mode_refuses_requests() {
+        let network = network(0, 0);
+        let mut settings = network.settings();
+        settings.offline = true;
+        network.set_settings(settings);
+
+        let result: Result<(), String> =
+            network.request_blocking(Provider::Simbad, || panic!("should not send"));
+        assert_eq!(
+            result,
+            Err("Offline mode is on; SIMBAD is unavailable".to_string())
+        );
+    }
+
+    #[test]
+    fn cached_responses_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let network = Network::new(NetworkSettings::default(), Some(dir.path().join("network")));
+        let url = "https://api.open-meteo.com/v1/elevation?latitude=1&longitude=2";
+        assert_eq!(
+            network.cached_response::<serde_json::Value>(Provider::Elevation, url),
+            None
+        );
+
+        network.store_response(Provider::Elevation, url, r#"{"elevation":[412.0]}"#);
+        let cached: serde_json::Value = network.cached_response(Provider::Elevation, url).unwrap();
+        assert_eq!(cached["elevation"][0], 412.0);
+        assert_eq!(
+            network.cached_response::<serde_json::Value>(Provider::Routing, url),
+            None
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! On-disk cache of converted previews
+//!
+//! Stretching and encoding a FITS file for display takes seconds, so the
+//! results are kept in one directory, keyed by a hash of the source file's
+//! contents and the conversion parameters. Files are touched when they're
+//! used; once the directory grows past its size cap, the least recently used
+//! files are removed.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+
+/// A source file's size and modification time, to tell when its contents
+/// need hashing again
+type FileStamp = (u64, SystemTime);
+
+pub struct PreviewCache {
+    dir: PathBuf,
+    max_bytes: AtomicU64,
+    /// Content hashes of source files seen so far
+    hashes: Mutex<HashMap<PathBuf, (FileStamp, String)>>,
+}
+
+impl PreviewCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_bytes: AtomicU64::new(max_bytes),
+            hashes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Change the size cap, evicting files if the cache is now over it
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        self.evict(None);
+    }
+
+    /// SHA-256 of a file's contents. Remembered until the file's size or
+    /// modification time changes, so large FITS files aren't re-read on
+    /// every view.
+    pub fn file_hash(&self, path: &Path) -> Result<String, String> {
+        let metadata =
+            fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let stamp = (
+            metadata.len(),
+            metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        );
+        let mut hashes = self.hashes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((known, hash)) = hashes.get(path) {
+            if *known == stamp {
+                return Ok(hash.clone());
+            }
+        }
+
+        let mut file =
+            File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 1 << 20];
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let hash = hex::encode(hasher.finalize());
+        hashes.insert(path.to_path_buf(), (stamp, hash.clone()));
+        Ok(hash)
+    }
+
+    /// Cache file name for a source file converted with `params`
+    pub fn key(&self, source: &Path, params: &str, extension: &str) -> Result<String, String> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.file_hash(source)?.as_bytes());
+        hasher.update([0]);
+        hasher.update(params.as_bytes());
+        let digest = hex::encode(hasher.finalize());
+        Ok(format!("{}.{}", &digest[..32], extension))
+    }
+
+    /// Where the file for `name` is (or would be) stored
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Path of a cached file, marking it as just used
+    pub fn get(&self, name: &str) -> Option<PathBuf> {
+        let path = self.path(name);
+        let file = File::options().append(true).open(&path).ok()?;
+        let _ = file.set_modified(SystemTime::now());
+        Some(path)
+    }
+
+    /// Contents of a cached file, marking it as just used
+    pub fn read(&self, name: &str) -> Option<Vec<u8>> {
+        fs::read(self.get(name)?).ok()
+    }
+
+    /// Store a file, then evict others if the cache is over its cap
+    pub fn insert(&self, name: &str, data: &[u8]) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create preview cache: {}", e))?;
+        let path = self.path(name);
+        // Written aside and renamed, so readers never see a partial file
+        let partial = self.dir.join(format!(".{}.partial", name));
+        fs::write(&partial, data)
+            .and_then(|_| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to write cached preview: {}", e))?;
+        self.evict(Some(&path));
+        Ok(path)
+    }
+
+    /// Account for a file written straight to [`PreviewCache::path`]
+    pub fn added(&self, name: &str) {
+        self.evict(Some(&self.path(name)));
+    }
+
+    /// Total size in bytes and number of cached files
+    pub fn usage(&self) -> (u64, usize) {
+        let entries = self.entries();
+        (entries.iter().map(|(_, size, _)| size).sum(), entries.len())
+    }
+
+    /// Remove every cached file
+    pub fn clear(&self) -> Result<(), String> {
+        for (path, _, _) in self.entries() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Cached files with their size and last use
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                if !metadata.is_file() || entry.file_name().to_string_lossy().starts_with('.') {
+                    return None;
+                }
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                Some((entry.path(), metadata.len(), used))
+            })
+            .collect()
+    }
+
+    /// Remove least recently used files until the cache fits its cap,
+    /// never removing `keep`
+    fn evict(&self, keep: Option<&Path>) {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        let max_bytes = self.max_bytes();
+        if total <= max_bytes {
+            return;
+        }
+
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, size, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            if Some(path.as_path()) == keep {
+                continue;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => total -= size,
+                Err(e) => log::warn!("Failed to evict cached preview {:?}: {}", path, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn touch(path: &Path, age_secs: u64) {
+        let time = SystemTime::now() - Duration::from_secs(age_secs);
+        File::options()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn key_depends_on_contents_and_params() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::new(dir.path().join("cache"), 1 << 20);
+        let source = dir.path().join("m42.fits");
+        fs::write(&source, b"SIMPLE").unwrap();
+
+        let key = cache.key(&source, "jpeg:90", "jpg").unwrap();
+        assert!(key.ends_with(".jpg"));
+        assert_eq!(key, cache.key(&source, "jpeg:90", "jpg").unwrap());
+        assert_ne!(key, cache.key(&source, "jpeg:80", "jpg").unwrap());
+
+        fs::write(&source, b"SIMPLE  = T").unwrap();
+        assert_ne!(key, cache.key(&source, "jpeg:90", "jpg").unwrap());
+        assert!(cache
+            .key(&dir.path().join("missing.fits"), "", "jpg")
+            .is_err());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_over_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::new(dir.path().to_path_buf(), 25);
+        cache.insert("a.jpg", &[0; 10]).unwrap();
+        cache.insert("b.jpg", &[0; 10]).unwrap();
+        touch(&cache.path("a.jpg"), 60);
+        touch(&cache.path("b.jpg"), 30);
+
+        // Using "a" makes "b" the oldest
+        assert_eq!(cache.read("a.jpg").unwrap().len(), 10);
+        cache.insert("c.jpg", &[0; 10]).unwrap();
+        assert!(cache.get("b.jpg").is_none());
+        assert!(cache.get("a.jpg").is_some());
+        assert_eq!(cache.usage(), (20, 2));
+
+        cache.set_max_bytes(5);
+        assert_eq!(cache.usage().1, 0);
+        cache.insert("big.jpg", &[0; 10]).unwrap();
+        assert!(cache.get("big.jpg").is_some(), "newest file is kept");
+
+        cache.clear().unwrap();
+        assert_eq!(cache.usage(), (0, 0));
+    }
+}
@@ -2,9 +2,8 @@
 //!
 //! Provides access to the Python altitude calculation functionality.
 
-use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 /// Observer location for altitude calculations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,63 +39,94 @@ pub struct SunTimes {
     pub astronomical_twilight_end: Option<String>,
 }
 
+/// A span of time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeInterval {
+    pub start: String,
+    pub end: String,
+}
+
+/// A span of one sun phase: "day", "civil", "nautical", "astronomical"
+/// (twilight) or "night"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwilightInterval {
+    pub start: String,
+    pub end: String,
+    pub phase: String,
+}
+
+/// Everything an altitude chart shows for one night
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NightChart {
+    /// Chart range: an hour before sunset to an hour after sunrise
+    pub start: String,
+    pub end: String,
+    /// Missing during polar day or night
+    pub sunset: Option<String>,
+    pub sunrise: Option<String>,
+    pub min_altitude: f64,
+    pub target: Vec<AltitudePoint>,
+    pub moon: Vec<AltitudePoint>,
+    /// Illuminated fraction of the moon at the middle of the chart
+    pub moon_illumination: f64,
+    pub twilight: Vec<TwilightInterval>,
+    /// Astronomical darkness
+    pub darkness: Vec<TimeInterval>,
+    /// Darkness with the target at or above `min_altitude`
+    pub imaging_windows: Vec<TimeInterval>,
+}
+
+/// An object to compare altitudes of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AltitudeTarget {
+    pub name: Option<String>,
+    pub ra_deg: f64,
+    pub dec_deg: f64,
+}
+
+/// One target's track over the night and when it's best placed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetTrack {
+    pub name: Option<String>,
+    pub ra_deg: f64,
+    pub dec_deg: f64,
+    pub points: Vec<AltitudePoint>,
+    /// Highest altitude in darkness (or at all, if it never gets dark)
+    pub max_altitude: Option<f64>,
+    pub best_time: Option<String>,
+    /// Dark time spent at or above the minimum altitude
+    pub hours_above_min: f64,
+    pub windows: Vec<TimeInterval>,
+}
+
+/// Altitude tracks of several targets over the same night
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AltitudeComparison {
+    pub start: String,
+    pub end: String,
+    pub sunset: Option<String>,
+    pub sunrise: Option<String>,
+    pub min_altitude: f64,
+    pub darkness: Vec<TimeInterval>,
+    /// In request order
+    pub targets: Vec<TargetTrack>,
+}
+
 /// Calculate current altitude and azimuth for an object
 pub fn calculate_altitude(
     ra_deg: f64,
     dec_deg: f64,
     location: &ObserverLocation,
 ) -> Result<AltitudePoint, String> {
-    Python::with_gil(|py| {
-        let astra_astro = py.import("astra_astro")
-            .map_err(|e| format!("Failed to import astra_astro: {}", e))?;
-
-        // Create ObserverLocation in Python
-        let altitude_module = py.import("astra_astro.altitude")
-            .map_err(|e| format!("Failed to import altitude module: {}", e))?;
-
-        let py_location = altitude_module
-            .getattr("ObserverLocation")
-            .map_err(|e| format!("Failed to get ObserverLocation: {}", e))?
-            .call1((
-                location.latitude,
-                location.longitude,
-                location.elevation,
-                location.name.clone(),
-            ))
-            .map_err(|e| format!("Failed to create ObserverLocation: {}", e))?;
-
-        // Call calculate_altitude
-        let result = astra_astro
-            .call_method1("calculate_altitude", (ra_deg, dec_deg, py_location))
-            .map_err(|e| format!("Altitude calculation failed: {}", e))?;
-
-        // Extract result
-        let dict: &Bound<'_, PyDict> = result.downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
-
-        Ok(AltitudePoint {
-            time: dict.get_item("time")
-                .map_err(|e| format!("Missing time: {}", e))?
-                .ok_or("Missing time field")?
-                .extract()
-                .map_err(|e| format!("Invalid time: {}", e))?,
-            altitude: dict.get_item("altitude")
-                .map_err(|e| format!("Missing altitude: {}", e))?
-                .ok_or("Missing altitude field")?
-                .extract()
-                .map_err(|e| format!("Invalid altitude: {}", e))?,
-            azimuth: dict.get_item("azimuth")
-                .map_err(|e| format!("Missing azimuth: {}", e))?
-                .ok_or("Missing azimuth field")?
-                .extract()
-                .map_err(|e| format!("Invalid azimuth: {}", e))?,
-            compass_direction: dict.get_item("compassDirection")
-                .map_err(|e| format!("Missing compassDirection: {}", e))?
-                .ok_or("Missing compassDirection field")?
-                .extract()
-                .map_err(|e| format!("Invalid compassDirection: {}", e))?,
-        })
-    })
+    super::call(
+        "calculate_altitude",
+        json!({ "ra_deg": ra_deg, "dec_deg": dec_deg, "location": location }),
+    )
+    .map_err(|e| format!("Altitude calculation failed: {}", e))
 }
 
 /// Calculate altitude data over a time range for plotting
@@ -107,115 +137,71 @@ pub fn calculate_altitude_data(
     duration_hours: Option<f64>,
     interval_minutes: Option<i32>,
 ) -> Result<Vec<AltitudePoint>, String> {
-    Python::with_gil(|py| {
-        let altitude_module = py.import("astra_astro.altitude")
-            .map_err(|e| format!("Failed to import altitude module: {}", e))?;
-
-        // Create ObserverLocation in Python
-        let py_location = altitude_module
-            .getattr("ObserverLocation")
-            .map_err(|e| format!("Failed to get ObserverLocation: {}", e))?
-            .call1((
-                location.latitude,
-                location.longitude,
-                location.elevation,
-                location.name.clone(),
-            ))
-            .map_err(|e| format!("Failed to create ObserverLocation: {}", e))?;
-
-        // Build kwargs
-        let kwargs = PyDict::new(py);
-        kwargs.set_item("location", py_location)
-            .map_err(|e| format!("Failed to set location: {}", e))?;
-        if let Some(duration) = duration_hours {
-            kwargs.set_item("duration_hours", duration)
-                .map_err(|e| format!("Failed to set duration_hours: {}", e))?;
-        }
-        if let Some(interval) = interval_minutes {
-            kwargs.set_item("interval_minutes", interval)
-                .map_err(|e| format!("Failed to set interval_minutes: {}", e))?;
-        }
-
-        // Call calculate_altitude_data
-        let result = altitude_module
-            .getattr("calculate_altitude_data")
-            .map_err(|e| format!("Failed to get calculate_altitude_data: {}", e))?
-            .call((ra_deg, dec_deg), Some(&kwargs))
-            .map_err(|e| format!("Altitude data calculation failed: {}", e))?;
-
-        // Extract result list
-        let list: &Bound<'_, PyList> = result.downcast()
-            .map_err(|e| format!("Expected list result: {}", e))?;
-
-        let mut points = Vec::new();
-        for item in list.iter() {
-            let dict: &Bound<'_, PyDict> = item.downcast()
-                .map_err(|e| format!("Expected dict item: {}", e))?;
-
-            points.push(AltitudePoint {
-                time: dict.get_item("time")
-                    .map_err(|e| format!("Missing time: {}", e))?
-                    .ok_or("Missing time field")?
-                    .extract()
-                    .map_err(|e| format!("Invalid time: {}", e))?,
-                altitude: dict.get_item("altitude")
-                    .map_err(|e| format!("Missing altitude: {}", e))?
-                    .ok_or("Missing altitude field")?
-                    .extract()
-                    .map_err(|e| format!("Invalid altitude: {}", e))?,
-                azimuth: dict.get_item("azimuth")
-                    .map_err(|e| format!("Missing azimuth: {}", e))?
-                    .ok_or("Missing azimuth field")?
-                    .extract()
-                    .map_err(|e| format!("Invalid azimuth: {}", e))?,
-                compass_direction: dict.get_item("compassDirection")
-                    .map_err(|e| format!("Missing compassDirection: {}", e))?
-                    .ok_or("Missing compassDirection field")?
-                    .extract()
-                    .map_err(|e| format!("Invalid compassDirection: {}", e))?,
-            });
-        }
-
-        Ok(points)
-    })
+    super::call(
+        "calculate_altitude_data",
+        json!({
+            "ra_deg": ra_deg,
+            "dec_deg": dec_deg,
+            "location": location,
+            "duration_hours": duration_hours,
+            "interval_minutes": interval_minutes,
+        }),
+    )
+    .map_err(|e| format!("Altitude data calculation failed: {}", e))
 }
 
 /// Get sunrise, sunset, and twilight times for a location
 pub fn get_sun_times(location: &ObserverLocation) -> Result<SunTimes, String> {
-    Python::with_gil(|py| {
-        let altitude_module = py.import("astra_astro.altitude")
-            .map_err(|e| format!("Failed to import altitude module: {}", e))?;
-
-        // Create ObserverLocation in Python
-        let py_location = altitude_module
-            .getattr("ObserverLocation")
-            .map_err(|e| format!("Failed to get ObserverLocation: {}", e))?
-            .call1((
-                location.latitude,
-                location.longitude,
-                location.elevation,
-                location.name.clone(),
-            ))
-            .map_err(|e| format!("Failed to create ObserverLocation: {}", e))?;
-
-        // Call get_sunset_sunrise
-        let result = altitude_module
-            .call_method1("get_sunset_sunrise", (py_location,))
-            .map_err(|e| format!("Sun times calculation failed: {}", e))?;
+    super::call("get_sunset_sunrise", json!({ "location": location }))
+        .map_err(|e| format!("Sun times calculation failed: {}", e))
+}
 
-        // Extract result
-        let dict: &Bound<'_, PyDict> = result.downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
+/// Target and moon tracks, twilight and imaging windows for the night
+/// starting on `date` (an ISO date, default tonight)
+pub fn get_night_chart(
+    ra_deg: f64,
+    dec_deg: f64,
+    location: &ObserverLocation,
+    date: Option<&str>,
+    min_altitude: Option<f64>,
+    interval_minutes: Option<i32>,
+) -> Result<NightChart, String> {
+    super::call(
+        "get_night_chart",
+        json!({
+            "ra_deg": ra_deg,
+            "dec_deg": dec_deg,
+            "location": location,
+            "date": date,
+            "min_altitude": min_altitude,
+            "interval_minutes": interval_minutes,
+        }),
+    )
+    .map_err(|e| format!("Night chart calculation failed: {}", e))
+}
 
-        Ok(SunTimes {
-            sunrise: dict.get_item("sunrise").ok().flatten().and_then(|v| v.extract().ok()),
-            sunset: dict.get_item("sunset").ok().flatten().and_then(|v| v.extract().ok()),
-            civil_twilight_start: dict.get_item("civilTwilightStart").ok().flatten().and_then(|v| v.extract().ok()),
-            civil_twilight_end: dict.get_item("civilTwilightEnd").ok().flatten().and_then(|v| v.extract().ok()),
-            nautical_twilight_start: dict.get_item("nauticalTwilightStart").ok().flatten().and_then(|v| v.extract().ok()),
-            nautical_twilight_end: dict.get_item("nauticalTwilightEnd").ok().flatten().and_then(|v| v.extract().ok()),
-            astronomical_twilight_start: dict.get_item("astronomicalTwilightStart").ok().flatten().and_then(|v| v.extract().ok()),
-            astronomical_twilight_end: dict.get_item("astronomicalTwilightEnd").ok().flatten().and_then(|v| v.extract().ok()),
-        })
-    })
+/// Altitude tracks for several targets over the night starting on `date`
+/// (an ISO date, default tonight), in one call
+pub fn compare_altitudes(
+    targets: &[AltitudeTarget],
+    location: &ObserverLocation,
+    date: Option<&str>,
+    min_altitude: Option<f64>,
+    interval_minutes: Option<i32>,
+) -> Result<AltitudeComparison, String> {
+    let targets: Vec<_> = targets
+        .iter()
+        .map(|t| json!({ "name": t.name, "ra_deg": t.ra_deg, "dec_deg": t.dec_deg }))
+        .collect();
+    super::call(
+        "compare_altitudes",
+        json!({
+            "targets": targets,
+            "location": location,
+            "date": date,
+            "min_altitude": min_altitude,
+            "interval_minutes": interval_minutes,
+        }),
+    )
+    .map_err(|e| format!("Altitude comparison failed: {}", e))
 }
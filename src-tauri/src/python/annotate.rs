@@ -0,0 +1,44 @@
+//! Annotated preview bridge
+//!
+//! Draws catalog objects, an RA/Dec grid and constellation boundaries over
+//! an image preview using the Python renderer.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Result from rendering an annotated preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotateResult {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Render `preview_path` with overlays from the WCS solution and catalog
+/// objects, writing a PNG to `output_path`
+pub fn render_annotated_preview(
+    preview_path: &str,
+    output_path: &str,
+    wcs: &serde_json::Value,
+    objects: &serde_json::Value,
+    grid: bool,
+    constellations: bool,
+    labels: bool,
+) -> Result<AnnotateResult, String> {
+    super::call(
+        "render_annotated_preview",
+        json!({
+            "preview_path": preview_path,
+            "output_path": output_path,
+            "wcs": wcs,
+            "objects": objects,
+            "grid": grid,
+            "constellations": constellations,
+            "labels": labels,
+        }),
+    )
+    .map_err(|e| format!("Annotated preview failed: {}", e))
+}
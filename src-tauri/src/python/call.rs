@@ -0,0 +1,193 @@
+//! Typed calls into astra_astro
+//!
+//! Bridges describe a call as a method name plus JSON parameters and get a
+//! deserialized result back, whichever backend is active. Both backends go
+//! through the `astra_astro.server.METHODS` table, so the embedded path
+//! resolves names and parameters exactly like the subprocess server does.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyCFunction, PyDict, PyFloat, PyInt, PyList, PyTuple};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+
+use super::subprocess;
+
+/// Receives progress reports (`{"step", "progress", "message"}`) while a
+/// long-running method executes
+pub type ProgressFn = Box<dyn Fn(Value) + Send + 'static>;
+
+/// Convert a Python value to JSON.
+///
+/// None entries are dropped from dicts (matching the subprocess server, so
+/// serde defaults apply the same way), non-finite floats become null, and
+/// numpy scalars/arrays are converted through `tolist()`.
+pub fn to_json(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(b) = obj.downcast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (key, value) in dict.iter() {
+            let value = to_json(&value)?;
+            if !value.is_null() {
+                let key = match key.extract::<String>() {
+                    Ok(key) => key,
+                    Err(_) => key.str()?.to_string(),
+                };
+                map.insert(key, value);
+            }
+        }
+        return Ok(Value::Object(map));
+    }
+    if let Ok(s) = obj.extract::<String>() {
+        return Ok(Value::String(s));
+    }
+    if obj.downcast::<PyInt>().is_ok() {
+        if let Ok(i) = obj.extract::<i64>() {
+            return Ok(Value::from(i));
+        }
+    }
+    if obj.downcast::<PyFloat>().is_ok() {
+        let f: f64 = obj.extract()?;
+        return Ok(Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null));
+    }
+    if obj.downcast::<PyList>().is_ok() || obj.downcast::<PyTuple>().is_ok() {
+        return obj
+            .try_iter()?
+            .map(|item| to_json(&item?))
+            .collect::<PyResult<Vec<_>>>()
+            .map(Value::Array);
+    }
+    if obj.hasattr("tolist")? {
+        return to_json(&obj.call_method0("tolist")?);
+    }
+    if let Ok(f) = obj.extract::<f64>() {
+        return Ok(Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or(Value::Null));
+    }
+    Ok(Value::String(obj.str()?.to_string()))
+}
+
+/// Keyword arguments for a call: null parameters are left out so the
+/// Python defaults apply
+fn kwargs<'py>(py: Python<'py>, params: &Value) -> PyResult<Bound<'py, PyDict>> {
+    let params: Map<String, Value> = params
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, v)| !v.is_null())
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let loaded = py
+        .import("json")?
+        .call_method1("loads", (Value::Object(params).to_string(),))?;
+    Ok(loaded.downcast_into::<PyDict>()?)
+}
+
+fn call_embedded(
+    method: &str,
+    params: &Value,
+    on_progress: Option<ProgressFn>,
+) -> Result<Value, String> {
+    Python::with_gil(|py| {
+        let methods = py.import("astra_astro.server")?.getattr("METHODS")?;
+        let function = methods.get_item(method)?;
+        let kwargs = kwargs(py, params)?;
+
+        if let Some(on_progress) = on_progress {
+            let callback = PyCFunction::new_closure(
+                py,
+                None,
+                None,
+                move |args: &Bound<'_, PyTuple>,
+                      _kwargs: Option<&Bound<'_, PyDict>>|
+                      -> PyResult<()> {
+                    let message = match args.len() {
+                        n if n > 2 => to_json(&args.get_item(2)?)?,
+                        _ => Value::String(String::new()),
+                    };
+                    on_progress(serde_json::json!({
+                        "step": to_json(&args.get_item(0)?)?,
+                        "progress": to_json(&args.get_item(1)?)?,
+                        "message": message,
+                    }));
+                    Ok(())
+                },
+            )?;
+            kwargs.set_item("progress_callback", callback)?;
+        }
+
+        to_json(&function.call((), Some(&kwargs))?)
+    })
+    .map_err(|e: PyErr| e.to_string())
+}
+
+/// Call an astra_astro method and deserialize its result, reporting
+/// progress for methods that support it
+pub fn call_with_progress<T: DeserializeOwned>(
+    method: &str,
+    params: Value,
+    on_progress: Option<ProgressFn>,
+) -> Result<T, String> {
+    let result = if super::use_subprocess() {
+        match on_progress {
+            Some(on_progress) => {
+                subprocess::call_with_progress(method, params, &mut |p| on_progress(p))
+            }
+            None => subprocess::call(method, params),
+        }
+    } else {
+        call_embedded(method, &params, on_progress)
+    }?;
+
+    serde_json::from_value(result).map_err(|e| format!("Unexpected result from {}: {}", method, e))
+}
+
+/// Call an astra_astro method and deserialize its result
+pub fn call<T: DeserializeOwned>(method: &str, params: Value) -> Result<T, String> {
+    call_with_progress(method, params, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn convert(code: &str) -> Value {
+        Python::with_gil(|py| {
+            let value = py
+                .eval(&std::ffi::CString::new(code).unwrap(), None, None)
+                .unwrap();
+            to_json(&value).unwrap()
+        })
+    }
+
+    #[test]
+    fn converts_python_values() {
+        assert_eq!(
+            convert("{'a': 1, 'b': None, 'c': [True, 2.5, None], 'd': ('x', {'e': None})}"),
+            json!({ "a": 1, "c": [true, 2.5, null], "d": ["x", {}] })
+        );
+        assert_eq!(convert("float('nan')"), Value::Null);
+        assert_eq!(convert("{'n': float('inf')}"), json!({}));
+        assert_eq!(convert("{1: 'one'}"), json!({ "1": "one" }));
+    }
+
+    #[test]
+    fn kwargs_skip_null_parameters() {
+        Python::with_gil(|py| {
+            let kwargs = kwargs(py, &json!({ "a": 1, "b": null, "c": { "d": [1.5] } })).unwrap();
+            assert_eq!(kwargs.len(), 2);
+            assert_eq!(
+                to_json(kwargs.as_any()).unwrap(),
+                json!({ "a": 1, "c": { "d": [1.5] } })
+            );
+        });
+    }
+}
@@ -2,10 +2,8 @@
 //!
 //! Provides access to the Python image processing functionality.
 
-use pyo3::prelude::*;
-use pyo3::types::{PyCFunction, PyDict, PyTuple};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::json;
 use std::sync::mpsc;
 
 /// Progress update from image processing
@@ -40,6 +38,21 @@ pub struct ProcessingParams {
     pub noise_reduction: f64,
     /// Contrast adjustment (1.0=none, 1.3=Seestar-like, 1.5=moderate, 2.0=strong)
     pub contrast: f64,
+    /// Background removal method: "median" (large median filter) or
+    /// "polynomial" (fitted gradient surface, also available natively)
+    #[serde(default = "default_background_method")]
+    pub background_method: String,
+    /// Polynomial order for the "polynomial" method (1-4)
+    #[serde(default = "default_gradient_order")]
+    pub gradient_order: u32,
+}
+
+fn default_background_method() -> String {
+    "median".to_string()
+}
+
+fn default_gradient_order() -> u32 {
+    2
 }
 
 impl Default for ProcessingParams {
@@ -53,6 +66,8 @@ impl Default for ProcessingParams {
             color_calibration: true,
             noise_reduction: 0.0,
             contrast: 1.3,
+            background_method: default_background_method(),
+            gradient_order: default_gradient_order(),
         }
     }
 }
@@ -62,10 +77,41 @@ impl Default for ProcessingParams {
 #[serde(rename_all = "camelCase")]
 pub struct ProcessingResult {
     pub success: bool,
+    #[serde(default)]
     pub output_fits_path: String,
+    #[serde(default)]
     pub output_preview_path: String,
+    #[serde(default)]
     pub target_type: String,
+    #[serde(default = "empty_params")]
     pub processing_params: serde_json::Value,
+    #[serde(default)]
+    pub processing_time: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+}
+
+fn empty_params() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+/// Result from preview processing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewResult {
+    pub success: bool,
+    /// Processed preview as a PNG data URL
+    #[serde(default)]
+    pub preview_data: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(default)]
+    pub target_type: String,
+    #[serde(default = "empty_params")]
+    pub processing_params: serde_json::Value,
+    #[serde(default)]
     pub processing_time: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
@@ -82,6 +128,29 @@ pub struct TargetInfo {
     pub simbad_type: Option<String>,
 }
 
+/// Result of a quick preview, as returned by Python
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuickPreviewResult {
+    success: bool,
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+fn process_params(
+    input_fits_path: &str,
+    output_dir: &str,
+    params: &ProcessingParams,
+    object_name: Option<&str>,
+) -> serde_json::Value {
+    json!({
+        "input_fits_path": input_fits_path,
+        "output_dir": output_dir,
+        "params_dict": params,
+        "object_name": object_name,
+    })
+}
+
 /// Process a FITS image with stretch and enhancements
 pub fn process_image(
     input_fits_path: &str,
@@ -89,141 +158,11 @@ pub fn process_image(
     params: &ProcessingParams,
     object_name: Option<&str>,
 ) -> Result<ProcessingResult, String> {
-    Python::with_gil(|py| {
-        // Import our module
-        let astra_astro = py
-            .import("astra_astro")
-            .map_err(|e| format!("Failed to import astra_astro: {}", e))?;
-
-        // Build params dictionary
-        let params_dict = PyDict::new(py);
-        params_dict
-            .set_item("targetType", &params.target_type)
-            .map_err(|e| format!("Failed to set targetType: {}", e))?;
-        params_dict
-            .set_item("stretchMethod", &params.stretch_method)
-            .map_err(|e| format!("Failed to set stretchMethod: {}", e))?;
-        params_dict
-            .set_item("stretchFactor", params.stretch_factor)
-            .map_err(|e| format!("Failed to set stretchFactor: {}", e))?;
-        params_dict
-            .set_item("backgroundRemoval", params.background_removal)
-            .map_err(|e| format!("Failed to set backgroundRemoval: {}", e))?;
-        params_dict
-            .set_item("starReduction", params.star_reduction)
-            .map_err(|e| format!("Failed to set starReduction: {}", e))?;
-        params_dict
-            .set_item("colorCalibration", params.color_calibration)
-            .map_err(|e| format!("Failed to set colorCalibration: {}", e))?;
-        params_dict
-            .set_item("noiseReduction", params.noise_reduction)
-            .map_err(|e| format!("Failed to set noiseReduction: {}", e))?;
-        params_dict
-            .set_item("contrast", params.contrast)
-            .map_err(|e| format!("Failed to set contrast: {}", e))?;
-
-        // Call process_image_from_dict function
-        let result = astra_astro
-            .call_method1(
-                "process_image_from_dict",
-                (input_fits_path, output_dir, params_dict, object_name),
-            )
-            .map_err(|e| format!("Image processing failed: {}", e))?;
-
-        // Convert Python dict to Rust struct
-        let dict: &Bound<'_, PyDict> = result
-            .downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
-
-        // Extract fields
-        let success: bool = dict
-            .get_item("success")
-            .map_err(|e| format!("Missing success: {}", e))?
-            .ok_or("Missing success field")?
-            .extract()
-            .map_err(|e| format!("Invalid success: {}", e))?;
-
-        let output_fits_path: String = dict
-            .get_item("outputFitsPath")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let output_preview_path: String = dict
-            .get_item("outputPreviewPath")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let target_type: String = dict
-            .get_item("targetType")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let processing_time: f64 = dict
-            .get_item("processingTime")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-
-        let error_message: Option<String> = dict
-            .get_item("errorMessage")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok());
-
-        // Extract processing params
-        let processing_params: serde_json::Value = dict
-            .get_item("processingParams")
-            .ok()
-            .flatten()
-            .and_then(|v| {
-                // Convert Python dict to HashMap, then to serde_json::Value
-                let params_map: HashMap<String, serde_json::Value> = v
-                    .extract::<HashMap<String, PyObject>>()
-                    .ok()?
-                    .into_iter()
-                    .filter_map(|(k, v)| {
-                        Python::with_gil(|py| {
-                            let py_obj = v.bind(py);
-                            if let Ok(s) = py_obj.extract::<String>() {
-                                Some((k, serde_json::Value::String(s)))
-                            } else if let Ok(b) = py_obj.extract::<bool>() {
-                                Some((k, serde_json::Value::Bool(b)))
-                            } else if let Ok(f) = py_obj.extract::<f64>() {
-                                Some((
-                                    k,
-                                    serde_json::Number::from_f64(f)
-                                        .map(serde_json::Value::Number)
-                                        .unwrap_or(serde_json::Value::Null),
-                                ))
-                            } else {
-                                None
-                            }
-                        })
-                    })
-                    .collect();
-                Some(serde_json::Value::Object(
-                    params_map.into_iter().collect(),
-                ))
-            })
-            .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
-
-        Ok(ProcessingResult {
-            success,
-            output_fits_path,
-            output_preview_path,
-            target_type,
-            processing_params,
-            processing_time,
-            error_message,
-        })
-    })
+    super::call(
+        "process_image_from_dict",
+        process_params(input_fits_path, output_dir, params, object_name),
+    )
+    .map_err(|e| format!("Image processing failed: {}", e))
 }
 
 /// Process a FITS image with progress reporting
@@ -234,213 +173,42 @@ pub fn process_image_with_progress(
     object_name: Option<&str>,
     progress_tx: ProgressSender,
 ) -> Result<ProcessingResult, String> {
-    Python::with_gil(|py| {
-        // Import our module
-        let astra_astro = py
-            .import("astra_astro")
-            .map_err(|e| format!("Failed to import astra_astro: {}", e))?;
-
-        // Build params dictionary
-        let params_dict = PyDict::new(py);
-        params_dict
-            .set_item("targetType", &params.target_type)
-            .map_err(|e| format!("Failed to set targetType: {}", e))?;
-        params_dict
-            .set_item("stretchMethod", &params.stretch_method)
-            .map_err(|e| format!("Failed to set stretchMethod: {}", e))?;
-        params_dict
-            .set_item("stretchFactor", params.stretch_factor)
-            .map_err(|e| format!("Failed to set stretchFactor: {}", e))?;
-        params_dict
-            .set_item("backgroundRemoval", params.background_removal)
-            .map_err(|e| format!("Failed to set backgroundRemoval: {}", e))?;
-        params_dict
-            .set_item("starReduction", params.star_reduction)
-            .map_err(|e| format!("Failed to set starReduction: {}", e))?;
-        params_dict
-            .set_item("colorCalibration", params.color_calibration)
-            .map_err(|e| format!("Failed to set colorCalibration: {}", e))?;
-        params_dict
-            .set_item("noiseReduction", params.noise_reduction)
-            .map_err(|e| format!("Failed to set noiseReduction: {}", e))?;
-        params_dict
-            .set_item("contrast", params.contrast)
-            .map_err(|e| format!("Failed to set contrast: {}", e))?;
-
-        // Create progress callback
-        let progress_callback = PyCFunction::new_closure(
-            py,
-            None,
-            None,
-            move |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
-                let step: String = args.get_item(0)?.extract()?;
-                let progress: f64 = args.get_item(1)?.extract()?;
-                let message: String = args.get_item(2)?.extract()?;
-
-                let _ = progress_tx.send(ProcessingProgress {
-                    step,
-                    progress,
-                    message,
-                });
-                Ok(())
-            },
-        )
-        .map_err(|e| format!("Failed to create progress callback: {}", e))?;
-
-        // Call process_image_from_dict function with progress callback
-        let result = astra_astro
-            .call_method1(
-                "process_image_from_dict",
-                (input_fits_path, output_dir, params_dict, object_name, progress_callback),
-            )
-            .map_err(|e| format!("Image processing failed: {}", e))?;
-
-        // Convert Python dict to Rust struct (same as process_image)
-        let dict: &Bound<'_, PyDict> = result
-            .downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
-
-        let success: bool = dict
-            .get_item("success")
-            .map_err(|e| format!("Missing success: {}", e))?
-            .ok_or("Missing success field")?
-            .extract()
-            .map_err(|e| format!("Invalid success: {}", e))?;
-
-        let output_fits_path: String = dict
-            .get_item("outputFitsPath")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let output_preview_path: String = dict
-            .get_item("outputPreviewPath")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let target_type: String = dict
-            .get_item("targetType")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or_default();
-
-        let processing_time: f64 = dict
-            .get_item("processingTime")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok())
-            .unwrap_or(0.0);
-
-        let error_message: Option<String> = dict
-            .get_item("errorMessage")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok());
-
-        let processing_params: serde_json::Value = dict
-            .get_item("processingParams")
-            .ok()
-            .flatten()
-            .and_then(|v| {
-                let params_map: HashMap<String, serde_json::Value> = v
-                    .extract::<HashMap<String, PyObject>>()
-                    .ok()?
-                    .into_iter()
-                    .filter_map(|(k, v)| {
-                        Python::with_gil(|py| {
-                            let py_obj = v.bind(py);
-                            if let Ok(s) = py_obj.extract::<String>() {
-                                Some((k, serde_json::Value::String(s)))
-                            } else if let Ok(b) = py_obj.extract::<bool>() {
-                                Some((k, serde_json::Value::Bool(b)))
-                            } else if let Ok(f) = py_obj.extract::<f64>() {
-                                Some((
-                                    k,
-                                    serde_json::Number::from_f64(f)
-                                        .map(serde_json::Value::Number)
-                                        .unwrap_or(serde_json::Value::Null),
-                                ))
-                            } else {
-                                None
-                            }
-                        })
-                    })
-                    .collect();
-                Some(serde_json::Value::Object(
-                    params_map.into_iter().collect(),
-                ))
-            })
-            .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+    let on_progress: super::ProgressFn = Box::new(move |progress| {
+        if let Ok(progress) = serde_json::from_value(progress) {
+            let _ = progress_tx.send(progress);
+        }
+    });
+    super::call_with_progress(
+        "process_image_from_dict",
+        process_params(input_fits_path, output_dir, params, object_name),
+        Some(on_progress),
+    )
+    .map_err(|e| format!("Image processing failed: {}", e))
+}
 
-        Ok(ProcessingResult {
-            success,
-            output_fits_path,
-            output_preview_path,
-            target_type,
-            processing_params,
-            processing_time,
-            error_message,
-        })
-    })
+/// Process a downsampled copy of a FITS image without writing any files
+pub fn preview_image(
+    input_fits_path: &str,
+    params: &ProcessingParams,
+    object_name: Option<&str>,
+    max_size: Option<u32>,
+) -> Result<PreviewResult, String> {
+    super::call(
+        "preview_image_from_dict",
+        json!({
+            "input_fits_path": input_fits_path,
+            "params_dict": params,
+            "object_name": object_name,
+            "max_size": max_size,
+        }),
+    )
+    .map_err(|e| format!("Preview processing failed: {}", e))
 }
 
 /// Classify a target from its object name
 pub fn classify_target(object_name: &str) -> Result<TargetInfo, String> {
-    Python::with_gil(|py| {
-        // Import our module
-        let astra_astro = py
-            .import("astra_astro")
-            .map_err(|e| format!("Failed to import astra_astro: {}", e))?;
-
-        // Call classify_target function
-        let result = astra_astro
-            .call_method1("classify_target", (object_name,))
-            .map_err(|e| format!("Target classification failed: {}", e))?;
-
-        // Convert Python dict to Rust struct
-        let dict: &Bound<'_, PyDict> = result
-            .downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
-
-        // Extract fields
-        let target_type: String = dict
-            .get_item("targetType")
-            .map_err(|e| format!("Missing targetType: {}", e))?
-            .ok_or("Missing targetType field")?
-            .extract()
-            .map_err(|e| format!("Invalid targetType: {}", e))?;
-
-        let object_name: String = dict
-            .get_item("objectName")
-            .map_err(|e| format!("Missing objectName: {}", e))?
-            .ok_or("Missing objectName field")?
-            .extract()
-            .map_err(|e| format!("Invalid objectName: {}", e))?;
-
-        let confidence: f64 = dict
-            .get_item("confidence")
-            .map_err(|e| format!("Missing confidence: {}", e))?
-            .ok_or("Missing confidence field")?
-            .extract()
-            .map_err(|e| format!("Invalid confidence: {}", e))?;
-
-        let simbad_type: Option<String> = dict
-            .get_item("simbadType")
-            .ok()
-            .flatten()
-            .and_then(|v| v.extract().ok());
-
-        Ok(TargetInfo {
-            target_type,
-            object_name,
-            confidence,
-            simbad_type,
-        })
-    })
+    super::call("classify_target", json!({ "object_name": object_name }))
+        .map_err(|e| format!("Target classification failed: {}", e))
 }
 
 /// Generate a quick preview JPEG/PNG from a FITS file using Python's MTF stretch.
@@ -450,55 +218,20 @@ pub fn quick_preview(
     bg_percent: Option<f64>,
     sigma: Option<f64>,
 ) -> Result<String, String> {
-    Python::with_gil(|py| {
-        let astra_astro = py
-            .import("astra_astro")
-            .map_err(|e| format!("Failed to import astra_astro: {}", e))?;
-
-        let image_process = astra_astro
-            .getattr("image_process")
-            .map_err(|e| format!("Failed to get image_process module: {}", e))?;
-
-        let result = image_process
-            .call_method1(
-                "quick_preview",
-                (
-                    input_fits_path,
-                    output_path,
-                    bg_percent.unwrap_or(0.15),
-                    sigma.unwrap_or(3.0),
-                ),
-            )
-            .map_err(|e| format!("Quick preview failed: {}", e))?;
-
-        let dict: &Bound<'_, PyDict> = result
-            .downcast()
-            .map_err(|e| format!("Expected dict result: {}", e))?;
-
-        let success: bool = dict
-            .get_item("success")
-            .map_err(|e| format!("Missing success: {}", e))?
-            .ok_or("Missing success field")?
-            .extract()
-            .map_err(|e| format!("Invalid success: {}", e))?;
-
-        if !success {
-            let error: String = dict
-                .get_item("error")
-                .ok()
-                .flatten()
-                .and_then(|v| v.extract().ok())
-                .unwrap_or_else(|| "Unknown error".to_string());
-            return Err(format!("Quick preview failed: {}", error));
-        }
-
-        let output: String = dict
-            .get_item("outputPath")
-            .map_err(|e| format!("Missing outputPath: {}", e))?
-            .ok_or("Missing outputPath field")?
-            .extract()
-            .map_err(|e| format!("Invalid outputPath: {}", e))?;
-
-        Ok(output)
-    })
+    let result: QuickPreviewResult = super::call(
+        "quick_preview",
+        json!({
+            "input_fits_path": input_fits_path,
+            "output_path": output_path,
+            "bg_percent": bg_percent.unwrap_or(0.15),
+            "sigma": sigma.unwrap_or(3.0),
+        }),
+    )
+    .map_err(|e| format!("Quick preview failed: {}", e))?;
+
+    if !result.success {
+        let error = result.error.unwrap_or_else(|| "Unknown error".to_string());
+        return Err(format!("Quick preview failed: {}", error));
+    }
+    result.output_path.ok_or_else(|| "Missing outputPath field".to_string())
 }
@@ -6,63 +6,367 @@
 pub mod simbad;
 pub mod altitude;
 pub mod plate_solve;
-pub mod skymap;
+pub mod annotate;
 pub mod image_process;
+pub mod report;
+pub mod call;
+pub mod subprocess;
+
+pub use call::{call, call_with_progress, ProgressFn};
 
 use pyo3::prelude::*;
-use std::path::PathBuf;
-use std::sync::OnceLock;
-
-static PYTHON_INITIALIZED: OnceLock<bool> = OnceLock::new();
-
-/// Initialize the Python interpreter and add the astra_astro module to the path
-pub fn init_python(python_path: Option<PathBuf>) -> PyResult<()> {
-    PYTHON_INITIALIZED.get_or_init(|| {
-        Python::with_gil(|py| {
-            // Add our Python module to the path
-            let sys = py.import("sys")?;
-            let path: Bound<'_, pyo3::types::PyList> = sys.getattr("path")?.downcast_into()?;
-
-            if let Some(ref p) = python_path {
-                // Add the module path
-                path.insert(0, p.to_string_lossy().to_string())?;
-
-                // Also add the venv's site-packages if it exists
-                // This ensures dependencies like starplot are available
-                // Try Python 3.12 first (matches PyO3's linked Python)
-                let venv_site_packages_312 = p.join(".venv/lib/python3.12/site-packages");
-                if venv_site_packages_312.exists() {
-                    path.insert(0, venv_site_packages_312.to_string_lossy().to_string())?;
-                    log::info!("Added venv site-packages to Python path: {:?}", venv_site_packages_312);
-                }
-
-                // Try Python 3.14 as fallback
-                let venv_site_packages = p.join(".venv/lib/python3.14/site-packages");
-                if venv_site_packages.exists() {
-                    path.insert(0, venv_site_packages.to_string_lossy().to_string())?;
-                    log::info!("Added venv site-packages to Python path: {:?}", venv_site_packages);
-                }
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Python environment state, kept so the interpreter can be re-pointed at a
+/// different virtualenv without restarting
+struct PythonEnv {
+    /// Directory containing the astra_astro package
+    module_path: Option<PathBuf>,
+    /// Entries we inserted into sys.path (removed again on re-initialization)
+    added_paths: Vec<String>,
+    /// Whether bridge calls go to the out-of-process server
+    use_subprocess: bool,
+    status: Option<PythonStatus>,
+}
+
+static PYTHON_ENV: Mutex<PythonEnv> = Mutex::new(PythonEnv {
+    module_path: None,
+    added_paths: Vec::new(),
+    use_subprocess: false,
+    status: None,
+});
+
+/// How astra_astro is run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PythonBackend {
+    /// Embedded interpreter, falling back to the subprocess if astra_astro
+    /// can't be loaded in it
+    #[default]
+    Auto,
+    /// Always use the embedded (PyO3) interpreter
+    Embedded,
+    /// Always run `python -m astra_astro.server` and call it over JSON-RPC
+    Subprocess,
+}
+
+/// User configuration for the Python environment
+#[derive(Debug, Clone, Default)]
+pub struct PythonConfig {
+    /// Virtualenv root or site-packages directory to use instead of discovery
+    pub venv_path: Option<PathBuf>,
+    pub backend: PythonBackend,
+    /// Interpreter for the subprocess backend (default: the venv's python)
+    pub python_executable: Option<PathBuf>,
+}
+
+/// Outcome of the last Python initialization, for the settings UI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PythonStatus {
+    /// Version of the linked interpreter, e.g. "3.12.4"
+    pub python_version: Option<String>,
+    pub module_path: Option<String>,
+    /// Virtualenv site-packages added to sys.path, if one was found
+    pub site_packages: Option<String>,
+    /// Whether astra_astro imported successfully
+    pub module_loaded: bool,
+    /// Backend the bridge functions are using (embedded or subprocess)
+    pub backend: PythonBackend,
+    /// Interpreter running the server, when using the subprocess backend
+    pub python_executable: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Find the site-packages directory of a virtualenv.
+///
+/// Handles the POSIX layout (`lib/python3.X/site-packages`, also `lib64`) and
+/// the Windows one (`Scripts/` + `Lib/site-packages`). When several Python
+/// versions are present, the one matching the linked interpreter wins, then
+/// the newest.
+pub fn venv_site_packages(venv: &Path, version: Option<(u8, u8)>) -> Option<PathBuf> {
+    let windows = venv.join("Lib").join("site-packages");
+    if venv.join("Scripts").is_dir() && windows.is_dir() {
+        return Some(windows);
+    }
+
+    let mut found: Vec<(u8, PathBuf)> = ["lib", "lib64"]
+        .iter()
+        .filter_map(|lib| std::fs::read_dir(venv.join(lib)).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let minor = name.strip_prefix("python3.")?.parse::<u8>().ok()?;
+            let site = entry.path().join("site-packages");
+            site.is_dir().then_some((minor, site))
+        })
+        .collect();
+    found.sort_by_key(|(minor, _)| std::cmp::Reverse(*minor));
+
+    match version {
+        Some((3, linked)) => found
+            .iter()
+            .position(|(minor, _)| *minor == linked)
+            .map(|i| found.swap_remove(i).1)
+            .or_else(|| found.into_iter().next().map(|(_, p)| p)),
+        _ => found.into_iter().next().map(|(_, p)| p),
+    }
+}
+
+/// Locate the virtualenv site-packages to use, in order of preference:
+/// the configured path (a venv root or a site-packages directory), the
+/// active `VIRTUAL_ENV`, then `.venv`/`venv` next to the astra_astro module.
+pub fn discover_site_packages(
+    module_path: Option<&Path>,
+    configured: Option<&Path>,
+    virtual_env: Option<&Path>,
+    version: Option<(u8, u8)>,
+) -> Option<PathBuf> {
+    if let Some(dir) = configured {
+        if dir.file_name().is_some_and(|n| n == "site-packages") && dir.is_dir() {
+            return Some(dir.to_path_buf());
+        }
+    }
+
+    let module_venvs = module_path
+        .into_iter()
+        .flat_map(|p| [p.join(".venv"), p.join("venv")]);
+    configured
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(virtual_env.map(Path::to_path_buf))
+        .chain(module_venvs)
+        .find_map(|venv| venv_site_packages(&venv, version))
+}
+
+/// Point the embedded interpreter at the astra_astro module and its
+/// virtualenv, returning the sys.path entries it added
+fn init_embedded(
+    old_paths: &[String],
+    module_path: Option<&Path>,
+    venv_path: Option<&Path>,
+    status: &mut PythonStatus,
+) -> Vec<String> {
+    let mut added_paths = Vec::new();
+
+    let result = Python::with_gil(|py| {
+        let info = py.version_info();
+        status.python_version = Some(format!("{}.{}.{}", info.major, info.minor, info.patch));
+
+        let sys = py.import("sys")?;
+        let path: Bound<'_, pyo3::types::PyList> = sys.getattr("path")?.downcast_into()?;
+
+        // Drop entries from a previous initialization
+        for old in old_paths {
+            while path.contains(old)? {
+                path.call_method1("remove", (old,))?;
             }
+        }
+
+        if let Some(p) = module_path {
+            let entry = p.to_string_lossy().to_string();
+            path.insert(0, &entry)?;
+            added_paths.push(entry);
+        }
 
-            // Try to import our module to verify it's accessible
-            match py.import("astra_astro") {
-                Ok(_) => {
-                    log::info!("Python astra_astro module loaded successfully");
-                }
-                Err(e) => {
-                    log::warn!("Could not load astra_astro module: {}", e);
-                    // Don't fail - the module might not be installed yet
-                }
+        // Add the venv's site-packages so dependencies like starplot are available
+        let virtual_env = std::env::var_os("VIRTUAL_ENV").map(PathBuf::from);
+        let site_packages = discover_site_packages(
+            module_path,
+            venv_path,
+            virtual_env.as_deref(),
+            Some((info.major, info.minor)),
+        );
+        match site_packages {
+            Some(site) => {
+                let entry = site.to_string_lossy().to_string();
+                path.insert(0, &entry)?;
+                log::info!("Added venv site-packages to Python path: {}", entry);
+                status.site_packages = Some(entry.clone());
+                added_paths.push(entry);
             }
+            None => log::info!("No virtualenv site-packages found for Python {}.{}", info.major, info.minor),
+        }
 
-            Ok::<(), PyErr>(())
-        }).map_err(|e| {
-            log::error!("Failed to initialize Python: {}", e);
-            e
-        }).ok();
+        py.import("importlib")?.call_method0("invalidate_caches")?;
+
+        // Try to import our module to verify it's accessible
+        match py.import("astra_astro") {
+            Ok(_) => {
+                log::info!("Python astra_astro module loaded successfully");
+                status.module_loaded = true;
+            }
+            Err(e) => {
+                log::warn!("Could not load astra_astro module: {}", e);
+                // Don't fail - the module might not be installed yet
+                status.error = Some(format!("Could not load astra_astro: {}", e));
+            }
+        }
 
-        true
+        Ok::<(), PyErr>(())
     });
 
-    Ok(())
+    if let Err(e) = result {
+        log::error!("Failed to initialize Python: {}", e);
+        status.error = Some(e.to_string());
+    }
+
+    added_paths
+}
+
+/// Start the out-of-process server and check that astra_astro loads in it
+fn init_subprocess(module_path: Option<&Path>, config: &PythonConfig, status: &mut PythonStatus) {
+    let virtual_env = std::env::var_os("VIRTUAL_ENV").map(PathBuf::from);
+    let executable = subprocess::python_executable(
+        config.python_executable.as_deref(),
+        config.venv_path.as_deref(),
+        virtual_env.as_deref(),
+        module_path,
+    );
+    status.python_executable = Some(executable.to_string_lossy().to_string());
+    subprocess::configure(executable, module_path.map(Path::to_path_buf));
+
+    let embedded_error = status.error.take();
+    match subprocess::call("ping", serde_json::json!({})) {
+        Ok(info) => {
+            if let Some(e) = embedded_error {
+                log::warn!("Falling back to the Python subprocess backend: {}", e);
+            }
+            log::info!("Python astra_astro server started");
+            status.python_version = info
+                .get("pythonVersion")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            status.site_packages = None;
+            status.module_loaded = true;
+        }
+        Err(e) => {
+            log::warn!("Could not start the Python subprocess backend: {}", e);
+            status.module_loaded = false;
+            status.error = Some(match embedded_error {
+                Some(prev) => format!("{}; subprocess fallback failed: {}", prev, e),
+                None => e,
+            });
+        }
+    }
+}
+
+/// Initialize the configured Python backend.
+///
+/// The embedded interpreter is pointed at the astra_astro module and its
+/// virtualenv; in `Auto` mode a failure to import astra_astro there switches
+/// the bridge functions to the subprocess server. Safe to call again (e.g.
+/// after the user fixes their environment): paths added by the previous
+/// call are removed first and import caches are reset.
+pub fn init_python(module_path: Option<PathBuf>, config: &PythonConfig) -> PythonStatus {
+    let mut env = PYTHON_ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let mut status = PythonStatus {
+        module_path: module_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+
+    let use_subprocess = match config.backend {
+        PythonBackend::Subprocess => true,
+        PythonBackend::Auto | PythonBackend::Embedded => {
+            env.added_paths = init_embedded(
+                &env.added_paths,
+                module_path.as_deref(),
+                config.venv_path.as_deref(),
+                &mut status,
+            );
+            config.backend == PythonBackend::Auto && !status.module_loaded
+        }
+    };
+
+    if use_subprocess {
+        init_subprocess(module_path.as_deref(), config, &mut status);
+        status.backend = PythonBackend::Subprocess;
+    } else {
+        subprocess::shutdown();
+        status.backend = PythonBackend::Embedded;
+    }
+
+    env.module_path = module_path;
+    env.use_subprocess = use_subprocess;
+    env.status = Some(status.clone());
+    status
+}
+
+/// Re-run initialization with the module path from startup and new settings
+pub fn reinit_python(config: &PythonConfig) -> PythonStatus {
+    let module_path = PYTHON_ENV
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .module_path
+        .clone();
+    init_python(module_path, config)
+}
+
+/// Whether bridge calls should go to the subprocess server
+pub(crate) fn use_subprocess() -> bool {
+    PYTHON_ENV
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .use_subprocess
+}
+
+/// Status of the last initialization
+pub fn python_status() -> PythonStatus {
+    PYTHON_ENV
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .status
+        .clone()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn make_site(root: &Path, rel: &str) -> PathBuf {
+        let site = root.join(rel);
+        std::fs::create_dir_all(&site).unwrap();
+        site
+    }
+
+    #[test]
+    fn venv_prefers_linked_python_version() {
+        let dir = TempDir::new().unwrap();
+        make_site(dir.path(), "lib/python3.11/site-packages");
+        let linked = make_site(dir.path(), "lib/python3.12/site-packages");
+        let newest = make_site(dir.path(), "lib/python3.14/site-packages");
+
+        assert_eq!(venv_site_packages(dir.path(), Some((3, 12))), Some(linked));
+        assert_eq!(venv_site_packages(dir.path(), Some((3, 13))), Some(newest));
+    }
+
+    #[test]
+    fn venv_supports_windows_layout() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("Scripts")).unwrap();
+        let site = make_site(dir.path(), "Lib/site-packages");
+        assert_eq!(venv_site_packages(dir.path(), Some((3, 12))), Some(site));
+    }
+
+    #[test]
+    fn discovery_order() {
+        let module = TempDir::new().unwrap();
+        let active = TempDir::new().unwrap();
+        let module_site = make_site(module.path(), ".venv/lib/python3.12/site-packages");
+        let active_site = make_site(active.path(), "lib/python3.12/site-packages");
+
+        let found = |configured: Option<&Path>, virtual_env: Option<&Path>| {
+            discover_site_packages(Some(module.path()), configured, virtual_env, Some((3, 12)))
+        };
+        assert_eq!(found(None, None), Some(module_site.clone()));
+        assert_eq!(found(None, Some(active.path())), Some(active_site.clone()));
+        // A configured site-packages directory is used as-is
+        assert_eq!(found(Some(&active_site), None), Some(active_site));
+        // A configured path without a venv falls through to the others
+        assert_eq!(found(Some(Path::new("/nonexistent")), None), Some(module_site));
+    }
 }
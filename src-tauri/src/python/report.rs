@@ -0,0 +1,38 @@
+//! Session report PDF bridge
+//!
+//! Lays out report blocks (the same ones rendered to Markdown) onto A4
+//! pages using the Python renderer.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Result from rendering a report PDF
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportPdfResult {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Render `blocks` to a PDF at `output_path`; relative image paths are
+/// resolved against `base_dir`
+pub fn render_report_pdf(
+    output_path: &str,
+    blocks: &serde_json::Value,
+    base_dir: &str,
+) -> Result<ReportPdfResult, String> {
+    super::call(
+        "render_report_pdf",
+        json!({
+            "output_path": output_path,
+            "blocks": blocks,
+            "base_dir": base_dir,
+        }),
+    )
+    .map_err(|e| format!("Report PDF failed: {}", e))
+}
@@ -0,0 +1,301 @@
+//! Out-of-process Python backend
+//!
+//! Runs `python -m astra_astro.server` and talks line-delimited JSON-RPC with
+//! it over stdin/stdout. Used instead of the embedded interpreter when that
+//! one can't load astra_astro, so the bridge functions keep working against
+//! whichever Python has the package's dependencies installed.
+
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// A running server process
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// Last line the server wrote to stderr, to explain an unexpected exit
+    last_stderr: Arc<Mutex<Option<String>>>,
+    stderr_reader: Option<JoinHandle<()>>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct Backend {
+    executable: Option<PathBuf>,
+    module_path: Option<PathBuf>,
+    server: Option<Server>,
+    next_id: u64,
+}
+
+static BACKEND: Mutex<Backend> = Mutex::new(Backend {
+    executable: None,
+    module_path: None,
+    server: None,
+    next_id: 1,
+});
+
+/// Python executable inside a virtualenv root, if there is one
+fn venv_python(venv: &Path) -> Option<PathBuf> {
+    ["bin/python3", "bin/python", "Scripts/python.exe"]
+        .iter()
+        .map(|rel| venv.join(rel))
+        .find(|p| p.is_file())
+}
+
+/// Choose the interpreter for the server, in order of preference: the
+/// configured executable, the configured virtualenv (a site-packages path
+/// is walked back up to its venv root), the active `VIRTUAL_ENV`, then
+/// `.venv`/`venv` next to the astra_astro module. Falls back to the
+/// `python3` (`python` on Windows) on PATH.
+pub fn python_executable(
+    configured: Option<&Path>,
+    venv_path: Option<&Path>,
+    virtual_env: Option<&Path>,
+    module_path: Option<&Path>,
+) -> PathBuf {
+    if let Some(exe) = configured {
+        return exe.to_path_buf();
+    }
+
+    let module_venvs = module_path
+        .into_iter()
+        .flat_map(|p| [p.join(".venv"), p.join("venv")]);
+    venv_path
+        .into_iter()
+        .flat_map(|p| p.ancestors().take(4).map(Path::to_path_buf))
+        .chain(virtual_env.map(Path::to_path_buf))
+        .chain(module_venvs)
+        .find_map(|venv| venv_python(&venv))
+        .unwrap_or_else(|| PathBuf::from(if cfg!(windows) { "python" } else { "python3" }))
+}
+
+/// Set the interpreter and module directory used to start the server.
+/// A running server is stopped if either changed.
+pub fn configure(executable: PathBuf, module_path: Option<PathBuf>) {
+    let mut backend = BACKEND.lock().unwrap_or_else(|e| e.into_inner());
+    if backend.executable.as_ref() != Some(&executable) || backend.module_path != module_path {
+        backend.server = None;
+    }
+    backend.executable = Some(executable);
+    backend.module_path = module_path;
+}
+
+/// Stop the server process, if running
+pub fn shutdown() {
+    BACKEND.lock().unwrap_or_else(|e| e.into_inner()).server = None;
+}
+
+fn spawn(executable: &Path, module_path: Option<&Path>) -> Result<Server, String> {
+    let mut command = Command::new(executable);
+    command
+        .args(["-m", "astra_astro.server"])
+        .env("PYTHONUNBUFFERED", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(dir) = module_path {
+        let mut paths = vec![dir.to_path_buf()];
+        if let Some(existing) = std::env::var_os("PYTHONPATH") {
+            paths.extend(std::env::split_paths(&existing));
+        }
+        let joined =
+            std::env::join_paths(paths).map_err(|e| format!("Invalid PYTHONPATH: {}", e))?;
+        command.env("PYTHONPATH", joined);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", executable.display(), e))?;
+    let stdin = child.stdin.take().ok_or("Failed to open server stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to open server stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to open server stderr")?;
+
+    // Forward tracebacks and library warnings to our log
+    let last_stderr = Arc::new(Mutex::new(None));
+    let last = Arc::clone(&last_stderr);
+    let stderr_reader = std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            log::debug!("[python] {}", line);
+            if !line.trim().is_empty() {
+                *last.lock().unwrap_or_else(|e| e.into_inner()) = Some(line);
+            }
+        }
+    });
+
+    log::info!("Started Python server with {}", executable.display());
+    Ok(Server {
+        child,
+        stdin,
+        stdout: BufReader::new(stdout),
+        last_stderr,
+        stderr_reader: Some(stderr_reader),
+    })
+}
+
+/// Why a request failed: the server couldn't be reached (it may be retried
+/// on a fresh process) or it answered with an error
+enum CallError {
+    Transport(String),
+    Remote(String),
+}
+
+fn request(
+    server: &mut Server,
+    id: u64,
+    method: &str,
+    params: &Value,
+    on_progress: &mut dyn FnMut(Value),
+) -> Result<Value, CallError> {
+    let line = serde_json::json!({ "id": id, "method": method, "params": params });
+    writeln!(server.stdin, "{}", line)
+        .and_then(|_| server.stdin.flush())
+        .map_err(|e| CallError::Transport(format!("Failed to send request: {}", e)))?;
+
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        let read = server
+            .stdout
+            .read_line(&mut buf)
+            .map_err(|e| CallError::Remote(format!("Failed to read response: {}", e)))?;
+        if read == 0 {
+            // Exited mid-request; don't retry something that may have crashed it
+            let _ = server.child.wait();
+            if let Some(reader) = server.stderr_reader.take() {
+                let _ = reader.join();
+            }
+            let reason = server
+                .last_stderr
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+            return Err(CallError::Remote(match reason {
+                Some(reason) => format!("Python server exited: {}", reason),
+                None => "Python server exited".to_string(),
+            }));
+        }
+
+        let message: Value = match serde_json::from_str(buf.trim()) {
+            Ok(message) => message,
+            Err(_) => {
+                log::debug!(
+                    "Ignoring non-protocol output from Python server: {}",
+                    buf.trim()
+                );
+                continue;
+            }
+        };
+        if message.get("id").and_then(Value::as_u64) != Some(id) {
+            continue;
+        }
+        if let Some(progress) = message.get("progress") {
+            on_progress(progress.clone());
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            let error = error
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(CallError::Remote(error));
+        }
+        return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+    }
+}
+
+/// Call a server method, reporting progress messages as they arrive.
+///
+/// The server is started on first use and restarted once if the previous
+/// process has gone away.
+pub fn call_with_progress(
+    method: &str,
+    params: Value,
+    on_progress: &mut dyn FnMut(Value),
+) -> Result<Value, String> {
+    let mut backend = BACKEND.lock().unwrap_or_else(|e| e.into_inner());
+    let executable = backend
+        .executable
+        .clone()
+        .ok_or("Python subprocess backend is not configured")?;
+
+    let mut restarted = false;
+    loop {
+        if backend.server.is_none() {
+            backend.server = Some(spawn(&executable, backend.module_path.as_deref())?);
+        }
+        let id = backend.next_id;
+        backend.next_id += 1;
+
+        let server = backend.server.as_mut().ok_or("Python server not running")?;
+        match request(server, id, method, &params, on_progress) {
+            Ok(result) => return Ok(result),
+            Err(CallError::Transport(e)) if !restarted => {
+                log::warn!("Python server unavailable ({}), restarting", e);
+                backend.server = None;
+                restarted = true;
+            }
+            Err(CallError::Transport(e)) => {
+                backend.server = None;
+                return Err(e);
+            }
+            Err(CallError::Remote(e)) => {
+                if server.child.try_wait().ok().flatten().is_some() {
+                    backend.server = None;
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Call a server method and return its raw result
+pub fn call(method: &str, params: Value) -> Result<Value, String> {
+    call_with_progress(method, params, &mut |_| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn make_python(venv: &Path) -> PathBuf {
+        let exe = venv.join("bin/python3");
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::write(&exe, "").unwrap();
+        exe
+    }
+
+    #[test]
+    fn executable_discovery_order() {
+        let module = TempDir::new().unwrap();
+        let configured = TempDir::new().unwrap();
+        let module_python = make_python(&module.path().join(".venv"));
+        let configured_python = make_python(configured.path());
+
+        let found = |venv: Option<&Path>| python_executable(None, venv, None, Some(module.path()));
+        assert_eq!(found(None), module_python);
+        assert_eq!(found(Some(configured.path())), configured_python);
+
+        // A configured site-packages path resolves to its venv's interpreter
+        let site = configured.path().join("lib/python3.12/site-packages");
+        std::fs::create_dir_all(&site).unwrap();
+        assert_eq!(found(Some(&site)), configured_python);
+
+        // An explicit executable always wins
+        let explicit = Path::new("/opt/python/bin/python3.12");
+        assert_eq!(
+            python_executable(Some(explicit), Some(configured.path()), None, None),
+            explicit
+        );
+    }
+}
@@ -19,7 +19,7 @@ pub struct ShareUploadConfig {
     pub public_url_base: String,
 }
 
-const CONFIG_FILENAME: &str = "share-config.json";
+const CONFIG_FILENAME: &str = crate::commands::settings::SHARE_CONFIG.name;
 
 /// Load share config from app data directory.
 pub fn load_config(data_dir: &Path) -> Result<Option<ShareUploadConfig>, String> {
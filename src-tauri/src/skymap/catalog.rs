@@ -0,0 +1,211 @@
+//! Embedded catalogs for the skymap renderer: a bright-star subset,
+//! constellation stick figures and the Messier objects, plus the frontend's
+//! deep sky catalogs for object search.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+const STARS_CSV: &str = include_str!("stars.csv");
+const CONSTELLATIONS: &str = include_str!("constellations.txt");
+/// Shared with the frontend's catalog browser
+const MESSIER_JSON: &str = include_str!("../../../public/catalogs/Messier.json");
+/// The rest of the catalog browser's catalogs
+const CATALOG_JSON: [&str; 6] = [
+    include_str!("../../../public/catalogs/OpenNGC.json"),
+    include_str!("../../../public/catalogs/OpenIC.json"),
+    include_str!("../../../public/catalogs/Sharpless.json"),
+    include_str!("../../../public/catalogs/Barnard.json"),
+    include_str!("../../../public/catalogs/LDN.json"),
+    include_str!("../../../public/catalogs/LBN.json"),
+];
+
+/// A star, with J2000 coordinates in degrees
+#[derive(Debug, Clone, PartialEq)]
+pub struct Star {
+    pub hip: u32,
+    pub ra: f64,
+    pub dec: f64,
+    pub magnitude: f64,
+}
+
+/// A deep sky object marker
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepSkyObject {
+    pub name: String,
+    pub ra: f64,
+    pub dec: f64,
+    pub size_arcmin: Option<f64>,
+    pub object_type: Option<String>,
+    /// Common names and cross-identifications, e.g. "Orion Nebula"
+    pub common_names: Vec<String>,
+}
+
+/// One line of a constellation figure, as (RA, Dec) endpoints in degrees
+pub type Segment = ((f64, f64), (f64, f64));
+
+/// Lines of `text` with comments and blank lines dropped
+fn data_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn parse_stars(text: &str) -> Vec<Star> {
+    data_lines(text)
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            Some(Star {
+                hip: fields.next()?.parse().ok()?,
+                ra: fields.next()?.parse().ok()?,
+                dec: fields.next()?.parse().ok()?,
+                magnitude: fields.next()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Constellation figures as segments between stars. Stars missing from
+/// `stars` are skipped along with their lines.
+fn parse_constellations(text: &str, stars: &[Star]) -> Vec<Segment> {
+    let by_hip: HashMap<u32, (f64, f64)> = stars.iter().map(|s| (s.hip, (s.ra, s.dec))).collect();
+    let mut segments = Vec::new();
+    for line in data_lines(text) {
+        let Some((_, figure)) = line.split_once(':') else {
+            continue;
+        };
+        let points: Vec<Option<(f64, f64)>> = figure
+            .split_whitespace()
+            .map(|hip| hip.parse().ok().and_then(|hip| by_hip.get(&hip).copied()))
+            .collect();
+        segments.extend(
+            points
+                .windows(2)
+                .filter_map(|pair| Some((pair[0]?, pair[1]?))),
+        );
+    }
+    segments
+}
+
+/// Objects from a catalog file in the frontend's format, whose rows start
+/// with name, RA (hours), Dec and type, with comma-separated common names at
+/// index 7 and the size in arcmin at index 9
+fn parse_catalog(json: &str) -> Vec<DeepSkyObject> {
+    let Ok(catalog) = serde_json::from_str::<serde_json::Value>(json) else {
+        return Vec::new();
+    };
+    let Some(rows) = catalog.get("data").and_then(|d| d.as_array()) else {
+        return Vec::new();
+    };
+    rows.iter()
+        .filter_map(|row| {
+            Some(DeepSkyObject {
+                name: row.get(0)?.as_str()?.to_string(),
+                ra: row.get(1)?.as_f64()? * 15.0,
+                dec: row.get(2)?.as_f64()?,
+                size_arcmin: row.get(9).and_then(|s| s.as_f64()).filter(|s| *s > 0.0),
+                object_type: row
+                    .get(3)
+                    .and_then(|t| t.as_str())
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string),
+                common_names: row
+                    .get(7)
+                    .and_then(|n| n.as_str())
+                    .map(|names| {
+                        names
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|n| !n.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Embedded bright stars, brightest first
+pub fn stars() -> &'static [Star] {
+    static STARS: OnceLock<Vec<Star>> = OnceLock::new();
+    STARS.get_or_init(|| {
+        let mut stars = parse_stars(STARS_CSV);
+        stars.sort_by(|a, b| a.magnitude.total_cmp(&b.magnitude));
+        stars
+    })
+}
+
+/// Segments of all constellation figures
+pub fn constellation_lines() -> &'static [Segment] {
+    static LINES: OnceLock<Vec<Segment>> = OnceLock::new();
+    LINES.get_or_init(|| parse_constellations(CONSTELLATIONS, stars()))
+}
+
+/// The Messier objects
+pub fn messier() -> &'static [DeepSkyObject] {
+    static MESSIER: OnceLock<Vec<DeepSkyObject>> = OnceLock::new();
+    MESSIER.get_or_init(|| parse_catalog(MESSIER_JSON))
+}
+
+/// Every embedded deep sky catalog, Messier first. Parsed on first use.
+pub fn deep_sky_objects() -> &'static [DeepSkyObject] {
+    static OBJECTS: OnceLock<Vec<DeepSkyObject>> = OnceLock::new();
+    OBJECTS.get_or_init(|| {
+        let mut objects = messier().to_vec();
+        for json in CATALOG_JSON {
+            objects.extend(parse_catalog(json));
+        }
+        objects
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_catalogs_load() {
+        let stars = stars();
+        assert!(stars.len() > 150);
+        assert_eq!(stars[0].hip, 32349, "Sirius is the brightest");
+        assert!(stars
+            .iter()
+            .all(|s| (0.0..360.0).contains(&s.ra) && s.dec.abs() <= 90.0));
+
+        let m42 = messier().iter().find(|o| o.name == "M 42").unwrap();
+        assert!((m42.ra - 83.8).abs() < 0.1 && (m42.dec + 5.4).abs() < 0.1);
+        assert_eq!(messier().len(), 110);
+
+        let orion = deep_sky_objects()
+            .iter()
+            .find(|o| o.name == "NGC 1976")
+            .unwrap();
+        assert_eq!(orion.common_names, ["Orion Nebula", "M42"]);
+        assert_eq!(orion.object_type.as_deref(), Some("Star cluster + Nebula"));
+        assert!(deep_sky_objects().len() > 10_000);
+    }
+
+    #[test]
+    fn every_figure_star_is_embedded() {
+        let stars = stars();
+        for line in data_lines(CONSTELLATIONS) {
+            let (name, figure) = line.split_once(':').unwrap();
+            for hip in figure.split_whitespace() {
+                let hip: u32 = hip.parse().unwrap();
+                assert!(stars.iter().any(|s| s.hip == hip), "{} star {}", name, hip);
+            }
+        }
+        assert!(!constellation_lines().is_empty());
+    }
+
+    #[test]
+    fn missing_figure_stars_drop_their_lines() {
+        let stars = parse_stars("# hip,ra,dec,mag\n1,10.0,20.0,2.0\n2,11.0,21.0,3.0\n");
+        let segments = parse_constellations("Xxx: 1 2 3\n", &stars);
+        assert_eq!(segments, [((10.0, 20.0), (11.0, 21.0))]);
+    }
+}
@@ -0,0 +1,209 @@
+//! Field maps as structured data, for charts drawn by the frontend.
+//!
+//! Everything carries both its sky position and its position on the map
+//! canvas, so the frontend can pan and zoom the canvas positions and show
+//! coordinates on hover without projecting anything itself.
+
+use serde::Serialize;
+
+use super::projection::Projection;
+use super::{
+    catalog, field_outline, object_radius, star_radius, FieldMap, FIELD_STAR_LIMIT, FIELD_STAR_SIZE,
+};
+
+/// Share of the canvas size kept beyond each edge, so panning doesn't
+/// uncover an empty border
+const MARGIN: f64 = 0.25;
+
+/// A position in degrees and canvas pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartPoint {
+    pub ra: f64,
+    pub dec: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartStar {
+    /// Hipparcos catalog number
+    pub hip: u32,
+    #[serde(flatten)]
+    pub position: ChartPoint,
+    pub magnitude: f64,
+    /// Suggested marker radius in pixels
+    pub radius: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartObject {
+    pub name: String,
+    #[serde(flatten)]
+    pub position: ChartPoint,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_arcmin: Option<f64>,
+    /// Marker radius in pixels: the object's size, or a minimum marker
+    pub radius: f64,
+}
+
+/// Contents of a field map
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartData {
+    /// Canvas size in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Map extent in degrees
+    pub fov_width: f64,
+    pub fov_height: f64,
+    /// Scale at the map center
+    pub pixels_per_degree: f64,
+    pub center: ChartPoint,
+    pub stars: Vec<ChartStar>,
+    pub objects: Vec<ChartObject>,
+    /// Constellation figure segments
+    pub lines: Vec<[ChartPoint; 2]>,
+    /// Closed outline of the image's field, empty when its size is unknown
+    pub field: Vec<ChartPoint>,
+}
+
+/// Projects sky positions, dropping those far outside the canvas
+struct Canvas {
+    projection: Projection,
+    width: f64,
+    height: f64,
+}
+
+impl Canvas {
+    fn point(&self, ra: f64, dec: f64) -> Option<ChartPoint> {
+        let (x, y) = self.projection.project(ra, dec)?;
+        Some(ChartPoint { ra, dec, x, y })
+    }
+
+    fn visible(&self, ra: f64, dec: f64) -> Option<ChartPoint> {
+        self.point(ra, dec).filter(|p| {
+            let (mx, my) = (self.width * MARGIN, self.height * MARGIN);
+            (-mx..=self.width + mx).contains(&p.x) && (-my..=self.height + my).contains(&p.y)
+        })
+    }
+}
+
+/// The stars, deep sky objects, constellation lines and image field that
+/// `render_field` draws for `map`
+pub fn field_chart(map: &FieldMap) -> ChartData {
+    let (fov_width, fov_height) = map.extent();
+    let projection = map.projection();
+    let pixels_per_degree = map.pixels_per_degree(&projection);
+    let canvas = Canvas {
+        projection,
+        width: map.width_px as f64,
+        height: map.height_px as f64,
+    };
+
+    let stars = catalog::stars()
+        .iter()
+        .filter(|star| star.magnitude <= FIELD_STAR_LIMIT)
+        .filter_map(|star| {
+            Some(ChartStar {
+                hip: star.hip,
+                position: canvas.visible(star.ra, star.dec)?,
+                magnitude: star.magnitude,
+                radius: star_radius(star.magnitude, FIELD_STAR_LIMIT, FIELD_STAR_SIZE),
+            })
+        })
+        .collect();
+    let objects = catalog::messier()
+        .iter()
+        .filter_map(|object| {
+            Some(ChartObject {
+                name: object.name.clone(),
+                position: canvas.visible(object.ra, object.dec)?,
+                size_arcmin: object.size_arcmin,
+                radius: object_radius(object, pixels_per_degree),
+            })
+        })
+        .collect();
+    // Lines with one end in view, drawn to the other end wherever it is
+    let lines = catalog::constellation_lines()
+        .iter()
+        .filter_map(|(from, to)| {
+            let ends = [canvas.point(from.0, from.1)?, canvas.point(to.0, to.1)?];
+            ends.iter()
+                .any(|p| canvas.visible(p.ra, p.dec).is_some())
+                .then_some(ends)
+        })
+        .collect();
+    let field = if map.image_width > 0.0 && map.image_height > 0.0 {
+        field_outline(
+            map.center_ra,
+            map.center_dec,
+            map.image_width,
+            map.image_height,
+            0.0,
+        )
+        .into_iter()
+        .filter_map(|(ra, dec)| canvas.point(ra, dec))
+        .collect()
+    } else {
+        Vec::new()
+    };
+
+    ChartData {
+        width: map.width_px,
+        height: map.height_px,
+        fov_width,
+        fov_height,
+        pixels_per_degree,
+        center: ChartPoint {
+            ra: map.center_ra,
+            dec: map.center_dec,
+            x: canvas.width / 2.0,
+            y: canvas.height / 2.0,
+        },
+        stars,
+        objects,
+        lines,
+        field,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chart_matches_the_rendered_field() {
+        let map = FieldMap {
+            fov_width: Some(40.0),
+            fov_height: Some(30.0),
+            image_width: 1.5,
+            image_height: 1.0,
+            ..FieldMap::new(83.82, -5.39)
+        };
+        let chart = field_chart(&map);
+        assert_eq!((chart.width, chart.height), (600, 450));
+        assert_eq!((chart.fov_width, chart.fov_height), (40.0, 30.0));
+
+        let m42 = chart.objects.iter().find(|o| o.name == "M 42").unwrap();
+        let (x, y) = map
+            .projection()
+            .project(m42.position.ra, m42.position.dec)
+            .unwrap();
+        assert_eq!((m42.position.x, m42.position.y), (x, y));
+        // Nothing from the other side of the sky
+        assert!(chart.objects.iter().all(|o| o.name != "M 31"));
+
+        let betelgeuse = chart.stars.iter().find(|s| s.hip == 27989).unwrap();
+        assert!(betelgeuse.position.x < 300.0, "east of the center is left");
+        assert!(betelgeuse.radius > 4.0);
+        assert!(!chart.lines.is_empty());
+        assert_eq!(chart.field.len(), 33);
+        assert_eq!(chart.field.first(), chart.field.last());
+
+        let json = serde_json::to_value(&chart).unwrap();
+        assert!(json["stars"][0]["ra"].is_f64() && json["pixelsPerDegree"].is_f64());
+    }
+}
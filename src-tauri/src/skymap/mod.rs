@@ -0,0 +1,568 @@
+//! Native skymap rendering.
+//!
+//! Replaces the Python/starplot skymaps, which pulled in matplotlib and
+//! often failed in packaged builds. Maps show bright stars, constellation
+//! figures and Messier objects from embedded catalogs, plus the image's
+//! field of view, and are written as PNG or SVG.
+
+mod catalog;
+mod chart;
+mod projection;
+mod scene;
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+pub use catalog::{deep_sky_objects, DeepSkyObject};
+pub use chart::{field_chart, ChartData};
+use projection::Projection;
+pub use scene::{Color, Scene};
+
+const BACKGROUND: Color = Color::rgb(0x0b1020);
+const GRID: Color = Color::rgb(0x334155);
+const CONSTELLATION: Color = Color::rgb(0x64748b);
+const STAR: Color = Color::rgb(0xf8fafc);
+const DEEP_SKY: Color = Color::rgb(0xa78bfa);
+const FIELD: Color = Color::rgb(0x14b8a6);
+const TARGET: Color = Color::rgb(0xf97316);
+
+/// Faintest star and star scale on field maps
+const FIELD_STAR_LIMIT: f64 = 5.0;
+const FIELD_STAR_SIZE: f64 = 0.8;
+
+/// Output format of a skymap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkymapFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl SkymapFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// A map of the region around an image
+#[derive(Debug, Clone)]
+pub struct FieldMap {
+    /// Image center in degrees
+    pub center_ra: f64,
+    pub center_dec: f64,
+    /// Map width in degrees (default: 5x the image, or 10°)
+    pub fov_width: Option<f64>,
+    /// Map height in degrees (default: 5x the image, or 3/4 of the width)
+    pub fov_height: Option<f64>,
+    /// Image field in degrees, outlined when both are non-zero
+    pub image_width: f64,
+    pub image_height: f64,
+    pub width_px: u32,
+    pub height_px: u32,
+}
+
+impl FieldMap {
+    pub fn new(center_ra: f64, center_dec: f64) -> Self {
+        Self {
+            center_ra,
+            center_dec,
+            fov_width: None,
+            fov_height: None,
+            image_width: 0.0,
+            image_height: 0.0,
+            width_px: 600,
+            height_px: 450,
+        }
+    }
+
+    /// Map extent in degrees, applying the defaults
+    fn extent(&self) -> (f64, f64) {
+        let width = self.fov_width.unwrap_or(if self.image_width > 0.0 {
+            self.image_width * 5.0
+        } else {
+            10.0
+        });
+        let height = self.fov_height.unwrap_or(if self.image_height > 0.0 {
+            self.image_height * 5.0
+        } else {
+            width * 0.75
+        });
+        (width, height)
+    }
+
+    fn projection(&self) -> Projection {
+        let (fov_width, fov_height) = self.extent();
+        Projection::stereographic(
+            self.center_ra,
+            self.center_dec,
+            fov_width,
+            fov_height,
+            self.width_px as f64,
+            self.height_px as f64,
+        )
+    }
+
+    /// Scale at the map center
+    fn pixels_per_degree(&self, projection: &Projection) -> f64 {
+        let center = projection.project(self.center_ra, self.center_dec);
+        let north = projection.project(self.center_ra, (self.center_dec + 0.1).min(90.0));
+        match (center, north) {
+            (Some(c), Some(n)) => (c.0 - n.0).hypot(c.1 - n.1) * 10.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Encode a rendered map as a data URL
+pub fn data_url(bytes: &[u8], format: SkymapFormat) -> String {
+    format!(
+        "data:{};base64,{}",
+        format.mime_type(),
+        BASE64.encode(bytes)
+    )
+}
+
+fn encode(scene: &Scene, format: SkymapFormat) -> Result<Vec<u8>, String> {
+    match format {
+        SkymapFormat::Png => scene.to_png(),
+        SkymapFormat::Svg => Ok(scene.to_svg().into_bytes()),
+    }
+}
+
+/// Draw a straight segment between two sky positions, unless either end
+/// can't be projected or it would wrap around the map
+fn sky_line(
+    scene: &mut Scene,
+    projection: &Projection,
+    from: (f64, f64),
+    to: (f64, f64),
+    color: Color,
+    width: f64,
+) {
+    let (Some(p), Some(q)) = (
+        projection.project(from.0, from.1),
+        projection.project(to.0, to.1),
+    ) else {
+        return;
+    };
+    if (p.0 - q.0).hypot(p.1 - q.1) <= projection.max_segment() {
+        scene.line(p, q, color, width);
+    }
+}
+
+/// Draw a polyline through sky positions
+fn sky_path(
+    scene: &mut Scene,
+    projection: &Projection,
+    points: &[(f64, f64)],
+    color: Color,
+    width: f64,
+) {
+    for pair in points.windows(2) {
+        sky_line(scene, projection, pair[0], pair[1], color, width);
+    }
+}
+
+fn draw_constellations(scene: &mut Scene, projection: &Projection, width: f64) {
+    for (from, to) in catalog::constellation_lines() {
+        sky_line(
+            scene,
+            projection,
+            *from,
+            *to,
+            CONSTELLATION.alpha(0.7),
+            width,
+        );
+    }
+}
+
+/// Pixel radius of a star of `magnitude` on a map showing stars down to
+/// `limit`
+fn star_radius(magnitude: f64, limit: f64, size: f64) -> f64 {
+    ((limit + 1.0 - magnitude) * size).clamp(0.5, 5.0)
+}
+
+/// Draw stars down to `limit`, sized by magnitude
+fn draw_stars(scene: &mut Scene, projection: &Projection, limit: f64, size: f64) {
+    for star in catalog::stars().iter().filter(|s| s.magnitude <= limit) {
+        if let Some(p) = projection.project(star.ra, star.dec) {
+            scene.disc(p, star_radius(star.magnitude, limit, size), STAR);
+        }
+    }
+}
+
+/// Tangent plane offsets in radians (east, north) around a position back
+/// to the sky, in degrees
+fn deproject(ra: f64, dec: f64, (xi, eta): (f64, f64)) -> (f64, f64) {
+    let dec0 = dec.to_radians();
+    let denom = dec0.cos() - eta * dec0.sin();
+    let ra = ra + xi.atan2(denom).to_degrees();
+    let dec = (dec0.sin() + eta * dec0.cos()).atan2(xi.hypot(denom));
+    (ra.rem_euclid(360.0), dec.to_degrees())
+}
+
+/// Tangent plane offsets in radians (east, north) of a sky position around
+/// a center, or None for the far hemisphere
+pub fn tangent_offsets(ra0: f64, dec0: f64, ra: f64, dec: f64) -> Option<(f64, f64)> {
+    let (ra0, dec0, ra, dec) = (
+        ra0.to_radians(),
+        dec0.to_radians(),
+        ra.to_radians(),
+        dec.to_radians(),
+    );
+    let d_ra = ra - ra0;
+    let cos_c = dec0.sin() * dec.sin() + dec0.cos() * dec.cos() * d_ra.cos();
+    if cos_c <= 0.0 {
+        return None;
+    }
+    let xi = dec.cos() * d_ra.sin() / cos_c;
+    let eta = (dec0.cos() * dec.sin() - dec0.sin() * dec.cos() * d_ra.cos()) / cos_c;
+    Some((xi, eta))
+}
+
+/// Corners of a `width` x `height` degree field centered on a position and
+/// turned `rotation` degrees east of north, with edges subdivided so they
+/// follow the projection
+fn field_outline(ra: f64, dec: f64, width: f64, height: f64, rotation: f64) -> Vec<(f64, f64)> {
+    const STEPS: usize = 8;
+    let (w, h) = (width.to_radians() / 2.0, height.to_radians() / 2.0);
+    let corners = [(w, h), (-w, h), (-w, -h), (w, -h), (w, h)];
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    corners
+        .windows(2)
+        .flat_map(|edge| {
+            let ((x0, y0), (x1, y1)) = (edge[0], edge[1]);
+            (0..STEPS).map(move |i| {
+                let t = i as f64 / STEPS as f64;
+                (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t)
+            })
+        })
+        .chain(std::iter::once(corners[0]))
+        .map(|(x, y)| deproject(ra, dec, (x * cos + y * sin, y * cos - x * sin)))
+        .collect()
+}
+
+/// Pixel radius of a deep sky object's marker: its true size, once that's
+/// visible
+fn object_radius(object: &catalog::DeepSkyObject, pixels_per_degree: f64) -> f64 {
+    (object.size_arcmin.unwrap_or(0.0) / 60.0 * pixels_per_degree / 2.0).max(4.0)
+}
+
+/// Render a map of the region around an image, with its field of view
+/// outlined and its center marked
+pub fn render_field(map: &FieldMap, format: SkymapFormat) -> Result<Vec<u8>, String> {
+    let projection = map.projection();
+    let mut scene = Scene::new(map.width_px, map.height_px, BACKGROUND);
+
+    draw_constellations(&mut scene, &projection, 1.2);
+
+    let center = projection.project(map.center_ra, map.center_dec);
+    let pixels_per_degree = map.pixels_per_degree(&projection);
+    for object in catalog::messier() {
+        let Some(p) = projection.project(object.ra, object.dec) else {
+            continue;
+        };
+        let radius = object_radius(object, pixels_per_degree);
+        scene.circle(p, radius, DEEP_SKY.alpha(0.8), 1.2);
+        scene.text(
+            (p.0 + radius + 3.0, p.1 + 4.0),
+            &object.name,
+            DEEP_SKY,
+            11.0,
+        );
+    }
+
+    draw_stars(&mut scene, &projection, FIELD_STAR_LIMIT, FIELD_STAR_SIZE);
+
+    if map.image_width > 0.0 && map.image_height > 0.0 {
+        let outline = field_outline(
+            map.center_ra,
+            map.center_dec,
+            map.image_width,
+            map.image_height,
+            0.0,
+        );
+        sky_path(&mut scene, &projection, &outline, FIELD.alpha(0.9), 2.0);
+    }
+    if let Some(center) = center {
+        scene.disc(center, 5.0, FIELD.alpha(0.9));
+        scene.text(
+            (center.0 + 9.0, center.1 - 9.0),
+            "Image Center",
+            FIELD,
+            12.0,
+        );
+    }
+
+    encode(&scene, format)
+}
+
+/// Whole-sky Mollweide map with a coordinate grid, constellations and the
+/// brightest stars
+fn all_sky(width: u32, height: u32) -> (Scene, Projection) {
+    let projection = Projection::mollweide(0.0, width as f64, height as f64, 4.0);
+    let mut scene = Scene::new(width, height, BACKGROUND);
+
+    // Parallels every 30° and meridians every 2h, then the sky's edge
+    let steps = |from: f64, to: f64, n: usize| {
+        (0..=n).map(move |i| from + (to - from) * i as f64 / n as f64)
+    };
+    for dec in [-60.0, -30.0, 0.0, 30.0, 60.0] {
+        let parallel: Vec<_> = steps(-179.999, 179.999, 72).map(|ra| (ra, dec)).collect();
+        sky_path(&mut scene, &projection, &parallel, GRID.alpha(0.6), 0.8);
+    }
+    for ra in steps(-150.0, 150.0, 10) {
+        let meridian: Vec<_> = steps(-90.0, 90.0, 36).map(|dec| (ra, dec)).collect();
+        sky_path(&mut scene, &projection, &meridian, GRID.alpha(0.6), 0.8);
+    }
+    for edge in [-179.999, 179.999] {
+        let meridian: Vec<_> = steps(-90.0, 90.0, 36).map(|dec| (edge, dec)).collect();
+        sky_path(&mut scene, &projection, &meridian, GRID, 1.0);
+    }
+
+    draw_constellations(&mut scene, &projection, 0.8);
+    draw_stars(&mut scene, &projection, 3.0, 0.5);
+    (scene, projection)
+}
+
+/// Render the whole sky with the target marked
+pub fn render_wide(
+    center_ra: f64,
+    center_dec: f64,
+    format: SkymapFormat,
+) -> Result<Vec<u8>, String> {
+    let (mut scene, projection) = all_sky(400, 200);
+    if let Some(target) = projection.project(center_ra, center_dec) {
+        scene.disc(target, 6.0, TARGET);
+        scene.text((target.0 + 9.0, target.1 - 8.0), "Target", TARGET, 11.0);
+    }
+
+    encode(&scene, format)
+}
+
+/// Where an image lies on the sky, for coverage maps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Footprint {
+    /// Field center in degrees
+    pub ra: f64,
+    pub dec: f64,
+    /// Field size in degrees; zero when only the position is known
+    pub width: f64,
+    pub height: f64,
+    /// Degrees east of north
+    pub rotation: f64,
+    /// Target name, labelled once per name (SVG only)
+    pub label: Option<String>,
+}
+
+impl Footprint {
+    fn has_size(&self) -> bool {
+        self.width > 0.0 && self.height > 0.0
+    }
+
+    fn contains(&self, ra: f64, dec: f64) -> bool {
+        let Some((xi, eta)) = tangent_offsets(self.ra, self.dec, ra, dec) else {
+            return false;
+        };
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (x, y) = (xi * cos - eta * sin, xi * sin + eta * cos);
+        x.abs() <= self.width.to_radians() / 2.0 && y.abs() <= self.height.to_radians() / 2.0
+    }
+}
+
+/// Fraction of the sky inside at least one footprint, sampled on a
+/// Fibonacci lattice of about 0.4 square degrees per point. Footprints
+/// without a size don't count.
+pub fn sky_coverage(footprints: &[Footprint]) -> f64 {
+    const POINTS: usize = 100_000;
+    let golden_angle = PI * (3.0 - 5f64.sqrt());
+    // Lattice point i is at sin(dec) = 1 - (2i + 1) / N
+    let index = |sin_dec: f64| ((1.0 - sin_dec) * POINTS as f64 - 1.0) / 2.0;
+
+    let mut covered = vec![false; POINTS];
+    for footprint in footprints.iter().filter(|f| f.has_size()) {
+        // Offsets bound the angular distance, so this covers the corners
+        let radius = footprint.width.hypot(footprint.height).to_radians() / 2.0 + 1e-3;
+        let dec = footprint.dec.to_radians();
+        let north = (dec + radius).min(FRAC_PI_2).sin();
+        let south = (dec - radius).max(-FRAC_PI_2).sin();
+        let first = index(north).floor().max(0.0) as usize;
+        let last = (index(south).ceil().max(0.0) as usize).min(POINTS - 1);
+        for (i, covered) in covered.iter_mut().enumerate().take(last + 1).skip(first) {
+            if *covered {
+                continue;
+            }
+            let dec = (1.0 - (2 * i + 1) as f64 / POINTS as f64)
+                .asin()
+                .to_degrees();
+            let ra = (golden_angle * i as f64).rem_euclid(TAU).to_degrees();
+            *covered = footprint.contains(ra, dec);
+        }
+    }
+    covered.iter().filter(|c| **c).count() as f64 / POINTS as f64
+}
+
+/// Render every footprint on a whole-sky map: solved fields as filled
+/// outlines, positions without a known size as dots
+pub fn render_coverage(footprints: &[Footprint], format: SkymapFormat) -> Result<Vec<u8>, String> {
+    let (mut scene, projection) = all_sky(800, 400);
+    let mut labelled = std::collections::HashSet::new();
+
+    for footprint in footprints {
+        let Some(center) = projection.project(footprint.ra, footprint.dec) else {
+            continue;
+        };
+        let outline: Option<Vec<(f64, f64)>> = footprint
+            .has_size()
+            .then(|| {
+                field_outline(
+                    footprint.ra,
+                    footprint.dec,
+                    footprint.width,
+                    footprint.height,
+                    footprint.rotation,
+                )
+                .iter()
+                .map(|(ra, dec)| projection.project(*ra, *dec))
+                .collect()
+            })
+            .flatten();
+        // Fields too small to see, or split by the edge of the map, are dots
+        let drawable = outline.filter(|points| {
+            let (min_x, max_x) = points
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+                    (lo.min(p.0), hi.max(p.0))
+                });
+            max_x - min_x >= 2.5 && max_x - min_x <= projection.max_segment()
+        });
+        match (drawable, footprint.has_size()) {
+            (Some(points), _) => {
+                scene.polygon(&points, FIELD.alpha(0.45));
+                for pair in points.windows(2) {
+                    scene.line(pair[0], pair[1], FIELD.alpha(0.9), 0.8);
+                }
+            }
+            (None, true) => scene.disc(center, 1.5, FIELD.alpha(0.9)),
+            (None, false) => scene.disc(center, 2.0, TARGET.alpha(0.8)),
+        }
+
+        if let Some(label) = &footprint.label {
+            if labelled.insert(label.as_str()) {
+                scene.text(
+                    (center.0 + 4.0, center.1 - 4.0),
+                    label,
+                    STAR.alpha(0.8),
+                    9.0,
+                );
+            }
+        }
+    }
+
+    encode(&scene, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_map_renders_png_and_svg() {
+        let map = FieldMap {
+            fov_width: Some(40.0),
+            fov_height: Some(30.0),
+            image_width: 1.5,
+            image_height: 1.0,
+            ..FieldMap::new(83.82, -5.39)
+        };
+        let png = render_field(&map, SkymapFormat::Png).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (600, 450));
+
+        let svg = String::from_utf8(render_field(&map, SkymapFormat::Svg).unwrap()).unwrap();
+        assert!(svg.contains(">M 42<") && svg.contains(">Image Center<"));
+        // Orion's brighter stars are drawn
+        assert!(svg.matches("<circle").count() > 10);
+    }
+
+    #[test]
+    fn field_outline_is_centered_on_the_image() {
+        let outline = field_outline(359.5, 0.0, 2.0, 1.0, 0.0);
+        assert_eq!(outline.len(), 33);
+        assert_eq!(outline.first(), outline.last());
+        let (ra, dec) = outline[0];
+        // First corner is north-east, across RA 0
+        assert!(
+            (ra - 0.5).abs() < 1e-3 && (dec - 0.5).abs() < 1e-3,
+            "{} {}",
+            ra,
+            dec
+        );
+    }
+
+    #[test]
+    fn wide_map_and_data_url() {
+        let png = render_wide(83.82, -5.39, SkymapFormat::Png).unwrap();
+        let url = data_url(&png, SkymapFormat::Png);
+        assert!(url.starts_with("data:image/png;base64,iVBOR"));
+        let svg = render_wide(83.82, -5.39, SkymapFormat::Svg).unwrap();
+        assert!(data_url(&svg, SkymapFormat::Svg).starts_with("data:image/svg+xml;base64,"));
+    }
+
+    #[test]
+    fn rotated_footprints_contain_their_field() {
+        let footprint = Footprint {
+            ra: 10.0,
+            dec: 40.0,
+            width: 2.0,
+            height: 0.5,
+            rotation: 90.0,
+            label: None,
+        };
+        assert!(footprint.contains(10.0, 40.0));
+        // Turned a quarter, the long side runs north-south
+        assert!(footprint.contains(10.0, 40.9));
+        assert!(!footprint.contains(10.0 + 0.9 / 40f64.to_radians().cos(), 40.0));
+        for (ra, dec) in field_outline(10.0, 40.0, 2.0, 0.5, 90.0) {
+            let (xi, eta) = tangent_offsets(10.0, 40.0, ra, dec).unwrap();
+            assert!(xi.abs() < 0.25f64.to_radians() + 1e-9, "{} {}", xi, eta);
+        }
+    }
+
+    #[test]
+    fn coverage_counts_overlaps_once() {
+        let field = |ra: f64| Footprint {
+            ra,
+            dec: 0.0,
+            width: 10.0,
+            height: 10.0,
+            rotation: 0.0,
+            label: Some("M 42".to_string()),
+        };
+        // A 10° square is about 100 of the sky's 41253 square degrees
+        let one = sky_coverage(&[field(80.0)]);
+        assert!((one - 100.0 / 41253.0).abs() < 2e-4, "{}", one);
+        assert_eq!(sky_coverage(&[field(80.0), field(80.0)]), one);
+        let two = sky_coverage(&[field(80.0), field(120.0)]);
+        assert!((two - 2.0 * one).abs() < 2e-4);
+
+        let position = Footprint {
+            width: 0.0,
+            height: 0.0,
+            ..field(200.0)
+        };
+        assert_eq!(sky_coverage(std::slice::from_ref(&position)), 0.0);
+
+        let svg =
+            render_coverage(&[field(80.0), field(120.0), position], SkymapFormat::Svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        assert_eq!(svg.matches(">M 42<").count(), 1);
+    }
+}
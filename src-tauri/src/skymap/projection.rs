@@ -0,0 +1,165 @@
+//! Sky projections onto the map canvas. RA increases to the left, as the
+//! sky is seen from the ground.
+
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    /// Stereographic around a center, for maps of a region
+    Stereographic {
+        ra: f64,
+        dec: f64,
+        /// Pixels per unit of projected distance
+        scale: f64,
+        cx: f64,
+        cy: f64,
+    },
+    /// Equal-area Mollweide of the whole sky, centered on `ra`
+    Mollweide {
+        ra: f64,
+        /// Semi-axes of the sky ellipse in pixels
+        rx: f64,
+        ry: f64,
+        cx: f64,
+        cy: f64,
+    },
+}
+
+impl Projection {
+    /// Stereographic projection of a `fov_width` x `fov_height` degree
+    /// region onto a `width` x `height` pixel canvas, fitting both
+    pub fn stereographic(
+        ra: f64,
+        dec: f64,
+        fov_width: f64,
+        fov_height: f64,
+        width: f64,
+        height: f64,
+    ) -> Self {
+        // Projected distance of the field edge is 2 tan(θ/2)
+        let extent = |fov: f64| 2.0 * (fov.clamp(0.01, 180.0).to_radians() / 4.0).tan();
+        let scale = (width / 2.0 / extent(fov_width)).min(height / 2.0 / extent(fov_height));
+        Self::Stereographic {
+            ra: ra.to_radians(),
+            dec: dec.to_radians(),
+            scale,
+            cx: width / 2.0,
+            cy: height / 2.0,
+        }
+    }
+
+    /// Whole-sky projection filling a `width` x `height` canvas with a
+    /// `margin` pixel border
+    pub fn mollweide(ra: f64, width: f64, height: f64, margin: f64) -> Self {
+        let rx = (width / 2.0 - margin).min(height - 2.0 * margin);
+        Self::Mollweide {
+            ra: ra.to_radians(),
+            rx,
+            ry: rx / 2.0,
+            cx: width / 2.0,
+            cy: height / 2.0,
+        }
+    }
+
+    /// Canvas position of a sky position in degrees, or None if it can't be
+    /// shown (the far side of the sky for the stereographic projection)
+    pub fn project(&self, ra: f64, dec: f64) -> Option<(f64, f64)> {
+        let (ra, dec) = (ra.to_radians(), dec.to_radians());
+        match *self {
+            Self::Stereographic {
+                ra: ra0,
+                dec: dec0,
+                scale,
+                cx,
+                cy,
+            } => {
+                let d_ra = ra - ra0;
+                let cos_c = dec0.sin() * dec.sin() + dec0.cos() * dec.cos() * d_ra.cos();
+                // Beyond ~120° from the center the projection blows up
+                if cos_c < -0.5 {
+                    return None;
+                }
+                let k = 2.0 / (1.0 + cos_c);
+                let x = k * dec.cos() * d_ra.sin();
+                let y = k * (dec0.cos() * dec.sin() - dec0.sin() * dec.cos() * d_ra.cos());
+                Some((cx - x * scale, cy - y * scale))
+            }
+            Self::Mollweide {
+                ra: ra0,
+                rx,
+                ry,
+                cx,
+                cy,
+            } => {
+                let lon = (ra - ra0 + PI).rem_euclid(2.0 * PI) - PI;
+                let theta = mollweide_theta(dec);
+                let x = 2.0 * SQRT_2 / PI * lon * theta.cos();
+                let y = SQRT_2 * theta.sin();
+                Some((cx - x * rx / (2.0 * SQRT_2), cy - y * ry / SQRT_2))
+            }
+        }
+    }
+
+    /// Longest line that can be drawn straight. Longer Mollweide segments
+    /// cross the edge of the map and wrap to the other side.
+    pub fn max_segment(&self) -> f64 {
+        match *self {
+            Self::Stereographic { .. } => f64::INFINITY,
+            Self::Mollweide { rx, .. } => rx,
+        }
+    }
+}
+
+/// Auxiliary angle θ with 2θ + sin 2θ = π sin φ
+fn mollweide_theta(dec: f64) -> f64 {
+    if (dec.abs() - FRAC_PI_2).abs() < 1e-9 {
+        return dec;
+    }
+    let target = PI * dec.sin();
+    let mut theta = dec;
+    for _ in 0..50 {
+        let delta =
+            (2.0 * theta + (2.0 * theta).sin() - target) / (2.0 + 2.0 * (2.0 * theta).cos());
+        theta -= delta;
+        if delta.abs() < 1e-10 {
+            break;
+        }
+    }
+    theta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereographic_fits_the_field_with_east_left() {
+        let projection = Projection::stereographic(83.8, -5.4, 10.0, 7.5, 600.0, 450.0);
+        let (x, y) = projection.project(83.8, -5.4).unwrap();
+        assert!((x - 300.0).abs() < 1e-9 && (y - 225.0).abs() < 1e-9);
+
+        // 5° east of the center lands at the left edge
+        let (x, _) = projection
+            .project(83.8 + 5.0 / (-5.4f64).to_radians().cos(), -5.4)
+            .unwrap();
+        assert!(x < 300.0 && (x - 0.0).abs() < 2.0, "{}", x);
+        // North is up
+        assert!(projection.project(83.8, 0.0).unwrap().1 < 225.0);
+        // The far side of the sky isn't drawn
+        assert!(projection.project(263.8, 5.4).is_none());
+    }
+
+    #[test]
+    fn mollweide_covers_the_sky_ellipse() {
+        let projection = Projection::mollweide(0.0, 400.0, 200.0, 0.0);
+        let (x, y) = projection.project(0.0, 0.0).unwrap();
+        assert!((x - 200.0).abs() < 1e-9 && (y - 100.0).abs() < 1e-9);
+        let (_, y) = projection.project(0.0, 90.0).unwrap();
+        assert!(y.abs() < 1e-9);
+        // RA 179.9° is just inside the left edge
+        let (x, _) = projection.project(179.9, 0.0).unwrap();
+        assert!(x < 1.0, "{}", x);
+        let (x, _) = projection.project(180.1, 0.0).unwrap();
+        assert!(x > 399.0, "{}", x);
+    }
+}
@@ -0,0 +1,425 @@
+//! Vector scene of a skymap, rendered to PNG or SVG.
+//!
+//! PNG output is rasterized here with simple anti-aliasing. There's no font
+//! rasterizer, so text labels only appear in SVG output.
+
+use std::fmt::Write;
+use std::io::Cursor;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+
+/// sRGB color with alpha
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f64,
+}
+
+impl Color {
+    pub const fn rgb(hex: u32) -> Self {
+        Self {
+            r: (hex >> 16) as u8,
+            g: (hex >> 8) as u8,
+            b: hex as u8,
+            a: 1.0,
+        }
+    }
+
+    pub const fn alpha(self, a: f64) -> Self {
+        Self { a, ..self }
+    }
+
+    fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        color: Color,
+        width: f64,
+    },
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+        color: Color,
+        /// Outline width, or None for a filled disc
+        stroke: Option<f64>,
+    },
+    Polygon {
+        points: Vec<(f64, f64)>,
+        color: Color,
+    },
+    Text {
+        at: (f64, f64),
+        text: String,
+        color: Color,
+        size: f64,
+    },
+}
+
+/// Shapes drawn in order over a background
+#[derive(Debug, Clone)]
+pub struct Scene {
+    width: u32,
+    height: u32,
+    background: Color,
+    shapes: Vec<Shape>,
+}
+
+impl Scene {
+    pub fn new(width: u32, height: u32, background: Color) -> Self {
+        Self {
+            width,
+            height,
+            background,
+            shapes: Vec::new(),
+        }
+    }
+
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64), color: Color, width: f64) {
+        self.shapes.push(Shape::Line {
+            from,
+            to,
+            color,
+            width,
+        });
+    }
+
+    pub fn disc(&mut self, center: (f64, f64), radius: f64, color: Color) {
+        self.shapes.push(Shape::Circle {
+            center,
+            radius,
+            color,
+            stroke: None,
+        });
+    }
+
+    pub fn circle(&mut self, center: (f64, f64), radius: f64, color: Color, width: f64) {
+        self.shapes.push(Shape::Circle {
+            center,
+            radius,
+            color,
+            stroke: Some(width),
+        });
+    }
+
+    /// Filled polygon through `points`
+    pub fn polygon(&mut self, points: &[(f64, f64)], color: Color) {
+        if points.len() >= 3 {
+            self.shapes.push(Shape::Polygon {
+                points: points.to_vec(),
+                color,
+            });
+        }
+    }
+
+    /// Text with its baseline starting at `at` (SVG only)
+    pub fn text(&mut self, at: (f64, f64), text: &str, color: Color, size: f64) {
+        self.shapes.push(Shape::Text {
+            at,
+            text: text.to_string(),
+            color,
+            size,
+        });
+    }
+
+    /// Whether a shape with this bounding box would be visible
+    fn visible(&self, min: (f64, f64), max: (f64, f64)) -> bool {
+        max.0 >= 0.0 && max.1 >= 0.0 && min.0 <= self.width as f64 && min.1 <= self.height as f64
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\">\n<rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n",
+            w = self.width,
+            h = self.height,
+            bg = self.background.hex(),
+        );
+        for shape in &self.shapes {
+            // Writing to a String can't fail
+            let _ = match shape {
+                Shape::Line {
+                    from,
+                    to,
+                    color,
+                    width,
+                } => {
+                    let min = (from.0.min(to.0), from.1.min(to.1));
+                    let max = (from.0.max(to.0), from.1.max(to.1));
+                    if !self.visible(min, max) {
+                        continue;
+                    }
+                    writeln!(
+                        svg,
+                        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" \
+                         stroke-opacity=\"{:.2}\" stroke-width=\"{:.1}\"/>",
+                        from.0,
+                        from.1,
+                        to.0,
+                        to.1,
+                        color.hex(),
+                        color.a,
+                        width
+                    )
+                }
+                Shape::Circle {
+                    center,
+                    radius,
+                    color,
+                    stroke,
+                } => {
+                    let reach = radius + stroke.unwrap_or(0.0);
+                    let min = (center.0 - reach, center.1 - reach);
+                    let max = (center.0 + reach, center.1 + reach);
+                    if !self.visible(min, max) {
+                        continue;
+                    }
+                    let paint = match stroke {
+                        Some(width) => format!(
+                            "fill=\"none\" stroke=\"{}\" stroke-opacity=\"{:.2}\" \
+                             stroke-width=\"{:.1}\"",
+                            color.hex(),
+                            color.a,
+                            width
+                        ),
+                        None => format!("fill=\"{}\" fill-opacity=\"{:.2}\"", color.hex(), color.a),
+                    };
+                    writeln!(
+                        svg,
+                        "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" {}/>",
+                        center.0, center.1, radius, paint
+                    )
+                }
+                Shape::Polygon { points, color } => {
+                    let (min, max) = bounds(points);
+                    if !self.visible(min, max) {
+                        continue;
+                    }
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|p| format!("{:.1},{:.1}", p.0, p.1))
+                        .collect();
+                    writeln!(
+                        svg,
+                        "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{:.2}\"/>",
+                        points.join(" "),
+                        color.hex(),
+                        color.a
+                    )
+                }
+                Shape::Text {
+                    at,
+                    text,
+                    color,
+                    size,
+                } => {
+                    if !self.visible(*at, *at) {
+                        continue;
+                    }
+                    writeln!(
+                        svg,
+                        "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" fill-opacity=\"{:.2}\" \
+                         font-family=\"sans-serif\" font-size=\"{:.0}\">{}</text>",
+                        at.0,
+                        at.1,
+                        color.hex(),
+                        color.a,
+                        size,
+                        escape_xml(text)
+                    )
+                }
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let bg = self.background;
+        let mut image =
+            RgbaImage::from_pixel(self.width, self.height, Rgba([bg.r, bg.g, bg.b, 255]));
+        self.draw_onto(&mut image);
+
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode skymap PNG: {}", e))?;
+        Ok(png)
+    }
+
+    /// Rasterize the shapes over an existing image, e.g. a survey cutout,
+    /// instead of the background
+    pub fn draw_onto(&self, image: &mut RgbaImage) {
+        for shape in &self.shapes {
+            match shape {
+                Shape::Line {
+                    from,
+                    to,
+                    color,
+                    width,
+                } => {
+                    let half = width / 2.0;
+                    let min = (from.0.min(to.0) - half - 1.0, from.1.min(to.1) - half - 1.0);
+                    let max = (from.0.max(to.0) + half + 1.0, from.1.max(to.1) + half + 1.0);
+                    fill(image, min, max, *color, |p| {
+                        half + 0.5 - segment_distance(p, *from, *to)
+                    });
+                }
+                Shape::Circle {
+                    center,
+                    radius,
+                    color,
+                    stroke,
+                } => {
+                    let reach = radius + stroke.unwrap_or(0.0) + 1.0;
+                    let min = (center.0 - reach, center.1 - reach);
+                    let max = (center.0 + reach, center.1 + reach);
+                    // Discs smaller than a pixel keep their area as coverage
+                    let dim = (radius * 2.0).min(1.0);
+                    let color = Color {
+                        a: color.a * if stroke.is_none() { dim } else { 1.0 },
+                        ..*color
+                    };
+                    fill(image, min, max, color, |p| {
+                        let d = (p.0 - center.0).hypot(p.1 - center.1);
+                        match stroke {
+                            Some(width) => width / 2.0 + 0.5 - (d - radius).abs(),
+                            None => radius.max(0.5) + 0.5 - d,
+                        }
+                    });
+                }
+                Shape::Polygon { points, color } => {
+                    let (min, max) = bounds(points);
+                    let (min, max) = ((min.0 - 1.0, min.1 - 1.0), (max.0 + 1.0, max.1 + 1.0));
+                    fill(image, min, max, *color, |p| {
+                        // Signed distance to the outline, positive inside
+                        let edge = (0..points.len())
+                            .map(|i| segment_distance(p, points[i], points[(i + 1) % points.len()]))
+                            .fold(f64::INFINITY, f64::min);
+                        let inside = if contains(points, p) { 1.0 } else { -1.0 };
+                        0.5 + inside * edge
+                    });
+                }
+                Shape::Text { .. } => {}
+            }
+        }
+    }
+}
+
+/// Blend `color` into the pixels within a bounding box, weighted by
+/// `coverage` (clamped to 0-1) at each pixel center
+fn fill(
+    image: &mut RgbaImage,
+    min: (f64, f64),
+    max: (f64, f64),
+    color: Color,
+    coverage: impl Fn((f64, f64)) -> f64,
+) {
+    let (w, h) = (image.width() as f64, image.height() as f64);
+    let x0 = min.0.max(0.0).floor() as u32;
+    let y0 = min.1.max(0.0).floor() as u32;
+    let x1 = max.0.min(w).ceil() as u32;
+    let y1 = max.1.min(h).ceil() as u32;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let alpha = coverage((x as f64 + 0.5, y as f64 + 0.5)).clamp(0.0, 1.0) * color.a;
+            if alpha <= 0.0 {
+                continue;
+            }
+            let pixel = image.get_pixel_mut(x, y);
+            for (channel, value) in pixel.0.iter_mut().zip([color.r, color.g, color.b]) {
+                *channel = (*channel as f64 * (1.0 - alpha) + value as f64 * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+/// Bounding box of a set of points
+fn bounds(points: &[(f64, f64)]) -> ((f64, f64), (f64, f64)) {
+    points.iter().fold(
+        (
+            (f64::INFINITY, f64::INFINITY),
+            (f64::NEG_INFINITY, f64::NEG_INFINITY),
+        ),
+        |(min, max), p| {
+            (
+                (min.0.min(p.0), min.1.min(p.1)),
+                (max.0.max(p.0), max.1.max(p.1)),
+            )
+        },
+    )
+}
+
+/// Even-odd test for whether `p` is inside a polygon
+fn contains(points: &[(f64, f64)], p: (f64, f64)) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - (a.0 + t * dx)).hypot(p.1 - (a.1 + t * dy))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_skips_shapes_outside_the_canvas() {
+        let mut scene = Scene::new(100, 50, Color::rgb(0x000000));
+        scene.line((10.0, 10.0), (20.0, 20.0), Color::rgb(0xffffff), 1.0);
+        scene.line((200.0, 10.0), (300.0, 20.0), Color::rgb(0xffffff), 1.0);
+        scene.circle((50.0, 25.0), 5.0, Color::rgb(0x14b8a6).alpha(0.5), 2.0);
+        scene.text((5.0, 40.0), "M 42 <Orion>", Color::rgb(0xffffff), 10.0);
+        let svg = scene.to_svg();
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains("stroke=\"#14b8a6\" stroke-opacity=\"0.50\""));
+        assert!(svg.contains("M 42 &lt;Orion&gt;"));
+    }
+
+    #[test]
+    fn png_draws_shapes() {
+        let mut scene = Scene::new(40, 20, Color::rgb(0x000000));
+        scene.disc((10.5, 10.5), 3.0, Color::rgb(0xffffff));
+        scene.line((20.0, 10.5), (39.0, 10.5), Color::rgb(0xff0000), 2.0);
+        scene.polygon(
+            &[(20.0, 14.0), (30.0, 14.0), (30.0, 20.0)],
+            Color::rgb(0x00ff00),
+        );
+        let png = scene.to_png().unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(10, 10).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(30, 10).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(28, 17).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(22, 18).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(30, 2).0, [0, 0, 0, 255]);
+    }
+}
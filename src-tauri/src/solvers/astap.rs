@@ -0,0 +1,293 @@
+//! ASTAP plate solver runner.
+//!
+//! Spawns the ASTAP command-line solver and reads the solution from the
+//! `.ini` file it writes, with the image dimensions taken from the `.wcs`
+//! header. Output goes to a temporary directory so nothing is left next to
+//! the image.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use super::{failure, find_executable, parse_header, run_with_timeout, wcs_solution, work_dir};
+use crate::python::plate_solve::{PlateSolveResult, SolverInfo};
+
+const SOLVER: &str = "astap";
+
+/// Executables tried, in order, when no binary path is configured
+const CANDIDATES: &[&str] = &[
+    "astap_cli",
+    "astap",
+    "/opt/astap/astap_cli",
+    "/Applications/ASTAP.app/Contents/MacOS/astap",
+    "C:\\Program Files\\astap\\astap_cli.exe",
+    "C:\\Program Files\\astap\\astap.exe",
+];
+
+/// Solve timeout when none is given
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Options for an ASTAP solve
+#[derive(Debug, Clone, Default)]
+pub struct AstapOptions {
+    /// ASTAP executable (default: the first installed of [`CANDIDATES`])
+    pub binary: Option<PathBuf>,
+    /// Field height in degrees
+    pub fov_deg: Option<f64>,
+    /// RA hint in degrees
+    pub hint_ra: Option<f64>,
+    /// Dec hint in degrees
+    pub hint_dec: Option<f64>,
+    /// Search radius around the hint in degrees
+    pub search_radius: Option<f64>,
+    pub timeout: Option<Duration>,
+}
+
+/// Find the ASTAP executable: the configured path, or the first installed
+/// of [`CANDIDATES`]
+pub fn find_binary(configured: Option<&Path>) -> Option<PathBuf> {
+    find_executable(configured, CANDIDATES)
+}
+
+/// Availability of ASTAP, in the shape `detect_plate_solvers` reports
+pub fn detect(configured: Option<&Path>) -> SolverInfo {
+    match find_binary(configured) {
+        Some(path) => SolverInfo {
+            available: true,
+            version: None,
+            details: format!("ASTAP solver ({})", path.display()),
+        },
+        None => SolverInfo {
+            available: false,
+            version: None,
+            details: "ASTAP solver not found (install from hnsky.org/astap.htm)".to_string(),
+        },
+    }
+}
+
+/// Plate solve an image with ASTAP.
+///
+/// Errors if ASTAP can't be found or started; a solve that fails or times
+/// out is returned as an unsuccessful result.
+pub fn solve(image_path: &str, options: &AstapOptions) -> Result<PlateSolveResult, String> {
+    let start = Instant::now();
+    let binary = find_binary(options.binary.as_deref()).ok_or_else(|| match &options.binary {
+        Some(path) => format!("ASTAP not found at {}", path.display()),
+        None => "ASTAP not found. Please install ASTAP solver.".to_string(),
+    })?;
+
+    let work_dir = work_dir(SOLVER)?;
+    let output_base = work_dir.join("solution");
+    let result = run(&binary, image_path, &output_base, options, start);
+    let _ = std::fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn run(
+    binary: &Path,
+    image_path: &str,
+    output_base: &Path,
+    options: &AstapOptions,
+    start: Instant,
+) -> Result<PlateSolveResult, String> {
+    log::info!("Running ASTAP ({}) on {}", binary.display(), image_path);
+    let mut command = Command::new(binary);
+    command.args(astap_args(image_path, output_base, options));
+    let timeout = options.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    if !run_with_timeout(&mut command, start, timeout)? {
+        return Ok(failure(SOLVER, start.elapsed().as_secs_f64(), "Solve timed out"));
+    }
+
+    let solve_time = start.elapsed().as_secs_f64();
+    let Ok(ini) = std::fs::read_to_string(output_base.with_extension("ini")) else {
+        return Ok(failure(SOLVER, solve_time, "No solution found"));
+    };
+    let dimensions = std::fs::read(output_base.with_extension("wcs"))
+        .ok()
+        .and_then(|header| header_dimensions(&header));
+
+    Ok(result_from_ini(&parse_ini(&ini), dimensions, solve_time))
+}
+
+/// Command-line arguments for a solve writing `<output_base>.ini/.wcs`
+fn astap_args(image_path: &str, output_base: &Path, options: &AstapOptions) -> Vec<String> {
+    let mut args = vec![
+        "-f".to_string(),
+        image_path.to_string(),
+        "-o".to_string(),
+        output_base.to_string_lossy().to_string(),
+        // Automatic downsampling
+        "-z".to_string(),
+        "0".to_string(),
+    ];
+    if let Some(fov) = options.fov_deg {
+        args.extend(["-fov".to_string(), format!("{:.4}", fov)]);
+    }
+    if let (Some(ra), Some(dec)) = (options.hint_ra, options.hint_dec) {
+        // ASTAP takes RA in hours and Dec as south pole distance
+        args.extend(["-ra".to_string(), format!("{:.6}", ra / 15.0)]);
+        args.extend(["-spd".to_string(), format!("{:.6}", dec + 90.0)]);
+    }
+    if let Some(radius) = options.search_radius {
+        args.extend(["-r".to_string(), format!("{:.2}", radius)]);
+    }
+    args
+}
+
+/// Parse the `KEY=VALUE` lines of an ASTAP `.ini` file
+fn parse_ini(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_uppercase(), value.trim().to_string()))
+        .collect()
+}
+
+/// NAXIS1/NAXIS2 from the `.wcs` header ASTAP writes
+fn header_dimensions(header: &[u8]) -> Option<(i32, i32)> {
+    let header = parse_header(header);
+    let value = |key: &str| header.get(key)?.parse::<i32>().ok();
+    Some((value("NAXIS1")?, value("NAXIS2")?))
+}
+
+/// Build the result from a parsed `.ini` solution, which uses the same
+/// keywords as a FITS WCS header
+fn result_from_ini(
+    ini: &HashMap<String, String>,
+    dimensions: Option<(i32, i32)>,
+    solve_time: f64,
+) -> PlateSolveResult {
+    if ini.get("PLTSOLVD").map(String::as_str) != Some("T") {
+        let message = ["ERROR", "WARNING"]
+            .iter()
+            .filter_map(|key| ini.get(*key))
+            .find(|m| !m.is_empty())
+            .map(String::as_str)
+            .unwrap_or("No solution found");
+        return failure(SOLVER, solve_time, message);
+    }
+    wcs_solution(SOLVER, ini, dimensions, solve_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLVED_INI: &str = "PLTSOLVD=T\r\nCRPIX1= 2.0725E+03\r\nCRPIX2= 1.4115E+03\r\n\
+        CRVAL1= 8.3822E+01\r\nCRVAL2=-5.3910E+00\r\nCDELT1=-3.3E-04\r\nCDELT2= 3.3E-04\r\n\
+        CROTA1= 1.05E+00\r\nCROTA2= 1.05E+00\r\nCD1_1=-3.3E-04\r\nCD1_2= 6.0E-06\r\n\
+        CD2_1= 6.0E-06\r\nCD2_2= 3.3E-04\r\nWARNING=\r\n";
+
+    #[test]
+    fn parses_solved_ini() {
+        let result = result_from_ini(&parse_ini(SOLVED_INI), Some((4144, 2822)), 2.5);
+        assert!(result.success);
+        assert!((result.center_ra - 83.822).abs() < 1e-9);
+        assert!((result.center_dec + 5.391).abs() < 1e-9);
+        assert!((result.pixel_scale - 1.188).abs() < 0.01, "{}", result.pixel_scale);
+        assert!((result.rotation - 1.05).abs() < 1e-9);
+        assert!((result.width_deg - 1.188 * 4144.0 / 3600.0).abs() < 0.01);
+        let wcs = result.wcs.unwrap();
+        assert_eq!(wcs["crpix"][0], 2072.5);
+        assert_eq!(wcs["cd"][1][1], 3.3e-4);
+    }
+
+    #[test]
+    fn unsolved_ini_reports_astap_error() {
+        let ini = parse_ini("PLTSOLVD=F\nERROR=Not enough stars\nWARNING=\n");
+        let result = result_from_ini(&ini, None, 1.0);
+        assert!(!result.success);
+        assert_eq!(result.error_message.as_deref(), Some("Not enough stars"));
+
+        let result = result_from_ini(&parse_ini("PLTSOLVD=F\n"), None, 1.0);
+        assert_eq!(result.error_message.as_deref(), Some("No solution found"));
+    }
+
+    #[test]
+    fn reads_dimensions_from_fits_cards() {
+        let cards = [
+            "SIMPLE  =                    T",
+            "NAXIS1  =                 4144 / width",
+            "NAXIS2  =                 2822 / height",
+            "END",
+        ];
+        let header: String = cards.iter().map(|c| format!("{:<80}", c)).collect();
+        assert_eq!(header_dimensions(header.as_bytes()), Some((4144, 2822)));
+        assert_eq!(header_dimensions(b"NAXIS1 = 10\nNAXIS2 = 20\n"), Some((10, 20)));
+        assert_eq!(header_dimensions(b"END"), None);
+    }
+
+    #[test]
+    fn hints_are_converted_to_astap_units() {
+        let options = AstapOptions {
+            fov_deg: Some(1.2),
+            hint_ra: Some(83.82),
+            hint_dec: Some(-5.39),
+            search_radius: Some(10.0),
+            ..Default::default()
+        };
+        let args = astap_args("/img/m42.fits", Path::new("/tmp/out/solution"), &options);
+        let value = |flag: &str| {
+            let i = args.iter().position(|a| a == flag).unwrap();
+            args[i + 1].clone()
+        };
+        assert_eq!(value("-f"), "/img/m42.fits");
+        assert_eq!(value("-fov"), "1.2000");
+        assert_eq!(value("-ra"), "5.588000");
+        assert_eq!(value("-spd"), "84.610000");
+        assert_eq!(value("-r"), "10.00");
+    }
+
+    #[cfg(unix)]
+    fn fake_astap(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("astap_cli");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn solve_runs_binary_and_reads_its_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = fake_astap(
+            dir.path(),
+            r#"while [ $# -gt 0 ]; do [ "$1" = "-o" ] && out="$2"; shift; done
+printf 'PLTSOLVD=T\nCRVAL1=10.5\nCRVAL2=20.25\nCDELT2=0.001\n' > "$out.ini""#,
+        );
+        let options = AstapOptions {
+            binary: Some(binary),
+            ..Default::default()
+        };
+        let result = solve("/img/m42.fits", &options).unwrap();
+        assert!(result.success, "{:?}", result.error_message);
+        assert_eq!((result.center_ra, result.center_dec), (10.5, 20.25));
+        assert!((result.pixel_scale - 3.6).abs() < 1e-9);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn solve_kills_astap_on_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = AstapOptions {
+            binary: Some(fake_astap(dir.path(), "sleep 10")),
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let result = solve("/img/m42.fits", &options).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error_message.as_deref(), Some("Solve timed out"));
+        assert!(result.solve_time < 5.0);
+    }
+
+    #[test]
+    fn missing_configured_binary_is_an_error() {
+        let options = AstapOptions {
+            binary: Some(PathBuf::from("/nonexistent/astap_cli")),
+            ..Default::default()
+        };
+        assert!(solve("/img/m42.fits", &options).is_err());
+        assert!(!detect(Some(Path::new("/nonexistent/astap_cli"))).available);
+    }
+}
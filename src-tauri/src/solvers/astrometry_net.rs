@@ -0,0 +1,376 @@
+//! Local astrometry.net (`solve-field`) runner.
+//!
+//! Spawns a locally installed `solve-field` and reads the `.wcs` header it
+//! writes on success. solve-field is useless without index files, so their
+//! presence is checked up front (and reported by [`detect`]) using the
+//! `add_path` entries of the installation's `astrometry.cfg`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use super::{failure, find_executable, parse_header, run_with_timeout, wcs_solution, work_dir};
+use crate::python::plate_solve::{PlateSolveResult, SolverInfo};
+
+const SOLVER: &str = "local";
+
+/// Executables tried, in order, when no binary path is configured
+const CANDIDATES: &[&str] = &[
+    "solve-field",
+    "/usr/local/astrometry/bin/solve-field",
+    "/opt/homebrew/bin/solve-field",
+];
+
+/// Config files tried after the one next to the binary (`<prefix>/etc`)
+const CONFIG_FILES: &[&str] = &[
+    "/etc/astrometry.cfg",
+    "/usr/local/etc/astrometry.cfg",
+    "/opt/homebrew/etc/astrometry.cfg",
+    "/usr/local/astrometry/etc/astrometry.cfg",
+];
+
+/// Index directories checked when no config file is found
+const DEFAULT_INDEX_DIRS: &[&str] = &[
+    "/usr/share/astrometry",
+    "/usr/local/share/astrometry",
+    "/usr/local/astrometry/data",
+];
+
+/// Solve timeout when none is given
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Extra time given to the process beyond solve-field's own CPU limit
+const PROCESS_GRACE: Duration = Duration::from_secs(30);
+
+/// Options for a solve-field solve
+#[derive(Debug, Clone, Default)]
+pub struct SolveFieldOptions {
+    /// solve-field executable (default: the first installed of [`CANDIDATES`])
+    pub binary: Option<PathBuf>,
+    /// Lower bound of the image scale (arcsec/pixel)
+    pub scale_lower: Option<f64>,
+    /// Upper bound of the image scale (arcsec/pixel)
+    pub scale_upper: Option<f64>,
+    /// RA hint in degrees
+    pub hint_ra: Option<f64>,
+    /// Dec hint in degrees
+    pub hint_dec: Option<f64>,
+    /// Search radius around the hint in degrees
+    pub search_radius: Option<f64>,
+    /// Downsample factor for star detection (default 2)
+    pub downsample: Option<u32>,
+    pub timeout: Option<Duration>,
+}
+
+/// Index files available to solve-field
+#[derive(Debug, Clone, Default)]
+pub struct IndexFiles {
+    /// Directories that were searched
+    pub dirs: Vec<PathBuf>,
+    /// Number of `index-*.fits` files found in them
+    pub count: usize,
+}
+
+/// Find the solve-field executable: the configured path, or the first
+/// installed of [`CANDIDATES`]
+pub fn find_binary(configured: Option<&Path>) -> Option<PathBuf> {
+    find_executable(configured, CANDIDATES)
+}
+
+/// Locate the index files for a solve-field installation
+pub fn find_index_files(binary: &Path) -> IndexFiles {
+    // A prefix install keeps its config in <prefix>/etc next to <prefix>/bin
+    let bundled = binary
+        .parent()
+        .and_then(Path::parent)
+        .map(|prefix| prefix.join("etc").join("astrometry.cfg"));
+    let config = bundled
+        .into_iter()
+        .chain(CONFIG_FILES.iter().map(PathBuf::from))
+        .find_map(|path| std::fs::read_to_string(path).ok());
+
+    let dirs = config
+        .map(|text| config_index_dirs(&text))
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_INDEX_DIRS.iter().map(PathBuf::from).collect());
+    let count = dirs.iter().map(|dir| count_index_files(dir)).sum();
+    IndexFiles { dirs, count }
+}
+
+/// `add_path` directories from an astrometry.cfg
+fn config_index_dirs(config: &str) -> Vec<PathBuf> {
+    config
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.trim();
+            let path = line.strip_prefix("add_path")?;
+            // Require whitespace after the keyword
+            path.starts_with(char::is_whitespace).then(|| PathBuf::from(path.trim()))
+        })
+        .collect()
+}
+
+fn count_index_files(dir: &Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_lowercase();
+                    name.starts_with("index-") && name.ends_with(".fits")
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+fn dirs_list(dirs: &[PathBuf]) -> String {
+    dirs.iter()
+        .map(|d| d.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Availability of solve-field and its index files, in the shape
+/// `detect_plate_solvers` reports
+pub fn detect(configured: Option<&Path>) -> SolverInfo {
+    let Some(binary) = find_binary(configured) else {
+        return SolverInfo {
+            available: false,
+            version: None,
+            details: "astrometry.net solve-field not found (install astrometry.net)".to_string(),
+        };
+    };
+
+    let index = find_index_files(&binary);
+    let details = if index.count > 0 {
+        format!(
+            "astrometry.net solve-field ({}), {} index files in {}",
+            binary.display(),
+            index.count,
+            dirs_list(&index.dirs)
+        )
+    } else {
+        format!(
+            "solve-field found ({}) but no index files in {} (download from data.astrometry.net)",
+            binary.display(),
+            dirs_list(&index.dirs)
+        )
+    };
+    SolverInfo {
+        available: index.count > 0,
+        version: None,
+        details,
+    }
+}
+
+/// Plate solve an image with solve-field.
+///
+/// Errors if solve-field or its index files can't be found; a solve that
+/// fails or times out is returned as an unsuccessful result.
+pub fn solve(image_path: &str, options: &SolveFieldOptions) -> Result<PlateSolveResult, String> {
+    let start = Instant::now();
+    let binary = find_binary(options.binary.as_deref()).ok_or_else(|| match &options.binary {
+        Some(path) => format!("solve-field not found at {}", path.display()),
+        None => "solve-field not found. Please install astrometry.net locally.".to_string(),
+    })?;
+    let index = find_index_files(&binary);
+    if index.count == 0 {
+        return Err(format!(
+            "No astrometry.net index files found in {}",
+            dirs_list(&index.dirs)
+        ));
+    }
+
+    let work_dir = work_dir(SOLVER)?;
+    let result = run(&binary, image_path, &work_dir, options, start);
+    let _ = std::fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn run(
+    binary: &Path,
+    image_path: &str,
+    work_dir: &Path,
+    options: &SolveFieldOptions,
+    start: Instant,
+) -> Result<PlateSolveResult, String> {
+    log::info!("Running solve-field ({}) on {}", binary.display(), image_path);
+    let timeout = options.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let mut command = Command::new(binary);
+    command.args(solve_field_args(image_path, work_dir, timeout, options));
+    if !run_with_timeout(&mut command, start, timeout + PROCESS_GRACE)? {
+        return Ok(failure(SOLVER, start.elapsed().as_secs_f64(), "Solve timed out"));
+    }
+
+    // solve-field only writes the .wcs file when it finds a solution
+    let solve_time = start.elapsed().as_secs_f64();
+    let Ok(header) = std::fs::read(work_dir.join("solution.wcs")) else {
+        return Ok(failure(SOLVER, solve_time, "No solution found"));
+    };
+    let header = parse_header(&header);
+    let dimension = |key: &str| header.get(key)?.parse::<f64>().ok().map(|v| v as i32);
+    let dimensions = dimension("IMAGEW").zip(dimension("IMAGEH"));
+
+    Ok(wcs_solution(SOLVER, &header, dimensions, solve_time))
+}
+
+/// Command-line arguments for a solve writing `<work_dir>/solution.*`
+fn solve_field_args(
+    image_path: &str,
+    work_dir: &Path,
+    timeout: Duration,
+    options: &SolveFieldOptions,
+) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "--overwrite",
+        "--no-plots",
+        // Skip writing a full-size copy of the image with the WCS
+        "--new-fits",
+        "none",
+        "--dir",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    args.push(work_dir.to_string_lossy().to_string());
+    args.extend(["--out".to_string(), "solution".to_string()]);
+    args.extend(["--cpulimit".to_string(), timeout.as_secs().max(1).to_string()]);
+    args.extend(["--downsample".to_string(), options.downsample.unwrap_or(2).to_string()]);
+
+    if let (Some(lower), Some(upper)) = (options.scale_lower, options.scale_upper) {
+        args.extend(["--scale-low".to_string(), format!("{:.4}", lower)]);
+        args.extend(["--scale-high".to_string(), format!("{:.4}", upper)]);
+        args.extend(["--scale-units".to_string(), "arcsecperpix".to_string()]);
+    }
+    if let (Some(ra), Some(dec)) = (options.hint_ra, options.hint_dec) {
+        args.extend(["--ra".to_string(), format!("{:.6}", ra)]);
+        args.extend(["--dec".to_string(), format!("{:.6}", dec)]);
+        let radius = options.search_radius.unwrap_or(10.0);
+        args.extend(["--radius".to_string(), format!("{:.2}", radius)]);
+    }
+
+    args.push(image_path.to_string());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_add_path_entries() {
+        let config = "# astrometry.cfg\ninparallel\nadd_path /usr/share/astrometry  # data\n\
+            #add_path /old\nadd_path\t/mnt/index\nadd_paths /nope\nautoindex\n";
+        assert_eq!(
+            config_index_dirs(config),
+            vec![PathBuf::from("/usr/share/astrometry"), PathBuf::from("/mnt/index")]
+        );
+    }
+
+    #[test]
+    fn scale_and_hint_flags() {
+        let options = SolveFieldOptions {
+            scale_lower: Some(1.0),
+            scale_upper: Some(1.5),
+            hint_ra: Some(83.82),
+            hint_dec: Some(-5.39),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(60);
+        let args = solve_field_args("/img/m42.fits", Path::new("/tmp/w"), timeout, &options);
+        let value = |flag: &str| {
+            let i = args.iter().position(|a| a == flag).unwrap();
+            args[i + 1].clone()
+        };
+        assert_eq!(value("--scale-low"), "1.0000");
+        assert_eq!(value("--scale-high"), "1.5000");
+        assert_eq!(value("--scale-units"), "arcsecperpix");
+        assert_eq!(value("--radius"), "10.00");
+        assert_eq!(value("--cpulimit"), "60");
+        assert_eq!(value("--downsample"), "2");
+        assert_eq!(args.last().map(String::as_str), Some("/img/m42.fits"));
+
+        // Scale flags need both bounds
+        let options = SolveFieldOptions {
+            scale_lower: Some(1.0),
+            ..Default::default()
+        };
+        let args = solve_field_args("/img/m42.fits", Path::new("/tmp/w"), timeout, &options);
+        assert!(!args.iter().any(|a| a == "--scale-low"));
+    }
+
+    /// Fake prefix install: <dir>/bin/solve-field with <dir>/etc/astrometry.cfg
+    /// pointing at <dir>/data
+    #[cfg(unix)]
+    fn fake_install(dir: &Path, script: &str, index_files: &[&str]) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        for sub in ["bin", "etc", "data"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        let config = format!("add_path {}\n", dir.join("data").display());
+        std::fs::write(dir.join("etc/astrometry.cfg"), config).unwrap();
+        for name in index_files {
+            std::fs::write(dir.join("data").join(name), b"").unwrap();
+        }
+        let binary = dir.join("bin/solve-field");
+        std::fs::write(&binary, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        binary
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detect_reports_index_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = ["index-4107.fits", "index-4108.fits", "notes.txt"];
+        let binary = fake_install(dir.path(), "exit 0", &files);
+        let info = detect(Some(&binary));
+        assert!(info.available);
+        assert!(info.details.contains("2 index files"), "{}", info.details);
+
+        let empty = tempfile::tempdir().unwrap();
+        let binary = fake_install(empty.path(), "exit 0", &[]);
+        assert!(!detect(Some(&binary)).available);
+        let options = SolveFieldOptions {
+            binary: Some(binary),
+            ..Default::default()
+        };
+        assert!(solve("/img/m42.fits", &options).unwrap_err().contains("index files"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn solve_reads_wcs_written_by_solve_field() {
+        let dir = tempfile::tempdir().unwrap();
+        // Write a WCS header with its reference pixel at the image center
+        let script = r#"while [ $# -gt 1 ]; do [ "$1" = "--dir" ] && out="$2"; shift; done
+printf "CRVAL1  = 10.0\nCRVAL2  = 0.0\nCRPIX1  = 50.5\nCRPIX2  = 50.5\nCD1_1   = -0.001\n\
+CD1_2   = 0\nCD2_1   = 0\nCD2_2   = 0.001\nIMAGEW  = 100\nIMAGEH  = 100\n" > "$out/solution.wcs""#;
+        let binary = fake_install(dir.path(), script, &["index-4107.fits"]);
+        let options = SolveFieldOptions {
+            binary: Some(binary),
+            ..Default::default()
+        };
+        let result = solve("/img/m42.fits", &options).unwrap();
+        assert!(result.success, "{:?}", result.error_message);
+        assert_eq!((result.image_width, result.image_height), (100, 100));
+        assert!((result.center_ra - 10.0).abs() < 1e-9 && result.center_dec.abs() < 1e-9);
+        assert!((result.pixel_scale - 3.6).abs() < 1e-9);
+        assert_eq!(result.solver, "local");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn solve_without_wcs_is_unsuccessful() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = fake_install(dir.path(), "exit 1", &["index-4107.fits"]);
+        let options = SolveFieldOptions {
+            binary: Some(binary),
+            ..Default::default()
+        };
+        let result = solve("/img/m42.fits", &options).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error_message.as_deref(), Some("No solution found"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Native plate solver runners.
+//!
+//! Solvers that are standalone executables are spawned directly from Rust
+//! rather than through the Python bridge. Each runner returns the same
+//! `PlateSolveResult` as the Python solvers so callers can dispatch freely.
+
+pub mod astap;
+pub mod astrometry_net;
+pub mod nova;
+pub mod wcs;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::python::plate_solve::PlateSolveResult;
+use wcs::Wcs;
+
+/// Find a solver executable: the configured path, or the first candidate
+/// that exists (bare names are looked up on PATH). Candidates are checked
+/// on disk rather than run, since some open a window when started.
+pub(crate) fn find_executable(configured: Option<&Path>, candidates: &[&str]) -> Option<PathBuf> {
+    if let Some(path) = configured {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let search_path: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect())
+        .unwrap_or_default();
+    candidates.iter().find_map(|candidate| {
+        let candidate = Path::new(candidate);
+        if candidate.is_absolute() {
+            return candidate.is_file().then(|| candidate.to_path_buf());
+        }
+        search_path.iter().find_map(|dir| {
+            let path = dir.join(candidate);
+            let exe = path.with_extension("exe");
+            [path, exe].into_iter().find(|p| p.is_file())
+        })
+    })
+}
+
+/// Fresh temporary directory for a solver's output files
+fn work_dir(solver: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("astra-{}-{}", solver, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {} work directory: {}", solver, e))?;
+    Ok(dir)
+}
+
+/// Run a solver process to completion, killing it once `timeout` has
+/// passed since `start`. Returns false if it timed out.
+fn run_with_timeout(
+    command: &mut Command,
+    start: Instant,
+    timeout: Duration,
+) -> Result<bool, String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return Ok(true),
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(false);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => return Err(format!("Failed to wait for {}: {}", program, e)),
+        }
+    }
+}
+
+/// Keyword values from a FITS header, either as 80-character cards or as
+/// lines. String values are unquoted and comments dropped.
+fn parse_header(header: &[u8]) -> HashMap<String, String> {
+    let text = String::from_utf8_lossy(header);
+    let cards: Vec<&str> = if text.contains('\n') {
+        text.lines().collect()
+    } else {
+        header
+            .chunks(80)
+            .filter_map(|card| std::str::from_utf8(card).ok())
+            .collect()
+    };
+
+    cards
+        .iter()
+        .filter_map(|card| {
+            let (key, rest) = card.split_once('=')?;
+            let rest = rest.trim();
+            let value = match rest.strip_prefix('\'') {
+                Some(quoted) => quoted.split('\'').next()?.trim_end(),
+                None => rest.split('/').next()?.trim(),
+            };
+            Some((key.trim().to_uppercase(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Build a successful result from WCS keywords (CRVAL, CRPIX and either
+/// the CD matrix or CDELT/CROTA2, plus any SIP terms). The center is found
+/// by deprojecting the image center, since CRPIX needn't be there.
+fn wcs_solution(
+    solver: &str,
+    header: &HashMap<String, String>,
+    dimensions: Option<(i32, i32)>,
+    solve_time: f64,
+) -> PlateSolveResult {
+    let num = |key: &str| header.get(key).and_then(|v| v.parse::<f64>().ok());
+    let (Some(crval1), Some(crval2)) = (num("CRVAL1"), num("CRVAL2")) else {
+        return failure(solver, solve_time, "Solution has no CRVAL1/CRVAL2");
+    };
+    let cd = wcs::cd_matrix(header);
+    let solution = Wcs::from_header(header);
+
+    // Pixel scale from the CD matrix determinant (or CDELT2), in arcsec
+    let pixel_scale = cd
+        .map(|cd| (cd[0][0] * cd[1][1] - cd[0][1] * cd[1][0]).abs().sqrt() * 3600.0)
+        .or_else(|| num("CDELT2").map(|d| d.abs() * 3600.0))
+        .unwrap_or(0.0);
+    let rotation = num("CROTA2")
+        .or_else(|| cd.map(|cd| cd[0][1].atan2(cd[0][0]).to_degrees()))
+        .unwrap_or(0.0)
+        .rem_euclid(360.0);
+
+    let (image_width, image_height) = dimensions.unwrap_or((0, 0));
+    let (center_ra, center_dec) = match (&solution, dimensions) {
+        (Some(solution), Some((w, h))) => {
+            solution.pixel_to_sky((w as f64 - 1.0) / 2.0, (h as f64 - 1.0) / 2.0)
+        }
+        _ => (crval1, crval2),
+    };
+
+    let wcs = match &solution {
+        Some(solution) => serde_json::to_value(solution).ok(),
+        None => Some(json!({ "crval": [crval1, crval2] })),
+    };
+
+    PlateSolveResult {
+        success: true,
+        center_ra,
+        center_dec,
+        pixel_scale,
+        rotation,
+        width_deg: pixel_scale * image_width as f64 / 3600.0,
+        height_deg: pixel_scale * image_height as f64 / 3600.0,
+        image_width,
+        image_height,
+        solver: solver.to_string(),
+        solve_time,
+        error_message: None,
+        wcs,
+    }
+}
+
+/// Unsuccessful result with an error message
+pub(crate) fn failure(solver: &str, solve_time: f64, message: &str) -> PlateSolveResult {
+    PlateSolveResult {
+        success: false,
+        center_ra: 0.0,
+        center_dec: 0.0,
+        pixel_scale: 0.0,
+        rotation: 0.0,
+        width_deg: 0.0,
+        height_deg: 0.0,
+        image_width: 0,
+        image_height: 0,
+        solver: solver.to_string(),
+        solve_time,
+        error_message: Some(message.to_string()),
+        wcs: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_cards_and_lines() {
+        let cards = [
+            "CTYPE1  = 'RA---TAN'           / projection",
+            "NAXIS1  =                 4144 / width",
+            "END",
+        ];
+        let header: String = cards.iter().map(|c| format!("{:<80}", c)).collect();
+        let values = parse_header(header.as_bytes());
+        assert_eq!(values["CTYPE1"], "RA---TAN");
+        assert_eq!(values["NAXIS1"], "4144");
+        assert_eq!(parse_header(b"NAXIS2 = 20\n")["NAXIS2"], "20");
+    }
+
+    #[test]
+    fn solution_from_cdelt_and_crota() {
+        let header: HashMap<String, String> = [
+            ("CRVAL1", "83.8"),
+            ("CRVAL2", "-5.4"),
+            ("CRPIX1", "50.5"),
+            ("CRPIX2", "25.5"),
+            ("CDELT1", "-0.001"),
+            ("CDELT2", "0.001"),
+            ("CROTA2", "90"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let result = wcs_solution("local", &header, Some((100, 50)), 1.0);
+        assert!(result.success);
+        assert!((result.pixel_scale - 3.6).abs() < 1e-9);
+        assert!((result.rotation - 90.0).abs() < 1e-9);
+        assert!((result.center_ra - 83.8).abs() < 1e-9 && (result.center_dec + 5.4).abs() < 1e-9);
+        assert!((result.width_deg - 0.1).abs() < 1e-9);
+    }
+}
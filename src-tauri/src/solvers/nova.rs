@@ -0,0 +1,354 @@
+//! nova.astrometry.net (or self-hosted astrometry.net web API) client.
+//!
+//! Jobs can sit in the nova queue for a long time, so submitting an image
+//! and collecting its solution are separate steps: [`submit`] uploads the
+//! image and returns the submission id, and [`check`] reports how the job
+//! is doing, fetching the WCS solution once it has one. Requests go through
+//! the network layer, so they're rate limited and refused when offline.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{failure, parse_header, wcs_solution};
+use crate::network::{Network, Provider};
+use crate::python::plate_solve::PlateSolveResult;
+
+const SOLVER: &str = "nova";
+
+pub const DEFAULT_API_URL: &str = "https://nova.astrometry.net/api";
+
+/// Search radius around a position hint when none is given (degrees)
+const DEFAULT_SEARCH_RADIUS: f64 = 10.0;
+
+/// Scale and position hints sent with an upload
+#[derive(Debug, Clone, Default)]
+pub struct SubmitOptions {
+    /// Lower bound of the image scale (arcsec/pixel)
+    pub scale_lower: Option<f64>,
+    /// Upper bound of the image scale (arcsec/pixel)
+    pub scale_upper: Option<f64>,
+    /// RA hint in degrees
+    pub hint_ra: Option<f64>,
+    /// Dec hint in degrees
+    pub hint_dec: Option<f64>,
+    /// Search radius around the hint in degrees
+    pub search_radius: Option<f64>,
+}
+
+/// Where a submitted job has got to
+#[derive(Debug, Clone)]
+pub enum JobStatus {
+    /// Waiting in the queue; no job has been started yet
+    Queued,
+    /// Job started and still solving
+    Solving(u64),
+    /// Job done: the solution, or an unsuccessful result
+    Finished(u64, PlateSolveResult),
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    status: Option<String>,
+    session: Option<String>,
+    subid: Option<u64>,
+    errormessage: Option<String>,
+}
+
+impl ApiResponse {
+    fn check(self, action: &str) -> Result<Self, String> {
+        if self.status.as_deref() == Some("success") {
+            return Ok(self);
+        }
+        Err(format!(
+            "astrometry.net {} failed: {}",
+            action,
+            self.errormessage
+                .as_deref()
+                .or(self.status.as_deref())
+                .unwrap_or("no status")
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmissionResponse {
+    #[serde(default)]
+    jobs: Vec<Option<u64>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobResponse {
+    status: String,
+}
+
+/// API base URL: the given URL with `/api` added when it's missing, or
+/// nova.astrometry.net's
+pub fn api_base(api_url: Option<&str>) -> String {
+    match api_url
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+    {
+        Some(url) if url.ends_with("/api") => url.to_string(),
+        Some(url) => format!("{}/api", url),
+        None => DEFAULT_API_URL.to_string(),
+    }
+}
+
+/// multipart/form-data body with the `request-json` field the API reads,
+/// plus the image file for uploads
+fn multipart_body(boundary: &str, request_json: &str, file: Option<(&str, &[u8])>) -> Vec<u8> {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"request-json\"\r\n\
+         Content-Type: text/plain\r\n\r\n{}\r\n",
+        boundary, request_json
+    )
+    .into_bytes();
+    if let Some((filename, data)) = file {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n",
+                boundary,
+                filename.replace('"', "")
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// POST a form to an API endpoint and read its JSON answer
+async fn post(
+    network: &Network,
+    url: &str,
+    request_json: serde_json::Value,
+    file: Option<(&str, &[u8])>,
+) -> Result<ApiResponse, String> {
+    let boundary = format!("astra-{}", uuid::Uuid::new_v4().simple());
+    let body = multipart_body(&boundary, &request_json.to_string(), file);
+    let content_type = format!("multipart/form-data; boundary={}", boundary);
+    network
+        .send(Provider::AstrometryNet, |client| {
+            client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, &content_type)
+                .body(body.clone())
+        })
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid astrometry.net response: {}", e))
+}
+
+/// Fields of the upload's `request-json`
+fn upload_request(session: &str, options: &SubmitOptions) -> serde_json::Value {
+    let mut request = json!({
+        "session": session,
+        "publicly_visible": "n",
+        "allow_modifications": "n",
+        "allow_commercial_use": "n",
+    });
+    if let (Some(lower), Some(upper)) = (options.scale_lower, options.scale_upper) {
+        request["scale_units"] = json!("arcsecperpix");
+        request["scale_type"] = json!("ul");
+        request["scale_lower"] = json!(lower);
+        request["scale_upper"] = json!(upper);
+    }
+    if let (Some(ra), Some(dec)) = (options.hint_ra, options.hint_dec) {
+        request["center_ra"] = json!(ra);
+        request["center_dec"] = json!(dec);
+        request["radius"] = json!(options.search_radius.unwrap_or(DEFAULT_SEARCH_RADIUS));
+    }
+    request
+}
+
+/// Log in and upload an image. Returns the submission id.
+pub async fn submit(
+    network: &Network,
+    api: &str,
+    api_key: &str,
+    image_path: &Path,
+    options: &SubmitOptions,
+) -> Result<u64, String> {
+    let data = tokio::fs::read(image_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", image_path.display(), e))?;
+    let filename = image_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "image".to_string());
+
+    let login = post(
+        network,
+        &format!("{}/login", api),
+        json!({ "apikey": api_key }),
+        None,
+    )
+    .await?
+    .check("login")?;
+    let session = login
+        .session
+        .ok_or_else(|| "astrometry.net login returned no session".to_string())?;
+
+    let upload = post(
+        network,
+        &format!("{}/upload", api),
+        upload_request(&session, options),
+        Some((&filename, &data)),
+    )
+    .await?
+    .check("upload")?;
+    let submission_id = upload
+        .subid
+        .ok_or_else(|| "astrometry.net upload returned no submission id".to_string())?;
+    log::info!(
+        "Submitted {} to astrometry.net as submission {}",
+        filename,
+        submission_id
+    );
+    Ok(submission_id)
+}
+
+/// Check on a submission. `solve_time` is the time since it was submitted,
+/// reported in the result once the job is done.
+pub async fn check(
+    network: &Network,
+    api: &str,
+    submission_id: u64,
+    solve_time: f64,
+) -> Result<JobStatus, String> {
+    let submission: SubmissionResponse = network
+        .send(Provider::AstrometryNet, |client| {
+            client.get(format!("{}/submissions/{}", api, submission_id))
+        })
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid astrometry.net submission: {}", e))?;
+    let Some(job_id) = submission.jobs.into_iter().flatten().next() else {
+        return Ok(JobStatus::Queued);
+    };
+
+    let job: JobResponse = network
+        .send(Provider::AstrometryNet, |client| {
+            client.get(format!("{}/jobs/{}", api, job_id))
+        })
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid astrometry.net job status: {}", e))?;
+    match job.status.as_str() {
+        "success" => {}
+        "failure" => {
+            return Ok(JobStatus::Finished(
+                job_id,
+                failure(SOLVER, solve_time, "No solution found"),
+            ))
+        }
+        _ => return Ok(JobStatus::Solving(job_id)),
+    }
+
+    // The WCS file is served from the site root rather than the API
+    let site = api.strip_suffix("/api").unwrap_or(api);
+    let header = network
+        .send(Provider::AstrometryNet, |client| {
+            client.get(format!("{}/wcs_file/{}", site, job_id))
+        })
+        .await?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download astrometry.net solution: {}", e))?;
+    Ok(JobStatus::Finished(job_id, solution(&header, solve_time)))
+}
+
+/// Result from a job's WCS file
+fn solution(wcs_file: &[u8], solve_time: f64) -> PlateSolveResult {
+    let header = parse_header(wcs_file);
+    let dimension = |key: &str| header.get(key)?.parse::<f64>().ok().map(|v| v as i32);
+    let dimensions = dimension("IMAGEW").zip(dimension("IMAGEH"));
+    wcs_solution(SOLVER, &header, dimensions, solve_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_base_adds_api_path() {
+        assert_eq!(api_base(None), DEFAULT_API_URL);
+        assert_eq!(api_base(Some(" ")), DEFAULT_API_URL);
+        assert_eq!(
+            api_base(Some("http://solver.local:8080/")),
+            "http://solver.local:8080/api"
+        );
+        assert_eq!(
+            api_base(Some("http://solver.local/api/")),
+            "http://solver.local/api"
+        );
+    }
+
+    #[test]
+    fn upload_request_includes_hints() {
+        let options = SubmitOptions {
+            scale_lower: Some(1.0),
+            scale_upper: Some(1.5),
+            hint_ra: Some(83.82),
+            hint_dec: Some(-5.39),
+            search_radius: None,
+        };
+        let request = upload_request("abc", &options);
+        assert_eq!(request["session"], "abc");
+        assert_eq!(request["scale_type"], "ul");
+        assert_eq!(request["scale_upper"], 1.5);
+        assert_eq!(request["center_dec"], -5.39);
+        assert_eq!(request["radius"], 10.0);
+
+        let blind = upload_request("abc", &SubmitOptions::default());
+        assert!(blind.get("scale_lower").is_none());
+        assert!(blind.get("center_ra").is_none());
+    }
+
+    #[test]
+    fn multipart_body_has_fields_and_file() {
+        let body = multipart_body("b", r#"{"session":"abc"}"#, Some(("m42.fits", b"SIMPLE")));
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("--b\r\nContent-Disposition: form-data; name=\"request-json\""));
+        assert!(body.contains("\r\n\r\n{\"session\":\"abc\"}\r\n"));
+        assert!(body.contains("name=\"file\"; filename=\"m42.fits\""));
+        assert!(body.contains("\r\n\r\nSIMPLE\r\n"));
+        assert!(body.ends_with("--b--\r\n"));
+    }
+
+    #[test]
+    fn reads_solution_from_wcs_file() {
+        let cards = [
+            "SIMPLE  =                    T",
+            "IMAGEW  =                 4000",
+            "IMAGEH  =                 3000",
+            "CTYPE1  = 'RA---TAN'",
+            "CTYPE2  = 'DEC--TAN'",
+            "CRVAL1  =                83.82",
+            "CRVAL2  =                -5.39",
+            "CRPIX1  =               2000.5",
+            "CRPIX2  =               1500.5",
+            "CD1_1   =          -0.00025",
+            "CD1_2   =                  0.0",
+            "CD2_1   =                  0.0",
+            "CD2_2   =           0.00025",
+            "END",
+        ];
+        let header: String = cards.iter().map(|c| format!("{:<80}", c)).collect();
+        let result = solution(header.as_bytes(), 12.0);
+        assert!(result.success);
+        assert_eq!((result.image_width, result.image_height), (4000, 3000));
+        assert!((result.pixel_scale - 0.9).abs() < 1e-6);
+        assert!((result.center_ra - 83.82).abs() < 0.01);
+        assert_eq!(result.solver, "nova");
+        assert_eq!(result.solve_time, 12.0);
+    }
+}
@@ -0,0 +1,328 @@
+//! TAN (gnomonic) world coordinate system with optional SIP distortion.
+//!
+//! Serializes to the same `{crval, crpix, cd}` shape the Python solvers
+//! store under `plate_solve.wcs`, plus a `sip` object when the solution has
+//! distortion terms. Pixel coordinates are 0-based like astropy's, so they
+//! match the `pixelX`/`pixelY` of catalog annotations.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wcs {
+    /// Reference point RA/Dec in degrees
+    pub crval: [f64; 2],
+    /// Reference pixel (FITS, 1-based)
+    pub crpix: [f64; 2],
+    /// Linear transform from pixel offsets to intermediate coordinates (degrees)
+    pub cd: [[f64; 2]; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sip: Option<Sip>,
+}
+
+/// SIP distortion polynomials, indexed `[p][q]` for the `u^p v^q` term.
+/// `ap`/`bp` are the inverse polynomials, which not every solver writes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sip {
+    pub a: Vec<Vec<f64>>,
+    pub b: Vec<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ap: Option<Vec<Vec<f64>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bp: Option<Vec<Vec<f64>>>,
+}
+
+/// Fixed-point iterations used to invert SIP when there are no AP/BP terms
+const SIP_INVERSE_ITERATIONS: usize = 20;
+
+impl Wcs {
+    /// WCS from FITS header keywords: CRVAL, CRPIX and either the CD matrix
+    /// or CDELT/CROTA2, with SIP terms when CTYPE1 ends in `-SIP`
+    pub fn from_header(header: &HashMap<String, String>) -> Option<Wcs> {
+        let num = |key: &str| header.get(key).and_then(|v| v.parse::<f64>().ok());
+        let crval = [num("CRVAL1")?, num("CRVAL2")?];
+        let crpix = [num("CRPIX1")?, num("CRPIX2")?];
+        let cd = cd_matrix(header)?;
+        let has_sip = header.get("CTYPE1").is_some_and(|c| c.ends_with("-SIP"));
+        let sip = has_sip.then(|| Sip::from_header(header)).flatten();
+        Some(Wcs {
+            crval,
+            crpix,
+            cd,
+            sip,
+        })
+    }
+
+    /// Approximate WCS from a solution's center, scale (arcsec/pixel) and
+    /// rotation (degrees), with the center at the middle of the image
+    pub fn from_center(
+        center_ra: f64,
+        center_dec: f64,
+        pixel_scale: f64,
+        rotation: f64,
+        image_width: i32,
+        image_height: i32,
+    ) -> Wcs {
+        let scale = pixel_scale / 3600.0;
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        Wcs {
+            crval: [center_ra, center_dec],
+            crpix: [
+                (image_width as f64 + 1.0) / 2.0,
+                (image_height as f64 + 1.0) / 2.0,
+            ],
+            cd: [[-scale * cos, scale * sin], [scale * sin, scale * cos]],
+            sip: None,
+        }
+    }
+
+    /// RA/Dec in degrees of a 0-based pixel position
+    pub fn pixel_to_sky(&self, x: f64, y: f64) -> (f64, f64) {
+        let (mut u, mut v) = (x + 1.0 - self.crpix[0], y + 1.0 - self.crpix[1]);
+        if let Some(sip) = &self.sip {
+            (u, v) = (u + poly(&sip.a, u, v), v + poly(&sip.b, u, v));
+        }
+        let cd = self.cd;
+        let xi = cd[0][0] * u + cd[0][1] * v;
+        let eta = cd[1][0] * u + cd[1][1] * v;
+        deproject(self.crval, xi, eta)
+    }
+
+    /// 0-based pixel position of an RA/Dec in degrees, or None if it is
+    /// more than 90° from the reference point and can't be projected
+    pub fn sky_to_pixel(&self, ra: f64, dec: f64) -> Option<(f64, f64)> {
+        let (xi, eta) = project(self.crval, ra, dec)?;
+        let cd = self.cd;
+        let det = cd[0][0] * cd[1][1] - cd[0][1] * cd[1][0];
+        if det == 0.0 {
+            return None;
+        }
+        let u0 = (cd[1][1] * xi - cd[0][1] * eta) / det;
+        let v0 = (cd[0][0] * eta - cd[1][0] * xi) / det;
+
+        let (u, v) = match &self.sip {
+            Some(Sip {
+                ap: Some(ap),
+                bp: Some(bp),
+                ..
+            }) => (u0 + poly(ap, u0, v0), v0 + poly(bp, u0, v0)),
+            Some(sip) => {
+                let (mut u, mut v) = (u0, v0);
+                for _ in 0..SIP_INVERSE_ITERATIONS {
+                    (u, v) = (u0 - poly(&sip.a, u, v), v0 - poly(&sip.b, u, v));
+                }
+                (u, v)
+            }
+            None => (u0, v0),
+        };
+        Some((u + self.crpix[0] - 1.0, v + self.crpix[1] - 1.0))
+    }
+}
+
+impl Sip {
+    fn from_header(header: &HashMap<String, String>) -> Option<Sip> {
+        let matrix = |prefix: &str| -> Option<Vec<Vec<f64>>> {
+            let order = header
+                .get(&format!("{}_ORDER", prefix))?
+                .parse::<usize>()
+                .ok()?;
+            let mut terms = vec![vec![0.0; order + 1]; order + 1];
+            for (p, row) in terms.iter_mut().enumerate() {
+                for (q, term) in row.iter_mut().enumerate().take(order + 1 - p) {
+                    if let Some(value) = header.get(&format!("{}_{}_{}", prefix, p, q)) {
+                        *term = value.parse().ok()?;
+                    }
+                }
+            }
+            Some(terms)
+        };
+        Some(Sip {
+            a: matrix("A")?,
+            b: matrix("B")?,
+            ap: matrix("AP"),
+            bp: matrix("BP"),
+        })
+    }
+}
+
+/// CD matrix from CD keywords, or from CDELT/CROTA2
+pub(super) fn cd_matrix(header: &HashMap<String, String>) -> Option<[[f64; 2]; 2]> {
+    let num = |key: &str| header.get(key).and_then(|v| v.parse::<f64>().ok());
+    match (num("CD1_1"), num("CD1_2"), num("CD2_1"), num("CD2_2")) {
+        (Some(cd11), Some(cd12), Some(cd21), Some(cd22)) => Some([[cd11, cd12], [cd21, cd22]]),
+        _ => num("CDELT1").zip(num("CDELT2")).map(|(cdelt1, cdelt2)| {
+            let (sin, cos) = num("CROTA2").unwrap_or(0.0).to_radians().sin_cos();
+            [[cdelt1 * cos, -cdelt2 * sin], [cdelt1 * sin, cdelt2 * cos]]
+        }),
+    }
+}
+
+/// Sum of `terms[p][q] * u^p * v^q`
+fn poly(terms: &[Vec<f64>], u: f64, v: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut up = 1.0;
+    for row in terms {
+        let mut vq = 1.0;
+        for term in row {
+            sum += term * up * vq;
+            vq *= v;
+        }
+        up *= u;
+    }
+    sum
+}
+
+/// Gnomonic deprojection of intermediate coordinates (degrees) to RA/Dec
+fn deproject(crval: [f64; 2], xi: f64, eta: f64) -> (f64, f64) {
+    let (xi, eta) = (xi.to_radians(), eta.to_radians());
+    let (ra0, dec0) = (crval[0].to_radians(), crval[1].to_radians());
+
+    let denom = dec0.cos() - eta * dec0.sin();
+    let ra = ra0 + xi.atan2(denom);
+    let dec = (dec0.sin() + eta * dec0.cos()).atan2(xi.hypot(denom));
+    (ra.to_degrees().rem_euclid(360.0), dec.to_degrees())
+}
+
+/// Gnomonic projection of RA/Dec to intermediate coordinates (degrees)
+fn project(crval: [f64; 2], ra: f64, dec: f64) -> Option<(f64, f64)> {
+    let (ra0, dec0) = (crval[0].to_radians(), crval[1].to_radians());
+    let (ra, dec) = (ra.to_radians(), dec.to_radians());
+    let dra = ra - ra0;
+
+    let cos_c = dec0.sin() * dec.sin() + dec0.cos() * dec.cos() * dra.cos();
+    if cos_c <= 0.0 {
+        return None;
+    }
+    let xi = dec.cos() * dra.sin() / cos_c;
+    let eta = (dec0.cos() * dec.sin() - dec0.sin() * dec.cos() * dra.cos()) / cos_c;
+    Some((xi.to_degrees(), eta.to_degrees()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(cards: &[(&str, &str)]) -> HashMap<String, String> {
+        cards
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn simple_wcs() -> Wcs {
+        Wcs {
+            crval: [10.0, 0.0],
+            crpix: [1.0, 1.0],
+            cd: [[-1e-3, 0.0], [0.0, 1e-3]],
+            sip: None,
+        }
+    }
+
+    #[test]
+    fn tan_deprojection_inverts_offsets() {
+        let wcs = simple_wcs();
+        let (ra, dec) = wcs.pixel_to_sky(0.0, 0.0);
+        assert!((ra - 10.0).abs() < 1e-12 && dec.abs() < 1e-12);
+        // 1000 pixels up at 1e-3 deg/px on the equator is atan(1 deg) north
+        let (ra, dec) = wcs.pixel_to_sky(0.0, 1000.0);
+        assert!((ra - 10.0).abs() < 1e-9);
+        assert!(
+            (dec - 1f64.to_radians().atan().to_degrees()).abs() < 1e-9,
+            "{}",
+            dec
+        );
+        // RA wraps into [0, 360)
+        let wcs = Wcs {
+            crval: [0.0, 0.0],
+            ..simple_wcs()
+        };
+        let (ra, _) = wcs.pixel_to_sky(100.0, 0.0);
+        assert!((ra - 359.9).abs() < 1e-3, "{}", ra);
+    }
+
+    #[test]
+    fn sky_to_pixel_round_trips() {
+        let wcs = Wcs {
+            crval: [83.8, -5.4],
+            crpix: [2072.5, 1411.5],
+            cd: [[-3.3e-4, 6.0e-6], [6.0e-6, 3.3e-4]],
+            sip: None,
+        };
+        for (x, y) in [(0.0, 0.0), (2071.5, 1410.5), (4143.0, 2821.0)] {
+            let (ra, dec) = wcs.pixel_to_sky(x, y);
+            let (px, py) = wcs.sky_to_pixel(ra, dec).unwrap();
+            assert!(
+                (px - x).abs() < 1e-6 && (py - y).abs() < 1e-6,
+                "{} {}",
+                px,
+                py
+            );
+        }
+        // The opposite side of the sky doesn't project
+        assert!(wcs.sky_to_pixel(263.8, 5.4).is_none());
+    }
+
+    #[test]
+    fn sip_distortion_is_applied_and_inverted() {
+        let values = header(&[
+            ("CTYPE1", "RA---TAN-SIP"),
+            ("CRVAL1", "83.8"),
+            ("CRVAL2", "-5.4"),
+            ("CRPIX1", "500.5"),
+            ("CRPIX2", "500.5"),
+            ("CD1_1", "-3.3E-04"),
+            ("CD1_2", "0"),
+            ("CD2_1", "0"),
+            ("CD2_2", "3.3E-04"),
+            ("A_ORDER", "2"),
+            ("A_2_0", "2.0E-06"),
+            ("A_0_2", "-1.0E-06"),
+            ("B_ORDER", "2"),
+            ("B_1_1", "3.0E-06"),
+        ]);
+        let wcs = Wcs::from_header(&values).unwrap();
+        let sip = wcs.sip.as_ref().unwrap();
+        assert_eq!(sip.a[2][0], 2.0e-6);
+        assert_eq!(sip.b[1][1], 3.0e-6);
+        assert!(sip.ap.is_none());
+
+        // A corner moves by several pixels compared to the linear solution
+        let linear = Wcs {
+            sip: None,
+            ..wcs.clone()
+        };
+        let (ra, dec) = wcs.pixel_to_sky(999.0, 999.0);
+        let (lx, _) = linear.sky_to_pixel(ra, dec).unwrap();
+        assert!((lx - 999.0).abs() > 0.1, "{}", lx);
+
+        let (x, y) = wcs.sky_to_pixel(ra, dec).unwrap();
+        assert!(
+            (x - 999.0).abs() < 1e-6 && (y - 999.0).abs() < 1e-6,
+            "{} {}",
+            x,
+            y
+        );
+    }
+
+    #[test]
+    fn reads_python_solver_shape() {
+        let wcs: Wcs = serde_json::from_value(serde_json::json!({
+            "crval": [83.8, -5.4],
+            "crpix": [50.5, 25.5],
+            "cd": [[-1e-3, 0.0], [0.0, 1e-3]],
+        }))
+        .unwrap();
+        assert!(wcs.sip.is_none());
+        let json = serde_json::to_value(&wcs).unwrap();
+        assert!(json.get("sip").is_none());
+        assert_eq!(json["crpix"][0], 50.5);
+    }
+
+    #[test]
+    fn center_solution_puts_center_in_the_middle() {
+        let wcs = Wcs::from_center(83.8, -5.4, 3.6, 30.0, 100, 50);
+        let (ra, dec) = wcs.pixel_to_sky(49.5, 24.5);
+        assert!((ra - 83.8).abs() < 1e-9 && (dec + 5.4).abs() < 1e-9);
+    }
+}
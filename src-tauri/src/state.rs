@@ -3,7 +3,10 @@
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
-use crate::db::DbPool;
+use crate::commands::api_server::ApiServerHandle;
+use crate::db::SharedDbPool;
+use crate::network::Network;
+use crate::preview_cache::PreviewCache;
 use crate::share::auth::AuthSession;
 pub use hoardfs_volume::HoardFs;
 
@@ -17,12 +20,42 @@ pub struct AutoImportStatus {
     pub total_imported: usize,
     pub is_scanning: bool,
     pub errors: Vec<String>,
+    /// Id the last scan's log lines are tagged with
+    pub last_job_id: Option<String>,
+}
+
+/// Progress of one background startup step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupStepState {
+    #[default]
+    Pending,
+    Running,
+    Ready,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupStep {
+    pub state: StartupStepState,
+    pub error: Option<String>,
+}
+
+/// Status of the initialization done after the window opens
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupStatus {
+    /// Opening the database and running migrations
+    pub database: StartupStep,
+    /// Python failures only disable the features that need it
+    pub python: StartupStep,
 }
 
 /// Application state shared across Tauri commands
 pub struct AppState {
-    /// Database connection pool
-    pub db: DbPool,
+    /// Database connection pool (swappable so a restore takes effect immediately)
+    pub db: SharedDbPool,
     /// Current user ID (for standalone mode, always "local-user")
     pub user_id: String,
     /// Active astra.gallery auth session (if signed in)
@@ -35,10 +68,23 @@ pub struct AppState {
     /// Wrapped in std::sync::Mutex because rusqlite::Connection is not Sync.
     /// Lock must NOT be held across .await points.
     pub hoardfs: Option<Arc<Mutex<HoardFs>>>,
+    /// Local HTTP API server, when running
+    pub api_server: Mutex<Option<ApiServerHandle>>,
+    /// Rate-limited access to external services, with offline mode
+    pub network: Arc<Network>,
+    /// Converted previews, evicted least recently used first
+    pub preview_cache: Arc<PreviewCache>,
+    /// Background startup progress (database and Python)
+    pub startup: Arc<Mutex<StartupStatus>>,
 }
 
 impl AppState {
-    pub fn new(db: DbPool, hoardfs: Option<Arc<Mutex<HoardFs>>>) -> Self {
+    pub fn new(
+        db: SharedDbPool,
+        hoardfs: Option<Arc<Mutex<HoardFs>>>,
+        network: Network,
+        preview_cache: PreviewCache,
+    ) -> Self {
         Self {
             db,
             user_id: "local-user".to_string(),
@@ -46,6 +92,10 @@ impl AppState {
             auto_import_cancel: Mutex::new(None),
             auto_import_status: Arc::new(Mutex::new(AutoImportStatus::default())),
             hoardfs,
+            api_server: Mutex::new(None),
+            network: Arc::new(network),
+            preview_cache: Arc::new(preview_cache),
+            startup: Arc::new(Mutex::new(StartupStatus::default())),
         }
     }
 }
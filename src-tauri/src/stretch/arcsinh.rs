@@ -0,0 +1,94 @@
+//! Arcsinh stretch algorithm.
+//!
+//! Clips shadows per channel like the MTF stretch, then applies one linked
+//! arcsinh curve to all channels so star colors are preserved. The curve's
+//! strength is chosen so the background lands at the requested level.
+
+use rayon::prelude::*;
+
+use super::mtf::{channel_stats, median_positive};
+
+/// Apply a linked arcsinh stretch to normalized [0,1] channel data.
+pub fn stretch_arcsinh_rgb(channels: &mut [Vec<f64>], bg_percent: f64, sigma: f64) {
+    // Step 1: Per-channel shadow clipping, keeping white at 1.0
+    channels.par_iter_mut().for_each(|ch| {
+        let (med, mad) = channel_stats(ch);
+        let shadow = (med - sigma * mad * 1.4826).max(0.0);
+        let range = 1.0 - shadow;
+        if range > 0.0 {
+            for v in ch.iter_mut() {
+                *v = ((*v - shadow) / range).clamp(0.0, 1.0);
+            }
+        }
+    });
+
+    // Step 2: Shared scale from reference channel (green or first)
+    let ref_idx = std::cmp::min(1, channels.len() - 1);
+    let Some(scale) = arcsinh_scale(median_positive(&channels[ref_idx]), bg_percent) else {
+        return;
+    };
+    let norm = scale.asinh();
+
+    // Step 3: asinh(x * s) / asinh(s) on all channels (parallel)
+    channels.par_iter_mut().for_each(|ch| {
+        for v in ch.iter_mut() {
+            *v = ((*v * scale).asinh() / norm).clamp(0.0, 1.0);
+        }
+    });
+}
+
+/// Scale `s` for which `asinh(s * median) / asinh(s) == target`.
+///
+/// Returns None when no brightening is needed (median already at or above
+/// the target). The curve grows with `s`, so the scale is found by
+/// bisection over its logarithm.
+pub fn arcsinh_scale(median: f64, target: f64) -> Option<f64> {
+    if !(median > 0.0 && median < target && target < 1.0) {
+        return None;
+    }
+
+    let curve = |log_s: f64| {
+        let s = 10f64.powf(log_s);
+        (s * median).asinh() / s.asinh()
+    };
+    let (mut lo, mut hi) = (-6.0, 12.0);
+    for _ in 0..60 {
+        let mid = (lo + hi) / 2.0;
+        if curve(mid) < target {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some(10f64.powf((lo + hi) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_maps_median_to_target() {
+        let scale = arcsinh_scale(0.01, 0.2).unwrap();
+        let stretched = (0.01 * scale).asinh() / scale.asinh();
+        assert!((stretched - 0.2).abs() < 1e-6, "{}", stretched);
+    }
+
+    #[test]
+    fn no_stretch_when_already_bright() {
+        assert!(arcsinh_scale(0.3, 0.2).is_none());
+        assert!(arcsinh_scale(0.0, 0.2).is_none());
+    }
+
+    #[test]
+    fn linked_stretch_brightens_background() {
+        let mut channels = vec![
+            (0..1000).map(|i| 0.01 + (i % 10) as f64 * 0.001).collect::<Vec<_>>(),
+            (0..1000).map(|i| 0.02 + (i % 10) as f64 * 0.001).collect::<Vec<_>>(),
+        ];
+        stretch_arcsinh_rgb(&mut channels, 0.2, 3.0);
+        let median = median_positive(&channels[1]);
+        assert!(median > 0.1 && median <= 1.0, "{}", median);
+        assert!(channels.iter().flatten().all(|v| (0.0..=1.0).contains(v)));
+    }
+}
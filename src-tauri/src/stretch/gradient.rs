@@ -244,3 +244,34 @@ fn solve_symmetric(a: &mut [f64], b: &mut [f64], n: usize) -> Option<Vec<f64>> {
     }
     Some(x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spread(data: &[f64]) -> f64 {
+        let (min, max) = data
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        max - min
+    }
+
+    #[test]
+    fn removes_linear_and_quadratic_gradients() {
+        let (width, height) = (200, 120);
+        let image = |f: &dyn Fn(f64, f64) -> f64| -> Vec<f64> {
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| f(x as f64 / width as f64, y as f64 / height as f64))
+                .collect()
+        };
+
+        let linear = image(&|x, y| 0.1 + 0.3 * x + 0.1 * y);
+        assert!(spread(&remove_gradient(&linear, width, height, 1)) < 0.01);
+
+        // A first-order fit can't model curvature; second order can
+        let curved = image(&|x, _| 0.1 + 0.4 * (x - 0.5) * (x - 0.5));
+        assert!(spread(&remove_gradient(&curved, width, height, 1)) > 0.02);
+        assert!(spread(&remove_gradient(&curved, width, height, 2)) < 0.01);
+    }
+}
@@ -6,12 +6,18 @@
 //! - Autocrop of dark stacking edges
 //! - Per-channel normalization
 //! - Background gradient removal (polynomial surface fit)
-//! - MTF (Midtones Transfer Function) stretch
-//! - JPEG output (via image crate)
+//! - MTF (Midtones Transfer Function) or arcsinh stretch
+//! - JPEG/PNG output (via image crate) and FITS output
+//! - ICC-tagged 16-bit TIFF and JPEG derivatives of processed images
 
+mod arcsinh;
 mod autocrop;
 mod gradient;
 pub mod mtf;
+mod output;
 mod pipeline;
 
-pub use pipeline::{generate_preview, read_fits_pixels, StretchParams};
+pub use pipeline::{
+    generate_preview, read_fits_pixels, stretch_fits, StretchMethod, StretchParams, StretchedImage,
+};
+pub use output::srgb_icc_profile;
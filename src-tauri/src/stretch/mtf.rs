@@ -116,7 +116,7 @@ fn apply_mtf(data: &mut [f64], m: f64) {
     }
 }
 
-fn channel_stats(data: &[f64]) -> (f64, f64) {
+pub(super) fn channel_stats(data: &[f64]) -> (f64, f64) {
     // Count positives first to size the allocation exactly
     let count = data.iter().filter(|&&v| v > 0.0).count();
     if count == 0 {
@@ -133,7 +133,7 @@ fn channel_stats(data: &[f64]) -> (f64, f64) {
     (med, mad)
 }
 
-fn median_positive(data: &[f64]) -> f64 {
+pub(super) fn median_positive(data: &[f64]) -> f64 {
     let count = data.iter().filter(|&&v| v > 0.0).count();
     if count == 0 {
         return 0.0;
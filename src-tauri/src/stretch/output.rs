@@ -0,0 +1,282 @@
+//! Derivative outputs of a processed image: 16-bit TIFF for further editing
+//! and JPEG for sharing.
+//!
+//! Stretched values are display-referred, so they are written as sRGB-encoded
+//! samples (as the PNG preview is) and tagged with an sRGB ICC profile so
+//! editors and browsers don't have to guess. Mono images are written as RGB.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use image::{DynamicImage, ImageBuffer, ImageEncoder, Rgb};
+
+use super::pipeline::{read_fits_pixels, StretchedImage};
+
+/// Entries in the profile's tone curves
+const CURVE_POINTS: usize = 1024;
+
+/// sRGB primaries adapted to D50 (Bradford), as the ICC PCS expects
+const RED: [f64; 3] = [0.436_074_7, 0.222_504_5, 0.013_932_2];
+const GREEN: [f64; 3] = [0.385_064_9, 0.716_878_6, 0.097_104_5];
+const BLUE: [f64; 3] = [0.143_080_4, 0.060_616_9, 0.714_173_3];
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+impl StretchedImage {
+    /// Read a processed FITS file (values in [0,1], channel-first), as
+    /// written by `write_fits` or the Python processor
+    pub fn read_fits(path: &Path) -> Result<Self, String> {
+        let (width, height, pixels, is_color) = read_fits_pixels(path)?;
+        let channel_size = width * height;
+        let count = if is_color { 3 } else { 1 };
+        if pixels.len() < channel_size * count {
+            return Err("FITS data is smaller than its dimensions".to_string());
+        }
+        let channels = pixels
+            .chunks_exact(channel_size)
+            .take(count)
+            .map(|c| c.iter().map(|v| v.clamp(0.0, 1.0)).collect())
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            channels,
+        })
+    }
+
+    /// Convert to 16-bit RGB (mono is replicated across channels).
+    fn to_rgb16_image(&self) -> Result<ImageBuffer<Rgb<u16>, Vec<u16>>, String> {
+        let channel_size = self.width * self.height;
+        let sample = |c: usize, i: usize| {
+            let channel = &self.channels[c.min(self.channels.len() - 1)];
+            (channel[i] * 65535.0).round().clamp(0.0, 65535.0) as u16
+        };
+        let rgb = (0..channel_size)
+            .flat_map(|i| [sample(0, i), sample(1, i), sample(2, i)])
+            .collect();
+        ImageBuffer::from_raw(self.width as u32, self.height as u32, rgb)
+            .ok_or_else(|| "Failed to create image buffer".to_string())
+    }
+
+    /// Write a 16-bit RGB TIFF tagged as sRGB.
+    pub fn write_tiff16(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create TIFF: {}", e))?;
+        let mut encoder = image::codecs::tiff::TiffEncoder::new(BufWriter::new(file));
+        encoder
+            .set_icc_profile(srgb_icc_profile().to_vec())
+            .map_err(|e| format!("Failed to embed ICC profile: {}", e))?;
+        DynamicImage::ImageRgb16(self.to_rgb16_image()?)
+            .write_with_encoder(encoder)
+            .map_err(|e| format!("Failed to write TIFF: {}", e))
+    }
+
+    /// Write an 8-bit JPEG tagged as sRGB.
+    pub fn write_jpeg(&self, path: &Path, quality: u8) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create JPEG: {}", e))?;
+        let mut encoder =
+            image::codecs::jpeg::JpegEncoder::new_with_quality(BufWriter::new(file), quality);
+        encoder
+            .set_icc_profile(srgb_icc_profile().to_vec())
+            .map_err(|e| format!("Failed to embed ICC profile: {}", e))?;
+        DynamicImage::ImageRgb8(self.to_rgb_image()?)
+            .write_with_encoder(encoder)
+            .map_err(|e| format!("Failed to write JPEG: {}", e))
+    }
+}
+
+/// An ICC v2 display profile for sRGB
+pub fn srgb_icc_profile() -> &'static [u8] {
+    static PROFILE: OnceLock<Vec<u8>> = OnceLock::new();
+    PROFILE.get_or_init(build_srgb_profile)
+}
+
+fn s15_fixed16(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    xyz.iter().for_each(|&v| tag.extend(s15_fixed16(v)));
+    tag
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut tag = b"text\0\0\0\0".to_vec();
+    tag.extend(text.as_bytes());
+    tag.push(0);
+    tag
+}
+
+fn description_tag(text: &str) -> Vec<u8> {
+    let mut tag = b"desc\0\0\0\0".to_vec();
+    tag.extend((text.len() as u32 + 1).to_be_bytes());
+    tag.extend(text.as_bytes());
+    tag.push(0);
+    // Empty Unicode and ScriptCode descriptions
+    tag.extend([0u8; 8]);
+    tag.extend([0u8; 3]);
+    tag.extend([0u8; 67]);
+    tag
+}
+
+/// sRGB decoding curve: encoded value to linear light
+fn srgb_to_linear(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn curve_tag() -> Vec<u8> {
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    tag.extend((CURVE_POINTS as u32).to_be_bytes());
+    for i in 0..CURVE_POINTS {
+        let linear = srgb_to_linear(i as f64 / (CURVE_POINTS - 1) as f64);
+        tag.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    tag
+}
+
+fn build_srgb_profile() -> Vec<u8> {
+    let curve = curve_tag();
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", description_tag("sRGB")),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(D50)),
+        (b"rXYZ", xyz_tag(RED)),
+        (b"gXYZ", xyz_tag(GREEN)),
+        (b"bXYZ", xyz_tag(BLUE)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    // Tag data follows the header and tag table, each entry 4-byte aligned
+    let mut table = Vec::new();
+    let mut data = Vec::new();
+    let data_start = 128 + 4 + tags.len() * 12;
+    for (signature, tag) in &tags {
+        table.extend(*signature);
+        table.extend(((data_start + data.len()) as u32).to_be_bytes());
+        table.extend((tag.len() as u32).to_be_bytes());
+        data.extend(tag);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let size = data_start + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend((size as u32).to_be_bytes());
+    profile.extend([0u8; 4]); // preferred CMM
+    profile.extend([0x02, 0x10, 0x00, 0x00]); // version 2.1
+    profile.extend(b"mntrRGB XYZ ");
+    // Creation date: 2024-01-01 00:00:00
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        profile.extend(field.to_be_bytes());
+    }
+    profile.extend(b"acsp");
+    profile.extend([0u8; 24]); // platform, flags, manufacturer, model, attributes
+    profile.extend(0u32.to_be_bytes()); // perceptual intent
+    D50.iter().for_each(|&v| profile.extend(s15_fixed16(v)));
+    profile.extend([0u8; 4]); // creator
+    profile.extend([0u8; 44]); // profile ID and reserved
+    debug_assert_eq!(profile.len(), 128);
+
+    profile.extend((tags.len() as u32).to_be_bytes());
+    profile.extend(table);
+    profile.extend(data);
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageDecoder;
+    use std::io::BufReader;
+
+    fn gradient() -> StretchedImage {
+        let (width, height) = (8, 4);
+        let ramp: Vec<f64> = (0..width * height)
+            .map(|i| i as f64 / (width * height - 1) as f64)
+            .collect();
+        StretchedImage {
+            width,
+            height,
+            channels: vec![ramp.clone(), ramp.iter().map(|v| 1.0 - v).collect(), ramp],
+        }
+    }
+
+    #[test]
+    fn profile_is_well_formed() {
+        let profile = srgb_icc_profile();
+        let size = u32::from_be_bytes(profile[..4].try_into().unwrap()) as usize;
+        assert_eq!(size, profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+        assert_eq!(&profile[12..24], b"mntrRGB XYZ ");
+
+        let count = u32::from_be_bytes(profile[128..132].try_into().unwrap()) as usize;
+        assert_eq!(count, 9);
+        for entry in profile[132..132 + count * 12].chunks(12) {
+            let offset = u32::from_be_bytes(entry[4..8].try_into().unwrap()) as usize;
+            let len = u32::from_be_bytes(entry[8..12].try_into().unwrap()) as usize;
+            assert_eq!(offset % 4, 0);
+            assert!(offset + len <= profile.len());
+        }
+    }
+
+    #[test]
+    fn tiff_keeps_sixteen_bits_and_the_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.tiff");
+        gradient().write_tiff16(&path).unwrap();
+
+        let mut decoder =
+            image::codecs::tiff::TiffDecoder::new(BufReader::new(File::open(&path).unwrap()))
+                .unwrap();
+        assert_eq!(
+            decoder.icc_profile().unwrap().as_deref(),
+            Some(srgb_icc_profile())
+        );
+        let image = DynamicImage::from_decoder(decoder).unwrap().into_rgb16();
+        assert_eq!(image.dimensions(), (8, 4));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 65535, 0]);
+        // A step of 1/31 isn't representable in 8 bits
+        assert_eq!(
+            image.get_pixel(1, 0).0[0],
+            (65535.0_f64 / 31.0).round() as u16
+        );
+    }
+
+    #[test]
+    fn jpeg_carries_the_profile_and_mono_becomes_rgb() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jpg");
+        let mono = StretchedImage {
+            width: 16,
+            height: 16,
+            channels: vec![vec![0.5; 256]],
+        };
+        mono.write_jpeg(&path, 95).unwrap();
+
+        let mut decoder =
+            image::codecs::jpeg::JpegDecoder::new(BufReader::new(File::open(&path).unwrap()))
+                .unwrap();
+        assert_eq!(
+            decoder.icc_profile().unwrap().as_deref(),
+            Some(srgb_icc_profile())
+        );
+        assert_eq!(decoder.color_type(), image::ColorType::Rgb8);
+    }
+
+    #[test]
+    fn processed_fits_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("processed.fits");
+        gradient().write_fits(&path).unwrap();
+
+        let image = StretchedImage::read_fits(&path).unwrap();
+        assert_eq!((image.width, image.height, image.channels.len()), (8, 4, 3));
+        assert!((image.channels[1][0] - 1.0).abs() < 1e-6);
+    }
+}
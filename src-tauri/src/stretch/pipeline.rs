@@ -1,21 +1,36 @@
 //! Full preview generation pipeline: FITS → stretch → JPEG.
 //!
-//! Replaces the Python/processinator path for `regenerate_preview`.
+//! Replaces the Python/processinator path for `regenerate_preview`, and
+//! backs image processing when Python is unavailable.
 
 use std::path::Path;
 
 use rayon::prelude::*;
 
+use super::arcsinh;
 use super::autocrop;
 use super::gradient;
 use super::mtf;
 
+/// Stretch curve applied after normalization and gradient removal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StretchMethod {
+    /// Midtones transfer function (STF-style auto-stretch)
+    #[default]
+    Mtf,
+    Arcsinh,
+}
+
 /// Parameters for the stretch pipeline.
 pub struct StretchParams {
+    /// Target background level after stretching
     pub bg_percent: f64,
     pub sigma: f64,
     pub gradient_removal: bool,
+    /// Order of the background polynomial fitted for gradient removal
+    pub gradient_order: usize,
     pub autocrop: bool,
+    pub method: StretchMethod,
 }
 
 impl Default for StretchParams {
@@ -24,11 +39,62 @@ impl Default for StretchParams {
             bg_percent: 0.15,
             sigma: 3.0,
             gradient_removal: true,
+            gradient_order: 2,
             autocrop: true,
+            method: StretchMethod::Mtf,
         }
     }
 }
 
+/// Stretched image data, each channel normalized to [0,1].
+pub struct StretchedImage {
+    pub width: usize,
+    pub height: usize,
+    /// One channel (mono) or three (RGB), each row-major
+    pub channels: Vec<Vec<f64>>,
+}
+
+impl StretchedImage {
+    /// Convert to 8-bit RGB (mono is replicated across channels).
+    pub fn to_rgb_image(&self) -> Result<image::RgbImage, String> {
+        let channel_size = self.width * self.height;
+        let mut rgb = vec![0u8; channel_size * 3];
+
+        if self.channels.len() >= 3 {
+            for i in 0..channel_size {
+                rgb[i * 3] = (self.channels[0][i] * 255.0).clamp(0.0, 255.0) as u8;
+                rgb[i * 3 + 1] = (self.channels[1][i] * 255.0).clamp(0.0, 255.0) as u8;
+                rgb[i * 3 + 2] = (self.channels[2][i] * 255.0).clamp(0.0, 255.0) as u8;
+            }
+        } else {
+            for i in 0..channel_size {
+                let v = (self.channels[0][i] * 255.0).clamp(0.0, 255.0) as u8;
+                rgb[i * 3] = v;
+                rgb[i * 3 + 1] = v;
+                rgb[i * 3 + 2] = v;
+            }
+        }
+
+        image::RgbImage::from_raw(self.width as u32, self.height as u32, rgb)
+            .ok_or_else(|| "Failed to create image buffer".to_string())
+    }
+
+    /// Write the stretched data as a 32-bit float FITS file.
+    pub fn write_fits(&self, path: &Path) -> Result<(), String> {
+        use fitrs::{Fits, Hdu};
+
+        let data: Vec<f32> = self.channels.iter().flatten().map(|&v| v as f32).collect();
+        let shape: Vec<usize> = if self.channels.len() > 1 {
+            vec![self.width, self.height, self.channels.len()]
+        } else {
+            vec![self.width, self.height]
+        };
+        Fits::create(path, Hdu::new(&shape, data))
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write FITS: {}", e))
+    }
+}
+
 /// Generate a JPEG preview from a FITS file using the native Rust pipeline.
 ///
 /// Returns the output path on success.
@@ -38,9 +104,29 @@ pub fn generate_preview(
     params: &StretchParams,
 ) -> Result<String, String> {
     let start = std::time::Instant::now();
+    let stretched = stretch_fits(fits_path, params)?;
+
+    // Step 6: Interleave channels → RGB bytes → JPEG
+    let t_save = std::time::Instant::now();
+    let img = stretched.to_rgb_image()?;
+
+    img.save(output_path)
+        .map_err(|e| format!("Failed to save JPEG: {}", e))?;
+
+    let file_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    log::info!("stretch: save in {:?} ({} bytes)", t_save.elapsed(), file_size);
+    log::info!("stretch: total pipeline in {:?}", start.elapsed());
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Read a FITS file and run it through the stretch pipeline.
+pub fn stretch_fits(fits_path: &Path, params: &StretchParams) -> Result<StretchedImage, String> {
+    let start = std::time::Instant::now();
 
-    log::info!("stretch: params bg_percent={}, sigma={}, gradient={}, autocrop={}",
-        params.bg_percent, params.sigma, params.gradient_removal, params.autocrop);
+    log::info!("stretch: params bg_percent={}, sigma={}, gradient={} (order {}), autocrop={}, method={:?}",
+        params.bg_percent, params.sigma, params.gradient_removal, params.gradient_order,
+        params.autocrop, params.method);
 
     // Step 1: Read FITS
     let (width, height, pixels, is_color) = read_fits_pixels(fits_path)?;
@@ -120,49 +206,28 @@ pub fn generate_preview(
     // Step 4: Gradient removal (parallel per channel)
     if params.gradient_removal {
         let t_grad = std::time::Instant::now();
-        let order = 2;
         channels.par_iter_mut().for_each(|ch| {
-            let corrected = gradient::remove_gradient(ch, width, height, order);
+            let corrected = gradient::remove_gradient(ch, width, height, params.gradient_order);
             ch.copy_from_slice(&corrected);
         });
         log::info!("stretch: gradient removal in {:?}", t_grad.elapsed());
     }
 
-    // Step 5: MTF stretch
-    let t_mtf = std::time::Instant::now();
-    mtf::stretch_mtf_rgb(&mut channels, params.bg_percent, params.sigma);
-    log::info!("stretch: MTF in {:?}", t_mtf.elapsed());
-
-    // Step 6: Interleave channels → RGB bytes → JPEG
-    let t_save = std::time::Instant::now();
-    let mut rgb = vec![0u8; channel_size * 3];
-
-    if is_color {
-        for i in 0..channel_size {
-            rgb[i * 3] = (channels[0][i] * 255.0).clamp(0.0, 255.0) as u8;
-            rgb[i * 3 + 1] = (channels[1][i] * 255.0).clamp(0.0, 255.0) as u8;
-            rgb[i * 3 + 2] = (channels[2][i] * 255.0).clamp(0.0, 255.0) as u8;
-        }
-    } else {
-        for i in 0..channel_size {
-            let v = (channels[0][i] * 255.0).clamp(0.0, 255.0) as u8;
-            rgb[i * 3] = v;
-            rgb[i * 3 + 1] = v;
-            rgb[i * 3 + 2] = v;
+    // Step 5: Stretch
+    let t_stretch = std::time::Instant::now();
+    match params.method {
+        StretchMethod::Mtf => mtf::stretch_mtf_rgb(&mut channels, params.bg_percent, params.sigma),
+        StretchMethod::Arcsinh => {
+            arcsinh::stretch_arcsinh_rgb(&mut channels, params.bg_percent, params.sigma)
         }
     }
+    log::info!("stretch: {:?} in {:?}", params.method, t_stretch.elapsed());
 
-    let img = image::RgbImage::from_raw(width as u32, height as u32, rgb)
-        .ok_or("Failed to create image buffer")?;
-
-    img.save(output_path)
-        .map_err(|e| format!("Failed to save JPEG: {}", e))?;
-
-    let file_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
-    log::info!("stretch: save in {:?} ({} bytes)", t_save.elapsed(), file_size);
-    log::info!("stretch: total pipeline in {:?}", start.elapsed());
-
-    Ok(output_path.to_string_lossy().to_string())
+    Ok(StretchedImage {
+        width,
+        height,
+        channels,
+    })
 }
 
 /// Read FITS pixel data as f64 channels.
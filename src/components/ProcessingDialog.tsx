@@ -31,6 +31,7 @@ import {
   imageProcessApi,
   TARGET_TYPES,
   STRETCH_METHODS,
+  BACKGROUND_METHODS,
   type ProcessImageInput,
   type ProcessImageResponse,
   type TargetInfo,
@@ -80,6 +81,7 @@ export function ProcessingDialog({
   const [stretchMethod, setStretchMethod] = useState("statistical");
   const [stretchFactor, setStretchFactor] = useState(0.15);
   const [backgroundRemoval, setBackgroundRemoval] = useState(true);
+  const [backgroundMethod, setBackgroundMethod] = useState("median");
   const [starReduction, setStarReduction] = useState(false);
   const [colorCalibration, setColorCalibration] = useState(true);
   const [noiseReduction, setNoiseReduction] = useState(0);
@@ -216,6 +218,7 @@ export function ProcessingDialog({
         stretchMethod,
         stretchFactor,
         backgroundRemoval,
+        backgroundMethod,
         starReduction,
         colorCalibration,
         noiseReduction,
@@ -380,6 +383,27 @@ export function ProcessingDialog({
               />
             </div>
 
+            {backgroundRemoval && (
+              <div className="space-y-2">
+                <Label htmlFor="bg-method">Background Method</Label>
+                <Select
+                  value={backgroundMethod}
+                  onValueChange={setBackgroundMethod}
+                >
+                  <SelectTrigger id="bg-method">
+                    <SelectValue />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {BACKGROUND_METHODS.map((method) => (
+                      <SelectItem key={method.value} value={method.value}>
+                        {method.label}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+              </div>
+            )}
+
             <div className="flex items-center justify-between">
               <div className="space-y-0.5">
                 <Label htmlFor="star-reduction">Star Reduction</Label>
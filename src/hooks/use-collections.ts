@@ -11,14 +11,17 @@ import {
   type CreateCollectionInput,
   type UpdateCollectionInput,
   type Image,
+  type SplitBy,
 } from "@/lib/tauri/commands";
 import { extractExposureSeconds } from "@/components/CatalogObjectDialog";
 
 export const collectionKeys = {
   all: ["collections"] as const,
   lists: () => [...collectionKeys.all, "list"] as const,
+  archived: () => [...collectionKeys.all, "archived"] as const,
   details: () => [...collectionKeys.all, "detail"] as const,
   detail: (id: string) => [...collectionKeys.details(), id] as const,
+  summary: (id: string) => [...collectionKeys.detail(id), "summary"] as const,
 };
 
 export function useCollections() {
@@ -30,6 +33,13 @@ export function useCollections() {
   });
 }
 
+export function useArchivedCollections() {
+  return useQuery({
+    queryKey: collectionKeys.archived(),
+    queryFn: () => collectionApi.getArchived(),
+  });
+}
+
 export function useCollection(id: string) {
   return useQuery({
     queryKey: collectionKeys.detail(id),
@@ -72,6 +82,85 @@ export function useDeleteCollection() {
   });
 }
 
+export function useArchiveCollection() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ id, archived }: { id: string; archived: boolean }) =>
+      collectionApi.archive(id, archived),
+    onSuccess: (data: Collection) => {
+      queryClient.invalidateQueries({ queryKey: collectionKeys.lists() });
+      queryClient.invalidateQueries({ queryKey: collectionKeys.archived() });
+      queryClient.setQueryData(collectionKeys.detail(data.id), data);
+    },
+  });
+}
+
+export function useMergeCollections() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ targetId, sourceId }: { targetId: string; sourceId: string }) =>
+      collectionApi.merge(targetId, sourceId),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: collectionKeys.all });
+    },
+  });
+}
+
+export function useDuplicateCollection() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({
+      id,
+      name,
+      includeImages,
+    }: {
+      id: string;
+      name?: string;
+      includeImages?: boolean;
+    }) => collectionApi.duplicate(id, name, includeImages),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: collectionKeys.lists() });
+    },
+  });
+}
+
+export function useSplitCollection() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ id, by, longitude }: { id: string; by: SplitBy; longitude?: number }) =>
+      collectionApi.split(id, by, longitude),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: collectionKeys.all });
+    },
+  });
+}
+
+export function useCollectionSummary(id: string) {
+  return useQuery({
+    queryKey: collectionKeys.summary(id),
+    queryFn: () => collectionApi.getSummary(id),
+    enabled: !!id,
+  });
+}
+
+export function useSetCollectionCover() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ id, imageId }: { id: string; imageId?: string }) =>
+      collectionApi.setCover(id, imageId),
+    onSuccess: (data: Collection) => {
+      queryClient.invalidateQueries({ queryKey: collectionKeys.lists() });
+      queryClient.invalidateQueries({ queryKey: collectionKeys.summary(data.id) });
+      queryClient.setQueryData(collectionKeys.detail(data.id), data);
+    },
+  });
+}
+
 // Helper to check if image is plate-solved
 function isPlateSolved(image: Image): boolean {
   if (!image.metadata) return false;
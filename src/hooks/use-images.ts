@@ -5,6 +5,8 @@
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
 import {
   imageApi,
+  subframeApi,
+  triageApi,
   type Image,
   type CreateImageInput,
   type UpdateImageInput,
@@ -17,6 +19,10 @@ export const imageKeys = {
     [...imageKeys.lists(), { collectionId }] as const,
   details: () => [...imageKeys.all, "detail"] as const,
   detail: (id: string) => [...imageKeys.details(), id] as const,
+  subframes: (id: string) => [...imageKeys.detail(id), "subframes"] as const,
+  triage: () => [...imageKeys.all, "triage"] as const,
+  comparison: (idA: string, idB: string) =>
+    [...imageKeys.all, "comparison", idA, idB] as const,
 };
 
 export function useImages() {
@@ -42,6 +48,22 @@ export function useImage(id: string) {
   });
 }
 
+export function useImageSubframes(id: string) {
+  return useQuery({
+    queryKey: imageKeys.subframes(id),
+    queryFn: () => subframeApi.getSubframes(id),
+    enabled: !!id,
+  });
+}
+
+export function useImageComparison(idA: string, idB: string) {
+  return useQuery({
+    queryKey: imageKeys.comparison(idA, idB),
+    queryFn: () => imageApi.compare(idA, idB),
+    enabled: !!idA && !!idB && idA !== idB,
+  });
+}
+
 export function useCreateImage() {
   const queryClient = useQueryClient();
 
@@ -75,3 +97,44 @@ export function useDeleteImage() {
     },
   });
 }
+
+export function useComputeAcquisitionBreakdown() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (imageId: string) => subframeApi.computeBreakdown(imageId),
+    onSuccess: (_, imageId) => {
+      queryClient.invalidateQueries({ queryKey: imageKeys.detail(imageId) });
+    },
+  });
+}
+
+export function useLinkSubframes() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: () => subframeApi.linkAll(),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: imageKeys.all });
+    },
+  });
+}
+
+export function useTriageImages() {
+  return useQuery({
+    queryKey: imageKeys.triage(),
+    queryFn: () => triageApi.getPending(),
+  });
+}
+
+export function useReviewTriageImages() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ ids, keep }: { ids: string[]; keep: boolean }) =>
+      triageApi.review(ids, keep),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: imageKeys.all });
+    },
+  });
+}
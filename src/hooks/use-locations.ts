@@ -0,0 +1,72 @@
+/**
+ * React Query hooks for observing locations
+ */
+
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import { locationApi, type ObserverLocation } from "@/lib/tauri/commands";
+
+export const locationKeys = {
+  all: ["locations"] as const,
+  lists: () => [...locationKeys.all, "list"] as const,
+};
+
+export function useLocations() {
+  return useQuery({
+    queryKey: locationKeys.lists(),
+    queryFn: () => locationApi.getAll(),
+  });
+}
+
+export function useCaptureCurrentLocation() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ name, setDefault }: { name?: string; setDefault?: boolean }) =>
+      locationApi.captureCurrent(name, setDefault),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: locationKeys.lists() });
+    },
+  });
+}
+
+export function useSetDefaultLocation() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ id, isDefault }: { id: string; isDefault: boolean }) =>
+      locationApi.setDefault(id, isDefault),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: locationKeys.lists() });
+    },
+  });
+}
+
+export function useDeleteLocation() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (id: string) => locationApi.delete(id),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: locationKeys.lists() });
+    },
+  });
+}
+
+export function useFindDarkSites() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({
+      location,
+      radiusKm,
+      maxSites,
+    }: {
+      location: ObserverLocation;
+      radiusKm: number;
+      maxSites?: number;
+    }) => locationApi.findDarkSites(location, radiusKm, maxSites),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: locationKeys.lists() });
+    },
+  });
+}
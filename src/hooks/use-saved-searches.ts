@@ -0,0 +1,87 @@
+/**
+ * React Query hooks for saved searches
+ */
+
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import {
+  savedSearchApi,
+  type CreateSavedSearchInput,
+  type SearchCriteria,
+  type SearchEntity,
+  type UpdateSavedSearchInput,
+} from "@/lib/tauri/commands";
+
+export const savedSearchKeys = {
+  all: ["savedSearches"] as const,
+  lists: () => [...savedSearchKeys.all, "list"] as const,
+  results: () => [...savedSearchKeys.all, "results"] as const,
+  result: (id: string, offset: number, limit: number) =>
+    [...savedSearchKeys.results(), id, { offset, limit }] as const,
+  adhoc: (
+    entity: SearchEntity,
+    criteria: SearchCriteria,
+    offset: number,
+    limit: number
+  ) =>
+    [...savedSearchKeys.results(), entity, { criteria, offset, limit }] as const,
+};
+
+export function useSavedSearches() {
+  return useQuery({
+    queryKey: savedSearchKeys.lists(),
+    queryFn: () => savedSearchApi.getAll(),
+  });
+}
+
+export function useSavedSearchResults(id: string, offset = 0, limit = 50) {
+  return useQuery({
+    queryKey: savedSearchKeys.result(id, offset, limit),
+    queryFn: () => savedSearchApi.execute(id, offset, limit),
+    enabled: !!id,
+  });
+}
+
+export function useSearchResults(
+  entity: SearchEntity,
+  criteria: SearchCriteria,
+  offset = 0,
+  limit = 50
+) {
+  return useQuery({
+    queryKey: savedSearchKeys.adhoc(entity, criteria, offset, limit),
+    queryFn: () => savedSearchApi.run(entity, criteria, offset, limit),
+  });
+}
+
+export function useCreateSavedSearch() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (input: CreateSavedSearchInput) => savedSearchApi.create(input),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: savedSearchKeys.lists() });
+    },
+  });
+}
+
+export function useUpdateSavedSearch() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (input: UpdateSavedSearchInput) => savedSearchApi.update(input),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: savedSearchKeys.all });
+    },
+  });
+}
+
+export function useDeleteSavedSearch() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (id: string) => savedSearchApi.delete(id),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: savedSearchKeys.lists() });
+    },
+  });
+}
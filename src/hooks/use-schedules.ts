@@ -6,6 +6,7 @@ import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
 import {
   scheduleApi,
   type ObservationSchedule,
+  type ObserverLocation,
   type CreateScheduleInput,
   type UpdateScheduleInput,
   type ScheduleItem,
@@ -15,8 +16,10 @@ export const scheduleKeys = {
   all: ["schedules"] as const,
   lists: () => [...scheduleKeys.all, "list"] as const,
   active: () => [...scheduleKeys.all, "active"] as const,
+  archived: () => [...scheduleKeys.all, "archived"] as const,
   details: () => [...scheduleKeys.all, "detail"] as const,
   detail: (id: string) => [...scheduleKeys.details(), id] as const,
+  warnings: (id: string) => [...scheduleKeys.detail(id), "warnings"] as const,
 };
 
 export function useSchedules() {
@@ -40,6 +43,21 @@ export function useActiveSchedules() {
   });
 }
 
+export function useActiveSchedulesForNight(date: string) {
+  return useQuery({
+    queryKey: [...scheduleKeys.active(), "night", date],
+    queryFn: () => scheduleApi.getActiveForNight(date),
+    enabled: !!date,
+  });
+}
+
+export function useArchivedSchedules() {
+  return useQuery({
+    queryKey: scheduleKeys.archived(),
+    queryFn: () => scheduleApi.getArchived(),
+  });
+}
+
 export function useSchedule(id: string) {
   return useQuery({
     queryKey: scheduleKeys.detail(id),
@@ -48,6 +66,18 @@ export function useSchedule(id: string) {
   });
 }
 
+export function useScheduleWarnings(
+  id: string,
+  location: ObserverLocation | null,
+  flipDelayMinutes?: number
+) {
+  return useQuery({
+    queryKey: [...scheduleKeys.warnings(id), location, flipDelayMinutes],
+    queryFn: () => scheduleApi.getWarnings(id, location!, flipDelayMinutes),
+    enabled: !!id && !!location,
+  });
+}
+
 export function useCreateSchedule() {
   const queryClient = useQueryClient();
 
@@ -69,6 +99,7 @@ export function useUpdateSchedule() {
       queryClient.invalidateQueries({ queryKey: scheduleKeys.lists() });
       queryClient.invalidateQueries({ queryKey: scheduleKeys.active() });
       queryClient.setQueryData(scheduleKeys.detail(data.id), data);
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.warnings(data.id) });
     },
   });
 }
@@ -85,6 +116,32 @@ export function useDeleteSchedule() {
   });
 }
 
+export function useArchiveSchedule() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ id, archived }: { id: string; archived: boolean }) =>
+      scheduleApi.archive(id, archived),
+    onSuccess: (data: ObservationSchedule) => {
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.lists() });
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.active() });
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.archived() });
+      queryClient.setQueryData(scheduleKeys.detail(data.id), data);
+    },
+  });
+}
+
+export function useArchivePastSchedules() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (before?: string) => scheduleApi.archivePast(before),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.all });
+    },
+  });
+}
+
 export function useAddScheduleItem() {
   const queryClient = useQueryClient();
 
@@ -95,6 +152,7 @@ export function useAddScheduleItem() {
       queryClient.invalidateQueries({ queryKey: scheduleKeys.lists() });
       queryClient.invalidateQueries({ queryKey: scheduleKeys.active() });
       queryClient.setQueryData(scheduleKeys.detail(data.id), data);
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.warnings(data.id) });
     },
   });
 }
@@ -109,6 +167,7 @@ export function useRemoveScheduleItem() {
       queryClient.invalidateQueries({ queryKey: scheduleKeys.lists() });
       queryClient.invalidateQueries({ queryKey: scheduleKeys.active() });
       queryClient.setQueryData(scheduleKeys.detail(data.id), data);
+      queryClient.invalidateQueries({ queryKey: scheduleKeys.warnings(data.id) });
     },
   });
 }
@@ -7,8 +7,11 @@ import {
   todoApi,
   type AstronomyTodo,
   type CreateTodoInput,
+  type ExposurePlanInput,
+  type FramingInput,
   type UpdateTodoInput,
 } from "@/lib/tauri/commands";
+import { scheduleKeys } from "./use-schedules";
 
 export const todoKeys = {
   all: ["todos"] as const,
@@ -77,3 +80,34 @@ export function useSyncTodos() {
     },
   });
 }
+
+export function useCalculateExposurePlan() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (input: ExposurePlanInput) => todoApi.calculateExposurePlan(input),
+    onSuccess: (_, input) => {
+      if (input.todoId) {
+        queryClient.invalidateQueries({ queryKey: todoKeys.lists() });
+        queryClient.invalidateQueries({ queryKey: todoKeys.detail(input.todoId) });
+      }
+    },
+  });
+}
+
+export function usePlanFraming() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (input: FramingInput) => todoApi.planFraming(input),
+    onSuccess: (plan, input) => {
+      if (plan.saved) {
+        queryClient.invalidateQueries({ queryKey: todoKeys.lists() });
+        queryClient.invalidateQueries({ queryKey: todoKeys.detail(input.todoId) });
+        if (input.scheduleId) {
+          queryClient.invalidateQueries({ queryKey: scheduleKeys.all });
+        }
+      }
+    },
+  });
+}
@@ -137,6 +137,7 @@ export interface ObserverLocation {
   latitude: number;
   longitude: number;
   horizon?: HorizonProfile;
+  bortle?: number;  // Sky quality class 1-9, for exposure planning
   equipmentIds?: string[];  // References to associated equipment sets
   isActive?: boolean;
 }
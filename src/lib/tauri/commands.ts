@@ -36,6 +36,8 @@ export interface AstronomyTodo {
   created_at: string;
   updated_at: string;
   tags: string | null;  // JSON array of tag strings
+  exposure_plan: string | null;  // JSON ExposurePlan
+  framing: string | null;  // JSON Framing
 }
 
 export interface CreateTodoInput {
@@ -66,6 +68,119 @@ export interface UpdateTodoInput {
   tags?: string[];
 }
 
+/**
+ * Inputs to the exposure calculator. Optics and camera values default to the
+ * equipment profile (read noise, gain and QE from the camera's metadata), the
+ * target to the todo's magnitude and size.
+ */
+export interface ExposurePlanInput {
+  todoId?: string;
+  profileId?: string;
+  apertureMm?: number;
+  /** Effective focal length, including any reducer */
+  focalLengthMm?: number;
+  pixelSizeUm?: number;
+  /** e- */
+  readNoise?: number;
+  /** e-/ADU */
+  gain?: number;
+  /** Quantum efficiency 0-1 (default 0.8) */
+  qe?: number;
+  /** e-/pixel/s (default 0) */
+  darkCurrent?: number;
+  bortle?: number;
+  /** mag/arcsec², instead of the Bortle class */
+  skyBrightness?: number;
+  filter?: string;
+  bandwidthNm?: number;
+  targetMagnitude?: number;
+  targetSizeArcmin?: number;
+  /** mag/arcsec², instead of magnitude and size */
+  targetSurfaceBrightness?: number;
+  /** Default 30 */
+  targetSnr?: number;
+  /** Default 600s */
+  maxSubExposure?: number;
+  /** Fraction read noise may add to the total noise (default 0.05) */
+  readNoiseContribution?: number;
+}
+
+export interface ExposurePlan {
+  filter: string | null;
+  bandwidthNm: number;
+  skyBrightness: number;
+  pixelScale: number;
+  /** e-/pixel/s */
+  skyRate: number;
+  readNoise: number;
+  idealSubExposure: number;
+  subExposure: number;
+  /** False when maxSubExposure cut the sub short of the sky limit */
+  skyLimited: boolean;
+  skyAdu: number | null;
+  targetSurfaceBrightness: number | null;
+  targetRate: number | null;
+  targetSnr: number;
+  snrPerSub: number | null;
+  subs: number | null;
+  totalIntegrationSeconds: number | null;
+  calculatedAt: string;
+}
+
+/** Where to point and how to turn the camera for a target */
+export interface Framing {
+  /** Field center in degrees */
+  centerRa: number;
+  centerDec: number;
+  /** Camera angle in degrees east of north */
+  rotation: number;
+  /** Camera field in degrees */
+  fieldWidth: number;
+  fieldHeight: number;
+  profileId: string | null;
+  plannedAt: string;
+}
+
+/**
+ * Inputs to the framing assistant. Optics and sensor default to the equipment
+ * profile; center and rotation to the todo's saved framing, else the target
+ * and 0.
+ */
+export interface FramingInput {
+  todoId: string;
+  profileId?: string;
+  /** Effective focal length, including any reducer */
+  focalLengthMm?: number;
+  pixelSizeUm?: number;
+  sensorWidthPx?: number;
+  sensorHeightPx?: number;
+  /** Degrees east of north */
+  rotation?: number;
+  centerRa?: number;
+  centerDec?: number;
+  /** Cutout width in degrees (default 1.6x the field's diagonal) */
+  fov?: number;
+  cutout?: SkyCutoutOptions;
+  /** Schedule item to store the framing on as well */
+  scheduleId?: string;
+  itemId?: string;
+  /** Render without saving, e.g. while adjusting the rotation */
+  preview?: boolean;
+}
+
+export interface FramingPlan {
+  framing: Framing;
+  /** JPEG of the cutout with the field drawn on it */
+  dataUrl: string;
+  width: number;
+  height: number;
+  /** Cutout width in degrees */
+  fov: number;
+  survey: string;
+  cached: boolean;
+  saved: boolean;
+}
+
 export interface Collection {
   id: string;
   user_id: string;
@@ -80,8 +195,15 @@ export interface Collection {
   created_at: string;
   updated_at: string;
   archived: boolean;
+  /** Image shown as the collection's cover */
+  cover_image_id: string | null;
+  /** Default image order; newest added first when null */
+  image_sort: ImageSort | null;
 }
 
+/** Order of a collection's images */
+export type ImageSort = "capture_time" | "name" | "rating" | "manual";
+
 export interface CreateCollectionInput {
   name: string;
   description?: string;
@@ -102,6 +224,50 @@ export interface UpdateCollectionInput {
   archived?: boolean;
 }
 
+export interface MergeCollectionsResult {
+  collection: Collection;
+  /** Images that were only in the merged-in collection */
+  imagesMoved: number;
+  /** Images that were already in both */
+  duplicates: number;
+}
+
+/** Split by local observing night or by target name */
+export type SplitBy = "date" | "target";
+
+export interface SplitCollectionResult {
+  /** New collections, one per night or target */
+  collections: Collection[];
+  imagesMoved: number;
+  /** Images without a night or target, left in the original collection */
+  imagesRemaining: number;
+}
+
+export interface FilterIntegration {
+  filter: string;
+  frames: number;
+  integrationSeconds: number;
+}
+
+/** Totals for a collection, computed from its images' metadata */
+export interface CollectionSummary {
+  collectionId: string;
+  /** The chosen cover, or the most recently added image if none is set */
+  coverImageId: string | null;
+  imageCount: number;
+  /** Frames captured (stacks count every integrated sub) */
+  totalFrames: number;
+  totalIntegrationSeconds: number;
+  /** First and last observing night (YYYY-MM-DD) */
+  firstNight: string | null;
+  lastNight: string | null;
+  nights: number;
+  targets: string[];
+  filters: FilterIntegration[];
+  telescopes: string[];
+  cameras: string[];
+}
+
 export interface Image {
   id: string;
   user_id: string;
@@ -121,6 +287,42 @@ export interface Image {
   updated_at: string;
   thumbnail: string | null;
   fits_url: string | null;
+  exposure: number | null;
+  gain: number | null;
+  filter: string | null;
+  telescope: string | null;
+  date_obs: string | null;
+  /** Plate solution as JSON (crval, crpix, cd and optional sip) */
+  wcs: string | null;
+  /** Stacked image this Light frame went into */
+  parent_image_id: string | null;
+  /** Import triage status ("pending" or "discarded"); null for normal images */
+  triage: string | null;
+  /** Star rating, 1-5; null when unrated */
+  rating: number | null;
+  /** Saved location the image was taken at, when one matched */
+  capture_site_id: string | null;
+  /** Capture site coordinates, kept if the location is deleted */
+  capture_latitude: number | null;
+  capture_longitude: number | null;
+}
+
+export interface AcquisitionQuery {
+  telescope?: string;
+  filter?: string;
+  gain?: number;
+  minExposure?: number;
+  maxExposure?: number;
+  dateFrom?: string;
+  dateTo?: string;
+  /** Saved location the images were taken at */
+  captureSiteId?: string;
+}
+
+export interface AcquisitionOptions {
+  telescopes: string[];
+  filters: string[];
+  gains: number[];
 }
 
 export interface CreateImageInput {
@@ -160,6 +362,69 @@ export interface PopulateFitsUrlsResult {
   noFitsFound: number;
 }
 
+/** Conversion applied by get_image_data */
+export interface ImageDataOptions {
+  /** Default "jpeg" */
+  format?: "jpeg" | "png";
+  /** Auto-stretch FITS sources from their pixels (default true) */
+  stretch?: boolean;
+  /** Largest width or height; bigger images are scaled down */
+  maxDimension?: number;
+  /** JPEG quality, 1-100 (default 90) */
+  quality?: number;
+}
+
+/** Pixel differences (B - A) in one channel, in 8-bit preview levels */
+export interface ChannelDelta {
+  channel: "red" | "green" | "blue";
+  mean: number;
+  meanAbs: number;
+  rms: number;
+  /** Pixel counts per bin, starting at `histogramMin` */
+  histogram: number[];
+}
+
+export interface MetadataDiff {
+  key: string;
+  a: string | null;
+  b: string | null;
+}
+
+export interface ImageComparison {
+  idA: string;
+  idB: string;
+  /** "wcs" when reprojected through both plate solutions */
+  alignment: "wcs" | "resize";
+  width: number;
+  height: number;
+  /** PNG data URLs, both in image A's frame */
+  previewA: string;
+  previewB: string;
+  difference: string;
+  /** Fraction of A's frame covered by B */
+  overlap: number;
+  channels: ChannelDelta[];
+  histogramMin: number;
+  histogramBinWidth: number;
+  metadata: MetadataDiff[];
+}
+
+/** An application images can be opened in (PixInsight, Siril...) */
+export interface ExternalEditor {
+  id: string;
+  name: string;
+  /** Application name on macOS, otherwise an executable path or command */
+  program: string;
+  /** Open the FITS file rather than the display preview */
+  useFits: boolean;
+}
+
+export interface ExternalEditorSettings {
+  editors: ExternalEditor[];
+  /** Editor used when none is given; null uses the system default app */
+  defaultEditor: string | null;
+}
+
 export interface ScheduleItem {
   id: string;
   todo_id: string;
@@ -169,6 +434,12 @@ export interface ScheduleItem {
   priority: number;
   notes: string | null;
   completed: boolean;
+  /** Framing chosen for this slot, when it differs from the todo's */
+  framing?: Framing;
+  /** Planned length; when set the end time follows from the start */
+  duration_minutes?: number;
+  /** Items that must finish before this one starts */
+  depends_on?: string[];
 }
 
 export interface ObservationSchedule {
@@ -184,6 +455,23 @@ export interface ObservationSchedule {
   created_at: string;
   updated_at: string;
   equipment_id: string | null;
+  /** Hidden from the schedule list and never active */
+  archived: boolean;
+}
+
+/** Something about a schedule item to know before the night */
+export interface ScheduleWarning {
+  itemId: string;
+  objectName: string;
+  kind: "meridianFlip" | "targetSets";
+  time: string;
+  message: string;
+}
+
+export interface ShiftedSchedule {
+  schedule: ObservationSchedule;
+  /** Items cut short, or left with no time, because their target sets */
+  warnings: ScheduleWarning[];
 }
 
 export interface CreateScheduleInput {
@@ -204,6 +492,7 @@ export interface UpdateScheduleInput {
   items?: ScheduleItem[];
   is_active?: boolean;
   equipment_id?: string;
+  archived?: boolean;
 }
 
 // =============================================================================
@@ -214,6 +503,28 @@ export const appApi = {
   getInfo: () => invoke<AppInfo>("get_app_info"),
 };
 
+// =============================================================================
+// Startup Commands
+// =============================================================================
+
+export type StartupStepState = "pending" | "running" | "ready" | "failed";
+
+export interface StartupStep {
+  state: StartupStepState;
+  error: string | null;
+}
+
+/** Background initialization progress; updates arrive as "startup-status" events */
+export interface StartupStatus {
+  database: StartupStep;
+  python: StartupStep;
+}
+
+export const startupApi = {
+  getStatus: () => invoke<StartupStatus>("get_startup_status"),
+  retry: () => invoke<StartupStatus>("retry_startup"),
+};
+
 // =============================================================================
 // Todo Commands
 // =============================================================================
@@ -232,15 +543,62 @@ export const todoApi = {
   delete: (id: string) => invoke<boolean>("delete_todo", { id }),
 
   sync: () => invoke<AstronomyTodo[]>("sync_todos"),
+
+  calculateExposurePlan: (input: ExposurePlanInput) =>
+    invoke<ExposurePlan>("calculate_exposure_plan", { input }),
+
+  planFraming: (input: FramingInput) =>
+    invoke<FramingPlan>("plan_framing", { input }),
 };
 
 // =============================================================================
 // Collection Commands
 // =============================================================================
 
+export interface SessionMove {
+  imageId: string;
+  filename: string;
+  from: string;
+  to: string;
+}
+
+export interface SessionRepairResult {
+  dryRun: boolean;
+  imagesMoved: number;
+  collectionsCreated: number;
+  collectionsRemoved: number;
+  moves: SessionMove[];
+}
+
+/** Where an inferred capture date came from, most to least reliable */
+export type DateSource = "fileName" | "folderName" | "siblingFrames" | "fileModified";
+
+export interface DateProposal {
+  imageId: string;
+  filename: string;
+  /** New DATE-OBS: the night (YYYY-MM-DD), or a UTC timestamp from the file time */
+  dateObs: string;
+  /** Observing night the image will be filed under */
+  sessionDate: string;
+  source: DateSource;
+}
+
+export interface DateBackfillResult {
+  dryRun: boolean;
+  proposals: DateProposal[];
+  /** Undated images no date could be found for */
+  unresolved: number;
+  imagesUpdated: number;
+  /** Images moved out of an undated session collection */
+  imagesMoved: number;
+  collectionsCreated: number;
+}
+
 export const collectionApi = {
   getAll: () => invoke<Collection[]>("get_collections"),
 
+  getArchived: () => invoke<Collection[]>("get_archived_collections"),
+
   getById: (id: string) =>
     invoke<Collection | null>("get_collection", { id }),
 
@@ -251,6 +609,52 @@ export const collectionApi = {
     invoke<Collection>("update_collection", { input }),
 
   delete: (id: string) => invoke<boolean>("delete_collection", { id }),
+
+  /** Archive (hide from the list) or restore a collection */
+  archive: (id: string, archived: boolean) =>
+    invoke<Collection>("archive_collection", { id, archived }),
+
+  repairSessions: (longitude?: number, dryRun?: boolean) =>
+    invoke<SessionRepairResult>("repair_session_collections", { longitude, dryRun }),
+
+  /**
+   * Infer capture dates for images without DATE-OBS from file and folder
+   * names, other frames in the folder, or file times. Preview with `dryRun`,
+   * then apply, optionally only for the accepted `imageIds`.
+   */
+  backfillDates: (dryRun?: boolean, imageIds?: string[], longitude?: number) =>
+    invoke<DateBackfillResult>("backfill_dates", { dryRun, imageIds, longitude }),
+
+  /** Merge `sourceId` into `targetId` and delete the source */
+  merge: (targetId: string, sourceId: string) =>
+    invoke<MergeCollectionsResult>("merge_collections", { targetId, sourceId }),
+
+  /** Copy a collection's settings, and optionally its images */
+  duplicate: (id: string, name?: string, includeImages?: boolean) =>
+    invoke<Collection>("duplicate_collection", { id, name, includeImages }),
+
+  /** Split a collection into one collection per night or target */
+  split: (id: string, by: SplitBy, longitude?: number) =>
+    invoke<SplitCollectionResult>("split_collection", { id, by, longitude }),
+
+  /** Set the cover image, or clear it with no `imageId` */
+  setCover: (id: string, imageId?: string) =>
+    invoke<Collection>("set_collection_cover", { id, imageId }),
+
+  /** Set the default image order, or go back to newest added first */
+  setImageSort: (id: string, sort?: ImageSort) =>
+    invoke<Collection>("set_collection_image_sort", { id, sort }),
+
+  /**
+   * Arrange images by hand (e.g. after a drag and drop); any left out follow
+   * the listed ones. Switches the collection to manual sorting.
+   */
+  reorderImages: (id: string, imageIds: string[]) =>
+    invoke<Image[]>("reorder_collection_images", { id, imageIds }),
+
+  /** Integration time, nights, targets, filters and equipment */
+  getSummary: (id: string) =>
+    invoke<CollectionSummary>("get_collection_summary", { id }),
 };
 
 // =============================================================================
@@ -258,10 +662,11 @@ export const collectionApi = {
 // =============================================================================
 
 export const imageApi = {
-  getAll: () => invoke<Image[]>("get_images"),
+  /** Light frames linked under a stacked image are left out unless asked for */
+  getAll: (includeSubframes?: boolean) => invoke<Image[]>("get_images", { includeSubframes }),
 
-  getByCollection: (collectionId: string) =>
-    invoke<Image[]>("get_collection_images", { collectionId }),
+  getByCollection: (collectionId: string, includeSubframes?: boolean) =>
+    invoke<Image[]>("get_collection_images", { collectionId, includeSubframes }),
 
   getById: (id: string) => invoke<Image | null>("get_image", { id }),
 
@@ -273,6 +678,10 @@ export const imageApi = {
 
   delete: (id: string) => invoke<boolean>("delete_image", { id }),
 
+  /** Rate 1-5 stars, or clear the rating with no `rating` */
+  setRating: (id: string, rating?: number) =>
+    invoke<Image>("set_image_rating", { id, rating }),
+
   // Many-to-many relationship methods
   addToCollection: (imageId: string, collectionId: string) =>
     invoke<boolean>("add_image_to_collection", { imageId, collectionId }),
@@ -284,12 +693,35 @@ export const imageApi = {
     invoke<Collection[]>("get_image_collections", { imageId }),
 
   // Image data methods
-  getData: (id: string) =>
-    invoke<string>("get_image_data", { id }),
+  /**
+   * Image data as a data URL. With options, the backend converts it to a
+   * display-ready PNG or JPEG (stretching FITS sources).
+   */
+  getData: (id: string, options?: ImageDataOptions) =>
+    invoke<string>("get_image_data", { id, options }),
 
   getThumbnail: (id: string) =>
     invoke<string>("get_image_thumbnail", { id }),
 
+  /** A/B comparison of two images, aligned to the first */
+  compare: (idA: string, idB: string, maxSize?: number) =>
+    invoke<ImageComparison>("compare_images", { idA, idB, maxSize }),
+
+  // Open-with methods (return the path on disk that was used)
+  revealInFileManager: (id: string) =>
+    invoke<string>("reveal_image_in_file_manager", { id }),
+
+  /** Open in an external editor by id, or the default editor if omitted */
+  openInEditor: (id: string, app?: string) =>
+    invoke<string>("open_image_in_external_editor", { id, app }),
+
+  getExternalEditors: () =>
+    invoke<ExternalEditorSettings>("get_external_editors"),
+
+  /** Save external editors (omit to restore the platform defaults) */
+  setExternalEditors: (settings?: ExternalEditorSettings) =>
+    invoke<ExternalEditorSettings>("set_external_editors", { settings }),
+
   // FITS URL population methods
   populateFitsUrls: () =>
     invoke<PopulateFitsUrlsResult>("populate_fits_urls"),
@@ -307,6 +739,12 @@ export const imageApi = {
 
   getUniqueCameras: () => invoke<string[]>("get_unique_cameras"),
 
+  searchByAcquisition: (query: AcquisitionQuery) =>
+    invoke<Image[]>("search_images_by_acquisition", { query }),
+
+  getAcquisitionOptions: () =>
+    invoke<AcquisitionOptions>("get_acquisition_options"),
+
   checkSourceHealth: () => invoke<[string, boolean, number][]>("check_source_health"),
 
   scanUnimportedFiles: (scanPaths?: string[], stacksOnly?: boolean) =>
@@ -354,6 +792,12 @@ export const scheduleApi = {
 
   getActiveSchedules: () => invoke<ObservationSchedule[]>("get_active_schedules"),
 
+  /** Active schedules for one night (YYYY-MM-DD), one per equipment set */
+  getActiveForNight: (date: string) =>
+    invoke<ObservationSchedule[]>("get_active_schedules_for_night", { date }),
+
+  getArchived: () => invoke<ObservationSchedule[]>("get_archived_schedules"),
+
   getById: (id: string) =>
     invoke<ObservationSchedule | null>("get_schedule", { id }),
 
@@ -365,11 +809,186 @@ export const scheduleApi = {
 
   delete: (id: string) => invoke<boolean>("delete_schedule", { id }),
 
+  /** Archive or restore a schedule; archiving also deactivates it */
+  archive: (id: string, archived: boolean) =>
+    invoke<ObservationSchedule>("archive_schedule", { id, archived }),
+
+  /** Archive schedules for nights before `before` (default today) */
+  archivePast: (before?: string) =>
+    invoke<number>("archive_past_schedules", { before }),
+
   addItem: (scheduleId: string, item: ScheduleItem) =>
     invoke<ObservationSchedule>("add_schedule_item", { scheduleId, item }),
 
   removeItem: (scheduleId: string, itemId: string) =>
     invoke<ObservationSchedule>("remove_schedule_item", { scheduleId, itemId }),
+
+  /**
+   * Warn about open items whose slot straddles the target's meridian flip.
+   * `flipDelayMinutes` is how long the mount tracks past the meridian.
+   */
+  getWarnings: (scheduleId: string, location: ObserverLocation, flipDelayMinutes?: number) =>
+    invoke<ScheduleWarning[]>("get_schedule_warnings", {
+      scheduleId,
+      location,
+      flipDelayMinutes,
+    }),
+
+  /**
+   * Push back (or, with a negative delta, pull in) the schedule after
+   * `fromItem` ran over by `deltaMinutes`. Later items follow their
+   * dependencies and durations and end by the time their target sets below
+   * `minAltitude` (default 0°) at the given or default location.
+   */
+  shift: (
+    scheduleId: string,
+    fromItem: string,
+    deltaMinutes: number,
+    locationId?: string,
+    minAltitude?: number,
+  ) =>
+    invoke<ShiftedSchedule>("shift_schedule", {
+      scheduleId,
+      fromItem,
+      deltaMinutes,
+      locationId,
+      minAltitude,
+    }),
+};
+
+// =============================================================================
+// Packing List Types
+// =============================================================================
+
+export type PackingCategory =
+  | "optics"
+  | "camera"
+  | "mount"
+  | "power"
+  | "dew"
+  | "cables"
+  | "consumables"
+  | "other";
+
+export interface PackingItem {
+  id: string;
+  user_id: string;
+  schedule_id: string;
+  name: string;
+  category: PackingCategory;
+  /** Equipment item the entry was generated from */
+  equipment_id: string | null;
+  packed: boolean;
+  sort_order: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PackingList {
+  scheduleId: string;
+  /** Equipment profile the list was generated from */
+  profileId: string | null;
+  profileName: string | null;
+  items: PackingItem[];
+  packed: number;
+  total: number;
+}
+
+// =============================================================================
+// Packing List Commands
+// =============================================================================
+
+export const packingApi = {
+  /**
+   * A schedule's field-kit checklist, generated from its equipment profile
+   * (or `profileId`) the first time
+   */
+  getList: (scheduleId: string, profileId?: string) =>
+    invoke<PackingList>("get_packing_list", { scheduleId, profileId }),
+
+  /** Rebuild the list; hand-added items are dropped, packed ticks kept */
+  regenerate: (scheduleId: string, profileId?: string) =>
+    invoke<PackingList>("regenerate_packing_list", { scheduleId, profileId }),
+
+  addItem: (scheduleId: string, name: string, category?: PackingCategory) =>
+    invoke<PackingItem>("add_packing_item", { scheduleId, name, category }),
+
+  setPacked: (id: string, packed: boolean) =>
+    invoke<PackingItem>("set_packing_item_packed", { id, packed }),
+
+  deleteItem: (id: string) => invoke<boolean>("delete_packing_item", { id }),
+};
+
+// =============================================================================
+// Night Digest Types
+// =============================================================================
+
+export type NightVerdict = "go" | "marginal" | "no-go";
+
+/** Conditions over the night's dark hours */
+export interface NightConditions {
+  darkHours: number;
+  /** Dark hours the forecast covers */
+  forecastHours: number;
+  /** Mean cloud cover (%) */
+  cloudCover: number | null;
+  clearHours: number;
+  maxPrecipitationProbability: number | null;
+  /** km/h */
+  maxWindSpeed: number | null;
+  maxHumidity: number | null;
+  moonIllumination: number;
+  moonUpHours: number;
+  /** Mean cloud cover (%) over the open schedule slots */
+  scheduledCloudCover: number | null;
+}
+
+/** Payload of the "night-digest" event */
+export interface NightDigest {
+  /** Local date the evening starts on (YYYY-MM-DD) */
+  night: string;
+  verdict: NightVerdict;
+  summary: string;
+  /** Worst first */
+  reasons: string[];
+  locationId: string;
+  locationName: string;
+  darkStart: string | null;
+  darkEnd: string | null;
+  conditions: NightConditions;
+  scheduledTargets: string[];
+  evaluatedAt: string;
+}
+
+export interface NightDigestConfig {
+  enabled: boolean;
+  /** Local hour (0-23) from which tonight is evaluated */
+  hour: number;
+  /** Defaults to the default location */
+  locationId: string | null;
+}
+
+// =============================================================================
+// Night Digest Commands
+// =============================================================================
+
+export const nightDigestApi = {
+  /** Stored digest for a night (default tonight), if evaluated */
+  get: (night?: string) =>
+    invoke<NightDigest | null>("get_night_digest", { night }),
+
+  /** Recent digests, newest night first */
+  getRecent: (limit?: number) =>
+    invoke<NightDigest[]>("get_night_digests", { limit }),
+
+  /** Evaluate a night now, replacing its stored digest */
+  evaluate: (night?: string, locationId?: string) =>
+    invoke<NightDigest>("evaluate_night_digest", { night, locationId }),
+
+  getConfig: () => invoke<NightDigestConfig>("get_night_digest_config"),
+
+  setConfig: (config: NightDigestConfig) =>
+    invoke<NightDigestConfig>("set_night_digest_config", { config }),
 };
 
 // =============================================================================
@@ -425,6 +1044,27 @@ export interface SimbadObject {
   catalogs?: Record<string, string>;
 }
 
+export interface BatchLookupEntry {
+  name: string;
+  object: SimbadObject | null;
+  cached: boolean;
+  error: string | null;
+}
+
+export interface BatchLookupResult {
+  results: BatchLookupEntry[];
+  lookedUp: number;
+  cached: number;
+  failed: number;
+}
+
+export interface BatchLookupProgress {
+  completed: number;
+  total: number;
+  name: string;
+  cached: boolean;
+}
+
 export interface ObserverLocation {
   latitude: number;
   longitude: number;
@@ -450,6 +1090,103 @@ export interface SunTimes {
   astronomicalTwilightEnd: string | null;
 }
 
+export interface TimeInterval {
+  start: string;
+  end: string;
+}
+
+export interface TwilightInterval extends TimeInterval {
+  phase: "day" | "civil" | "nautical" | "astronomical" | "night";
+}
+
+/** Everything an altitude chart shows for one night */
+export interface NightChart {
+  /** Chart range: an hour before sunset to an hour after sunrise */
+  start: string;
+  end: string;
+  /** Missing during polar day or night */
+  sunset?: string;
+  sunrise?: string;
+  minAltitude: number;
+  target: AltitudePoint[];
+  moon: AltitudePoint[];
+  /** Illuminated fraction of the moon at the middle of the chart */
+  moonIllumination: number;
+  twilight: TwilightInterval[];
+  /** Astronomical darkness */
+  darkness: TimeInterval[];
+  /** Darkness with the target at or above minAltitude */
+  imagingWindows: TimeInterval[];
+}
+
+export interface AltitudeTarget {
+  name?: string;
+  raDeg: number;
+  decDeg: number;
+}
+
+/** One target's track over the night and when it's best placed */
+export interface TargetTrack {
+  name?: string;
+  raDeg: number;
+  decDeg: number;
+  points: AltitudePoint[];
+  /** Highest altitude in darkness (or at all, if it never gets dark) */
+  maxAltitude?: number;
+  bestTime?: string;
+  /** Dark time spent at or above the minimum altitude */
+  hoursAboveMin: number;
+  windows: TimeInterval[];
+}
+
+/** Altitude tracks of several targets over the same night */
+export interface AltitudeComparison {
+  start: string;
+  end: string;
+  sunset?: string;
+  sunrise?: string;
+  minAltitude: number;
+  darkness: TimeInterval[];
+  /** In request order */
+  targets: TargetTrack[];
+}
+
+export interface MeridianTransit {
+  time: string;
+  /** Hours, negative while the target is still rising in the east */
+  hourAngle: number;
+  previousTransit: string;
+  nextTransit: string;
+  /** Degrees; negative when the target is below the horizon at transit */
+  transitAltitude: number;
+}
+
+export interface MeridianFlip {
+  time: string;
+  /** Transit the next flip follows (past while tracking past the meridian) */
+  transitTime: string;
+  flipTime: string;
+  remainingSeconds: number;
+  /** Crossed the meridian but the flip is still to come */
+  pastMeridian: boolean;
+  hourAngle: number;
+  transitAltitude: number;
+}
+
+export interface PolarAlignment {
+  time: string;
+  /** Polaris in the northern hemisphere, Sigma Octantis in the southern */
+  star: string;
+  /** Precessed to the date, in degrees */
+  ra: number;
+  dec: number;
+  /** Hours, 0-24 */
+  hourAngle: number;
+  /** Position on a 12-hour clock face in an inverting polar scope (12 at the top) */
+  clockPosition: number;
+  poleDistanceArcmin: number;
+}
+
 // =============================================================================
 // Astronomy Commands
 // =============================================================================
@@ -476,6 +1213,29 @@ export interface RestoreResult {
   message: string;
 }
 
+export interface BundleManifest {
+  format_version: number;
+  app_version: string;
+  created_at: string;
+  settings: string[];
+  thumbnails: { file: string; size_bytes: number }[];
+}
+
+export interface BackupVerification {
+  valid: boolean;
+  message: string;
+  format: "bundle" | "database" | null;
+  compressed: boolean;
+  encrypted: boolean;
+  manifest: BundleManifest | null;
+  database: {
+    integrity: string;
+    schema_version: string | null;
+    row_counts: { table: string; rows: number }[];
+  } | null;
+  missing_thumbnails: number;
+}
+
 export interface PathPrefix {
   prefix: string;
   count: number;
@@ -486,6 +1246,39 @@ export interface RemapResult {
   urls_updated: number;
   fits_urls_updated: number;
   message: string;
+  dry_run: boolean;
+  preview: RemapPreviewItem[];
+}
+
+export interface RemapPreviewItem {
+  image_id: string;
+  old_path: string;
+  new_path: string;
+}
+
+export interface RemapSuggestion {
+  old_prefix: string;
+  new_prefix: string;
+  matched: number;
+  preset_id: string | null;
+}
+
+export interface PathCheckResult {
+  total: number;
+  missing: number;
+  missing_fraction: number;
+  sample_missing: string[];
+  suggestions: RemapSuggestion[];
+}
+
+export interface PathRemapPreset {
+  id: string;
+  name: string;
+  old_prefix: string;
+  new_prefix: string;
+  created_at: string;
+  last_applied_at: string | null;
+  times_applied: number;
 }
 
 // =============================================================================
@@ -496,7 +1289,8 @@ export const backupApi = {
   /**
    * Create a backup of the database
    */
-  create: () => invoke<BackupResult>("create_backup"),
+  create: (compression?: "none" | "gzip" | "zstd", passphrase?: string) =>
+    invoke<BackupResult>("create_backup", { compression, passphrase }),
 
   /**
    * List all available backups
@@ -506,8 +1300,8 @@ export const backupApi = {
   /**
    * Restore database from a backup
    */
-  restore: (backupPath: string) =>
-    invoke<RestoreResult>("restore_backup", { backupPath }),
+  restore: (backupPath: string, passphrase?: string) =>
+    invoke<RestoreResult>("restore_backup", { backupPath, passphrase }),
 
   /**
    * Delete a backup file
@@ -518,14 +1312,28 @@ export const backupApi = {
   /**
    * Export database to a custom location
    */
-  export: (exportPath: string) =>
-    invoke<BackupResult>("export_database", { exportPath }),
+  export: (
+    exportPath: string,
+    compression?: "none" | "gzip" | "zstd",
+    passphrase?: string,
+  ) =>
+    invoke<BackupResult>("export_database", {
+      exportPath,
+      compression,
+      passphrase,
+    }),
 
   /**
    * Import database from a custom location
    */
-  import: (importPath: string) =>
-    invoke<RestoreResult>("import_database", { importPath }),
+  import: (importPath: string, passphrase?: string) =>
+    invoke<RestoreResult>("import_database", { importPath, passphrase }),
+
+  /**
+   * Check a backup's integrity, schema version, and row counts before restoring
+   */
+  verify: (backupPath: string, passphrase?: string) =>
+    invoke<BackupVerification>("verify_backup", { backupPath, passphrase }),
 
   /**
    * Get common path prefixes from image URLs (for path remapping after import)
@@ -536,8 +1344,38 @@ export const backupApi = {
   /**
    * Remap image file paths (replace old prefix with new prefix)
    */
-  remapPaths: (oldPrefix: string, newPrefix: string) =>
-    invoke<RemapResult>("remap_image_paths", { oldPrefix, newPrefix }),
+  remapPaths: (oldPrefix: string, newPrefix: string, dryRun?: boolean) =>
+    invoke<RemapResult>("remap_image_paths", { oldPrefix, newPrefix, dryRun }),
+
+  /**
+   * Count unreachable image files and suggest prefix remaps
+   */
+  checkPaths: (searchRoots?: string[]) =>
+    invoke<PathCheckResult>("check_image_paths", { searchRoots }),
+
+  /**
+   * List saved path remap presets
+   */
+  getRemapPresets: () =>
+    invoke<PathRemapPreset[]>("get_path_remap_presets"),
+
+  /**
+   * Save a path remap preset
+   */
+  saveRemapPreset: (name: string, oldPrefix: string, newPrefix: string) =>
+    invoke<PathRemapPreset>("save_path_remap_preset", { name, oldPrefix, newPrefix }),
+
+  /**
+   * Delete a path remap preset
+   */
+  deleteRemapPreset: (presetId: string) =>
+    invoke<void>("delete_path_remap_preset", { presetId }),
+
+  /**
+   * Apply (or preview) a saved path remap preset
+   */
+  applyRemapPreset: (presetId: string, dryRun?: boolean) =>
+    invoke<RemapResult>("apply_path_remap_preset", { presetId, dryRun }),
 };
 
 export const astronomyApi = {
@@ -547,6 +1385,13 @@ export const astronomyApi = {
   lookupObject: (name: string) =>
     invoke<SimbadObject | null>("lookup_astronomy_object", { name }),
 
+  /**
+   * Look up many objects at once, using the SIMBAD cache where possible.
+   * Emits "simbad-batch-progress" events while running.
+   */
+  lookupObjectsBatch: (names: string[]) =>
+    invoke<BatchLookupResult>("lookup_objects_batch", { names }),
+
   /**
    * Calculate current altitude and azimuth for an object
    */
@@ -576,14 +1421,254 @@ export const astronomyApi = {
    */
   getSunTimes: (location: ObserverLocation) =>
     invoke<SunTimes>("get_sun_times", { location }),
-};
 
-// =============================================================================
-// Bulk Scan Types
-// =============================================================================
+  /**
+   * Get target and moon altitude, twilight and imaging windows for the
+   * night starting on `date` (YYYY-MM-DD, default tonight)
+   */
+  getNightChart: (
+    raDeg: number,
+    decDeg: number,
+    location: ObserverLocation,
+    date?: string,
+    minAltitude?: number,
+    intervalMinutes?: number
+  ) =>
+    invoke<NightChart>("get_night_chart", {
+      raDeg,
+      decDeg,
+      location,
+      date,
+      minAltitude,
+      intervalMinutes,
+    }),
 
-export interface BulkScanInput {
-  directory: string;
+  /**
+   * Get altitude tracks for several targets over one night in one call
+   */
+  compareAltitudes: (
+    targets: AltitudeTarget[],
+    location: ObserverLocation,
+    date?: string,
+    minAltitude?: number,
+    intervalMinutes?: number
+  ) =>
+    invoke<AltitudeComparison>("compare_altitudes", {
+      targets,
+      location,
+      date,
+      minAltitude,
+      intervalMinutes,
+    }),
+
+  /**
+   * Get a target's previous and next meridian transit around `time`
+   * (ISO 8601, default now). Coordinates are J2000.
+   */
+  getMeridianTransit: (
+    raDeg: number,
+    decDeg: number,
+    location: ObserverLocation,
+    time?: string
+  ) =>
+    invoke<MeridianTransit>("get_meridian_transit", { raDeg, decDeg, location, time }),
+
+  /**
+   * Get the time left before a meridian flip for a target being imaged.
+   * `flipDelayMinutes` is how long the mount tracks past the meridian.
+   */
+  getMeridianFlip: (
+    raDeg: number,
+    decDeg: number,
+    location: ObserverLocation,
+    time?: string,
+    flipDelayMinutes?: number
+  ) =>
+    invoke<MeridianFlip>("get_meridian_flip", {
+      raDeg,
+      decDeg,
+      location,
+      time,
+      flipDelayMinutes,
+    }),
+
+  /**
+   * Get the pole star's position for aligning a polar scope
+   */
+  getPolarAlignment: (location: ObserverLocation, time?: string) =>
+    invoke<PolarAlignment>("get_polar_alignment", { location, time }),
+};
+
+// =============================================================================
+// Location Commands
+// =============================================================================
+
+/** An observing site stored in the database */
+export interface ObservingLocation {
+  id: string;
+  user_id: string;
+  name: string;
+  latitude: number;
+  longitude: number;
+  /** Metres above sea level */
+  elevation: number | null;
+  bortle: number | null;
+  /** How the location was added; candidates come from a dark site search */
+  source: "manual" | "gps" | "candidate";
+  is_default: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+/** A darker spot suggested by a dark site search */
+export interface DarkSite {
+  latitude: number;
+  longitude: number;
+  /** Zenith sky brightness in mag/arcsec² */
+  skyBrightness: number;
+  bortle: number;
+  /** Straight-line distance from the search location */
+  distanceKm: number;
+  bearing: number;
+  /** By road; null when routing was unavailable */
+  drivingDistanceKm: number | null;
+  drivingMinutes: number | null;
+  /** Candidate location saved for this site */
+  locationId: string | null;
+}
+
+export interface DarkSiteSearch {
+  /** Sky at the search location, when the light-pollution map covers it */
+  skyBrightness: number | null;
+  bortle: number | null;
+  sites: DarkSite[];
+}
+
+export const locationApi = {
+  getAll: () => invoke<ObservingLocation[]>("get_locations"),
+
+  /**
+   * Save the device's current position as a location, with elevation from a
+   * DEM lookup (or the GPS altitude when offline)
+   */
+  captureCurrent: (name?: string, setDefault?: boolean) =>
+    invoke<ObservingLocation>("capture_current_location", { name, setDefault }),
+
+  setDefault: (id: string, isDefault: boolean) =>
+    invoke<ObservingLocation>("set_default_location", { id, isDefault }),
+
+  delete: (id: string) => invoke<boolean>("delete_location", { id }),
+
+  /**
+   * Suggest darker spots within `radiusKm`, saved as candidate locations
+   * (replacing those from the previous search). Needs a light-pollution map
+   * in the app data dir.
+   */
+  findDarkSites: (location: ObserverLocation, radiusKm: number, maxSites?: number) =>
+    invoke<DarkSiteSearch>("find_dark_sites", { location, radiusKm, maxSites }),
+};
+
+// =============================================================================
+// Capture Site Commands
+// =============================================================================
+
+export const captureSiteApi = {
+  /**
+   * Set where images were taken: a saved location, or coordinates for a site
+   * that isn't saved. Neither clears the capture site.
+   */
+  set: (imageIds: string[], locationId?: string, latitude?: number, longitude?: number) =>
+    invoke<number>("set_image_capture_site", { imageIds, locationId, latitude, longitude }),
+
+  /**
+   * Tag images imported before capture sites were recorded, from their
+   * headers, the night's active schedule or the default location
+   */
+  backfill: (overwrite?: boolean) => invoke<number>("backfill_capture_sites", { overwrite }),
+};
+
+// =============================================================================
+// Network Types
+// =============================================================================
+
+export type NetworkProvider =
+  | "simbad"
+  | "astrometryNet"
+  | "elevation"
+  | "routing"
+  | "hips"
+  | "weather"
+  | "downloads";
+
+export interface ProviderLimit {
+  /** Minimum spacing between requests to the provider */
+  minIntervalMs: number;
+  /** Retries after a network error or HTTP 429/5xx */
+  maxRetries: number;
+}
+
+export interface NetworkSettings {
+  /** Refuse external requests; lookups answer from cached responses */
+  offline: boolean;
+  /** Providers left out use their defaults */
+  limits: Partial<Record<NetworkProvider, ProviderLimit>>;
+}
+
+// =============================================================================
+// Network Commands
+// =============================================================================
+
+export const networkApi = {
+  getSettings: () => invoke<NetworkSettings>("get_network_settings"),
+
+  /**
+   * Save network settings (omit to restore the defaults)
+   */
+  setSettings: (settings?: NetworkSettings) =>
+    invoke<NetworkSettings>("set_network_settings", { settings }),
+
+  setOffline: (offline: boolean) =>
+    invoke<NetworkSettings>("set_offline_mode", { offline }),
+};
+
+// =============================================================================
+// Preview Cache Types
+// =============================================================================
+
+export interface PreviewCacheSettings {
+  /** Least recently used previews are removed past this size */
+  maxSizeMb: number;
+}
+
+export interface PreviewCacheStatus {
+  maxSizeMb: number;
+  usedBytes: number;
+  files: number;
+  path: string;
+}
+
+// =============================================================================
+// Preview Cache Commands
+// =============================================================================
+
+export const previewCacheApi = {
+  getStatus: () => invoke<PreviewCacheStatus>("get_preview_cache_status"),
+
+  /**
+   * Save the cache size cap (omit to restore the default)
+   */
+  setSettings: (settings?: PreviewCacheSettings) =>
+    invoke<PreviewCacheStatus>("set_preview_cache_settings", { settings }),
+
+  clear: () => invoke<PreviewCacheStatus>("clear_preview_cache"),
+};
+
+// =============================================================================
+// Bulk Scan Types
+// =============================================================================
+
+export interface BulkScanInput {
+  directory: string;
   tags?: string;
   stacked_only: boolean;
   max_files?: number;
@@ -594,7 +1679,13 @@ export interface BulkScanResult {
   images_imported: number;
   collections_created: number;
   images_skipped: number;
+  /** Light frames linked under their stacked image */
+  subframes_linked: number;
+  /** Imported frames held back for triage review */
+  images_triaged: number;
   errors: string[];
+  /** Id the scan's log lines are tagged with */
+  job_id: string | null;
 }
 
 export interface BulkScanPreview {
@@ -614,6 +1705,54 @@ export interface PreviewFile {
   is_stacked: boolean;
 }
 
+/** A directory bulk scans have gone through; unchanged ones are skipped */
+export interface ScannedDirectory {
+  id: string;
+  user_id: string;
+  path: string;
+  /** Directory modification time at the last scan (Unix seconds) */
+  fs_modified_at: number;
+  /** When the directory was last scanned (RFC 3339) */
+  last_scanned_at: string;
+  /** Images found in the directory by the last scan */
+  image_count: number;
+  /** Images the last scan imported from it */
+  last_imported: number;
+  /** Images the last scan failed to import */
+  last_failed: number;
+  /** Fingerprint of the image files found by the last scan */
+  listing_hash: string | null;
+}
+
+export type FrameType = "stacked" | "light" | "dark" | "flat" | "bias" | "unknown";
+
+/** How scans tell stacked images from raw frames */
+export interface FrameRules {
+  /** File name prefixes of stacked images (case-insensitive) */
+  stackedPrefixes: string[];
+  /** File name prefixes of raw Light frames (case-insensitive) */
+  lightPrefixes: string[];
+  /** Regular expressions matched against the file name without extension */
+  stackedPatterns: string[];
+  lightPatterns: string[];
+  /** Fall back to the FITS IMAGETYP header */
+  useImagetyp: boolean;
+  /** Treat NCOMBINE / STACKCNT above 1 as a stack */
+  useNcombine: boolean;
+}
+
+/** Template for the session collections scans and auto-import create */
+export interface CollectionNaming {
+  /** Uses {date} (YYYY-MM-DD) and {object}, e.g. "{date} – {object}" */
+  template: string;
+}
+
+/** Memory ceiling for processing images during a scan */
+export interface ImportMemorySettings {
+  /** Images wait for memory to free up past this (256 MB to 256 GB) */
+  maxMemoryMb: number;
+}
+
 // =============================================================================
 // Bulk Scan Commands
 // =============================================================================
@@ -635,6 +1774,77 @@ export const scanApi = {
    * Cancel an ongoing scan operation
    */
   cancel: () => invoke<void>("cancel_scan"),
+
+  /**
+   * Job id of the scan in progress (null when none), to pause or resume it
+   */
+  getRunningJob: () => invoke<string | null>("get_running_scan_job"),
+
+  /**
+   * Pause the scan with this job id; images already being processed are
+   * finished. Fails if that scan isn't the one running.
+   */
+  pause: (jobId: string) => invoke<void>("pause_scan", { jobId }),
+
+  /**
+   * Resume the paused scan with this job id
+   */
+  resume: (jobId: string) => invoke<void>("resume_scan", { jobId }),
+
+  /**
+   * Directories scanned so far with their last scan results, optionally
+   * only a directory and those under it
+   */
+  getScannedDirectories: (parent?: string) =>
+    invoke<ScannedDirectory[]>("get_scanned_directories", { parent }),
+
+  /**
+   * Forget a directory's scan cache (and with recursive, its
+   * subdirectories') so the next scan looks at every image again
+   */
+  forgetScannedDirectory: (path: string, recursive = false) =>
+    invoke<number>("forget_scanned_directory", { path, recursive }),
+
+  /**
+   * Get the rules used to tell stacked images from raw frames
+   */
+  getFrameRules: () => invoke<FrameRules>("get_frame_rules"),
+
+  /**
+   * Save frame rules (omit to restore the defaults)
+   */
+  setFrameRules: (rules?: FrameRules) =>
+    invoke<FrameRules>("set_frame_rules", { rules }),
+
+  /**
+   * Get the naming template for session collections
+   */
+  getCollectionNaming: () =>
+    invoke<CollectionNaming>("get_collection_naming"),
+
+  /**
+   * Save the collection naming template (omit to restore the default)
+   */
+  setCollectionNaming: (naming?: CollectionNaming) =>
+    invoke<CollectionNaming>("set_collection_naming", { naming }),
+
+  /**
+   * Name a template would give a session (date is YYYY-MM-DD)
+   */
+  previewCollectionName: (template: string, date: string, object?: string) =>
+    invoke<string>("preview_collection_name", { template, date, object }),
+
+  /**
+   * Get the memory ceiling for scans
+   */
+  getMemorySettings: () =>
+    invoke<ImportMemorySettings>("get_import_memory_settings"),
+
+  /**
+   * Save the memory ceiling for scans (omit to restore the default)
+   */
+  setMemorySettings: (settings?: ImportMemorySettings) =>
+    invoke<ImportMemorySettings>("set_import_memory_settings", { settings }),
 };
 
 // =============================================================================
@@ -677,318 +1887,1757 @@ export const collectApi = {
 };
 
 // =============================================================================
-// Plate Solving Types
+// Siril Script Types
 // =============================================================================
 
-export interface PlateSolveInput {
-  /** Image ID to plate solve */
-  id: string;
-  /** Solver type: "nova", "local", or "astap" */
-  solver: string;
-  /** API key for nova.astrometry.net (required for nova solver) */
-  apiKey?: string;
-  /** Custom API URL for local astrometry.net instance (optional) */
-  apiUrl?: string;
-  /** Lower bound of expected image scale (arcsec/pixel) */
-  scaleLower?: number;
-  /** Upper bound of expected image scale (arcsec/pixel) */
-  scaleUpper?: number;
-  /** Timeout in seconds */
-  timeout?: number;
-  /** Whether to query catalogs for objects after solving */
-  queryCatalogs?: boolean;
-  /** Catalogs to query (if not specified, queries all) */
-  catalogs?: string[];
-  /** Magnitude limit for bright stars */
-  starMagLimit?: number;
-  /** Hint RA in degrees (to speed up solving) */
-  hintRa?: number;
-  /** Hint Dec in degrees (to speed up solving) */
-  hintDec?: number;
-  /** Hint search radius in degrees (default: 10) */
-  hintRadius?: number;
-  /** Path to tetra3 database file (.bin, postcard format) — required for "tetra3" solver.
-   *  Older .rkyv databases from tetra3 ≤0.5.x will not load. */
-  tetra3DbPath?: string;
-  /** FOV estimate in degrees for tetra3 solver */
-  fovEstimate?: number;
+export interface SirilScriptExport {
+  scriptPath: string;
+  lights: number;
+  darks: number;
+  flats: number;
+  biases: number;
+  /** Frames copied into the target directory (ones already there are reused) */
+  filesCopied: number;
+  /** Selected images that couldn't be used, with the reason */
+  skipped: string[];
 }
 
-export interface PlateSolveResult {
-  success: boolean;
-  centerRa: number;
-  centerDec: number;
-  pixelScale: number;
-  rotation: number;
-  widthDeg: number;
-  heightDeg: number;
-  imageWidth: number;
-  imageHeight: number;
-  solver: string;
-  solveTime: number;
-  errorMessage?: string;
+// =============================================================================
+// Siril Script Commands
+// =============================================================================
+
+export const sirilApi = {
+  /**
+   * Copy the frames behind the selected images into Siril's lights/darks/
+   * flats/biases folders under `targetDir` and write a preprocessing script
+   * (convert, calibrate, register, stack) next to them
+   */
+  exportScript: (imageIds: string[], targetDir: string) =>
+    invoke<SirilScriptExport>("export_siril_script", { imageIds, targetDir }),
+};
+
+// =============================================================================
+// Stacking Set Types
+// =============================================================================
+
+/** Which of a target's Light frames go into a set; empty fields match all */
+export interface StackingCriteria {
+  /** Filter names, matched after normalizing ("H-alpha" is "Ha") */
+  filters?: string[];
+  /** Sub exposure in seconds */
+  exposure?: number | null;
+  /** Camera (INSTRUME header), matched case-insensitively */
+  camera?: string | null;
+  /** Keep frames the session quality check flagged as clouded */
+  includeRejected?: boolean;
 }
 
-export interface CatalogObject {
-  name: string;
-  catalog: string;
-  objectType: string;
-  ra: number;
-  dec: number;
-  magnitude?: number;
-  size?: string;
-  sizeArcmin?: number;
-  commonName?: string;
-  pixelX?: number;
-  pixelY?: number;
-  radiusPx?: number;
+export interface StackingFrame {
+  imageId: string;
+  path: string;
+  filter: string | null;
+  exposure: number | null;
+  camera: string | null;
+  gain: number | null;
+  /** Observing night (YYYY-MM-DD) */
+  night: string | null;
 }
 
-export interface PlateSolveResponse extends PlateSolveResult {
-  objects: CatalogObject[];
+/** Lights with one filter, exposure, camera and gain, and the calibration that fits them */
+export interface StackingGroup {
+  filter: string | null;
+  exposure: number | null;
+  camera: string | null;
+  gain: number | null;
+  lights: StackingFrame[];
+  totalSeconds: number;
+  nights: string[];
+  darks: StackingFrame[];
+  flats: StackingFrame[];
+  biases: StackingFrame[];
+}
+
+export interface StackingSet {
+  /** File stem of a recorded set; empty until it's saved */
+  id: string;
+  target: string;
+  createdAt: string;
+  criteria: StackingCriteria;
+  /** Longest total integration first */
+  groups: StackingGroup[];
+  lightCount: number;
+  totalSeconds: number;
+  /** Light frames of the target that didn't match the criteria */
+  notMatching: number;
+  /** Lights left out, with the reason */
+  skipped: string[];
+  /** Missing calibration and other problems to fix before stacking */
+  warnings: string[];
 }
 
 // =============================================================================
-// Plate Solving Commands
+// Stacking Set Commands
 // =============================================================================
 
-export interface SolverInfo {
-  available: boolean;
-  version: string | null;
-  details: string;
-}
-
-export interface SolveHints {
-  scaleArcsec?: number;
-  scaleLower?: number;
-  scaleUpper?: number;
-  raHint?: number;
-  decHint?: number;
-  fovDeg?: number;
-  focalLengthMm?: number;
-  pixelSizeUm?: number;
-  imageWidth: number;
-  imageHeight: number;
-}
+export const stackingSetApi = {
+  /** Gather a target's matching Light frames across nights and their calibration */
+  build: (target: string, criteria?: StackingCriteria) =>
+    invoke<StackingSet>("build_stacking_set", { target, criteria }),
 
-export const plateSolveApi = {
   /**
-   * Plate solve an image and optionally query catalogs for objects
+   * Build a stacking set and record it with the app's data, or export the
+   * manifest to `path`
    */
-  solve: (input: PlateSolveInput) =>
-    invoke<PlateSolveResponse>("plate_solve_image", { input }),
+  save: (target: string, criteria?: StackingCriteria, path?: string) =>
+    invoke<StackingSet>("save_stacking_set", { target, criteria, path }),
 
-  /**
-   * Detect which plate solvers are installed
-   */
-  detectSolvers: () =>
-    invoke<Record<string, SolverInfo>>("detect_plate_solvers"),
+  /** Recorded stacking sets, newest first */
+  list: (target?: string) => invoke<StackingSet[]>("get_stacking_sets", { target }),
 
-  /**
-   * Extract plate solving hints from a FITS file's headers
+  delete: (id: string) => invoke<void>("delete_stacking_set", { id }),
+};
+
+// =============================================================================
+// Subframe Types
+// =============================================================================
+
+/** Subs taken through one filter at one exposure time */
+export interface SubExposureGroup {
+  /** Canonical filter name; null when the subs had no filter */
+  filter: string | null;
+  /** Seconds per sub */
+  exposure: number;
+  count: number;
+  total_seconds: number;
+}
+
+/** Acquisition summary of the subs behind a stacked image */
+export interface AcquisitionBreakdown {
+  /** Longest total integration first */
+  groups: SubExposureGroup[];
+  sub_count: number;
+  /** Subs without a known exposure time, left out of the totals */
+  unknown_exposure: number;
+  total_integration_seconds: number;
+}
+
+/** One light frame on a session's quality chart */
+export interface QualityPoint {
+  imageId: string;
+  filename: string;
+  dateObs: string | null;
+  starCount: number;
+  /** Median pixel value, in the file's own units (ADU for FITS) */
+  background: number;
+  /** Star count relative to the session's typical clear frame */
+  relativeStars: number;
+  /** Why the frame was flagged (clouds, dew); null if it looks fine */
+  rejected: string | null;
+}
+
+export interface SessionQualityTimeline {
+  /** Frames in capture order */
+  points: QualityPoint[];
+  /** Star count of a typical clear frame this session */
+  referenceStars: number;
+  medianBackground: number;
+  rejected: number;
+  /** Frames that couldn't be measured */
+  errors: string[];
+  cancelled: boolean;
+}
+
+// =============================================================================
+// Subframe Commands
+// =============================================================================
+
+export const subframeApi = {
+  /**
+   * Summarize the imported Light frames behind a stacked image and store the
+   * breakdown in its metadata as `acquisition_breakdown`
+   */
+  computeBreakdown: (imageId: string) =>
+    invoke<AcquisitionBreakdown>("compute_acquisition_breakdown", { imageId }),
+
+  /** Light frames linked under a stacked image, in capture order */
+  getSubframes: (id: string) => invoke<Image[]>("get_image_subframes", { id }),
+
+  /**
+   * Link Light frames already in the library to their stacked images.
+   * Returns the number linked.
+   */
+  linkAll: () => invoke<number>("link_image_subframes"),
+
+  /**
+   * Star count and background of a session's light frames with clouded
+   * frames flagged. Unmeasured frames are measured first, with progress as
+   * `session-quality-progress` events.
+   */
+  getQualityTimeline: (collectionId: string) =>
+    invoke<SessionQualityTimeline>("get_session_quality_timeline", { collectionId }),
+
+  cancelQualityTimeline: () => invoke<void>("cancel_session_quality"),
+};
+
+// =============================================================================
+// Sky Brightness Types
+// =============================================================================
+
+export interface SkyBrightnessOptions {
+  /** Camera gain in electrons per ADU (default: the EGAIN header, else 1) */
+  electronsPerAdu?: number;
+  /** Telescope aperture (default: the APTDIA header, else the linked telescope) */
+  apertureMm?: number;
+  /** Bias level still in the frame, in ADU (default 0 for calibrated frames) */
+  pedestal?: number;
+  /** Optics transmission times quantum efficiency (default 0.5) */
+  throughput?: number;
+  /** Added to the result to match a reference SQM reading */
+  calibrationOffset?: number;
+}
+
+/** Stored in image metadata under "sky_brightness" */
+export interface SkyBrightnessReading {
+  /** Surface brightness in mag/arcsec²; higher is darker */
+  mag_arcsec2: number;
+  /** Background level in ADU, pedestal removed */
+  background_adu: number;
+  electrons_per_adu: number;
+  /** No gain was known and unity gain was assumed */
+  gain_assumed: boolean;
+  exposure: number;
+  pixel_scale: number;
+  aperture_mm: number;
+  measured_at: string;
+}
+
+export interface SkyBrightnessNight {
+  collectionId: string;
+  name: string;
+  sessionDate: string;
+  /** Median of the night's readings */
+  magArcsec2: number;
+  darkest: number;
+  readings: number;
+}
+
+// =============================================================================
+// Sky Brightness Commands
+// =============================================================================
+
+export const skyBrightnessApi = {
+  /**
+   * Estimate the sky brightness in mag/arcsec² from a plate-solved,
+   * calibrated FITS frame and record it on the image and its session
+   */
+  estimate: (imageId: string, options?: SkyBrightnessOptions) =>
+    invoke<SkyBrightnessReading>("estimate_sky_brightness", { imageId, options }),
+
+  /** Nightly sky brightness of every session with readings, oldest first */
+  getTrend: () => invoke<SkyBrightnessNight[]>("get_sky_brightness_trend"),
+};
+
+// =============================================================================
+// Import Triage Types
+// =============================================================================
+
+/** Rules that hold failed frames and test shots back for review at import */
+export interface TriageRules {
+  enabled: boolean;
+  /** Frames with an EXPTIME below this many seconds are test shots */
+  minExposure: number;
+  /** Flag frames without an OBJECT header */
+  requireObject: boolean;
+  /** Markers of a failed frame, matched against the file name and OBJECT */
+  errorMarkers: string[];
+}
+
+// =============================================================================
+// Import Triage Commands
+// =============================================================================
+
+export const triageApi = {
+  getRules: () => invoke<TriageRules>("get_triage_rules"),
+
+  /** Save triage rules (omit to restore the defaults) */
+  setRules: (rules?: TriageRules) => invoke<TriageRules>("set_triage_rules", { rules }),
+
+  /**
+   * Frames waiting for review, newest first. Their reasons are in the
+   * metadata JSON under `triage_reasons`.
+   */
+  getPending: () => invoke<Image[]>("get_triage_images"),
+
+  /**
+   * Keep frames (they join the library) or discard them. Returns the number
+   * of images updated.
+   */
+  review: (ids: string[], keep: boolean) =>
+    invoke<number>("review_triage_images", { ids, keep }),
+};
+
+// =============================================================================
+// Auto-tagging Types
+// =============================================================================
+
+export type TagField =
+  | "object"
+  | "exposure"
+  | "gain"
+  | "filter"
+  | "telescope"
+  | "instrument"
+  | "stackedFrames"
+  /** "stacked", "light", "dark", "flat", "bias" or "unknown" */
+  | "frameType";
+
+/** equals/contains compare text (ignoring case); equals/atLeast/atMost compare numbers */
+export type TagMatch = "equals" | "contains" | "atLeast" | "atMost";
+
+/** Adds `tag` to images whose `field` matches `value` */
+export interface TagRule {
+  tag: string;
+  field: TagField;
+  match: TagMatch;
+  value: string;
+}
+
+export interface TagRules {
+  /** Tag images at import */
+  enabled: boolean;
+  rules: TagRule[];
+}
+
+// =============================================================================
+// Auto-tagging Commands
+// =============================================================================
+
+export const tagRulesApi = {
+  getRules: () => invoke<TagRules>("get_tag_rules"),
+
+  /** Save tag rules (omit to restore the defaults) */
+  setRules: (rules?: TagRules) => invoke<TagRules>("set_tag_rules", { rules }),
+
+  /**
+   * Apply the saved rules to images in the library (all of them when `ids`
+   * is omitted). Only adds tags. Returns the number of images that gained tags.
+   */
+  apply: (ids?: string[]) => invoke<number>("apply_tag_rules", { ids }),
+};
+
+// =============================================================================
+// Sidecar Types
+// =============================================================================
+
+export type SidecarFormat = "xmp" | "json";
+
+export interface SidecarSettings {
+  /** Keep an XMP sidecar next to each original up to date as images are edited */
+  writeXmp: boolean;
+  /** Same, for the JSON variant */
+  writeJson: boolean;
+}
+
+export interface SidecarWriteResult {
+  filesWritten: number;
+  errors: string[];
+}
+
+// =============================================================================
+// Sidecar Commands
+// =============================================================================
+
+export const sidecarApi = {
+  getSettings: () => invoke<SidecarSettings>("get_sidecar_settings"),
+
+  /** Save sidecar settings (omit to restore the defaults) */
+  setSettings: (settings?: SidecarSettings) =>
+    invoke<SidecarSettings>("set_sidecar_settings", { settings }),
+
+  /**
+   * Write title, description, tags, rating and solved coordinates next to
+   * the images' originals, in `formats` or else the ones turned on in the
+   * settings. Existing XMP sidecars keep their other properties.
+   */
+  write: (imageIds: string[], formats?: SidecarFormat[]) =>
+    invoke<SidecarWriteResult>("write_image_sidecars", { imageIds, formats }),
+};
+
+// =============================================================================
+// Solar System Types
+// =============================================================================
+
+export type SolarSystemBody =
+  | "Sun"
+  | "Moon"
+  | "Mercury"
+  | "Venus"
+  | "Mars"
+  | "Jupiter"
+  | "Saturn"
+  | "Uranus"
+  | "Neptune";
+
+/** Geocentric position and appearance of a body at capture time */
+export interface SolarSystemEphemeris {
+  /** J2000 position in degrees */
+  ra: number;
+  dec: number;
+  distance_au: number;
+  /** Equatorial diameter in arcseconds */
+  apparent_diameter_arcsec: number;
+  /** Sun-body-Earth angle in degrees (null for the Sun) */
+  phase_angle: number | null;
+  /** Lit fraction of the disk, 0-1 (null for the Sun) */
+  illuminated_fraction: number | null;
+}
+
+/** Stored in image metadata under "solar_system" */
+export interface SolarSystemInfo {
+  body: SolarSystemBody;
+  observed_at: string | null;
+  /** Null when the capture time isn't known */
+  ephemeris: SolarSystemEphemeris | null;
+}
+
+// =============================================================================
+// Solar System Commands
+// =============================================================================
+
+export const solarSystemApi = {
+  /**
+   * Recognize planetary, lunar and solar images (all images when `imageIds`
+   * is omitted) and record their ephemeris. Returns the number updated.
+   */
+  identify: (imageIds?: string[]) =>
+    invoke<number>("identify_solar_system_images", { imageIds }),
+};
+
+// =============================================================================
+// Attention Queue Types
+// =============================================================================
+
+/** Why an image needs attention */
+export type AttentionCategory = "unsolved" | "missingThumbnail" | "missingTarget" | "missingFile";
+
+export interface AttentionCounts {
+  unsolved: number;
+  missingThumbnail: number;
+  missingTarget: number;
+  missingFile: number;
+  /** Images in at least one category */
+  total: number;
+}
+
+export interface AttentionItem {
+  image: Image;
+  categories: AttentionCategory[];
+}
+
+export interface AttentionQueue {
+  /** Counts across the whole library, whatever category was asked for */
+  counts: AttentionCounts;
+  /** Images needing attention, newest first */
+  items: AttentionItem[];
+}
+
+// =============================================================================
+// Attention Queue Commands
+// =============================================================================
+
+export const attentionApi = {
+  /**
+   * Images that are unsolved, lack a thumbnail or target name, or whose files
+   * are missing. Pass a category to list only those images.
+   */
+  getQueue: (category?: AttentionCategory) =>
+    invoke<AttentionQueue>("get_attention_queue", { category }),
+};
+
+// =============================================================================
+// Library Health Types
+// =============================================================================
+
+export interface CacheUsage {
+  /** Cache directory name, e.g. "previews" or "sky-cutouts" */
+  name: string;
+  path: string;
+  bytes: number;
+  files: number;
+}
+
+export interface LibraryHealth {
+  /** Library images, leaving out frames in triage and stacked subframes */
+  images: number;
+  /** Images whose image or FITS file is no longer on disk */
+  imagesMissingFiles: number;
+  /** Images never plate solved (solar system images aren't counted) */
+  unsolvedImages: number;
+  collections: number;
+  /** Session collections with no night, like "Unknown Session" */
+  collectionsWithoutDates: number;
+  /** Solves submitted to astrometry.net and not yet finished */
+  pendingJobs: number;
+  /** RFC 3339; null when there are no backups */
+  lastBackupAt: string | null;
+  /** Whole days since the last backup */
+  backupAgeDays: number | null;
+  /** Database file size, including its write-ahead log */
+  databaseBytes: number;
+  /** Whether astra_astro is loaded and the Python functions are usable */
+  pythonAvailable: boolean;
+  python: PythonStatus;
+  /** Each directory under the app cache dir, largest first */
+  caches: CacheUsage[];
+  cacheBytes: number;
+}
+
+// =============================================================================
+// Library Health Commands
+// =============================================================================
+
+export const libraryHealthApi = {
+  /** Summary of the library's state for a status screen */
+  get: () => invoke<LibraryHealth>("get_library_health"),
+};
+
+// =============================================================================
+// Log Types
+// =============================================================================
+
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+export interface LogEntry {
+  /** RFC 3339, local time */
+  timestamp: string;
+  level: LogLevel;
+  /** Module that logged it */
+  target: string;
+  /** Scan or auto-import job the entry was logged in */
+  jobId: string | null;
+  message: string;
+}
+
+// =============================================================================
+// Log Commands
+// =============================================================================
+
+export const logsApi = {
+  /**
+   * Recent log entries, oldest first. `level` is the least severe level to
+   * include (default "info"); `jobId` keeps only one job's entries.
+   */
+  getRecent: (level?: LogLevel, limit?: number, jobId?: string) =>
+    invoke<LogEntry[]>("get_recent_logs", { level, limit, jobId }),
+
+  /**
+   * Zip the log files for a support request, to `path` or the app data
+   * dir's log-exports/. Returns where it was written.
+   */
+  export: (path?: string) => invoke<string>("export_logs", { path }),
+};
+
+// =============================================================================
+// Job Metrics Types
+// =============================================================================
+
+export type JobPhase = "scan" | "fits_parse" | "thumbnail" | "db_insert" | "total";
+
+export interface PhaseMetrics {
+  phase: JobPhase;
+  /** Files (or runs, for scan and total) timed */
+  count: number;
+  /** Summed over parallel workers, so can exceed the job's wall time */
+  totalMs: number;
+  /** Slowest single file or run */
+  maxMs: number;
+  meanMs: number;
+}
+
+export interface JobMetrics {
+  jobId: string;
+  /** When the job finished, RFC 3339 */
+  recordedAt: string;
+  /** In pipeline order */
+  phases: PhaseMetrics[];
+}
+
+// =============================================================================
+// Job Metrics Commands
+// =============================================================================
+
+export const jobMetricsApi = {
+  /**
+   * Phase timings of a scan or auto-import job, by the job id on its
+   * result, status or log entries. Only recent jobs are kept.
+   */
+  get: (jobId: string) => invoke<JobMetrics>("get_job_metrics", { jobId }),
+};
+
+// =============================================================================
+// Plate Solving Types
+// =============================================================================
+
+export interface PlateSolveInput {
+  /** Image ID to plate solve */
+  id: string;
+  /** Solver type: "nova", "local", "astap", or "tetra3" */
+  solver: string;
+  /** API key for nova.astrometry.net (required for nova solver) */
+  apiKey?: string;
+  /** Custom API URL for local astrometry.net instance (optional) */
+  apiUrl?: string;
+  /** Lower bound of expected image scale (arcsec/pixel) */
+  scaleLower?: number;
+  /** Upper bound of expected image scale (arcsec/pixel) */
+  scaleUpper?: number;
+  /** Timeout in seconds */
+  timeout?: number;
+  /** Whether to query catalogs for objects after solving */
+  queryCatalogs?: boolean;
+  /** Catalogs to query (if not specified, queries all) */
+  catalogs?: string[];
+  /** Magnitude limit for bright stars */
+  starMagLimit?: number;
+  /** Hint RA in degrees (to speed up solving) */
+  hintRa?: number;
+  /** Hint Dec in degrees (to speed up solving) */
+  hintDec?: number;
+  /** Hint search radius in degrees (default: 10) */
+  hintRadius?: number;
+  /** Path to tetra3 database file (.bin, postcard format) — required for "tetra3" solver.
+   *  Older .rkyv databases from tetra3 ≤0.5.x will not load. */
+  tetra3DbPath?: string;
+  /** FOV estimate in degrees for tetra3 solver */
+  fovEstimate?: number;
+  /** Path to the ASTAP executable (optional, defaults to the first ASTAP found) */
+  astapPath?: string;
+  /** Path to solve-field for the "local" solver (optional, defaults to the first found) */
+  solveFieldPath?: string;
+}
+
+export interface PlateSolveResult {
+  success: boolean;
+  centerRa: number;
+  centerDec: number;
+  pixelScale: number;
+  rotation: number;
+  widthDeg: number;
+  heightDeg: number;
+  imageWidth: number;
+  imageHeight: number;
+  solver: string;
+  solveTime: number;
+  errorMessage?: string;
+}
+
+export interface CatalogObject {
+  name: string;
+  catalog: string;
+  objectType: string;
+  ra: number;
+  dec: number;
+  magnitude?: number;
+  size?: string;
+  sizeArcmin?: number;
+  commonName?: string;
+  pixelX?: number;
+  pixelY?: number;
+  radiusPx?: number;
+}
+
+export interface PlateSolveResponse extends PlateSolveResult {
+  objects: CatalogObject[];
+}
+
+// =============================================================================
+// Plate Solving Commands
+// =============================================================================
+
+export interface SolverInfo {
+  available: boolean;
+  version: string | null;
+  details: string;
+}
+
+export interface SolveHints {
+  scaleArcsec?: number;
+  scaleLower?: number;
+  scaleUpper?: number;
+  raHint?: number;
+  decHint?: number;
+  fovDeg?: number;
+  focalLengthMm?: number;
+  pixelSizeUm?: number;
+  imageWidth: number;
+  imageHeight: number;
+}
+
+export interface PlateSolveCollectionOptions {
+  solver: string;
+  apiKey?: string;
+  apiUrl?: string;
+  timeout?: number;
+  queryCatalogs?: boolean;
+  catalogs?: string[];
+  starMagLimit?: number;
+  tetra3DbPath?: string;
+  fovEstimate?: number;
+  astapPath?: string;
+  solveFieldPath?: string;
+}
+
+export interface BatchSolveFailure {
+  imageId: string;
+  filename: string;
+  error: string;
+}
+
+export interface PlateSolveBatchSummary {
+  total: number;
+  solved: number;
+  failed: number;
+  skipped: number;
+  failures: BatchSolveFailure[];
+}
+
+/** Payload of the "plate-solve-batch-progress" event */
+export interface PlateSolveBatchProgress {
+  completed: number;
+  total: number;
+  imageId: string;
+  filename: string;
+  success: boolean;
+  error: string | null;
+}
+
+/** Sky position in degrees */
+export interface SkyPosition {
+  ra: number;
+  dec: number;
+}
+
+/** 0-based pixel position, in the same frame as annotation pixelX/pixelY */
+export interface PixelPosition {
+  x: number;
+  y: number;
+}
+
+export interface IdentifyTargetOptions {
+  /** Solver options, used when the image has no plate solution yet */
+  solve?: PlateSolveCollectionOptions;
+  renameCollection?: boolean;
+}
+
+export interface IdentifiedTarget {
+  imageId: string;
+  target: CatalogObject | null;
+  solved: boolean;
+  collectionName: string | null;
+}
+
+export interface AnnotatedPreviewOptions {
+  grid?: boolean;
+  constellations?: boolean;
+  labels?: boolean;
+}
+
+export interface AnnotatedPreview {
+  path: string;
+  cached: boolean;
+}
+
+/** A solve waiting on nova.astrometry.net */
+export interface NovaJobStatus {
+  imageId: string;
+  filename: string;
+  submissionId: number;
+  jobId: number | null;
+  status: "queued" | "solving";
+  submittedAt: string;
+}
+
+/** Payload of the "nova-job-finished" event */
+export interface NovaJobFinished {
+  imageId: string;
+  success: boolean;
+  errorMessage: string | null;
+}
+
+export const plateSolveApi = {
+  /**
+   * Plate solve an image and optionally query catalogs for objects
+   */
+  solve: (input: PlateSolveInput) =>
+    invoke<PlateSolveResponse>("plate_solve_image", { input }),
+
+  /**
+   * Plate solve every unsolved image in a collection, using scale hints
+   * from equipment profiles. Emits "plate-solve-batch-progress" events.
+   */
+  solveCollection: (
+    collectionId: string,
+    options: PlateSolveCollectionOptions,
+  ) =>
+    invoke<PlateSolveBatchSummary>("plate_solve_collection", {
+      collectionId,
+      options,
+    }),
+
+  /**
+   * Detect which plate solvers are installed, checking configured ASTAP and
+   * solve-field paths if given. The "local" entry's details report the
+   * astrometry.net index files found.
+   */
+  detectSolvers: (astapPath?: string, solveFieldPath?: string) =>
+    invoke<Record<string, SolverInfo>>("detect_plate_solvers", {
+      astapPath,
+      solveFieldPath,
+    }),
+
+  /**
+   * Extract plate solving hints from a FITS file's headers
+   */
+  getSolveHints: (imageId: string) =>
+    invoke<SolveHints>("get_solve_hints", { imageId }),
+
+  /**
+   * Upload an image to nova.astrometry.net without waiting for the solve.
+   * The result is recorded when the job finishes, and "nova-job-finished"
+   * is emitted.
+   */
+  submitNovaSolve: (input: PlateSolveInput) =>
+    invoke<NovaJobStatus>("submit_nova_solve", { input }),
+
+  /**
+   * Solves waiting on nova.astrometry.net, oldest first
+   */
+  getNovaJobs: () => invoke<NovaJobStatus[]>("get_nova_jobs"),
+
+  /**
+   * Convert a pixel position to RA/Dec using the image's plate solution
+   */
+  pixelToSky: (imageId: string, x: number, y: number) =>
+    invoke<SkyPosition>("pixel_to_sky", { imageId, x, y }),
+
+  /**
+   * Convert RA/Dec to a pixel position using the image's plate solution.
+   * Returns null for positions on the far side of the sky.
+   */
+  skyToPixel: (imageId: string, ra: number, dec: number) =>
+    invoke<PixelPosition | null>("sky_to_pixel", { imageId, ra, dec }),
+
+  /**
+   * Get a PNG of a solved image with catalog objects, an RA/Dec grid and
+   * constellation boundaries drawn on it. Renders are cached per solution.
+   */
+  getAnnotatedPreview: (imageId: string, options?: AnnotatedPreviewOptions) =>
+    invoke<AnnotatedPreview>("get_annotated_preview", { imageId, options }),
+
+  /**
+   * Identify an image's target from the most prominent catalog object in
+   * its field, plate solving it first if needed
+   */
+  identifyTarget: (imageId: string, options?: IdentifyTargetOptions) =>
+    invoke<IdentifiedTarget>("identify_target", { imageId, options }),
+
+  /**
+   * Query catalogs for objects in a given sky region
+   */
+  queryRegion: (
+    centerRa: number,
+    centerDec: number,
+    widthDeg: number,
+    heightDeg: number,
+    catalogs?: string[],
+    starMagLimit?: number
+  ) =>
+    invoke<CatalogObject[]>("query_sky_region", {
+      centerRa,
+      centerDec,
+      widthDeg,
+      heightDeg,
+      catalogs,
+      starMagLimit,
+    }),
+};
+
+// =============================================================================
+// Skymap Types
+// =============================================================================
+
+export interface SkymapInput {
+  /** Center Right Ascension in degrees */
+  centerRa: number;
+  /** Center Declination in degrees */
+  centerDec: number;
+  /** Field of view width in degrees for the map */
+  fovWidth?: number;
+  /** Field of view height in degrees for the map */
+  fovHeight?: number;
+  /** Image FOV width in degrees (for rectangle overlay) */
+  imageWidth?: number;
+  /** Image FOV height in degrees (for rectangle overlay) */
+  imageHeight?: number;
+  /** Output format (default: png) */
+  format?: SkymapFormat;
+}
+
+export type SkymapFormat = "png" | "svg";
+
+export interface SkymapResponse {
+  success: boolean;
+  /** Map as a PNG or SVG data URL */
+  image?: string;
+  error?: string;
+}
+
+/** A position in degrees and map canvas pixels */
+export interface ChartPoint {
+  ra: number;
+  dec: number;
+  x: number;
+  y: number;
+}
+
+export interface ChartStar extends ChartPoint {
+  /** Hipparcos catalog number */
+  hip: number;
+  magnitude: number;
+  /** Suggested marker radius in pixels */
+  radius: number;
+}
+
+export interface ChartObject extends ChartPoint {
+  name: string;
+  sizeArcmin?: number;
+  /** Marker radius in pixels: the object's size, or a minimum marker */
+  radius: number;
+}
+
+/** Contents of a skymap, for drawing an interactive chart */
+export interface SkymapData {
+  /** Canvas size in pixels */
+  width: number;
+  height: number;
+  /** Map extent in degrees */
+  fovWidth: number;
+  fovHeight: number;
+  /** Scale at the map center */
+  pixelsPerDegree: number;
+  center: ChartPoint;
+  stars: ChartStar[];
+  objects: ChartObject[];
+  /** Constellation figure segments */
+  lines: [ChartPoint, ChartPoint][];
+  /** Closed outline of the image's field, empty when its size is unknown */
+  field: ChartPoint[];
+}
+
+export interface CoverageMapInput {
+  /** Only images matching these acquisition criteria */
+  query?: AcquisitionQuery;
+  /** Only images in this collection */
+  collectionId?: string;
+  /** Output format (default: png) */
+  format?: SkymapFormat;
+}
+
+export interface SkyCutoutOptions {
+  /** Width in pixels (default 800) */
+  width?: number;
+  /** Height in pixels (default: square) */
+  height?: number;
+  /** HiPS survey ID, e.g. "CDS/P/DSS2/red" (default DSS2 color) */
+  survey?: string;
+}
+
+export interface SkyCutout {
+  /** JPEG in the cutout cache */
+  path: string;
+  dataUrl: string;
+  width: number;
+  height: number;
+  survey: string;
+  ra: number;
+  dec: number;
+  /** Field of view across the width, in degrees */
+  fov: number;
+  /** Served from the cache rather than downloaded */
+  cached: boolean;
+}
+
+export interface CoverageMapResponse extends SkymapResponse {
+  /** Images drawn with their plate-solved field */
+  solved: number;
+  /** Images drawn at their header coordinates only */
+  positioned: number;
+  /** Images with no known sky position */
+  unplaced: number;
+  /** Percentage of the sky inside at least one solved field */
+  coveragePercent: number;
+}
+
+// =============================================================================
+// Skymap Commands
+// =============================================================================
+
+export const skymapApi = {
+  /**
+   * Generate a skymap showing the location of an image on the sky
+   */
+  generate: (input: SkymapInput) =>
+    invoke<SkymapResponse>("generate_skymap", { input }),
+
+  /**
+   * Get the contents of a skymap as structured data for an interactive chart
+   */
+  getData: (input: SkymapInput) =>
+    invoke<SkymapData>("get_skymap_data", { input }),
+
+  /**
+   * Generate a wide-field skymap showing position on the entire sky
+   */
+  generateWide: (centerRa: number, centerDec: number, format?: SkymapFormat) =>
+    invoke<SkymapResponse>("generate_wide_skymap", {
+      centerRa,
+      centerDec,
+      format,
+    }),
+
+  /**
+   * Generate an all-sky map of every imaged field
+   */
+  generateCoverageMap: (input?: CoverageMapInput) =>
+    invoke<CoverageMapResponse>("generate_coverage_map", { input }),
+
+  /**
+   * Fetch a survey cutout (DSS2 color by default) of the sky around a
+   * position, spanning `fov` degrees across its width. Cached locally.
+   */
+  getCutout: (ra: number, dec: number, fov: number, options?: SkyCutoutOptions) =>
+    invoke<SkyCutout>("get_sky_cutout", { ra, dec, fov, options }),
+};
+
+// =============================================================================
+// Image Processing Types
+// =============================================================================
+
+export interface ProcessImageInput {
+  /** Image ID to process */
+  id: string;
+  /** Target type override (optional, defaults to "auto") */
+  targetType?: string;
+  /** Stretch method (optional, defaults to "statistical") */
+  stretchMethod?: string;
+  /** Stretch factor (optional, default based on target type) */
+  stretchFactor?: number;
+  /** Whether to remove background (optional, defaults to true) */
+  backgroundRemoval?: boolean;
+  /** Background extraction method: "median" or "polynomial" (optional, defaults to "median") */
+  backgroundMethod?: string;
+  /** Polynomial order for the "polynomial" method, 1-4 (optional, defaults to 2) */
+  gradientOrder?: number;
+  /** Whether to reduce star brightness (optional, defaults to false) */
+  starReduction?: boolean;
+  /** Whether to apply color calibration (optional, defaults to true) */
+  colorCalibration?: boolean;
+  /** Noise reduction strength 0-1 (optional, defaults to 0) */
+  noiseReduction?: number;
+  /** Contrast adjustment (optional, defaults to 1.3 for Seestar-like output) */
+  contrast?: number;
+  /** Preset to start from (optional, defaults to the target type's default preset) */
+  presetId?: string;
+  /** Formats written alongside the processed FITS and PNG preview (optional, defaults to the processing settings) */
+  outputFormats?: OutputFormat[];
+}
+
+/** Additional output of a processed image: 16-bit TIFF or JPEG, tagged sRGB */
+export type OutputFormat = "tiff" | "jpeg";
+
+export interface ReprocessImageInput extends ProcessImageInput {
+  /** Move the superseded version's output files into an archive/ folder */
+  archivePrevious?: boolean;
+}
+
+export interface ProcessingResult {
+  success: boolean;
+  outputFitsPath: string;
+  outputPreviewPath: string;
+  targetType: string;
+  processingParams: Record<string, unknown>;
+  processingTime: number;
+  errorMessage?: string;
+}
+
+export interface ProcessImageResponse extends ProcessingResult {
+  /** 16-bit TIFF written alongside, if requested */
+  outputTiffPath?: string;
+  /** JPEG written alongside, if requested */
+  outputJpegPath?: string;
+}
+
+export interface PreviewResult {
+  success: boolean;
+  /** Processed preview as a PNG data URL */
+  previewData: string;
+  width: number;
+  height: number;
+  targetType: string;
+  processingParams: Record<string, unknown>;
+  processingTime: number;
+  errorMessage?: string;
+}
+
+export interface TargetInfo {
+  targetType: string;
+  objectName: string;
+  confidence: number;
+  simbadType?: string;
+}
+
+export interface ProcessingParams {
+  targetType: string;
+  stretchMethod: string;
+  stretchFactor: number;
+  backgroundRemoval: boolean;
+  backgroundMethod?: string;
+  gradientOrder?: number;
+  starReduction: boolean;
+  colorCalibration: boolean;
+  noiseReduction: number;
+  contrast: number;
+}
+
+export interface ProcessingPreset {
+  id: string;
+  user_id: string;
+  name: string;
+  /** Target type the preset is meant for (null = any target) */
+  target_type: string | null;
+  /** JSON object of ProcessingParams */
+  params: string;
+  is_default: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreateProcessingPresetInput {
+  name: string;
+  targetType?: string;
+  params: Partial<ProcessingParams>;
+  isDefault?: boolean;
+}
+
+export interface UpdateProcessingPresetInput {
+  id: string;
+  name?: string;
+  targetType?: string;
+  params?: Partial<ProcessingParams>;
+}
+
+export interface ProcessingSettings {
+  /** Root directory for processed outputs (null = next to each original) */
+  outputRoot: string | null;
+  /** Formats written alongside the processed FITS and PNG preview */
+  outputFormats: OutputFormat[];
+  /** Quality of JPEG outputs, 1-100 (null = 92) */
+  jpegQuality: number | null;
+}
+
+// Target type enum for UI
+export const TARGET_TYPES = [
+  { value: "auto", label: "Auto-detect" },
+  { value: "emission_nebula", label: "Emission Nebula" },
+  { value: "reflection_nebula", label: "Reflection Nebula" },
+  { value: "planetary_nebula", label: "Planetary Nebula" },
+  { value: "galaxy", label: "Galaxy" },
+  { value: "globular_cluster", label: "Globular Cluster" },
+  { value: "open_cluster", label: "Open Cluster" },
+  { value: "star_field", label: "Star Field" },
+] as const;
+
+// Stretch method enum for UI
+export const STRETCH_METHODS = [
+  { value: "statistical", label: "Statistical (Recommended)" },
+  { value: "arcsinh", label: "Arcsinh" },
+  { value: "log", label: "Logarithmic" },
+] as const;
+
+// Background extraction method enum for UI
+export const BACKGROUND_METHODS = [
+  { value: "median", label: "Median Filter" },
+  { value: "polynomial", label: "Polynomial Fit" },
+] as const;
+
+// =============================================================================
+// Image Processing Commands
+// =============================================================================
+
+export const imageProcessApi = {
+  /**
+   * Process a FITS image with stretch and enhancements
    */
-  getSolveHints: (imageId: string) =>
-    invoke<SolveHints>("get_solve_hints", { imageId }),
+  process: (input: ProcessImageInput) =>
+    invoke<ProcessImageResponse>("process_fits_image", { input }),
 
   /**
-   * Query catalogs for objects in a given sky region
+   * Reprocess an image (the source or any processed version of it). The new
+   * result supersedes the current processed version and becomes current.
    */
-  queryRegion: (
-    centerRa: number,
-    centerDec: number,
-    widthDeg: number,
-    heightDeg: number,
-    catalogs?: string[],
-    starMagLimit?: number
+  reprocess: (input: ReprocessImageInput) =>
+    invoke<ProcessImageResponse>("reprocess_image", { input }),
+
+  /**
+   * Process a downsampled preview without writing files, for tuning
+   * parameters interactively
+   */
+  preview: (imageId: string, params: ProcessingParams, maxSize?: number) =>
+    invoke<PreviewResult>("preview_processing", { imageId, params, maxSize }),
+
+  /**
+   * Classify a target from its object name
+   */
+  classifyTarget: (objectName: string) =>
+    invoke<TargetInfo>("classify_target_type", { objectName }),
+
+  /**
+   * Get default processing parameters for a target type, applying the
+   * matched equipment profile's overrides when an image ID is given
+   */
+  getDefaults: (targetType: string, imageId?: string) =>
+    invoke<ProcessingParams>("get_processing_defaults", { targetType, imageId }),
+
+  /**
+   * List saved processing presets
+   */
+  getPresets: () => invoke<ProcessingPreset[]>("get_processing_presets"),
+
+  createPreset: (input: CreateProcessingPresetInput) =>
+    invoke<ProcessingPreset>("create_processing_preset", { input }),
+
+  updatePreset: (input: UpdateProcessingPresetInput) =>
+    invoke<ProcessingPreset>("update_processing_preset", { input }),
+
+  deletePreset: (id: string) => invoke<boolean>("delete_processing_preset", { id }),
+
+  /**
+   * Make a preset the default for its target type, or unset it
+   */
+  setDefaultPreset: (id: string, isDefault: boolean) =>
+    invoke<ProcessingPreset>("set_default_processing_preset", { id, isDefault }),
+
+  /**
+   * Get the processing output settings
+   */
+  getSettings: () => invoke<ProcessingSettings>("get_processing_settings"),
+
+  /**
+   * Set (or clear, with null) the root directory processed outputs are
+   * written under, in <root>/<target>/<date> subfolders
+   */
+  setOutputRoot: (outputRoot: string | null) =>
+    invoke<ProcessingSettings>("set_processing_output_root", { outputRoot }),
+
+  /**
+   * Set the formats written alongside each processed image, and the JPEG
+   * quality (null = the default)
+   */
+  setOutputFormats: (outputFormats: OutputFormat[], jpegQuality: number | null = null) =>
+    invoke<ProcessingSettings>("set_processing_output_formats", {
+      outputFormats,
+      jpegQuality,
+    }),
+};
+
+// =============================================================================
+// Target Browser Types
+// =============================================================================
+
+export interface TargetWithCount {
+  /** Target/object name */
+  name: string;
+  /** Number of images of this target */
+  imageCount: number;
+  /** ID of the most recent image */
+  latestImageId: string | null;
+  /** Thumbnail of the most recent image */
+  latestThumbnail: string | null;
+}
+
+export interface ObjectName {
+  /** Canonical name */
+  name: string;
+  /** Every known name, the canonical one first */
+  aliases: string[];
+}
+
+export interface MergeTargetsResult {
+  /** Name both targets are known by now */
+  name: string;
+  imagesUpdated: number;
+  todosUpdated: number;
+}
+
+export interface FilterExposureSummary {
+  filter: string;
+  band: string;
+  imageCount: number;
+  frameCount: number;
+  totalExposureSeconds: number;
+}
+
+/** Entry in the embedded deep sky catalogs */
+export interface DeepSkyObject {
+  name: string;
+  ra: number;
+  dec: number;
+  sizeArcmin: number | null;
+  objectType: string | null;
+  commonNames: string[];
+}
+
+/** One night a target was imaged */
+export interface TargetSession {
+  /** YYYY-MM-DD */
+  date: string;
+  imageCount: number;
+  exposureSeconds: number;
+  collectionIds: string[];
+}
+
+export interface TargetVisibility {
+  transit: MeridianTransit;
+  /** Tonight's altitude chart, when it could be computed */
+  night: NightChart | null;
+}
+
+export interface TargetProfile {
+  /** Canonical name */
+  name: string;
+  aliases: string[];
+  simbad: SimbadObject | null;
+  catalog: DeepSkyObject | null;
+  /** J2000 degrees, from SIMBAD or the catalogs */
+  ra: number | null;
+  dec: number | null;
+  /** Newest first */
+  images: Image[];
+  /** Integration per filter, most first */
+  filters: FilterExposureSummary[];
+  totalExposureSeconds: number;
+  todos: AstronomyTodo[];
+  /** Most recent first */
+  sessions: TargetSession[];
+  /** Best rated processed or stacked image */
+  bestImage: Image | null;
+  /** Only with a location and known coordinates */
+  visibility: TargetVisibility | null;
+}
+
+// =============================================================================
+// Target Browser Commands
+// =============================================================================
+
+export const targetApi = {
+  /**
+   * Get all unique targets with their image counts
+   */
+  getAll: () => invoke<TargetWithCount[]>("get_targets"),
+
+  /**
+   * Search images by target name (partial match)
+   */
+  searchImages: (query: string) =>
+    invoke<Image[]>("search_images_by_target", { query }),
+
+  /**
+   * Get all images for a specific target under any of its names
+   */
+  getImages: (targetName: string) =>
+    invoke<Image[]>("get_images_by_target", { targetName }),
+
+  /**
+   * Canonical name and known aliases of an object
+   */
+  normalizeName: (name: string) =>
+    invoke<ObjectName>("normalize_object_name", { name }),
+
+  /**
+   * Make nameB an alias of nameA and rename images and todos to match
+   */
+  merge: (nameA: string, nameB: string) =>
+    invoke<MergeTargetsResult>("merge_targets", { nameA, nameB }),
+
+  /**
+   * Everything known about a target for its detail page; visibility needs
+   * a location
+   */
+  getProfile: (name: string, location?: ObserverLocation) =>
+    invoke<TargetProfile>("get_target_profile", { name, location }),
+};
+
+// =============================================================================
+// Quick Search Types
+// =============================================================================
+
+/** What a quick search result refers to */
+export type QuickSearchKind =
+  | "target"
+  | "collection"
+  | "image"
+  | "todo"
+  | "note"
+  | "catalogObject";
+
+export interface QuickSearchResult {
+  kind: QuickSearchKind;
+  /** Record id; the name for targets and catalog objects */
+  id: string;
+  title: string;
+  subtitle: string | null;
+  thumbnail: string | null;
+  /** Higher is a better match */
+  score: number;
+}
+
+// =============================================================================
+// Quick Search Commands
+// =============================================================================
+
+export const quickSearchApi = {
+  /**
+   * Search targets, collections, images, the observing list and catalog
+   * objects at once, best matches first (20 results unless a limit is given)
+   */
+  search: (query: string, limit?: number) =>
+    invoke<QuickSearchResult[]>("quick_search", { query, limit }),
+};
+
+// =============================================================================
+// Saved Search Types
+// =============================================================================
+
+export type SearchEntity = "images" | "todos";
+
+/** Date range ending today, so a saved "this year" stays current */
+export type SearchPeriod = "last7Days" | "last30Days" | "thisMonth" | "thisYear";
+
+/**
+ * Search criteria; unset fields match anything. Image-only fields are
+ * ignored for todos and vice versa.
+ */
+export interface SearchCriteria extends AcquisitionQuery {
+  /** Text in an image's summary, filename or description, or a todo's name or notes */
+  text?: string;
+  /** Tags that must all be present */
+  tags?: string[];
+  /** Only images observed (todos added) in this period */
+  period?: SearchPeriod;
+  /** Filter band: "narrowband", "broadband" or "dualband" */
+  band?: string;
+  favorite?: boolean;
+  collectionId?: string;
+  /** true: processed outputs; false: originals without a processed version */
+  processed?: boolean;
+  completed?: boolean;
+  flagged?: boolean;
+  objectType?: string;
+}
+
+export interface SavedSearch {
+  id: string;
+  user_id: string;
+  name: string;
+  entity: SearchEntity;
+  /** JSON object of SearchCriteria */
+  criteria: string;
+  created_at: string;
+  updated_at: string;
+  /** Shown on the Home screen */
+  pinned: boolean;
+}
+
+export interface CreateSavedSearchInput {
+  name: string;
+  entity: SearchEntity;
+  criteria: SearchCriteria;
+}
+
+export interface UpdateSavedSearchInput {
+  id: string;
+  name?: string;
+  entity?: SearchEntity;
+  criteria?: SearchCriteria;
+}
+
+/** One page of results; only the list for the search's entity is filled */
+export interface SearchResults {
+  entity: SearchEntity;
+  /** Matches across all pages */
+  total: number;
+  offset: number;
+  limit: number;
+  images: Image[];
+  todos: AstronomyTodo[];
+}
+
+// =============================================================================
+// Saved Search Commands
+// =============================================================================
+
+export const savedSearchApi = {
+  getAll: () => invoke<SavedSearch[]>("get_saved_searches"),
+
+  create: (input: CreateSavedSearchInput) =>
+    invoke<SavedSearch>("create_saved_search", { input }),
+
+  update: (input: UpdateSavedSearchInput) =>
+    invoke<SavedSearch>("update_saved_search", { input }),
+
+  delete: (id: string) => invoke<boolean>("delete_saved_search", { id }),
+
+  /** Run a saved search and return one page of results */
+  execute: (id: string, offset?: number, limit?: number) =>
+    invoke<SearchResults>("execute_saved_search", { id, offset, limit }),
+
+  /** Run criteria without saving them (quick filters, previews) */
+  run: (
+    entity: SearchEntity,
+    criteria: SearchCriteria,
+    offset?: number,
+    limit?: number
   ) =>
-    invoke<CatalogObject[]>("query_sky_region", {
-      centerRa,
-      centerDec,
-      widthDeg,
-      heightDeg,
-      catalogs,
-      starMagLimit,
+    invoke<SearchResults>("execute_search", {
+      entity,
+      criteria,
+      offset,
+      limit,
     }),
 };
 
 // =============================================================================
-// Skymap Types
+// Favorites Types
 // =============================================================================
 
-export interface SkymapInput {
-  /** Center Right Ascension in degrees */
-  centerRa: number;
-  /** Center Declination in degrees */
-  centerDec: number;
-  /** Field of view width in degrees for the map */
-  fovWidth?: number;
-  /** Field of view height in degrees for the map */
-  fovHeight?: number;
-  /** Image FOV width in degrees (for rectangle overlay) */
-  imageWidth?: number;
-  /** Image FOV height in degrees (for rectangle overlay) */
-  imageHeight?: number;
+/** Everything favorited, flagged or pinned, for the Home screen */
+export interface Favorites {
+  images: Image[];
+  /** Favorite collections that aren't archived */
+  collections: Collection[];
+  /** Flagged todos that are still open */
+  todos: AstronomyTodo[];
+  /** Pinned targets in the order they were pinned, with their image counts */
+  targets: TargetWithCount[];
+  savedSearches: SavedSearch[];
 }
 
-export interface SkymapResponse {
-  success: boolean;
-  /** Base64-encoded PNG image */
-  image?: string;
-  error?: string;
-}
+// =============================================================================
+// Favorites Commands
+// =============================================================================
+
+export const favoritesApi = {
+  /** Favorite images and collections, flagged todos, pinned targets and searches */
+  getAll: () => invoke<Favorites>("get_favorites"),
+
+  /** Pin a target; returns false if it was already pinned */
+  pinTarget: (name: string) => invoke<boolean>("pin_target", { name }),
+
+  /** Unpin a target; returns false if it wasn't pinned */
+  unpinTarget: (name: string) => invoke<boolean>("unpin_target", { name }),
+
+  pinSavedSearch: (id: string) => invoke<SavedSearch>("pin_saved_search", { id }),
+
+  unpinSavedSearch: (id: string) =>
+    invoke<SavedSearch>("unpin_saved_search", { id }),
+};
+
+// =============================================================================
+// UI Preference Types
+// =============================================================================
+
+/**
+ * Theme, layout, default views and column choices, keyed by the part of the
+ * UI that owns them. Stored per user and synced with the library.
+ */
+export type UiPreferences = Record<string, unknown>;
 
 // =============================================================================
-// Skymap Commands
+// UI Preference Commands
 // =============================================================================
 
-export const skymapApi = {
-  /**
-   * Generate a skymap showing the location of an image on the sky
-   */
-  generate: (input: SkymapInput) =>
-    invoke<SkymapResponse>("generate_skymap", { input }),
+export const uiPreferencesApi = {
+  /** The current user's preferences ({} before the first save) */
+  get: () => invoke<UiPreferences>("get_ui_preferences"),
 
   /**
-   * Generate a wide-field skymap showing position on the entire sky
+   * Save preferences. Top-level keys are merged into what's stored (null
+   * removes one) unless `replace` is set; returns the preferences as saved.
+   * Emits "ui-preferences-changed" with the same object.
    */
-  generateWide: (centerRa: number, centerDec: number) =>
-    invoke<SkymapResponse>("generate_wide_skymap", { centerRa, centerDec }),
+  set: (preferences: UiPreferences, replace?: boolean) =>
+    invoke<UiPreferences>("set_ui_preferences", { preferences, replace }),
 };
 
 // =============================================================================
-// Image Processing Types
+// Note Types
 // =============================================================================
 
-export interface ProcessImageInput {
-  /** Image ID to process */
+/** What a note is attached to */
+export type NoteEntity = "image" | "collection" | "session" | "target" | "equipment";
+
+export interface Note {
   id: string;
-  /** Target type override (optional, defaults to "auto") */
-  targetType?: string;
-  /** Stretch method (optional, defaults to "statistical") */
-  stretchMethod?: string;
-  /** Stretch factor (optional, default based on target type) */
-  stretchFactor?: number;
-  /** Whether to remove background (optional, defaults to true) */
-  backgroundRemoval?: boolean;
-  /** Whether to reduce star brightness (optional, defaults to false) */
-  starReduction?: boolean;
-  /** Whether to apply color calibration (optional, defaults to true) */
-  colorCalibration?: boolean;
-  /** Noise reduction strength 0-1 (optional, defaults to 0) */
-  noiseReduction?: number;
-  /** Contrast adjustment (optional, defaults to 1.3 for Seestar-like output) */
-  contrast?: number;
+  user_id: string;
+  entity_type: NoteEntity;
+  /** Record id; the night (YYYY-MM-DD) for sessions, canonical name for targets */
+  entity_id: string;
+  /** Markdown */
+  body: string;
+  attachments: string | null;  // JSON NoteAttachment[]
+  created_at: string;
+  updated_at: string;
 }
 
-export interface ProcessingResult {
-  success: boolean;
-  outputFitsPath: string;
-  outputPreviewPath: string;
-  targetType: string;
-  processingParams: Record<string, unknown>;
-  processingTime: number;
-  errorMessage?: string;
+export interface NoteAttachment {
+  id: string;
+  /** Original file name */
+  fileName: string;
+  contentType: string;
+  size: number;
+  addedAt: string;
 }
 
-export interface ProcessImageResponse extends ProcessingResult {}
-
-export interface TargetInfo {
-  targetType: string;
-  objectName: string;
-  confidence: number;
-  simbadType?: string;
+export interface CreateNoteInput {
+  entityType: NoteEntity;
+  entityId: string;
+  body: string;
+  /** Files to copy in as attachments */
+  attachments?: string[];
 }
 
-export interface ProcessingParams {
-  targetType: string;
-  stretchMethod: string;
-  stretchFactor: number;
-  backgroundRemoval: boolean;
-  starReduction: boolean;
-  colorCalibration: boolean;
-  noiseReduction: number;
-  contrast: number;
+export interface UpdateNoteInput {
+  id: string;
+  body: string;
 }
 
-// Target type enum for UI
-export const TARGET_TYPES = [
-  { value: "auto", label: "Auto-detect" },
-  { value: "emission_nebula", label: "Emission Nebula" },
-  { value: "reflection_nebula", label: "Reflection Nebula" },
-  { value: "planetary_nebula", label: "Planetary Nebula" },
-  { value: "galaxy", label: "Galaxy" },
-  { value: "globular_cluster", label: "Globular Cluster" },
-  { value: "open_cluster", label: "Open Cluster" },
-  { value: "star_field", label: "Star Field" },
-] as const;
-
-// Stretch method enum for UI
-export const STRETCH_METHODS = [
-  { value: "statistical", label: "Statistical (Recommended)" },
-  { value: "arcsinh", label: "Arcsinh" },
-  { value: "log", label: "Logarithmic" },
-] as const;
-
 // =============================================================================
-// Image Processing Commands
+// Note Commands
 // =============================================================================
 
-export const imageProcessApi = {
-  /**
-   * Process a FITS image with stretch and enhancements
-   */
-  process: (input: ProcessImageInput) =>
-    invoke<ProcessImageResponse>("process_fits_image", { input }),
+export const notesApi = {
+  /** Notes on an entity, newest first */
+  getForEntity: (entityType: NoteEntity, entityId: string) =>
+    invoke<Note[]>("get_notes", { entityType, entityId }),
 
-  /**
-   * Classify a target from its object name
-   */
-  classifyTarget: (objectName: string) =>
-    invoke<TargetInfo>("classify_target_type", { objectName }),
+  getById: (id: string) => invoke<Note | null>("get_note", { id }),
 
-  /**
-   * Get default processing parameters for a target type
-   */
-  getDefaults: (targetType: string) =>
-    invoke<ProcessingParams>("get_processing_defaults", { targetType }),
+  create: (input: CreateNoteInput) => invoke<Note>("create_note", { input }),
+
+  update: (input: UpdateNoteInput) => invoke<Note>("update_note", { input }),
+
+  /** Delete a note and its attachments */
+  delete: (id: string) => invoke<boolean>("delete_note", { id }),
+
+  /** Copy a file onto a note */
+  addAttachment: (noteId: string, path: string) =>
+    invoke<Note>("add_note_attachment", { noteId, path }),
+
+  removeAttachment: (noteId: string, attachmentId: string) =>
+    invoke<Note>("remove_note_attachment", { noteId, attachmentId }),
+
+  /** Local path of an attachment, for opening or previewing it */
+  getAttachmentPath: (noteId: string, attachmentId: string) =>
+    invoke<string>("get_note_attachment_path", { noteId, attachmentId }),
+
+  /** Notes containing the query, most recently changed first */
+  search: (query: string, entityType?: NoteEntity) =>
+    invoke<Note[]>("search_notes", { query, entityType }),
 };
 
+/**
+ * Parse a note's attachments from its JSON column
+ */
+export function parseNoteAttachments(note: Note): NoteAttachment[] {
+  if (!note.attachments) return [];
+  try {
+    return JSON.parse(note.attachments);
+  } catch {
+    return [];
+  }
+}
+
 // =============================================================================
-// Target Browser Types
+// Maintenance Types
 // =============================================================================
 
-export interface TargetWithCount {
-  /** Target/object name */
-  name: string;
-  /** Number of images of this target */
-  imageCount: number;
-  /** ID of the most recent image */
-  latestImageId: string | null;
-  /** Thumbnail of the most recent image */
-  latestThumbnail: string | null;
+export interface MaintenanceEntry {
+  id: string;
+  user_id: string;
+  equipment_id: string;
+  /** e.g. "Collimation", "Desiccant regeneration", "Firmware update" */
+  task: string;
+  /** YYYY-MM-DD */
+  performed_on: string;
+  /** Days until due again; null for one-off work */
+  interval_days: number | null;
+  notes: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreateMaintenanceInput {
+  equipmentId: string;
+  task: string;
+  /** YYYY-MM-DD; defaults to today */
+  performedOn?: string;
+  intervalDays?: number;
+  notes?: string;
+}
+
+/** Replaces an entry's editable fields */
+export interface UpdateMaintenanceInput {
+  id: string;
+  task: string;
+  performedOn: string;
+  intervalDays: number | null;
+  notes: string | null;
+}
+
+/** Payload item of the "maintenance-due" event */
+export interface DueMaintenance {
+  entryId: string;
+  equipmentId: string;
+  equipmentName: string;
+  equipmentKind: string;
+  task: string;
+  lastPerformedOn: string;
+  intervalDays: number;
+  dueOn: string;
+  /** Negative while the task is still coming up */
+  daysOverdue: number;
 }
 
 // =============================================================================
-// Target Browser Commands
+// Maintenance Commands
 // =============================================================================
 
-export const targetApi = {
-  /**
-   * Get all unique targets with their image counts
-   */
-  getAll: () => invoke<TargetWithCount[]>("get_targets"),
+export const maintenanceApi = {
+  /** Maintenance log for one item or all equipment, most recent first */
+  getLog: (equipmentId?: string) =>
+    invoke<MaintenanceEntry[]>("get_maintenance_log", { equipmentId }),
 
-  /**
-   * Search images by target name (partial match)
-   */
-  searchImages: (query: string) =>
-    invoke<Image[]>("search_images_by_target", { query }),
+  add: (input: CreateMaintenanceInput) =>
+    invoke<MaintenanceEntry>("add_maintenance_entry", { input }),
 
-  /**
-   * Get all images for a specific target (exact match)
-   */
-  getImages: (targetName: string) =>
-    invoke<Image[]>("get_images_by_target", { targetName }),
+  update: (input: UpdateMaintenanceInput) =>
+    invoke<MaintenanceEntry>("update_maintenance_entry", { input }),
+
+  delete: (id: string) => invoke<boolean>("delete_maintenance_entry", { id }),
+
+  /** Recurring tasks overdue or due within `withinDays` (default today) */
+  getDue: (withinDays?: number) =>
+    invoke<DueMaintenance[]>("get_due_maintenance", { withinDays }),
 };
 
 // =============================================================================
@@ -1050,6 +3699,8 @@ export interface AutoImportStatus {
   totalImported: number;
   isScanning: boolean;
   errors: string[];
+  /** Id the last scan's log lines are tagged with */
+  lastJobId: string | null;
 }
 
 // =============================================================================
@@ -1109,6 +3760,105 @@ export interface PublishStatus {
 // Share Commands
 // =============================================================================
 
+export interface GalleryExportResult {
+  indexPath: string;
+  images: number;
+  skipped: number;
+}
+
+export interface CollectionFilesOptions {
+  /** Hard-link instead of copying; falls back to copying across filesystems */
+  hardLink?: boolean;
+  /** Only export FITS files */
+  fitsOnly?: boolean;
+  /** Write a JSON sidecar per image (default true) */
+  sidecars?: boolean;
+}
+
+export interface CollectionFilesResult {
+  /** Folder the collection was exported into */
+  folder: string;
+  filesCopied: number;
+  filesLinked: number;
+  /** Files already in the destination from an earlier export */
+  filesSkipped: number;
+  bytesCopied: number;
+  errors: string[];
+  cancelled: boolean;
+}
+
+export type WatermarkPosition = "topLeft" | "topRight" | "bottomLeft" | "bottomRight" | "center";
+
+export interface PresentationOptions {
+  /** Longest side of the image in pixels; full size when omitted */
+  maxSize?: number;
+  /** JPEG quality, 1-100 (default 92) */
+  quality?: number;
+  /** Add a caption bar with target, date, exposure and equipment (default true) */
+  caption?: boolean;
+  /** Text drawn over the image, e.g. a name or copyright notice */
+  watermark?: string;
+  /** Default "bottomRight" */
+  watermarkPosition?: WatermarkPosition;
+  /** 0 (invisible) to 1 (solid), default 0.6 */
+  watermarkOpacity?: number;
+}
+
+export interface PresentationExportResult {
+  path: string;
+  width: number;
+  height: number;
+}
+
+export type TimelapseFormat = "gif" | "mp4";
+
+export interface TimelapseOptions {
+  format?: TimelapseFormat;
+  /** Frames per second (default 10) */
+  frameRate?: number;
+  /** Longest side in pixels (default 720 for GIFs, 1920 for MP4s) */
+  maxSize?: number;
+}
+
+export interface TimelapseResult {
+  path: string;
+  frames: number;
+  width: number;
+  height: number;
+  /** Images that couldn't be loaded, left out of the movie */
+  errors: string[];
+  cancelled: boolean;
+}
+
+export interface SessionReportResult {
+  markdownPath: string;
+  markdown: string;
+  /** Set when a PDF was requested */
+  pdfPath?: string;
+  pdfPages?: number;
+  images: number;
+  skipped: number;
+}
+
+export interface ImageBundleExportResult {
+  path: string;
+  images: number;
+  /** Image and FITS files included */
+  files: number;
+  /** Files the images refer to that aren't on disk */
+  missingFiles: string[];
+}
+
+export interface ImageBundleImportResult {
+  /** Collection holding the imported images; null when nothing was new */
+  collectionId: string | null;
+  imagesImported: number;
+  /** Images already in the library */
+  imagesSkipped: number;
+  filesExtracted: number;
+  errors: string[];
+}
+
 export const shareApi = {
   configureUpload: (input: ConfigureShareInput) =>
     invoke<void>("configure_share_upload", { input }),
@@ -1136,4 +3886,194 @@ export const shareApi = {
 
   publishGallery: (collectionId: string) =>
     invoke<PublishResult>("publish_collection_gallery", { collectionId }),
+
+  exportStaticGallery: (collectionId: string, path: string) =>
+    invoke<GalleryExportResult>("export_gallery", { collectionId, path }),
+
+  /**
+   * Copy (or hard-link) a collection's original files into
+   * `<destination>/<collection>/<object>/<night>/<filter>/` with a sidecar
+   * per image. Progress arrives as `collection-files-progress` events.
+   */
+  exportCollectionFiles: (
+    collectionId: string,
+    destination: string,
+    options?: CollectionFilesOptions
+  ) =>
+    invoke<CollectionFilesResult>("export_collection_files", {
+      collectionId,
+      destination,
+      options,
+    }),
+
+  cancelCollectionFilesExport: () =>
+    invoke<void>("cancel_collection_files_export"),
+
+  /**
+   * Share-ready JPEG of one image with an optional caption bar and
+   * watermark, keeping the source's color profile (sRGB otherwise)
+   */
+  exportPresentationImage: (imageId: string, path: string, options?: PresentationOptions) =>
+    invoke<PresentationExportResult>("export_presentation_image", { imageId, path, options }),
+
+  /**
+   * Time-lapse of `imageIds` (or every image in `collectionId`) in capture
+   * order. MP4 needs ffmpeg installed. Progress arrives as
+   * `timelapse-progress` events.
+   */
+  createTimelapse: (
+    target: { imageIds?: string[]; collectionId?: string },
+    path: string,
+    options?: TimelapseOptions
+  ) =>
+    invoke<TimelapseResult>("create_timelapse", {
+      imageIds: target.imageIds,
+      collectionId: target.collectionId,
+      path,
+      options,
+    }),
+
+  cancelTimelapse: () => invoke<void>("cancel_timelapse"),
+
+  /** Observing report (Markdown, optionally PDF) written into the folder at `path` */
+  generateSessionReport: (collectionId: string, path: string, pdf?: boolean) =>
+    invoke<SessionReportResult>("generate_session_report", { collectionId, path, pdf }),
+
+  /** Zip of images with their files, previews and metadata for another Astra user */
+  exportImageBundle: (imageIds: string[], path: string) =>
+    invoke<ImageBundleExportResult>("export_image_bundle", { imageIds, path }),
+
+  /** Import a bundle into a new collection; files go under `destination` when given */
+  importImageBundle: (path: string, destination?: string) =>
+    invoke<ImageBundleImportResult>("import_image_bundle", { path, destination }),
+};
+
+// =============================================================================
+// Sync Types
+// =============================================================================
+
+export interface SyncImport {
+  bundle_id: string;
+  device_id: string;
+  change_count: number;
+  imported_at: string;
+}
+
+export interface SyncStatus {
+  deviceId: string;
+  pendingChanges: number;
+  importedBundles: SyncImport[];
+}
+
+export interface SyncExportResult {
+  bundlePath: string | null;
+  changes: number;
+  message: string;
+}
+
+export interface SyncImportResult {
+  bundlesImported: number;
+  applied: number;
+  skipped: number;
+  message: string;
+}
+
+// =============================================================================
+// Sync Commands
+// =============================================================================
+
+export const syncApi = {
+  getStatus: () =>
+    invoke<SyncStatus>("get_sync_status"),
+
+  exportChanges: (folder: string) =>
+    invoke<SyncExportResult>("export_sync_changes", { folder }),
+
+  importChanges: (folder: string) =>
+    invoke<SyncImportResult>("import_sync_changes", { folder }),
+
+  syncFolder: (folder: string) =>
+    invoke<SyncImportResult>("sync_folder", { folder }),
+};
+
+// =============================================================================
+// Local HTTP API Types
+// =============================================================================
+
+export interface ApiServerConfig {
+  enabled: boolean;
+  port: number;
+  allowLan: boolean;
+  token: string;
+  /** Stream activity events over the /api/events websocket */
+  eventsEnabled: boolean;
+}
+
+/** Message pushed to /api/events websocket clients */
+export interface ActivityEvent {
+  /** e.g. "scan-progress", "image-added", "nova-job-finished" */
+  event: string;
+  payload: unknown;
+  timestamp: string;
+}
+
+export interface ApiServerStatus {
+  running: boolean;
+  address: string | null;
+  config: ApiServerConfig;
+}
+
+// =============================================================================
+// Local HTTP API Commands
+// =============================================================================
+
+export const apiServerApi = {
+  getStatus: () =>
+    invoke<ApiServerStatus>("get_api_server_status"),
+
+  configure: (config: ApiServerConfig) =>
+    invoke<ApiServerStatus>("configure_api_server", { config }),
+
+  regenerateToken: () =>
+    invoke<ApiServerStatus>("regenerate_api_token"),
+};
+
+// =============================================================================
+// Python Environment Types
+// =============================================================================
+
+export type PythonBackend = "auto" | "embedded" | "subprocess";
+
+export interface PythonStatus {
+  pythonVersion: string | null;
+  modulePath: string | null;
+  sitePackages: string | null;
+  moduleLoaded: boolean;
+  backend: PythonBackend;
+  pythonExecutable: string | null;
+  error: string | null;
+}
+
+export interface PythonSettings {
+  venvPath: string | null;
+  backend: PythonBackend;
+  pythonExecutable: string | null;
+}
+
+// =============================================================================
+// Python Environment Commands
+// =============================================================================
+
+export const pythonApi = {
+  getStatus: () => invoke<PythonStatus>("get_python_status"),
+
+  getSettings: () => invoke<PythonSettings>("get_python_settings"),
+
+  setVenvPath: (venvPath: string | null) =>
+    invoke<PythonStatus>("set_python_venv_path", { venvPath }),
+
+  setBackend: (backend: PythonBackend, pythonExecutable?: string | null) =>
+    invoke<PythonStatus>("set_python_backend", { backend, pythonExecutable }),
+
+  reinitialize: () => invoke<PythonStatus>("reinitialize_python"),
 };
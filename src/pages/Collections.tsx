@@ -35,6 +35,8 @@ import { Label } from "@/components/ui/label";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import {
   useCollections,
+  useArchivedCollections,
+  useArchiveCollection,
   useCreateCollection,
   useUpdateCollection,
   useDeleteCollection,
@@ -70,11 +72,19 @@ export default function CollectionsPage() {
   });
 
   // Queries and mutations
-  const { data: collections = [], isLoading, error } = useCollections();
+  const { data: activeCollections = [], isLoading, error } = useCollections();
+  const { data: archivedCollections = [] } = useArchivedCollections();
   const createCollection = useCreateCollection();
   const updateCollection = useUpdateCollection();
+  const archiveCollection = useArchiveCollection();
   const deleteCollection = useDeleteCollection();
 
+  // The collection list excludes archived collections, which are fetched separately
+  const collections = useMemo(
+    () => [...activeCollections, ...archivedCollections],
+    [activeCollections, archivedCollections]
+  );
+
   const handleAddCollection = async () => {
     if (!newCollection.name.trim()) {
       toast.error("Please enter a collection name");
@@ -122,7 +132,7 @@ export default function CollectionsPage() {
 
   const handleToggleArchived = async (collection: Collection) => {
     try {
-      await updateCollection.mutateAsync({
+      await archiveCollection.mutateAsync({
         id: collection.id,
         archived: !collection.archived,
       });